@@ -71,18 +71,67 @@ defmodule EctoLibSql do
   - `:busy_timeout` - Busy timeout in milliseconds (default: 5000)
                       Controls how long SQLite waits for locks before returning SQLITE_BUSY.
                       Set to 0 to disable (not recommended for production).
+  - `:vfs` - Name of a registered SQLite VFS to open the database with (string). Only
+             supported for local connections; connecting fails with a descriptive error if
+             the VFS isn't registered.
+  - `:shared_memory` - Name of an in-memory database shared through SQLite's shared cache
+                       (string), used instead of `:database`. Every connection in the VM
+                       opened with the same name sees the same database, which is discarded
+                       when the last of them closes - handy for exercising pool behaviour in
+                       tests without touching disk. Shared-cache connections use table-level
+                       locks: a connection blocked by another's write fails immediately with
+                       `SQLITE_LOCKED` ("database table is locked") rather than waiting out
+                       `:busy_timeout`, and uncommitted writes can be read by the others when
+                       `read_uncommitted` is enabled. Not for production concurrency.
+  - `:secure_delete` - Overwrite deleted content (`PRAGMA secure_delete`), applied after
+                      connecting. One of `:on`, `:off` or `:fast`. `:on` adds write I/O;
+                      see `EctoLibSql.Pragma.set_secure_delete/2`.
+  - `:diagnostics` - When `true`, records how long building the database handle,
+                     connecting and (for remote connections) the verification ping took in
+                     `state.connect_timings`, to diagnose slow pool warmup. Default: `false`.
+  - `:mmap_size` - Maximum bytes of memory-mapped I/O (`PRAGMA mmap_size`), applied after
+                   connecting. Speeds up reads on large local databases. Must be a
+                   non-negative integer. See `EctoLibSql.Pragma.set_mmap_size/2` for caveats.
+  - `:temp_store` - Where large sorts and temporary tables spill (`PRAGMA temp_store`),
+                    applied after connecting. One of `:default`, `:file` or `:memory`.
+                    See `EctoLibSql.Pragma.set_temp_store/2`.
+  - `:synchronous` - How often SQLite syncs to disk (`PRAGMA synchronous`), applied after
+                     connecting. One of `:off`, `:normal`, `:full` or `:extra`. `:normal`
+                     pairs well with WAL journal mode for write-heavy caches; `:off` is
+                     fastest but a power loss can corrupt the database. See
+                     `EctoLibSql.Pragma.set_synchronous/2`.
+  - `:column_naming` - How duplicate result column names (e.g. two `id` columns from a
+                       join) are disambiguated: `:raw` (default, names as reported),
+                       `:index_suffix` (`id`, `id_2`) or `:table_prefix` (`users.id`,
+                       `posts.id`). See `EctoLibSql.Native.column_naming/2`.
 
   """
   @spec connect(Keyword.t()) :: {:ok, EctoLibSql.State.t()} | {:error, term()}
   def connect(opts) do
+    with :ok <- validate_mmap_size(opts),
+         :ok <- validate_secure_delete(opts),
+         :ok <- validate_temp_store(opts),
+         :ok <- validate_synchronous(opts) do
+      do_connect(opts)
+    end
+  end
+
+  defp do_connect(opts) do
     mode = EctoLibSql.State.detect_mode(opts)
 
-    case EctoLibSql.Native.connect(opts, mode) do
+    {result, timings} =
+      case EctoLibSql.Native.connect(opts, mode) do
+        {conn_id, timings} when is_binary(conn_id) -> {conn_id, timings}
+        other -> {other, nil}
+      end
+
+    case result do
       conn_id when is_binary(conn_id) ->
         state = %EctoLibSql.State{
           conn_id: conn_id,
           mode: mode,
-          sync: EctoLibSql.State.detect_sync(opts)
+          sync: EctoLibSql.State.detect_sync(opts),
+          connect_timings: timings
         }
 
         # Set busy_timeout for better concurrency handling
@@ -90,13 +139,26 @@ defmodule EctoLibSql do
 
         case EctoLibSql.Native.set_busy_timeout(conn_id, busy_timeout) do
           :ok ->
-            {:ok, state}
+            :ok
 
           {:error, reason} ->
             # Log warning but don't fail connection - busy_timeout is an optimisation
             require Logger
             Logger.warning("Failed to set busy_timeout: #{inspect(reason)}")
-            {:ok, state}
+        end
+
+        apply_mmap_size(state, Keyword.get(opts, :mmap_size))
+        apply_temp_store(state, Keyword.get(opts, :temp_store))
+
+        # Unlike the performance options above, secure_delete and synchronous protect
+        # data, so failing to apply them fails the connection.
+        with :ok <- apply_secure_delete(state, Keyword.get(opts, :secure_delete)),
+             :ok <- apply_synchronous(state, Keyword.get(opts, :synchronous)) do
+          {:ok, state}
+        else
+          {:error, _} = err ->
+            EctoLibSql.Native.close(conn_id, :conn_id)
+            err
         end
 
       {:error, _} = err ->
@@ -107,6 +169,100 @@ defmodule EctoLibSql do
     end
   end
 
+  defp validate_mmap_size(opts) do
+    case Keyword.get(opts, :mmap_size) do
+      nil -> :ok
+      bytes when is_integer(bytes) and bytes >= 0 -> :ok
+      bytes -> {:error, "mmap_size must be a non-negative integer, got: #{inspect(bytes)}"}
+    end
+  end
+
+  defp validate_secure_delete(opts) do
+    case Keyword.get(opts, :secure_delete) do
+      nil ->
+        :ok
+
+      mode when mode in [:on, :off, :fast] ->
+        :ok
+
+      mode ->
+        {:error, "secure_delete must be one of :on, :off or :fast, got: #{inspect(mode)}"}
+    end
+  end
+
+  defp validate_temp_store(opts) do
+    case Keyword.get(opts, :temp_store) do
+      nil ->
+        :ok
+
+      mode when mode in [:default, :file, :memory] ->
+        :ok
+
+      mode ->
+        {:error, "temp_store must be one of :default, :file or :memory, got: #{inspect(mode)}"}
+    end
+  end
+
+  defp validate_synchronous(opts) do
+    case Keyword.get(opts, :synchronous) do
+      nil ->
+        :ok
+
+      level when level in [:off, :normal, :full, :extra] ->
+        :ok
+
+      level ->
+        {:error,
+         "synchronous must be one of :off, :normal, :full or :extra, got: #{inspect(level)}"}
+    end
+  end
+
+  defp apply_mmap_size(_state, nil), do: :ok
+
+  defp apply_mmap_size(state, bytes) do
+    case EctoLibSql.Pragma.set_mmap_size(state, bytes) do
+      {:ok, _result} ->
+        :ok
+
+      {:error, reason} ->
+        # Log warning but don't fail connection - mmap_size is an optimisation
+        require Logger
+        Logger.warning("Failed to set mmap_size: #{inspect(reason)}")
+    end
+  end
+
+  defp apply_temp_store(_state, nil), do: :ok
+
+  defp apply_temp_store(state, mode) do
+    case EctoLibSql.Pragma.set_temp_store(state, mode) do
+      {:ok, _result} ->
+        :ok
+
+      {:error, reason} ->
+        # Log warning but don't fail connection - temp_store is an optimisation
+        require Logger
+        Logger.warning("Failed to set temp_store: #{inspect(reason)}")
+    end
+  end
+
+  defp apply_secure_delete(_state, nil), do: :ok
+
+  defp apply_secure_delete(state, mode) do
+    case EctoLibSql.Pragma.set_secure_delete(state, mode) do
+      {:ok, _result} -> :ok
+      {:error, reason} -> {:error, "Failed to set secure_delete: #{inspect(reason)}"}
+    end
+  end
+
+  defp apply_synchronous(_state, nil), do: :ok
+
+  defp apply_synchronous(state, level) do
+    case EctoLibSql.Pragma.set_synchronous(state, level) do
+      {:ok, _result} -> :ok
+      {:error, reason} -> {:error, "Failed to set synchronous: #{inspect(reason)}"}
+    end
+  end
+
   @impl true
   @doc """
   Pings the current connection to ensure it is still alive.
@@ -136,6 +292,36 @@ defmodule EctoLibSql do
   @doc """
   Executes an SQL query, delegating to transactional or non-transactional logic
   depending on the connection state.
+
+  ## Options
+
+  - `:coerce_text_numbers` - For queries that return rows, convert numbers stored as
+    text back to numbers when their column is declared numeric (`INTEGER`, `REAL`,
+    `NUMERIC`, ...), so `"123"` reads as `123`. Meant for legacy databases that store
+    numbers as `TEXT`. Best-effort: only columns selected directly from a table have a
+    declared type, and text that isn't a number is returned unchanged. Default: `false`.
+
+  - `:normalise_datetimes` - For queries that return rows, rewrite datetime text read
+    from columns declared `DATE`, `DATETIME` or `TIMESTAMP` as canonical ISO 8601
+    (`2024-01-02T03:04:05`, keeping any fractional seconds and UTC offset), however it
+    was stored (`2024-01-02 03:04:05`, `2024-01-02T03:04`, ...). Bare dates and
+    unrecognised text are returned unchanged, as are columns without a declared type.
+    Can be combined with `:coerce_text_numbers`. Default: `false`.
+
+  - `:decode_blobs` - For queries that return rows, a keyword list or map of result
+    columns whose blobs hold text in a legacy encoding, e.g. `[name: :latin1]`. Encodings
+    are `:utf16le` and `:latin1`; those blobs are returned as UTF-8 strings, and other
+    values in the columns are returned unchanged. The query fails if a named column isn't
+    in the result or a blob isn't valid in its encoding. Not combined with
+    `:coerce_text_numbers` or `:normalise_datetimes`. Default: none.
+
+  - `:max_result_bytes` - For queries that return rows, stop reading once the returned
+    values add up to more than this many bytes (text and blobs by length, numbers as 8
+    bytes each), and fail with an `EctoLibSql.Error` whose `sqlite` map is
+    `%{code: :result_too_large, bytes: bytes_so_far}`. A guard against a single query
+    exhausting memory, which `max_rows` can't give for wide or blob-heavy rows.
+    Applies to plain queries and queries in a transaction, including those using the
+    options above. Default: no limit.
   """
   @spec handle_execute(
           EctoLibSql.Query.t() | String.t(),
@@ -145,7 +331,7 @@ defmodule EctoLibSql do
         ) ::
           {:ok, EctoLibSql.Query.t(), EctoLibSql.Result.t(), EctoLibSql.State.t()}
           | {:error, EctoLibSql.Error.t(), EctoLibSql.State.t()}
-  def handle_execute(query, args, _opts, %EctoLibSql.State{trx_id: trx_id} = state) do
+  def handle_execute(query, args, opts, %EctoLibSql.State{trx_id: trx_id} = state) do
     query_struct =
       case query do
         %EctoLibSql.Query{} -> query
@@ -161,20 +347,64 @@ defmodule EctoLibSql do
         # Query returns rows, use the query path.
         # Convert map arguments to list if needed (NIFs expect lists).
         normalised_args = normalise_args_for_query(sql, args)
-
-        if trx_id do
-          EctoLibSql.Native.query_with_trx_args(trx_id, state.conn_id, sql, normalised_args)
-          |> format_query_result(state)
-        else
-          EctoLibSql.Native.query_args(
-            state.conn_id,
-            state.mode,
-            state.sync,
-            sql,
-            normalised_args
-          )
-          |> format_query_result(state)
-        end
+        max_result_bytes = Keyword.get(opts, :max_result_bytes)
+
+        # The converting queries run in the open transaction, if any, as
+        # query_with_trx_args does
+        result =
+          cond do
+            decodings = Keyword.get(opts, :decode_blobs) ->
+              EctoLibSql.Native.query_decode_blobs(
+                state.conn_id,
+                sql,
+                normalised_args,
+                Enum.map(decodings, fn {column, encoding} -> {to_string(column), encoding} end),
+                max_result_bytes,
+                trx_id
+              )
+
+            Keyword.get(opts, :normalise_datetimes, false) ->
+              EctoLibSql.Native.query_normalise_datetimes(
+                state.conn_id,
+                sql,
+                normalised_args,
+                Keyword.get(opts, :coerce_text_numbers, false),
+                max_result_bytes,
+                trx_id
+              )
+
+            Keyword.get(opts, :coerce_text_numbers, false) ->
+              EctoLibSql.Native.query_coerce_numbers(
+                state.conn_id,
+                sql,
+                normalised_args,
+                max_result_bytes,
+                trx_id
+              )
+
+            trx_id ->
+              EctoLibSql.Native.query_with_trx_args(
+                trx_id,
+                state.conn_id,
+                sql,
+                normalised_args,
+                max_result_bytes
+              )
+
+            true ->
+              EctoLibSql.Native.query_args(
+                state.conn_id,
+                state.mode,
+                state.sync,
+                sql,
+                normalised_args,
+                max_result_bytes
+              )
+          end
+
+        result
+        |> EctoLibSql.Native.warn_replica_write(sql)
+        |> format_query_result(state)
 
       false ->
         # Query doesn't return rows, use the execute path (INSERT/UPDATE/DELETE).
@@ -198,26 +428,19 @@ defmodule EctoLibSql do
     {:ok, %EctoLibSql.Query{}, result, state}
   end
 
-  defp format_query_result({:error, reason}, state) do
-    error = build_error(reason)
-    {:error, error, state}
-  end
-
-  # Build an EctoLibSql.Error from various reason formats.
-  defp build_error(%EctoLibSql.Error{} = error), do: error
+  defp format_query_result({:error, :result_too_large, bytes}, state) do
+    message = "Query result exceeded max_result_bytes (#{bytes} bytes read)"
 
-  defp build_error(reason) when is_binary(reason) do
-    %EctoLibSql.Error{message: reason, sqlite: %{code: :error, message: reason}}
-  end
+    error = %EctoLibSql.Error{
+      message: message,
+      sqlite: %{code: :result_too_large, bytes: bytes, message: message}
+    }
 
-  defp build_error(reason) when is_map(reason) do
-    message = Map.get(reason, :message) || Map.get(reason, "message") || inspect(reason)
-    %EctoLibSql.Error{message: message, sqlite: %{code: :error, message: message}}
+    {:error, error, state}
   end
 
-  defp build_error(reason) do
-    message = inspect(reason)
-    %EctoLibSql.Error{message: message, sqlite: %{code: :error, message: message}}
+  defp format_query_result({:error, reason}, state) do
+    {:error, EctoLibSql.Error.from_reason(reason), state}
   end
 
   # Convert map arguments to a list by extracting named parameters from SQL.
@@ -462,18 +685,32 @@ defmodule EctoLibSql do
 
   Cursors allow you to iterate through large result sets in chunks, which is
   more memory-efficient than loading all rows at once.
+
+  ## Options
+
+    - `:expected_rows` - Estimated number of rows, used to size the cursor's buffer
+      up front and avoid reallocating as it fills. Hints above 10,000 rows are
+      capped, and the buffer grows as usual beyond that.
   """
   def handle_declare(
         %EctoLibSql.Query{statement: statement} = query,
         params,
-        _opts,
+        opts,
         %EctoLibSql.State{conn_id: conn_id, trx_id: trx_id} = state
       ) do
     # Use transaction ID if in a transaction, otherwise use connection ID
     id = trx_id || conn_id
     id_type = if trx_id, do: :transaction, else: :connection
-
-    case EctoLibSql.Native.declare_cursor_with_context(conn_id, id, id_type, statement, params) do
+    expected_rows = Keyword.get(opts, :expected_rows)
+
+    case EctoLibSql.Native.declare_cursor_with_context(
+           conn_id,
+           id,
+           id_type,
+           statement,
+           params,
+           expected_rows
+         ) do
       cursor_id when is_binary(cursor_id) ->
         cursor = %{ref: cursor_id}
         {:ok, query, cursor, state}
@@ -8,7 +8,8 @@ defmodule EctoLibSql.Error do
   ## Fields
 
   - `:message` - Human-readable error message
-  - `:sqlite` - Map containing SQLite-specific error details (`:code`, `:message`)
+  - `:sqlite` - Map containing SQLite-specific error details (`:code`, `:message`).
+    Busy and locked errors have `code: :busy` and a `:lock` key; see `busy/1`.
   """
 
   defexception [:message, :sqlite]
@@ -16,6 +17,7 @@ defmodule EctoLibSql.Error do
   @type t :: %__MODULE__{
           message: String.t(),
           sqlite: %{
+            optional(:lock) => :read_lock | :write_lock,
             code: atom(),
             message: String.t()
           }
@@ -38,6 +40,59 @@ defmodule EctoLibSql.Error do
     String.contains?(message, "constraint failed")
   end
 
+  @doc """
+  Classifies a busy or locked error by the kind of lock it conflicted with.
+
+  SQLite doesn't say which connection is blocking, but its result code tells the
+  two cases apart:
+
+    - `{:busy, :write_lock}` - Another connection holds the write lock
+      (`SQLITE_BUSY`). Retrying after a backoff can succeed.
+    - `{:busy, :read_lock}` - The conflict is with a read: the transaction's
+      snapshot is out of date (`SQLITE_BUSY_SNAPSHOT`), or a table is in use by a
+      reading statement (`SQLITE_LOCKED`). Retrying the statement won't help;
+      roll back and restart the transaction.
+
+  Returns `nil` for other errors.
+
+  ## Examples
+
+      iex> error = %EctoLibSql.Error{
+      ...>   message: "database is locked",
+      ...>   sqlite: %{code: :busy, lock: :write_lock, message: "database is locked"}
+      ...> }
+      iex> EctoLibSql.Error.busy(error)
+      {:busy, :write_lock}
+
+  """
+  @spec busy(t()) :: {:busy, :read_lock | :write_lock} | nil
+  def busy(%__MODULE__{sqlite: %{code: :busy, lock: lock}}), do: {:busy, lock}
+  def busy(%__MODULE__{}), do: nil
+
+  # Builds an error from the reason a NIF returned, so busy errors are shaped the same
+  # whichever path ran the statement.
+  @doc false
+  @spec from_reason(term()) :: t()
+  def from_reason(%__MODULE__{} = error), do: error
+
+  def from_reason({:busy, lock, message}) do
+    %__MODULE__{message: message, sqlite: %{code: :busy, lock: lock, message: message}}
+  end
+
+  def from_reason(reason) when is_binary(reason) do
+    %__MODULE__{message: reason, sqlite: %{code: :error, message: reason}}
+  end
+
+  def from_reason(reason) when is_map(reason) do
+    message = Map.get(reason, :message) || Map.get(reason, "message") || inspect(reason)
+    %__MODULE__{message: message, sqlite: %{code: :error, message: message}}
+  end
+
+  def from_reason(reason) do
+    message = inspect(reason)
+    %__MODULE__{message: message, sqlite: %{code: :error, message: message}}
+  end
+
   @doc """
   Extracts the constraint field name from an error message.
 
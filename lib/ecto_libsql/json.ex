@@ -127,7 +127,8 @@ defmodule EctoLibSql.JSON do
       state.mode,
       :disable_sync,
       "SELECT json_extract(?, ?)",
-      [json, path]
+      [json, path],
+      nil
     )
     |> handle_single_result()
   end
@@ -162,7 +163,8 @@ defmodule EctoLibSql.JSON do
       state.mode,
       :disable_sync,
       "SELECT json_type(?, ?)",
-      [json, path]
+      [json, path],
+      nil
     )
     |> handle_single_result()
   end
@@ -194,7 +196,8 @@ defmodule EctoLibSql.JSON do
       state.mode,
       :disable_sync,
       "SELECT json_valid(?)",
-      [json]
+      [json],
+      nil
     )
     |> handle_boolean_result()
   end
@@ -235,7 +238,8 @@ defmodule EctoLibSql.JSON do
       state.mode,
       :disable_sync,
       sql,
-      values
+      values,
+      nil
     )
     |> handle_single_result()
   end
@@ -289,7 +293,8 @@ defmodule EctoLibSql.JSON do
         state.mode,
         :disable_sync,
         sql,
-        pairs
+        pairs,
+        nil
       )
       |> handle_single_result()
     end
@@ -336,7 +341,8 @@ defmodule EctoLibSql.JSON do
       state.mode,
       :disable_sync,
       sql,
-      [json, path]
+      [json, path],
+      nil
     )
     |> handle_multiple_rows(fn [key, value, type] -> {key, value, type} end)
   end
@@ -379,7 +385,8 @@ defmodule EctoLibSql.JSON do
       state.mode,
       :disable_sync,
       sql,
-      [json, path]
+      [json, path],
+      nil
     )
     |> handle_multiple_rows(fn [fullkey, atom, type] -> {fullkey, atom, type} end)
   end
@@ -435,7 +442,8 @@ defmodule EctoLibSql.JSON do
       state.mode,
       :disable_sync,
       sql,
-      [json]
+      [json],
+      nil
     )
     |> handle_single_result()
   end
@@ -622,7 +630,8 @@ defmodule EctoLibSql.JSON do
       state.mode,
       :disable_sync,
       "SELECT json_quote(?)",
-      [value]
+      [value],
+      nil
     )
     |> handle_single_result()
   end
@@ -661,7 +670,8 @@ defmodule EctoLibSql.JSON do
       state.mode,
       :disable_sync,
       "SELECT json_length(?, ?)",
-      [json, path]
+      [json, path],
+      nil
     )
     |> handle_single_result()
   end
@@ -701,7 +711,8 @@ defmodule EctoLibSql.JSON do
       state.mode,
       :disable_sync,
       "SELECT json_depth(?)",
-      [json]
+      [json],
+      nil
     )
     |> handle_single_result()
   end
@@ -746,7 +757,8 @@ defmodule EctoLibSql.JSON do
       state.mode,
       :disable_sync,
       sql,
-      args
+      args,
+      nil
     )
     |> handle_single_result()
   end
@@ -785,7 +797,8 @@ defmodule EctoLibSql.JSON do
       state.mode,
       :disable_sync,
       "SELECT json_set(?, ?, ?)",
-      [json, path, value]
+      [json, path, value],
+      nil
     )
     |> handle_single_result()
   end
@@ -825,7 +838,8 @@ defmodule EctoLibSql.JSON do
       state.mode,
       :disable_sync,
       "SELECT json_replace(?, ?, ?)",
-      [json, path, value]
+      [json, path, value],
+      nil
     )
     |> handle_single_result()
   end
@@ -864,7 +878,8 @@ defmodule EctoLibSql.JSON do
       state.mode,
       :disable_sync,
       "SELECT json_insert(?, ?, ?)",
-      [json, path, value]
+      [json, path, value],
+      nil
     )
     |> handle_single_result()
   end
@@ -926,7 +941,8 @@ defmodule EctoLibSql.JSON do
       state.mode,
       :disable_sync,
       "SELECT json_patch(?, ?)",
-      [json, patch_json]
+      [json, patch_json],
+      nil
     )
     |> handle_single_result()
   end
@@ -962,7 +978,8 @@ defmodule EctoLibSql.JSON do
       state.mode,
       :disable_sync,
       "SELECT json_keys(?, ?)",
-      [json, path]
+      [json, path],
+      nil
     )
     |> handle_nullable_result()
   end
@@ -16,8 +16,8 @@ defmodule EctoLibSql.Native do
   - Prepared statements: `prepare_statement/2`, `query_prepared/5`, `execute_prepared/6`
   - Batch operations: `execute_batch/4`, `execute_transactional_batch/4`
   - Metadata: `last_insert_rowid/1`, `changes/1`, `total_changes/1`, `is_autocommit/1`
-  - Cursors: `declare_cursor/3`, `fetch_cursor/2`
-  - Sync: `do_sync/2`
+  - Cursors: `declare_cursor/4`, `fetch_cursor/2`
+  - Sync: `do_sync/1`
 
   ## Helper Functions
 
@@ -71,7 +71,8 @@ defmodule EctoLibSql.Native do
   def connect(_opts, _mode), do: :erlang.nif_error(:nif_not_loaded)
 
   @doc false
-  def query_args(_conn, _mode, _query, _args, _sync), do: :erlang.nif_error(:nif_not_loaded)
+  def query_args(_conn, _mode, _sync, _query, _args, _max_result_bytes),
+    do: :erlang.nif_error(:nif_not_loaded)
 
   @doc false
   def begin_transaction(_conn), do: :erlang.nif_error(:nif_not_loaded)
@@ -80,11 +81,15 @@ defmodule EctoLibSql.Native do
   def begin_transaction_with_behavior(_conn, _behavior), do: :erlang.nif_error(:nif_not_loaded)
 
   @doc false
-  def execute_with_transaction(_trx_id, _conn_id, _query, _args),
+  def begin_transaction_with_timeout(_conn, _behavior, _busy_timeout_ms),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def execute_with_transaction(_trx_id, _conn_id, _query, _args, _return_rowid),
     do: :erlang.nif_error(:nif_not_loaded)
 
   @doc false
-  def query_with_trx_args(_trx_id, _conn_id, _query, _args),
+  def query_with_trx_args(_trx_id, _conn_id, _query, _args, _max_result_bytes),
     do: :erlang.nif_error(:nif_not_loaded)
 
   @doc false
@@ -95,7 +100,7 @@ defmodule EctoLibSql.Native do
     do: :erlang.nif_error(:nif_not_loaded)
 
   @doc false
-  def do_sync(_conn, _mode), do: :erlang.nif_error(:nif_not_loaded)
+  def do_sync(_conn), do: :erlang.nif_error(:nif_not_loaded)
 
   @doc false
   def close(_id, _opt), do: :erlang.nif_error(:nif_not_loaded)
@@ -111,14 +116,18 @@ defmodule EctoLibSql.Native do
   def prepare_statement(_conn, _sql), do: :erlang.nif_error(:nif_not_loaded)
 
   @doc false
-  def query_prepared(_conn, _stmt_id, _mode, _sync, _args), do: :erlang.nif_error(:nif_not_loaded)
+  def prepare_statement_diagnostics(_conn, _sql), do: :erlang.nif_error(:nif_not_loaded)
 
   @doc false
-  def execute_prepared(_conn, _stmt_id, _mode, _sync, _sql_hint, _args),
+  def query_prepared(_conn, _stmt_id, _mode, _sync, _args, _debug_params),
     do: :erlang.nif_error(:nif_not_loaded)
 
   @doc false
-  def declare_cursor_with_context(_conn_id, _id, _id_type, _sql, _args),
+  def execute_prepared(_conn, _stmt_id, _mode, _sync, _sql_hint, _args, _debug_params),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def declare_cursor_with_context(_conn_id, _id, _id_type, _sql, _args, _expected_rows),
     do: :erlang.nif_error(:nif_not_loaded)
 
   @doc false
@@ -134,7 +143,10 @@ defmodule EctoLibSql.Native do
   def is_autocommit(_conn), do: :erlang.nif_error(:nif_not_loaded)
 
   @doc false
-  def declare_cursor(_conn, _sql, _args), do: :erlang.nif_error(:nif_not_loaded)
+  def declare_cursor(_conn, _sql, _args, _expected_rows), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def cursor_memory(_conn_id, _cursor_id), do: :erlang.nif_error(:nif_not_loaded)
 
   @doc false
   def fetch_cursor(_conn_id, _cursor_id, _max_rows), do: :erlang.nif_error(:nif_not_loaded)
@@ -143,7 +155,10 @@ defmodule EctoLibSql.Native do
   def set_busy_timeout(_conn_id, _timeout_ms), do: :erlang.nif_error(:nif_not_loaded)
 
   @doc false
-  def reset_connection(_conn_id), do: :erlang.nif_error(:nif_not_loaded)
+  def reset_connection(_conn_id, _shrink), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def shrink_memory(_conn_id), do: :erlang.nif_error(:nif_not_loaded)
 
   @doc false
   def interrupt_connection(_conn_id), do: :erlang.nif_error(:nif_not_loaded)
@@ -163,6 +178,9 @@ defmodule EctoLibSql.Native do
   @doc false
   def set_authorizer(_conn_id, _pid), do: :erlang.nif_error(:nif_not_loaded)
 
+  @doc false
+  def set_deny_rules(_conn_id, _rules), do: :erlang.nif_error(:nif_not_loaded)
+
   @doc false
   def should_use_query_path(_sql), do: :erlang.nif_error(:nif_not_loaded)
 
@@ -193,6 +211,9 @@ defmodule EctoLibSql.Native do
   @doc false
   def get_statement_columns(_conn_id, _stmt_id), do: :erlang.nif_error(:nif_not_loaded)
 
+  @doc false
+  def column_provenance(_conn_id, _stmt_id), do: :erlang.nif_error(:nif_not_loaded)
+
   @doc false
   def savepoint(_conn_id, _trx_id, _name), do: :erlang.nif_error(:nif_not_loaded)
 
@@ -202,6 +223,21 @@ defmodule EctoLibSql.Native do
   @doc false
   def rollback_to_savepoint(_conn_id, _trx_id, _name), do: :erlang.nif_error(:nif_not_loaded)
 
+  @doc false
+  def transaction_depth(_conn_id), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def transaction_state(_conn_id), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def statement_count(_conn_id), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def error_counts(_conn_id), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def reset_error_counts(_conn_id), do: :erlang.nif_error(:nif_not_loaded)
+
   @doc false
   def get_frame_number(_conn_id), do: :erlang.nif_error(:nif_not_loaded)
 
@@ -242,6 +278,304 @@ defmodule EctoLibSql.Native do
   @doc false
   def freeze_database(_conn_id), do: :erlang.nif_error(:nif_not_loaded)
 
+  @doc false
+  def sync_sequence(_conn_id, _table), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def last_error(_conn_id), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def stream_table_dump(_conn_id, _table, _pid, _chunk), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def prepare_many(_conn_id, _sqls, _continue_on_error), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def declare_cursor_from_statement(_conn_id, _stmt_id, _args),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def write_blob_range(_conn_id, _table, _column, _rowid, _offset, _data),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def connections_for_path(_path), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def execute_write_batch(_conn, _statements), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def set_column_naming(_conn_id, _naming), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def set_progress_limit(_conn_id, _max_steps), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def fetch_by_ids(_conn_id, _table, _id_column, _ids), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def index_usage(_conn_id, _sql, _args), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def validate_statements(_conn_id, _sql), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def vacuum_with_sizes(_conn_id), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def wal_file_info(_conn_id), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def capture_affected_rowids(_conn_id, _sql, _args), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def query_arrow_ipc(_conn_id, _sql, _args), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def set_deadline(_token, _deadline_ms), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def clear_deadline(_token), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def query_args_deadline(_conn_id, _sql, _args, _token),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def execute_with_triggers_disabled(_conn_id, _table, _statements),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def statement_digest(_sql), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def keyset_page(_conn_id, _base_sql, _key_column, _after_key, _limit),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def inline_value(_value), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def connect_from_bytes(_bytes), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def serialize_database(_conn), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def check_backup(_path), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def query_multi(_conn, _queries), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def classify_write(_conn, _sql), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def statement_conflict_action(_sql), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def table_column_details(_conn, _table), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def database_list(_conn_id), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def query_coerce_numbers(_conn, _query, _args, _max_result_bytes, _trx_id),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def increment(_conn_id, _table, _key_column, _key, _value_column, _delta),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def table_row_counts(_conn_id, _approximate), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def set_soft_heap_limit(_bytes), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def get_soft_heap_limit(), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def memory_stats(), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def status_counters(), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def statement_valid(_conn_id, _stmt_id), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def reprepare_statement(_conn_id, _stmt_id), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def migrate_table(_conn_id, _statements), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def query_args_indexed(_conn_id, _sql, _indexed_args), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def query_on_database(_conn_id, _db_alias, _sql, _args),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def enable_dirty_tracking(_conn_id), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def take_dirty_tables(_conn_id), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def query_with_nullability(_conn_id, _sql, _args), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def compare_and_execute(
+        _conn_id,
+        _precheck_sql,
+        _precheck_args,
+        _expected,
+        _update_sql,
+        _update_args
+      ),
+      do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def query_tuples(_conn_id, _sql, _args), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def query_normalise_datetimes(
+        _conn,
+        _query,
+        _args,
+        _coerce_numbers,
+        _max_result_bytes,
+        _trx_id
+      ),
+      do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def query_decode_blobs(_conn, _query, _args, _decodings, _max_result_bytes, _trx_id),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def pragma_snapshot(_conn_id), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def set_wal_autocheckpoint(_conn_id, _pages), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def get_wal_autocheckpoint(_conn_id), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def set_cache_spill(_conn_id, _setting), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def get_cache_spill(_conn_id), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def can_acquire_write_lock(_conn_id), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def connection_threading_mode(_conn_id), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def validate_connect_opts(_opts, _mode), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def estimate_write_cost(_conn_id, _sql, _args), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def replication_diagnostics(_conn_id), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def sync_if_behind(_conn_id, _max_lag_frames), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def pause_sync(_conn_id), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def resume_sync(_conn_id), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def blob_builder_new, do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def blob_builder_append(_id, _chunk), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def blob_builder_discard(_id), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def insert_blob(_conn_id, _sql, _args), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def query_with_blob_refs(_conn_id, _sql, _args), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def fetch_blob_ref(_conn_id, _rowid, _column, _table, _schema),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def consistent_read(_conn_id, _queries), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def set_defensive(_conn_id, _enabled), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def get_defensive(_conn_id), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def set_attached_limit(_conn_id, _limit), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def get_attached_limit(_conn_id), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def set_sql_length_limit(_conn_id, _bytes), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def get_sql_length_limit(_conn_id), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def configure_lookaside(_conn_id, _slot_size, _slot_count),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def row_fingerprint(_conn_id, _sql, _args), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def query_transform(_conn_id, _sql, _args, _transforms),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def benchmark_query(_conn_id, _sql, _args, _iterations),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def is_column_indexed(_conn_id, _table, _column), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def atomic_statements_retry(_conn_id, _statements, _max_retries),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def query_to_ndjson(_conn_id, _sql, _args), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def find_unique_violations(_conn_id, _table, _columns),
+    do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def suggest_indexes(_conn_id, _sql, _args), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def changeset_apply(_conn_id, _changeset, _policy), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def changeset_capture(_conn_id, _statements), do: :erlang.nif_error(:nif_not_loaded)
+
+  @doc false
+  def query_binary(_conn_id, _sql, _args), do: :erlang.nif_error(:nif_not_loaded)
+
   # High-level Elixir helper functions
 
   @doc """
@@ -270,6 +604,7 @@ defmodule EctoLibSql.Native do
 
   ## Returns
     - `{:ok, "success sync"}` on successful sync
+    - `{:error, :not_a_replica}` if the connection isn't a remote replica
     - `{:error, reason}` if sync fails
 
   ## Examples
@@ -285,21 +620,23 @@ defmodule EctoLibSql.Native do
 
   ## Notes
 
-  - Sync is only applicable for `:remote_replica` mode connections
-  - For `:local` mode, this is a no-op
+  - Sync is only applicable for `:remote_replica` mode connections; the mode the
+    connection was opened with is used, not `state.mode`
   - For `:remote` mode, data is already on the remote server
   - Sync happens synchronously and may take time depending on data size
 
   """
   @spec sync(EctoLibSql.State.t()) :: {:ok, String.t()} | {:error, term()}
-  def sync(%EctoLibSql.State{conn_id: conn_id, mode: mode} = _state) do
-    do_sync(conn_id, mode)
+  def sync(%EctoLibSql.State{conn_id: conn_id} = _state) do
+    do_sync(conn_id)
   end
 
   @doc false
   def close_conn(id, opt, state) do
     case close(id, opt) do
       :ok -> :ok
+      # Closing a connection also reports how many statements and cursors it swept
+      {:ok, _swept} -> :ok
       {:error, message} -> {:error, message, state}
     end
   end
@@ -324,6 +661,20 @@ defmodule EctoLibSql.Native do
   - **Map**: Converted to positional list using statement parameter introspection
 
   Any other type returns `{:error, "arguments must be a list or map"}`.
+
+  ## Named Parameters: `nil` vs Missing Keys
+
+  A key that is present with a `nil` value binds SQL `NULL`. A key that is
+  absent raises `ArgumentError` listing the missing parameters, so an omitted
+  argument is never silently stored as `NULL`. Column defaults must therefore
+  be expressed in SQL (e.g. by leaving the column out of the `INSERT` or using
+  `COALESCE(:param, default)`) rather than by omitting the key.
+
+      # Binds NULL for :age
+      %{id: 1, name: "Alice", age: nil}
+
+      # Raises ArgumentError: Missing required parameters: :age
+      %{id: 1, name: "Alice"}
   """
   @spec normalise_arguments(String.t(), String.t(), list() | map()) ::
           list() | {:error, term()}
@@ -385,6 +736,10 @@ defmodule EctoLibSql.Native do
             do: [],
             else: Enum.map(1..count, &extract_param_name(conn_id, stmt_id, &1))
 
+        # Missing keys must raise rather than silently binding NULL; an explicit
+        # nil value is still bound as NULL.
+        validate_params_exist(map, param_names)
+
         # Convert map to positional list using the names.
         # Support both atom and string keys in the input map.
         Enum.map(param_names, &get_map_value_flexible(map, &1))
@@ -705,7 +1060,8 @@ defmodule EctoLibSql.Native do
     # Encode parameters to handle complex Elixir types (maps, etc.).
     encoded_args = encode_parameters(args_for_execution)
 
-    case query_args(conn_id, mode, syncx, statement, encoded_args) do
+    case query_args(conn_id, mode, syncx, statement, encoded_args, nil)
+         |> warn_replica_write(statement) do
       %{
         "columns" => columns,
         "rows" => rows,
@@ -743,8 +1099,8 @@ defmodule EctoLibSql.Native do
 
         {:ok, query, result, state}
 
-      {:error, message} ->
-        {:error, %EctoLibSql.Error{message: message}, state}
+      {:error, reason} ->
+        {:error, EctoLibSql.Error.from_reason(reason), state}
     end
   end
 
@@ -785,7 +1141,7 @@ defmodule EctoLibSql.Native do
 
     if should_query do
       # Use query_with_trx_args for SELECT or statements with RETURNING.
-      case query_with_trx_args(trx_id, conn_id, statement, encoded_args) do
+      case query_with_trx_args(trx_id, conn_id, statement, encoded_args, nil) do
         %{
           "columns" => columns,
           "rows" => rows,
@@ -809,12 +1165,12 @@ defmodule EctoLibSql.Native do
 
           {:ok, query, result, state}
 
-        {:error, message} ->
-          {:error, %EctoLibSql.Error{message: message}, state}
+        {:error, reason} ->
+          {:error, EctoLibSql.Error.from_reason(reason), state}
       end
     else
       # Use execute_with_transaction for INSERT/UPDATE/DELETE without RETURNING
-      case execute_with_transaction(trx_id, conn_id, statement, encoded_args) do
+      case execute_with_transaction(trx_id, conn_id, statement, encoded_args, false) do
         num_rows when is_integer(num_rows) ->
           result = %EctoLibSql.Result{
             command: command,
@@ -836,6 +1192,8 @@ defmodule EctoLibSql.Native do
     - state: The connection state
     - opts: Options keyword list
       - `:behavior` - Transaction behaviour (`:deferred`, `:immediate`, or `:exclusive`), defaults to `:deferred`
+      - `:busy_timeout` - Busy timeout in milliseconds for this transaction only; must be
+        a non-negative integer
 
   ## Transaction Behaviours
 
@@ -843,16 +1201,38 @@ defmodule EctoLibSql.Native do
   - `:immediate` - Acquires write lock immediately when transaction begins
   - `:exclusive` - Acquires exclusive lock immediately, blocking all other connections
 
+  ## Transaction Busy Timeout
+
+  With `:busy_timeout`, the connection's busy timeout is replaced for the lifetime
+  of the transaction, including the `BEGIN` itself, so a critical write can wait
+  longer for locks without changing the connection default. The previous timeout is
+  restored when the transaction is committed, rolled back or closed.
+
   ## Example
       {:ok, new_state} = EctoLibSql.Native.begin(state, behavior: :immediate)
 
+      {:ok, new_state} =
+        EctoLibSql.Native.begin(state, behavior: :immediate, busy_timeout: 30_000)
+
   """
   @spec begin(EctoLibSql.State.t(), Keyword.t()) ::
           {:ok, EctoLibSql.State.t()} | {:error, term()}
   def begin(%EctoLibSql.State{conn_id: conn_id, mode: mode} = _state, opts \\ []) do
     behavior = Keyword.get(opts, :behavior, :deferred)
 
-    case begin_transaction_with_behavior(conn_id, behavior) do
+    result =
+      case Keyword.fetch(opts, :busy_timeout) do
+        {:ok, timeout_ms} when is_integer(timeout_ms) and timeout_ms >= 0 ->
+          begin_transaction_with_timeout(conn_id, behavior, timeout_ms)
+
+        {:ok, timeout_ms} ->
+          {:error, "busy_timeout must be a non-negative integer, got: #{inspect(timeout_ms)}"}
+
+        :error ->
+          begin_transaction_with_behavior(conn_id, behavior)
+      end
+
+    case result do
       trx_id when is_binary(trx_id) ->
         {:ok, %EctoLibSql.State{conn_id: conn_id, trx_id: trx_id, mode: mode}}
 
@@ -1016,46 +1396,110 @@ defmodule EctoLibSql.Native do
   end
 
   @doc """
-  Execute a prepared statement with arguments.
+  Prepare a SQL statement, returning detailed diagnostics if it fails.
 
-  Automatically routes to query_stmt if the statement returns rows (e.g., SELECT, EXPLAIN, RETURNING),
-  or to execute_prepared if it doesn't (e.g., INSERT/UPDATE/DELETE without RETURNING).
+  Behaves like `prepare/2` on success. On failure, the error is a map with the
+  SQLite message, the SQLite result `code`, and the byte `offset` in `sql` where the
+  error was found, so an editor can highlight the exact spot.
+
+  libsql does not expose SQLite's `sqlite3_error_offset`, so the offset is recovered
+  from the error: for syntax errors it points at the token SQLite complained about,
+  and for unknown tables, columns and functions at the first use of the name.
+  `offset` is `nil` when the error has no position (such as truncated SQL), and
+  `code` is `nil` for errors that did not come from SQLite.
+
+  Finding a syntax error's token means re-preparing parts of the SQL, which would be
+  a round trip each on a remote connection, so remote connections don't try: expect
+  `offset: nil` for syntax errors there. Remote servers often report errors without
+  the SQLite message this relies on at all.
 
   ## Parameters
     - state: The connection state
-    - stmt_id: The statement ID from prepare/2
-    - sql: The original SQL (for sync detection and statement type detection)
-    - args: List of positional parameters OR map with atom keys for named parameters
+    - sql: The SQL query to prepare
 
-  ## Examples
+  ## Returns
+    - `{:ok, stmt_id}` - The prepared statement ID
+    - `{:error, %{message: String.t(), offset: non_neg_integer() | nil, code: integer() | nil}}`
 
-      # INSERT without RETURNING
-      {:ok, stmt_id} = EctoLibSql.Native.prepare(state, "INSERT INTO users (name) VALUES (?)")
-      {:ok, num_rows} = EctoLibSql.Native.execute_stmt(state, stmt_id, sql, ["Alice"])
+  ## Example
+      {:error, %{message: "near \"users\": syntax error", offset: 21, code: 1}} =
+        EctoLibSql.Native.prepare_with_diagnostics(state, "SELECT id, name FORM users")
 
-      # SELECT query
-      {:ok, stmt_id} = EctoLibSql.Native.prepare(state, "SELECT * FROM users WHERE id = ?")
-      {:ok, result} = EctoLibSql.Native.execute_stmt(state, stmt_id, sql, [1])
+  """
+  @spec prepare_with_diagnostics(EctoLibSql.State.t(), String.t()) ::
+          {:ok, String.t()}
+          | {:error,
+             %{message: String.t(), offset: non_neg_integer() | nil, code: integer() | nil}}
+          | {:error, term()}
+  def prepare_with_diagnostics(%EctoLibSql.State{conn_id: conn_id}, sql) when is_binary(sql) do
+    case prepare_statement_diagnostics(conn_id, sql) do
+      stmt_id when is_binary(stmt_id) ->
+        {:ok, stmt_id}
 
-      # EXPLAIN query
+      {:error, {message, offset, code}} ->
+        {:error, %{message: message, offset: offset, code: code}}
+
+      {:error, reason} ->
+        {:error, reason}
+    end
+  end
+
+  @doc """
+  Execute a prepared statement with arguments.
+
+  Automatically routes to query_stmt if the statement returns rows (e.g., SELECT, EXPLAIN, RETURNING),
+  or to execute_prepared if it doesn't (e.g., INSERT/UPDATE/DELETE without RETURNING).
+
+  ## Parameters
+    - state: The connection state
+    - stmt_id: The statement ID from prepare/2
+    - sql: The original SQL (for sync detection and statement type detection)
+    - args: List of positional parameters OR map with atom keys for named parameters
+    - opts: Options
+      - `:debug_params` - Also return how many parameters the statement expected and how
+        many values were supplied, as `{:ok, result, %{expected: n, supplied: m}}`.
+        SQLite binds `NULL` to parameters given no value, so a statement supplied too few
+        still runs; this makes the mismatch visible. Off by default to keep the hot path
+        lean.
+
+  ## Examples
+
+      # INSERT without RETURNING
+      {:ok, stmt_id} = EctoLibSql.Native.prepare(state, "INSERT INTO users (name) VALUES (?)")
+      {:ok, num_rows} = EctoLibSql.Native.execute_stmt(state, stmt_id, sql, ["Alice"])
+
+      # SELECT query
+      {:ok, stmt_id} = EctoLibSql.Native.prepare(state, "SELECT * FROM users WHERE id = ?")
+      {:ok, result} = EctoLibSql.Native.execute_stmt(state, stmt_id, sql, [1])
+
+      # EXPLAIN query
       {:ok, stmt_id} = EctoLibSql.Native.prepare(state, "EXPLAIN QUERY PLAN SELECT * FROM users")
       {:ok, result} = EctoLibSql.Native.execute_stmt(state, stmt_id, sql, [])
 
       # INSERT with RETURNING
       {:ok, stmt_id} = EctoLibSql.Native.prepare(state, "INSERT INTO users (name) VALUES (?) RETURNING *")
       {:ok, result} = EctoLibSql.Native.execute_stmt(state, stmt_id, sql, ["Alice"])
+
+      # Too few values supplied
+      sql = "INSERT INTO users (name, email) VALUES (?, ?)"
+      {:ok, stmt_id} = EctoLibSql.Native.prepare(state, sql)
+      {:ok, 1, %{expected: 2, supplied: 1}} =
+        EctoLibSql.Native.execute_stmt(state, stmt_id, sql, ["Alice"], debug_params: true)
   """
   def execute_stmt(
         %EctoLibSql.State{conn_id: conn_id, mode: mode, sync: syncx} = state,
         stmt_id,
         sql,
-        args
+        args,
+        opts \\ []
       ) do
+    debug_params = Keyword.get(opts, :debug_params, false)
+
     # Check if this statement returns rows (uses the NIF for consistency with handle_execute).
     case should_use_query_path(sql) do
       true ->
         # Use query_stmt path for statements that return rows.
-        query_stmt(state, stmt_id, args)
+        query_stmt(state, stmt_id, args, opts)
 
       false ->
         # Use execute path for statements that don't return rows.
@@ -1065,10 +1509,21 @@ defmodule EctoLibSql.Native do
             {:error, "Failed to normalise parameters: #{reason}"}
 
           normalised_args ->
-            case execute_prepared(conn_id, stmt_id, mode, syncx, sql, normalised_args) do
+            case execute_prepared(
+                   conn_id,
+                   stmt_id,
+                   mode,
+                   syncx,
+                   sql,
+                   normalised_args,
+                   debug_params
+                 ) do
               num_rows when is_integer(num_rows) ->
                 {:ok, num_rows}
 
+              {num_rows, %{expected: _, supplied: _} = params} when is_integer(num_rows) ->
+                {:ok, num_rows, params}
+
               {:error, reason} ->
                 {:error, reason}
             end
@@ -1084,6 +1539,7 @@ defmodule EctoLibSql.Native do
     - state: The connection state
     - stmt_id: The statement ID from prepare/2
     - args: List of positional parameters OR map with atom keys for named parameters
+    - opts: Options; `:debug_params` as for `execute_stmt/5`
 
   ## Examples
 
@@ -1098,24 +1554,23 @@ defmodule EctoLibSql.Native do
   def query_stmt(
         %EctoLibSql.State{conn_id: conn_id, mode: mode, sync: syncx} = _state,
         stmt_id,
-        args
+        args,
+        opts \\ []
       ) do
+    debug_params = Keyword.get(opts, :debug_params, false)
+
     # Normalise arguments (convert map to positional list if needed).
     case normalise_arguments_for_stmt(conn_id, stmt_id, args) do
       {:error, reason} ->
         {:error, "Failed to normalise parameters: #{reason}"}
 
       normalised_args ->
-        case query_prepared(conn_id, stmt_id, mode, syncx, normalised_args) do
-          %{"columns" => columns, "rows" => rows, "num_rows" => num_rows} ->
-            result = %EctoLibSql.Result{
-              command: :select,
-              columns: columns,
-              rows: rows,
-              num_rows: num_rows
-            }
+        case query_prepared(conn_id, stmt_id, mode, syncx, normalised_args, debug_params) do
+          %{"columns" => _} = result ->
+            {:ok, select_result(result)}
 
-            {:ok, result}
+          {%{"columns" => _} = result, %{expected: _, supplied: _} = params} ->
+            {:ok, select_result(result), params}
 
           {:error, reason} ->
             {:error, reason}
@@ -1123,6 +1578,10 @@ defmodule EctoLibSql.Native do
     end
   end
 
+  defp select_result(%{"columns" => columns, "rows" => rows, "num_rows" => num_rows}) do
+    %EctoLibSql.Result{command: :select, columns: columns, rows: rows, num_rows: num_rows}
+  end
+
   @doc """
   Close a prepared statement and free its resources.
 
@@ -1192,6 +1651,70 @@ defmodule EctoLibSql.Native do
     is_autocommit(conn_id)
   end
 
+  @doc """
+  Get the number of statements run on the connection since it was opened.
+
+  A cheap throughput signal for load analysis, without logging every query. Counts
+  statements run through queries, transactions, prepared statements and batches,
+  where each statement of a batch counts once. Failed statements are included.
+
+  ## Parameters
+    - state: The connection state
+
+  ## Example
+      before = EctoLibSql.Native.get_statement_count(state)
+      # ... run some queries ...
+      ran = EctoLibSql.Native.get_statement_count(state) - before
+  """
+  @spec get_statement_count(EctoLibSql.State.t()) :: non_neg_integer() | {:error, term()}
+  def get_statement_count(%EctoLibSql.State{conn_id: conn_id} = _state) do
+    statement_count(conn_id)
+  end
+
+  @doc """
+  Get how many errors of each class have occurred on the connection.
+
+  Counts errors since the connection was opened or the counts were last reset, by
+  class: `:constraint` (constraint violations), `:busy` (busy and locked errors),
+  `:syntax` (SQL that failed to parse) and `:other`. Useful for spotting connections
+  worth evicting from a pool, such as one piling up busy errors.
+
+  ## Parameters
+    - state: The connection state
+
+  ## Example
+      {:ok, %{busy: busy}} = EctoLibSql.Native.get_error_counts(state)
+  """
+  @spec get_error_counts(EctoLibSql.State.t()) ::
+          {:ok, %{atom() => non_neg_integer()}} | {:error, term()}
+  def get_error_counts(%EctoLibSql.State{conn_id: conn_id} = _state) do
+    case error_counts(conn_id) do
+      %{} = counts -> {:ok, counts}
+      {:error, reason} -> {:error, reason}
+    end
+  end
+
+  @doc """
+  Reset the connection's error counts to zero.
+
+  Returns the counts that were cleared, so no error is missed between reading and
+  resetting the counts.
+
+  ## Parameters
+    - state: The connection state
+
+  ## Example
+      {:ok, counts_since_last_reset} = EctoLibSql.Native.clear_error_counts(state)
+  """
+  @spec clear_error_counts(EctoLibSql.State.t()) ::
+          {:ok, %{atom() => non_neg_integer()}} | {:error, term()}
+  def clear_error_counts(%EctoLibSql.State{conn_id: conn_id} = _state) do
+    case reset_error_counts(conn_id) do
+      %{} = counts -> {:ok, counts}
+      {:error, reason} -> {:error, reason}
+    end
+  end
+
   @doc """
   Create a vector from a list of numbers for use in vector columns.
 
@@ -1360,14 +1883,43 @@ defmodule EctoLibSql.Native do
 
   ## Parameters
     - state: The connection state
+    - opts: Options
+      - `:shrink_memory` - Also release the connection's unused cache memory, as
+        `release_memory/1` does (default `false`)
 
   ## Example
 
       :ok = EctoLibSql.Native.reset(state)
+      :ok = EctoLibSql.Native.reset(state, shrink_memory: true)
+
+  """
+  def reset(%EctoLibSql.State{conn_id: conn_id} = _state, opts \\ []) do
+    reset_connection(conn_id, Keyword.get(opts, :shrink_memory, false))
+  end
+
+  @doc """
+  Release the connection's unused cache memory.
+
+  Runs `PRAGMA shrink_memory`, asking SQLite to free as much of the connection's heap
+  as it can, mostly cached pages. Useful after a heavy query, or for pooled connections
+  going idle, to keep the node's memory lean. The cache refills as the connection is
+  used again, so shrinking a busy connection only costs it extra reads.
+
+  ## Parameters
+    - state: The connection state
+
+  ## Returns
+    - `:ok` - Memory released
+    - `{:error, reason}` - The pragma failed
+
+  ## Example
+
+      :ok = EctoLibSql.Native.release_memory(state)
 
   """
-  def reset(%EctoLibSql.State{conn_id: conn_id} = _state) do
-    reset_connection(conn_id)
+  @spec release_memory(EctoLibSql.State.t()) :: :ok | {:error, term()}
+  def release_memory(%EctoLibSql.State{conn_id: conn_id} = _state) do
+    shrink_memory(conn_id)
   end
 
   @doc """
@@ -1609,6 +2161,9 @@ defmodule EctoLibSql.Native do
 
   4. **Connection-level restrictions** - Use different database connections with different privileges
 
+  5. **Native deny rules** - For a fixed deny-list, `set_authorizer_rules/2` installs an
+     authorizer that decides in Rust without calling back into Elixir
+
   ## Returns
     - `:unsupported` - Always returns unsupported
 
@@ -1617,6 +2172,60 @@ defmodule EctoLibSql.Native do
     set_authorizer(conn_id, pid)
   end
 
+  @doc """
+  Deny operations on a connection at the engine level with a native authorizer.
+
+  For running semi-trusted SQL. SQLite consults the authorizer while compiling each
+  statement, so a denied operation fails when the statement is prepared, before
+  anything runs. This also catches operations reached through views, triggers and
+  subqueries, which checking statement types can't. Unlike `add_authorizer/2`, the
+  decision is made natively from the rules, with no call back into Elixir.
+
+  Each rule is an action atom, denying that action everywhere, or an `{action, name}`
+  tuple limiting it to one object (compared case-insensitively):
+
+    - `:read`, `:insert`, `:update`, `:delete` - table access; `name` is the table
+    - `:schema` - creating, dropping, altering, reindexing or analysing; `name` is the
+      table, view or index
+    - `:pragma` - any pragma; `:pragma_write` only pragmas given a value, such as
+      `PRAGMA journal_mode = WAL`; `name` is the pragma
+    - `:attach`, `:detach` - `name` is the attached file or database
+    - `:transaction` - beginning, committing or rolling back, including savepoints
+    - `:function` - calling an SQL function; `name` is the function
+
+  Everything not denied is allowed. Passing `[]` removes the authorizer. Rules replace
+  any set earlier, and statements prepared before they were set are not checked again.
+  Other functions in this library that run pragmas or read tables are subject to the
+  rules too. Not available for remote connections.
+
+  ## Parameters
+    - state: The connection state
+    - rules: List of deny rules
+
+  ## Returns
+    - `:ok` - Rules installed
+    - `{:error, reason}` - Invalid rule, or a remote connection
+
+  ## Examples
+
+      :ok =
+        EctoLibSql.Native.set_authorizer_rules(state, [
+          :attach,
+          :pragma_write,
+          {:read, "secrets"}
+        ])
+
+      # Fails at prepare time: "access to secrets.token is prohibited"
+      {:error, _, _} = EctoLibSql.handle_execute("SELECT token FROM secrets", [], [], state)
+
+  """
+  @spec set_authorizer_rules(EctoLibSql.State.t(), [atom() | {atom(), String.t()}]) ::
+          :ok | {:error, term()}
+  def set_authorizer_rules(%EctoLibSql.State{conn_id: conn_id} = _state, rules)
+      when is_list(rules) do
+    set_deny_rules(conn_id, rules)
+  end
+
   @doc """
   Execute multiple SQL statements from a semicolon-separated string.
 
@@ -1935,6 +2544,76 @@ defmodule EctoLibSql.Native do
     {:error, "No active transaction"}
   end
 
+  @doc """
+  Get how deeply transactions are nested on the connection.
+
+  Returns `0` when no transaction is active, `1` for a top-level transaction, and one
+  more for each savepoint open within it. Releasing or rolling back to a savepoint
+  also closes any savepoints created after it, and the depth reflects that. Useful for
+  tracking down nesting bugs in code using nested transactions.
+
+  Only savepoints created with `create_savepoint/2` are counted, not `SAVEPOINT`
+  statements run directly.
+
+  ## Parameters
+    - state: The connection state
+
+  ## Example
+
+      0 = EctoLibSql.Native.get_transaction_depth(state)
+      {:ok, trx_state} = EctoLibSql.Native.begin(state)
+      :ok = EctoLibSql.Native.create_savepoint(trx_state, "sp1")
+      2 = EctoLibSql.Native.get_transaction_depth(trx_state)
+
+  """
+  @spec get_transaction_depth(EctoLibSql.State.t()) :: non_neg_integer() | {:error, term()}
+  def get_transaction_depth(%EctoLibSql.State{conn_id: conn_id} = _state) do
+    transaction_depth(conn_id)
+  end
+
+  @doc """
+  Check whether SQLite's transaction state agrees with the transaction registry.
+
+  Transactions begun with `begin/2` are tracked by the native layer, but a raw
+  `BEGIN` run as a query opens a transaction it knows nothing about, and a raw
+  `COMMIT` or `ROLLBACK` can end a tracked transaction behind its back. Mixing the two
+  leaves later transaction calls failing in confusing ways; this detects it so callers
+  can recover, for instance by rolling back.
+
+  ## Parameters
+    - state: The connection state
+
+  ## Returns
+  A map with:
+    - `:sqlite_autocommit` - SQLite's autocommit flag (`false` inside any transaction)
+    - `:registry_has_txn` - Whether a transaction begun with `begin/2` is tracked
+    - `:consistent` - `true` when SQLite is in a transaction exactly when one is tracked
+
+  ## Example
+
+      {:ok, _, _, state} = EctoLibSql.handle_execute("BEGIN", [], [], state)
+
+      %{consistent: false, sqlite_autocommit: false, registry_has_txn: false} =
+        EctoLibSql.Native.get_transaction_state(state)
+
+  """
+  @spec get_transaction_state(EctoLibSql.State.t()) ::
+          %{sqlite_autocommit: boolean(), registry_has_txn: boolean(), consistent: boolean()}
+          | {:error, term()}
+  def get_transaction_state(%EctoLibSql.State{conn_id: conn_id} = _state) do
+    case transaction_state(conn_id) do
+      {sqlite_autocommit, registry_has_txn, consistent} ->
+        %{
+          sqlite_autocommit: sqlite_autocommit,
+          registry_has_txn: registry_has_txn,
+          consistent: consistent
+        }
+
+      {:error, _} = error ->
+        error
+    end
+  end
+
   @doc """
   Get the current replication frame number from a remote replica.
 
@@ -1983,6 +2662,7 @@ defmodule EctoLibSql.Native do
 
   ## Returns
     - `:ok` - Successfully synced to the target frame
+    - `{:error, :not_a_replica}` - The connection is local or direct remote
     - `{:error, reason}` - If sync failed or connection is invalid
 
   ## Example
@@ -1995,7 +2675,6 @@ defmodule EctoLibSql.Native do
   ## Notes
     - This blocks until the frame is reached (with internal timeout)
     - Only works for remote replica connections
-    - Returns error if called on local or remote primary connections
 
   """
   def sync_until_frame(conn_id, target_frame)
@@ -2023,6 +2702,7 @@ defmodule EctoLibSql.Native do
 
   ## Returns
     - `{:ok, new_frame}` - Flush succeeded, returns new frame number
+    - `{:error, :not_a_replica}` - The connection is local or direct remote
     - `{:error, reason}` - If flush failed
 
   ## Example
@@ -2032,8 +2712,8 @@ defmodule EctoLibSql.Native do
 
   ## Notes
     - This is useful before taking snapshots or backups
-    - Returns the frame number after the flush (0 if not a replica)
-    - For local or remote primary connections, returns 0
+    - Returns the frame number after the flush (0 if no frames have been applied)
+    - Only works for remote replica connections
 
   """
   def flush_and_get_frame(conn_id) when is_binary(conn_id) do
@@ -2172,6 +2852,54 @@ defmodule EctoLibSql.Native do
     end
   end
 
+  @doc """
+  Get the source database, table and column of each result column in a prepared statement.
+
+  Lets tooling map aliased result columns back to the schema: a column selected as
+  `u.name AS author` reports table `"users"` and origin column `"name"`. Sources are
+  only known for plain column references. Expressions, aggregates and literals report
+  `nil` for `:database`, `:table` and `:origin_column`.
+
+  ## Parameters
+    - state: The connection state
+    - stmt_id: The statement ID returned from `prepare/2`
+
+  ## Returns
+    - `{:ok, columns}` - One `%{name:, database:, table:, origin_column:}` map per
+      result column, in order
+    - `{:error, reason}` - Unknown statement, or one owned by another connection
+
+  ## Example
+
+      {:ok, stmt_id} =
+        EctoLibSql.Native.prepare(state, "SELECT u.name AS author, count(*) AS n FROM users u")
+
+      {:ok,
+       [
+         %{name: "author", database: "main", table: "users", origin_column: "name"},
+         %{name: "n", database: nil, table: nil, origin_column: nil}
+       ]} = EctoLibSql.Native.stmt_column_provenance(state, stmt_id)
+
+  """
+  @spec stmt_column_provenance(EctoLibSql.State.t(), String.t()) ::
+          {:ok,
+           [
+             %{
+               name: String.t(),
+               database: String.t() | nil,
+               table: String.t() | nil,
+               origin_column: String.t() | nil
+             }
+           ]}
+          | {:error, term()}
+  def stmt_column_provenance(%EctoLibSql.State{conn_id: conn_id} = _state, stmt_id)
+      when is_binary(stmt_id) do
+    case column_provenance(conn_id, stmt_id) do
+      columns when is_list(columns) -> {:ok, columns}
+      {:error, reason} -> {:error, reason}
+    end
+  end
+
   @doc """
   Freeze a remote replica, converting it to a standalone local database.
 
@@ -2225,6 +2953,3197 @@ defmodule EctoLibSql.Native do
     {:error, :unsupported}
   end
 
+  @doc """
+  Normalise the `AUTOINCREMENT` sequence for a table after manual id inserts.
+
+  When rows are inserted with explicit ids (for example during a data migration),
+  the `sqlite_sequence` entry for the table can lag behind the real maximum id.
+  This raises it to `MAX(rowid)` so subsequent automatically assigned ids don't
+  collide. The sequence is never lowered, so ids are still never reused.
+
+  Tables without an `AUTOINCREMENT` column are left untouched.
+
+  ## Parameters
+    - state: The connection state
+    - table: The table name (atom or string, quoted internally)
+
+  ## Returns
+    - `{:ok, seq}` - The resulting sequence value
+    - `{:ok, nil}` - The table doesn't use `AUTOINCREMENT` (no-op)
+    - `{:error, reason}` - Table not found or the update failed
+
+  ## Example
+
+      {:ok, 1000} = EctoLibSql.Native.sync_autoincrement_sequence(state, "users")
+
+  """
+  @spec sync_autoincrement_sequence(EctoLibSql.State.t(), atom() | String.t()) ::
+          {:ok, integer() | nil} | {:error, term()}
+  def sync_autoincrement_sequence(%EctoLibSql.State{} = state, table) when is_atom(table) do
+    sync_autoincrement_sequence(state, Atom.to_string(table))
+  end
+
+  def sync_autoincrement_sequence(%EctoLibSql.State{conn_id: conn_id} = _state, table)
+      when is_binary(table) do
+    case sync_sequence(conn_id, table) do
+      {:error, reason} -> {:error, reason}
+      seq -> {:ok, seq}
+    end
+  end
+
+  @doc """
+  Get the most recent database error recorded for the connection.
+
+  NIFs that perform database work record the raw SQLite error before wrapping it
+  in an `{:error, reason}` term. This is useful for diagnosing failures where the
+  original error was wrapped or swallowed.
+
+  The record is sticky: it is replaced by the next error rather than cleared by
+  successful operations, and is discarded when the connection is closed.
+
+  ## Parameters
+    - state: The connection state
+
+  ## Returns
+    - `%{code: code, extended_code: extended_code, message: message}` - The last error.
+      `code` is the primary SQLite result code (e.g. `19` for `SQLITE_CONSTRAINT`) and
+      `extended_code` the extended code (e.g. `2067` for `SQLITE_CONSTRAINT_UNIQUE`).
+      Both are `nil` for errors that didn't originate in SQLite.
+    - `nil` - No error has been recorded
+    - `{:error, reason}` - Invalid connection
+
+  ## Example
+
+      {:error, _, _state} = EctoLibSql.handle_execute("INSERT INTO users (email) VALUES ('dup')", [], [], state)
+      %{code: 19, extended_code: 2067, message: message} = EctoLibSql.Native.get_last_error(state)
+
+  """
+  @spec get_last_error(EctoLibSql.State.t()) ::
+          %{code: integer() | nil, extended_code: integer() | nil, message: String.t()}
+          | nil
+          | {:error, term()}
+  def get_last_error(%EctoLibSql.State{conn_id: conn_id} = _state) do
+    case last_error(conn_id) do
+      {code, extended_code, message} ->
+        %{code: code, extended_code: extended_code, message: message}
+
+      other ->
+        other
+    end
+  end
+
+  @doc """
+  Stream a table's contents to a process as SQL `INSERT` statements.
+
+  Intended for lightweight logical replication: the receiving process can replay
+  the statements against another database (for example with `execute_batch_sql/2`).
+  Text is single-quoted with embedded quotes doubled and blobs are emitted as
+  `X'..'` hex literals, so the statements are safe to execute verbatim. Generated
+  columns are left out of the statements, so the target recomputes them.
+
+  The table is validated before this returns; the dump itself runs in the
+  background and delivers these messages to `pid`:
+
+    - `{:dump_chunk, statements}` - Up to `chunk` `INSERT` statements
+    - `:done` - Sent after the final chunk
+    - `{:dump_error, reason}` - Reading the table failed part-way through
+
+  Only `chunk` statements are held in memory at a time. The dump stops early if
+  `pid` exits. The connection is busy until the dump completes, so other
+  operations on it will wait.
+
+  ## Parameters
+    - state: The connection state
+    - table: The table name (atom or string, quoted internally)
+    - pid: The process to receive the statements
+    - chunk: Maximum statements per message (default: 500)
+
+  ## Returns
+    - `:ok` - The dump has started
+    - `{:error, reason}` - Table not found, invalid chunk size, or invalid connection
+
+  ## Example
+
+      :ok = EctoLibSql.Native.stream_table_as_sql(state, "users", self(), 100)
+
+      receive do
+        {:dump_chunk, statements} -> statements
+      end
+
+  """
+  @spec stream_table_as_sql(EctoLibSql.State.t(), atom() | String.t(), pid(), pos_integer()) ::
+          :ok | {:error, term()}
+  def stream_table_as_sql(state, table, pid, chunk \\ 500)
+
+  def stream_table_as_sql(%EctoLibSql.State{} = state, table, pid, chunk) when is_atom(table) do
+    stream_table_as_sql(state, Atom.to_string(table), pid, chunk)
+  end
+
+  def stream_table_as_sql(%EctoLibSql.State{conn_id: conn_id} = _state, table, pid, chunk)
+      when is_binary(table) and is_pid(pid) and is_integer(chunk) and chunk > 0 do
+    stream_table_dump(conn_id, table, pid, chunk)
+  end
+
+  def stream_table_as_sql(%EctoLibSql.State{}, _table, _pid, _chunk) do
+    {:error, "chunk must be a positive integer"}
+  end
+
+  @doc """
+  Prepare several SQL statements in one call and return their metadata.
+
+  Equivalent to calling `prepare/2`, `get_stmt_columns/2` and `stmt_parameter_count/2`
+  for each statement, but in a single round trip. Each statement is stored against
+  this connection and can be used with `query_stmt/3`, `execute_stmt/4` and
+  `close_stmt/1` as usual.
+
+  ## Parameters
+    - state: The connection state
+    - sqls: List of SQL statements to prepare
+    - opts: Options
+      - `:on_error` - `:abort` (default) or `:continue`
+
+  ## Returns
+    - `{:ok, entries}` - One entry per statement, in order:
+      - `%{stmt_id: id, columns: columns, param_count: count}`, with `columns` as
+        returned by `get_stmt_columns/2`
+      - `%{index: index, sql: sql, error: reason}` for a failed statement when
+        `on_error: :continue`
+    - `{:error, %{index: index, sql: sql, error: reason}}` - The first failure when
+      `on_error: :abort`; statements already prepared by the call are released
+    - `{:error, reason}` - Invalid connection
+
+  ## Example
+
+      {:ok, [%{stmt_id: select_id, param_count: 1}, %{stmt_id: insert_id}]} =
+        EctoLibSql.Native.prepare_all(state, [
+          "SELECT id, name FROM users WHERE id = ?",
+          "INSERT INTO users (name) VALUES (?)"
+        ])
+
+  """
+  @spec prepare_all(EctoLibSql.State.t(), [String.t()], keyword()) ::
+          {:ok, [map()]} | {:error, term()}
+  def prepare_all(%EctoLibSql.State{conn_id: conn_id} = _state, sqls, opts \\ [])
+      when is_list(sqls) do
+    continue_on_error = Keyword.get(opts, :on_error, :abort) == :continue
+
+    case prepare_many(conn_id, sqls, continue_on_error) do
+      entries when is_list(entries) ->
+        {:ok, Enum.map(entries, &prepared_entry/1)}
+
+      {:error, {index, sql, reason}} ->
+        {:error, %{index: index, sql: sql, error: reason}}
+
+      {:error, reason} ->
+        {:error, reason}
+    end
+  end
+
+  defp prepared_entry({:ok, stmt_id, columns, param_count}) do
+    %{stmt_id: stmt_id, columns: columns, param_count: param_count}
+  end
+
+  defp prepared_entry({:error, index, sql, reason}) do
+    %{index: index, sql: sql, error: reason}
+  end
+
+  @doc """
+  Declare a cursor over a prepared statement.
+
+  Executes the prepared statement with `args` and stores the results in a cursor
+  that can be read in batches with `fetch_cursor/3`. The statement's plan is reused,
+  so declaring several cursors over the same query with different parameters avoids
+  re-parsing the SQL each time.
+
+  ## Parameters
+    - state: The connection state
+    - stmt_id: The statement ID from `prepare/2` (must belong to this connection)
+    - args: List of positional parameters OR map with atom keys for named parameters
+
+  ## Returns
+    - `{:ok, cursor_id}` - The cursor was declared
+    - `{:error, reason}` - Statement not found, owned by another connection, or the query failed
+
+  ## Example
+
+      {:ok, stmt_id} = EctoLibSql.Native.prepare(state, "SELECT * FROM events WHERE kind = ?")
+      {:ok, cursor_id} = EctoLibSql.Native.declare_stmt_cursor(state, stmt_id, ["click"])
+      {columns, rows, count} = EctoLibSql.Native.fetch_cursor(state.conn_id, cursor_id, 500)
+
+  """
+  @spec declare_stmt_cursor(EctoLibSql.State.t(), String.t(), list() | map()) ::
+          {:ok, String.t()} | {:error, term()}
+  def declare_stmt_cursor(%EctoLibSql.State{conn_id: conn_id} = _state, stmt_id, args) do
+    case normalise_arguments_for_stmt(conn_id, stmt_id, args) do
+      {:error, reason} ->
+        {:error, "Failed to normalise parameters: #{reason}"}
+
+      normalised_args ->
+        case declare_cursor_from_statement(conn_id, stmt_id, normalised_args) do
+          cursor_id when is_binary(cursor_id) -> {:ok, cursor_id}
+          {:error, reason} -> {:error, reason}
+        end
+    end
+  end
+
+  @doc """
+  Overwrite a region of an existing blob in place.
+
+  Writes `data` at byte `offset` within the blob stored in `column` of the row
+  identified by `rowid`, leaving the rest of the blob untouched. This avoids
+  round-tripping a large binary through Elixir to patch a few bytes.
+
+  As with SQLite's incremental blob I/O, a blob cannot be resized this way: a
+  write that would extend past the end of the blob is rejected and nothing is
+  modified. libsql does not expose the incremental blob API itself, so the write
+  is performed by a single in-engine `UPDATE`; update hooks and triggers fire as
+  for any other update.
+
+  ## Parameters
+    - state: The connection state
+    - table: Table name (atom or string)
+    - column: Blob column name (atom or string)
+    - rowid: Rowid of the row to update
+    - offset: Zero-based byte offset to start writing at
+    - data: Binary to write
+
+  ## Returns
+    - `:ok` - The region was written
+    - `{:error, reason}` - Row not found, value not a blob, or the write is out of bounds
+
+  ## Example
+
+      :ok = EctoLibSql.Native.write_blob(state, "files", "data", 1, 1024, <<0xFF, 0xFE>>)
+
+  """
+  @spec write_blob(
+          EctoLibSql.State.t(),
+          atom() | String.t(),
+          atom() | String.t(),
+          integer(),
+          non_neg_integer(),
+          binary()
+        ) :: :ok | {:error, term()}
+  def write_blob(%EctoLibSql.State{conn_id: conn_id}, table, column, rowid, offset, data)
+      when is_integer(rowid) and is_integer(offset) and offset >= 0 and is_binary(data) do
+    write_blob_range(conn_id, to_string(table), to_string(column), rowid, offset, data)
+  end
+
+  @doc """
+  Execute a query, returning blobs as references instead of their bytes.
+
+  Each blob cell in the result is replaced by a `{:blob_ref, rowid, column, size, schema}`
+  marker, where `column` is the blob's column in its table, `size` is its length in
+  bytes and `schema` is the database holding the table (`"main"`, `"temp"` or an
+  attached name). Pass a marker to `fetch_blob/3` to load the bytes when they are
+  actually needed, which keeps large blobs out of the result when listing rows.
+
+  The query must be a `SELECT` that selects the rowid (or `INTEGER PRIMARY KEY`) of
+  the table holding the blobs, otherwise an error is returned. Blobs that cannot be
+  traced to a selected rowid, such as computed values or columns from another joined
+  table, are returned inline as usual. Referenced blobs are measured with `length()`,
+  so their bytes aren't read until they are fetched.
+
+  ## Parameters
+    - state: The connection state
+    - sql: The query to run
+    - args: Query parameters (list or map of named parameters)
+
+  ## Returns
+    - `{:ok, %EctoLibSql.Result{}}` - Rows with blob cells replaced by markers
+    - `{:error, reason}` - No rowid selected, or the query failed
+
+  ## Example
+
+      {:ok, %{rows: [[1, "a.png", {:blob_ref, 1, "data", 52_311, "main"}]]}} =
+        EctoLibSql.Native.query_blob_refs(state, "SELECT id, name, data FROM files")
+
+  """
+  @spec query_blob_refs(EctoLibSql.State.t(), String.t(), list() | map()) ::
+          {:ok, EctoLibSql.Result.t()} | {:error, term()}
+  def query_blob_refs(%EctoLibSql.State{conn_id: conn_id}, sql, args \\ [])
+      when is_binary(sql) do
+    with args when is_list(args) <- normalise_arguments(conn_id, sql, args),
+         %{"columns" => columns, "rows" => rows, "num_rows" => num_rows} <-
+           query_with_blob_refs(conn_id, sql, encode_parameters(args)) do
+      {:ok,
+       %EctoLibSql.Result{
+         command: detect_command(sql),
+         columns: columns,
+         rows: rows,
+         num_rows: num_rows
+       }}
+    end
+  end
+
+  @doc """
+  Load the bytes behind a blob reference returned by `query_blob_refs/3`.
+
+  Markers record their schema but not their table, so pass the table the blob was
+  selected from.
+
+  ## Parameters
+    - state: The connection state
+    - table: Table name (atom or string)
+    - blob_ref: A `{:blob_ref, rowid, column, size, schema}` marker
+
+  ## Returns
+    - `{:ok, binary}` - The blob's current bytes
+    - `{:error, reason}` - Row not found, or the value is no longer a blob
+
+  ## Example
+
+      {:ok, %{rows: [[_id, ref]]}} =
+        EctoLibSql.Native.query_blob_refs(state, "SELECT id, data FROM files WHERE id = 1")
+
+      {:ok, bytes} = EctoLibSql.Native.fetch_blob(state, "files", ref)
+
+  """
+  @spec fetch_blob(
+          EctoLibSql.State.t(),
+          atom() | String.t(),
+          {:blob_ref, integer(), String.t(), non_neg_integer(), String.t()}
+        ) :: {:ok, binary()} | {:error, term()}
+  def fetch_blob(
+        %EctoLibSql.State{conn_id: conn_id},
+        table,
+        {:blob_ref, rowid, column, _size, schema}
+      )
+      when is_integer(rowid) and is_binary(column) and is_binary(schema) do
+    case fetch_blob_ref(conn_id, rowid, column, to_string(table), schema) do
+      bytes when is_binary(bytes) -> {:ok, bytes}
+      {:error, reason} -> {:error, reason}
+    end
+  end
+
+  @doc """
+  Count the open connections pointing at a database path.
+
+  Scans all registered connections in this node and returns those opened against
+  `path`: the database file for local and replica connections, or the URL for
+  remote ones. Local paths are compared after resolving them, so `"./app.db"` and
+  `"app.db"` refer to the same file.
+
+  Useful for leak detection and for spotting pool misconfigurations that open
+  duplicate connections to the same database.
+
+  ## Parameters
+    - path: Database path or remote URL
+
+  ## Returns
+    - `{:ok, %{count: count, conn_ids: conn_ids}}` - Matching connections, with ids sorted
+    - `{:error, reason}` - The registry could not be read
+
+  ## Example
+
+      {:ok, %{count: 3}} = EctoLibSql.Native.count_connections_for_path("app.db")
+
+  """
+  @spec count_connections_for_path(String.t()) ::
+          {:ok, %{count: non_neg_integer(), conn_ids: [String.t()]}} | {:error, term()}
+  def count_connections_for_path(path) when is_binary(path) do
+    case connections_for_path(path) do
+      {:error, reason} -> {:error, reason}
+      conn_ids -> {:ok, %{count: length(conn_ids), conn_ids: conn_ids}}
+    end
+  end
+
+  @doc """
+  Execute write statements atomically and return each statement's affected row count.
+
+  Designed for remote (non-replica) connections, where every statement is a network
+  round trip: the statements are sent to the server as a single transactional batch
+  request. Other modes run them in a local transaction with the same result.
+
+  For the single-request path, parameters are inlined as escaped SQL literals, so
+  each entry must contain exactly one statement using positional `?` or `?NNN`
+  placeholders; named parameters are rejected.
+
+  Counts follow SQLite's `changes()`: statements that don't modify rows (such as
+  DDL) report the count of the most recent modifying statement.
+
+  ## Parameters
+    - state: The connection state
+    - statements: A list of `{sql, args}` tuples
+
+  ## Returns
+    - `{:ok, counts}` - Affected row counts, one per statement
+    - `{:error, reason}` - A statement failed and nothing was committed
+
+  ## Example
+
+      {:ok, [1, 1, 5]} =
+        EctoLibSql.Native.remote_batch_write(state, [
+          {"INSERT INTO users (name) VALUES (?)", ["Alice"]},
+          {"INSERT INTO users (name) VALUES (?)", ["Bob"]},
+          {"UPDATE users SET active = ?", [1]}
+        ])
+
+  """
+  @spec remote_batch_write(EctoLibSql.State.t(), list({String.t(), list()})) ::
+          {:ok, [non_neg_integer()]} | {:error, term()}
+  def remote_batch_write(%EctoLibSql.State{conn_id: conn_id}, statements)
+      when is_list(statements) do
+    case execute_write_batch(conn_id, statements) do
+      {:error, reason} -> {:error, reason}
+      counts -> {:ok, counts}
+    end
+  end
+
+  @doc """
+  Set how duplicate result column names are disambiguated for a connection.
+
+  Joins often return the same column name more than once (e.g. two `id` columns),
+  which makes results ambiguous and collides when rows are turned into maps. This
+  applies to subsequent queries and prepared statement queries on the connection;
+  batch and cursor results always use the raw names.
+
+  Only duplicated names are changed:
+
+    - `:raw` - Names exactly as SQLite reports them (the default)
+    - `:index_suffix` - Later duplicates get a numeric suffix: `id`, `id_2`
+    - `:table_prefix` - Duplicates are prefixed with their origin table: `users.id`,
+      `posts.id`. SQLite reports the underlying table rather than the alias, so
+      self-joins and computed columns fall back to a numeric suffix.
+
+  The same setting is available at connect time via the `:column_naming` option.
+
+  ## Parameters
+    - state: The connection state
+    - naming: `:raw`, `:index_suffix` or `:table_prefix`
+
+  ## Example
+
+      :ok = EctoLibSql.Native.column_naming(state, :table_prefix)
+      {:ok, _, result, _} =
+        EctoLibSql.handle_execute(
+          "SELECT u.id, p.id FROM users u JOIN posts p ON p.user_id = u.id",
+          [],
+          [],
+          state
+        )
+
+      result.columns
+      # => ["users.id", "posts.id"]
+
+  """
+  @spec column_naming(EctoLibSql.State.t(), :raw | :index_suffix | :table_prefix) ::
+          :ok | {:error, term()}
+  def column_naming(%EctoLibSql.State{conn_id: conn_id} = _state, naming)
+      when naming in [:raw, :index_suffix, :table_prefix] do
+    set_column_naming(conn_id, naming)
+  end
+
+  @doc """
+  Abort queries that exceed a number of SQLite virtual machine steps.
+
+  **NOT SUPPORTED** - A scan limit would be enforced by SQLite's progress handler,
+  counting VM operations and aborting the statement with
+  `{:error, :query_too_expensive}` once `max_steps` is exceeded. libsql does not
+  expose the progress handler, and registering it directly would require unsafe
+  FFI, which this library doesn't use.
+
+  ## Alternatives
+
+  To protect a shared database from expensive queries, consider:
+
+  1. **Interrupts** - Run the query in a task and call `interrupt/1` when a timer
+     fires. This bounds wall-clock time rather than VM steps.
+
+  2. **Bounded queries** - Add `LIMIT` clauses to queries built from untrusted input.
+
+  3. **Query plan checks** - Run `EXPLAIN QUERY PLAN` first and reject queries that
+     perform an unindexed `SCAN` of a large table.
+
+  ## Returns
+    - `{:error, :unsupported}` - Always returns unsupported
+
+  """
+  def set_scan_limit(%EctoLibSql.State{conn_id: conn_id} = _state, max_steps)
+      when is_integer(max_steps) and max_steps > 0 do
+    set_progress_limit(conn_id, max_steps)
+  end
+
+  @doc """
+  Fetch multiple rows by id, keyed by id.
+
+  Runs parameterised `IN (...)` queries natively (chunked to stay within SQLite's
+  bound parameter limit) and returns a map of `id => row`, where each row is a map
+  keyed by column name. Useful for filling a keyed cache from a set of ids in one call.
+
+  Ids that match no row are absent from the result. Map keys are the id values as
+  stored, so integer primary keys come back as integers.
+
+  ## Parameters
+    - state: The connection state
+    - table: Table name (atom or string, quoted internally)
+    - id_column: Column to match ids against, usually the primary key
+    - ids: List of id values
+
+  ## Returns
+    - `{:ok, rows_by_id}` - Map of `id => %{"column" => value}`
+    - `{:error, reason}` - Query failed (e.g. unknown table or column)
+
+  ## Example
+
+      {:ok, users} = EctoLibSql.Native.get_by_ids(state, "users", "id", [1, 2, 3])
+      users[2]["name"]
+      # => "Bob"
+
+  """
+  @spec get_by_ids(EctoLibSql.State.t(), atom() | String.t(), atom() | String.t(), list()) ::
+          {:ok, %{optional(term()) => %{String.t() => term()}}} | {:error, term()}
+  def get_by_ids(%EctoLibSql.State{}, _table, _id_column, []), do: {:ok, %{}}
+
+  def get_by_ids(%EctoLibSql.State{conn_id: conn_id}, table, id_column, ids) when is_list(ids) do
+    case fetch_by_ids(conn_id, to_string(table), to_string(id_column), ids) do
+      {:error, reason} -> {:error, reason}
+      rows_by_id when is_map(rows_by_id) -> {:ok, rows_by_id}
+    end
+  end
+
+  @doc """
+  Report whether a query uses an index or scans a table.
+
+  Plans the query with `EXPLAIN QUERY PLAN` (without executing it) and summarises how
+  each table is accessed, for quick query-tuning feedback during development:
+
+    - `:scan` - A full table scan. Automatic indexes count as scans, as SQLite
+      builds them by reading the whole table.
+    - `{:index, name}` - A lookup through the named index
+    - `{:index, :primary_key}` - A lookup by rowid or primary key
+
+  Queries that read a single table return that table's access directly. Queries
+  over several tables (joins, subqueries) return a list of `{table, access}` tuples
+  in plan order, where `table` is the alias if the query gives the table one.
+
+  ## Parameters
+    - state: The connection state
+    - sql: The query to analyse
+    - args: Query parameters (list or map of named parameters)
+
+  ## Returns
+    - `{:ok, access}` - For a query that reads one table
+    - `{:ok, [{table, access}]}` - For a query that reads several tables, or `[]` for none
+    - `{:error, reason}` - The query could not be planned
+
+  ## Examples
+
+      {:ok, {:index, "users_email_index"}} =
+        EctoLibSql.Native.used_index(state, "SELECT * FROM users WHERE email = ?", ["a@b.c"])
+
+      {:ok, :scan} =
+        EctoLibSql.Native.used_index(state, "SELECT * FROM users WHERE bio LIKE ?", ["%x%"])
+
+  """
+  @spec used_index(EctoLibSql.State.t(), String.t(), list() | map()) ::
+          {:ok, access | [{String.t(), access}]} | {:error, term()}
+        when access: :scan | {:index, String.t() | :primary_key}
+  def used_index(%EctoLibSql.State{conn_id: conn_id}, sql, args \\ []) when is_binary(sql) do
+    with args when is_list(args) <- normalise_arguments(conn_id, sql, args),
+         steps when is_list(steps) <- index_usage(conn_id, sql, encode_parameters(args)) do
+      case steps do
+        [{_table, access}] -> {:ok, access}
+        steps -> {:ok, steps}
+      end
+    end
+  end
+
+  @doc """
+  Check that an SQL script parses, without executing it.
+
+  Splits the script into statements (respecting string literals, quoted identifiers,
+  comments and trigger bodies) and prepares each one in turn, releasing it straight
+  away. Useful for validating migration files or user-supplied scripts before running
+  them.
+
+  Statements are prepared against the current schema, so references to tables,
+  columns and other objects that don't exist yet are not reported - an earlier
+  statement in the script may create them. Syntax errors are always reported.
+
+  ## Parameters
+    - state: The connection state
+    - sql: The SQL script to check
+
+  ## Returns
+    - `:ok` - Every statement parsed
+    - `{:error, {index, sql, reason}}` - The first statement that failed, with its
+      zero-based position in the script
+
+  ## Examples
+
+      :ok = EctoLibSql.Native.validate_sql(state, "CREATE TABLE t (a); INSERT INTO t VALUES (1)")
+
+      {:error, {1, "SELEC * FROM t", _reason}} =
+        EctoLibSql.Native.validate_sql(state, "SELECT 1; SELEC * FROM t")
+
+  """
+  @spec validate_sql(EctoLibSql.State.t(), String.t()) ::
+          :ok | {:error, {non_neg_integer(), String.t(), String.t()} | term()}
+  def validate_sql(%EctoLibSql.State{conn_id: conn_id}, sql) when is_binary(sql) do
+    validate_statements(conn_id, sql)
+  end
+
+  @doc """
+  Compact the database with `VACUUM` and report how much space was reclaimed.
+
+  Measures the database size (`page_count × page_size`) before and after running
+  `VACUUM`, for logging and operator feedback. Sizes cover the main database only,
+  not the WAL file.
+
+  `VACUUM` rewrites the entire database, so it can take a while on large databases
+  and needs up to twice the database size in free disk space. It cannot run inside
+  a transaction.
+
+  ## Parameters
+    - state: The connection state
+
+  ## Returns
+    - `{:ok, %{before_bytes: integer, after_bytes: integer, reclaimed_bytes: integer}}`
+    - `{:error, :unsupported}` - For remote connections, where the server manages the file
+    - `{:error, reason}` - `VACUUM` failed
+
+  ## Examples
+
+      {:ok, %{reclaimed_bytes: reclaimed}} = EctoLibSql.Native.vacuum_report(state)
+      Logger.info("VACUUM reclaimed \#{reclaimed} bytes")
+
+  """
+  @spec vacuum_report(EctoLibSql.State.t()) ::
+          {:ok,
+           %{
+             before_bytes: non_neg_integer(),
+             after_bytes: non_neg_integer(),
+             reclaimed_bytes: integer()
+           }}
+          | {:error, term()}
+  def vacuum_report(%EctoLibSql.State{conn_id: conn_id} = _state) do
+    case vacuum_with_sizes(conn_id) do
+      {:error, reason} ->
+        {:error, reason}
+
+      {before_bytes, after_bytes} ->
+        {:ok,
+         %{
+           before_bytes: before_bytes,
+           after_bytes: after_bytes,
+           reclaimed_bytes: before_bytes - after_bytes
+         }}
+    end
+  end
+
+  @doc """
+  Report the path and current size of the connection's WAL file.
+
+  For monitoring WAL growth separately from the main database. A WAL file that keeps
+  growing usually means checkpoints are being blocked, for example by long-running
+  read transactions, and is worth alerting on.
+
+  The path is the main database file plus SQLite's `-wal` suffix, and the size is read
+  from the filesystem. A WAL file that doesn't exist yet is reported as 0 bytes.
+
+  ## Parameters
+    - state: The connection state
+
+  ## Returns
+    - `{:ok, %{path: String.t(), size_bytes: non_neg_integer()}}`
+    - `{:error, :not_wal}` - The database is not in WAL mode, or is in memory
+    - `{:error, :unsupported}` - Remote connection, whose files are managed by the server
+    - `{:error, reason}` - Query or filesystem failure
+
+  ## Examples
+
+      {:ok, %{size_bytes: size}} = EctoLibSql.Native.wal_info(state)
+
+      if size > 100 * 1024 * 1024 do
+        Logger.warning("WAL is \#{size} bytes; checkpoints may be blocked")
+      end
+
+  """
+  @spec wal_info(EctoLibSql.State.t()) ::
+          {:ok, %{path: String.t(), size_bytes: non_neg_integer()}} | {:error, term()}
+  def wal_info(%EctoLibSql.State{conn_id: conn_id} = _state) do
+    case wal_file_info(conn_id) do
+      {path, size_bytes} -> {:ok, %{path: path, size_bytes: size_bytes}}
+      {:error, reason} -> {:error, reason}
+    end
+  end
+
+  @doc """
+  Execute an `INSERT`, `UPDATE` or `DELETE` and return the rowids of the affected rows.
+
+  A fallback for change capture where `RETURNING` isn't available (older SQLite
+  servers) or would return more data than needed. A temporary trigger on the
+  statement's target table records each affected rowid while the statement runs, and
+  is dropped again afterwards, even if the statement fails. Rows updated by an upsert
+  (`INSERT ... ON CONFLICT ... DO UPDATE`) are reported along with inserted ones.
+
+  Only rows of the target table are reported; rows changed by other triggers or by
+  foreign key actions are not. Tables declared `WITHOUT ROWID` are not supported.
+
+  ## Parameters
+    - state: The connection state
+    - sql: A single `INSERT`, `REPLACE`, `UPDATE` or `DELETE` statement. Statements
+      starting with a `WITH` clause are not supported.
+    - args: Statement parameters (list or map of named parameters)
+
+  ## Returns
+    - `{:ok, rowids}` - Affected rowids, in the order the rows were changed
+    - `{:error, reason}` - Unsupported statement, or the statement failed
+
+  ## Examples
+
+      {:ok, [2, 4, 6]} =
+        EctoLibSql.Native.execute_capturing_rowids(
+          state,
+          "UPDATE users SET active = 0 WHERE last_seen < ?",
+          [cutoff]
+        )
+
+  """
+  @spec execute_capturing_rowids(EctoLibSql.State.t(), String.t(), list() | map()) ::
+          {:ok, [integer()]} | {:error, term()}
+  def execute_capturing_rowids(%EctoLibSql.State{conn_id: conn_id}, sql, args \\ [])
+      when is_binary(sql) do
+    with args when is_list(args) <- normalise_arguments(conn_id, sql, args),
+         rowids when is_list(rowids) <-
+           capture_affected_rowids(conn_id, sql, encode_parameters(args)) do
+      {:ok, rowids}
+    end
+  end
+
+  @doc """
+  Run a query and return its results as an Arrow IPC stream.
+
+  Builds the columnar binary natively, skipping the per-cell conversion to Elixir
+  terms, for handing results to analytics tools. The binary can be loaded with
+  `Explorer.DataFrame.load_ipc_stream/2`.
+
+  Column types come from the storage class of each column's first non-`NULL` value:
+
+    - integer → `int64`
+    - real → `float64` (integers in a real column are widened)
+    - text → `utf8`
+    - blob → `binary`
+    - no values → `null`
+
+  Any other mix of storage classes in one column is an error; `CAST` the column in
+  the query. All rows are read into memory and returned in a single record batch.
+
+  ## Parameters
+    - state: The connection state
+    - sql: The query to run
+    - args: Query parameters (list or map of named parameters)
+
+  ## Returns
+    - `{:ok, binary}` - The Arrow IPC stream
+    - `{:error, reason}` - The query failed or a column mixes storage classes
+
+  ## Examples
+
+      {:ok, ipc} = EctoLibSql.Native.query_to_arrow(state, "SELECT * FROM readings")
+      {:ok, df} = Explorer.DataFrame.load_ipc_stream(ipc)
+
+  """
+  @spec query_to_arrow(EctoLibSql.State.t(), String.t(), list() | map()) ::
+          {:ok, binary()} | {:error, term()}
+  def query_to_arrow(%EctoLibSql.State{conn_id: conn_id}, sql, args \\ []) when is_binary(sql) do
+    with args when is_list(args) <- normalise_arguments(conn_id, sql, args),
+         ipc when is_binary(ipc) <- query_arrow_ipc(conn_id, sql, encode_parameters(args)) do
+      {:ok, ipc}
+    end
+  end
+
+  @doc """
+  Run a query, applying predefined transforms to named columns as rows are read.
+
+  For simple projections done on every row, such as lowercasing an email column,
+  the work happens natively while the result is built rather than per row in Elixir.
+  The transform set is deliberately small and fixed:
+
+    - `:lower` - lowercase text (Unicode-aware, unlike SQLite's `lower()`)
+    - `:upper` - uppercase text (Unicode-aware, unlike SQLite's `upper()`)
+    - `:trim` - strip leading and trailing whitespace from text
+    - `:base64` - encode text or blob bytes as base64 text
+
+  Values of other types, including `nil`, are returned unchanged. Columns are named
+  as they appear in the result, after any `:column_naming` disambiguation.
+
+  ## Parameters
+    - state: The connection state
+    - sql: The query to run
+    - args: Query parameters (list or map of named parameters)
+    - transforms: Map of result column name to transform atom
+
+  ## Returns
+    - `{:ok, %EctoLibSql.Result{}}` - The transformed result
+    - `{:error, reason}` - Unknown transform or column, or the query failed
+
+  ## Examples
+
+      {:ok, result} =
+        EctoLibSql.Native.query_with_transforms(
+          state,
+          "SELECT id, email FROM users",
+          [],
+          %{"email" => :lower}
+        )
+
+  """
+  @spec query_with_transforms(
+          EctoLibSql.State.t(),
+          String.t(),
+          list() | map(),
+          %{String.t() => :lower | :upper | :trim | :base64}
+        ) :: {:ok, EctoLibSql.Result.t()} | {:error, term()}
+  def query_with_transforms(%EctoLibSql.State{conn_id: conn_id}, sql, args, transforms)
+      when is_binary(sql) and is_map(transforms) do
+    with args when is_list(args) <- normalise_arguments(conn_id, sql, args),
+         %{"columns" => columns, "rows" => rows, "num_rows" => num_rows} <-
+           query_transform(conn_id, sql, encode_parameters(args), transforms) do
+      {:ok,
+       %EctoLibSql.Result{
+         command: detect_command(sql),
+         columns: columns,
+         rows: rows,
+         num_rows: num_rows
+       }}
+    end
+  end
+
+  @doc """
+  Start a time budget shared by several queries.
+
+  Returns a token to pass to `query_with_deadline/4`. Each query run with the token
+  gets whatever time is left as its timeout, so a request handler can bound the total
+  time spent across all of its queries rather than each query separately.
+
+  Release the token with `release_deadline/1` once the work is done. Tokens that are
+  never released are forgotten a minute after their budget runs out, after which
+  queries against them fail as with an unknown token.
+
+  ## Parameters
+    - deadline_ms: Total budget in milliseconds, starting now
+
+  ## Examples
+
+      {:ok, token} = EctoLibSql.Native.new_deadline(200)
+      {:ok, users} = EctoLibSql.Native.query_with_deadline(state, "SELECT * FROM users", [], token)
+      {:ok, posts} = EctoLibSql.Native.query_with_deadline(state, "SELECT * FROM posts", [], token)
+      :ok = EctoLibSql.Native.release_deadline(token)
+
+  """
+  @spec new_deadline(non_neg_integer()) :: {:ok, String.t()} | {:error, term()}
+  def new_deadline(deadline_ms) when is_integer(deadline_ms) and deadline_ms >= 0 do
+    token = "deadline-" <> Integer.to_string(System.unique_integer([:positive]))
+
+    case set_deadline(token, deadline_ms) do
+      :ok -> {:ok, token}
+      {:error, reason} -> {:error, reason}
+    end
+  end
+
+  @doc """
+  Release a token created with `new_deadline/1`.
+
+  Releasing an unknown or already released token is not an error.
+  """
+  @spec release_deadline(String.t()) :: :ok | {:error, term()}
+  def release_deadline(token) when is_binary(token), do: clear_deadline(token)
+
+  @doc """
+  Run a query bounded by the time left on a deadline token.
+
+  The remaining budget of `token` is applied as the query's timeout. A query that is
+  still running when the budget runs out is interrupted, and a query started after the
+  budget is spent is not run at all.
+
+  ## Parameters
+    - state: The connection state
+    - sql: The query to run
+    - args: Query parameters (list or map of named parameters)
+    - token: A token from `new_deadline/1`
+
+  ## Returns
+    - `{:ok, %EctoLibSql.Result{}}` - The query finished in time
+    - `{:error, :deadline_exceeded}` - The budget ran out before or during the query
+    - `{:error, reason}` - The query failed, or the token is unknown
+
+  ## Examples
+
+      {:ok, token} = EctoLibSql.Native.new_deadline(100)
+
+      case EctoLibSql.Native.query_with_deadline(state, "SELECT * FROM reports", [], token) do
+        {:ok, result} -> result.rows
+        {:error, :deadline_exceeded} -> []
+      end
+
+  """
+  @spec query_with_deadline(EctoLibSql.State.t(), String.t(), list() | map(), String.t()) ::
+          {:ok, EctoLibSql.Result.t()} | {:error, term()}
+  def query_with_deadline(%EctoLibSql.State{conn_id: conn_id}, sql, args, token)
+      when is_binary(sql) and is_binary(token) do
+    with args when is_list(args) <- normalise_arguments(conn_id, sql, args),
+         %{"columns" => columns, "rows" => rows, "num_rows" => num_rows} <-
+           query_args_deadline(conn_id, sql, encode_parameters(args), token) do
+      command = detect_command(sql)
+
+      {columns, rows} =
+        if command in [:insert, :update, :delete] and columns == [] and rows == [] do
+          {nil, nil}
+        else
+          {columns, rows}
+        end
+
+      {:ok,
+       %EctoLibSql.Result{command: command, columns: columns, rows: rows, num_rows: num_rows}}
+    end
+  end
+
+  @doc """
+  Run write statements against a table with its triggers disabled.
+
+  Speeds up bulk loads into tables with expensive triggers. SQLite can't switch a
+  single trigger off, so the table's triggers are dropped, the statements run, and the
+  triggers are recreated from their original definitions, all in one transaction. The
+  triggers don't fire for any of the statements, and are restored even when a
+  statement fails.
+
+  Only triggers stored in the main database schema are disabled; `TEMP` triggers
+  still fire. Other connections never see the table without its triggers.
+
+  ## Parameters
+    - state: The connection state
+    - table: The table whose triggers are disabled
+    - statements: A list of `{sql, args}` tuples
+
+  ## Returns
+    - `{:ok, counts}` - Affected row counts, one per statement
+    - `{:error, reason}` - A statement failed; nothing was committed and the triggers
+      are unchanged
+
+  ## Example
+
+      {:ok, [500, 500]} =
+        EctoLibSql.Native.with_triggers_disabled(state, "events", [
+          {"INSERT INTO events SELECT * FROM staging WHERE batch = ?", [1]},
+          {"INSERT INTO events SELECT * FROM staging WHERE batch = ?", [2]}
+        ])
+
+  """
+  @spec with_triggers_disabled(EctoLibSql.State.t(), String.t(), list({String.t(), list()})) ::
+          {:ok, [non_neg_integer()]} | {:error, term()}
+  def with_triggers_disabled(%EctoLibSql.State{conn_id: conn_id}, table, statements)
+      when is_binary(table) and is_list(statements) do
+    case execute_with_triggers_disabled(conn_id, table, statements) do
+      {:error, reason} -> {:error, reason}
+      counts -> {:ok, counts}
+    end
+  end
+
+  @doc """
+  Compute a digest of a statement's shape, for grouping telemetry by query.
+
+  String, blob and numeric literals and bound parameters are replaced with `?`, comma
+  separated runs of `?` (such as `IN` lists) collapse to one, comments are dropped,
+  unquoted words are lowercased and whitespace is normalised before hashing. Statements
+  that differ only in their values therefore share a digest, and the digest never
+  reveals those values. This is a lightweight token scan, not a full parse.
+
+  Digests are 16 lowercase hex digits and stable across restarts and releases. No
+  connection is needed. Use `query_shape/1` for the normalised text itself.
+
+  ## Examples
+
+      digest = EctoLibSql.Native.query_digest("SELECT * FROM users WHERE id = 42")
+      ^digest = EctoLibSql.Native.query_digest("select * from users where id = 7")
+
+  """
+  @spec query_digest(String.t()) :: String.t()
+  def query_digest(sql) when is_binary(sql) do
+    {digest, _shape} = statement_digest(sql)
+    digest
+  end
+
+  @doc """
+  Normalise a statement to the shape hashed by `query_digest/1`.
+
+  Literal values are replaced with `?`, so the result is safe to log or display in
+  "top queries" dashboards.
+
+  ## Examples
+
+      "select*from users where id=?" =
+        EctoLibSql.Native.query_shape("SELECT * FROM users WHERE id = 42")
+
+  """
+  @spec query_shape(String.t()) :: String.t()
+  def query_shape(sql) when is_binary(sql) do
+    {_digest, shape} = statement_digest(sql)
+    shape
+  end
+
+  @doc """
+  Fetch one page of a query using keyset pagination.
+
+  Returns up to `limit` rows of `base_sql` whose `key_column` is greater than
+  `after_key`, in key order, plus the key of the page's last row to pass as
+  `after_key` for the next page. Pass `nil` for the first page. An empty page, with a
+  `nil` next key, means there are no more rows.
+
+  Unlike `OFFSET`, each page seeks straight to its first row through the key's index,
+  so late pages of a large table cost no more than the first. The base query is
+  wrapped as a subquery, so it may filter and join freely, but must be a single
+  `SELECT` without its own top-level `ORDER BY`, `LIMIT` or `OFFSET`. The key column
+  must be unique and appear in the base query's results.
+
+  ## Parameters
+    - state: The connection state
+    - base_sql: The query to page through
+    - key_column: The result column to order and page by
+    - after_key: The last key of the previous page, or `nil` for the first page
+    - limit: Maximum rows per page
+
+  ## Returns
+    - `{:ok, %EctoLibSql.Result{}, next_key}` - The page, and the key to continue from
+    - `{:error, reason}` - Invalid base query, or the query failed
+
+  ## Example
+
+      {:ok, page, next_key} =
+        EctoLibSql.Native.paginate_keyset(state, "SELECT id, name FROM users", "id", nil, 100)
+
+      {:ok, next_page, _} =
+        EctoLibSql.Native.paginate_keyset(state, "SELECT id, name FROM users", "id", next_key, 100)
+
+  """
+  @spec paginate_keyset(
+          EctoLibSql.State.t(),
+          String.t(),
+          atom() | String.t(),
+          term(),
+          non_neg_integer()
+        ) :: {:ok, EctoLibSql.Result.t(), term()} | {:error, term()}
+  def paginate_keyset(
+        %EctoLibSql.State{conn_id: conn_id},
+        base_sql,
+        key_column,
+        after_key,
+        limit
+      )
+      when is_binary(base_sql) and is_integer(limit) and limit >= 0 do
+    case keyset_page(conn_id, base_sql, to_string(key_column), after_key, limit) do
+      {%{"columns" => columns, "rows" => rows, "num_rows" => num_rows}, next_key} ->
+        result = %EctoLibSql.Result{
+          command: :select,
+          columns: columns,
+          rows: rows,
+          num_rows: num_rows
+        }
+
+        {:ok, result, next_key}
+
+      {:error, reason} ->
+        {:error, reason}
+    end
+  end
+
+  @doc """
+  Render a value as an SQLite literal, for inlining into logged SQL.
+
+  Uses the same escaping as the native layer's table dumps, so logs and fixture
+  tooling can share it:
+
+    - text → single-quoted, with embedded quotes doubled
+    - `{:blob, data}` and non-UTF-8 binaries → `X'..'` hex notation
+    - integers and floats → bare numbers (floats keep a decimal point)
+    - `true`/`false` → `1`/`0`
+    - `nil` → `NULL`
+    - plain maps → JSON text
+
+  Intended for human-readable query logs during development. Always bind values as
+  parameters when running queries.
+
+  ## Examples
+
+      {:ok, "'O''Brien'"} = EctoLibSql.Native.to_sql_literal("O'Brien")
+      {:ok, "X'CAFE'"} = EctoLibSql.Native.to_sql_literal({:blob, <<0xCA, 0xFE>>})
+      {:ok, "NULL"} = EctoLibSql.Native.to_sql_literal(nil)
+
+  """
+  @spec to_sql_literal(term()) :: {:ok, String.t()} | {:error, term()}
+  def to_sql_literal(value) do
+    case inline_value(encode_param(value)) do
+      literal when is_binary(literal) -> {:ok, literal}
+      {:error, reason} -> {:error, reason}
+    end
+  end
+
+  @doc """
+  Open a connection to a private in-memory copy of an SQLite database given as bytes.
+
+  Handy for fixture-based tests: load a known database from a binary without a file
+  on disk, and let each test write to its own copy. Writes stay in memory and are lost
+  when the connection is closed; use `serialize/1` to capture them as bytes again.
+
+  The bytes pass briefly through a temporary file while being loaded, which is removed
+  before this returns.
+
+  ## Parameters
+    - bytes: Contents of an SQLite database file
+
+  ## Example
+      fixture = File.read!("test/fixtures/catalogue.db")
+      {:ok, state} = EctoLibSql.Native.open_from_bytes(fixture)
+  """
+  @spec open_from_bytes(binary()) :: {:ok, EctoLibSql.State.t()} | {:error, term()}
+  def open_from_bytes(bytes) when is_binary(bytes) do
+    case connect_from_bytes(bytes) do
+      conn_id when is_binary(conn_id) ->
+        {:ok, %EctoLibSql.State{conn_id: conn_id, mode: :local, sync: :disable_sync}}
+
+      {:error, _} = error ->
+        error
+    end
+  end
+
+  @doc """
+  Return the contents of the connection's main database as an SQLite database file.
+
+  The result can be written to disk or reopened with `open_from_bytes/1`, which
+  makes it a natural snapshot for in-memory databases. For file databases it captures
+  the committed state, including changes not yet checkpointed from the WAL. Returns
+  an error while a transaction is open, and is not supported for remote connections.
+
+  ## Parameters
+    - state: The connection state
+
+  ## Example
+      {:ok, bytes} = EctoLibSql.Native.serialize(state)
+      File.write!("snapshot.db", bytes)
+  """
+  @spec serialize(EctoLibSql.State.t()) :: {:ok, binary()} | {:error, term()}
+  def serialize(%EctoLibSql.State{conn_id: conn_id} = _state) do
+    case serialize_database(conn_id) do
+      bytes when is_binary(bytes) -> {:ok, bytes}
+      {:error, _} = error -> error
+    end
+  end
+
+  @doc """
+  Check that a backup file opens and passes SQLite's integrity check.
+
+  Opens the file read-only through a temporary connection, runs
+  `PRAGMA integrity_check` and `PRAGMA schema_version`, then closes it again whatever
+  the outcome. No connection state is needed, so a backup written with `serialize/1`
+  can be checked before it's relied upon. The check reads the whole file, so it can
+  take a while on large backups.
+
+  ## Parameters
+    - path: Path to the backup database file
+
+  ## Returns
+    - `{:ok, %{schema_version: version}}` - The backup is intact
+    - `{:error, problems}` - A list of problems found, as strings. Covers missing or
+      non-SQLite files, files that fail to open, and every integrity check failure
+
+  ## Example
+      {:ok, bytes} = EctoLibSql.Native.serialize(state)
+      File.write!("backup.db", bytes)
+      {:ok, %{schema_version: _}} = EctoLibSql.Native.verify_backup("backup.db")
+  """
+  @spec verify_backup(Path.t()) ::
+          {:ok, %{schema_version: integer()}} | {:error, [String.t()]}
+  def verify_backup(path) when is_binary(path) do
+    case check_backup(path) do
+      version when is_integer(version) -> {:ok, %{schema_version: version}}
+      {:error, _} = error -> error
+    end
+  end
+
+  @doc """
+  Run several independent read queries in one native call.
+
+  Useful when a page fans out to many unrelated `SELECT`s: each query would otherwise
+  be its own NIF call. Queries run in order on the connection and each returns its own
+  result. Unlike `batch/2`, this is only for reads: SQLite's `query_only` mode is
+  switched on while they run, so any query that would write fails instead.
+
+  Execution stops at the first failing query, and its zero-based index is reported.
+  If `query_only` can't be switched back off afterwards, the connection is left in an
+  unknown state: it fails `ping/1` from then on so the pool replaces it.
+
+  ## Parameters
+    - state: The connection state
+    - queries: A list of `{sql, args}` tuples
+
+  ## Returns
+    - `{:ok, [%EctoLibSql.Result{}]}` - One result per query, in order
+    - `{:error, {index, reason}}` - The query at `index` failed
+
+  ## Example
+      {:ok, [users, orders, stock]} =
+        EctoLibSql.Native.query_multiple(state, [
+          {"SELECT count(*) FROM users", []},
+          {"SELECT id, total FROM orders WHERE placed_at > ?", [since]},
+          {"SELECT sku FROM products WHERE stock < ?", [10]}
+        ])
+  """
+  @spec query_multiple(EctoLibSql.State.t(), list({String.t(), list()})) ::
+          {:ok, list(EctoLibSql.Result.t())} | {:error, term()}
+  def query_multiple(%EctoLibSql.State{conn_id: conn_id} = _state, queries)
+      when is_list(queries) do
+    encoded = Enum.map(queries, fn {sql, args} -> {sql, encode_parameters(args)} end)
+
+    case query_multi(conn_id, encoded) do
+      results when is_list(results) ->
+        parsed =
+          Enum.map(results, fn %{"columns" => columns, "rows" => rows, "num_rows" => num_rows} ->
+            %EctoLibSql.Result{
+              command: :select,
+              columns: columns,
+              rows: rows,
+              num_rows: num_rows
+            }
+          end)
+
+        {:ok, parsed}
+
+      {:error, reason} ->
+        {:error, reason}
+    end
+  end
+
+  @doc """
+  Classify where a statement's writes would land on this connection.
+
+  On a remote replica, writes are sent to the remote primary and only appear in the
+  local replica once the primary accepts them and they are synced back. Callers that
+  expect replica writes to behave like local ones can check before running a
+  statement, and log or route accordingly.
+
+  Only the leading keyword is examined: `INSERT`, `REPLACE`, `UPDATE`, `DELETE` and
+  schema changes are writes.
+
+  ## Parameters
+    - state: The connection state
+    - sql: The statement to classify
+
+  ## Returns
+    - `:read` - The statement doesn't write
+    - `:local` - Written to the local database file
+    - `:remote` - Sent to the remote server
+    - `:replica` - Sent to the remote primary, then synced back to this replica
+
+  Queries run through `query_args/6` already flag replica writes: their result map
+  carries `"write_route" => :replica`, and `EctoLibSql.handle_execute/4` logs a
+  warning for them.
+
+  ## Example
+      if EctoLibSql.Native.write_route(state, sql) == :replica do
+        Logger.debug("Write goes to the primary; call sync/1 before reading it back")
+      end
+  """
+  @spec write_route(EctoLibSql.State.t(), String.t()) ::
+          :read | :local | :remote | :replica | {:error, term()}
+  def write_route(%EctoLibSql.State{conn_id: conn_id} = _state, sql) when is_binary(sql) do
+    classify_write(conn_id, sql)
+  end
+
+  @doc false
+  def warn_replica_write(%{"write_route" => :replica} = result, sql) do
+    require Logger
+
+    # The statement may carry sensitive literals, so only its command goes in the warning.
+    command = sql |> detect_command() |> Atom.to_string() |> String.upcase()
+
+    Logger.warning(
+      "#{command} sent to the remote primary; it appears in this replica after the next sync"
+    )
+
+    Logger.debug(fn -> "Replica write: #{sql}" end)
+
+    result
+  end
+
+  def warn_replica_write(result, _sql), do: result
+
+  @doc """
+  Detect the conflict action of an `INSERT`, `REPLACE` or `UPDATE` statement.
+
+  Recognises `INSERT OR <action>`, `UPDATE OR <action>` and `REPLACE` (shorthand for
+  `INSERT OR REPLACE`). Statements without an `OR` clause use `:abort`, SQLite's
+  default. Writes behind a `WITH` clause are not recognised.
+
+  The action decides what a write's `num_rows` means:
+
+    - `:abort`, `:fail`, `:rollback` - every row written is counted; a conflict is an
+      error instead (`:fail` keeps earlier rows, `:rollback` ends the transaction)
+    - `:ignore` - conflicting rows are skipped and not counted, so `num_rows` can be
+      lower than the number of rows given, and is `0` when every row conflicted
+    - `:replace` - rows deleted to resolve a conflict are not counted, so one inserted
+      row may have replaced several existing ones while `num_rows` is `1`
+
+  ## Parameters
+    - sql: The statement to analyse
+
+  ## Returns
+    - `:abort`, `:fail`, `:ignore`, `:replace` or `:rollback`
+    - `nil` - Not an `INSERT`, `REPLACE` or `UPDATE` statement
+
+  ## Examples
+
+      :ignore = EctoLibSql.Native.conflict_action("INSERT OR IGNORE INTO users VALUES (?)")
+      :replace = EctoLibSql.Native.conflict_action("REPLACE INTO users VALUES (?)")
+      nil = EctoLibSql.Native.conflict_action("SELECT 1")
+
+  """
+  @spec conflict_action(String.t()) :: :abort | :fail | :ignore | :replace | :rollback | nil
+  def conflict_action(sql) when is_binary(sql) do
+    statement_conflict_action(sql)
+  end
+
+  @doc """
+  Describe each column of a table, including generated columns.
+
+  Combines `PRAGMA table_xinfo`, which (unlike `table_info`) lists generated columns
+  and whether they are `VIRTUAL` or `STORED`, with the generating expressions read from
+  the table's `CREATE TABLE` SQL. Useful for tooling that has to understand computed
+  columns, such as schema diffing or fixture generation.
+
+  ## Parameters
+    - state: The connection state
+    - table: The table name, matched case-insensitively
+
+  ## Returns
+    - `{:ok, columns}` - One map per column in declaration order, with keys `:name`,
+      `:type` (empty string when undeclared), `:default` (the default expression as
+      written, or `nil`) and `:generated` (`nil`, or `{:virtual | :stored, expression}`)
+    - `{:error, reason}` - Unknown table, or the query failed
+
+  ## Example
+      {:ok, [_id, _price, total]} = EctoLibSql.Native.column_details(state, "items")
+      total.generated
+      # => {:virtual, "price * qty"}
+  """
+  @spec column_details(EctoLibSql.State.t(), String.t()) ::
+          {:ok, [map()]} | {:error, term()}
+  def column_details(%EctoLibSql.State{conn_id: conn_id} = _state, table)
+      when is_binary(table) do
+    case table_column_details(conn_id, table) do
+      columns when is_list(columns) ->
+        {:ok,
+         Enum.map(columns, fn {name, type, default, generated} ->
+           %{name: name, type: type, default: default, generated: generated}
+         end)}
+
+      {:error, reason} ->
+        {:error, reason}
+    end
+  end
+
+  @doc """
+  List the databases open on a connection: `main`, `temp` and each attached database.
+
+  Runs `PRAGMA database_list`, for managing databases added with `ATTACH` and
+  debugging shard setups. `temp` is only listed once the connection has used it, such
+  as by creating a temporary table.
+
+  ## Parameters
+    - state: The connection state
+
+  ## Returns
+    - `{:ok, databases}` - One `%{seq: integer, name: String.t(), file: String.t() | nil}`
+      per database in `seq` order, where `file` is the database file's absolute path, or
+      `nil` for in-memory and temporary databases
+    - `{:error, reason}` - Query failure
+
+  ## Examples
+
+      {:ok, _, _, state} =
+        EctoLibSql.handle_execute("ATTACH DATABASE 'shard_1.db' AS shard_1", [], [], state)
+
+      {:ok, [%{name: "main"}, %{name: "shard_1", file: "/srv/app/shard_1.db"}]} =
+        EctoLibSql.Native.attached_databases(state)
+
+  """
+  @spec attached_databases(EctoLibSql.State.t()) ::
+          {:ok, [%{seq: integer(), name: String.t(), file: String.t() | nil}]}
+          | {:error, term()}
+  def attached_databases(%EctoLibSql.State{conn_id: conn_id} = _state) do
+    case database_list(conn_id) do
+      databases when is_list(databases) ->
+        {:ok,
+         Enum.map(databases, fn {seq, name, file} -> %{seq: seq, name: name, file: file} end)}
+
+      {:error, reason} ->
+        {:error, reason}
+    end
+  end
+
+  @doc """
+  Run a query against one attached database without prefixing its tables.
+
+  SQLite has no `USE` statement, so the query is rewritten instead: each unqualified
+  table reference naming a table or view of `db_alias` is qualified with it. Table
+  references are the names after `FROM` and `JOIN`, in a comma-separated `FROM` list,
+  after `INTO`, and after `UPDATE`. Names that `db_alias` doesn't have, such as CTEs
+  or tables only in `main`, are left for SQLite to resolve as usual, and explicitly
+  qualified references are never changed.
+
+  The rewrite is a token scan rather than a full parse, so a CTE sharing its name
+  with a table of `db_alias` is qualified too; rename the CTE to avoid this.
+
+  ## Parameters
+    - state: The connection state
+    - db_alias: The name the database was attached as
+    - sql: The query to run
+    - args: Query parameters (default: [])
+
+  ## Returns
+    - `{:ok, %EctoLibSql.Result{}}` - The query ran against `db_alias`
+    - `{:error, reason}` - No database named `db_alias` is attached, or the query failed
+
+  ## Example
+
+      {:ok, _, _, state} =
+        EctoLibSql.handle_execute("ATTACH DATABASE 'shard_1.db' AS shard_1", [], [], state)
+
+      # Runs `SELECT name FROM "shard_1".users WHERE id = ?`
+      sql = "SELECT name FROM users WHERE id = ?"
+      {:ok, result} = EctoLibSql.Native.query_attached(state, "shard_1", sql, [1])
+
+  """
+  @spec query_attached(EctoLibSql.State.t(), String.t(), String.t(), list() | map()) ::
+          {:ok, EctoLibSql.Result.t()} | {:error, term()}
+  def query_attached(%EctoLibSql.State{conn_id: conn_id}, db_alias, sql, args \\ [])
+      when is_binary(db_alias) and is_binary(sql) do
+    with args when is_list(args) <- normalise_arguments(conn_id, sql, args),
+         %{"columns" => columns, "rows" => rows, "num_rows" => num_rows} <-
+           query_on_database(conn_id, db_alias, sql, encode_parameters(args)) do
+      command = detect_command(sql)
+
+      {columns, rows} =
+        if command in [:insert, :update, :delete] and columns == [] and rows == [] do
+          {nil, nil}
+        else
+          {columns, rows}
+        end
+
+      {:ok,
+       %EctoLibSql.Result{command: command, columns: columns, rows: rows, num_rows: num_rows}}
+    end
+  end
+
+  @doc """
+  Estimate the memory held by a cursor's buffered rows.
+
+  Cursors buffer their whole result set when declared, so a cursor over a large
+  query can hold a lot of memory until it is closed. The estimate sums the size of
+  every buffered value, including rows already fetched.
+
+  ## Parameters
+    - state: The connection state that declared the cursor
+    - cursor_id: The cursor ID
+
+  ## Returns
+    - `{:ok, bytes}` - Estimated size of the cursor's buffer in bytes
+    - `{:error, reason}` - Cursor not found or owned by another connection
+
+  ## Example
+
+      {:ok, bytes} = EctoLibSql.Native.get_cursor_memory(state, cursor_id)
+
+  """
+  @spec get_cursor_memory(EctoLibSql.State.t(), String.t()) ::
+          {:ok, non_neg_integer()} | {:error, term()}
+  def get_cursor_memory(%EctoLibSql.State{conn_id: conn_id} = _state, cursor_id) do
+    case cursor_memory(conn_id, cursor_id) do
+      bytes when is_integer(bytes) -> {:ok, bytes}
+      {:error, reason} -> {:error, reason}
+    end
+  end
+
+  @doc """
+  Atomically increment a counter column and return its new value.
+
+  Runs a single `INSERT ... ON CONFLICT DO UPDATE ... RETURNING`, so concurrent
+  increments never lose updates. If no row has `key` yet, one is created with the
+  counter at `delta`; a NULL counter is treated as zero. Table and column names are
+  quoted internally.
+
+  `key_column` must be the table's primary key or have a unique constraint, as the
+  upsert conflicts on it.
+
+  ## Parameters
+    - state: The connection state
+    - table: Table name (atom or string)
+    - key_column: Column identifying the counter (atom or string)
+    - key: Key of the counter row
+    - value_column: Integer counter column (atom or string)
+    - delta: Amount to add, may be negative (default: 1)
+
+  ## Returns
+    - `{:ok, value}` - The counter's new value
+    - `{:error, reason}` - Unknown table or column, `key_column` not unique, or
+      the counter holds a non-integer value
+
+  ## Example
+
+      {:ok, 1} = EctoLibSql.Native.increment_counter(state, :page_views, :path, "/", :hits)
+      {:ok, 11} = EctoLibSql.Native.increment_counter(state, :page_views, :path, "/", :hits, 10)
+
+  """
+  @spec increment_counter(
+          EctoLibSql.State.t(),
+          atom() | String.t(),
+          atom() | String.t(),
+          term(),
+          atom() | String.t(),
+          integer()
+        ) :: {:ok, integer()} | {:error, term()}
+  def increment_counter(
+        %EctoLibSql.State{conn_id: conn_id},
+        table,
+        key_column,
+        key,
+        value_column,
+        delta \\ 1
+      )
+      when is_integer(delta) do
+    case increment(
+           conn_id,
+           to_string(table),
+           to_string(key_column),
+           key,
+           to_string(value_column),
+           delta
+         ) do
+      value when is_integer(value) -> {:ok, value}
+      {:error, reason} -> {:error, reason}
+    end
+  end
+
+  @doc """
+  Count the rows of every user table.
+
+  Gives a quick overview of a database in one call. Virtual tables, their shadow
+  tables and SQLite's internal `sqlite_*` tables are excluded.
+
+  Exact counts run `SELECT count(*)` against each table, which can be slow on large
+  databases. With `approximate: true`, the estimates written by `ANALYZE` to
+  `sqlite_stat1` are used instead; tables without statistics are still counted
+  exactly. Estimates reflect the table as of the last `ANALYZE`.
+
+  ## Parameters
+    - state: The connection state
+    - opts: Options
+      - `:approximate` - Use `sqlite_stat1` estimates when available (default: `false`)
+
+  ## Returns
+    - `{:ok, counts}` - Map of table name to row count
+    - `{:error, reason}` - Query failure
+
+  ## Example
+
+      {:ok, %{"users" => 3, "posts" => 12}} = EctoLibSql.Native.get_table_row_counts(state)
+
+  """
+  @spec get_table_row_counts(EctoLibSql.State.t(), keyword()) ::
+          {:ok, %{String.t() => non_neg_integer()}} | {:error, term()}
+  def get_table_row_counts(%EctoLibSql.State{conn_id: conn_id}, opts \\ []) do
+    case table_row_counts(conn_id, Keyword.get(opts, :approximate, false)) do
+      counts when is_map(counts) -> {:ok, counts}
+      {:error, reason} -> {:error, reason}
+    end
+  end
+
+  @doc """
+  Set SQLite's soft heap limit.
+
+  When SQLite's heap grows past the limit it frees cached pages to get back under it,
+  which keeps the native layer from growing without bound under heavy result sets.
+  It is a soft limit: allocations never fail because of it. Pass `0` to remove the
+  limit.
+
+  **This is process-global.** The limit belongs to the SQLite library, not to a
+  connection, so it applies to every connection in the BEAM node. Set it once at
+  application start rather than per connection.
+
+  ## Parameters
+    - bytes: New soft heap limit in bytes, or `0` for no limit
+
+  ## Returns
+    - `:ok` - Limit set
+    - `{:error, reason}` - The limit could not be applied
+
+  ## Example
+
+      :ok = EctoLibSql.Native.put_soft_heap_limit(64 * 1024 * 1024)
+
+  """
+  @spec put_soft_heap_limit(non_neg_integer()) :: :ok | {:error, term()}
+  def put_soft_heap_limit(bytes) when is_integer(bytes) and bytes >= 0 do
+    set_soft_heap_limit(bytes)
+  end
+
+  @doc """
+  Get SQLite's process-global soft heap limit.
+
+  See `put_soft_heap_limit/1`.
+
+  ## Returns
+    - `{:ok, bytes}` - The current limit in bytes, `0` meaning no limit
+    - `{:error, reason}` - The limit could not be read
+
+  """
+  @spec soft_heap_limit() :: {:ok, non_neg_integer()} | {:error, term()}
+  def soft_heap_limit do
+    case get_soft_heap_limit() do
+      bytes when is_integer(bytes) -> {:ok, bytes}
+      {:error, reason} -> {:error, reason}
+    end
+  end
+
+  @doc """
+  Report SQLite's process-wide memory use and its high-water mark.
+
+  **NOT SUPPORTED** - The figures come from SQLite's `sqlite3_memory_used` and
+  `sqlite3_memory_highwater`, which libsql does not expose. No pragma reports them,
+  and the `sqlite_memstat` virtual table isn't compiled into libsql's SQLite. Reading
+  them directly would require unsafe FFI, which this library doesn't use.
+
+  ## Alternatives
+
+  To relate native memory growth to SQLite, consider:
+
+  1. **OS-level measurement** - Compare the BEAM's resident memory with
+     `:erlang.memory(:total)`. The difference is native memory, SQLite's included.
+
+  2. **Bounded caches** - Cap SQLite's memory with `put_soft_heap_limit/1` and each
+     connection's `PRAGMA cache_size`, rather than measuring it.
+
+  ## Returns
+    - `{:error, :unsupported}` - Always returns unsupported
+
+  """
+  @spec sqlite_memory_stats() :: {:error, :unsupported}
+  def sqlite_memory_stats do
+    memory_stats()
+  end
+
+  @doc """
+  Report SQLite's engine-wide status counters, each as `%{current, highwater}`.
+
+  **NOT SUPPORTED** - The counters (memory used, page cache used and overflow, and
+  the like) come from SQLite's `sqlite3_status64`, which libsql does not expose and
+  no pragma reports. Reading them directly would require unsafe FFI, which this
+  library doesn't use.
+
+  ## Alternatives
+
+  For engine diagnostics, consider:
+
+  1. **Per-connection pragmas** - `PRAGMA cache_size`, `page_count` and
+     `freelist_count` describe each connection's cache and database file.
+
+  2. **Storage statistics** - Query the `dbstat` virtual table, which libsql's SQLite
+     includes, for page usage per table and index.
+
+  ## Returns
+    - `{:error, :unsupported}` - Always returns unsupported
+
+  """
+  @spec global_status() :: {:error, :unsupported}
+  def global_status do
+    status_counters()
+  end
+
+  @doc """
+  Check whether a prepared statement still matches the database schema.
+
+  Each statement records SQLite's `schema_version` when it is prepared. Any DDL,
+  from this or another connection, bumps the version and the statement is then
+  reported as stale: its column metadata may be out of date and running it may fail
+  with "schema has changed". Use `revalidate_stmt/2` to refresh stale statements.
+
+  Remote connections send the SQL to the server to prepare on every run, so their
+  statements are never stale and always report `{:ok, true}`.
+
+  ## Parameters
+    - state: The connection state
+    - stmt_id: The statement ID from `prepare/2`
+
+  ## Returns
+    - `{:ok, true}` - The schema is unchanged since the statement was prepared
+    - `{:ok, false}` - The schema has changed
+    - `{:error, reason}` - Statement not found or owned by another connection
+
+  ## Example
+
+      {:ok, stmt_id} = EctoLibSql.Native.prepare(state, "SELECT * FROM users")
+      {:ok, _, _, state} = EctoLibSql.handle_execute("ALTER TABLE users ADD COLUMN age INTEGER", [], [], state)
+      {:ok, false} = EctoLibSql.Native.stmt_valid(state, stmt_id)
+
+  """
+  @spec stmt_valid(EctoLibSql.State.t(), String.t()) :: {:ok, boolean()} | {:error, term()}
+  def stmt_valid(%EctoLibSql.State{conn_id: conn_id} = _state, stmt_id) do
+    case statement_valid(conn_id, stmt_id) do
+      valid when is_boolean(valid) -> {:ok, valid}
+      {:error, reason} -> {:error, reason}
+    end
+  end
+
+  @doc """
+  Re-prepare a statement if the schema has changed since it was prepared.
+
+  The statement is rebuilt from its original SQL and keeps its ID, so code holding
+  the ID carries on transparently. Statements that are still valid are left alone.
+
+  ## Parameters
+    - state: The connection state
+    - stmt_id: The statement ID from `prepare/2`
+
+  ## Returns
+    - `{:ok, :valid}` - The statement was already current
+    - `{:ok, :reprepared}` - The statement was stale and has been re-prepared
+    - `{:error, reason}` - Statement not found, owned by another connection, or its
+      SQL no longer prepares against the new schema (the old statement is kept)
+
+  ## Example
+
+      {:ok, _} = EctoLibSql.Native.revalidate_stmt(state, stmt_id)
+      {:ok, result} = EctoLibSql.Native.query_stmt(state, stmt_id, [])
+
+  """
+  @spec revalidate_stmt(EctoLibSql.State.t(), String.t()) ::
+          {:ok, :valid | :reprepared} | {:error, term()}
+  def revalidate_stmt(%EctoLibSql.State{conn_id: conn_id} = state, stmt_id) do
+    with {:ok, false} <- stmt_valid(state, stmt_id),
+         :ok <- reprepare_statement(conn_id, stmt_id) do
+      {:ok, :reprepared}
+    else
+      {:ok, true} -> {:ok, :valid}
+      {:error, reason} -> {:error, reason}
+    end
+  end
+
+  @doc """
+  Run a table migration with foreign key enforcement suspended.
+
+  Implements SQLite's recommended procedure for changes `ALTER TABLE` can't make,
+  such as changing a column's type by recreating the table:
+
+    1. Turn `foreign_keys` off
+    2. `BEGIN`, then run `statements` in order
+    3. Run `PRAGMA foreign_key_check`, rolling back if any reference is broken
+    4. `COMMIT`
+    5. Restore the previous `foreign_keys` setting
+
+  The setting is restored whether the migration succeeds or fails. As `foreign_keys`
+  cannot change inside a transaction, this must not be called within one.
+
+  ## Parameters
+    - state: The connection state (not in a transaction)
+    - statements: SQL statements to run, in order
+
+  ## Returns
+    - `:ok` - The migration was committed
+    - `{:error, {:foreign_key_violations, violations}}` - Broken references were found
+      and the migration was rolled back. Each violation is a map with `:table`,
+      `:rowid`, `:parent` and `:fkid` keys, as reported by `PRAGMA foreign_key_check`
+    - `{:error, reason}` - A statement failed (rolled back) or a transaction was open
+
+  ## Example
+
+      :ok =
+        EctoLibSql.Native.run_table_migration(state, [
+          "CREATE TABLE books_new (id INTEGER PRIMARY KEY, author_id INTEGER REFERENCES authors, price REAL)",
+          "INSERT INTO books_new SELECT id, author_id, CAST(price AS REAL) FROM books",
+          "DROP TABLE books",
+          "ALTER TABLE books_new RENAME TO books"
+        ])
+
+  """
+  @spec run_table_migration(EctoLibSql.State.t(), [String.t()]) :: :ok | {:error, term()}
+  def run_table_migration(%EctoLibSql.State{conn_id: conn_id}, statements)
+      when is_list(statements) do
+    case migrate_table(conn_id, statements) do
+      :ok ->
+        :ok
+
+      {:error, {:foreign_key_violations, violations}} ->
+        violations =
+          Enum.map(violations, fn {table, rowid, parent, fkid} ->
+            %{table: table, rowid: rowid, parent: parent, fkid: fkid}
+          end)
+
+        {:error, {:foreign_key_violations, violations}}
+
+      {:error, reason} ->
+        {:error, reason}
+    end
+  end
+
+  @doc """
+  Run a query with each argument bound to an explicit parameter index.
+
+  Each argument is a `{index, value}` tuple naming the 1-based SQLite parameter it
+  binds to, so arguments can be listed in any order. This suits generated SQL where
+  argument order differs from placeholder order, statements that reuse `?N`
+  placeholders, and sparse bindings. Parameters left unbound are NULL.
+
+  ## Parameters
+    - state: The connection state
+    - sql: The query to run
+    - indexed_args: List of `{index, value}` tuples
+
+  ## Returns
+    - `{:ok, %EctoLibSql.Result{}}` - The query ran
+    - `{:error, reason}` - An index is outside the statement's parameters or given
+      twice, or the query failed
+
+  ## Example
+
+      {:ok, result} =
+        EctoLibSql.Native.query_indexed(state, "SELECT ?2, ?1", [{2, "b"}, {1, "a"}])
+
+      result.rows
+      # => [["b", "a"]]
+
+  """
+  @spec query_indexed(EctoLibSql.State.t(), String.t(), [{pos_integer(), term()}]) ::
+          {:ok, EctoLibSql.Result.t()} | {:error, term()}
+  def query_indexed(%EctoLibSql.State{conn_id: conn_id}, sql, indexed_args)
+      when is_binary(sql) and is_list(indexed_args) do
+    encoded = Enum.map(indexed_args, fn {index, value} -> {index, encode_param(value)} end)
+
+    case query_args_indexed(conn_id, sql, encoded) do
+      %{"columns" => columns, "rows" => rows, "num_rows" => num_rows} ->
+        command = detect_command(sql)
+
+        {columns, rows} =
+          if command in [:insert, :update, :delete] and columns == [] and rows == [] do
+            {nil, nil}
+          else
+            {columns, rows}
+          end
+
+        {:ok,
+         %EctoLibSql.Result{command: command, columns: columns, rows: rows, num_rows: num_rows}}
+
+      {:error, reason} ->
+        {:error, reason}
+    end
+  end
+
+  @doc """
+  Start recording which tables are written on a connection.
+
+  A lightweight, pull-based alternative to update hook subscriptions, suited to cache
+  invalidation: an update hook inside the NIF collects the names of tables whose rows
+  are inserted, updated or deleted, and `dirty_tables/1` returns and clears them.
+  Only table names are kept, so the set stays small however many rows change.
+  Tables outside the main schema are reported as `"schema.table"`.
+
+  Writes are recorded as they happen, so a rolled-back transaction still marks its
+  tables dirty, which is safe for invalidation. Like every SQLite update hook, it
+  misses writes to `WITHOUT ROWID` tables, rows removed by `REPLACE` conflict
+  resolution, and `DELETE` statements without a `WHERE` clause.
+
+  Not available for remote connections.
+
+  ## Parameters
+    - state: The connection state
+
+  ## Returns
+    - `:ok` - Tracking is enabled (enabling again keeps the current set)
+    - `{:error, reason}` - The connection does not support update hooks
+
+  ## Example
+
+      :ok = EctoLibSql.Native.track_dirty_tables(state)
+      # ... writes ...
+      {:ok, ["posts", "users"]} = EctoLibSql.Native.dirty_tables(state)
+
+  """
+  @spec track_dirty_tables(EctoLibSql.State.t()) :: :ok | {:error, term()}
+  def track_dirty_tables(%EctoLibSql.State{conn_id: conn_id}) do
+    enable_dirty_tracking(conn_id)
+  end
+
+  @doc """
+  Return the tables written since tracking started or since the last call, and clear them.
+
+  See `track_dirty_tables/1`.
+
+  ## Parameters
+    - state: The connection state
+
+  ## Returns
+    - `{:ok, tables}` - Sorted list of table names, empty if nothing was written
+    - `{:error, reason}` - Tracking is not enabled for the connection
+
+  """
+  @spec dirty_tables(EctoLibSql.State.t()) :: {:ok, [String.t()]} | {:error, term()}
+  def dirty_tables(%EctoLibSql.State{conn_id: conn_id}) do
+    case take_dirty_tables(conn_id) do
+      tables when is_list(tables) -> {:ok, tables}
+      {:error, reason} -> {:error, reason}
+    end
+  end
+
+  @doc """
+  Run a query and annotate each result column with whether it can be NULL.
+
+  Intended for generating typed structs from query results. SQLite doesn't report
+  nullability for result columns, so it is derived from the schema of the table
+  each column comes from: a column is non-nullable when declared `NOT NULL`, when it
+  is an `INTEGER PRIMARY KEY`, or when it is part of a `WITHOUT ROWID` table's
+  primary key. Expressions, aggregates and literals have no source column and are
+  reported as `:unknown`.
+
+  Columns from the optional side of an outer join are reported from their table
+  definition, although the join itself may produce NULLs for them.
+
+  ## Parameters
+    - state: The connection state
+    - sql: The query to run
+    - args: Query parameters (list or map of named parameters)
+
+  ## Returns
+    - `{:ok, %{columns: columns, rows: rows, num_rows: count}}` - Each column is a map
+      with `:name` and `:nullable` (`true`, `false` or `:unknown`)
+    - `{:error, reason}` - The query failed
+
+  ## Example
+
+      {:ok, %{columns: columns}} =
+        EctoLibSql.Native.query_nullability(state, "SELECT id, name, upper(name) FROM users", [])
+
+      columns
+      # => [%{name: "id", nullable: false}, %{name: "name", nullable: true},
+      #     %{name: "upper(name)", nullable: :unknown}]
+
+  """
+  @spec query_nullability(EctoLibSql.State.t(), String.t(), list() | map()) ::
+          {:ok,
+           %{
+             columns: [%{name: String.t(), nullable: boolean() | :unknown}],
+             rows: [list()],
+             num_rows: non_neg_integer()
+           }}
+          | {:error, term()}
+  def query_nullability(%EctoLibSql.State{conn_id: conn_id}, sql, args \\ [])
+      when is_binary(sql) do
+    with args when is_list(args) <- normalise_arguments(conn_id, sql, args),
+         %{"columns" => names, "nullable" => nullable, "rows" => rows, "num_rows" => num_rows} <-
+           query_with_nullability(conn_id, sql, encode_parameters(args)) do
+      columns =
+        Enum.zip_with(names, nullable, fn name, nullable -> %{name: name, nullable: nullable} end)
+
+      {:ok, %{columns: columns, rows: rows, num_rows: num_rows}}
+    end
+  end
+
+  @doc """
+  Run an update only if a precheck query returns the expected value.
+
+  The precheck is a scalar query; the first column of its first row (`nil` when it
+  returns no rows) is compared with `expected`. The precheck and update run in a
+  single `BEGIN IMMEDIATE` transaction, or a savepoint when one is already open, so the
+  checked value cannot change in between. Integers and floats compare numerically.
+
+  Useful for optimistic concurrency, e.g. bumping a row only if its version column
+  still holds the version the caller read.
+
+  ## Parameters
+    - state: The connection state
+    - precheck_sql: Scalar query to compare
+    - precheck_args: Precheck parameters
+    - expected: Value the precheck must return
+    - update_sql: Statement to run when the precheck matches
+    - update_args: Update parameters
+
+  ## Returns
+    - `{:ok, affected}` - The precheck matched and the update ran
+    - `{:error, :precondition_failed, actual}` - The precheck returned `actual`; nothing
+      was changed
+    - `{:error, reason}` - Either statement failed
+
+  ## Example
+
+      EctoLibSql.Native.compare_and_update(
+        state,
+        "SELECT version FROM docs WHERE id = ?",
+        [id],
+        3,
+        "UPDATE docs SET body = ?, version = version + 1 WHERE id = ?",
+        [body, id]
+      )
+      # => {:ok, 1}, or {:error, :precondition_failed, 4} if someone else got there first
+
+  """
+  @spec compare_and_update(
+          EctoLibSql.State.t(),
+          String.t(),
+          list() | map(),
+          term(),
+          String.t(),
+          list() | map()
+        ) ::
+          {:ok, non_neg_integer()} | {:error, :precondition_failed, term()} | {:error, term()}
+  def compare_and_update(
+        %EctoLibSql.State{conn_id: conn_id},
+        precheck_sql,
+        precheck_args,
+        expected,
+        update_sql,
+        update_args
+      )
+      when is_binary(precheck_sql) and is_binary(update_sql) do
+    with precheck_args when is_list(precheck_args) <-
+           normalise_arguments(conn_id, precheck_sql, precheck_args),
+         update_args when is_list(update_args) <-
+           normalise_arguments(conn_id, update_sql, update_args) do
+      compare_and_execute(
+        conn_id,
+        precheck_sql,
+        encode_parameters(precheck_args),
+        encode_param(expected),
+        update_sql,
+        encode_parameters(update_args)
+      )
+    end
+  end
+
+  @doc """
+  Execute a query and insert each row into an ETS table, keyed by one of its columns.
+
+  Rows are built as tuples natively (one element per column, in column order) and
+  inserted with a single `:ets.insert/2`, skipping the intermediate row lists that
+  `query/3` builds. Erlang has no NIF API for writing to ETS, so the insert itself
+  happens in the calling process.
+
+  ## ETS table requirements
+
+  - The calling process must be allowed to write to the table: it must be `:public`,
+    or owned by the caller.
+  - Rows are keyed by the table's `keypos`, which must be `key_index + 1` (ETS positions
+    are 1-based, column indexes 0-based). The default `keypos: 1` keys by the first
+    column.
+  - In a `:set` table, rows with the same key overwrite each other; use a `:bag` or
+    `:duplicate_bag` table to keep them all.
+
+  ## Parameters
+    - state: The connection state
+    - sql: The query to run
+    - args: Query parameters (list or map of named parameters)
+    - tid: ETS table identifier or name
+    - key_index: 0-based index of the key column
+
+  ## Returns
+    - `{:ok, count}` - Number of rows inserted
+    - `{:error, reason}` - The query failed, or the table's `keypos` doesn't match
+      `key_index`, or a row has too few columns
+
+  ## Example
+
+      tid = :ets.new(:users, [:set, :public, keypos: 1])
+      {:ok, 1000} = EctoLibSql.Native.query_to_ets(state, "SELECT id, name FROM users", [], tid, 0)
+      :ets.lookup(tid, 42)
+      # => [{42, "Ada"}]
+
+  """
+  @spec query_to_ets(
+          EctoLibSql.State.t(),
+          String.t(),
+          list() | map(),
+          :ets.table(),
+          non_neg_integer()
+        ) :: {:ok, non_neg_integer()} | {:error, term()}
+  def query_to_ets(%EctoLibSql.State{conn_id: conn_id}, sql, args, tid, key_index)
+      when is_binary(sql) and is_integer(key_index) and key_index >= 0 do
+    case :ets.info(tid, :keypos) do
+      :undefined ->
+        {:error, "ETS table does not exist"}
+
+      keypos when keypos != key_index + 1 ->
+        {:error, "ETS table keypos #{keypos} does not match key_index #{key_index}"}
+
+      _ ->
+        with args when is_list(args) <- normalise_arguments(conn_id, sql, args),
+             tuples when is_list(tuples) <- query_tuples(conn_id, sql, encode_parameters(args)) do
+          insert_ets_tuples(tid, tuples, key_index)
+        end
+    end
+  end
+
+  defp insert_ets_tuples(_tid, [], _key_index), do: {:ok, 0}
+
+  defp insert_ets_tuples(_tid, [first | _], key_index) when tuple_size(first) <= key_index do
+    {:error, "key_index #{key_index} is out of range for #{tuple_size(first)} columns"}
+  end
+
+  defp insert_ets_tuples(tid, tuples, _key_index) do
+    true = :ets.insert(tid, tuples)
+    {:ok, length(tuples)}
+  end
+
+  @doc """
+  Check whether the database write lock could be taken right now.
+
+  A non-blocking probe for diagnosing write contention on local databases. It tries
+  `BEGIN IMMEDIATE` with the busy timeout temporarily set to zero and rolls straight
+  back if that succeeded, so it never waits and never leaves a transaction open. The
+  connection's busy timeout is restored afterwards.
+
+  The answer is only a snapshot: another writer may take the lock straight after.
+
+  ## Parameters
+    - state: The connection state (must not be inside a transaction)
+
+  ## Returns
+    - `{:ok, true}` - The write lock is free
+    - `{:ok, false}` - Another connection holds the write lock
+    - `{:error, :unsupported}` - For remote connections
+    - `{:error, reason}` - Inside a transaction, or the probe failed
+
+  ## Example
+
+      {:ok, available?} = EctoLibSql.Native.write_lock_available(state)
+
+  """
+  @spec write_lock_available(EctoLibSql.State.t()) :: {:ok, boolean()} | {:error, term()}
+  def write_lock_available(%EctoLibSql.State{conn_id: conn_id}) do
+    case can_acquire_write_lock(conn_id) do
+      available when is_boolean(available) -> {:ok, available}
+      {:error, reason} -> {:error, reason}
+    end
+  end
+
+  @doc """
+  Report the threading mode of the SQLite library behind a local connection.
+
+  A read-only diagnostic. `:serialized` means SQLite guards each connection with its
+  own mutex; `:multi_thread` means a connection must never be used by two threads at
+  once; `:single_thread` disables SQLite's mutexes entirely. EctoLibSql always uses
+  connections under its own mutexes, so every mode is safe here, but the mode shows
+  whether that external locking is strictly necessary.
+
+  The mode is read from SQLite's compile options (`THREADSAFE=N`).
+
+  ## Parameters
+    - state: The connection state
+
+  ## Returns
+    - `{:ok, :serialized | :multi_thread | :single_thread}`
+    - `{:error, :unsupported}` - For remote connections
+    - `{:error, reason}` - The mode could not be determined
+
+  ## Example
+
+      {:ok, :serialized} = EctoLibSql.Native.threading_mode(state)
+
+  """
+  @spec threading_mode(EctoLibSql.State.t()) ::
+          {:ok, :serialized | :multi_thread | :single_thread} | {:error, term()}
+  def threading_mode(%EctoLibSql.State{conn_id: conn_id}) do
+    case connection_threading_mode(conn_id) do
+      mode when mode in [:serialized, :multi_thread, :single_thread] -> {:ok, mode}
+      {:error, reason} -> {:error, reason}
+    end
+  end
+
+  @doc """
+  Execute a statement in the current transaction and return its rowid.
+
+  Runs a statement that doesn't return rows, such as an `INSERT`, within the state's
+  transaction, and reads `last_insert_rowid` from the transaction's own connection in
+  the same call. The rowid reflects the insert before the transaction commits, with
+  no separate query that could run elsewhere.
+
+  ## Parameters
+    - state: The connection state, inside a transaction
+    - sql: The statement to execute
+    - args: Statement parameters (list or map of named parameters)
+
+  ## Returns
+    - `{:ok, {affected, last_rowid}}` - Rows changed, and the last inserted rowid
+    - `{:error, reason}` - Not in a transaction, or the statement failed
+
+  ## Example
+
+      {:ok, state} = EctoLibSql.Native.begin(state)
+      {:ok, {1, id}} =
+        EctoLibSql.Native.execute_in_trx_with_rowid(state, "INSERT INTO users (name) VALUES (?)", ["Ada"])
+
+  """
+  @spec execute_in_trx_with_rowid(EctoLibSql.State.t(), String.t(), list() | map()) ::
+          {:ok, {non_neg_integer(), integer()}} | {:error, term()}
+  def execute_in_trx_with_rowid(%EctoLibSql.State{trx_id: nil}, _sql, _args) do
+    {:error, "No active transaction"}
+  end
+
+  def execute_in_trx_with_rowid(
+        %EctoLibSql.State{conn_id: conn_id, trx_id: trx_id},
+        sql,
+        args
+      )
+      when is_binary(sql) do
+    with args when is_list(args) <- normalise_arguments(conn_id, sql, args),
+         {affected, last_rowid} <-
+           execute_with_transaction(trx_id, conn_id, sql, encode_parameters(args), true) do
+      {:ok, {affected, last_rowid}}
+    end
+  end
+
+  @doc """
+  Validate connection options before passing them to `EctoLibSql.connect/1`.
+
+  `connect/1` only looks up the keys it knows, so a typo such as `auth_tokne` otherwise
+  surfaces as a confusing authentication failure. This checks `opts` against the documented
+  connection options and the keys `mode` requires: `:database` (or `:shared_memory`) for
+  `:local`, `:uri` and `:auth_token` for `:remote`, and all three for `:remote_replica`.
+  Options set to `nil` count as absent.
+
+  `mode` defaults to the one `connect/1` would pick for `opts`. Pass only the adapter's
+  connection options - pool and repo settings such as `:pool_size` are reported as unknown.
+
+  Returns `{:ok, opts}` with `nil` values dropped and repeated keys reduced to their first
+  occurrence, `{:error, {:unknown_option, key}}` or `{:error, {:missing_option, key}}`.
+
+  ## Example
+
+      {:error, {:unknown_option, :auth_tokne}} =
+        EctoLibSql.Native.check_connect_opts(uri: "libsql://x.turso.io", auth_tokne: "t")
+
+  """
+  def check_connect_opts(opts, mode \\ nil) when is_list(opts) do
+    mode =
+      case mode || EctoLibSql.State.detect_mode(opts) do
+        :unknown -> :local
+        mode -> mode
+      end
+
+    validate_connect_opts(opts, mode)
+  end
+
+  @doc """
+  Estimate the cost of a write statement without running it.
+
+  Plans the statement with `EXPLAIN QUERY PLAN` and reports whether it will do a full
+  table scan, and of which tables. Use it before an expensive `UPDATE` or `DELETE` - in a
+  migration, say - to catch a scan of a huge table before it happens. Nothing is written.
+
+  Each scanned table comes with its estimated row count from the `sqlite_stat1`
+  statistics written by `ANALYZE`, or `nil` if the table hasn't been analysed. Tables the
+  statement refers to by an alias are reported by the alias, without an estimate.
+
+  ## Parameters
+    - state: The connection state
+    - sql: The statement to analyse
+    - args: Statement parameters (list or map of named parameters)
+
+  ## Returns
+    - `{:ok, %{full_scan: boolean(), scans: [{table, estimated_rows | nil}]}}`
+    - `{:error, reason}` - The statement could not be planned
+
+  ## Example
+
+      {:ok, %{full_scan: true, scans: [{"events", 12_000_000}]}} =
+        EctoLibSql.Native.write_cost(state, "DELETE FROM events WHERE payload LIKE ?", ["%x%"])
+
+  """
+  @spec write_cost(EctoLibSql.State.t(), String.t(), list() | map()) ::
+          {:ok, %{full_scan: boolean(), scans: [{String.t(), non_neg_integer() | nil}]}}
+          | {:error, term()}
+  def write_cost(%EctoLibSql.State{conn_id: conn_id}, sql, args \\ []) when is_binary(sql) do
+    with args when is_list(args) <- normalise_arguments(conn_id, sql, args),
+         %{} = cost <- estimate_write_cost(conn_id, sql, encode_parameters(args)) do
+      {:ok, cost}
+    end
+  end
+
+  @doc """
+  Report a remote replica's replication state in one call.
+
+  Gives a fuller picture than `get_frame_number_for_replica/1` when diagnosing sync
+  problems, combining:
+
+    - `:current_frame` - The replica's committed replication index
+    - `:max_write_frame` - The highest frame written through this connection's database
+    - `:durable_frame` - The frame the primary has confirmed as durable. libsql does not
+      expose this for embedded replicas yet, so it is always `nil`
+
+  Frame numbers are 0 when no frames have been applied or written.
+
+  ## Returns
+    - `{:ok, %{current_frame: integer, max_write_frame: integer, durable_frame: nil}}`
+    - `{:error, :not_a_replica}` - The connection is local or direct remote
+    - `{:error, reason}` - The replication index could not be read
+
+  ## Example
+
+      {:ok, %{current_frame: current, max_write_frame: written}} =
+        EctoLibSql.Native.replica_diagnostics(state)
+
+      if written > current, do: Logger.warning("Replica is behind its own writes")
+
+  """
+  @spec replica_diagnostics(EctoLibSql.State.t()) ::
+          {:ok,
+           %{
+             current_frame: non_neg_integer(),
+             max_write_frame: non_neg_integer(),
+             durable_frame: non_neg_integer() | nil
+           }}
+          | {:error, term()}
+  def replica_diagnostics(%EctoLibSql.State{conn_id: conn_id}) do
+    case replication_diagnostics(conn_id) do
+      %{} = diagnostics -> {:ok, diagnostics}
+      {:error, reason} -> {:error, reason}
+    end
+  end
+
+  @doc """
+  Sync a remote replica only when it is more than `max_lag_frames` frames behind.
+
+  For cost control with Turso: rather than syncing on every operation, compares the
+  replica's replication index with the highest frame written through this connection's
+  database (see `replica_diagnostics/1`) and only makes the sync round trip when the gap
+  exceeds `max_lag_frames`. Pass `0` to sync whenever the replica trails its own writes.
+
+  The primary's position can't be read without syncing, so writes made by other
+  clients since the last sync don't count as lag. Pair this with a periodic
+  `sync/1` if those need to show up too.
+
+  ## Parameters
+    - state: The connection state
+    - max_lag_frames: Frames the replica may trail by before it is synced
+
+  ## Returns
+    - `{:synced, frame}` - The replica was synced; `frame` is its new replication index
+    - `:up_to_date` - Within `max_lag_frames`, so no sync was made
+    - `{:error, :not_a_replica}` - The connection is local or direct remote
+    - `{:error, reason}` - The sync failed or timed out
+
+  ## Example
+
+      case EctoLibSql.Native.sync_replica_if_behind(state, 100) do
+        {:synced, frame} -> Logger.debug("Replica synced to frame \#{frame}")
+        :up_to_date -> :ok
+      end
+
+  """
+  @spec sync_replica_if_behind(EctoLibSql.State.t(), non_neg_integer()) ::
+          {:synced, non_neg_integer()} | :up_to_date | {:error, term()}
+  def sync_replica_if_behind(%EctoLibSql.State{conn_id: conn_id}, max_lag_frames)
+      when is_integer(max_lag_frames) and max_lag_frames >= 0 do
+    sync_if_behind(conn_id, max_lag_frames)
+  end
+
+  @doc """
+  Pause automatic sync on a remote replica connection, for example during a bulk import.
+
+  **NOT SUPPORTED** - ecto_libsql never syncs automatically after a write; `sync/1`
+  only runs when called. The per-write sync on an embedded replica is libsql's own
+  read-your-writes catch-up, which is fixed when the database is opened and cannot be
+  switched off for a connection afterwards, so there is nothing for a pause to
+  suppress.
+
+  ## Alternatives
+
+  To keep a bulk import from syncing once per write, consider:
+
+  1. **One transaction** - Run the import inside a single transaction, so the writes
+     reach the primary as one unit and are caught up once on commit.
+
+  2. **Batches** - Send the statements with `batch_transactional/2`, which runs them in
+     one call and one transaction.
+
+  Call `sync/1` afterwards if the replica must reflect changes made elsewhere.
+
+  ## Returns
+    - `{:error, :unsupported}` - Always returns unsupported
+
+  """
+  def pause_replica_sync(%EctoLibSql.State{conn_id: conn_id} = _state) do
+    pause_sync(conn_id)
+  end
+
+  @doc """
+  Resume automatic sync on a remote replica connection after `pause_replica_sync/1`.
+
+  **NOT SUPPORTED** - Automatic sync cannot be paused (see `pause_replica_sync/1`), so
+  there is nothing to resume.
+
+  ## Returns
+    - `{:error, :unsupported}` - Always returns unsupported
+
+  """
+  def resume_replica_sync(%EctoLibSql.State{conn_id: conn_id} = _state) do
+    resume_sync(conn_id)
+  end
+
+  @doc """
+  Start a blob builder for uploading a large blob in chunks.
+
+  Sending a very large binary to a query passes it across the NIF boundary in one go. A
+  blob builder instead accumulates chunks in native memory - from a streamed upload, say -
+  with `append_blob_chunk/2`, and `insert_built_blob/3` binds the assembled bytes to a
+  single statement.
+
+  The bytes all builders hold between them are capped at SQLite's default maximum blob
+  length (1,000,000,000 bytes). Discard builders you don't insert with
+  `discard_blob_builder/1` to release their memory. A builder that goes ten minutes
+  without an append is discarded automatically, so an upload abandoned by a crashed
+  process doesn't hold its bytes forever.
+
+  ## Returns
+    - `{:ok, builder}` - A `{:blob_builder, id}` reference, usable as a statement parameter
+
+  ## Example
+
+      {:ok, builder} = EctoLibSql.Native.new_blob_builder()
+
+      Enum.each(File.stream!("upload.bin", [], 1_048_576), fn chunk ->
+        {:ok, _size} = EctoLibSql.Native.append_blob_chunk(builder, chunk)
+      end)
+
+      {:ok, 1} =
+        EctoLibSql.Native.insert_built_blob(
+          state,
+          "INSERT INTO files (name, data) VALUES (?, ?)",
+          ["upload.bin", builder]
+        )
+
+  """
+  @spec new_blob_builder() :: {:ok, {:blob_builder, String.t()}}
+  def new_blob_builder do
+    {:ok, {:blob_builder, blob_builder_new()}}
+  end
+
+  @doc """
+  Append a chunk of bytes to a blob builder from `new_blob_builder/0`.
+
+  ## Returns
+    - `{:ok, size}` - The builder's size in bytes after appending
+    - `{:error, reason}` - Unknown or expired builder, or the builders' size cap would be
+      exceeded
+  """
+  @spec append_blob_chunk({:blob_builder, String.t()}, binary()) ::
+          {:ok, non_neg_integer()} | {:error, term()}
+  def append_blob_chunk({:blob_builder, id}, chunk) when is_binary(chunk) do
+    case blob_builder_append(id, chunk) do
+      size when is_integer(size) -> {:ok, size}
+      {:error, reason} -> {:error, reason}
+    end
+  end
+
+  @doc """
+  Drop a blob builder and the bytes it holds without inserting them.
+
+  Discarding an unknown or already inserted builder is not an error.
+  """
+  @spec discard_blob_builder({:blob_builder, String.t()}) :: :ok
+  def discard_blob_builder({:blob_builder, id}), do: blob_builder_discard(id)
+
+  @doc """
+  Execute a statement with blob builders bound as parameters.
+
+  Any parameter given as a builder from `new_blob_builder/0` is bound as the bytes it
+  holds; other parameters are bound as usual. The builders are consumed whether or not
+  the statement succeeds, so the bytes are never held twice. Each builder can therefore
+  be bound only once per statement; binding it twice is an error and consumes nothing.
+
+  ## Parameters
+    - state: The connection state
+    - sql: The statement to execute, usually an `INSERT`
+    - args: Statement parameters (list or map of named parameters)
+
+  ## Returns
+    - `{:ok, affected}` - Number of rows affected
+    - `{:error, reason}` - Unknown or repeated builder, or the statement failed
+  """
+  @spec insert_built_blob(EctoLibSql.State.t(), String.t(), list() | map()) ::
+          {:ok, non_neg_integer()} | {:error, term()}
+  def insert_built_blob(%EctoLibSql.State{conn_id: conn_id}, sql, args)
+      when is_binary(sql) do
+    with args when is_list(args) <- normalise_arguments(conn_id, sql, args),
+         affected when is_integer(affected) <-
+           insert_blob(conn_id, sql, encode_parameters(args)) do
+      {:ok, affected}
+    end
+  end
+
+  @doc """
+  Run several read queries against one point-in-time snapshot of the database.
+
+  All the queries share a single read transaction, so their results are consistent with
+  each other even while other connections write - useful for exporting several related
+  tables without mixing rows from before and after a concurrent write. In WAL mode
+  writers carry on and their changes are not seen; in rollback-journal mode they wait for
+  the read to finish. Called inside a transaction, the queries run in it instead.
+
+  ## Parameters
+    - state: The connection state
+    - queries: List of `{name, sql, args}` tuples. `name` can be any term and keys the
+      result; `args` is a list or map of named parameters
+
+  ## Returns
+    - `{:ok, %{name => %EctoLibSql.Result{}}}` - One result per query
+    - `{:error, reason}` - A query failed; no results are returned
+
+  ## Example
+
+      {:ok, %{users: users, orders: orders}} =
+        EctoLibSql.Native.snapshot_read(state, [
+          {:users, "SELECT * FROM users", []},
+          {:orders, "SELECT * FROM orders WHERE placed_at >= ?", [since]}
+        ])
+
+  """
+  @spec snapshot_read(EctoLibSql.State.t(), [{term(), String.t(), list() | map()}]) ::
+          {:ok, %{term() => EctoLibSql.Result.t()}} | {:error, term()}
+  def snapshot_read(%EctoLibSql.State{conn_id: conn_id}, queries) when is_list(queries) do
+    with {:ok, queries} <- normalise_snapshot_queries(conn_id, queries),
+         %{} = results <- consistent_read(conn_id, queries) do
+      {:ok,
+       Map.new(results, fn {name, %{"columns" => columns, "rows" => rows, "num_rows" => n}} ->
+         {name, %EctoLibSql.Result{columns: columns, rows: rows, num_rows: n}}
+       end)}
+    end
+  end
+
+  defp normalise_snapshot_queries(conn_id, queries) do
+    Enum.reduce_while(queries, {:ok, []}, fn {name, sql, args}, {:ok, acc} ->
+      case normalise_arguments(conn_id, sql, args) do
+        args when is_list(args) -> {:cont, {:ok, [{name, sql, encode_parameters(args)} | acc]}}
+        {:error, _} = error -> {:halt, error}
+      end
+    end)
+    |> case do
+      {:ok, acc} -> {:ok, Enum.reverse(acc)}
+      error -> error
+    end
+  end
+
+  @doc """
+  Enable or disable SQLite's defensive mode.
+
+  **NOT SUPPORTED** - Defensive mode (`SQLITE_DBCONFIG_DEFENSIVE`) blocks writes to
+  virtual table shadow tables and other operations that can corrupt a database, and is
+  switched with `sqlite3_db_config`. libsql only exposes that call for loading
+  extensions, and making it directly would require unsafe FFI, which this library
+  doesn't use.
+
+  ## Alternatives
+
+  To harden a connection that runs untrusted SQL, consider:
+
+  1. **Read-only connections** - Use `query_multiple/2`, or switch on `PRAGMA query_only`,
+     so that shadow tables can't be written along with everything else.
+
+  2. **Statement allow-lists** - Reject untrusted SQL that names a virtual table's
+     shadow tables (for FTS5, `<table>_data`, `<table>_idx`, `<table>_content` and so on).
+
+  3. **Separate databases** - Keep FTS indexes and other virtual tables in a database
+     that untrusted connections never open or attach.
+
+  ## Returns
+    - `{:error, :unsupported}` - Always returns unsupported
+
+  """
+  def set_defensive_mode(%EctoLibSql.State{conn_id: conn_id} = _state, enabled)
+      when is_boolean(enabled) do
+    set_defensive(conn_id, enabled)
+  end
+
+  @doc """
+  Report whether SQLite's defensive mode is on.
+
+  **NOT SUPPORTED** - Reading the setting needs the same `sqlite3_db_config` call as
+  `set_defensive_mode/2`, which libsql does not expose.
+
+  ## Returns
+    - `{:error, :unsupported}` - Always returns unsupported
+
+  """
+  def defensive_mode(%EctoLibSql.State{conn_id: conn_id} = _state) do
+    get_defensive(conn_id)
+  end
+
+  @doc """
+  Set the maximum number of databases the connection may `ATTACH`.
+
+  **NOT SUPPORTED** - The limit is adjusted with SQLite's `sqlite3_limit`, which libsql
+  does not expose, and calling it directly would require unsafe FFI, which this library
+  doesn't use. The API could only lower the limit anyway: libsql's bundled SQLite is
+  compiled with the default maximum of 10 attached databases.
+
+  `limit` must be between 0 and 125, SQLite's hard maximum.
+
+  ## Alternatives
+
+  For shard fan-out queries, consider:
+
+  1. **Detach when done** - Attach shards only for the queries that need them and
+     `DETACH` them afterwards, keeping at most 10 attached at once.
+
+  2. **Fan out across connections** - Query groups of shards over separate connections
+     and merge the results.
+
+  ## Returns
+    - `{:error, :unsupported}` - Always returns unsupported
+
+  """
+  def set_max_attached(%EctoLibSql.State{conn_id: conn_id} = _state, limit)
+      when is_integer(limit) and limit >= 0 and limit <= 125 do
+    set_attached_limit(conn_id, limit)
+  end
+
+  @doc """
+  Get the maximum number of databases the connection may `ATTACH`.
+
+  **NOT SUPPORTED** - Reading the limit needs the same `sqlite3_limit` call as
+  `set_max_attached/2`, which libsql does not expose. The compiled-in limit is 10.
+
+  ## Returns
+    - `{:error, :unsupported}` - Always returns unsupported
+
+  """
+  def max_attached(%EctoLibSql.State{conn_id: conn_id} = _state) do
+    get_attached_limit(conn_id)
+  end
+
+  @doc """
+  Set the maximum length, in bytes, of an SQL statement the connection will run.
+
+  **NOT SUPPORTED** - The limit is adjusted with SQLite's `sqlite3_limit`, which libsql
+  does not expose, and calling it directly would require unsafe FFI, which this library
+  doesn't use. Statements are bounded only by the compiled-in maximum of 1,000,000,000
+  bytes.
+
+  `bytes` must be between 1 and 1,000,000,000, SQLite's hard maximum.
+
+  ## Alternatives
+
+  To guard connections that run generated or user-influenced SQL, consider:
+
+  1. **Check before running** - Reject statements whose `byte_size/1` is over your
+     limit before passing them to the connection.
+
+  2. **Bind values** - Pass user-influenced data as query parameters rather than
+     building it into the SQL, which keeps statements short and avoids injection.
+
+  ## Returns
+    - `{:error, :unsupported}` - Always returns unsupported
+
+  """
+  def set_max_sql_length(%EctoLibSql.State{conn_id: conn_id} = _state, bytes)
+      when is_integer(bytes) and bytes >= 1 and bytes <= 1_000_000_000 do
+    set_sql_length_limit(conn_id, bytes)
+  end
+
+  @doc """
+  Get the maximum length, in bytes, of an SQL statement the connection will run.
+
+  **NOT SUPPORTED** - Reading the limit needs the same `sqlite3_limit` call as
+  `set_max_sql_length/2`, which libsql does not expose. The compiled-in limit is
+  1,000,000,000 bytes.
+
+  ## Returns
+    - `{:error, :unsupported}` - Always returns unsupported
+
+  """
+  def max_sql_length(%EctoLibSql.State{conn_id: conn_id} = _state) do
+    get_sql_length_limit(conn_id)
+  end
+
+  @doc """
+  Configure the connection's lookaside memory allocator.
+
+  **NOT SUPPORTED** - Lookaside is configured with SQLite's
+  `sqlite3_db_config(SQLITE_DBCONFIG_LOOKASIDE, ...)`, which libsql does not expose,
+  and calling it directly would require unsafe FFI, which this library doesn't use.
+  SQLite would also only accept the change before the connection prepares its first
+  statement, while no lookaside memory is in use.
+
+  `slot_size` and `slot_count` must be non-negative integers.
+
+  ## Alternatives
+
+  For allocation-heavy workloads, consider:
+
+  1. **Default lookaside** - Every connection already gets SQLite's default 48 KB
+     lookaside buffer, which suits most workloads.
+
+  2. **Fewer allocations** - Reuse prepared statements and batch small writes, which
+     cuts allocation churn more than lookaside tuning does.
+
+  ## Returns
+    - `{:error, :unsupported}` - Always returns unsupported
+
+  """
+  @spec set_lookaside(EctoLibSql.State.t(), non_neg_integer(), non_neg_integer()) ::
+          {:error, :unsupported}
+  def set_lookaside(%EctoLibSql.State{conn_id: conn_id} = _state, slot_size, slot_count)
+      when is_integer(slot_size) and slot_size >= 0 and is_integer(slot_count) and
+             slot_count >= 0 do
+    configure_lookaside(conn_id, slot_size, slot_count)
+  end
+
+  @doc """
+  Fingerprint a row's values, for deduplicating rows in data pipelines.
+
+  Runs a query returning a single row and hashes its values in column order into 16 hex
+  digits. Each value is hashed with its type, so `NULL`, `0`, `''` and an empty blob
+  all fingerprint differently, and `1` differs from `1.0`. Identical rows always
+  fingerprint equal, across connections and restarts. Rows after the first are ignored.
+
+  The fingerprint is a 64-bit hash: rows that differ almost always fingerprint
+  differently, but collisions are possible, so confirm matches before discarding data.
+
+  ## Parameters
+    - state: The connection state
+    - sql: Query returning the row to fingerprint
+    - args: Query parameters (list or map of named parameters)
+
+  ## Returns
+    - `{:ok, fingerprint}` - The row's fingerprint
+    - `{:ok, nil}` - The query returned no rows
+    - `{:error, reason}` - The query failed
+
+  ## Example
+
+      {:ok, fingerprint} =
+        EctoLibSql.Native.fingerprint_row(
+          state,
+          "SELECT kind, payload FROM events WHERE id = ?",
+          [42]
+        )
+
+  """
+  @spec fingerprint_row(EctoLibSql.State.t(), String.t(), list() | map()) ::
+          {:ok, String.t() | nil} | {:error, term()}
+  def fingerprint_row(%EctoLibSql.State{conn_id: conn_id}, sql, args \\ [])
+      when is_binary(sql) do
+    with args when is_list(args) <- normalise_arguments(conn_id, sql, args),
+         fingerprint when is_binary(fingerprint) or is_nil(fingerprint) <-
+           row_fingerprint(conn_id, sql, encode_parameters(args)) do
+      {:ok, fingerprint}
+    end
+  end
+
+  @doc """
+  Time a query over repeated runs, for quick performance checks during development.
+
+  The query runs back to back in native code, so BEAM scheduling doesn't skew the
+  timings. It runs once untimed to warm the cache, then `iterations` more times. Each
+  run steps through every row and discards it, so results are never accumulated.
+
+  Statements that write take effect on every run, so benchmark writes inside a
+  transaction you roll back, or against a scratch database. The connection is held for
+  all the runs, so `iterations` is capped at 10,000.
+
+  ## Parameters
+    - state: The connection state
+    - sql: The query to time
+    - args: Query parameters (list or map of named parameters)
+    - iterations: Number of timed runs, at most 10,000 (default 100)
+
+  ## Returns
+    - `{:ok, %{min_us: integer, max_us: integer, mean_us: integer, p95_us: integer}}` -
+      Run times in microseconds, `p95_us` being the nearest-rank 95th percentile
+    - `{:error, reason}` - `iterations` was over the cap, or a run failed
+
+  ## Example
+
+      {:ok, %{mean_us: mean, p95_us: p95}} =
+        EctoLibSql.Native.benchmark(state, "SELECT * FROM users WHERE email = ?", ["a@b.c"])
+
+  """
+  @spec benchmark(EctoLibSql.State.t(), String.t(), list() | map(), pos_integer()) ::
+          {:ok,
+           %{
+             min_us: non_neg_integer(),
+             max_us: non_neg_integer(),
+             mean_us: non_neg_integer(),
+             p95_us: non_neg_integer()
+           }}
+          | {:error, term()}
+  def benchmark(%EctoLibSql.State{conn_id: conn_id}, sql, args \\ [], iterations \\ 100)
+      when is_binary(sql) and is_integer(iterations) and iterations > 0 do
+    with args when is_list(args) <- normalise_arguments(conn_id, sql, args),
+         %{} = stats <- benchmark_query(conn_id, sql, encode_parameters(args), iterations) do
+      {:ok, stats}
+    end
+  end
+
+  @doc """
+  Report whether a column is indexed, and by which indexes.
+
+  Reads `PRAGMA index_list` and `PRAGMA index_info` for the table. A column counts as
+  indexed when some index leads with it, so that a `WHERE` clause on the column alone
+  can use the index, or when it is the table's rowid alias (an `INTEGER PRIMARY KEY`),
+  which the table itself is keyed by. Query builders can use this to warn about filters
+  that will scan the whole table.
+
+  ## Parameters
+    - state: The connection state
+    - table: The table name
+    - column: The column name
+
+  ## Returns
+    - `{:ok, %{indexed: boolean, indexes: [{index_name, position}]}}` - `indexes` lists
+      every index including the column, with the column's zero-based position in it;
+      position `0` means the index leads with the column
+    - `{:error, reason}` - Unknown table or column, or the query failed
+
+  ## Example
+
+      {:ok, %{indexed: false, indexes: [{"orders_customer_status", 1}]}} =
+        EctoLibSql.Native.column_indexed(state, "orders", "status")
+
+  """
+  @spec column_indexed(EctoLibSql.State.t(), String.t(), String.t()) ::
+          {:ok, %{indexed: boolean(), indexes: [{String.t(), non_neg_integer()}]}}
+          | {:error, term()}
+  def column_indexed(%EctoLibSql.State{conn_id: conn_id} = _state, table, column)
+      when is_binary(table) and is_binary(column) do
+    case is_column_indexed(conn_id, table, column) do
+      {indexed, indexes} when is_boolean(indexed) ->
+        {:ok, %{indexed: indexed, indexes: indexes}}
+
+      {:error, reason} ->
+        {:error, reason}
+    end
+  end
+
+  @doc """
+  Execute statements atomically, retrying the whole transaction on write conflicts.
+
+  The statements run in one transaction started with `BEGIN IMMEDIATE`. If it hits a
+  busy or locked error at any point - starting, running a statement or committing -
+  it is rolled back and the whole sequence runs again, after a backoff that starts at
+  10ms and doubles each time (up to one second), at most `max_retries` more times.
+  Errors other than conflicts fail at once.
+
+  An Elixir closure can't be re-run from native code, so this takes the statements
+  themselves. Only use it for sequences that are safe to run more than once, such as
+  upserts or writes of absolute values. It must be called outside a transaction.
+
+  ## Parameters
+    - state: The connection state
+    - statements: A list of `{sql, args}` tuples
+    - max_retries: Retries after the first attempt (default 3)
+
+  ## Returns
+    - `{:ok, %{counts: counts, attempts: attempts}}` - Committed; `counts` holds the
+      affected row count of each statement
+    - `{:error, {:retries_exhausted, attempts, message}}` - Every attempt conflicted
+      with another writer, and nothing was committed
+    - `{:error, reason}` - A statement failed, and nothing was committed
+
+  ## Example
+
+      {:ok, %{counts: [1, 1]}} =
+        EctoLibSql.Native.atomic_with_retry(state, [
+          {"INSERT OR REPLACE INTO balances (id, amount) VALUES (?, ?)", [1, 100]},
+          {"INSERT OR REPLACE INTO balances (id, amount) VALUES (?, ?)", [2, 50]}
+        ])
+
+  """
+  @spec atomic_with_retry(EctoLibSql.State.t(), list({String.t(), list()}), non_neg_integer()) ::
+          {:ok, %{counts: [non_neg_integer()], attempts: pos_integer()}}
+          | {:error, {:retries_exhausted, pos_integer(), String.t()}}
+          | {:error, term()}
+  def atomic_with_retry(%EctoLibSql.State{conn_id: conn_id}, statements, max_retries \\ 3)
+      when is_list(statements) and is_integer(max_retries) and max_retries >= 0 do
+    case atomic_statements_retry(conn_id, statements, max_retries) do
+      {counts, attempts} when is_list(counts) -> {:ok, %{counts: counts, attempts: attempts}}
+      {:error, reason} -> {:error, reason}
+    end
+  end
+
+  @doc """
+  Run a query and return its rows as newline-delimited JSON (NDJSON).
+
+  The output is built in native code, so no Elixir maps are created and encoded
+  again. This suits log ingestion and other export pipelines. Each row becomes one
+  JSON object mapping column name to value, followed by a newline. Column names
+  follow the connection's `:column_naming` option.
+
+  Values are encoded as follows:
+    - NULL becomes `null`
+    - Integers and reals become JSON numbers (NaN and infinities become `null`)
+    - Text becomes a JSON string
+    - Blobs become base64-encoded strings (standard alphabet, padded)
+
+  The whole result is returned as one binary, so page through very large tables.
+
+  ## Parameters
+    - state: The connection state
+    - sql: The query to run
+    - args: Query parameters (list or map of named parameters)
+
+  ## Returns
+    - `{:ok, ndjson}` - One line per row, or `""` if there are no rows
+    - `{:error, reason}` - The query failed
+
+  ## Example
+
+      {:ok, ndjson} =
+        EctoLibSql.Native.query_ndjson(state, "SELECT id, name FROM users WHERE id > ?", [100])
+
+      # ~s({"id":101,"name":"Alice"}\n{"id":102,"name":"Bob"}\n)
+
+  """
+  @spec query_ndjson(EctoLibSql.State.t(), String.t(), list() | map()) ::
+          {:ok, binary()} | {:error, term()}
+  def query_ndjson(%EctoLibSql.State{conn_id: conn_id}, sql, args \\ [])
+      when is_binary(sql) do
+    with args when is_list(args) <- normalise_arguments(conn_id, sql, args),
+         ndjson when is_binary(ndjson) <-
+           query_to_ndjson(conn_id, sql, encode_parameters(args)) do
+      {:ok, ndjson}
+    end
+  end
+
+  @doc """
+  Find the duplicate keys that would make creating a unique index fail.
+
+  `CREATE UNIQUE INDEX` fails on the first duplicate without saying which rows clash.
+  This groups the table by `columns` and reports every key shared by more than one
+  row, so a migration can clean them up before creating the index. Composite keys
+  are supported. Rows with a NULL in any of the columns are skipped, as SQLite
+  treats NULLs as distinct in unique indexes. Keys compare using each column's
+  collation, as the index would.
+
+  ## Parameters
+    - state: The connection state
+    - table: Table the index would be created on
+    - columns: Column names the index would cover, in order
+
+  ## Returns
+    - `{:ok, duplicates}` - A list of `%{key: values, count: count}` maps, most
+      duplicated first, where `values` follows the order of `columns`. An empty list
+      means the index can be created
+    - `{:error, reason}` - No columns were given, or the table or a column does not exist
+
+  ## Example
+
+      {:ok, [%{key: [1, "a@example.com"], count: 3}]} =
+        EctoLibSql.Native.unique_violations(state, "users", ["org_id", "email"])
+
+  """
+  @spec unique_violations(EctoLibSql.State.t(), String.t(), [String.t()]) ::
+          {:ok, [%{key: list(), count: pos_integer()}]} | {:error, term()}
+  def unique_violations(%EctoLibSql.State{conn_id: conn_id}, table, columns)
+      when is_binary(table) and is_list(columns) do
+    case find_unique_violations(conn_id, table, columns) do
+      duplicates when is_list(duplicates) ->
+        {:ok, Enum.map(duplicates, fn {key, count} -> %{key: key, count: count} end)}
+
+      {:error, _} = error ->
+        error
+    end
+  end
+
+  @doc """
+  Suggest indexes that could avoid full table scans in a query.
+
+  Meant as a development-time advisor. The query is planned with
+  `EXPLAIN QUERY PLAN` but not executed. For each table the plan reads with a full
+  scan, the columns the query compares in `WHERE` and `ON` clauses become a
+  suggested index: equality comparisons (`=`, `IN`, `IS`) first, then at most one
+  range comparison. Scans that filter on nothing get no suggestion.
+
+  These are heuristic suggestions, not recommendations: nothing is created, and
+  columns inside function calls or expressions are not considered. Weigh each one
+  against its write and storage cost, and confirm it is used with `used_index/3`.
+
+  ## Parameters
+    - state: The connection state
+    - sql: The query to analyse
+    - args: Query parameters (list or map of named parameters)
+
+  ## Returns
+    - `{:ok, statements}` - Suggested `CREATE INDEX` statements, possibly empty
+    - `{:error, reason}` - The query could not be planned
+
+  ## Example
+
+      {:ok, [suggestion]} =
+        EctoLibSql.Native.index_suggestions(
+          state,
+          "SELECT * FROM orders WHERE customer_id = ?",
+          [1]
+        )
+
+      # suggestion == ~s(CREATE INDEX "orders_customer_id_index" ON "orders" ("customer_id"))
+
+  """
+  @spec index_suggestions(EctoLibSql.State.t(), String.t(), list() | map()) ::
+          {:ok, [String.t()]} | {:error, term()}
+  def index_suggestions(%EctoLibSql.State{conn_id: conn_id}, sql, args \\ [])
+      when is_binary(sql) do
+    with args when is_list(args) <- normalise_arguments(conn_id, sql, args),
+         suggestions when is_list(suggestions) <-
+           suggest_indexes(conn_id, sql, encode_parameters(args)) do
+      {:ok, suggestions}
+    end
+  end
+
+  @doc """
+  Apply a changeset produced by SQLite's session extension.
+
+  **NOT SUPPORTED** - Changesets are applied with SQLite's `sqlite3changeset_apply`.
+  libsql's SQLite includes the session extension, but libsql does not expose its
+  functions, and calling them directly would require unsafe FFI, which this library
+  doesn't use. Returns `{:error, :session_not_available}` so sync code can detect
+  this and fall back.
+
+  `policy` must be `:abort`, `:replace` or `:omit`.
+
+  ## Alternatives
+
+  For offline-first sync, consider:
+
+  1. **Replay SQL** - Record changes as SQL statements (for example with
+     `stream_table_as_sql/4`, or a change log table filled by triggers) and replay them
+     with `execute_batch_sql/2` inside a transaction.
+
+  2. **Embedded replicas** - Connect as a remote replica and call `sync/1`, which ships
+     changes through libsql's own replication protocol.
+
+  ## Returns
+    - `{:error, :session_not_available}` - Always returned
+
+  """
+  @spec apply_changeset(EctoLibSql.State.t(), binary(), :abort | :replace | :omit) ::
+          {:error, :session_not_available}
+  def apply_changeset(%EctoLibSql.State{conn_id: conn_id} = _state, changeset, policy \\ :abort)
+      when is_binary(changeset) and policy in [:abort, :replace, :omit] do
+    changeset_apply(conn_id, changeset, policy)
+  end
+
+  @doc """
+  Record the changes made by a list of statements as a session extension changeset.
+
+  **NOT SUPPORTED** - Recording needs SQLite's `sqlite3session_*` functions, which
+  libsql does not expose, and calling them directly would require unsafe FFI, which
+  this library doesn't use. Returns `{:error, :session_not_available}` without
+  running the statements, so a fallback can run them without applying them twice.
+
+  ## Alternatives
+
+  To capture changes for offline sync, consider:
+
+  1. **Change log table** - Have triggers record inserts, updates and deletes in a log
+     table, and read and clear it in the same transaction as the statements.
+
+  2. **Embedded replicas** - Write through a remote replica connection, whose changes
+     libsql ships with its own replication protocol on `sync/1`.
+
+  ## Returns
+    - `{:error, :session_not_available}` - Always returned
+
+  """
+  @spec capture_changeset(EctoLibSql.State.t(), [String.t()]) ::
+          {:error, :session_not_available}
+  def capture_changeset(%EctoLibSql.State{conn_id: conn_id} = _state, statements)
+      when is_list(statements) do
+    changeset_capture(conn_id, statements)
+  end
+
+  @doc """
+  Run a query and return its rows as one compact binary frame.
+
+  Wide result sets are expensive to hand back as nested terms. This returns the column
+  names and a single binary holding every value, which is cheap to pass through an RPC
+  layer. Decode it with `decode_frame/2` only when the values are needed.
+
+  ## Frame format
+
+  The frame is every row's values in column order, one after another, with no header
+  or row separator. The row count is the number of values divided by the number of
+  columns. Each value is a one-byte type tag followed by its data, all big-endian:
+
+    - `0` - NULL, with no data
+    - `1` - Integer: 64-bit signed integer
+    - `2` - Real: 64-bit IEEE 754 float
+    - `3` - Text: 32-bit unsigned length, then that many bytes of UTF-8
+    - `4` - Blob: 32-bit unsigned length, then that many bytes
+
+  ## Parameters
+    - state: The connection state
+    - sql: The query to run
+    - args: Query parameters (list or map of named parameters)
+
+  ## Returns
+    - `{:ok, {columns, frame}}` - Column names and the binary frame
+    - `{:error, reason}` - The query failed
+
+  ## Example
+
+      {:ok, {columns, frame}} =
+        EctoLibSql.Native.query_frame(state, "SELECT id, name FROM users WHERE id > ?", [100])
+
+      rows = EctoLibSql.Native.decode_frame(columns, frame)
+
+  """
+  @spec query_frame(EctoLibSql.State.t(), String.t(), list() | map()) ::
+          {:ok, {[String.t()], binary()}} | {:error, term()}
+  def query_frame(%EctoLibSql.State{conn_id: conn_id}, sql, args \\ [])
+      when is_binary(sql) do
+    with args when is_list(args) <- normalise_arguments(conn_id, sql, args),
+         {columns, frame} when is_list(columns) and is_binary(frame) <-
+           query_binary(conn_id, sql, encode_parameters(args)) do
+      {:ok, {columns, frame}}
+    end
+  end
+
+  @doc """
+  Decode a binary frame from `query_frame/3` into rows.
+
+  Rows come back as lists of values, as in `EctoLibSql.Result` rows: integers, floats,
+  `nil`, and binaries for both text and blobs. Text and blob values are sub-binaries
+  of `frame`, so they keep it in memory while they are referenced.
+
+  ## Parameters
+    - columns: The column names returned with the frame
+    - frame: The binary frame
+
+  ## Example
+
+      {:ok, {columns, frame}} = EctoLibSql.Native.query_frame(state, "SELECT id, name FROM users")
+      [[1, "Alice"], [2, "Bob"]] = EctoLibSql.Native.decode_frame(columns, frame)
+
+  """
+  @spec decode_frame([String.t()], binary()) :: [list()]
+  def decode_frame([], frame) when is_binary(frame), do: []
+
+  def decode_frame(columns, frame) when is_list(columns) and is_binary(frame) do
+    frame
+    |> decode_frame_values([])
+    |> Enum.chunk_every(length(columns))
+  end
+
+  defp decode_frame_values(<<>>, acc), do: Enum.reverse(acc)
+  defp decode_frame_values(<<0, rest::binary>>, acc), do: decode_frame_values(rest, [nil | acc])
+
+  defp decode_frame_values(<<1, int::signed-64, rest::binary>>, acc),
+    do: decode_frame_values(rest, [int | acc])
+
+  defp decode_frame_values(<<2, float::float-64, rest::binary>>, acc),
+    do: decode_frame_values(rest, [float | acc])
+
+  defp decode_frame_values(<<tag, len::32, bytes::binary-size(len), rest::binary>>, acc)
+       when tag in [3, 4],
+       do: decode_frame_values(rest, [bytes | acc])
+
   # Encode parameters to handle complex Elixir types before passing to NIF.
   # The Rust NIF cannot serialize plain Elixir maps, so we convert them to JSON strings.
   @doc false
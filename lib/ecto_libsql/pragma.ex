@@ -250,6 +250,12 @@ defmodule EctoLibSql.Pragma do
   - `:full` (2) - Sync after every write (safest, slowest)
   - `:extra` (3) - Even more syncing than FULL
 
+  Lower levels trade durability for write speed. With `:normal` in WAL mode, a power
+  loss or OS crash can roll back the most recent commits but leaves the database
+  consistent; in rollback journal mode it can corrupt it. With `:off`, any OS crash or
+  power loss can corrupt the database, so keep it for data you can rebuild, such as
+  caches. Application crashes alone never lose committed data at any level.
+
   ## Parameters
 
     - state: Connection state
@@ -258,12 +264,14 @@ defmodule EctoLibSql.Pragma do
   ## Returns
 
     - `{:ok, result}` on success
-    - `{:error, reason}` on failure
+    - `{:error, reason}` on failure, or for an invalid level
 
   ## Examples
 
       {:ok, _} = EctoLibSql.Pragma.set_synchronous(state, :normal)
 
+  The level can also be set when connecting with the `:synchronous` option.
+
   ## Recommendations
 
   - Production: `:normal` or `:full` (with WAL mode, `:normal` is usually sufficient)
@@ -281,6 +289,11 @@ defmodule EctoLibSql.Pragma do
     query(state, "PRAGMA synchronous = #{level}")
   end
 
+  def set_synchronous(%State{}, level) do
+    {:error,
+     "synchronous must be one of :off, :normal, :full, :extra or 0-3, got: #{inspect(level)}"}
+  end
+
   @doc """
   Query the current synchronous setting.
 
@@ -303,6 +316,320 @@ defmodule EctoLibSql.Pragma do
     query(state, "PRAGMA synchronous")
   end
 
+  @doc """
+  Set the maximum size of memory-mapped I/O.
+
+  Memory-mapped I/O lets SQLite read pages directly from the OS page cache
+  instead of copying them, which can significantly speed up reads on large,
+  read-heavy local databases. A size of `0` disables it.
+
+  ## Parameters
+
+    - state: Connection state
+    - bytes: Maximum number of bytes to map (non-negative integer)
+
+  ## Returns
+
+    - `{:ok, result}` where result.rows contains the effective size
+    - `{:error, reason}` if the size is invalid or the PRAGMA fails
+
+  ## Examples
+
+      {:ok, result} = EctoLibSql.Pragma.set_mmap_size(state, 268_435_456)
+      # result.rows => [[268435456]]
+
+  ## Platform Caveats
+
+  The effective size is capped by SQLite's compile-time `SQLITE_MAX_MMAP_SIZE`,
+  and is `0` on builds or platforms where memory-mapped I/O is disabled, so check
+  the returned value rather than assuming the request was honoured. Memory-mapped
+  I/O only applies to local database files, not remote connections.
+
+  """
+  def set_mmap_size(%State{} = state, bytes) when is_integer(bytes) and bytes >= 0 do
+    query(state, "PRAGMA mmap_size = #{bytes}")
+  end
+
+  def set_mmap_size(%State{}, bytes) do
+    {:error, "mmap_size must be a non-negative integer, got: #{inspect(bytes)}"}
+  end
+
+  @doc """
+  Query the current memory-mapped I/O size.
+
+  ## Parameters
+
+    - state: Connection state
+
+  ## Returns
+
+    - `{:ok, result}` where result.rows contains the size in bytes
+    - `{:error, reason}` on failure
+
+  ## Examples
+
+      {:ok, result} = EctoLibSql.Pragma.mmap_size(state)
+      # result.rows => [[0]] when memory-mapped I/O is disabled
+
+  """
+  def mmap_size(%State{} = state) do
+    query(state, "PRAGMA mmap_size")
+  end
+
+  @doc """
+  Set how many WAL pages trigger an automatic checkpoint.
+
+  In WAL mode SQLite copies the write-ahead log back into the database once it
+  grows past this many pages (1000 by default). Write-heavy workloads may raise it
+  to checkpoint less often; `0` disables automatic checkpoints, leaving them to be
+  run manually with `PRAGMA wal_checkpoint`. The setting is per connection.
+
+  ## Parameters
+
+    - state: Connection state
+    - pages: Threshold in pages (non-negative integer)
+
+  ## Returns
+
+    - `:ok` on success
+    - `{:error, reason}` if `pages` is negative or the PRAGMA fails
+
+  ## Examples
+
+      :ok = EctoLibSql.Pragma.set_wal_autocheckpoint(state, 4000)
+
+  """
+  @spec set_wal_autocheckpoint(State.t(), non_neg_integer()) :: :ok | {:error, term()}
+  def set_wal_autocheckpoint(%State{conn_id: conn_id}, pages) when is_integer(pages) do
+    Native.set_wal_autocheckpoint(conn_id, pages)
+  end
+
+  def set_wal_autocheckpoint(%State{}, pages) do
+    {:error, "wal_autocheckpoint must be a non-negative integer, got: #{inspect(pages)}"}
+  end
+
+  @doc """
+  Query the WAL auto-checkpoint threshold.
+
+  ## Parameters
+
+    - state: Connection state
+
+  ## Returns
+
+    - `{:ok, pages}` - `0` means automatic checkpoints are disabled
+    - `{:error, reason}` on failure
+
+  ## Examples
+
+      {:ok, 1000} = EctoLibSql.Pragma.wal_autocheckpoint(state)
+
+  """
+  @spec wal_autocheckpoint(State.t()) :: {:ok, non_neg_integer()} | {:error, term()}
+  def wal_autocheckpoint(%State{conn_id: conn_id}) do
+    case Native.get_wal_autocheckpoint(conn_id) do
+      pages when is_integer(pages) -> {:ok, pages}
+      {:error, reason} -> {:error, reason}
+    end
+  end
+
+  @doc """
+  Control whether dirty pages spill to disk before a transaction commits.
+
+  A transaction's changes are held in the page cache until it commits. With spilling
+  on (SQLite's default), once more pages are cached than the spill threshold SQLite
+  writes dirty pages to the database file early, and takes an exclusive lock for the
+  rest of the transaction. With spilling off, a long or large transaction keeps every
+  page it changes in memory instead, growing the cache past `cache_size` if it must.
+
+  On memory-constrained nodes, keep spilling on (optionally with a lower threshold) so
+  large transactions touch disk rather than hold memory. Turn it off only when
+  transactions are small or memory is plentiful, for example to avoid taking the
+  exclusive lock early while readers are active.
+
+  The threshold applies to the `main` database; switching spilling on or off covers
+  the whole connection.
+
+  ## Parameters
+
+    - state: Connection state
+    - setting: `true` or `false`, or a threshold in pages (`0` switches spilling off)
+
+  ## Returns
+
+    - `:ok` on success
+    - `{:error, reason}` if the setting is invalid or the PRAGMA fails
+
+  ## Examples
+
+      :ok = EctoLibSql.Pragma.set_cache_spill(state, false)
+      :ok = EctoLibSql.Pragma.set_cache_spill(state, 5000)
+
+  """
+  @spec set_cache_spill(State.t(), boolean() | non_neg_integer()) :: :ok | {:error, term()}
+  def set_cache_spill(%State{conn_id: conn_id}, setting)
+      when is_boolean(setting) or (is_integer(setting) and setting >= 0) do
+    Native.set_cache_spill(conn_id, setting)
+  end
+
+  def set_cache_spill(%State{}, setting) do
+    {:error,
+     "cache_spill must be a boolean or a non-negative page count, got: #{inspect(setting)}"}
+  end
+
+  @doc """
+  Query the cache spill setting.
+
+  ## Parameters
+
+    - state: Connection state
+
+  ## Returns
+
+    - `{:ok, 0}` - Spilling is off
+    - `{:ok, pages}` - Pages cached before spilling starts; SQLite reports the larger of
+      the spill threshold and the cache size
+    - `{:error, reason}` on failure
+
+  ## Examples
+
+      {:ok, pages} = EctoLibSql.Pragma.cache_spill(state)
+
+  """
+  @spec cache_spill(State.t()) :: {:ok, non_neg_integer()} | {:error, term()}
+  def cache_spill(%State{conn_id: conn_id}) do
+    case Native.get_cache_spill(conn_id) do
+      pages when is_integer(pages) -> {:ok, pages}
+      {:error, reason} -> {:error, reason}
+    end
+  end
+
+  @doc """
+  Set secure delete behaviour.
+
+  Controls whether SQLite overwrites deleted content so it can't be recovered
+  from the database file:
+  - `:off` (0) - Deleted content is left in place (SQLite's default, fastest)
+  - `:on` (1) - Deleted content is overwritten with zeros
+  - `:fast` (2) - Overwrites deleted content only when it doesn't add I/O
+
+  ## Parameters
+
+    - state: Connection state
+    - mode: One of `:on`, `:off`, `:fast`
+
+  ## Returns
+
+    - `{:ok, result}` where result.rows contains the new setting (0-2)
+    - `{:error, reason}` if the mode is invalid or the PRAGMA fails
+
+  ## Examples
+
+      {:ok, _} = EctoLibSql.Pragma.set_secure_delete(state, :on)
+
+  ## Performance
+
+  `:on` increases write I/O, since every freed page and cell is zeroed, and can
+  noticeably slow down deletes and updates on large tables. `:fast` gives most of
+  the protection without extra I/O, but can leave some deleted content on free
+  pages. The setting is per-connection.
+
+  """
+  def set_secure_delete(%State{} = state, mode) when mode in [:on, :off, :fast] do
+    mode_str = mode |> Atom.to_string() |> String.upcase()
+    query(state, "PRAGMA secure_delete = #{mode_str}")
+  end
+
+  def set_secure_delete(%State{}, mode) do
+    {:error, "secure_delete must be one of :on, :off or :fast, got: #{inspect(mode)}"}
+  end
+
+  @doc """
+  Query the current secure delete setting.
+
+  ## Parameters
+
+    - state: Connection state
+
+  ## Returns
+
+    - `{:ok, result}` where result.rows contains the current setting
+      (0 = OFF, 1 = ON, 2 = FAST)
+    - `{:error, reason}` on failure
+
+  ## Examples
+
+      {:ok, result} = EctoLibSql.Pragma.secure_delete(state)
+      # result.rows => [[1]] when ON
+
+  """
+  def secure_delete(%State{} = state) do
+    query(state, "PRAGMA secure_delete")
+  end
+
+  @doc """
+  Set where temporary tables and indices are stored.
+
+  Large `ORDER BY`, `GROUP BY` and `DISTINCT` queries, as well as temporary tables,
+  spill to temporary storage. This controls whether that storage is:
+  - `:default` (0) - Use the compile-time default (usually a file)
+  - `:file` (1) - Temporary files on disk
+  - `:memory` (2) - Memory
+
+  ## Parameters
+
+    - state: Connection state
+    - mode: One of `:default`, `:file`, `:memory`
+
+  ## Returns
+
+    - `{:ok, result}` where result.rows contains the new setting (0-2)
+    - `{:error, reason}` if the mode is invalid or the PRAGMA fails
+
+  ## Examples
+
+      {:ok, _} = EctoLibSql.Pragma.set_temp_store(state, :memory)
+
+  ## Choosing a mode
+
+  `:memory` is faster on machines with RAM to spare, but a large sort can use as
+  much memory as the data being sorted. In memory-constrained environments,
+  `:file` keeps big sorts on disk and avoids running out of memory. The setting
+  is per-connection.
+
+  """
+  def set_temp_store(%State{} = state, mode) when mode in [:default, :file, :memory] do
+    mode_str = mode |> Atom.to_string() |> String.upcase()
+    query(state, "PRAGMA temp_store = #{mode_str}")
+  end
+
+  def set_temp_store(%State{}, mode) do
+    {:error, "temp_store must be one of :default, :file or :memory, got: #{inspect(mode)}"}
+  end
+
+  @doc """
+  Query the current temporary storage location.
+
+  ## Parameters
+
+    - state: Connection state
+
+  ## Returns
+
+    - `{:ok, result}` where result.rows contains the current setting
+      (0 = DEFAULT, 1 = FILE, 2 = MEMORY)
+    - `{:error, reason}` on failure
+
+  ## Examples
+
+      {:ok, result} = EctoLibSql.Pragma.temp_store(state)
+      # result.rows => [[2]] when MEMORY
+
+  """
+  def temp_store(%State{} = state) do
+    query(state, "PRAGMA temp_store")
+  end
+
   @doc """
   Get information about a table's columns.
 
@@ -418,4 +745,68 @@ defmodule EctoLibSql.Pragma do
   def set_user_version(%State{} = state, version) when is_integer(version) do
     query(state, "PRAGMA user_version = #{version}")
   end
+
+  @doc """
+  Get the schema version (schema cookie).
+
+  SQLite increments this counter on every schema change (`CREATE`, `ALTER`,
+  `DROP`, ...), by any connection. Comparing it against a previously seen value
+  is the canonical way to detect DDL changes, e.g. to invalidate cached prepared
+  statements, without polling `sqlite_master`.
+
+  Unlike `user_version/1`, this value is maintained by SQLite; it should be read,
+  never written.
+
+  ## Parameters
+
+    - state: Connection state
+
+  ## Returns
+
+    - `{:ok, result}` where result.rows contains the schema version
+    - `{:error, reason}` on failure
+
+  ## Examples
+
+      {:ok, %{rows: [[version]]}} = EctoLibSql.Pragma.schema_version(state)
+
+  """
+  def schema_version(%State{} = state) do
+    query(state, "PRAGMA schema_version")
+  end
+
+  @doc """
+  Snapshot the connection's effective configuration.
+
+  Reads `journal_mode`, `synchronous`, `foreign_keys`, `busy_timeout`, `cache_size`,
+  `page_size`, `temp_store`, `mmap_size` and `wal_autocheckpoint` in one call, as a
+  single diagnostic dump of how a connection is actually configured. Useful for
+  spotting configuration drift between connections.
+
+  Values are returned as SQLite reports them: `journal_mode` as a lowercase string,
+  the rest as integers (e.g. `synchronous: 1` for NORMAL).
+
+  ## Parameters
+
+    - state: Connection state
+
+  ## Returns
+
+    - `{:ok, snapshot}` - Map of pragma name to value
+    - `{:error, reason}` on failure
+
+  ## Examples
+
+      {:ok, snapshot} = EctoLibSql.Pragma.snapshot(state)
+      snapshot.journal_mode
+      # => "wal"
+
+  """
+  @spec snapshot(State.t()) :: {:ok, %{atom() => String.t() | integer() | nil}} | {:error, term()}
+  def snapshot(%State{conn_id: conn_id}) do
+    case Native.pragma_snapshot(conn_id) do
+      snapshot when is_map(snapshot) -> {:ok, snapshot}
+      {:error, reason} -> {:error, reason}
+    end
+  end
 end
@@ -11,7 +11,12 @@ defmodule EctoLibSql.Result do
   - `:command` - The type of SQL command (`:select`, `:insert`, `:update`, `:delete`, `:create`, `:begin`, `:commit`, `:rollback`, `:pragma`, `:batch`, `:unknown`, `:other`, or `nil`)
   - `:columns` - List of column names (for SELECT queries), or `nil` for write operations
   - `:rows` - List of rows, where each row is a list of values, or `nil` for write operations
-  - `:num_rows` - Number of rows affected or returned
+  - `:num_rows` - Number of rows affected or returned. For INSERT/UPDATE/DELETE without
+    RETURNING this is exactly the number of rows the statement changed, so `0` reliably
+    means a no-op - including `INSERT ... ON CONFLICT DO NOTHING` (or `INSERT OR IGNORE`)
+    hitting a conflict, which lets callers tell a skipped upsert from a successful insert.
+    `INSERT OR REPLACE` (and `REPLACE`) counts only the rows written, not the rows
+    deleted to make way for them. See `EctoLibSql.Native.conflict_action/1`
 
   ## Examples
 
@@ -11,6 +11,9 @@ defmodule EctoLibSql.State do
   - `:trx_id` - Transaction ID if a transaction is active, `nil` otherwise
   - `:mode` - Connection mode (`:local`, `:remote`, or `:remote_replica`)
   - `:sync` - Sync mode for replicas (`:enable_sync` or `:disable_sync`)
+  - `:connect_timings` - Connection phase timings in microseconds
+    (`%{build_us: _, connect_us: _, ping_us: _}`) when connected with
+    `diagnostics: true`, `nil` otherwise
 
   ## Connection Modes
 
@@ -26,12 +29,20 @@ defmodule EctoLibSql.State do
   @typedoc "Sync mode for replica connections."
   @type sync_mode :: :enable_sync | :disable_sync
 
+  @typedoc "Time spent in each phase of connecting, in microseconds (`ping_us` is `nil` for local)."
+  @type connect_timings :: %{
+          build_us: non_neg_integer(),
+          connect_us: non_neg_integer(),
+          ping_us: non_neg_integer() | nil
+        }
+
   @typedoc "Connection state struct."
   @type t :: %__MODULE__{
           conn_id: String.t(),
           trx_id: String.t() | nil,
           mode: mode() | nil,
-          sync: sync_mode() | nil
+          sync: sync_mode() | nil,
+          connect_timings: connect_timings() | nil
         }
 
   @enforce_keys [:conn_id]
@@ -40,7 +51,8 @@ defmodule EctoLibSql.State do
     :conn_id,
     :trx_id,
     :mode,
-    :sync
+    :sync,
+    :connect_timings
   ]
 
   @doc """
@@ -51,6 +63,9 @@ defmodule EctoLibSql.State do
       iex> EctoLibSql.State.detect_mode(database: "local.db")
       :local
 
+      iex> EctoLibSql.State.detect_mode(shared_memory: "test_db")
+      :local
+
       iex> EctoLibSql.State.detect_mode(uri: "libsql://...", auth_token: "...")
       :remote
 
@@ -64,11 +79,12 @@ defmodule EctoLibSql.State do
     token = Keyword.get(opts, :auth_token)
     db = Keyword.get(opts, :database)
     sync = Keyword.get(opts, :sync)
+    shared_memory = Keyword.get(opts, :shared_memory)
 
     cond do
       uri != nil and token != nil and db != nil and sync != nil -> :remote_replica
       uri != nil and token != nil -> :remote
-      db != nil -> :local
+      db != nil or shared_memory != nil -> :local
       true -> :unknown
     end
   end
@@ -0,0 +1,494 @@
+/// Arrow IPC export of query results
+///
+/// This module encodes query results in Arrow's streaming IPC format, so analytics
+/// tools (such as Explorer) can load them as columns without converting each cell to
+/// an Elixir term. The format is written directly: a small flatbuffer encoder produces
+/// the schema and record batch metadata, followed by the column buffers.
+use crate::constants::*;
+use crate::utils::{safe_lock, safe_lock_arc};
+use libsql::Value;
+use rustler::{Binary, Env, NifResult, OwnedBinary, Term};
+
+/// Arrow type of an exported column
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArrowType {
+    /// Every value is `NULL`
+    Null,
+    /// Signed 64-bit integers
+    Int64,
+    /// 64-bit floats
+    Float64,
+    /// UTF-8 strings
+    Utf8,
+    /// Variable-length bytes
+    Binary,
+}
+
+impl ArrowType {
+    /// The Arrow type matching a value's SQLite storage class.
+    pub fn of(value: &Value) -> Self {
+        match value {
+            Value::Null => ArrowType::Null,
+            Value::Integer(_) => ArrowType::Int64,
+            Value::Real(_) => ArrowType::Float64,
+            Value::Text(_) => ArrowType::Utf8,
+            Value::Blob(_) => ArrowType::Binary,
+        }
+    }
+
+    /// Id of the type in the `Type` union of Arrow's `Schema.fbs`.
+    fn union_id(self) -> u8 {
+        match self {
+            ArrowType::Null => 1,
+            ArrowType::Int64 => 2,
+            ArrowType::Float64 => 3,
+            ArrowType::Binary => 4,
+            ArrowType::Utf8 => 5,
+        }
+    }
+
+    /// Fields of the type's flatbuffer table.
+    fn type_table(self) -> Vec<Slot> {
+        match self {
+            ArrowType::Int64 => vec![Slot::I32(64), Slot::Bool(true)],
+            // Precision DOUBLE
+            ArrowType::Float64 => vec![Slot::I16(2)],
+            ArrowType::Null | ArrowType::Utf8 | ArrowType::Binary => Vec::new(),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            ArrowType::Null => "null",
+            ArrowType::Int64 => "integer",
+            ArrowType::Float64 => "real",
+            ArrowType::Utf8 => "text",
+            ArrowType::Binary => "blob",
+        }
+    }
+}
+
+/// Work out each column's Arrow type from the storage class of its first non-`NULL`
+/// value.
+///
+/// Integers in a column of reals are widened to floats. Any other mix of storage
+/// classes in one column is an error, since Arrow columns have a single type.
+pub fn column_types(columns: &[String], rows: &[Vec<Value>]) -> Result<Vec<ArrowType>, String> {
+    let mut types = vec![ArrowType::Null; columns.len()];
+    for row in rows {
+        for (i, value) in row.iter().enumerate() {
+            let value_type = ArrowType::of(value);
+            types[i] = match (types[i], value_type) {
+                (current, ArrowType::Null) => current,
+                (ArrowType::Null, found) => found,
+                (ArrowType::Int64, ArrowType::Float64) => ArrowType::Float64,
+                (ArrowType::Float64, ArrowType::Int64) => ArrowType::Float64,
+                (current, found) if current == found => current,
+                (current, found) => {
+                    return Err(format!(
+                        "Column {} mixes {} and {} values; CAST it to a single type in the query",
+                        columns[i],
+                        current.name(),
+                        found.name()
+                    ));
+                }
+            };
+        }
+    }
+    Ok(types)
+}
+
+/// Encode query results as an Arrow IPC stream: a schema message, one record batch
+/// holding every row, and the end-of-stream marker.
+pub fn encode_ipc_stream(columns: &[String], rows: &[Vec<Value>]) -> Result<Vec<u8>, String> {
+    let types = column_types(columns, rows)?;
+
+    let fields = columns
+        .iter()
+        .zip(&types)
+        .map(|(name, arrow_type)| {
+            Object::Table(vec![
+                Slot::Offset(Object::Str(name.clone())),
+                Slot::Bool(true),
+                Slot::U8(arrow_type.union_id()),
+                Slot::Offset(Object::Table(arrow_type.type_table())),
+                Slot::Absent,
+                Slot::Offset(Object::Tables(Vec::new())),
+            ])
+        })
+        .collect();
+    // Little-endian, then the fields
+    let schema = Object::Table(vec![Slot::I16(0), Slot::Offset(Object::Tables(fields))]);
+
+    let mut batch = RecordBatchBody::default();
+    for (i, arrow_type) in types.iter().enumerate() {
+        batch.push_column(*arrow_type, rows.iter().map(|row| &row[i]))?;
+    }
+    let record_batch = Object::Table(vec![
+        Slot::I64(len_i64(rows.len())),
+        Slot::Offset(Object::Structs(batch.nodes)),
+        Slot::Offset(Object::Structs(batch.buffers)),
+    ]);
+
+    let mut out = Vec::new();
+    write_message(&mut out, SCHEMA_HEADER, schema, &[]);
+    write_message(&mut out, RECORD_BATCH_HEADER, record_batch, &batch.body);
+    // End-of-stream marker
+    out.extend_from_slice(&CONTINUATION.to_le_bytes());
+    out.extend_from_slice(&0u32.to_le_bytes());
+    Ok(out)
+}
+
+/// Marks the start of each encapsulated IPC message.
+const CONTINUATION: u32 = 0xFFFF_FFFF;
+/// `MetadataVersion.V5`
+const METADATA_VERSION: i16 = 4;
+/// Ids in the `MessageHeader` union of Arrow's `Message.fbs`
+const SCHEMA_HEADER: u8 = 1;
+const RECORD_BATCH_HEADER: u8 = 3;
+
+fn len_i64(len: usize) -> i64 {
+    i64::try_from(len).unwrap_or(i64::MAX)
+}
+
+/// Append an encapsulated message: continuation marker, metadata length, the
+/// flatbuffer `Message` padded to 8 bytes, then the message body.
+fn write_message(out: &mut Vec<u8>, header_type: u8, header: Object, body: &[u8]) {
+    let message = Object::Table(vec![
+        Slot::I16(METADATA_VERSION),
+        Slot::U8(header_type),
+        Slot::Offset(header),
+        Slot::I64(len_i64(body.len())),
+    ]);
+    let metadata = FlatBufferWriter::finish(&message);
+
+    out.extend_from_slice(&CONTINUATION.to_le_bytes());
+    out.extend_from_slice(&(metadata.len() as u32).to_le_bytes());
+    out.extend_from_slice(&metadata);
+    out.extend_from_slice(body);
+}
+
+/// Column buffers and their descriptions for a single record batch
+#[derive(Default)]
+struct RecordBatchBody {
+    /// `FieldNode` structs: length and null count per column
+    nodes: Vec<u8>,
+    /// `Buffer` structs: offset and length of each buffer within `body`
+    buffers: Vec<u8>,
+    body: Vec<u8>,
+}
+
+impl RecordBatchBody {
+    fn push_column<'v>(
+        &mut self,
+        arrow_type: ArrowType,
+        values: impl ExactSizeIterator<Item = &'v Value> + Clone,
+    ) -> Result<(), String> {
+        let len = values.len();
+        let null_count = values.clone().filter(|v| matches!(v, Value::Null)).count();
+        self.nodes.extend_from_slice(&len_i64(len).to_le_bytes());
+        self.nodes
+            .extend_from_slice(&len_i64(null_count).to_le_bytes());
+
+        // Null columns have no buffers at all
+        if arrow_type == ArrowType::Null {
+            return Ok(());
+        }
+
+        // Validity bitmap, omitted when nothing is null
+        let mut validity = Vec::new();
+        if null_count > 0 {
+            validity = vec![0u8; len.div_ceil(8)];
+            for (i, value) in values.clone().enumerate() {
+                if !matches!(value, Value::Null) {
+                    validity[i / 8] |= 1 << (i % 8);
+                }
+            }
+        }
+        self.push_buffer(&validity);
+
+        match arrow_type {
+            ArrowType::Int64 | ArrowType::Float64 => {
+                let mut data = Vec::with_capacity(len * 8);
+                for value in values {
+                    let bytes = match (arrow_type, value) {
+                        (ArrowType::Int64, Value::Integer(n)) => n.to_le_bytes(),
+                        (_, Value::Real(f)) => f.to_le_bytes(),
+                        // Widened integer in a float column
+                        (_, Value::Integer(n)) => (*n as f64).to_le_bytes(),
+                        _ => [0; 8],
+                    };
+                    data.extend_from_slice(&bytes);
+                }
+                self.push_buffer(&data);
+            }
+            ArrowType::Utf8 | ArrowType::Binary => {
+                let mut offsets = Vec::with_capacity((len + 1) * 4);
+                let mut data = Vec::new();
+                offsets.extend_from_slice(&0i32.to_le_bytes());
+                for value in values {
+                    match value {
+                        Value::Text(s) => data.extend_from_slice(s.as_bytes()),
+                        Value::Blob(b) => data.extend_from_slice(b),
+                        _ => {}
+                    }
+                    let end = i32::try_from(data.len())
+                        .map_err(|_| "Column data exceeds 2GB Arrow limit".to_string())?;
+                    offsets.extend_from_slice(&end.to_le_bytes());
+                }
+                self.push_buffer(&offsets);
+                self.push_buffer(&data);
+            }
+            ArrowType::Null => {}
+        }
+        Ok(())
+    }
+
+    /// Append a buffer to the body, padded to 8 bytes, and record where it is.
+    fn push_buffer(&mut self, bytes: &[u8]) {
+        self.buffers
+            .extend_from_slice(&len_i64(self.body.len()).to_le_bytes());
+        self.buffers
+            .extend_from_slice(&len_i64(bytes.len()).to_le_bytes());
+        self.body.extend_from_slice(bytes);
+        self.body.resize(self.body.len().next_multiple_of(8), 0);
+    }
+}
+
+/// A field of a flatbuffer table, in schema order
+enum Slot {
+    /// Field not written; readers use the schema default
+    Absent,
+    U8(u8),
+    Bool(bool),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    /// Reference to an object stored after the table
+    Offset(Object),
+}
+
+impl Slot {
+    fn size(&self) -> usize {
+        match self {
+            Slot::Absent => 0,
+            Slot::U8(_) | Slot::Bool(_) => 1,
+            Slot::I16(_) => 2,
+            Slot::I32(_) | Slot::Offset(_) => 4,
+            Slot::I64(_) => 8,
+        }
+    }
+}
+
+/// A flatbuffer object
+enum Object {
+    Table(Vec<Slot>),
+    Str(String),
+    /// Vector of tables
+    Tables(Vec<Object>),
+    /// Vector of 16-byte structs of two `long`s, already laid out
+    Structs(Vec<u8>),
+}
+
+/// Writes flatbuffers front to back: each table is preceded by its vtable and
+/// followed by the objects it refers to, so every offset points forwards.
+struct FlatBufferWriter {
+    buf: Vec<u8>,
+}
+
+impl FlatBufferWriter {
+    /// Serialise `root`, padded to a multiple of 8 bytes.
+    fn finish(root: &Object) -> Vec<u8> {
+        let mut writer = FlatBufferWriter { buf: vec![0; 4] };
+        let root_pos = writer.write(root);
+        writer.patch_offset(0, root_pos);
+        writer.align(8);
+        writer.buf
+    }
+
+    fn align(&mut self, alignment: usize) {
+        self.buf
+            .resize(self.buf.len().next_multiple_of(alignment), 0);
+    }
+
+    /// Point the `uoffset` at `at` to `target`.
+    fn patch_offset(&mut self, at: usize, target: usize) {
+        let relative = (target - at) as u32;
+        self.buf[at..at + 4].copy_from_slice(&relative.to_le_bytes());
+    }
+
+    /// Write an object and return its position.
+    fn write(&mut self, object: &Object) -> usize {
+        match object {
+            Object::Table(slots) => self.write_table(slots),
+            Object::Str(s) => {
+                self.align(4);
+                let pos = self.buf.len();
+                self.buf.extend_from_slice(&(s.len() as u32).to_le_bytes());
+                self.buf.extend_from_slice(s.as_bytes());
+                self.buf.push(0);
+                pos
+            }
+            Object::Tables(items) => {
+                self.align(4);
+                let pos = self.buf.len();
+                self.buf
+                    .extend_from_slice(&(items.len() as u32).to_le_bytes());
+                let slots = self.buf.len();
+                self.buf.resize(slots + 4 * items.len(), 0);
+                for (i, item) in items.iter().enumerate() {
+                    let item_pos = self.write(item);
+                    self.patch_offset(slots + 4 * i, item_pos);
+                }
+                pos
+            }
+            Object::Structs(bytes) => {
+                // The elements, which follow the length, must be 8-byte aligned
+                while !(self.buf.len() + 4).is_multiple_of(8) {
+                    self.buf.push(0);
+                }
+                let pos = self.buf.len();
+                self.buf
+                    .extend_from_slice(&((bytes.len() / 16) as u32).to_le_bytes());
+                self.buf.extend_from_slice(bytes);
+                pos
+            }
+        }
+    }
+
+    fn write_table(&mut self, slots: &[Slot]) -> usize {
+        // Lay fields out largest first so each is naturally aligned; the table itself
+        // starts 8-byte aligned, with its 4-byte vtable offset first.
+        let mut order: Vec<usize> = (0..slots.len()).filter(|&i| slots[i].size() > 0).collect();
+        order.sort_by_key(|&i| std::cmp::Reverse(slots[i].size()));
+
+        let mut field_offsets = vec![0u16; slots.len()];
+        let mut cursor: usize = 4;
+        for &i in &order {
+            let size = slots[i].size();
+            cursor = cursor.next_multiple_of(size);
+            field_offsets[i] = cursor as u16;
+            cursor += size;
+        }
+
+        self.align(2);
+        let vtable_pos = self.buf.len();
+        self.buf
+            .extend_from_slice(&((4 + 2 * slots.len()) as u16).to_le_bytes());
+        self.buf.extend_from_slice(&(cursor as u16).to_le_bytes());
+        for offset in &field_offsets {
+            self.buf.extend_from_slice(&offset.to_le_bytes());
+        }
+
+        self.align(8);
+        let table_pos = self.buf.len();
+        self.buf
+            .extend_from_slice(&((table_pos - vtable_pos) as i32).to_le_bytes());
+        self.buf.resize(table_pos + cursor, 0);
+
+        for &i in &order {
+            let at = table_pos + field_offsets[i] as usize;
+            match &slots[i] {
+                Slot::U8(v) => self.buf[at] = *v,
+                Slot::Bool(v) => self.buf[at] = u8::from(*v),
+                Slot::I16(v) => self.buf[at..at + 2].copy_from_slice(&v.to_le_bytes()),
+                Slot::I32(v) => self.buf[at..at + 4].copy_from_slice(&v.to_le_bytes()),
+                Slot::I64(v) => self.buf[at..at + 8].copy_from_slice(&v.to_le_bytes()),
+                Slot::Offset(_) | Slot::Absent => {}
+            }
+        }
+        for &i in &order {
+            if let Slot::Offset(object) = &slots[i] {
+                let object_pos = self.write(object);
+                self.patch_offset(table_pos + field_offsets[i] as usize, object_pos);
+            }
+        }
+        table_pos
+    }
+}
+
+/// Run a query and collect its column names and rows of values.
+pub async fn query_values(
+    conn: &libsql::Connection,
+    sql: &str,
+    params: Vec<Value>,
+) -> Result<(Vec<String>, Vec<Vec<Value>>), libsql::Error> {
+    let mut rows = conn.query(sql, params).await?;
+    let column_count = rows.column_count();
+    let columns = (0..column_count)
+        .map(|i| rows.column_name(i).unwrap_or_default().to_string())
+        .collect();
+
+    let mut values = Vec::new();
+    while let Some(row) = rows.next().await? {
+        values.push(
+            (0..column_count)
+                .map(|i| row.get_value(i))
+                .collect::<Result<Vec<_>, _>>()?,
+        );
+    }
+    Ok((columns, values))
+}
+
+/// Execute a query and return its results as an Arrow IPC stream.
+///
+/// Column types come from the storage class of each column's first non-`NULL` value:
+/// integers become `int64`, reals `float64`, text `utf8`, blobs `binary`, and columns
+/// with no values `null`. Every row is returned in a single record batch.
+///
+/// # Arguments
+/// - `env`: Elixir environment
+/// - `conn_id`: Database connection ID
+/// - `sql`: Query to run
+/// - `args`: Query parameters
+///
+/// # Returns
+/// - Binary containing the Arrow IPC stream
+/// - `{:error, reason}` - Query failed, or a column mixes storage classes
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn query_arrow_ipc<'a>(
+    env: Env<'a>,
+    conn_id: &str,
+    sql: &str,
+    args: Vec<Term<'a>>,
+) -> NifResult<Binary<'a>> {
+    let client = {
+        let conn_map = safe_lock(&CONNECTION_REGISTRY, "query_arrow_ipc conn_map")?;
+        conn_map
+            .get(conn_id)
+            .cloned()
+            .ok_or_else(|| rustler::Error::Term(Box::new("Invalid connection ID")))?
+    }; // Lock dropped here
+
+    let params: Vec<Value> = args
+        .into_iter()
+        .map(|t| crate::utils::decode_term_to_value(t))
+        .collect::<Result<_, _>>()
+        .map_err(|e| rustler::Error::Term(Box::new(e)))?;
+
+    // Clone the inner connection Arc and drop the outer lock before async operations
+    let connection = {
+        let client_guard = safe_lock_arc(&client, "query_arrow_ipc client")?;
+        client_guard.client.clone()
+    }; // Outer lock dropped here
+
+    // SAFETY: We're inside TOKIO_RUNTIME.block_on(), so this is synchronous execution.
+    // The std::sync::Mutex guards are safe to hold across await points here because
+    // we're not in a true async context - block_on runs the future to completion.
+    #[allow(clippy::await_holding_lock)]
+    let (columns, rows) = TOKIO_RUNTIME.block_on(async {
+        let conn_guard = safe_lock_arc(&connection, "query_arrow_ipc conn")?;
+
+        query_values(&conn_guard, sql, params).await.map_err(|e| {
+            crate::utils::record_last_error(conn_id, &e);
+            rustler::Error::Term(Box::new(format!("Query failed: {e}")))
+        })
+    })?;
+
+    let bytes =
+        encode_ipc_stream(&columns, &rows).map_err(|e| rustler::Error::Term(Box::new(e)))?;
+    let mut binary = OwnedBinary::new(bytes.len())
+        .ok_or_else(|| rustler::Error::Term(Box::new("Failed to allocate binary")))?;
+    binary.as_mut_slice().copy_from_slice(&bytes);
+    Ok(binary.release(env))
+}
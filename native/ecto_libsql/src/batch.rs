@@ -2,12 +2,19 @@
 ///
 /// This module handles batch execution of multiple SQL statements, both with
 /// and without transactional semantics. Supports both statement-level batch
-/// execution (with parameterized queries) and native SQL batch execution.
-use crate::constants::{CONNECTION_REGISTRY, TOKIO_RUNTIME};
-use crate::utils::{collect_rows, decode_term_to_value, safe_lock, safe_lock_arc};
-use libsql::Value;
+/// execution (with parameterized queries) and native SQL batch execution, and
+/// retrying a whole transaction when it conflicts with another writer.
+use crate::constants::{retries_exhausted, CONNECTION_REGISTRY, TOKIO_RUNTIME};
+use crate::models::Mode;
+use crate::utils::{
+    classify_busy, collect_rows, count_statements, decode_term_to_value, inline_params,
+    last_error_from, quote_identifier, record_last_error, safe_lock, safe_lock_arc,
+    split_statements, QuoteStyle,
+};
+use libsql::{BatchRows, Value};
 use rustler::types::atom::nil;
 use rustler::{Atom, Encoder, Env, NifResult, Term};
+use std::time::Duration;
 
 /// Execute multiple SQL statements sequentially without a transaction.
 ///
@@ -68,6 +75,7 @@ pub fn execute_batch<'a>(
         // Execute each statement sequentially
         for (sql, args) in &batch_stmts {
             let client_guard = safe_lock_arc(&client, "execute_batch client")?;
+            count_statements(conn_id, 1);
             let conn_guard = safe_lock_arc(&client_guard.client, "execute_batch conn")?;
             let result = conn_guard.query(sql, args.clone()).await;
 
@@ -79,6 +87,7 @@ pub fn execute_batch<'a>(
                     all_results.push(collected);
                 }
                 Err(e) => {
+                    record_last_error(conn_id, &e);
                     return Err(rustler::Error::Term(Box::new(format!(
                         "Batch statement error: {e}"
                     ))));
@@ -146,6 +155,7 @@ pub fn execute_transactional_batch<'a>(
     #[allow(clippy::await_holding_lock)]
     TOKIO_RUNTIME.block_on(async {
         let client_guard = safe_lock_arc(&client, "execute_transactional_batch client")?;
+        count_statements(conn_id, batch_stmts.len() as u64);
         let conn_guard = safe_lock_arc(&client_guard.client, "execute_transactional_batch conn")?;
         let trx = conn_guard.transaction().await.map_err(|e| {
             rustler::Error::Term(Box::new(format!("Begin transaction failed: {e}")))
@@ -166,8 +176,12 @@ pub fn execute_transactional_batch<'a>(
                     all_results.push(collected);
                 }
                 Err(e) => {
-                    // Rollback on error
-                    let _ = trx.rollback().await;
+                    record_last_error(conn_id, &e);
+                    // Rollback on error; a rollback failure is recorded but the
+                    // original statement error is what gets reported
+                    if let Err(rollback_err) = trx.rollback().await {
+                        record_last_error(conn_id, &rollback_err);
+                    }
                     return Err(rustler::Error::Term(Box::new(format!(
                         "Batch statement error: {e}"
                     ))));
@@ -176,9 +190,10 @@ pub fn execute_transactional_batch<'a>(
         }
 
         // Commit the transaction
-        trx.commit()
-            .await
-            .map_err(|e| rustler::Error::Term(Box::new(format!("Commit failed: {e}"))))?;
+        trx.commit().await.map_err(|e| {
+            record_last_error(conn_id, &e);
+            rustler::Error::Term(Box::new(format!("Commit failed: {e}")))
+        })?;
 
         Ok(all_results.encode(env))
     })
@@ -216,11 +231,12 @@ pub fn execute_batch_native<'a>(env: Env<'a>, conn_id: &str, sql: &str) -> NifRe
         #[allow(clippy::await_holding_lock)]
         let result = TOKIO_RUNTIME.block_on(async {
             let client_guard = safe_lock_arc(&client, "execute_batch_native client")?;
+            count_statements(conn_id, split_statements(sql).len() as u64);
             let conn_guard = safe_lock_arc(&client_guard.client, "execute_batch_native conn")?;
-            let mut batch_rows = conn_guard
-                .execute_batch(sql)
-                .await
-                .map_err(|e| rustler::Error::Term(Box::new(format!("batch failed: {e}"))))?;
+            let mut batch_rows = conn_guard.execute_batch(sql).await.map_err(|e| {
+                record_last_error(conn_id, &e);
+                rustler::Error::Term(Box::new(format!("batch failed: {e}")))
+            })?;
             // Drop guards after batch is retrieved
             drop(conn_guard);
             drop(client_guard);
@@ -287,6 +303,7 @@ pub fn execute_transactional_batch_native<'a>(
         #[allow(clippy::await_holding_lock)]
         let result = TOKIO_RUNTIME.block_on(async {
             let client_guard = safe_lock_arc(&client, "execute_transactional_batch_native client")?;
+            count_statements(conn_id, split_statements(sql).len() as u64);
             let conn_guard = safe_lock_arc(
                 &client_guard.client,
                 "execute_transactional_batch_native conn",
@@ -296,6 +313,7 @@ pub fn execute_transactional_batch_native<'a>(
                     .execute_transactional_batch(sql)
                     .await
                     .map_err(|e| {
+                        record_last_error(conn_id, &e);
                         rustler::Error::Term(Box::new(format!("transactional batch failed: {e}")))
                     })?;
             // Drop guards after batch is retrieved
@@ -324,3 +342,463 @@ pub fn execute_transactional_batch_native<'a>(
         Err(rustler::Error::Term(Box::new("Invalid connection ID")))
     }
 }
+
+/// Build a native batch script that reports each statement's affected row count.
+///
+/// Parameters are inlined as escaped literals (native batches can't bind them) and
+/// every statement is followed by `SELECT changes()`, so the script's results
+/// alternate between a statement and its count.
+pub fn counted_write_script(statements: &[(String, Vec<Value>)]) -> Result<String, String> {
+    let mut script = String::new();
+    for (index, (sql, params)) in statements.iter().enumerate() {
+        let stmt = inline_params(sql, params).map_err(|e| format!("Statement {index}: {e}"))?;
+        script.push_str(&stmt);
+        script.push_str(";\nSELECT changes();\n");
+    }
+    Ok(script)
+}
+
+/// Read the affected row counts from the results of a `counted_write_script` batch.
+pub async fn affected_counts(
+    mut batch_rows: BatchRows,
+    expected: usize,
+) -> Result<Vec<u64>, String> {
+    let mut counts = Vec::with_capacity(expected);
+    while let Some(_statement) = batch_rows.next_stmt_row() {
+        let mut rows = batch_rows
+            .next_stmt_row()
+            .flatten()
+            .ok_or_else(|| "Missing affected row count in batch result".to_string())?;
+        let count: i64 = rows
+            .next()
+            .await
+            .map_err(|e| format!("Failed to read affected row count: {e}"))?
+            .ok_or_else(|| "Missing affected row count in batch result".to_string())?
+            .get(0)
+            .map_err(|e| format!("Failed to read affected row count: {e}"))?;
+        counts.push(u64::try_from(count).unwrap_or(0));
+    }
+
+    if counts.len() == expected {
+        Ok(counts)
+    } else {
+        Err(format!(
+            "Expected {expected} batch results, got {}",
+            counts.len()
+        ))
+    }
+}
+
+/// Execute write statements atomically, returning each statement's affected row count.
+///
+/// Optimised for remote (non-replica) connections, where every statement would
+/// otherwise cost a network round trip: the statements are sent as a single
+/// transactional batch request. Parameters are inlined as escaped literals because
+/// native batches can't bind them, so only positional `?`/`?NNN` placeholders are
+/// supported and each entry must contain exactly one statement.
+///
+/// Other modes run the statements in a local transaction with bound parameters,
+/// returning the same result.
+///
+/// Counts follow SQLite's `changes()`: statements that don't modify rows (such as
+/// DDL) report the count of the most recent modifying statement.
+///
+/// # Arguments
+/// - `conn_id`: Database connection ID
+/// - `statements`: List of `{sql, params}` tuples
+///
+/// # Returns
+/// - List of affected row counts, one per statement
+/// - `{:error, reason}` - Nothing was committed
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn execute_write_batch(conn_id: &str, statements: Vec<Term>) -> NifResult<Vec<u64>> {
+    let client = {
+        let conn_map = safe_lock(&CONNECTION_REGISTRY, "execute_write_batch conn_map")?;
+        conn_map
+            .get(conn_id)
+            .cloned()
+            .ok_or_else(|| rustler::Error::Term(Box::new("Invalid connection ID")))?
+    }; // Lock dropped here
+
+    let mut batch_stmts: Vec<(String, Vec<Value>)> = Vec::with_capacity(statements.len());
+    for stmt_term in statements {
+        let (query, args): (String, Vec<Term>) = stmt_term.decode().map_err(|e| {
+            rustler::Error::Term(Box::new(format!("Failed to decode statement: {e:?}")))
+        })?;
+
+        let decoded_args: Vec<Value> = args
+            .into_iter()
+            .map(|t| decode_term_to_value(t))
+            .collect::<Result<_, _>>()
+            .map_err(|e| rustler::Error::Term(Box::new(e)))?;
+
+        batch_stmts.push((query, decoded_args));
+    }
+
+    if batch_stmts.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    // Clone the inner connection Arc and drop the outer lock before async operations
+    let (connection, mode) = {
+        let client_guard = safe_lock_arc(&client, "execute_write_batch client")?;
+        count_statements(conn_id, batch_stmts.len() as u64);
+        (client_guard.client.clone(), client_guard.mode)
+    }; // Outer lock dropped here
+
+    // SAFETY: We're inside TOKIO_RUNTIME.block_on(), so this is synchronous execution.
+    // The std::sync::Mutex guards are safe to hold across await points here because
+    // we're not in a true async context - block_on runs the future to completion.
+    #[allow(clippy::await_holding_lock)]
+    TOKIO_RUNTIME.block_on(async {
+        let conn_guard = safe_lock_arc(&connection, "execute_write_batch conn")?;
+
+        if mode == Mode::Remote {
+            let script = counted_write_script(&batch_stmts)
+                .map_err(|e| rustler::Error::Term(Box::new(e)))?;
+            let batch_rows = conn_guard
+                .execute_transactional_batch(&script)
+                .await
+                .map_err(|e| {
+                    record_last_error(conn_id, &e);
+                    rustler::Error::Term(Box::new(format!("Write batch failed: {e}")))
+                })?;
+
+            return affected_counts(batch_rows, batch_stmts.len())
+                .await
+                .map_err(|e| rustler::Error::Term(Box::new(e)));
+        }
+
+        let tx = conn_guard.transaction().await.map_err(|e| {
+            record_last_error(conn_id, &e);
+            rustler::Error::Term(Box::new(format!("Failed to begin write batch: {e}")))
+        })?;
+
+        let mut counts = Vec::with_capacity(batch_stmts.len());
+        for (index, (sql, args)) in batch_stmts.into_iter().enumerate() {
+            match tx.execute(&sql, args).await {
+                Ok(count) => counts.push(count),
+                Err(e) => {
+                    record_last_error(conn_id, &e);
+                    let _ = tx.rollback().await;
+                    return Err(rustler::Error::Term(Box::new(format!(
+                        "Write batch failed at statement {index}: {e}"
+                    ))));
+                }
+            }
+        }
+
+        tx.commit().await.map_err(|e| {
+            record_last_error(conn_id, &e);
+            rustler::Error::Term(Box::new(format!("Failed to commit write batch: {e}")))
+        })?;
+
+        Ok(counts)
+    })
+}
+
+/// Read the name and `CREATE TRIGGER` statement of every trigger on `table`.
+///
+/// Only triggers stored in the main database schema are returned.
+pub async fn table_triggers(
+    conn: &libsql::Connection,
+    table: &str,
+) -> Result<Vec<(String, String)>, String> {
+    let mut rows = conn
+        .query(
+            "SELECT name, sql FROM sqlite_master \
+             WHERE type = 'trigger' AND tbl_name = ?1 COLLATE NOCASE ORDER BY rowid",
+            [table],
+        )
+        .await
+        .map_err(|e| format!("Failed to read triggers: {e}"))?;
+
+    let mut triggers = Vec::new();
+    while let Some(row) = rows
+        .next()
+        .await
+        .map_err(|e| format!("Failed to read triggers: {e}"))?
+    {
+        let name: String = row
+            .get(0)
+            .map_err(|e| format!("Failed to read trigger name: {e}"))?;
+        let sql: String = row
+            .get(1)
+            .map_err(|e| format!("Failed to read trigger definition: {e}"))?;
+        triggers.push((name, sql));
+    }
+    Ok(triggers)
+}
+
+/// Run write statements with the triggers on `table` removed, returning each
+/// statement's affected row count.
+///
+/// The triggers are dropped, the statements run and the triggers recreated from their
+/// original definitions, all in one transaction. SQLite DDL is transactional, so a
+/// failure at any step rolls back to the original triggers and data.
+pub async fn execute_without_triggers(
+    conn: &libsql::Connection,
+    table: &str,
+    statements: Vec<(String, Vec<Value>)>,
+) -> Result<Vec<u64>, libsql::Error> {
+    let tx = conn.transaction().await?;
+
+    let result = async {
+        let triggers = table_triggers(&tx, table)
+            .await
+            .map_err(libsql::Error::Misuse)?;
+
+        for (name, _) in &triggers {
+            let drop_sql = format!(
+                "DROP TRIGGER {}",
+                quote_identifier(name, QuoteStyle::DoubleQuote)
+            );
+            tx.execute(&drop_sql, ()).await?;
+        }
+
+        let mut counts = Vec::with_capacity(statements.len());
+        for (sql, args) in statements {
+            counts.push(tx.execute(&sql, args).await?);
+        }
+
+        for (_, create_sql) in &triggers {
+            tx.execute(create_sql, ()).await?;
+        }
+
+        Ok(counts)
+    }
+    .await;
+
+    match result {
+        Ok(counts) => {
+            tx.commit().await?;
+            Ok(counts)
+        }
+        Err(e) => {
+            let _ = tx.rollback().await;
+            Err(e)
+        }
+    }
+}
+
+/// Run write statements against a table with its triggers disabled.
+///
+/// SQLite has no way to switch a single trigger off, so the table's triggers are
+/// dropped for the duration of the statements and recreated afterwards, all within one
+/// transaction. Triggers never fire for the statements, and are restored even when a
+/// statement fails.
+///
+/// # Arguments
+/// - `conn_id`: Database connection ID
+/// - `table`: Table whose triggers are disabled
+/// - `statements`: List of `{sql, params}` tuples
+///
+/// # Returns
+/// - List of affected row counts, one per statement
+/// - `{:error, reason}` - Nothing was committed and the triggers are unchanged
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn execute_with_triggers_disabled(
+    conn_id: &str,
+    table: &str,
+    statements: Vec<Term>,
+) -> NifResult<Vec<u64>> {
+    let client = {
+        let conn_map = safe_lock(
+            &CONNECTION_REGISTRY,
+            "execute_with_triggers_disabled conn_map",
+        )?;
+        conn_map
+            .get(conn_id)
+            .cloned()
+            .ok_or_else(|| rustler::Error::Term(Box::new("Invalid connection ID")))?
+    }; // Lock dropped here
+
+    let mut batch_stmts: Vec<(String, Vec<Value>)> = Vec::with_capacity(statements.len());
+    for stmt_term in statements {
+        let (query, args): (String, Vec<Term>) = stmt_term.decode().map_err(|e| {
+            rustler::Error::Term(Box::new(format!("Failed to decode statement: {e:?}")))
+        })?;
+
+        let decoded_args: Vec<Value> = args
+            .into_iter()
+            .map(|t| decode_term_to_value(t))
+            .collect::<Result<_, _>>()
+            .map_err(|e| rustler::Error::Term(Box::new(e)))?;
+
+        batch_stmts.push((query, decoded_args));
+    }
+
+    let connection = {
+        let client_guard = safe_lock_arc(&client, "execute_with_triggers_disabled client")?;
+        count_statements(conn_id, batch_stmts.len() as u64);
+        client_guard.client.clone()
+    }; // Outer lock dropped here
+
+    // SAFETY: We're inside TOKIO_RUNTIME.block_on(), so this is synchronous execution.
+    // The std::sync::Mutex guards are safe to hold across await points here because
+    // we're not in a true async context - block_on runs the future to completion.
+    #[allow(clippy::await_holding_lock)]
+    TOKIO_RUNTIME.block_on(async {
+        let conn_guard = safe_lock_arc(&connection, "execute_with_triggers_disabled conn")?;
+
+        execute_without_triggers(&conn_guard, table, batch_stmts)
+            .await
+            .map_err(|e| {
+                record_last_error(conn_id, &e);
+                rustler::Error::Term(Box::new(format!("Bulk load failed: {e}")))
+            })
+    })
+}
+
+/// Delay before the first retry of a conflicting transaction; it doubles on each retry.
+pub const RETRY_BACKOFF: Duration = Duration::from_millis(10);
+
+/// Longest delay between retries of a conflicting transaction.
+const MAX_RETRY_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Why `execute_with_retry` gave up
+#[derive(Debug)]
+pub enum RetryError {
+    /// Every attempt hit a busy or locked error; the last one is kept.
+    Exhausted { attempts: u32, error: libsql::Error },
+    /// A statement failed for a reason retrying won't fix.
+    Failed(libsql::Error),
+    /// The connection already has a transaction open, which a retry can't restart.
+    InTransaction,
+}
+
+/// Run `statements` in one transaction, restarting the whole transaction when it
+/// conflicts with another connection.
+///
+/// The transaction starts with `BEGIN IMMEDIATE`. Any busy or locked error, from
+/// `BEGIN`, a statement or `COMMIT`, rolls everything back and, after a backoff that
+/// starts at `backoff` and doubles each time, runs the sequence again, up to
+/// `max_retries` more times. Other errors roll back and fail at once. The statements
+/// must be safe to run more than once, as a failed attempt's effects are discarded.
+///
+/// Returns the affected row count of each statement and the number of attempts made.
+pub async fn execute_with_retry(
+    conn: &libsql::Connection,
+    statements: &[(String, Vec<Value>)],
+    max_retries: u32,
+    backoff: Duration,
+) -> Result<(Vec<u64>, u32), RetryError> {
+    if !conn.is_autocommit() {
+        return Err(RetryError::InTransaction);
+    }
+
+    let mut delay = backoff;
+    let mut attempts = 0;
+    loop {
+        attempts += 1;
+        let attempt = async {
+            conn.execute_batch("BEGIN IMMEDIATE").await?;
+            let mut counts = Vec::with_capacity(statements.len());
+            for (sql, params) in statements {
+                counts.push(conn.execute(sql, params.clone()).await?);
+            }
+            conn.execute_batch("COMMIT").await?;
+            Ok::<_, libsql::Error>(counts)
+        }
+        .await;
+
+        let error = match attempt {
+            Ok(counts) => return Ok((counts, attempts)),
+            Err(error) => error,
+        };
+        // A failed COMMIT leaves the transaction open, as does any failed statement
+        if !conn.is_autocommit() {
+            let _ = conn.execute_batch("ROLLBACK").await;
+        }
+
+        if classify_busy(&last_error_from(&error)).is_none() {
+            return Err(RetryError::Failed(error));
+        }
+        if attempts > max_retries {
+            return Err(RetryError::Exhausted { attempts, error });
+        }
+        tokio::time::sleep(delay).await;
+        delay = (delay * 2).min(MAX_RETRY_BACKOFF);
+    }
+}
+
+/// Execute statements atomically, retrying the whole transaction on write conflicts.
+///
+/// For known-idempotent statement sequences against a contended database: a busy or
+/// locked error at any point, including `COMMIT`, restarts the transaction from the
+/// beginning instead of failing, up to `max_retries` times with exponential backoff.
+/// Must be called outside a transaction. See `execute_with_retry`.
+///
+/// # Arguments
+/// - `conn_id`: Database connection ID
+/// - `statements`: List of `{sql, params}` tuples
+/// - `max_retries`: Retries after the first attempt
+///
+/// # Returns
+/// - `{counts, attempts}` - Affected row count per statement, and the attempts it took
+/// - `{:error, {:retries_exhausted, attempts, message}}` - Every attempt conflicted;
+///   nothing was committed
+/// - `{:error, reason}` - A statement failed for another reason; nothing was committed
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn atomic_statements_retry(
+    conn_id: &str,
+    statements: Vec<Term>,
+    max_retries: u32,
+) -> NifResult<(Vec<u64>, u32)> {
+    let client = {
+        let conn_map = safe_lock(&CONNECTION_REGISTRY, "atomic_statements_retry conn_map")?;
+        conn_map
+            .get(conn_id)
+            .cloned()
+            .ok_or_else(|| rustler::Error::Term(Box::new("Invalid connection ID")))?
+    }; // Lock dropped here
+
+    let mut batch_stmts: Vec<(String, Vec<Value>)> = Vec::with_capacity(statements.len());
+    for stmt_term in statements {
+        let (query, args): (String, Vec<Term>) = stmt_term.decode().map_err(|e| {
+            rustler::Error::Term(Box::new(format!("Failed to decode statement: {e:?}")))
+        })?;
+
+        let decoded_args: Vec<Value> = args
+            .into_iter()
+            .map(|t| decode_term_to_value(t))
+            .collect::<Result<_, _>>()
+            .map_err(|e| rustler::Error::Term(Box::new(e)))?;
+
+        batch_stmts.push((query, decoded_args));
+    }
+
+    let connection = {
+        let client_guard = safe_lock_arc(&client, "atomic_statements_retry client")?;
+        count_statements(conn_id, batch_stmts.len() as u64);
+        client_guard.client.clone()
+    }; // Outer lock dropped here
+
+    // SAFETY: We're inside TOKIO_RUNTIME.block_on(), so this is synchronous execution.
+    // The std::sync::Mutex guards are safe to hold across await points here because
+    // we're not in a true async context - block_on runs the future to completion.
+    #[allow(clippy::await_holding_lock)]
+    TOKIO_RUNTIME.block_on(async {
+        let conn_guard = safe_lock_arc(&connection, "atomic_statements_retry conn")?;
+
+        execute_with_retry(&conn_guard, &batch_stmts, max_retries, RETRY_BACKOFF)
+            .await
+            .map_err(|e| match e {
+                RetryError::Exhausted { attempts, error } => {
+                    record_last_error(conn_id, &error);
+                    rustler::Error::Term(Box::new((
+                        retries_exhausted(),
+                        attempts,
+                        error.to_string(),
+                    )))
+                }
+                RetryError::Failed(error) => {
+                    record_last_error(conn_id, &error);
+                    rustler::Error::Term(Box::new(format!("Transaction failed: {error}")))
+                }
+                RetryError::InTransaction => rustler::Error::Term(Box::new(
+                    "Cannot retry statements inside an open transaction",
+                )),
+            })
+    })
+}
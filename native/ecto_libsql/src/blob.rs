@@ -0,0 +1,772 @@
+/// In-place blob updates for LibSQL databases
+///
+/// libsql does not expose SQLite's incremental blob I/O (`sqlite3_blob_open` and
+/// friends), so region writes are performed with a single `UPDATE` that splices
+/// the new bytes into the existing value inside the engine. Like incremental
+/// blob writes, a region write can never change the size of the blob.
+///
+/// Large blobs can also be uploaded in chunks: a blob builder accumulates the chunks in
+/// native memory, and the assembled bytes are bound to a single insert.
+///
+/// Queries can also defer blobs: in blob-ref mode each blob cell is returned as a
+/// marker naming its row and column, and the bytes are fetched only when needed.
+use crate::constants::*;
+use crate::models::{BlobBuilder, ColumnNaming, LazyCell};
+use crate::utils::{
+    column_origin_tables, count_statements, decode_term_to_value, dedupe_column_names,
+    encode_value, quote_identifier, record_last_error, safe_lock, safe_lock_arc, QuoteStyle,
+};
+use libsql::Value;
+use rustler::{Atom, Binary, Encoder, Env, NifResult, OwnedBinary, Term};
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::time::Instant;
+
+/// Overwrite `data.len()` bytes of a blob starting at byte `offset`.
+///
+/// The blob is identified by `table`, `column` and `rowid`. Fails without
+/// modifying anything if the row does not exist, the value is not a blob, or the
+/// write would extend past the end of the blob.
+pub async fn write_blob_region(
+    conn: &libsql::Connection,
+    table: &str,
+    column: &str,
+    rowid: i64,
+    offset: u64,
+    data: &[u8],
+) -> Result<(), String> {
+    let table_ident = quote_identifier(table, QuoteStyle::Backtick);
+    let column_ident = quote_identifier(column, QuoteStyle::Backtick);
+
+    let mut rows = conn
+        .query(
+            &format!("SELECT {column_ident} FROM {table_ident} WHERE rowid = ?1"),
+            vec![Value::Integer(rowid)],
+        )
+        .await
+        .map_err(|e| format!("Failed to read blob: {e}"))?;
+    let original = match rows
+        .next()
+        .await
+        .map_err(|e| format!("Failed to read blob: {e}"))?
+    {
+        None => return Err(format!("Row not found: rowid {rowid}")),
+        Some(row) => match row
+            .get_value(0)
+            .map_err(|e| format!("Failed to read blob: {e}"))?
+        {
+            Value::Blob(bytes) => bytes,
+            other => {
+                return Err(format!(
+                    "Column {column} is not a blob (found {})",
+                    value_type_name(&other)
+                ))
+            }
+        },
+    };
+
+    let end = usize::try_from(offset)
+        .ok()
+        .and_then(|start| start.checked_add(data.len()))
+        .filter(|end| *end <= original.len());
+    let Some(end) = end else {
+        return Err(format!(
+            "Write of {} bytes at offset {offset} exceeds blob size of {} bytes (blobs cannot be resized by region writes)",
+            data.len(),
+            original.len()
+        ));
+    };
+
+    // The splice is done here and bound as a single blob, because SQLite's `||` works on
+    // text and would re-encode the bytes on a UTF-16 database. Matching on the original
+    // value keeps the read and write atomic: a concurrent change makes the update miss.
+    let mut patched = original.clone();
+    patched[end - data.len()..end].copy_from_slice(data);
+    let affected = conn
+        .execute(
+            &format!(
+                "UPDATE {table_ident} SET {column_ident} = ?1 WHERE rowid = ?2 AND {column_ident} = ?3"
+            ),
+            vec![
+                Value::Blob(patched),
+                Value::Integer(rowid),
+                Value::Blob(original),
+            ],
+        )
+        .await
+        .map_err(|e| format!("Failed to write blob: {e}"))?;
+
+    if affected == 0 {
+        return Err(format!(
+            "Blob in rowid {rowid} changed while it was being written"
+        ));
+    }
+    Ok(())
+}
+
+/// Overwrite a region of an existing blob in place.
+///
+/// Writes `data` at byte `offset` within the blob stored in `column` of the row
+/// with the given `rowid`. The blob keeps its size: writes that would extend past
+/// the end of the blob are rejected, matching SQLite's incremental blob I/O.
+///
+/// # Arguments
+/// - `conn_id`: Database connection ID
+/// - `table`: Table name (quoted internally)
+/// - `column`: Blob column name (quoted internally)
+/// - `rowid`: Rowid of the row to update
+/// - `offset`: Byte offset to start writing at
+/// - `data`: Bytes to write
+///
+/// # Returns
+/// - `:ok` - The region was written
+/// - `{:error, reason}` - Row not found, value not a blob, or the write is out of bounds
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn write_blob_range(
+    conn_id: &str,
+    table: &str,
+    column: &str,
+    rowid: i64,
+    offset: u64,
+    data: Binary,
+) -> NifResult<rustler::Atom> {
+    let client = {
+        let conn_map = safe_lock(&CONNECTION_REGISTRY, "write_blob_range conn_map")?;
+        conn_map
+            .get(conn_id)
+            .cloned()
+            .ok_or_else(|| rustler::Error::Term(Box::new("Invalid connection ID")))?
+    }; // Lock dropped here
+
+    // Clone the inner connection Arc and drop the outer lock before async operations
+    let connection = {
+        let client_guard = safe_lock_arc(&client, "write_blob_range client")?;
+        client_guard.client.clone()
+    }; // Outer lock dropped here
+
+    // SAFETY: We're inside TOKIO_RUNTIME.block_on(), so this is synchronous execution.
+    // The std::sync::Mutex guards are safe to hold across await points here because
+    // we're not in a true async context - block_on runs the future to completion.
+    #[allow(clippy::await_holding_lock)]
+    TOKIO_RUNTIME.block_on(async {
+        let conn_guard = safe_lock_arc(&connection, "write_blob_range conn")?;
+
+        write_blob_region(&conn_guard, table, column, rowid, offset, data.as_slice())
+            .await
+            .map(|_| rustler::types::atom::ok())
+            .map_err(|e| rustler::Error::Term(Box::new(e)))
+    })
+}
+
+/// Drop builders that haven't been appended to within `BLOB_BUILDER_IDLE_TIMEOUT`.
+fn sweep_idle_builders(builders: &mut HashMap<String, BlobBuilder>) {
+    builders.retain(|_, builder| builder.touched.elapsed() < BLOB_BUILDER_IDLE_TIMEOUT);
+}
+
+/// Append `chunk` to the blob builder `id`, returning the builder's new size.
+///
+/// Idle builders are swept first, so abandoned uploads don't count towards the cap.
+/// Fails if the builder does not exist (or has expired), or if the chunk would take the
+/// bytes held by all builders past `MAX_BLOB_BUILDER_BYTES`.
+pub fn append_to_builder(id: &str, chunk: &[u8]) -> Result<usize, String> {
+    let mut builders = safe_lock(&BLOB_BUILDER_REGISTRY, "append_to_builder builders")
+        .map_err(|_| "Failed to lock blob builder registry".to_string())?;
+    sweep_idle_builders(&mut builders);
+    let held: usize = builders.values().map(|builder| builder.bytes.len()).sum();
+    if held.saturating_add(chunk.len()) > MAX_BLOB_BUILDER_BYTES {
+        return Err(format!(
+            "Blob builders cannot hold more than {MAX_BLOB_BUILDER_BYTES} bytes"
+        ));
+    }
+
+    let builder = builders
+        .get_mut(id)
+        .ok_or_else(|| "Blob builder not found".to_string())?;
+    builder.bytes.extend_from_slice(chunk);
+    builder.touched = Instant::now();
+    Ok(builder.bytes.len())
+}
+
+/// Move the bytes of each referenced builder into its parameter.
+///
+/// `builder_ids` holds, for each parameter, the builder it refers to, if any. All the
+/// builders are checked before any is consumed, so on error none are. A builder can
+/// only be bound once, as its bytes are moved rather than copied.
+pub fn take_builder_params(
+    params: &mut [Value],
+    builder_ids: &[Option<String>],
+) -> Result<(), String> {
+    let mut builders = safe_lock(&BLOB_BUILDER_REGISTRY, "take_builder_params builders")
+        .map_err(|_| "Failed to lock blob builder registry".to_string())?;
+    let mut seen = HashSet::new();
+    for id in builder_ids.iter().flatten() {
+        if !builders.contains_key(id) {
+            return Err(format!("Blob builder not found: {id}"));
+        }
+        if !seen.insert(id) {
+            return Err(format!("Blob builder bound more than once: {id}"));
+        }
+    }
+    for (param, id) in params.iter_mut().zip(builder_ids) {
+        if let Some(builder) = id.as_ref().and_then(|id| builders.remove(id)) {
+            *param = Value::Blob(builder.bytes);
+        }
+    }
+    Ok(())
+}
+
+/// Start a blob builder for uploading a large blob in chunks.
+///
+/// A builder that goes `BLOB_BUILDER_IDLE_TIMEOUT` without an append is discarded.
+///
+/// # Returns
+/// - Builder ID, to append chunks to and reference from `insert_blob` parameters
+#[rustler::nif]
+pub fn blob_builder_new() -> NifResult<String> {
+    let id = uuid::Uuid::new_v4().to_string();
+    let mut builders = safe_lock(&BLOB_BUILDER_REGISTRY, "blob_builder_new builders")?;
+    sweep_idle_builders(&mut builders);
+    builders.insert(
+        id.clone(),
+        BlobBuilder {
+            bytes: Vec::new(),
+            touched: Instant::now(),
+        },
+    );
+    Ok(id)
+}
+
+/// Append a chunk of bytes to a blob builder.
+///
+/// # Arguments
+/// - `id`: Builder ID from `blob_builder_new`
+/// - `chunk`: Bytes to append
+///
+/// # Returns
+/// - The builder's size in bytes after appending
+/// - `{:error, reason}` - Unknown builder, or the registry's size cap would be exceeded
+#[rustler::nif(schedule = "DirtyCpu")]
+pub fn blob_builder_append(id: &str, chunk: Binary) -> NifResult<usize> {
+    append_to_builder(id, chunk.as_slice()).map_err(|e| rustler::Error::Term(Box::new(e)))
+}
+
+/// Drop a blob builder and the bytes it holds without inserting them.
+///
+/// Discarding an unknown builder is not an error.
+///
+/// # Returns
+/// - `:ok` - Builder removed
+#[rustler::nif]
+pub fn blob_builder_discard(id: &str) -> NifResult<Atom> {
+    safe_lock(&BLOB_BUILDER_REGISTRY, "blob_builder_discard builders")?.remove(id);
+    Ok(rustler::types::atom::ok())
+}
+
+/// Execute a statement with assembled blob builders bound as parameters.
+///
+/// Any parameter given as `{:blob_builder, id}` is replaced by the bytes that builder
+/// holds; other parameters are bound as usual. The referenced builders are consumed
+/// whether or not the statement succeeds, so their bytes are never held twice, and each
+/// builder can appear only once.
+///
+/// # Arguments
+/// - `conn_id`: Database connection ID
+/// - `sql`: Statement to execute, usually an `INSERT`
+/// - `args`: Statement parameters
+///
+/// # Returns
+/// - Number of rows affected
+/// - `{:error, reason}` - Unknown or repeated builder, or the statement failed
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn insert_blob(conn_id: &str, sql: &str, args: Vec<Term>) -> NifResult<u64> {
+    let client = {
+        let conn_map = safe_lock(&CONNECTION_REGISTRY, "insert_blob conn_map")?;
+        conn_map
+            .get(conn_id)
+            .cloned()
+            .ok_or_else(|| rustler::Error::Term(Box::new("Invalid connection ID")))?
+    }; // Lock dropped here
+
+    let mut builder_ids: Vec<Option<String>> = Vec::with_capacity(args.len());
+    let mut params: Vec<Value> = Vec::with_capacity(args.len());
+    for term in args {
+        match term.decode::<(Atom, String)>() {
+            Ok((tag, id)) if tag == blob_builder() => {
+                builder_ids.push(Some(id));
+                params.push(Value::Null);
+            }
+            _ => {
+                builder_ids.push(None);
+                params.push(
+                    decode_term_to_value(term).map_err(|e| rustler::Error::Term(Box::new(e)))?,
+                );
+            }
+        }
+    }
+
+    // Move the assembled bytes out rather than copying them
+    take_builder_params(&mut params, &builder_ids)
+        .map_err(|e| rustler::Error::Term(Box::new(e)))?;
+
+    let connection = {
+        let client_guard = safe_lock_arc(&client, "insert_blob client")?;
+        count_statements(conn_id, 1);
+        client_guard.client.clone()
+    }; // Outer lock dropped here
+
+    // SAFETY: We're inside TOKIO_RUNTIME.block_on(), so this is synchronous execution.
+    // The std::sync::Mutex guards are safe to hold across await points here because
+    // we're not in a true async context - block_on runs the future to completion.
+    #[allow(clippy::await_holding_lock)]
+    TOKIO_RUNTIME.block_on(async {
+        let conn_guard = safe_lock_arc(&connection, "insert_blob conn")?;
+        conn_guard.execute(sql, params).await.map_err(|e| {
+            record_last_error(conn_id, &e);
+            rustler::Error::Term(Box::new(format!("Execute failed: {e}")))
+        })
+    })
+}
+
+/// Name of the column that reports `table`'s rowid as its origin, if the table has one.
+///
+/// SQLite reports `rowid` under the table's `INTEGER PRIMARY KEY` column when the
+/// table has one, so that column's name is returned; otherwise `rowid`. `None` for
+/// `WITHOUT ROWID` tables.
+async fn rowid_origin_name(
+    conn: &libsql::Connection,
+    schema: &str,
+    table: &str,
+) -> Result<Option<String>, String> {
+    let schema_ident = quote_identifier(schema, QuoteStyle::DoubleQuote);
+    let table_ident = quote_identifier(table, QuoteStyle::DoubleQuote);
+
+    let mut rows = conn
+        .query(
+            &format!("PRAGMA {schema_ident}.table_list({table_ident})"),
+            (),
+        )
+        .await
+        .map_err(|e| format!("Failed to look up table: {e}"))?;
+    if let Some(row) = rows
+        .next()
+        .await
+        .map_err(|e| format!("Failed to look up table: {e}"))?
+    {
+        let without_rowid: i64 = row
+            .get(4)
+            .map_err(|e| format!("Failed to read table kind: {e}"))?;
+        if without_rowid != 0 {
+            return Ok(None);
+        }
+    }
+
+    let mut pk_columns: Vec<(String, String)> = Vec::new();
+    let mut rows = conn
+        .query(
+            &format!("PRAGMA {schema_ident}.table_info({table_ident})"),
+            (),
+        )
+        .await
+        .map_err(|e| format!("Failed to read table info: {e}"))?;
+    while let Some(row) = rows
+        .next()
+        .await
+        .map_err(|e| format!("Failed to read table info: {e}"))?
+    {
+        let pk: i64 = row
+            .get(5)
+            .map_err(|e| format!("Failed to read primary key flag: {e}"))?;
+        if pk > 0 {
+            let name: String = row
+                .get(1)
+                .map_err(|e| format!("Failed to read column name: {e}"))?;
+            let column_type: String = row
+                .get(2)
+                .map_err(|e| format!("Failed to read column type: {e}"))?;
+            pk_columns.push((name, column_type));
+        }
+    }
+
+    // A single INTEGER primary key is a rowid alias unless SQLite had to back it
+    // with an index (as for `INTEGER PRIMARY KEY DESC`).
+    let mut pk_index = false;
+    let mut rows = conn
+        .query(
+            &format!("PRAGMA {schema_ident}.index_list({table_ident})"),
+            (),
+        )
+        .await
+        .map_err(|e| format!("Failed to read index list: {e}"))?;
+    while let Some(row) = rows
+        .next()
+        .await
+        .map_err(|e| format!("Failed to read index list: {e}"))?
+    {
+        let origin: String = row
+            .get(3)
+            .map_err(|e| format!("Failed to read index origin: {e}"))?;
+        pk_index |= origin == "pk";
+    }
+
+    match pk_columns.as_slice() {
+        [(name, column_type)] if !pk_index && column_type.eq_ignore_ascii_case("INTEGER") => {
+            Ok(Some(name.clone()))
+        }
+        _ => Ok(Some("rowid".to_string())),
+    }
+}
+
+/// For each result column of `stmt`, the schema of the table it was read from and the
+/// index of the result column holding the rowid of its row, where the query selects one.
+pub async fn blob_ref_sources(
+    conn: &libsql::Connection,
+    stmt: &libsql::Statement,
+) -> Result<Vec<Option<(String, usize)>>, String> {
+    let origins: Vec<Option<(String, String, String)>> = stmt
+        .columns()
+        .iter()
+        .map(|column| {
+            Some((
+                column.database_name()?.to_string(),
+                column.table_name()?.to_string(),
+                column.origin_name()?.to_string(),
+            ))
+        })
+        .collect();
+
+    let mut rowid_columns: HashMap<(String, String), Option<usize>> = HashMap::new();
+    for origin in origins.iter().flatten() {
+        let (schema, table, _) = origin;
+        if let Entry::Vacant(entry) = rowid_columns.entry((schema.clone(), table.clone())) {
+            let rowid_name = rowid_origin_name(conn, schema, table).await?;
+            entry.insert(rowid_name.and_then(|rowid_name| {
+                origins.iter().position(|other| {
+                    other.as_ref().is_some_and(|other| {
+                        (&other.0, &other.1) == (schema, table)
+                            && other.2.eq_ignore_ascii_case(&rowid_name)
+                    })
+                })
+            }));
+        }
+    }
+
+    Ok(origins
+        .iter()
+        .map(|origin| {
+            origin.as_ref().and_then(|(schema, table, _)| {
+                rowid_columns
+                    .get(&(schema.clone(), table.clone()))
+                    .copied()
+                    .flatten()
+                    .map(|rowid_column| (schema.clone(), rowid_column))
+            })
+        })
+        .collect())
+}
+
+/// Wrap `sql` so referenced blobs are read as their length rather than their bytes.
+///
+/// The result columns are renamed `c0`, `c1`, ... in a CTE. A column without a source
+/// is selected as is; one with a source is selected twice, first as its value (NULL
+/// where it holds a blob with an integer rowid) and then as that blob's length (NULL
+/// otherwise). SQLite can answer `length()` of a blob from its header, so the bytes
+/// of a referenced blob are never copied out of the database.
+pub fn blob_ref_sql(sql: &str, sources: &[Option<(String, usize)>]) -> String {
+    let columns: Vec<String> = (0..sources.len()).map(|i| format!("c{i}")).collect();
+    let mut selected = Vec::with_capacity(sources.len());
+    for (i, source) in sources.iter().enumerate() {
+        match source {
+            None => selected.push(format!("c{i}")),
+            Some((_, rowid)) => {
+                let referenced = format!("typeof(c{i}) = 'blob' AND typeof(c{rowid}) = 'integer'");
+                selected.push(format!("CASE WHEN {referenced} THEN NULL ELSE c{i} END"));
+                selected.push(format!("CASE WHEN {referenced} THEN length(c{i}) END"));
+            }
+        }
+    }
+
+    // The newline before the closing parenthesis keeps a trailing `--` comment in `sql`
+    // from swallowing it.
+    format!(
+        "WITH blob_ref_query({}) AS (\n{}\n) SELECT {} FROM blob_ref_query",
+        columns.join(", "),
+        sql.trim().trim_end_matches(';').trim_end(),
+        selected.join(", ")
+    )
+}
+
+/// Run a query, returning blobs as references instead of their bytes.
+///
+/// A blob cell becomes `LazyCell::BlobRef` when the query also selects the rowid
+/// (or `INTEGER PRIMARY KEY`) of the table it was read from. Blobs that cannot be
+/// traced to a row, such as computed values or columns from tables whose rowid is
+/// not selected, are returned inline. Fails if no result column can be traced to
+/// a selected rowid at all. `sql` must be a `SELECT` (or `VALUES`), as it is run
+/// wrapped by `blob_ref_sql` so referenced blobs are never read.
+///
+/// Returns the column names, after `naming` is applied, and the rows.
+pub async fn query_blob_ref_rows(
+    conn: &libsql::Connection,
+    sql: &str,
+    params: Vec<Value>,
+    naming: ColumnNaming,
+) -> Result<(Vec<String>, Vec<Vec<LazyCell>>), String> {
+    let stmt = conn
+        .prepare(sql)
+        .await
+        .map_err(|e| format!("Query failed: {e}"))?;
+    let sources = blob_ref_sources(conn, &stmt).await?;
+    if sources.iter().all(Option::is_none) {
+        return Err(
+            "Query must select the rowid or INTEGER PRIMARY KEY of the table holding the blobs"
+                .to_string(),
+        );
+    }
+
+    let origins: Vec<String> = stmt
+        .columns()
+        .iter()
+        .map(|column| column.origin_name().unwrap_or(column.name()).to_string())
+        .collect();
+    let names: Vec<String> = stmt
+        .columns()
+        .iter()
+        .map(|column| column.name().to_string())
+        .collect();
+    let names = dedupe_column_names(&names, &column_origin_tables(&stmt), naming);
+
+    // Where each result column starts in the wrapped query, which selects a length
+    // after every column with a source.
+    let positions: Vec<usize> = sources
+        .iter()
+        .scan(0, |next, source| {
+            let position = *next;
+            *next += if source.is_some() { 2 } else { 1 };
+            Some(position)
+        })
+        .collect();
+
+    let mut rows = conn
+        .query(&blob_ref_sql(sql, &sources), params)
+        .await
+        .map_err(|e| format!("Query failed: {e}"))?;
+    let mut collected = Vec::new();
+    while let Some(row) = rows
+        .next()
+        .await
+        .map_err(|e| format!("Query failed: {e}"))?
+    {
+        let mut cells = Vec::with_capacity(names.len());
+        for (i, source) in sources.iter().enumerate() {
+            let value = row
+                .get_value(positions[i] as i32)
+                .map_err(|e| format!("Failed to read column '{}': {e}", names[i]))?;
+            let Some((schema, rowid_column)) = source else {
+                cells.push(LazyCell::Value(value));
+                continue;
+            };
+            let size = row
+                .get_value(positions[i] as i32 + 1)
+                .map_err(|e| format!("Failed to read column '{}': {e}", names[i]))?;
+            let rowid = row
+                .get_value(positions[*rowid_column] as i32)
+                .map_err(|e| format!("Failed to read column '{}': {e}", names[*rowid_column]))?;
+            cells.push(match (size, rowid) {
+                (Value::Integer(size), Value::Integer(rowid)) => LazyCell::BlobRef {
+                    rowid,
+                    column: origins[i].clone(),
+                    size: size as u64,
+                    schema: schema.clone(),
+                },
+                _ => LazyCell::Value(value),
+            });
+        }
+        collected.push(cells);
+    }
+
+    Ok((names, collected))
+}
+
+/// Read the blob in `column` of the row of `schema.table` with the given `rowid`.
+pub async fn read_blob(
+    conn: &libsql::Connection,
+    schema: &str,
+    table: &str,
+    column: &str,
+    rowid: i64,
+) -> Result<Vec<u8>, String> {
+    let schema_ident = quote_identifier(schema, QuoteStyle::Backtick);
+    let table_ident = quote_identifier(table, QuoteStyle::Backtick);
+    let column_ident = quote_identifier(column, QuoteStyle::Backtick);
+
+    let mut rows = conn
+        .query(
+            &format!("SELECT {column_ident} FROM {schema_ident}.{table_ident} WHERE rowid = ?1"),
+            vec![Value::Integer(rowid)],
+        )
+        .await
+        .map_err(|e| format!("Failed to read blob: {e}"))?;
+
+    match rows
+        .next()
+        .await
+        .map_err(|e| format!("Failed to read blob: {e}"))?
+    {
+        None => Err(format!("Row not found: rowid {rowid}")),
+        Some(row) => match row
+            .get_value(0)
+            .map_err(|e| format!("Failed to read blob: {e}"))?
+        {
+            Value::Blob(bytes) => Ok(bytes),
+            other => Err(format!(
+                "Column {column} is not a blob (found {})",
+                value_type_name(&other)
+            )),
+        },
+    }
+}
+
+fn value_type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Integer(_) => "integer",
+        Value::Real(_) => "real",
+        Value::Text(_) => "text",
+        Value::Blob(_) => "blob",
+    }
+}
+
+/// Execute a query with lazy blob materialisation.
+///
+/// Blob cells are returned as `{:blob_ref, rowid, column, size, schema}` markers
+/// instead of their bytes, so large blobs are only read when fetched with
+/// `fetch_blob_ref`. The query must select the rowid (or `INTEGER PRIMARY KEY`) of
+/// the table holding the blobs; blobs that cannot be traced to a selected rowid are
+/// returned inline.
+///
+/// # Arguments
+/// - `conn_id`: Database connection ID
+/// - `sql`: Query to run
+/// - `args`: Positional query parameters
+///
+/// # Returns
+/// - Result map with `columns`, `rows` and `num_rows`, as `query_args` returns
+/// - `{:error, reason}` - No rowid selected, or the query failed
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn query_with_blob_refs<'a>(
+    env: Env<'a>,
+    conn_id: &str,
+    sql: &str,
+    args: Vec<Term<'a>>,
+) -> NifResult<Term<'a>> {
+    let client = {
+        let conn_map = safe_lock(&CONNECTION_REGISTRY, "query_with_blob_refs conn_map")?;
+        conn_map
+            .get(conn_id)
+            .cloned()
+            .ok_or_else(|| rustler::Error::Term(Box::new("Invalid connection ID")))?
+    }; // Lock dropped here
+
+    let params: Vec<Value> = args
+        .into_iter()
+        .map(|t| decode_term_to_value(t))
+        .collect::<Result<_, _>>()
+        .map_err(|e| rustler::Error::Term(Box::new(e)))?;
+
+    let (connection, column_naming) = {
+        let client_guard = safe_lock_arc(&client, "query_with_blob_refs client")?;
+        count_statements(conn_id, 1);
+        (client_guard.client.clone(), client_guard.column_naming)
+    }; // Outer lock dropped here
+
+    // SAFETY: We're inside TOKIO_RUNTIME.block_on(), so this is synchronous execution.
+    // The std::sync::Mutex guards are safe to hold across await points here because
+    // we're not in a true async context - block_on runs the future to completion.
+    #[allow(clippy::await_holding_lock)]
+    let (columns, rows) = TOKIO_RUNTIME.block_on(async {
+        let conn_guard = safe_lock_arc(&connection, "query_with_blob_refs conn")?;
+
+        query_blob_ref_rows(&conn_guard, sql, params, column_naming)
+            .await
+            .map_err(|e| rustler::Error::Term(Box::new(e)))
+    })?;
+
+    let mut encoded_rows: Vec<Term<'a>> = Vec::with_capacity(rows.len());
+    for row in &rows {
+        let mut row_terms = Vec::with_capacity(row.len());
+        for cell in row {
+            row_terms.push(match cell {
+                LazyCell::BlobRef {
+                    rowid,
+                    column,
+                    size,
+                    schema,
+                } => (blob_ref(), *rowid, column, *size, schema).encode(env),
+                LazyCell::Value(value) => encode_value(env, value).ok_or_else(|| {
+                    rustler::Error::Term(Box::new("Failed to allocate binary for blob"))
+                })?,
+            });
+        }
+        encoded_rows.push(row_terms.encode(env));
+    }
+
+    let mut result_map: HashMap<String, Term<'a>> = HashMap::with_capacity(3);
+    result_map.insert("columns".to_string(), columns.encode(env));
+    result_map.insert("rows".to_string(), encoded_rows.encode(env));
+    result_map.insert("num_rows".to_string(), (rows.len() as u64).encode(env));
+
+    Ok(result_map.encode(env))
+}
+
+/// Fetch the bytes behind a `{:blob_ref, rowid, column, size, schema}` marker.
+///
+/// # Arguments
+/// - `conn_id`: Database connection ID
+/// - `rowid`: Rowid from the marker
+/// - `column`: Column from the marker (quoted internally)
+/// - `table`: Table the blob was selected from (quoted internally)
+/// - `schema`: Schema from the marker (quoted internally)
+///
+/// # Returns
+/// - The blob's bytes
+/// - `{:error, reason}` - Row not found, or the value is no longer a blob
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn fetch_blob_ref<'a>(
+    env: Env<'a>,
+    conn_id: &str,
+    rowid: i64,
+    column: &str,
+    table: &str,
+    schema: &str,
+) -> NifResult<Binary<'a>> {
+    let client = {
+        let conn_map = safe_lock(&CONNECTION_REGISTRY, "fetch_blob_ref conn_map")?;
+        conn_map
+            .get(conn_id)
+            .cloned()
+            .ok_or_else(|| rustler::Error::Term(Box::new("Invalid connection ID")))?
+    }; // Lock dropped here
+
+    let connection = {
+        let client_guard = safe_lock_arc(&client, "fetch_blob_ref client")?;
+        count_statements(conn_id, 1);
+        client_guard.client.clone()
+    }; // Outer lock dropped here
+
+    // SAFETY: We're inside TOKIO_RUNTIME.block_on(), so this is synchronous execution.
+    // The std::sync::Mutex guards are safe to hold across await points here because
+    // we're not in a true async context - block_on runs the future to completion.
+    #[allow(clippy::await_holding_lock)]
+    let bytes = TOKIO_RUNTIME.block_on(async {
+        let conn_guard = safe_lock_arc(&connection, "fetch_blob_ref conn")?;
+
+        read_blob(&conn_guard, schema, table, column, rowid)
+            .await
+            .map_err(|e| rustler::Error::Term(Box::new(e)))
+    })?;
+
+    let mut owned = OwnedBinary::new(bytes.len())
+        .ok_or_else(|| rustler::Error::Term(Box::new("Failed to allocate binary for blob")))?;
+    owned.as_mut_slice().copy_from_slice(&bytes);
+    Ok(Binary::from_owned(owned, env))
+}
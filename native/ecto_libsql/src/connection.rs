@@ -4,14 +4,15 @@
 /// and connection state management including cleanup and timeouts.
 use crate::constants::*;
 use crate::decode;
-use crate::models::{LibSQLConn, Mode};
+use crate::models::{ColumnNaming, DatabasePath, ErrorClass, ErrorCounts, LibSQLConn, Mode};
 use crate::utils::safe_lock_arc;
 use bytes::Bytes;
 use libsql::{Builder, Cipher, EncryptionConfig, EncryptionContext, EncryptionKey};
-use rustler::{Atom, NifResult, Term};
+use rustler::{Atom, Binary, Encoder, Env, NifResult, OwnedBinary, Term};
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use uuid::Uuid;
 
 /// Establish a database connection to a local, remote, or remote replica database.
@@ -27,17 +28,24 @@ use uuid::Uuid;
 /// - `auth_token` - Authentication token (required for `remote`/`remote_replica` modes)
 /// - `encryption_key` - Optional local encryption key for local database encryption at rest (`local`/`remote_replica` modes)
 /// - `remote_encryption_key` - Optional remote encryption key for Turso encrypted databases (`remote`/`remote_replica` modes)
+/// - `vfs` - Optional name of a registered SQLite VFS to open the database with (`local` mode only)
+/// - `diagnostics` - When `true`, also return how long each phase of connecting took
+/// - `column_naming` - How duplicate result column names are disambiguated: `:raw` (default),
+///   `:index_suffix` or `:table_prefix`
 ///
 /// **Encryption Support**:
 /// - **Local encryption**: Uses AES-256-CBC for local database files (via `encryption_key`)
 /// - **Remote encryption**: Sends encryption key with each request to Turso (via `remote_encryption_key`)
 /// - **Remote replica**: Supports both local and remote encryption simultaneously
 ///
-/// Returns the connection ID as a string on success, or an error on failure.
+/// Returns the connection ID as a string on success, or an error on failure. With
+/// `diagnostics: true` it returns `{conn_id, timings}` instead, where `timings` is a map of
+/// `build_us` (building the database handle), `connect_us` (opening the connection) and
+/// `ping_us` (verifying remote connections; `nil` for local) in microseconds.
 ///
 /// **Timeouts**: Connection establishment has a 30-second timeout to prevent hanging.
 #[rustler::nif(schedule = "DirtyIo")]
-pub fn connect(opts: Term, mode: Term) -> NifResult<String> {
+pub fn connect<'a>(env: Env<'a>, opts: Term<'a>, mode: Term<'a>) -> NifResult<Term<'a>> {
     let list: Vec<Term> = opts
         .decode()
         .map_err(|e| rustler::Error::Term(Box::new(format!("decode failed: {e:?}"))))?;
@@ -62,9 +70,25 @@ pub fn connect(opts: Term, mode: Term) -> NifResult<String> {
     let remote_encryption_key = map
         .get("remote_encryption_key")
         .and_then(|t| t.decode::<String>().ok());
+    let vfs = map.get("vfs").and_then(|t| t.decode::<String>().ok());
+    let shared_memory = map
+        .get("shared_memory")
+        .and_then(|t| t.decode::<String>().ok());
+    let diagnostics = map
+        .get("diagnostics")
+        .and_then(|t| t.decode::<bool>().ok())
+        .unwrap_or(false);
+    let column_naming = match map.get("column_naming") {
+        Some(term) => term
+            .decode::<Atom>()
+            .ok()
+            .and_then(decode::decode_column_naming)
+            .ok_or_else(|| rustler::Error::Term(Box::new(INVALID_COLUMN_NAMING)))?,
+        None => ColumnNaming::default(),
+    };
 
     // Wrap the entire connection process with a timeout using the global runtime.
-    TOKIO_RUNTIME.block_on(async {
+    let (conn_id, timings) = TOKIO_RUNTIME.block_on(async {
         let timeout = Duration::from_secs(DEFAULT_SYNC_TIMEOUT_SECS);
 
         tokio::time::timeout(timeout, async {
@@ -75,6 +99,36 @@ pub fn connect(opts: Term, mode: Term) -> NifResult<String> {
             let mode_enum = decode::decode_mode(mode_atom)
                 .ok_or_else(|| rustler::Error::Term(Box::new("Unknown mode")))?;
 
+            if vfs.is_some() && mode_enum != Mode::Local {
+                return Err(rustler::Error::Term(Box::new(
+                    "The vfs option is only supported for local connections",
+                )));
+            }
+
+            // A named shared-cache in-memory database stands in for the local file
+            let dbname = match (shared_memory.as_deref(), mode_enum) {
+                (None, _) => dbname,
+                (Some(_), Mode::Remote | Mode::RemoteReplica) => {
+                    return Err(rustler::Error::Term(Box::new(
+                        "The shared_memory option is only supported for local connections",
+                    )));
+                }
+                (Some(_), Mode::Local) if dbname.is_some() || vfs.is_some() => {
+                    return Err(rustler::Error::Term(Box::new(
+                        "The shared_memory option cannot be combined with database or vfs",
+                    )));
+                }
+                (Some(name), Mode::Local) => Some(shared_memory_uri(name)),
+            };
+
+            // Remember where this connection points, for per-path lookups
+            let path = match mode_enum {
+                Mode::Remote => url.clone(),
+                Mode::Local | Mode::RemoteReplica => dbname.clone(),
+            }
+            .unwrap_or_default();
+
+            let build_started = Instant::now();
             let db = match mode_enum {
                 Mode::RemoteReplica => {
                     let url = url.ok_or_else(|| rustler::Error::BadArg)?;
@@ -121,6 +175,13 @@ pub fn connect(opts: Term, mode: Term) -> NifResult<String> {
                 Mode::Local => {
                     let dbname = dbname.ok_or_else(|| rustler::Error::BadArg)?;
 
+                    // libsql has no VFS setting, but hands the path straight to SQLite,
+                    // which is built with URI filename support.
+                    let dbname = match &vfs {
+                        Some(vfs) => local_uri_with_vfs(&dbname, vfs),
+                        None => dbname,
+                    };
+
                     let mut builder = Builder::new_local(dbname);
 
                     if let Some(key) = encryption_key {
@@ -135,31 +196,62 @@ pub fn connect(opts: Term, mode: Term) -> NifResult<String> {
                 }
             }
             .map_err(|e| rustler::Error::Term(Box::new(format!("Failed to build DB: {e}"))))?;
+            let build_us = elapsed_us(build_started);
 
-            let conn = db
-                .connect()
-                .map_err(|e| rustler::Error::Term(Box::new(format!("Failed to connect: {e}"))))?;
+            let connect_started = Instant::now();
+
+            let conn = db.connect().map_err(|e| match &vfs {
+                // SQLite only reports a generic error code for an unknown VFS
+                Some(vfs) => rustler::Error::Term(Box::new(format!(
+                    "Failed to connect using VFS \"{vfs}\" (is it registered?): {e}"
+                ))),
+                None => rustler::Error::Term(Box::new(format!("Failed to connect: {e}"))),
+            })?;
+
+            let connect_us = elapsed_us(connect_started);
 
             // Ping remote connections to verify they're accessible
-            if mode_enum != Mode::Local {
+            let ping_us = if mode_enum == Mode::Local {
+                None
+            } else {
+                let ping_started = Instant::now();
                 conn.query("SELECT 1", ())
                     .await
                     .map_err(|e| rustler::Error::Term(Box::new(format!("Failed ping: {e}"))))?;
-            }
+                Some(elapsed_us(ping_started))
+            };
 
             let libsql_conn = Arc::new(Mutex::new(LibSQLConn {
                 db,
                 client: Arc::new(Mutex::new(conn)),
+                mode: mode_enum,
+                column_naming,
             }));
 
             let conn_id = Uuid::new_v4().to_string();
+            crate::utils::safe_lock(&ERROR_COUNT_REGISTRY, "connect error_counts")
+                .map_err(|e| {
+                    rustler::Error::Term(Box::new(format!("Failed to register connection: {e:?}")))
+                })?
+                .insert(conn_id.clone(), Arc::new(ErrorCounts::default()));
+            crate::utils::safe_lock(&STATEMENT_COUNT_REGISTRY, "connect statement_counts")
+                .map_err(|e| {
+                    rustler::Error::Term(Box::new(format!("Failed to register connection: {e:?}")))
+                })?
+                .insert(conn_id.clone(), Arc::new(AtomicU64::new(0)));
+            // Canonicalised now the database file exists
+            crate::utils::safe_lock(&CONNECTION_PATH_REGISTRY, "connect conn_paths")
+                .map_err(|e| {
+                    rustler::Error::Term(Box::new(format!("Failed to register connection: {e:?}")))
+                })?
+                .insert(conn_id.clone(), DatabasePath::new(&path));
             crate::utils::safe_lock(&CONNECTION_REGISTRY, "connect conn_registry")
                 .map_err(|e| {
                     rustler::Error::Term(Box::new(format!("Failed to register connection: {e:?}")))
                 })?
                 .insert(conn_id.clone(), libsql_conn);
 
-            Ok(conn_id)
+            Ok((conn_id, (build_us, connect_us, ping_us)))
         })
         .await
         .map_err(|_| {
@@ -167,15 +259,360 @@ pub fn connect(opts: Term, mode: Term) -> NifResult<String> {
                 "Connection timeout after {DEFAULT_SYNC_TIMEOUT_SECS} seconds"
             )))
         })?
-    })
+    })?;
+
+    if !diagnostics {
+        return Ok(conn_id.encode(env));
+    }
+
+    let (build_us, connect_us, ping_us) = timings;
+    let timings = Term::map_from_pairs(
+        env,
+        &[
+            (build_us_key().encode(env), build_us.encode(env)),
+            (connect_us_key().encode(env), connect_us.encode(env)),
+            (ping_us_key().encode(env), ping_us.encode(env)),
+        ],
+    )?;
+
+    Ok((conn_id, timings).encode(env))
+}
+
+const INVALID_COLUMN_NAMING: &str =
+    "Invalid column_naming; expected :raw, :index_suffix or :table_prefix";
+
+/// Microseconds elapsed since `started`, saturating rather than overflowing.
+fn elapsed_us(started: Instant) -> u64 {
+    u64::try_from(started.elapsed().as_micros()).unwrap_or(u64::MAX)
+}
+
+/// Every option key `EctoLibSql.connect/1` understands, whether read here or applied by the
+/// Elixir side after connecting.
+pub const CONNECT_OPTION_KEYS: &[&str] = &[
+    "database",
+    "uri",
+    "auth_token",
+    "sync",
+    "encryption_key",
+    "remote_encryption_key",
+    "vfs",
+    "shared_memory",
+    "diagnostics",
+    "column_naming",
+    "busy_timeout",
+    "mmap_size",
+    "secure_delete",
+    "temp_store",
+    "synchronous",
+];
+
+/// Why a connection options list was rejected.
+#[derive(Debug, PartialEq, Eq)]
+pub enum ConnectOptionError {
+    /// A key `connect` does not understand, most likely a typo
+    Unknown(String),
+    /// A key `mode` cannot connect without
+    Missing(&'static str),
+}
+
+/// Check the option `keys` given for a connection in `mode`.
+///
+/// Unknown keys are reported before missing ones, in the order given, since a typo'd key
+/// usually explains the missing one. Local connections need `database` or `shared_memory`;
+/// remote connections need `uri` and `auth_token`, and replicas `database` as well.
+pub fn check_connect_options(keys: &[&str], mode: Mode) -> Result<(), ConnectOptionError> {
+    if let Some(key) = keys.iter().find(|key| !CONNECT_OPTION_KEYS.contains(key)) {
+        return Err(ConnectOptionError::Unknown((*key).to_string()));
+    }
+
+    let required: &[&'static str] = match mode {
+        Mode::Local if keys.contains(&"shared_memory") => &[],
+        Mode::Local => &["database"],
+        Mode::Remote => &["uri", "auth_token"],
+        Mode::RemoteReplica => &["database", "uri", "auth_token"],
+    };
+
+    match required.iter().find(|key| !keys.contains(key)) {
+        Some(key) => Err(ConnectOptionError::Missing(key)),
+        None => Ok(()),
+    }
+}
+
+/// Validate and normalise a connection options keyword list before connecting.
+///
+/// `connect` only looks up the keys it knows, so a typo such as `auth_tokne` otherwise
+/// surfaces as a confusing authentication failure. Options set to `nil` count as absent.
+///
+/// # Returns
+/// - `{:ok, opts}` - The options with `nil` values dropped and repeated keys reduced to
+///   their first occurrence, matching `Keyword.get/2`
+/// - `{:error, {:unknown_option, key}}` - `key` is not a connection option
+/// - `{:error, {:missing_option, key}}` - `mode` requires `key`
+#[rustler::nif]
+pub fn validate_connect_opts<'a>(
+    env: Env<'a>,
+    opts: Vec<(Atom, Term<'a>)>,
+    mode: Atom,
+) -> NifResult<Term<'a>> {
+    let mode =
+        decode::decode_mode(mode).ok_or_else(|| rustler::Error::Term(Box::new("Unknown mode")))?;
+
+    let mut names: Vec<String> = Vec::with_capacity(opts.len());
+    let mut normalised: Vec<(Atom, Term<'a>)> = Vec::with_capacity(opts.len());
+    for (key, value) in opts {
+        let name = format!("{key:?}");
+        if value.decode::<Atom>().is_ok_and(|atom| atom == nil()) || names.contains(&name) {
+            continue;
+        }
+        names.push(name);
+        normalised.push((key, value));
+    }
+
+    let keys: Vec<&str> = names.iter().map(String::as_str).collect();
+    let (reason, key) = match check_connect_options(&keys, mode) {
+        Ok(()) => return Ok((ok(), normalised).encode(env)),
+        Err(ConnectOptionError::Unknown(key)) => (unknown_option(), key),
+        Err(ConnectOptionError::Missing(key)) => (missing_option(), key.to_string()),
+    };
+    let key = Atom::from_str(env, &key)?;
+    Ok((error(), (reason, key)).encode(env))
+}
+
+/// Percent-encode `%` and the `reserved` characters for use in an SQLite URI filename.
+fn encode_uri_part(value: &str, reserved: &[char]) -> String {
+    value
+        .chars()
+        .map(|c| {
+            if c == '%' || reserved.contains(&c) {
+                format!("%{:02X}", c as u32)
+            } else {
+                c.to_string()
+            }
+        })
+        .collect()
+}
+
+/// Build an SQLite URI filename that opens `path` with the named VFS.
+///
+/// Characters with special meaning in URI filenames are percent-encoded so that
+/// arbitrary paths and VFS names survive SQLite's URI parsing.
+pub fn local_uri_with_vfs(path: &str, vfs: &str) -> String {
+    format!(
+        "file:{}?vfs={}",
+        encode_uri_part(path, &['?', '#']),
+        encode_uri_part(vfs, &['?', '#', '&', '='])
+    )
+}
+
+/// Name of SQLite's default VFS on this platform, for paths that must reach the disk
+/// even from a connection using another VFS.
+const DEFAULT_VFS: &str = if cfg!(windows) { "win32" } else { "unix" };
+
+/// Leading bytes of every SQLite database file.
+const SQLITE_HEADER: &[u8] = b"SQLite format 3\0";
+
+/// A scratch database file in the system temp directory, removed again on drop.
+struct ScratchFile(std::path::PathBuf);
+
+impl ScratchFile {
+    fn new() -> Self {
+        Self(std::env::temp_dir().join(format!("ecto_libsql_{}.db", Uuid::new_v4())))
+    }
+
+    fn path(&self) -> Result<&str, String> {
+        self.0
+            .to_str()
+            .ok_or_else(|| "Temporary directory path is not valid UTF-8".to_string())
+    }
+}
+
+impl Drop for ScratchFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+        // Bytes of a WAL-mode database open the scratch file in WAL mode, adding `-wal` and `-shm`
+        for suffix in ["db-journal", "db-wal", "db-shm"] {
+            let _ = std::fs::remove_file(self.0.with_extension(suffix));
+        }
+    }
+}
+
+/// Open a private in-memory database holding a copy of the SQLite database in `bytes`.
+///
+/// The database lives in SQLite's `memdb` VFS under a unique name, so nothing else can
+/// open it, and is freed when its last connection closes. The bytes pass through a
+/// scratch file (removed before returning), as copying them straight into memory
+/// would need SQLite's deserialise API, which is only reachable through raw FFI.
+///
+/// Returns the database, its connection, and the URI it was opened with.
+pub async fn open_from_bytes(
+    bytes: &[u8],
+) -> Result<(libsql::Database, libsql::Connection, String), String> {
+    if !bytes.starts_with(SQLITE_HEADER) {
+        return Err("Not an SQLite database: missing file header".to_string());
+    }
+
+    let uri = local_uri_with_vfs(&format!("/ecto_libsql_{}", Uuid::new_v4()), "memdb");
+    let db = Builder::new_local(&uri)
+        .build()
+        .await
+        .map_err(|e| format!("Failed to open in-memory database: {e}"))?;
+    // Must stay open while the copy is written, or the in-memory database is freed
+    let conn = db
+        .connect()
+        .map_err(|e| format!("Failed to open in-memory database: {e}"))?;
+
+    let scratch = ScratchFile::new();
+    std::fs::write(&scratch.0, bytes).map_err(|e| format!("Failed to stage database: {e}"))?;
+    {
+        let source = Builder::new_local(scratch.path()?)
+            .build()
+            .await
+            .map_err(|e| format!("Failed to read database: {e}"))?;
+        let source_conn = source
+            .connect()
+            .map_err(|e| format!("Failed to read database: {e}"))?;
+        source_conn
+            .execute("VACUUM INTO ?1", [uri.as_str()])
+            .await
+            .map_err(|e| format!("Failed to load database: {e}"))?;
+    }
+
+    Ok((db, conn, uri))
+}
+
+/// Copy the main database of `conn` into a byte vector, as it would be stored on disk.
+///
+/// Works for in-memory and file databases alike. The copy is taken with `VACUUM INTO`,
+/// so it is a consistent snapshot, compacted, and in rollback journal mode. Committed
+/// changes still in a WAL file are included. It cannot be taken while `conn` has a
+/// transaction open, as the snapshot would not include the uncommitted writes.
+pub async fn serialize_connection(conn: &libsql::Connection) -> Result<Vec<u8>, String> {
+    if !conn.is_autocommit() {
+        return Err("Cannot serialise a database while a transaction is open".to_string());
+    }
+
+    let scratch = ScratchFile::new();
+    // Name the default VFS explicitly: `VACUUM INTO` otherwise uses the connection's own
+    // VFS, which for `memdb` databases would keep the copy in memory
+    let target = local_uri_with_vfs(scratch.path()?, DEFAULT_VFS);
+    conn.execute("VACUUM INTO ?1", [target.as_str()])
+        .await
+        .map_err(|e| format!("Failed to serialise database: {e}"))?;
+    std::fs::read(&scratch.0).map_err(|e| format!("Failed to read serialised database: {e}"))
+}
+
+/// Check that the database file at `path` opens and passes `PRAGMA integrity_check`.
+///
+/// The file is opened read-only through a temporary connection, which is closed again
+/// before returning whatever the outcome. Returns the schema version on success, or
+/// every problem found: a missing or non-SQLite file, an open or query failure, or
+/// each line reported by the integrity check.
+pub async fn verify_backup_file(path: &str) -> Result<i64, Vec<String>> {
+    let mut header = [0u8; 16];
+    std::fs::File::open(path)
+        .and_then(|mut file| std::io::Read::read_exact(&mut file, &mut header))
+        .map_err(|e| vec![format!("Failed to read backup: {e}")])?;
+    if header != SQLITE_HEADER {
+        return Err(vec![
+            "Not an SQLite database: missing file header".to_string()
+        ]);
+    }
+
+    // Read-only, so checking the backup can never create or modify it
+    let uri = format!("{}&mode=ro", local_uri_with_vfs(path, DEFAULT_VFS));
+    let db = Builder::new_local(&uri)
+        .build()
+        .await
+        .map_err(|e| vec![format!("Failed to open backup: {e}")])?;
+    let conn = db
+        .connect()
+        .map_err(|e| vec![format!("Failed to open backup: {e}")])?;
+
+    let mut problems = Vec::new();
+    let mut rows = conn
+        .query("PRAGMA integrity_check", ())
+        .await
+        .map_err(|e| vec![format!("Integrity check failed: {e}")])?;
+    while let Some(row) = rows
+        .next()
+        .await
+        .map_err(|e| vec![format!("Integrity check failed: {e}")])?
+    {
+        let line: String = row
+            .get(0)
+            .map_err(|e| vec![format!("Integrity check failed: {e}")])?;
+        if line != "ok" {
+            problems.push(line);
+        }
+    }
+    if !problems.is_empty() {
+        return Err(problems);
+    }
+
+    let mut rows = conn
+        .query("PRAGMA schema_version", ())
+        .await
+        .map_err(|e| vec![format!("Failed to read schema version: {e}")])?;
+    match rows.next().await {
+        Ok(Some(row)) => row
+            .get::<i64>(0)
+            .map_err(|e| vec![format!("Failed to read schema version: {e}")]),
+        Ok(None) => Err(vec!["Failed to read schema version".to_string()]),
+        Err(e) => Err(vec![format!("Failed to read schema version: {e}")]),
+    }
+}
+
+/// Build an SQLite URI filename for a named in-memory database in shared-cache mode.
+///
+/// Every connection in the process opened with the same `name` sees the same
+/// database, which lives until the last of those connections closes.
+pub fn shared_memory_uri(name: &str) -> String {
+    format!(
+        "file:{}?mode=memory&cache=shared",
+        encode_uri_part(name, &['?', '#'])
+    )
+}
+
+/// Ids of all registered connections opened against `path`, sorted for stable output.
+///
+/// `path` is canonicalised once and compared with the paths recorded at connect time, so
+/// no connection is locked and the filesystem is only touched for `path` itself.
+pub fn connection_ids_for_path(path: &str) -> Result<Vec<String>, rustler::Error> {
+    let wanted = DatabasePath::new(path);
+    let paths = crate::utils::safe_lock(&CONNECTION_PATH_REGISTRY, "connections_for_path paths")?;
+    let mut ids: Vec<String> = paths
+        .iter()
+        .filter(|(_, recorded)| recorded.matches(&wanted))
+        .map(|(id, _)| id.clone())
+        .collect();
+    ids.sort();
+    Ok(ids)
+}
+
+/// List the open connections pointing at a database path.
+///
+/// Scans the connection registry for connections opened against `path` (the
+/// database file for local and replica connections, the URL for remote ones).
+/// Useful for spotting leaks or pool misconfigurations that open duplicate
+/// connections to the same file.
+///
+/// # Returns
+/// - List of connection ids
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn connections_for_path(path: &str) -> NifResult<Vec<String>> {
+    connection_ids_for_path(path)
 }
 
 /// Check if a database connection is alive and responsive.
 ///
 /// Performs a simple `SELECT 1` query to verify the connection is working.
-/// Returns `true` if the connection is healthy, error otherwise.
+/// Returns `true` if the connection is healthy, error otherwise, including for a
+/// connection marked broken (see `mark_connection_broken`).
 #[rustler::nif(schedule = "DirtyIo")]
 pub fn ping(conn_id: &str) -> NifResult<bool> {
+    crate::utils::check_not_broken(conn_id)
+        .map_err(|e| rustler::Error::Term(Box::new(format!("Ping error: {e}"))))?;
+
     let conn_map = crate::utils::safe_lock(&CONNECTION_REGISTRY, "ping conn_map")?;
 
     let maybe_conn = conn_map.get(conn_id);
@@ -207,39 +644,79 @@ pub fn ping(conn_id: &str) -> NifResult<bool> {
     }
 }
 
+/// Remove the prepared statements and cursors owned by a connection.
+///
+/// A closed connection's statements and cursors can no longer be used, so `close`
+/// sweeps them rather than leaving dangling registry entries behind. Returns the
+/// number of entries removed.
+pub fn sweep_connection_resources(conn_id: &str) -> NifResult<usize> {
+    let mut removed = 0;
+
+    let mut statements = crate::utils::safe_lock(&STMT_REGISTRY, "sweep stmt_registry")?;
+    let before = statements.len();
+    statements.retain(|_, (owner, _, _)| owner != conn_id);
+    removed += before - statements.len();
+    drop(statements);
+
+    let mut cursors = crate::utils::safe_lock(&CURSOR_REGISTRY, "sweep cursor_registry")?;
+    let before = cursors.len();
+    cursors.retain(|_, cursor| cursor.conn_id != conn_id);
+    removed += before - cursors.len();
+
+    Ok(removed)
+}
+
 /// Close a resource (connection, transaction, statement, or cursor).
 ///
 /// The `opt` parameter specifies which type of resource to close:
-/// - `:conn_id` - Close a database connection
+/// - `:conn_id` - Close a database connection, along with its prepared statements and
+///   cursors (see `sweep_connection_resources`)
 /// - `:trx_id` - Close/forget a transaction
 /// - `:stmt_id` - Close a prepared statement
 /// - `:cursor_id` - Close a cursor
 ///
-/// Returns `:ok` on success, error if the resource ID is not found.
+/// # Returns
+/// - `{:ok, swept}` - For `:conn_id`, with the number of statements and cursors removed
+/// - `:ok` - For other resources
+/// - `{:error, reason}` - The resource ID is not found
 #[rustler::nif(schedule = "DirtyIo")]
-pub fn close(id: &str, opt: Atom) -> NifResult<Atom> {
+pub fn close<'a>(env: Env<'a>, id: &str, opt: Atom) -> NifResult<Term<'a>> {
     if opt == conn_id() {
         let removed = crate::utils::safe_lock(&CONNECTION_REGISTRY, "close conn")?.remove(id);
+        crate::utils::safe_lock(&LAST_ERROR_REGISTRY, "close last_error")?.remove(id);
+        crate::utils::safe_lock(&ERROR_COUNT_REGISTRY, "close error_counts")?.remove(id);
+        crate::utils::safe_lock(&CONNECTION_PATH_REGISTRY, "close conn_paths")?.remove(id);
+        crate::utils::safe_lock(&STATEMENT_COUNT_REGISTRY, "close statement_counts")?.remove(id);
+        crate::utils::safe_lock(&DIRTY_TABLE_REGISTRY, "close dirty_tables")?.remove(id);
+        crate::utils::safe_lock(&BROKEN_CONNECTION_REGISTRY, "close broken")?.remove(id);
         match removed {
-            Some(_) => Ok(rustler::types::atom::ok()),
+            Some(_) => {
+                let swept = sweep_connection_resources(id)?;
+                Ok((rustler::types::atom::ok(), swept).encode(env))
+            }
             None => Err(rustler::Error::Term(Box::new("Connection not found"))),
         }
     } else if opt == trx_id() {
         let removed = crate::utils::safe_lock(&TXN_REGISTRY, "close trx")?.remove(id);
         match removed {
-            Some(_) => Ok(rustler::types::atom::ok()),
+            // Dropping the entry rolls the transaction back
+            Some(entry) => {
+                crate::transaction::restore_busy_timeout(&entry)
+                    .map_err(|e| rustler::Error::Term(Box::new(e)))?;
+                Ok(rustler::types::atom::ok().encode(env))
+            }
             None => Err(rustler::Error::Term(Box::new("Transaction not found"))),
         }
     } else if opt == stmt_id() {
         let removed = crate::utils::safe_lock(&STMT_REGISTRY, "close stmt")?.remove(id);
         match removed {
-            Some(_) => Ok(rustler::types::atom::ok()),
+            Some(_) => Ok(rustler::types::atom::ok().encode(env)),
             None => Err(rustler::Error::Term(Box::new("Statement not found"))),
         }
     } else if opt == cursor_id() {
         let removed = crate::utils::safe_lock(&CURSOR_REGISTRY, "close cursor")?.remove(id);
         match removed {
-            Some(_) => Ok(rustler::types::atom::ok()),
+            Some(_) => Ok(rustler::types::atom::ok().encode(env)),
             None => Err(rustler::Error::Term(Box::new("Cursor not found"))),
         }
     } else {
@@ -285,6 +762,83 @@ pub fn set_busy_timeout(conn_id: &str, timeout_ms: u64) -> NifResult<Atom> {
     }
 }
 
+/// Set how duplicate result column names are disambiguated for a connection.
+///
+/// Applies to subsequent queries and prepared statement queries on the connection.
+///
+/// # Arguments
+/// - `conn_id`: Database connection ID
+/// - `naming`: `:raw`, `:index_suffix` or `:table_prefix`
+///
+/// Returns `:ok` on success, error on an unknown strategy or connection.
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn set_column_naming(conn_id: &str, naming: Atom) -> NifResult<Atom> {
+    let naming = decode::decode_column_naming(naming)
+        .ok_or_else(|| rustler::Error::Term(Box::new(INVALID_COLUMN_NAMING)))?;
+
+    let client = {
+        let conn_map = crate::utils::safe_lock(&CONNECTION_REGISTRY, "set_column_naming conn_map")?;
+        conn_map
+            .get(conn_id)
+            .cloned()
+            .ok_or_else(|| rustler::Error::Term(Box::new("Invalid connection ID")))?
+    }; // Lock dropped here
+
+    safe_lock_arc(&client, "set_column_naming client")?.column_naming = naming;
+    Ok(rustler::types::atom::ok())
+}
+
+/// Ask SQLite to free as much of a connection's heap memory as it can.
+///
+/// Runs `PRAGMA shrink_memory`, which calls `sqlite3_db_release_memory` and releases
+/// unused page cache memory. Pages in use by an open transaction or statement stay.
+pub async fn release_memory(conn: &libsql::Connection) -> Result<(), libsql::Error> {
+    conn.execute("PRAGMA shrink_memory", ()).await.map(|_| ())
+}
+
+/// Release a connection's unused cache memory.
+///
+/// Useful after a heavy query, or for pooled connections going idle, to keep the
+/// node's memory lean. The cache refills as the connection is used again, so shrinking
+/// a busy connection only costs it reads. See `release_memory`.
+///
+/// # Arguments
+/// - `conn_id`: Database connection ID
+///
+/// # Returns
+/// - `:ok` - Memory released
+/// - `{:error, reason}` - Query failure
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn shrink_memory(conn_id: &str) -> NifResult<Atom> {
+    let client = {
+        let conn_map = crate::utils::safe_lock(&CONNECTION_REGISTRY, "shrink_memory conn_map")?;
+        conn_map
+            .get(conn_id)
+            .cloned()
+            .ok_or_else(|| rustler::Error::Term(Box::new("Invalid connection ID")))?
+    }; // Lock dropped here
+
+    let connection = {
+        let client_guard = safe_lock_arc(&client, "shrink_memory client")?;
+        client_guard.client.clone()
+    }; // Outer lock dropped here
+
+    // SAFETY: We're inside TOKIO_RUNTIME.block_on(), so this is synchronous execution.
+    // The std::sync::Mutex guards are safe to hold across await points here because
+    // we're not in a true async context - block_on runs the future to completion.
+    #[allow(clippy::await_holding_lock)]
+    TOKIO_RUNTIME.block_on(async {
+        let conn_guard = safe_lock_arc(&connection, "shrink_memory conn")?;
+        release_memory(&conn_guard)
+            .await
+            .map(|_| rustler::types::atom::ok())
+            .map_err(|e| {
+                crate::utils::record_last_error(conn_id, &e);
+                rustler::Error::Term(Box::new(format!("Failed to shrink memory: {e}")))
+            })
+    })
+}
+
 /// Reset the connection state to a clean state.
 ///
 /// This clears any prepared statements and resets the connection to a clean state.
@@ -292,10 +846,11 @@ pub fn set_busy_timeout(conn_id: &str, timeout_ms: u64) -> NifResult<Atom> {
 ///
 /// # Arguments
 /// - `conn_id`: Database connection ID
+/// - `shrink`: Also release unused cache memory afterwards, as `shrink_memory` does
 ///
 /// Returns `:ok` on success, error on failure.
 #[rustler::nif(schedule = "DirtyIo")]
-pub fn reset_connection(conn_id: &str) -> NifResult<Atom> {
+pub fn reset_connection(conn_id: &str, shrink: bool) -> NifResult<Atom> {
     let conn_map = crate::utils::safe_lock(&CONNECTION_REGISTRY, "reset_connection conn_map")?;
 
     if let Some(client) = conn_map.get(conn_id) {
@@ -313,6 +868,12 @@ pub fn reset_connection(conn_id: &str) -> NifResult<Atom> {
                     safe_lock_arc(&client_guard.client, "reset_connection conn")?;
 
                 conn_guard.reset().await;
+                if shrink {
+                    release_memory(&conn_guard).await.map_err(|e| {
+                        crate::utils::record_last_error(conn_id, &e);
+                        rustler::Error::Term(Box::new(format!("Failed to shrink memory: {e}")))
+                    })?;
+                }
                 Ok::<(), rustler::Error>(())
             })?;
         }
@@ -323,6 +884,114 @@ pub fn reset_connection(conn_id: &str) -> NifResult<Atom> {
     }
 }
 
+/// Open a connection to an in-memory copy of an SQLite database given as bytes.
+///
+/// Useful for fixture-based tests: each call gets its own private copy, so tests can
+/// write freely without touching the fixture or each other. Writes stay in memory and
+/// are discarded when the connection closes; use `serialize_database` to save them.
+///
+/// # Arguments
+/// - `bytes`: Contents of an SQLite database file
+///
+/// # Returns
+/// - The connection ID
+/// - `{:error, reason}` - The bytes are not a valid SQLite database
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn connect_from_bytes(bytes: Binary) -> NifResult<String> {
+    let (db, conn, uri) = TOKIO_RUNTIME
+        .block_on(open_from_bytes(bytes.as_slice()))
+        .map_err(|e| rustler::Error::Term(Box::new(e)))?;
+
+    let libsql_conn = Arc::new(Mutex::new(LibSQLConn {
+        db,
+        client: Arc::new(Mutex::new(conn)),
+        mode: Mode::Local,
+        column_naming: ColumnNaming::Raw,
+    }));
+
+    let conn_id = Uuid::new_v4().to_string();
+    crate::utils::safe_lock(&ERROR_COUNT_REGISTRY, "connect_from_bytes error_counts")?
+        .insert(conn_id.clone(), Arc::new(ErrorCounts::default()));
+    crate::utils::safe_lock(
+        &STATEMENT_COUNT_REGISTRY,
+        "connect_from_bytes statement_counts",
+    )?
+    .insert(conn_id.clone(), Arc::new(AtomicU64::new(0)));
+    crate::utils::safe_lock(&CONNECTION_PATH_REGISTRY, "connect_from_bytes conn_paths")?
+        .insert(conn_id.clone(), DatabasePath::new(&uri));
+    crate::utils::safe_lock(&CONNECTION_REGISTRY, "connect_from_bytes conn_registry")?
+        .insert(conn_id.clone(), libsql_conn);
+
+    Ok(conn_id)
+}
+
+/// Return the contents of a connection's main database as an SQLite database file.
+///
+/// The bytes can be written to disk, kept as a fixture, or reopened with
+/// `connect_from_bytes`. Serialising can mean copying the whole database, so this runs
+/// on a dirty scheduler. Not supported for remote connections, nor while the connection
+/// has a transaction open.
+///
+/// # Arguments
+/// - `conn_id`: Database connection ID
+///
+/// # Returns
+/// - The database file contents
+/// - `{:error, :unsupported}` - Remote connection
+/// - `{:error, reason}` - A transaction is open, or the copy failed
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn serialize_database<'a>(env: Env<'a>, conn_id: &str) -> NifResult<Binary<'a>> {
+    let client = crate::utils::safe_lock(&CONNECTION_REGISTRY, "serialize_database conn_map")?
+        .get(conn_id)
+        .cloned()
+        .ok_or_else(|| rustler::Error::Term(Box::new("Invalid connection ID")))?;
+
+    let (connection, mode) = {
+        let client_guard = safe_lock_arc(&client, "serialize_database client")?;
+        (client_guard.client.clone(), client_guard.mode)
+    }; // Outer lock dropped here
+
+    if mode == Mode::Remote {
+        return Err(rustler::Error::Term(Box::new(unsupported())));
+    }
+
+    // SAFETY: We're inside TOKIO_RUNTIME.block_on(), so this is synchronous execution.
+    // The std::sync::Mutex guards are safe to hold across await points here because
+    // we're not in a true async context - block_on runs the future to completion.
+    #[allow(clippy::await_holding_lock)]
+    let bytes = TOKIO_RUNTIME.block_on(async {
+        let conn_guard = safe_lock_arc(&connection, "serialize_database conn")?;
+        serialize_connection(&conn_guard)
+            .await
+            .map_err(|e| rustler::Error::Term(Box::new(e)))
+    })?;
+
+    let mut binary = OwnedBinary::new(bytes.len())
+        .ok_or_else(|| rustler::Error::Term(Box::new("Failed to allocate binary for database")))?;
+    binary.as_mut_slice().copy_from_slice(&bytes);
+    Ok(binary.release(env))
+}
+
+/// Check that a backup file opens and passes an integrity check.
+///
+/// Opens the file read-only through a temporary connection, runs
+/// `PRAGMA integrity_check` and `PRAGMA schema_version`, and closes the connection
+/// again whatever the outcome. Nothing is registered, so no connection ID is needed.
+/// See `verify_backup_file`.
+///
+/// # Arguments
+/// - `path`: Path to the backup database file
+///
+/// # Returns
+/// - The backup's schema version
+/// - `{:error, problems}` - List of problems found, as strings
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn check_backup(path: &str) -> NifResult<i64> {
+    TOKIO_RUNTIME
+        .block_on(verify_backup_file(path))
+        .map_err(|problems| rustler::Error::Term(Box::new(problems)))
+}
+
 /// Interrupt any ongoing operation on a database connection.
 ///
 /// Causes the current operation to return at the earliest opportunity.
@@ -354,6 +1023,86 @@ pub fn interrupt_connection(conn_id: &str) -> NifResult<Atom> {
     }
 }
 
+/// Report how many statements have been run on a connection.
+///
+/// A running total kept since the connection was opened, counting statements run
+/// through `query_args`, transactions, prepared statements and batches (each
+/// statement of a batch counts once). Failed statements are included. Cheaper than
+/// logging every query when only a throughput signal is needed.
+///
+/// # Arguments
+/// - `conn_id`: Database connection ID
+///
+/// # Returns
+/// - The number of statements run
+/// - `{:error, reason}` - Unknown connection
+#[rustler::nif]
+pub fn statement_count(conn_id: &str) -> NifResult<u64> {
+    // Read from the registry rather than the connection, whose lock batches and syncs hold
+    crate::utils::safe_lock(&STATEMENT_COUNT_REGISTRY, "statement_count registry")?
+        .get(conn_id)
+        .map(|total| total.load(Ordering::Relaxed))
+        .ok_or_else(|| rustler::Error::Term(Box::new("Invalid connection ID")))
+}
+
+/// Encode error counts as a map from class atom to count.
+fn encode_error_counts<'a>(env: Env<'a>, count: impl Fn(ErrorClass) -> u64) -> NifResult<Term<'a>> {
+    let mut map = Term::map_new(env);
+    for class in ErrorClass::ALL {
+        let key = match class {
+            ErrorClass::Constraint => constraint(),
+            ErrorClass::Busy => busy(),
+            ErrorClass::Syntax => syntax(),
+            ErrorClass::Other => other(),
+        };
+        map = map.map_put(key.encode(env), count(class).encode(env))?;
+    }
+    Ok(map)
+}
+
+/// Report how many errors of each class have been recorded on a connection.
+///
+/// Counts every error a NIF records as the connection's last error (see `last_error`),
+/// classified by `classify_error`, since the connection was opened or the counts were
+/// last reset. A connection piling up busy errors, for example, may be worth evicting
+/// from a pool.
+///
+/// # Arguments
+/// - `conn_id`: Database connection ID
+///
+/// # Returns
+/// - `%{constraint: n, busy: n, syntax: n, other: n}`
+/// - `{:error, reason}` - Unknown connection
+#[rustler::nif]
+pub fn error_counts<'a>(env: Env<'a>, conn_id: &str) -> NifResult<Term<'a>> {
+    // Read from the registry rather than the connection, whose lock a running query holds
+    let counts = crate::utils::safe_lock(&ERROR_COUNT_REGISTRY, "error_counts registry")?
+        .get(conn_id)
+        .cloned()
+        .ok_or_else(|| rustler::Error::Term(Box::new("Invalid connection ID")))?;
+    encode_error_counts(env, |class| counts.get(class))
+}
+
+/// Reset a connection's error counters to zero.
+///
+/// Each counter is swapped for zero, so errors recorded while resetting are never lost:
+/// they are either in the returned counts or in the next ones.
+///
+/// # Arguments
+/// - `conn_id`: Database connection ID
+///
+/// # Returns
+/// - The counts replaced by the reset, as `error_counts` returns them
+/// - `{:error, reason}` - Unknown connection
+#[rustler::nif]
+pub fn reset_error_counts<'a>(env: Env<'a>, conn_id: &str) -> NifResult<Term<'a>> {
+    let counts = crate::utils::safe_lock(&ERROR_COUNT_REGISTRY, "reset_error_counts registry")?
+        .get(conn_id)
+        .cloned()
+        .ok_or_else(|| rustler::Error::Term(Box::new("Invalid connection ID")))?;
+    encode_error_counts(env, |class| counts.take(class))
+}
+
 /// Enable or disable loading of SQLite extensions.
 ///
 /// By default, extension loading is disabled for security reasons.
@@ -3,14 +3,22 @@
 /// This module holds all static configuration, global registries, and atom definitions
 /// used throughout the codebase.
 use rustler::atoms;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::AtomicU64;
 use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, Instant};
 use tokio::runtime::Runtime;
 
-use crate::models::{CursorData, LibSQLConn, TransactionEntry};
+use crate::models::{
+    BlobBuilder, CursorData, DatabasePath, ErrorCounts, LastError, LibSQLConn, StatementSource,
+    TransactionEntry,
+};
+
+/// Tables written on a connection, shared with the update hook that records them
+pub type DirtyTables = Arc<Mutex<HashSet<String>>>;
 
 /// Type alias to reduce complexity of the statement registry
-type StatementEntry = (String, Arc<Mutex<libsql::Statement>>);
+type StatementEntry = (String, Arc<Mutex<libsql::Statement>>, StatementSource);
 
 /// Global Tokio runtime for async operations
 ///
@@ -45,7 +53,7 @@ pub static TXN_REGISTRY: LazyLock<Mutex<HashMap<String, TransactionEntry>>> =
 
 /// Global registry for prepared statements
 ///
-/// Maps statement ID to (connection_id, cached_statement) tuple.
+/// Maps statement ID to (connection_id, cached_statement, source) tuple.
 pub static STMT_REGISTRY: LazyLock<Mutex<HashMap<String, StatementEntry>>> =
     LazyLock::new(|| Mutex::new(HashMap::new()));
 
@@ -55,6 +63,87 @@ pub static STMT_REGISTRY: LazyLock<Mutex<HashMap<String, StatementEntry>>> =
 pub static CURSOR_REGISTRY: LazyLock<Mutex<HashMap<String, CursorData>>> =
     LazyLock::new(|| Mutex::new(HashMap::new()));
 
+/// Global registry for the most recent error per connection
+///
+/// Maps connection ID to the last `LastError` recorded by a NIF performing database work.
+pub static LAST_ERROR_REGISTRY: LazyLock<Mutex<HashMap<String, LastError>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Global registry for connection database paths
+///
+/// Maps connection ID to the path the connection was opened against, so connections can be
+/// looked up by path without locking each one.
+pub static CONNECTION_PATH_REGISTRY: LazyLock<Mutex<HashMap<String, DatabasePath>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Global registry for per-connection statement counts
+///
+/// Maps connection ID to the number of statements run on the connection, including those
+/// that failed. Kept apart from `LibSQLConn` so the count can be bumped and read without
+/// locking the connection, which batch and sync paths hold for long stretches.
+pub static STATEMENT_COUNT_REGISTRY: LazyLock<Mutex<HashMap<String, Arc<AtomicU64>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Global registry for per-connection error counters
+///
+/// Maps connection ID to the errors recorded on the connection, by class. Kept apart from
+/// `LibSQLConn` so errors can be counted and read without locking the connection, which
+/// the code recording the error may already hold.
+pub static ERROR_COUNT_REGISTRY: LazyLock<Mutex<HashMap<String, Arc<ErrorCounts>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Global registry for connections left in an unknown state
+///
+/// Maps connection ID to why, for connections where an operation couldn't undo a
+/// setting it changed temporarily. `ping` fails for these, so a pool replaces them.
+pub static BROKEN_CONNECTION_REGISTRY: LazyLock<Mutex<HashMap<String, String>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Global registry for dirty table tracking
+///
+/// Maps connection ID to the set of tables written since the set was last taken, for
+/// connections with tracking enabled.
+pub static DIRTY_TABLE_REGISTRY: LazyLock<Mutex<HashMap<String, DirtyTables>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Global registry for deadline tokens
+///
+/// Maps a caller-chosen token to the `Instant` its shared time budget runs out. Tokens
+/// expired for longer than `DEADLINE_RETENTION` are swept away when a deadline is set.
+pub static DEADLINE_REGISTRY: LazyLock<Mutex<HashMap<String, Instant>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// How long an expired deadline token is kept before it may be swept
+///
+/// Until then, queries against the token report the deadline as exceeded; afterwards
+/// the token is unknown.
+pub const DEADLINE_RETENTION: Duration = Duration::from_secs(60);
+
+/// Global registry for blob builders
+///
+/// Maps builder ID to the bytes appended so far, for blobs uploaded in chunks before a
+/// single insert. Builders idle for longer than `BLOB_BUILDER_IDLE_TIMEOUT` are swept
+/// away, so a caller that dies mid-upload doesn't hold its bytes for the life of the VM.
+pub static BLOB_BUILDER_REGISTRY: LazyLock<Mutex<HashMap<String, BlobBuilder>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// How long a blob builder may go without an append before it is discarded
+///
+/// Expired builders are removed whenever a builder is created or appended to.
+pub const BLOB_BUILDER_IDLE_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Most prefixes of the SQL re-prepared while looking for a syntax error's offset
+///
+/// Each probe prepares a longer prefix, so a token repeated many times in a long
+/// statement would otherwise cost quadratic work before giving up.
+pub const MAX_ERROR_OFFSET_PROBES: usize = 32;
+
+/// Most bytes all blob builders may hold between them
+///
+/// Matches SQLite's default maximum string or blob length, so any blob that fits could
+/// also be inserted.
+pub const MAX_BLOB_BUILDER_BYTES: usize = 1_000_000_000;
+
 // Atom declarations for EctoLibSql - used as return values and option identifiers in the NIF interface
 atoms! {
     local,
@@ -76,5 +165,79 @@ atoms! {
     connection,
     blob,
     nil,
-    unsupported
+    unsupported,
+    not_wal,
+    lower,
+    upper,
+    trim,
+    base64,
+    abort,
+    fail,
+    ignore,
+    replace,
+    rollback,
+    insert,
+    update,
+    delete,
+    schema,
+    pragma,
+    pragma_write,
+    attach,
+    detach,
+    function,
+    synced,
+    up_to_date,
+    done,
+    dump_chunk,
+    dump_error,
+    raw,
+    index_suffix,
+    table_prefix,
+    scan,
+    index,
+    primary_key,
+    busy,
+    read_lock,
+    write_lock,
+    deadline_exceeded,
+    read,
+    replica,
+    stored,
+    virtual_ = "virtual",
+    foreign_key_violations,
+    unknown,
+    precondition_failed,
+    result_too_large,
+    serialized,
+    multi_thread,
+    single_thread,
+    utf16le,
+    latin1,
+    not_a_replica,
+    blob_builder,
+    blob_ref,
+    constraint,
+    syntax,
+    other,
+    retries_exhausted,
+    build_us_key = "build_us",
+    connect_us_key = "connect_us",
+    ping_us_key = "ping_us",
+    unknown_option,
+    missing_option,
+    full_scan,
+    scans_key = "scans",
+    min_us,
+    max_us,
+    mean_us,
+    p95_us,
+    current_frame_key = "current_frame",
+    max_write_frame_key = "max_write_frame",
+    durable_frame_key = "durable_frame",
+    expected_key = "expected",
+    supplied_key = "supplied",
+    name_key = "name",
+    database_key = "database",
+    table_key = "table",
+    origin_column_key = "origin_column"
 }
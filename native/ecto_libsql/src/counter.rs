@@ -0,0 +1,111 @@
+/// Atomic counters for LibSQL databases
+///
+/// A counter is an integer column keyed by another column in the same table. Each
+/// increment is a single upsert with `RETURNING`, so concurrent increments never lose
+/// updates and the caller always sees the value its own increment produced.
+use crate::constants::*;
+use crate::utils::{decode_term_to_value, quote_identifier, safe_lock, safe_lock_arc, QuoteStyle};
+use libsql::Value;
+use rustler::{NifResult, Term};
+
+/// Add `delta` to the counter keyed by `key` and return the new value.
+///
+/// A missing row is created with the counter at `delta`, and a NULL counter is treated as
+/// zero. `key_column` must be the primary key or carry a unique constraint, as the upsert
+/// conflicts on it.
+pub async fn increment_counter(
+    conn: &libsql::Connection,
+    table: &str,
+    key_column: &str,
+    key: Value,
+    value_column: &str,
+    delta: i64,
+) -> Result<i64, String> {
+    let table_ident = quote_identifier(table, QuoteStyle::Backtick);
+    let key_ident = quote_identifier(key_column, QuoteStyle::Backtick);
+    let value_ident = quote_identifier(value_column, QuoteStyle::Backtick);
+
+    let sql = format!(
+        "INSERT INTO {table_ident} ({key_ident}, {value_ident}) VALUES (?1, ?2) \
+         ON CONFLICT ({key_ident}) DO UPDATE SET \
+         {value_ident} = coalesce({value_ident}, 0) + excluded.{value_ident} \
+         RETURNING {value_ident}"
+    );
+
+    let mut rows = conn
+        .query(&sql, vec![key, Value::Integer(delta)])
+        .await
+        .map_err(|e| format!("Failed to increment counter: {e}"))?;
+
+    let row = rows
+        .next()
+        .await
+        .map_err(|e| format!("Failed to increment counter: {e}"))?
+        .ok_or_else(|| "Increment returned no row".to_string())?;
+
+    match row
+        .get_value(0)
+        .map_err(|e| format!("Failed to read counter: {e}"))?
+    {
+        Value::Integer(value) => Ok(value),
+        other => Err(format!(
+            "Column {value_column} is not an integer counter (found {other:?})"
+        )),
+    }
+}
+
+/// Atomically add `delta` to a counter column and return the new value.
+///
+/// Runs `INSERT ... ON CONFLICT DO UPDATE ... RETURNING` in a single statement, so the
+/// increment is race-free. A row that doesn't exist yet is created with the counter at
+/// `delta`.
+///
+/// # Arguments
+/// - `conn_id`: Database connection ID
+/// - `table`: Table name (quoted internally)
+/// - `key_column`: Primary key or unique column identifying the counter (quoted internally)
+/// - `key`: Key of the counter row
+/// - `value_column`: Integer counter column (quoted internally)
+/// - `delta`: Amount to add (may be negative)
+///
+/// # Returns
+/// - The counter's new value
+/// - `{:error, reason}` - Unknown table or column, no unique constraint on `key_column`,
+///   or the counter holds a non-integer value
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn increment(
+    conn_id: &str,
+    table: &str,
+    key_column: &str,
+    key: Term,
+    value_column: &str,
+    delta: i64,
+) -> NifResult<i64> {
+    let client = {
+        let conn_map = safe_lock(&CONNECTION_REGISTRY, "increment conn_map")?;
+        conn_map
+            .get(conn_id)
+            .cloned()
+            .ok_or_else(|| rustler::Error::Term(Box::new("Invalid connection ID")))?
+    }; // Lock dropped here
+
+    let key = decode_term_to_value(key).map_err(|e| rustler::Error::Term(Box::new(e)))?;
+
+    // Clone the inner connection Arc and drop the outer lock before async operations
+    let connection = {
+        let client_guard = safe_lock_arc(&client, "increment client")?;
+        client_guard.client.clone()
+    }; // Outer lock dropped here
+
+    // SAFETY: We're inside TOKIO_RUNTIME.block_on(), so this is synchronous execution.
+    // The std::sync::Mutex guards are safe to hold across await points here because
+    // we're not in a true async context - block_on runs the future to completion.
+    #[allow(clippy::await_holding_lock)]
+    TOKIO_RUNTIME.block_on(async {
+        let conn_guard = safe_lock_arc(&connection, "increment conn")?;
+
+        increment_counter(&conn_guard, table, key_column, key, value_column, delta)
+            .await
+            .map_err(|e| rustler::Error::Term(Box::new(e)))
+    })
+}
@@ -9,7 +9,7 @@
 /// Cursors allow processing large result sets without loading everything into memory at once.
 /// Results are fetched in configurable batch sizes for efficient memory usage.
 use crate::{
-    constants::{CONNECTION_REGISTRY, CURSOR_REGISTRY, TOKIO_RUNTIME},
+    constants::{CONNECTION_REGISTRY, CURSOR_REGISTRY, STMT_REGISTRY, TOKIO_RUNTIME},
     decode,
     models::CursorData,
     transaction::TransactionEntryGuard,
@@ -18,6 +18,50 @@ use crate::{
 use libsql::Value;
 use rustler::{Atom, Binary, Encoder, Env, NifResult, OwnedBinary, Term};
 
+/// Largest `expected_rows` hint honoured when sizing a cursor's buffer, so a wildly
+/// wrong hint can't reserve an enormous allocation up front.
+///
+/// Each reserved row is a 24-byte `Vec` header, so this caps the up-front reservation
+/// at about 240 KB per cursor. Larger results still grow the buffer as rows arrive.
+pub const MAX_EXPECTED_ROWS: usize = 10_000;
+
+/// Buffer every row of a result set for a cursor.
+///
+/// `expected_rows` reserves the row buffer up front (capped at `MAX_EXPECTED_ROWS`),
+/// avoiding repeated reallocation as a large result grows. Column names are taken
+/// from the first row, so an empty result has no columns.
+pub async fn collect_cursor_rows(
+    mut result_rows: libsql::Rows,
+    expected_rows: Option<usize>,
+) -> Result<(Vec<String>, Vec<Vec<Value>>), libsql::Error> {
+    let mut columns: Vec<String> = Vec::new();
+    let mut rows: Vec<Vec<Value>> =
+        Vec::with_capacity(expected_rows.unwrap_or(0).min(MAX_EXPECTED_ROWS));
+
+    while let Some(row) = result_rows.next().await? {
+        // Get column names on first row
+        if columns.is_empty() {
+            for i in 0..row.column_count() {
+                if let Some(name) = row.column_name(i) {
+                    columns.push(name.to_string());
+                } else {
+                    columns.push(format!("col{i}"));
+                }
+            }
+        }
+
+        // Collect row values
+        let mut row_values = Vec::with_capacity(columns.len());
+        for i in 0..columns.len() {
+            let value = row.get(i as i32).unwrap_or(Value::Null);
+            row_values.push(value);
+        }
+        rows.push(row_values);
+    }
+
+    Ok((columns, rows))
+}
+
 /// Declare a cursor for streaming result set from a connection.
 ///
 /// This executes a query and stores all results in a cursor, which can then
@@ -27,10 +71,16 @@ use rustler::{Atom, Binary, Encoder, Env, NifResult, OwnedBinary, Term};
 /// - `conn_id`: Database connection ID
 /// - `sql`: SQL query string
 /// - `args`: Query parameters
+/// - `expected_rows`: Optional estimate of the row count, to size the buffer up front
 ///
 /// Returns a cursor ID on success, error on failure.
 #[rustler::nif(schedule = "DirtyIo")]
-pub fn declare_cursor(conn_id: &str, sql: &str, args: Vec<Term>) -> NifResult<String> {
+pub fn declare_cursor(
+    conn_id: &str,
+    sql: &str,
+    args: Vec<Term>,
+    expected_rows: Option<usize>,
+) -> NifResult<String> {
     // UTF-8 validation is guaranteed by Rust's &str type and Rustler's conversion,
     // so we can rely on the type system rather than runtime checks.
 
@@ -57,32 +107,110 @@ pub fn declare_cursor(conn_id: &str, sql: &str, args: Vec<Term>) -> NifResult<St
         let client_guard = utils::safe_lock_arc(&client, "declare_cursor client")?;
         let conn_guard = utils::safe_lock_arc(&client_guard.client, "declare_cursor conn")?;
 
-        let mut result_rows = conn_guard
-            .query(sql, decoded_args)
+        let result_rows = conn_guard.query(sql, decoded_args).await.map_err(|e| {
+            utils::record_last_error(conn_id, &e);
+            rustler::Error::Term(Box::new(format!("Query failed: {e}")))
+        })?;
+
+        collect_cursor_rows(result_rows, expected_rows)
             .await
-            .map_err(|e| rustler::Error::Term(Box::new(format!("Query failed: {e}"))))?;
+            .map_err(|e| rustler::Error::Term(Box::new(e.to_string())))
+    })?;
 
-        let mut columns: Vec<String> = Vec::new();
-        let mut rows: Vec<Vec<Value>> = Vec::new();
+    let cursor_id = uuid::Uuid::new_v4().to_string();
+    let cursor_data = CursorData {
+        conn_id: conn_id.to_string(),
+        columns,
+        rows,
+        position: 0,
+    };
+
+    utils::safe_lock(&CURSOR_REGISTRY, "declare_cursor cursor_registry")?
+        .insert(cursor_id.clone(), cursor_data);
+
+    Ok(cursor_id)
+}
+
+/// Declare a cursor from an existing prepared statement.
+///
+/// Executes the cached statement with new arguments and stores the results in a
+/// cursor, reusing the prepared plan instead of re-parsing the SQL. The statement is
+/// reset first, so it can be re-declared cheaply with different parameters.
+///
+/// # Arguments
+/// - `conn_id`: Database connection ID
+/// - `stmt_id`: Prepared statement ID (must belong to `conn_id`)
+/// - `args`: Query parameters
+///
+/// Returns a cursor ID on success, error on failure.
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn declare_cursor_from_statement(
+    conn_id: &str,
+    stmt_id: &str,
+    args: Vec<Term>,
+) -> NifResult<String> {
+    let conn_map = utils::safe_lock(
+        &CONNECTION_REGISTRY,
+        "declare_cursor_from_statement conn_map",
+    )?;
+    let stmt_registry = utils::safe_lock(
+        &STMT_REGISTRY,
+        "declare_cursor_from_statement stmt_registry",
+    )?;
+
+    if conn_map.get(conn_id).is_none() {
+        return Err(rustler::Error::Term(Box::new("Invalid connection ID")));
+    }
+
+    let (stored_conn_id, cached_stmt, _) = stmt_registry
+        .get(stmt_id)
+        .ok_or_else(|| rustler::Error::Term(Box::new("Statement not found")))?;
+
+    // Verify statement belongs to this connection
+    decode::verify_statement_ownership(stored_conn_id, conn_id)?;
+
+    let cached_stmt = cached_stmt.clone();
+
+    drop(stmt_registry); // Release lock before async operation
+    drop(conn_map); // Release lock before async operation
 
+    let decoded_args: Vec<Value> = args
+        .into_iter()
+        .map(|t| utils::decode_term_to_value(t))
+        .collect::<Result<_, _>>()
+        .map_err(|e| rustler::Error::Term(Box::new(e)))?;
+
+    // SAFETY: We're inside TOKIO_RUNTIME.block_on(), so this is synchronous execution.
+    // The std::sync::Mutex guards are safe to hold across await points here because
+    // we're not in a true async context - block_on runs the future to completion.
+    #[allow(clippy::await_holding_lock)]
+    let (columns, rows) = TOKIO_RUNTIME.block_on(async {
+        let stmt_guard = utils::safe_lock_arc(&cached_stmt, "declare_cursor_from_statement stmt")?;
+
+        // Reset clears any previous bindings and execution state
+        stmt_guard.reset();
+
+        let mut result_rows = stmt_guard.query(decoded_args).await.map_err(|e| {
+            utils::record_last_error(conn_id, &e);
+            rustler::Error::Term(Box::new(format!("Query failed: {e}")))
+        })?;
+
+        // Take column names from the statement so empty results still have columns
+        let columns: Vec<String> = (0..result_rows.column_count())
+            .map(|i| {
+                result_rows
+                    .column_name(i)
+                    .map_or_else(|| format!("col{i}"), ToString::to_string)
+            })
+            .collect();
+
+        let mut rows: Vec<Vec<Value>> = Vec::new();
         while let Some(row) = result_rows
             .next()
             .await
             .map_err(|e| rustler::Error::Term(Box::new(e.to_string())))?
         {
-            // Get column names on first row
-            if columns.is_empty() {
-                for i in 0..row.column_count() {
-                    if let Some(name) = row.column_name(i) {
-                        columns.push(name.to_string());
-                    } else {
-                        columns.push(format!("col{i}"));
-                    }
-                }
-            }
-
-            // Collect row values
-            let mut row_values = Vec::new();
+            let mut row_values = Vec::with_capacity(columns.len());
             for i in 0..columns.len() {
                 let value = row.get(i as i32).unwrap_or(Value::Null);
                 row_values.push(value);
@@ -101,8 +229,11 @@ pub fn declare_cursor(conn_id: &str, sql: &str, args: Vec<Term>) -> NifResult<St
         position: 0,
     };
 
-    utils::safe_lock(&CURSOR_REGISTRY, "declare_cursor cursor_registry")?
-        .insert(cursor_id.clone(), cursor_data);
+    utils::safe_lock(
+        &CURSOR_REGISTRY,
+        "declare_cursor_from_statement cursor_registry",
+    )?
+    .insert(cursor_id.clone(), cursor_data);
 
     Ok(cursor_id)
 }
@@ -118,6 +249,7 @@ pub fn declare_cursor(conn_id: &str, sql: &str, args: Vec<Term>) -> NifResult<St
 /// - `id_type`: Atom indicating whether `id` is a transaction (`:transaction`) or connection (`:connection`)
 /// - `sql`: SQL query string
 /// - `args`: Query parameters
+/// - `expected_rows`: Optional estimate of the row count, to size the buffer up front
 ///
 /// Returns a cursor ID on success, error on failure.
 #[rustler::nif(schedule = "DirtyIo")]
@@ -127,6 +259,7 @@ pub fn declare_cursor_with_context(
     id_type: Atom,
     sql: &str,
     args: Vec<Term>,
+    expected_rows: Option<usize>,
 ) -> NifResult<String> {
     // UTF-8 validation is guaranteed by Rust's &str type and Rustler's conversion,
     // so we can rely on the type system rather than runtime checks.
@@ -146,39 +279,18 @@ pub fn declare_cursor_with_context(
 
         // Execute query without holding the lock
         let (cols, rows) = TOKIO_RUNTIME.block_on(async {
-            let mut result_rows = guard
+            let result_rows = guard
                 .transaction()?
                 .query(sql, decoded_args)
                 .await
-                .map_err(|e| rustler::Error::Term(Box::new(format!("Query failed: {e}"))))?;
+                .map_err(|e| {
+                    utils::record_last_error(conn_id, &e);
+                    rustler::Error::Term(Box::new(format!("Query failed: {e}")))
+                })?;
 
-            let mut columns: Vec<String> = Vec::new();
-            let mut rows: Vec<Vec<Value>> = Vec::new();
-
-            while let Some(row) = result_rows
-                .next()
+            collect_cursor_rows(result_rows, expected_rows)
                 .await
-                .map_err(|e| rustler::Error::Term(Box::new(e.to_string())))?
-            {
-                if columns.is_empty() {
-                    for i in 0..row.column_count() {
-                        if let Some(name) = row.column_name(i) {
-                            columns.push(name.to_string());
-                        } else {
-                            columns.push(format!("col{i}"));
-                        }
-                    }
-                }
-
-                let mut row_values = Vec::new();
-                for i in 0..columns.len() {
-                    let value = row.get(i as i32).unwrap_or(Value::Null);
-                    row_values.push(value);
-                }
-                rows.push(row_values);
-            }
-
-            Ok::<_, rustler::Error>((columns, rows))
+                .map_err(|e| rustler::Error::Term(Box::new(e.to_string())))
         })?;
 
         // Guard automatically re-inserts the entry on drop
@@ -215,38 +327,14 @@ pub fn declare_cursor_with_context(
         let (cols, rows) = TOKIO_RUNTIME.block_on(async {
             let conn_guard = utils::safe_lock_arc(&connection, "declare_cursor_with_context conn")?;
 
-            let mut result_rows = conn_guard
-                .query(sql, decoded_args)
-                .await
-                .map_err(|e| rustler::Error::Term(Box::new(format!("Query failed: {e}"))))?;
-
-            let mut columns: Vec<String> = Vec::new();
-            let mut rows: Vec<Vec<Value>> = Vec::new();
+            let result_rows = conn_guard.query(sql, decoded_args).await.map_err(|e| {
+                utils::record_last_error(conn_id, &e);
+                rustler::Error::Term(Box::new(format!("Query failed: {e}")))
+            })?;
 
-            while let Some(row) = result_rows
-                .next()
+            collect_cursor_rows(result_rows, expected_rows)
                 .await
-                .map_err(|e| rustler::Error::Term(Box::new(e.to_string())))?
-            {
-                if columns.is_empty() {
-                    for i in 0..row.column_count() {
-                        if let Some(name) = row.column_name(i) {
-                            columns.push(name.to_string());
-                        } else {
-                            columns.push(format!("col{i}"));
-                        }
-                    }
-                }
-
-                let mut row_values = Vec::new();
-                for i in 0..columns.len() {
-                    let value = row.get(i as i32).unwrap_or(Value::Null);
-                    row_values.push(value);
-                }
-                rows.push(row_values);
-            }
-
-            Ok::<_, rustler::Error>((columns, rows))
+                .map_err(|e| rustler::Error::Term(Box::new(e.to_string())))
         })?;
 
         (cursor_conn_id, cols, rows)
@@ -268,6 +356,29 @@ pub fn declare_cursor_with_context(
     Ok(cursor_id)
 }
 
+/// Estimate how much memory a cursor's buffered rows occupy.
+///
+/// Lets operators spot heavy cursors. The estimate counts every buffered row, including
+/// those already fetched, as they stay buffered until the cursor is closed.
+///
+/// # Arguments
+/// - `conn_id`: Connection ID (for ownership verification)
+/// - `cursor_id`: Cursor ID
+///
+/// Returns the estimated size in bytes
+#[rustler::nif]
+pub fn cursor_memory(conn_id: &str, cursor_id: &str) -> NifResult<usize> {
+    let cursor_registry = utils::safe_lock(&CURSOR_REGISTRY, "cursor_memory cursor_registry")?;
+
+    let cursor = cursor_registry
+        .get(cursor_id)
+        .ok_or_else(|| rustler::Error::Term(Box::new("Cursor not found")))?;
+
+    decode::verify_cursor_ownership(cursor, conn_id)?;
+
+    Ok(cursor.estimated_memory())
+}
+
 /// Fetch rows from a cursor in batches.
 ///
 /// Returns up to `max_rows` rows from the cursor's current position.
@@ -0,0 +1,176 @@
+/// Deadline tokens shared across queries
+///
+/// A deadline token names a point in time. Each query run against the token gets whatever
+/// time remains as its timeout, so a sequence of queries shares a single budget. A query
+/// still running when the budget is spent is interrupted.
+use crate::constants::*;
+use crate::query::run_query;
+use crate::utils::{count_statements, safe_lock, safe_lock_arc};
+use libsql::Value;
+use rustler::{Atom, Env, NifResult, Term};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Start a shared time budget of `deadline_ms` milliseconds under `token`.
+///
+/// Setting a token that already exists restarts its budget. Tokens that expired more
+/// than `DEADLINE_RETENTION` ago are swept, so callers that never clear their tokens
+/// don't grow the registry without bound.
+///
+/// # Returns
+/// - `:ok` - Deadline recorded
+#[rustler::nif]
+pub fn set_deadline(token: &str, deadline_ms: u64) -> NifResult<Atom> {
+    let mut deadlines = safe_lock(&DEADLINE_REGISTRY, "set_deadline deadlines")?;
+    sweep_expired(&mut deadlines, Instant::now());
+    deadlines.insert(
+        token.to_string(),
+        Instant::now() + Duration::from_millis(deadline_ms),
+    );
+    Ok(rustler::types::atom::ok())
+}
+
+/// Drop tokens whose deadline passed more than `DEADLINE_RETENTION` before `now`.
+pub fn sweep_expired(deadlines: &mut HashMap<String, Instant>, now: Instant) {
+    deadlines.retain(|_, deadline| now.saturating_duration_since(*deadline) < DEADLINE_RETENTION);
+}
+
+/// Forget the deadline stored under `token`.
+///
+/// Clearing an unknown token is not an error.
+///
+/// # Returns
+/// - `:ok` - Deadline removed
+#[rustler::nif]
+pub fn clear_deadline(token: &str) -> NifResult<Atom> {
+    let mut deadlines = safe_lock(&DEADLINE_REGISTRY, "clear_deadline deadlines")?;
+    deadlines.remove(token);
+    Ok(rustler::types::atom::ok())
+}
+
+/// Time left before the deadline stored under `token` runs out.
+///
+/// Returns `Duration::ZERO` once the deadline has passed.
+pub fn remaining(token: &str) -> Result<Duration, String> {
+    let deadlines = safe_lock(&DEADLINE_REGISTRY, "remaining deadlines")
+        .map_err(|_| "Failed to lock deadline registry".to_string())?;
+    deadlines
+        .get(token)
+        .map(|deadline| deadline.saturating_duration_since(Instant::now()))
+        .ok_or_else(|| "Unknown deadline token".to_string())
+}
+
+/// Run `operation` with `budget` as its timeout, interrupting `conn` if it runs over.
+///
+/// A timer task on `TOKIO_RUNTIME` interrupts the connection when the budget is spent.
+/// The operation then fails at its next step and `None` is returned, so callers can
+/// report the deadline rather than the interruption. Operations that finish in time
+/// return `Some(result)`.
+pub async fn run_within<T, E, F>(
+    conn: &libsql::Connection,
+    budget: Duration,
+    operation: F,
+) -> Option<Result<T, E>>
+where
+    F: Future<Output = Result<T, E>>,
+{
+    let fired = Arc::new(AtomicBool::new(false));
+
+    // Spawned on the shared runtime's workers rather than awaited here, as a local query
+    // blocks the thread driving `operation` until it finishes or is interrupted.
+    let watchdog = {
+        let fired = Arc::clone(&fired);
+        let conn = conn.clone();
+        TOKIO_RUNTIME.spawn(async move {
+            tokio::time::sleep(budget).await;
+            fired.store(true, Ordering::SeqCst);
+            let _ = conn.interrupt();
+        })
+    };
+
+    let result = operation.await;
+    // Wait for the task to stop, so it can't interrupt whatever runs on the connection next.
+    watchdog.abort();
+    let _ = watchdog.await;
+
+    if fired.load(Ordering::SeqCst) && result.is_err() {
+        None
+    } else {
+        Some(result)
+    }
+}
+
+/// Execute a SQL query bounded by the time left on a deadline token.
+///
+/// Behaves like `query_args`, but the remaining budget of `token` is applied as the
+/// operation timeout. The query is interrupted if the budget runs out before it finishes.
+///
+/// # Returns
+/// - Result map as from `query_args`
+/// - `{:error, :deadline_exceeded}` - The budget was spent before or during the query
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn query_args_deadline<'a>(
+    env: Env<'a>,
+    conn_id: &str,
+    query: &str,
+    args: Vec<Term<'a>>,
+    token: &str,
+) -> NifResult<Term<'a>> {
+    let budget = remaining(token).map_err(|e| rustler::Error::Term(Box::new(e)))?;
+    if budget.is_zero() {
+        return Err(rustler::Error::Term(Box::new(deadline_exceeded())));
+    }
+
+    let client = {
+        let conn_map = safe_lock(&CONNECTION_REGISTRY, "query_args_deadline conn_map")?;
+        conn_map
+            .get(conn_id)
+            .cloned()
+            .ok_or_else(|| rustler::Error::Term(Box::new("Invalid connection ID")))?
+    }; // Lock dropped here
+
+    let params: Vec<Value> = args
+        .into_iter()
+        .map(|t| crate::utils::decode_term_to_value(t))
+        .collect::<Result<_, _>>()
+        .map_err(|e| rustler::Error::Term(Box::new(e)))?;
+
+    let (connection, column_naming) = {
+        let client_guard = safe_lock_arc(&client, "query_args_deadline client")?;
+        count_statements(conn_id, 1);
+        (client_guard.client.clone(), client_guard.column_naming)
+    }; // Outer lock dropped here
+
+    // SAFETY: We're inside TOKIO_RUNTIME.block_on(), so this is synchronous execution.
+    // The std::sync::Mutex guards are safe to hold across await points here because
+    // we're not in a true async context - block_on runs the future to completion.
+    #[allow(clippy::await_holding_lock)]
+    TOKIO_RUNTIME.block_on(async {
+        let conn_guard = safe_lock_arc(&connection, "query_args_deadline conn")?;
+
+        // Recompute after taking the lock, as waiting for it spends the budget too.
+        let budget = remaining(token).map_err(|e| rustler::Error::Term(Box::new(e)))?;
+        if budget.is_zero() {
+            return Err(rustler::Error::Term(Box::new(deadline_exceeded())));
+        }
+
+        run_within(
+            &conn_guard,
+            budget,
+            run_query(
+                env,
+                &conn_guard,
+                conn_id,
+                query,
+                params,
+                column_naming,
+                None,
+            ),
+        )
+        .await
+        .unwrap_or_else(|| Err(rustler::Error::Term(Box::new(deadline_exceeded()))))
+    })
+}
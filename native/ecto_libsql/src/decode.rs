@@ -6,7 +6,9 @@ use libsql::TransactionBehavior;
 use rustler::Atom;
 
 use crate::constants::*;
-use crate::models::{CursorData, Mode};
+use crate::models::{
+    AuthorizerAction, ColumnNaming, ColumnTransform, CursorData, DenyRule, Mode, TextEncoding,
+};
 
 /// Decode an Elixir atom to a Mode enum
 ///
@@ -23,6 +25,103 @@ pub fn decode_mode(atom: Atom) -> Option<Mode> {
     }
 }
 
+/// Decode an Elixir atom to a ColumnNaming strategy
+///
+/// Converts `:raw`, `:index_suffix` and `:table_prefix` to their Rust equivalents.
+pub fn decode_column_naming(atom: Atom) -> Option<ColumnNaming> {
+    if atom == raw() {
+        Some(ColumnNaming::Raw)
+    } else if atom == index_suffix() {
+        Some(ColumnNaming::IndexSuffix)
+    } else if atom == table_prefix() {
+        Some(ColumnNaming::TablePrefix)
+    } else {
+        None
+    }
+}
+
+/// Decode an Elixir atom to a ColumnTransform
+///
+/// Converts `:lower`, `:upper`, `:trim` and `:base64` to their Rust equivalents.
+pub fn decode_column_transform(atom: Atom) -> Option<ColumnTransform> {
+    if atom == lower() {
+        Some(ColumnTransform::Lower)
+    } else if atom == upper() {
+        Some(ColumnTransform::Upper)
+    } else if atom == trim() {
+        Some(ColumnTransform::Trim)
+    } else if atom == base64() {
+        Some(ColumnTransform::Base64)
+    } else {
+        None
+    }
+}
+
+/// Decode an Elixir atom to an AuthorizerAction
+///
+/// Converts `:read`, `:insert`, `:update`, `:delete`, `:schema`, `:pragma`,
+/// `:pragma_write`, `:attach`, `:detach`, `:transaction` and `:function` to their Rust
+/// equivalents.
+pub fn decode_authorizer_action(atom: Atom) -> Option<AuthorizerAction> {
+    if atom == read() {
+        Some(AuthorizerAction::Read)
+    } else if atom == insert() {
+        Some(AuthorizerAction::Insert)
+    } else if atom == update() {
+        Some(AuthorizerAction::Update)
+    } else if atom == delete() {
+        Some(AuthorizerAction::Delete)
+    } else if atom == schema() {
+        Some(AuthorizerAction::Schema)
+    } else if atom == pragma() {
+        Some(AuthorizerAction::Pragma)
+    } else if atom == pragma_write() {
+        Some(AuthorizerAction::PragmaWrite)
+    } else if atom == attach() {
+        Some(AuthorizerAction::Attach)
+    } else if atom == detach() {
+        Some(AuthorizerAction::Detach)
+    } else if atom == transaction() {
+        Some(AuthorizerAction::Transaction)
+    } else if atom == function() {
+        Some(AuthorizerAction::Function)
+    } else {
+        None
+    }
+}
+
+/// Decode an authorizer deny rule
+///
+/// Accepts an action atom, denying it everywhere, or an `{action, name}` tuple limiting
+/// it to one table, pragma, function, file or database.
+pub fn decode_deny_rule(term: rustler::Term) -> Result<DenyRule, String> {
+    let (atom, target) = if let Ok(atom) = term.decode::<Atom>() {
+        (atom, None)
+    } else if let Ok((atom, target)) = term.decode::<(Atom, String)>() {
+        (atom, Some(target))
+    } else {
+        return Err(format!(
+            "Invalid authorizer rule {term:?}: expected an action atom or {{action, name}}"
+        ));
+    };
+    let action = decode_authorizer_action(atom)
+        .ok_or_else(|| format!("Unknown authorizer action: {atom:?}"))?;
+    Ok(DenyRule { action, target })
+}
+
+/// Decode an Elixir atom to a TextEncoding
+///
+/// Converts `:utf16le` and `:latin1` to their Rust equivalents.
+pub fn decode_text_encoding(atom: Atom) -> Option<TextEncoding> {
+    if atom == utf16le() {
+        Some(TextEncoding::Utf16Le)
+    } else if atom == latin1() {
+        Some(TextEncoding::Latin1)
+    } else {
+        None
+    }
+}
+
 /// Decode an Elixir atom to a TransactionBehavior
 ///
 /// Converts atoms like `:deferred`, `:immediate`, `:exclusive`, `:read_only`
@@ -0,0 +1,482 @@
+/// Data export helpers for LibSQL databases
+///
+/// This module streams table contents out of the database in portable forms, such as
+/// the SQL `INSERT` statements used for lightweight logical replication,
+/// newline-delimited JSON, or a compact binary frame of typed values.
+use crate::constants::*;
+use crate::models::ColumnNaming;
+use crate::utils::{
+    column_origin_tables, count_statements, dedupe_column_names, push_json_string, push_json_value,
+    quote_identifier, safe_lock, safe_lock_arc, sql_literal, QuoteStyle,
+};
+use libsql::Value;
+use rustler::{Atom, Binary, Encoder, Env, LocalPid, NifResult, OwnedBinary, OwnedEnv, Term};
+
+/// Resolve a table name case-insensitively to its canonical name in `sqlite_master`.
+///
+/// Returns `Err("Table not found: ...")` if no such table exists.
+pub async fn resolve_table_name(conn: &libsql::Connection, table: &str) -> Result<String, String> {
+    let mut rows = conn
+        .query(
+            "SELECT name FROM sqlite_master WHERE type = 'table' AND name = ?1 COLLATE NOCASE",
+            vec![Value::Text(table.to_string())],
+        )
+        .await
+        .map_err(|e| format!("Failed to look up table: {e}"))?;
+
+    match rows
+        .next()
+        .await
+        .map_err(|e| format!("Failed to look up table: {e}"))?
+    {
+        Some(row) => row
+            .get(0)
+            .map_err(|e| format!("Failed to read table name: {e}")),
+        None => Err(format!("Table not found: {table}")),
+    }
+}
+
+/// List the columns of `table` that an `INSERT` can set, in declaration order.
+///
+/// Generated columns can't be inserted into, and hidden virtual table columns aren't
+/// part of the row, so only columns `PRAGMA table_xinfo` reports as not hidden are kept.
+pub async fn insertable_columns(
+    conn: &libsql::Connection,
+    table: &str,
+) -> Result<Vec<String>, String> {
+    let mut rows = conn
+        .query(
+            "SELECT name FROM pragma_table_xinfo(?1) WHERE hidden = 0 ORDER BY cid",
+            vec![Value::Text(table.to_string())],
+        )
+        .await
+        .map_err(|e| format!("Failed to read columns: {e}"))?;
+
+    let mut columns = Vec::new();
+    while let Some(row) = rows
+        .next()
+        .await
+        .map_err(|e| format!("Failed to read column: {e}"))?
+    {
+        columns.push(
+            row.get(0)
+                .map_err(|e| format!("Failed to read column name: {e}"))?,
+        );
+    }
+
+    // SQLite won't create a table whose columns are all generated, so none means no table
+    if columns.is_empty() {
+        return Err(format!("Table not found: {table}"));
+    }
+    Ok(columns)
+}
+
+/// Dump a table as `INSERT` statements, handing them to `emit` in chunks.
+///
+/// Generated columns are left out, so replaying the statements recomputes them.
+/// Rows are read incrementally, so at most `chunk_size` statements are held in memory
+/// at once. `emit` returns `false` to stop the dump early (e.g. the consumer has gone
+/// away), in which case this returns `Ok(false)`; a completed dump returns `Ok(true)`.
+pub async fn dump_table<F>(
+    conn: &libsql::Connection,
+    table: &str,
+    chunk_size: usize,
+    mut emit: F,
+) -> Result<bool, String>
+where
+    F: FnMut(Vec<String>) -> bool,
+{
+    if chunk_size == 0 {
+        return Err("Chunk size must be greater than 0".to_string());
+    }
+
+    let quoted_table = quote_identifier(table, QuoteStyle::DoubleQuote);
+    let columns = insertable_columns(conn, table)
+        .await?
+        .iter()
+        .map(|column| quote_identifier(column, QuoteStyle::DoubleQuote))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let mut rows = conn
+        .query(&format!("SELECT {columns} FROM {quoted_table}"), ())
+        .await
+        .map_err(|e| format!("Failed to query table: {e}"))?;
+
+    let column_count = rows.column_count();
+    let prefix = format!("INSERT INTO {quoted_table} ({columns}) VALUES (");
+
+    let mut chunk = Vec::with_capacity(chunk_size);
+    while let Some(row) = rows
+        .next()
+        .await
+        .map_err(|e| format!("Failed to read row: {e}"))?
+    {
+        let mut values = Vec::with_capacity(column_count as usize);
+        for i in 0..column_count {
+            let value = row
+                .get_value(i)
+                .map_err(|e| format!("Failed to read column {i}: {e}"))?;
+            values.push(sql_literal(&value));
+        }
+        chunk.push(format!("{prefix}{});", values.join(", ")));
+
+        if chunk.len() >= chunk_size
+            && !emit(std::mem::replace(
+                &mut chunk,
+                Vec::with_capacity(chunk_size),
+            ))
+        {
+            return Ok(false);
+        }
+    }
+
+    if !chunk.is_empty() && !emit(chunk) {
+        return Ok(false);
+    }
+
+    Ok(true)
+}
+
+/// Stream a table's contents to a process as SQL `INSERT` statements.
+///
+/// Intended for lightweight logical replication: the receiving side can replay the
+/// statements against another database. The table is validated up front, then the dump
+/// runs on a background thread and the process receives:
+///
+/// - `{:dump_chunk, [sql]}` - Up to `chunk` escaped `INSERT` statements per message
+/// - `:done` - After the final chunk
+/// - `{:dump_error, reason}` - If reading the table fails part-way through
+///
+/// The dump stops as soon as the receiving process is no longer alive. The connection
+/// is held for the duration of the dump, so other operations on it will wait.
+///
+/// # Arguments
+/// - `conn_id`: Database connection ID
+/// - `table`: Table name (quoted internally)
+/// - `pid`: Process to receive the statements
+/// - `chunk`: Maximum number of statements per message (must be greater than 0)
+///
+/// # Returns
+/// - `:ok` - The dump has started
+/// - `{:error, reason}` - Invalid connection, table not found, or invalid chunk size
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn stream_table_dump(
+    conn_id: &str,
+    table: &str,
+    pid: LocalPid,
+    chunk: usize,
+) -> NifResult<Atom> {
+    if chunk == 0 {
+        return Err(rustler::Error::Term(Box::new(
+            "Chunk size must be greater than 0",
+        )));
+    }
+
+    let client = {
+        let conn_map = safe_lock(&CONNECTION_REGISTRY, "stream_table_dump conn_map")?;
+        conn_map
+            .get(conn_id)
+            .cloned()
+            .ok_or_else(|| rustler::Error::Term(Box::new("Invalid connection ID")))?
+    }; // Lock dropped here
+
+    // Clone the inner connection Arc and drop the outer lock before async operations
+    let connection = {
+        let client_guard = safe_lock_arc(&client, "stream_table_dump client")?;
+        client_guard.client.clone()
+    }; // Outer lock dropped here
+
+    // Validate the table synchronously so the caller gets a direct error.
+    // SAFETY: We're inside TOKIO_RUNTIME.block_on(), so this is synchronous execution.
+    // The std::sync::Mutex guards are safe to hold across await points here because
+    // we're not in a true async context - block_on runs the future to completion.
+    #[allow(clippy::await_holding_lock)]
+    let table = TOKIO_RUNTIME.block_on(async {
+        let conn_guard = safe_lock_arc(&connection, "stream_table_dump conn")?;
+
+        resolve_table_name(&conn_guard, table)
+            .await
+            .map_err(|e| rustler::Error::Term(Box::new(e)))
+    })?;
+
+    // Messages must be sent from an unmanaged thread via OwnedEnv (see hooks.rs), which
+    // also lets each chunk's terms be freed once sent, keeping memory bounded.
+    std::thread::spawn(move || {
+        let mut msg_env = OwnedEnv::new();
+
+        #[allow(clippy::await_holding_lock)]
+        let result = TOKIO_RUNTIME.block_on(async {
+            let conn_guard = safe_lock_arc(&connection, "stream_table_dump conn")
+                .map_err(|e| format!("{e:?}"))?;
+
+            dump_table(&conn_guard, &table, chunk, |statements| {
+                msg_env
+                    .send_and_clear(&pid, |env| (dump_chunk(), statements).encode(env))
+                    .is_ok()
+            })
+            .await
+        });
+
+        // If the receiver died mid-dump there is nobody left to notify.
+        match result {
+            Ok(true) => {
+                let _ = msg_env.send_and_clear(&pid, |env| done().encode(env));
+            }
+            Ok(false) => {}
+            Err(reason) => {
+                let _ = msg_env.send_and_clear(&pid, |env| (dump_error(), reason).encode(env));
+            }
+        }
+    });
+
+    Ok(ok())
+}
+
+/// Render a single value as an SQLite literal, for logs and fixture tooling.
+///
+/// Accepts the same terms as query parameters and renders them with the escaping used
+/// for table dumps: text single-quoted with embedded quotes doubled, blobs as `X'..'`,
+/// numbers bare, booleans as `1`/`0` and `nil` as `NULL`. Needs no connection.
+///
+/// # Arguments
+/// - `value`: Value to render
+///
+/// # Returns
+/// - The SQL literal
+/// - `{:error, reason}` - The term can't be used as a query parameter
+#[rustler::nif]
+pub fn inline_value(value: Term) -> NifResult<String> {
+    crate::utils::decode_term_to_value(value)
+        .map(|value| sql_literal(&value))
+        .map_err(|e| rustler::Error::Term(Box::new(e)))
+}
+
+/// Run a query and render its rows as newline-delimited JSON.
+///
+/// Each row becomes one JSON object mapping column name to value, followed by `\n`;
+/// see `push_json_value` for how values are written. Column names follow `naming`, so
+/// duplicate names can be disambiguated rather than repeated as object keys. Rows are
+/// written out as they are read, without being collected first.
+pub async fn query_ndjson(
+    conn: &libsql::Connection,
+    sql: &str,
+    params: Vec<Value>,
+    naming: ColumnNaming,
+) -> Result<String, libsql::Error> {
+    let stmt = conn.prepare(sql).await?;
+    let names: Vec<String> = stmt
+        .columns()
+        .iter()
+        .map(|column| column.name().to_string())
+        .collect();
+    let names = dedupe_column_names(&names, &column_origin_tables(&stmt), naming);
+
+    // Render each key once, ready to be copied into every row
+    let keys: Vec<String> = names
+        .iter()
+        .map(|name| {
+            let mut key = String::new();
+            push_json_string(&mut key, name);
+            key.push(':');
+            key
+        })
+        .collect();
+
+    let mut out = String::new();
+    let mut rows = stmt.query(params).await?;
+    while let Some(row) = rows.next().await? {
+        out.push('{');
+        for (index, key) in keys.iter().enumerate() {
+            if index > 0 {
+                out.push(',');
+            }
+            out.push_str(key);
+            push_json_value(&mut out, &row.get_value(index as i32)?);
+        }
+        out.push_str("}\n");
+    }
+    Ok(out)
+}
+
+/// Execute a query and return its rows as newline-delimited JSON, built natively.
+///
+/// Skips building Elixir maps and JSON-encoding them again, for log ingestion and
+/// other export paths. Numbers are bare, text is a JSON string, blobs are base64 strings
+/// and NULL is `null`. See `query_ndjson`.
+///
+/// # Arguments
+/// - `conn_id`: Database connection ID
+/// - `sql`: Query to run
+/// - `args`: Query parameter values
+///
+/// # Returns
+/// - A binary holding one JSON object per row, each followed by a newline
+/// - `{:error, reason}` - The query failed
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn query_to_ndjson(conn_id: &str, sql: &str, args: Vec<Term>) -> NifResult<String> {
+    let client = {
+        let conn_map = safe_lock(&CONNECTION_REGISTRY, "query_to_ndjson conn_map")?;
+        conn_map
+            .get(conn_id)
+            .cloned()
+            .ok_or_else(|| rustler::Error::Term(Box::new("Invalid connection ID")))?
+    }; // Lock dropped here
+
+    let params: Vec<Value> = args
+        .into_iter()
+        .map(|t| crate::utils::decode_term_to_value(t))
+        .collect::<Result<_, _>>()
+        .map_err(|e| rustler::Error::Term(Box::new(e)))?;
+
+    let (connection, column_naming) = {
+        let client_guard = safe_lock_arc(&client, "query_to_ndjson client")?;
+        count_statements(conn_id, 1);
+        (client_guard.client.clone(), client_guard.column_naming)
+    }; // Outer lock dropped here
+
+    // SAFETY: We're inside TOKIO_RUNTIME.block_on(), so this is synchronous execution.
+    // The std::sync::Mutex guards are safe to hold across await points here because
+    // we're not in a true async context - block_on runs the future to completion.
+    #[allow(clippy::await_holding_lock)]
+    TOKIO_RUNTIME.block_on(async {
+        let conn_guard = safe_lock_arc(&connection, "query_to_ndjson conn")?;
+        query_ndjson(&conn_guard, sql, params, column_naming)
+            .await
+            .map_err(|e| {
+                crate::utils::record_last_error(conn_id, &e);
+                rustler::Error::Term(Box::new(format!("Query failed: {e}")))
+            })
+    })
+}
+
+/// Type tags used in binary result frames, one per SQLite storage class
+pub const FRAME_NULL: u8 = 0;
+pub const FRAME_INTEGER: u8 = 1;
+pub const FRAME_REAL: u8 = 2;
+pub const FRAME_TEXT: u8 = 3;
+pub const FRAME_BLOB: u8 = 4;
+
+/// Append one value to a binary result frame.
+///
+/// Each value is a one-byte type tag followed by its data, all big-endian:
+/// - `0` NULL: no data
+/// - `1` integer: 8-byte signed integer
+/// - `2` real: 8-byte IEEE 754 float
+/// - `3` text: 4-byte unsigned length, then that many bytes of UTF-8
+/// - `4` blob: 4-byte unsigned length, then that many bytes
+///
+/// Fails for text or blobs of 4 GiB or more, whose length doesn't fit the frame.
+pub fn push_frame_value(out: &mut Vec<u8>, value: &Value) -> Result<(), String> {
+    let mut push_bytes = |tag: u8, bytes: &[u8]| {
+        let len = u32::try_from(bytes.len())
+            .map_err(|_| "Value too large for a binary frame".to_string())?;
+        out.push(tag);
+        out.extend_from_slice(&len.to_be_bytes());
+        out.extend_from_slice(bytes);
+        Ok(())
+    };
+    match value {
+        Value::Null => {
+            out.push(FRAME_NULL);
+            Ok(())
+        }
+        Value::Integer(i) => {
+            out.push(FRAME_INTEGER);
+            out.extend_from_slice(&i.to_be_bytes());
+            Ok(())
+        }
+        Value::Real(f) => {
+            out.push(FRAME_REAL);
+            out.extend_from_slice(&f.to_be_bytes());
+            Ok(())
+        }
+        Value::Text(text) => push_bytes(FRAME_TEXT, text.as_bytes()),
+        Value::Blob(blob) => push_bytes(FRAME_BLOB, blob),
+    }
+}
+
+/// Run a query and encode its rows as a binary result frame.
+///
+/// Returns the column names and the frame: every row's values in column order, one after
+/// another, encoded by `push_frame_value`. There is no header or row separator, so the
+/// row count is the number of values divided by the number of columns. Rows are encoded
+/// as they are read, without being collected first.
+pub async fn query_frame(
+    conn: &libsql::Connection,
+    sql: &str,
+    params: Vec<Value>,
+) -> Result<(Vec<String>, Vec<u8>), libsql::Error> {
+    let mut rows = conn.query(sql, params).await?;
+    let column_count = rows.column_count();
+    let columns = (0..column_count)
+        .map(|i| rows.column_name(i).unwrap_or_default().to_string())
+        .collect();
+
+    let mut frame = Vec::new();
+    while let Some(row) = rows.next().await? {
+        for i in 0..column_count {
+            push_frame_value(&mut frame, &row.get_value(i)?).map_err(libsql::Error::Misuse)?;
+        }
+    }
+    Ok((columns, frame))
+}
+
+/// Execute a query and return its rows as one compact binary frame.
+///
+/// Builds a single binary rather than a term per value, which is much cheaper to pass
+/// back for wide result sets. The caller decodes it when, and if, it needs the values.
+/// See `push_frame_value` for the value encoding and `query_frame` for the layout.
+///
+/// # Arguments
+/// - `env`: Elixir environment
+/// - `conn_id`: Database connection ID
+/// - `sql`: Query to run
+/// - `args`: Query parameter values
+///
+/// # Returns
+/// - `{columns, frame}` - Column names and the binary frame
+/// - `{:error, reason}` - The query failed, or a value was too large to frame
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn query_binary<'a>(
+    env: Env<'a>,
+    conn_id: &str,
+    sql: &str,
+    args: Vec<Term<'a>>,
+) -> NifResult<(Vec<String>, Binary<'a>)> {
+    let client = {
+        let conn_map = safe_lock(&CONNECTION_REGISTRY, "query_binary conn_map")?;
+        conn_map
+            .get(conn_id)
+            .cloned()
+            .ok_or_else(|| rustler::Error::Term(Box::new("Invalid connection ID")))?
+    }; // Lock dropped here
+
+    let params: Vec<Value> = args
+        .into_iter()
+        .map(|t| crate::utils::decode_term_to_value(t))
+        .collect::<Result<_, _>>()
+        .map_err(|e| rustler::Error::Term(Box::new(e)))?;
+
+    let connection = {
+        let client_guard = safe_lock_arc(&client, "query_binary client")?;
+        count_statements(conn_id, 1);
+        client_guard.client.clone()
+    }; // Outer lock dropped here
+
+    // SAFETY: We're inside TOKIO_RUNTIME.block_on(), so this is synchronous execution.
+    // The std::sync::Mutex guards are safe to hold across await points here because
+    // we're not in a true async context - block_on runs the future to completion.
+    #[allow(clippy::await_holding_lock)]
+    let (columns, frame) = TOKIO_RUNTIME.block_on(async {
+        let conn_guard = safe_lock_arc(&connection, "query_binary conn")?;
+        query_frame(&conn_guard, sql, params).await.map_err(|e| {
+            crate::utils::record_last_error(conn_id, &e);
+            rustler::Error::Term(Box::new(format!("Query failed: {e}")))
+        })
+    })?;
+
+    let mut binary = OwnedBinary::new(frame.len())
+        .ok_or_else(|| rustler::Error::Term(Box::new("Failed to allocate binary")))?;
+    binary.as_mut_slice().copy_from_slice(&frame);
+    Ok((columns, binary.release(env)))
+}
@@ -4,8 +4,16 @@
 /// Hooks allow Elixir processes to receive notifications about database changes and control access.
 ///
 /// **CURRENT STATUS**: Both update hooks and authorizer hooks are currently **NOT SUPPORTED**
-/// due to fundamental threading limitations with Rustler and the BEAM VM.
-use rustler::{Atom, Env, LocalPid, NifResult};
+/// due to fundamental threading limitations with Rustler and the BEAM VM. Dirty table
+/// tracking and authorizer deny rules are supported, as their hooks run entirely in
+/// Rust and never call back into the BEAM.
+use crate::constants::{DirtyTables, CONNECTION_REGISTRY, DIRTY_TABLE_REGISTRY};
+use crate::models::{AuthorizerAction, DenyRule};
+use crate::utils::{safe_lock, safe_lock_arc};
+use libsql::{AuthAction, AuthContext, Authorization};
+use rustler::{Atom, Env, LocalPid, NifResult, Term};
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
 
 /// Set update hook for a connection
 ///
@@ -161,6 +169,250 @@ pub fn set_authorizer(env: Env, _conn_id: &str, _pid: LocalPid) -> NifResult<(At
     ))
 }
 
+/// Set a per-connection scan limit using SQLite's progress handler
+///
+/// **NOT SUPPORTED** - The limit would be enforced by a progress handler that counts
+/// virtual machine steps and aborts the statement once `max_steps` is exceeded, but
+/// libsql does not expose `sqlite3_progress_handler`, and registering it directly
+/// would require raw FFI, which this crate forbids (`unsafe_code = "deny"`).
+///
+/// # Alternatives
+///
+/// 1. **Interrupts** - Run the query in a task and call `interrupt_connection` when a
+///    timer fires, bounding wall-clock time rather than VM steps
+///
+/// 2. **Bounded queries** - Add `LIMIT` clauses to queries built from untrusted input
+///
+/// 3. **Query plan checks** - Reject queries whose `EXPLAIN QUERY PLAN` output contains
+///    an unindexed `SCAN` of a large table before running them
+///
+/// # Arguments
+/// - `_conn_id` - Connection identifier (ignored)
+/// - `_max_steps` - Maximum VM steps per statement (ignored)
+///
+/// # Returns
+/// - `{:error, :unsupported}` - Always returns unsupported
+#[rustler::nif]
+pub fn set_progress_limit(env: Env, _conn_id: &str, _max_steps: u64) -> NifResult<(Atom, Atom)> {
+    Ok((
+        Atom::from_str(env, "error")?,
+        Atom::from_str(env, "unsupported")?,
+    ))
+}
+
+/// Install an update hook on `conn` that adds each written table's name to `tables`.
+///
+/// Tables outside the main schema are recorded as `schema.table`. Replaces any update
+/// hook already installed on the connection.
+pub fn record_dirty_tables(conn: &libsql::Connection, tables: DirtyTables) -> libsql::Result<()> {
+    conn.add_update_hook(Box::new(move |_op, db, table, _rowid| {
+        if let Ok(mut set) = safe_lock_arc(&tables, "dirty table hook") {
+            let name = if db == "main" {
+                table.to_string()
+            } else {
+                format!("{db}.{table}")
+            };
+            set.insert(name);
+        }
+    }))
+}
+
+/// Start recording which tables are written on a connection
+///
+/// A pull-based alternative to update hook subscriptions: an update hook accumulates
+/// the names of tables that rows are inserted into, updated or deleted from, and
+/// `take_dirty_tables` returns and clears them. Only table names are kept, so the set
+/// stays small however many rows change. Enabling tracking again keeps the set.
+///
+/// Writes are recorded when they happen, so tables written by a transaction that is
+/// later rolled back are still reported. As with any SQLite update hook, changes made
+/// without firing it are missed: writes to `WITHOUT ROWID` tables, rows removed by
+/// `REPLACE` conflict resolution, and `DELETE` without a `WHERE` clause (the truncate
+/// optimisation).
+///
+/// # Arguments
+/// - `conn_id` - Connection identifier
+///
+/// # Returns
+/// - `:ok` - Tracking is enabled
+/// - `{:error, reason}` - Unknown connection, or the connection type has no update hooks
+///   (remote connections)
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn enable_dirty_tracking(conn_id: &str) -> NifResult<Atom> {
+    let client = safe_lock(&CONNECTION_REGISTRY, "enable_dirty_tracking conn_map")?
+        .get(conn_id)
+        .cloned()
+        .ok_or_else(|| rustler::Error::Term(Box::new("Invalid connection ID")))?;
+
+    let tables = safe_lock(&DIRTY_TABLE_REGISTRY, "enable_dirty_tracking registry")?
+        .entry(conn_id.to_string())
+        .or_insert_with(|| Arc::new(Mutex::new(HashSet::new())))
+        .clone();
+
+    let connection = safe_lock_arc(&client, "enable_dirty_tracking client")?
+        .client
+        .clone();
+    let conn_guard = safe_lock_arc(&connection, "enable_dirty_tracking conn")?;
+
+    if let Err(e) = record_dirty_tables(&conn_guard, tables) {
+        safe_lock(&DIRTY_TABLE_REGISTRY, "enable_dirty_tracking registry")?.remove(conn_id);
+        return Err(rustler::Error::Term(Box::new(format!(
+            "Failed to enable dirty table tracking: {e}"
+        ))));
+    }
+
+    Ok(rustler::types::atom::ok())
+}
+
+/// Return the tables written since tracking was enabled or last taken, and clear them
+///
+/// # Arguments
+/// - `conn_id` - Connection identifier
+///
+/// # Returns
+/// - Sorted list of table names
+/// - `{:error, reason}` - Tracking is not enabled for the connection
+#[rustler::nif]
+pub fn take_dirty_tables(conn_id: &str) -> NifResult<Vec<String>> {
+    let tables = safe_lock(&DIRTY_TABLE_REGISTRY, "take_dirty_tables registry")?
+        .get(conn_id)
+        .cloned()
+        .ok_or_else(|| rustler::Error::Term(Box::new("Dirty table tracking is not enabled")))?;
+
+    let mut taken: Vec<String> =
+        std::mem::take(&mut *safe_lock_arc(&tables, "take_dirty_tables tables")?)
+            .into_iter()
+            .collect();
+    taken.sort();
+    Ok(taken)
+}
+
+/// Check whether `rule` denies an authorizer `action`.
+///
+/// Table actions are matched on the table name (views and indexes on their own name
+/// where SQLite reports no table), pragmas on the pragma name, functions on the
+/// function name, and attach and detach on the file and database name. Rules with a
+/// target never match actions without one, such as transactions and savepoints.
+pub fn rule_denies(rule: &DenyRule, action: &AuthAction) -> bool {
+    use AuthorizerAction as A;
+
+    let (matches, target) = match *action {
+        AuthAction::Read { table_name, .. } => (rule.action == A::Read, Some(table_name)),
+        AuthAction::Insert { table_name } => (rule.action == A::Insert, Some(table_name)),
+        AuthAction::Update { table_name, .. } => (rule.action == A::Update, Some(table_name)),
+        AuthAction::Delete { table_name } => (rule.action == A::Delete, Some(table_name)),
+        AuthAction::Pragma {
+            pragma_name,
+            pragma_value,
+        } => (
+            rule.action == A::Pragma || (rule.action == A::PragmaWrite && pragma_value.is_some()),
+            Some(pragma_name),
+        ),
+        AuthAction::Attach { filename } => (rule.action == A::Attach, Some(filename)),
+        AuthAction::Detach { database_name } => (rule.action == A::Detach, Some(database_name)),
+        AuthAction::Transaction { .. } | AuthAction::Savepoint { .. } => {
+            (rule.action == A::Transaction, None)
+        }
+        AuthAction::Function { function_name } => (rule.action == A::Function, Some(function_name)),
+        AuthAction::CreateIndex { table_name, .. }
+        | AuthAction::CreateTempIndex { table_name, .. }
+        | AuthAction::DropIndex { table_name, .. }
+        | AuthAction::DropTempIndex { table_name, .. }
+        | AuthAction::CreateTrigger { table_name, .. }
+        | AuthAction::CreateTempTrigger { table_name, .. }
+        | AuthAction::DropTrigger { table_name, .. }
+        | AuthAction::DropTempTrigger { table_name, .. }
+        | AuthAction::CreateTable { table_name }
+        | AuthAction::CreateTempTable { table_name }
+        | AuthAction::DropTable { table_name }
+        | AuthAction::DropTempTable { table_name }
+        | AuthAction::AlterTable { table_name, .. }
+        | AuthAction::Analyze { table_name }
+        | AuthAction::CreateVtable { table_name, .. }
+        | AuthAction::DropVtable { table_name, .. } => (rule.action == A::Schema, Some(table_name)),
+        AuthAction::CreateView { view_name }
+        | AuthAction::CreateTempView { view_name }
+        | AuthAction::DropView { view_name }
+        | AuthAction::DropTempView { view_name } => (rule.action == A::Schema, Some(view_name)),
+        AuthAction::Reindex { index_name } => (rule.action == A::Schema, Some(index_name)),
+        AuthAction::Select | AuthAction::Recursive | AuthAction::Unknown { .. } => return false,
+    };
+
+    matches
+        && match (&rule.target, target) {
+            (None, _) => true,
+            (Some(wanted), Some(target)) => wanted.eq_ignore_ascii_case(target),
+            (Some(_), None) => false,
+        }
+}
+
+/// Install an authorizer on `conn` that denies every action matched by `rules`.
+///
+/// Everything else is allowed. An empty rule list removes the authorizer. Replaces any
+/// authorizer already installed on the connection.
+pub fn install_deny_rules(conn: &libsql::Connection, rules: Vec<DenyRule>) -> libsql::Result<()> {
+    if rules.is_empty() {
+        return conn.authorizer(None);
+    }
+    conn.authorizer(Some(std::sync::Arc::new(move |context: &AuthContext| {
+        if rules.iter().any(|rule| rule_denies(rule, &context.action)) {
+            Authorization::Deny
+        } else {
+            Authorization::Allow
+        }
+    })))
+}
+
+/// Deny operations on a connection with a native SQLite authorizer
+///
+/// SQLite consults the authorizer while compiling each statement, so a denied
+/// operation fails at prepare time, before anything runs, with a "not authorized"
+/// error ("access to table.column is prohibited" for reads). This is stronger than
+/// checking statement types, as it also catches operations reached through views,
+/// triggers and subqueries. The decision is made in Rust from the rules alone, so no
+/// call back into the BEAM is needed (see `set_authorizer` for why that is not
+/// supported).
+///
+/// Statements prepared before the rules were set are not checked again. Pragmas and
+/// functions used internally by other functions in this library are subject to the
+/// rules too.
+///
+/// # Arguments
+/// - `conn_id` - Connection identifier
+/// - `rules` - List of rules, each an action atom (`:read`, `:insert`, `:update`,
+///   `:delete`, `:schema`, `:pragma`, `:pragma_write`, `:attach`, `:detach`,
+///   `:transaction`, `:function`) or an `{action, name}` tuple limiting it to one
+///   object. An empty list removes the authorizer.
+///
+/// # Returns
+/// - `:ok` - Rules installed
+/// - `{:error, reason}` - Invalid rule, or the connection type has no authorizer
+///   (remote connections)
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn set_deny_rules(conn_id: &str, rules: Vec<Term>) -> NifResult<Atom> {
+    let rules: Vec<DenyRule> = rules
+        .into_iter()
+        .map(crate::decode::decode_deny_rule)
+        .collect::<Result<_, _>>()
+        .map_err(|e| rustler::Error::Term(Box::new(e)))?;
+
+    let client = safe_lock(&CONNECTION_REGISTRY, "set_deny_rules conn_map")?
+        .get(conn_id)
+        .cloned()
+        .ok_or_else(|| rustler::Error::Term(Box::new("Invalid connection ID")))?;
+
+    let connection = safe_lock_arc(&client, "set_deny_rules client")?
+        .client
+        .clone();
+    let conn_guard = safe_lock_arc(&connection, "set_deny_rules conn")?;
+
+    install_deny_rules(&conn_guard, rules).map_err(|e| {
+        rustler::Error::Term(Box::new(format!("Failed to set authorizer rules: {e}")))
+    })?;
+
+    Ok(rustler::types::atom::ok())
+}
+
 /// Determine if a SQL query should use the query path (returns rows) or execute path (no rows)
 ///
 /// This is used by the Elixir adapter to route queries correctly:
@@ -177,3 +429,188 @@ pub fn set_authorizer(env: Env, _conn_id: &str, _pid: LocalPid) -> NifResult<(At
 pub fn should_use_query_path(sql: String) -> bool {
     crate::should_use_query(&sql)
 }
+
+/// Enable or disable SQLite's defensive mode for a connection
+///
+/// **NOT SUPPORTED** - Defensive mode is switched with `sqlite3_db_config` and
+/// `SQLITE_DBCONFIG_DEFENSIVE`. libsql only exposes `sqlite3_db_config` for loading
+/// extensions, and calling it directly would require raw FFI, which this crate forbids
+/// (`unsafe_code = "deny"`).
+///
+/// # Alternatives
+///
+/// 1. **Read-only connections** - Switch on `PRAGMA query_only` for connections that run
+///    untrusted SQL, which blocks shadow table writes along with every other write
+///
+/// 2. **Statement allow-lists** - Reject untrusted SQL that names a virtual table's shadow
+///    tables (for FTS5, `<table>_data`, `<table>_idx`, `<table>_content` and so on)
+///
+/// 3. **Separate databases** - Keep FTS indexes and other virtual tables in a database
+///    that untrusted connections never open or attach
+///
+/// # Arguments
+/// - `_conn_id` - Connection identifier (ignored)
+/// - `_enabled` - Whether defensive mode should be on (ignored)
+///
+/// # Returns
+/// - `{:error, :unsupported}` - Always returns unsupported
+#[rustler::nif]
+pub fn set_defensive(env: Env, _conn_id: &str, _enabled: bool) -> NifResult<(Atom, Atom)> {
+    Ok((
+        Atom::from_str(env, "error")?,
+        Atom::from_str(env, "unsupported")?,
+    ))
+}
+
+/// Report whether SQLite's defensive mode is on for a connection
+///
+/// **NOT SUPPORTED** - Reading the setting needs the same `sqlite3_db_config` call as
+/// `set_defensive`, which libsql does not expose.
+///
+/// # Arguments
+/// - `_conn_id` - Connection identifier (ignored)
+///
+/// # Returns
+/// - `{:error, :unsupported}` - Always returns unsupported
+#[rustler::nif]
+pub fn get_defensive(env: Env, _conn_id: &str) -> NifResult<(Atom, Atom)> {
+    Ok((
+        Atom::from_str(env, "error")?,
+        Atom::from_str(env, "unsupported")?,
+    ))
+}
+
+/// Set the maximum number of databases a connection may attach
+///
+/// **NOT SUPPORTED** - The limit is `SQLITE_LIMIT_ATTACHED`, adjusted with
+/// `sqlite3_limit`, which libsql does not expose and which has no pragma equivalent.
+/// Calling it directly would require raw FFI, which this crate forbids
+/// (`unsafe_code = "deny"`). Even then, `sqlite3_limit` can only lower a limit: the
+/// SQLite bundled with libsql is compiled with `SQLITE_MAX_ATTACHED` at its default of
+/// 10, so raising it would also need a rebuild of libsql's SQLite.
+///
+/// # Alternatives
+///
+/// 1. **Detach when done** - Attach shards only for the queries that need them and
+///    `DETACH` them afterwards, keeping at most 10 attached at once
+///
+/// 2. **Fan out across connections** - Query groups of shards over separate connections
+///    and merge the results in Elixir
+///
+/// # Arguments
+/// - `_conn_id` - Connection identifier (ignored)
+/// - `_limit` - Maximum number of attached databases (ignored)
+///
+/// # Returns
+/// - `{:error, :unsupported}` - Always returns unsupported
+#[rustler::nif]
+pub fn set_attached_limit(env: Env, _conn_id: &str, _limit: u32) -> NifResult<(Atom, Atom)> {
+    Ok((
+        Atom::from_str(env, "error")?,
+        Atom::from_str(env, "unsupported")?,
+    ))
+}
+
+/// Get the maximum number of databases a connection may attach
+///
+/// **NOT SUPPORTED** - Reading the limit needs the same `sqlite3_limit` call as
+/// `set_attached_limit`, which libsql does not expose. The compiled-in limit is 10.
+///
+/// # Arguments
+/// - `_conn_id` - Connection identifier (ignored)
+///
+/// # Returns
+/// - `{:error, :unsupported}` - Always returns unsupported
+#[rustler::nif]
+pub fn get_attached_limit(env: Env, _conn_id: &str) -> NifResult<(Atom, Atom)> {
+    Ok((
+        Atom::from_str(env, "error")?,
+        Atom::from_str(env, "unsupported")?,
+    ))
+}
+
+/// Set the maximum length of an SQL statement a connection will prepare
+///
+/// **NOT SUPPORTED** - The limit is `SQLITE_LIMIT_SQL_LENGTH`, adjusted with
+/// `sqlite3_limit`, which libsql does not expose and which has no pragma equivalent.
+/// Calling it directly would require raw FFI, which this crate forbids
+/// (`unsafe_code = "deny"`). libsql's SQLite keeps the compiled-in maximum of
+/// 1,000,000,000 bytes.
+///
+/// # Alternatives
+///
+/// 1. **Check before running** - Reject generated SQL by its byte size in Elixir before
+///    passing it to the connection
+///
+/// 2. **Bind values** - Pass user-influenced data as parameters rather than building it
+///    into the SQL text, which keeps statements short and avoids injection
+///
+/// # Arguments
+/// - `_conn_id` - Connection identifier (ignored)
+/// - `_bytes` - Maximum statement length in bytes (ignored)
+///
+/// # Returns
+/// - `{:error, :unsupported}` - Always returns unsupported
+#[rustler::nif]
+pub fn set_sql_length_limit(env: Env, _conn_id: &str, _bytes: u32) -> NifResult<(Atom, Atom)> {
+    Ok((
+        Atom::from_str(env, "error")?,
+        Atom::from_str(env, "unsupported")?,
+    ))
+}
+
+/// Get the maximum length of an SQL statement a connection will prepare
+///
+/// **NOT SUPPORTED** - Reading the limit needs the same `sqlite3_limit` call as
+/// `set_sql_length_limit`, which libsql does not expose. The compiled-in limit is
+/// 1,000,000,000 bytes.
+///
+/// # Arguments
+/// - `_conn_id` - Connection identifier (ignored)
+///
+/// # Returns
+/// - `{:error, :unsupported}` - Always returns unsupported
+#[rustler::nif]
+pub fn get_sql_length_limit(env: Env, _conn_id: &str) -> NifResult<(Atom, Atom)> {
+    Ok((
+        Atom::from_str(env, "error")?,
+        Atom::from_str(env, "unsupported")?,
+    ))
+}
+
+/// Configure a connection's lookaside memory allocator
+///
+/// **NOT SUPPORTED** - Lookaside is configured with
+/// `sqlite3_db_config(SQLITE_DBCONFIG_LOOKASIDE, ...)`, which libsql does not expose and
+/// which has no pragma equivalent. Calling it directly would require raw FFI, which this
+/// crate forbids (`unsafe_code = "deny"`). SQLite would also refuse the change once any
+/// lookaside memory is in use, so it would have to be made before the connection
+/// prepares its first statement.
+///
+/// # Alternatives
+///
+/// 1. **Default lookaside** - libsql's SQLite already gives every connection SQLite's
+///    default 48 KB lookaside buffer, which suits most workloads
+///
+/// 2. **Fewer allocations** - Reuse prepared statements and batch small writes, which
+///    cuts allocation churn more than lookaside tuning does
+///
+/// # Arguments
+/// - `_conn_id` - Connection identifier (ignored)
+/// - `_slot_size` - Bytes per lookaside slot (ignored)
+/// - `_slot_count` - Number of lookaside slots (ignored)
+///
+/// # Returns
+/// - `{:error, :unsupported}` - Always returns unsupported
+#[rustler::nif]
+pub fn configure_lookaside(
+    env: Env,
+    _conn_id: &str,
+    _slot_size: u32,
+    _slot_count: u32,
+) -> NifResult<(Atom, Atom)> {
+    Ok((
+        Atom::from_str(env, "error")?,
+        Atom::from_str(env, "unsupported")?,
+    ))
+}
@@ -2,17 +2,26 @@
 //!
 //! This is the root module for the `EctoLibSql` NIF (Native Implemented Function) library.
 //! It declares and organizes all submodules handling different aspects of database operations.
+pub mod arrow;
 pub mod batch;
+pub mod blob;
 pub mod connection;
 pub mod constants;
+pub mod counter;
 pub mod cursor;
+pub mod deadline;
 pub mod decode;
+pub mod export;
 pub mod hooks;
+pub mod maintenance;
+pub mod memory;
 pub mod metadata;
 pub mod models;
+pub mod plan;
 pub mod query;
 pub mod replication;
 pub mod savepoint;
+pub mod session;
 pub mod statement;
 pub mod transaction;
 pub mod utils;
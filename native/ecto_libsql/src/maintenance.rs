@@ -0,0 +1,833 @@
+/// Database maintenance helpers for LibSQL databases
+///
+/// This module provides housekeeping operations that repair or tidy database
+/// state after bulk data changes, such as normalising `AUTOINCREMENT` sequences
+/// after rows have been inserted with explicit ids, compacting the database file
+/// with `VACUUM`, tuning WAL auto-checkpoints, reporting WAL file growth, redefining
+/// tables with foreign key enforcement suspended, and finding duplicates that would
+/// block a unique index.
+use crate::constants::*;
+use crate::metadata::table_info;
+use crate::models::{CacheSpill, ForeignKeyViolation, Mode};
+use crate::utils::{
+    count_statements, encode_value, has_keyword, quote_identifier, safe_lock, safe_lock_arc,
+    QuoteStyle,
+};
+use libsql::Value;
+use rustler::{Env, NifResult, Term};
+
+/// Normalise the `sqlite_sequence` entry for an `AUTOINCREMENT` table.
+///
+/// Raises the stored sequence to `MAX(rowid)` so the next automatically assigned
+/// id cannot collide with rows inserted using explicit ids. The sequence is never
+/// lowered, preserving `AUTOINCREMENT`'s guarantee that ids are not reused.
+///
+/// Returns `Ok(Some(seq))` with the resulting sequence value, or `Ok(None)` when the
+/// table does not use `AUTOINCREMENT` (nothing to normalise).
+pub async fn sync_autoincrement_sequence(
+    conn: &libsql::Connection,
+    table: &str,
+) -> Result<Option<i64>, String> {
+    let mut rows = conn
+        .query(
+            "SELECT name, sql FROM sqlite_master WHERE type = 'table' AND name = ?1 COLLATE NOCASE",
+            vec![Value::Text(table.to_string())],
+        )
+        .await
+        .map_err(|e| format!("Failed to look up table: {e}"))?;
+
+    // Use the canonical table name from sqlite_master, as sqlite_sequence stores it verbatim
+    let (table, create_sql): (String, String) = match rows
+        .next()
+        .await
+        .map_err(|e| format!("Failed to read table definition: {e}"))?
+    {
+        Some(row) => (
+            row.get(0)
+                .map_err(|e| format!("Failed to read table name: {e}"))?,
+            row.get(1)
+                .map_err(|e| format!("Failed to read table definition: {e}"))?,
+        ),
+        None => return Err(format!("Table not found: {table}")),
+    };
+
+    // Tables without AUTOINCREMENT have no sqlite_sequence entry; SQLite already
+    // picks MAX(rowid) + 1 for them, so there is nothing to do. The keyword is
+    // matched as a token, so comments and default values mentioning it don't count.
+    if !has_keyword(&create_sql, "AUTOINCREMENT") {
+        return Ok(None);
+    }
+
+    let max_query = format!(
+        "SELECT COALESCE(MAX(rowid), 0) FROM {}",
+        quote_identifier(&table, QuoteStyle::DoubleQuote)
+    );
+    let mut max_rows = conn
+        .query(&max_query, ())
+        .await
+        .map_err(|e| format!("Failed to query MAX(rowid): {e}"))?;
+    let max_rowid: i64 = match max_rows
+        .next()
+        .await
+        .map_err(|e| format!("Failed to read MAX(rowid): {e}"))?
+    {
+        Some(row) => row
+            .get(0)
+            .map_err(|e| format!("Failed to read MAX(rowid): {e}"))?,
+        None => 0,
+    };
+
+    let mut seq_rows = conn
+        .query(
+            "SELECT seq FROM sqlite_sequence WHERE name = ?1",
+            vec![Value::Text(table.clone())],
+        )
+        .await
+        .map_err(|e| format!("Failed to query sqlite_sequence: {e}"))?;
+    let current_seq: Option<i64> = match seq_rows
+        .next()
+        .await
+        .map_err(|e| format!("Failed to read sqlite_sequence: {e}"))?
+    {
+        Some(row) => Some(
+            row.get(0)
+                .map_err(|e| format!("Failed to read sqlite_sequence: {e}"))?,
+        ),
+        None => None,
+    };
+
+    match current_seq {
+        Some(seq) if seq >= max_rowid => Ok(Some(seq)),
+        Some(_) => {
+            conn.execute(
+                "UPDATE sqlite_sequence SET seq = ?1 WHERE name = ?2",
+                vec![Value::Integer(max_rowid), Value::Text(table)],
+            )
+            .await
+            .map_err(|e| format!("Failed to update sqlite_sequence: {e}"))?;
+            Ok(Some(max_rowid))
+        }
+        None => {
+            conn.execute(
+                "INSERT INTO sqlite_sequence (name, seq) VALUES (?1, ?2)",
+                vec![Value::Text(table), Value::Integer(max_rowid)],
+            )
+            .await
+            .map_err(|e| format!("Failed to insert into sqlite_sequence: {e}"))?;
+            Ok(Some(max_rowid))
+        }
+    }
+}
+
+/// Normalise the `AUTOINCREMENT` sequence for a table after manual id inserts.
+///
+/// When rows are inserted with explicit ids (e.g. during a data migration), the
+/// `sqlite_sequence` entry for the table can lag behind the real maximum id. This
+/// raises it to `MAX(rowid)` so subsequent automatically assigned ids don't collide.
+///
+/// Tables that don't use `AUTOINCREMENT` are left untouched.
+///
+/// # Arguments
+/// - `conn_id`: Database connection ID
+/// - `table`: Table name (quoted internally)
+///
+/// # Returns
+/// - The resulting sequence value, or `nil` if the table has no `AUTOINCREMENT` column
+/// - `{:error, reason}` - Table not found or the update failed
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn sync_sequence(conn_id: &str, table: &str) -> NifResult<Option<i64>> {
+    let client = {
+        let conn_map = safe_lock(&CONNECTION_REGISTRY, "sync_sequence conn_map")?;
+        conn_map
+            .get(conn_id)
+            .cloned()
+            .ok_or_else(|| rustler::Error::Term(Box::new("Invalid connection ID")))?
+    }; // Lock dropped here
+
+    // Clone the inner connection Arc and drop the outer lock before async operations
+    let connection = {
+        let client_guard = safe_lock_arc(&client, "sync_sequence client")?;
+        client_guard.client.clone()
+    }; // Outer lock dropped here
+
+    // SAFETY: We're inside TOKIO_RUNTIME.block_on(), so this is synchronous execution.
+    // The std::sync::Mutex guards are safe to hold across await points here because
+    // we're not in a true async context - block_on runs the future to completion.
+    #[allow(clippy::await_holding_lock)]
+    TOKIO_RUNTIME.block_on(async {
+        let conn_guard = safe_lock_arc(&connection, "sync_sequence conn")?;
+
+        sync_autoincrement_sequence(&conn_guard, table)
+            .await
+            .map_err(|e| rustler::Error::Term(Box::new(e)))
+    })
+}
+
+/// Read a single integer pragma value.
+async fn pragma_i64(conn: &libsql::Connection, pragma: &str) -> Result<i64, String> {
+    let mut rows = conn
+        .query(&format!("PRAGMA {pragma}"), ())
+        .await
+        .map_err(|e| format!("Failed to query {pragma}: {e}"))?;
+    match rows
+        .next()
+        .await
+        .map_err(|e| format!("Failed to read {pragma}: {e}"))?
+    {
+        Some(row) => row
+            .get(0)
+            .map_err(|e| format!("Failed to read {pragma}: {e}")),
+        None => Err(format!("No value returned for {pragma}")),
+    }
+}
+
+/// Size of the main database in bytes, as `page_count × page_size`.
+pub async fn database_size(conn: &libsql::Connection) -> Result<u64, String> {
+    let page_count = pragma_i64(conn, "page_count").await?;
+    let page_size = pragma_i64(conn, "page_size").await?;
+    u64::try_from(page_count.saturating_mul(page_size))
+        .map_err(|_| format!("Invalid database size: {page_count} pages of {page_size} bytes"))
+}
+
+/// Run `VACUUM` and return the database size in bytes before and after.
+pub async fn vacuum_measured(conn: &libsql::Connection) -> Result<(u64, u64), String> {
+    let before = database_size(conn).await?;
+    conn.execute("VACUUM", ())
+        .await
+        .map_err(|e| format!("Vacuum failed: {e}"))?;
+    let after = database_size(conn).await?;
+    Ok((before, after))
+}
+
+/// Compact the database with `VACUUM` and report its size before and after.
+///
+/// Sizes are measured as `page_count × page_size`, so they reflect the main database
+/// only (not the WAL file). `VACUUM` rewrites the whole database and needs up to twice
+/// its size in free disk space while it runs. It cannot run inside a transaction.
+///
+/// # Arguments
+/// - `conn_id`: Database connection ID
+///
+/// # Returns
+/// - `{before_bytes, after_bytes}` - Database size before and after compaction
+/// - `{:error, :unsupported}` - For remote connections, whose file is managed by the server
+/// - `{:error, reason}` - `VACUUM` failed (e.g. inside a transaction)
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn vacuum_with_sizes(conn_id: &str) -> NifResult<(u64, u64)> {
+    let client = {
+        let conn_map = safe_lock(&CONNECTION_REGISTRY, "vacuum_with_sizes conn_map")?;
+        conn_map
+            .get(conn_id)
+            .cloned()
+            .ok_or_else(|| rustler::Error::Term(Box::new("Invalid connection ID")))?
+    }; // Lock dropped here
+
+    // Clone the inner connection Arc and drop the outer lock before async operations
+    let connection = {
+        let client_guard = safe_lock_arc(&client, "vacuum_with_sizes client")?;
+        if matches!(client_guard.mode, Mode::Remote) {
+            return Err(rustler::Error::Term(Box::new(unsupported())));
+        }
+        client_guard.client.clone()
+    }; // Outer lock dropped here
+
+    // SAFETY: We're inside TOKIO_RUNTIME.block_on(), so this is synchronous execution.
+    // The std::sync::Mutex guards are safe to hold across await points here because
+    // we're not in a true async context - block_on runs the future to completion.
+    #[allow(clippy::await_holding_lock)]
+    TOKIO_RUNTIME.block_on(async {
+        let conn_guard = safe_lock_arc(&connection, "vacuum_with_sizes conn")?;
+
+        vacuum_measured(&conn_guard)
+            .await
+            .map_err(|e| rustler::Error::Term(Box::new(e)))
+    })
+}
+
+/// Set the WAL auto-checkpoint threshold, in pages, and return the value now in effect.
+///
+/// `0` disables automatic checkpoints. Negative values are rejected rather than passed
+/// on, as SQLite would treat them as `0`.
+pub async fn set_autocheckpoint(conn: &libsql::Connection, pages: i64) -> Result<i64, String> {
+    if pages < 0 {
+        return Err(format!(
+            "wal_autocheckpoint must be non-negative, got {pages}"
+        ));
+    }
+    // The pragma reports the new value as a row, so run it as a query
+    conn.query(&format!("PRAGMA wal_autocheckpoint = {pages}"), ())
+        .await
+        .map_err(|e| format!("Failed to set wal_autocheckpoint: {e}"))?;
+    pragma_i64(conn, "wal_autocheckpoint").await
+}
+
+/// Set how many WAL pages trigger an automatic checkpoint.
+///
+/// SQLite checkpoints the WAL back into the database once it grows past this many pages
+/// (1000 by default). Write-heavy workloads may raise it to checkpoint less often, or
+/// set `0` to disable automatic checkpoints and run `PRAGMA wal_checkpoint` themselves.
+/// The setting is per connection.
+///
+/// # Arguments
+/// - `conn_id`: Database connection ID
+/// - `pages`: Threshold in pages; `0` disables auto-checkpointing
+///
+/// # Returns
+/// - `:ok` - Threshold set
+/// - `{:error, reason}` - Negative `pages` or query failure
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn set_wal_autocheckpoint(conn_id: &str, pages: i64) -> NifResult<rustler::Atom> {
+    let client = {
+        let conn_map = safe_lock(&CONNECTION_REGISTRY, "set_wal_autocheckpoint conn_map")?;
+        conn_map
+            .get(conn_id)
+            .cloned()
+            .ok_or_else(|| rustler::Error::Term(Box::new("Invalid connection ID")))?
+    }; // Lock dropped here
+
+    let connection = {
+        let client_guard = safe_lock_arc(&client, "set_wal_autocheckpoint client")?;
+        client_guard.client.clone()
+    }; // Outer lock dropped here
+
+    // SAFETY: We're inside TOKIO_RUNTIME.block_on(), so this is synchronous execution.
+    // The std::sync::Mutex guards are safe to hold across await points here because
+    // we're not in a true async context - block_on runs the future to completion.
+    #[allow(clippy::await_holding_lock)]
+    TOKIO_RUNTIME.block_on(async {
+        let conn_guard = safe_lock_arc(&connection, "set_wal_autocheckpoint conn")?;
+
+        set_autocheckpoint(&conn_guard, pages)
+            .await
+            .map(|_| rustler::types::atom::ok())
+            .map_err(|e| rustler::Error::Term(Box::new(e)))
+    })
+}
+
+/// Read the connection's WAL auto-checkpoint threshold, in pages.
+///
+/// # Arguments
+/// - `conn_id`: Database connection ID
+///
+/// # Returns
+/// - The threshold in pages; `0` means auto-checkpointing is disabled
+/// - `{:error, reason}` - Query failure
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn get_wal_autocheckpoint(conn_id: &str) -> NifResult<i64> {
+    let client = {
+        let conn_map = safe_lock(&CONNECTION_REGISTRY, "get_wal_autocheckpoint conn_map")?;
+        conn_map
+            .get(conn_id)
+            .cloned()
+            .ok_or_else(|| rustler::Error::Term(Box::new("Invalid connection ID")))?
+    }; // Lock dropped here
+
+    let connection = {
+        let client_guard = safe_lock_arc(&client, "get_wal_autocheckpoint client")?;
+        client_guard.client.clone()
+    }; // Outer lock dropped here
+
+    // SAFETY: We're inside TOKIO_RUNTIME.block_on(), so this is synchronous execution.
+    // The std::sync::Mutex guards are safe to hold across await points here because
+    // we're not in a true async context - block_on runs the future to completion.
+    #[allow(clippy::await_holding_lock)]
+    TOKIO_RUNTIME.block_on(async {
+        let conn_guard = safe_lock_arc(&connection, "get_wal_autocheckpoint conn")?;
+
+        pragma_i64(&conn_guard, "wal_autocheckpoint")
+            .await
+            .map_err(|e| rustler::Error::Term(Box::new(e)))
+    })
+}
+
+/// Locate the WAL file of the connection's main database and measure its size.
+///
+/// The path is the main database file, as reported by `PRAGMA database_list`, plus
+/// SQLite's `-wal` suffix. Returns `Ok(None)` when the database is not in WAL mode or
+/// has no file (in-memory databases). A WAL file that does not exist yet, because
+/// nothing has been written since it was last removed, is reported as 0 bytes.
+pub async fn wal_file(conn: &libsql::Connection) -> Result<Option<(String, u64)>, String> {
+    let mut rows = conn
+        .query("PRAGMA journal_mode", ())
+        .await
+        .map_err(|e| format!("Failed to query journal_mode: {e}"))?;
+    let journal_mode: String = match rows
+        .next()
+        .await
+        .map_err(|e| format!("Failed to read journal_mode: {e}"))?
+    {
+        Some(row) => row
+            .get(0)
+            .map_err(|e| format!("Failed to read journal_mode: {e}"))?,
+        None => return Err("No value returned for journal_mode".to_string()),
+    };
+    if !journal_mode.eq_ignore_ascii_case("wal") {
+        return Ok(None);
+    }
+
+    let main_file = crate::metadata::list_databases(conn)
+        .await?
+        .into_iter()
+        .find(|(_, name, _)| name == "main")
+        .and_then(|(_, _, file)| file);
+    let Some(main_file) = main_file else {
+        return Ok(None);
+    };
+
+    let path = format!("{main_file}-wal");
+    let size = match std::fs::metadata(&path) {
+        Ok(metadata) => metadata.len(),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => 0,
+        Err(e) => return Err(format!("Failed to read WAL file {path}: {e}")),
+    };
+    Ok(Some((path, size)))
+}
+
+/// Report the path and current size of the connection's WAL file.
+///
+/// For monitoring WAL growth separately from the main database: a WAL file that keeps
+/// growing usually means checkpoints are being blocked by long-lived readers. The
+/// size is read from the filesystem, so it covers frames already checkpointed but not
+/// yet overwritten. See `wal_file`.
+///
+/// # Arguments
+/// - `conn_id`: Database connection ID
+///
+/// # Returns
+/// - `{path, size_bytes}` - WAL file path and size in bytes
+/// - `{:error, :not_wal}` - The database is not in WAL mode, or is in memory
+/// - `{:error, :unsupported}` - Remote connection, whose files are managed by the server
+/// - `{:error, reason}` - Query or filesystem failure
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn wal_file_info(conn_id: &str) -> NifResult<(String, u64)> {
+    let client = {
+        let conn_map = safe_lock(&CONNECTION_REGISTRY, "wal_file_info conn_map")?;
+        conn_map
+            .get(conn_id)
+            .cloned()
+            .ok_or_else(|| rustler::Error::Term(Box::new("Invalid connection ID")))?
+    }; // Lock dropped here
+
+    let connection = {
+        let client_guard = safe_lock_arc(&client, "wal_file_info client")?;
+        if matches!(client_guard.mode, Mode::Remote) {
+            return Err(rustler::Error::Term(Box::new(unsupported())));
+        }
+        client_guard.client.clone()
+    }; // Outer lock dropped here
+
+    // SAFETY: We're inside TOKIO_RUNTIME.block_on(), so this is synchronous execution.
+    // The std::sync::Mutex guards are safe to hold across await points here because
+    // we're not in a true async context - block_on runs the future to completion.
+    #[allow(clippy::await_holding_lock)]
+    TOKIO_RUNTIME.block_on(async {
+        let conn_guard = safe_lock_arc(&connection, "wal_file_info conn")?;
+
+        wal_file(&conn_guard)
+            .await
+            .map_err(|e| rustler::Error::Term(Box::new(e)))?
+            .ok_or_else(|| rustler::Error::Term(Box::new(not_wal())))
+    })
+}
+
+/// Apply a `PRAGMA cache_spill` setting and return the value now in effect.
+///
+/// Thresholds must be between `0` and `i32::MAX` pages. SQLite would read a negative
+/// threshold as a size in KiB; those are rejected rather than passed on.
+pub async fn set_spill(conn: &libsql::Connection, setting: CacheSpill) -> Result<i64, String> {
+    let value = match setting {
+        CacheSpill::Enabled(on) => if on { "ON" } else { "OFF" }.to_string(),
+        CacheSpill::Threshold(pages) if (0..=i64::from(i32::MAX)).contains(&pages) => {
+            pages.to_string()
+        }
+        CacheSpill::Threshold(pages) => {
+            return Err(format!(
+                "cache_spill threshold must be between 0 and {}, got {pages}",
+                i32::MAX
+            ))
+        }
+    };
+    conn.execute(&format!("PRAGMA cache_spill = {value}"), ())
+        .await
+        .map_err(|e| format!("Failed to set cache_spill: {e}"))?;
+    pragma_i64(conn, "cache_spill").await
+}
+
+/// Control whether dirty pages spill to disk before a transaction commits.
+///
+/// A transaction's changes stay in the page cache until commit. With spilling on (the
+/// default), once the cache holds more pages than the spill threshold SQLite writes
+/// dirty pages to the database file early, taking an exclusive lock for the rest of the
+/// transaction. With it off, a long transaction keeps every changed page in memory
+/// instead, growing the cache past `cache_size` if it must. The threshold is only
+/// applied to the `main` database; switching spilling on or off covers the connection.
+///
+/// # Arguments
+/// - `conn_id`: Database connection ID
+/// - `setting`: `true` or `false` to switch spilling on or off, or a threshold in pages
+///
+/// # Returns
+/// - `:ok` - Setting applied
+/// - `{:error, reason}` - Invalid setting or query failure
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn set_cache_spill(conn_id: &str, setting: Term) -> NifResult<rustler::Atom> {
+    let setting = match setting.decode::<bool>() {
+        Ok(on) => CacheSpill::Enabled(on),
+        Err(_) => CacheSpill::Threshold(setting.decode::<i64>().map_err(|_| {
+            rustler::Error::Term(Box::new(
+                "cache_spill must be a boolean or a threshold in pages",
+            ))
+        })?),
+    };
+
+    let client = {
+        let conn_map = safe_lock(&CONNECTION_REGISTRY, "set_cache_spill conn_map")?;
+        conn_map
+            .get(conn_id)
+            .cloned()
+            .ok_or_else(|| rustler::Error::Term(Box::new("Invalid connection ID")))?
+    }; // Lock dropped here
+
+    let connection = {
+        let client_guard = safe_lock_arc(&client, "set_cache_spill client")?;
+        client_guard.client.clone()
+    }; // Outer lock dropped here
+
+    // SAFETY: We're inside TOKIO_RUNTIME.block_on(), so this is synchronous execution.
+    // The std::sync::Mutex guards are safe to hold across await points here because
+    // we're not in a true async context - block_on runs the future to completion.
+    #[allow(clippy::await_holding_lock)]
+    TOKIO_RUNTIME.block_on(async {
+        let conn_guard = safe_lock_arc(&connection, "set_cache_spill conn")?;
+
+        set_spill(&conn_guard, setting)
+            .await
+            .map(|_| rustler::types::atom::ok())
+            .map_err(|e| rustler::Error::Term(Box::new(e)))
+    })
+}
+
+/// Read the connection's `PRAGMA cache_spill` value.
+///
+/// # Arguments
+/// - `conn_id`: Database connection ID
+///
+/// # Returns
+/// - `0` when spilling is off; otherwise the pages cached before spilling starts, which
+///   SQLite reports as the larger of the threshold and the cache size
+/// - `{:error, reason}` - Query failure
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn get_cache_spill(conn_id: &str) -> NifResult<i64> {
+    let client = {
+        let conn_map = safe_lock(&CONNECTION_REGISTRY, "get_cache_spill conn_map")?;
+        conn_map
+            .get(conn_id)
+            .cloned()
+            .ok_or_else(|| rustler::Error::Term(Box::new("Invalid connection ID")))?
+    }; // Lock dropped here
+
+    let connection = {
+        let client_guard = safe_lock_arc(&client, "get_cache_spill client")?;
+        client_guard.client.clone()
+    }; // Outer lock dropped here
+
+    // SAFETY: We're inside TOKIO_RUNTIME.block_on(), so this is synchronous execution.
+    // The std::sync::Mutex guards are safe to hold across await points here because
+    // we're not in a true async context - block_on runs the future to completion.
+    #[allow(clippy::await_holding_lock)]
+    TOKIO_RUNTIME.block_on(async {
+        let conn_guard = safe_lock_arc(&connection, "get_cache_spill conn")?;
+
+        pragma_i64(&conn_guard, "cache_spill")
+            .await
+            .map_err(|e| rustler::Error::Term(Box::new(e)))
+    })
+}
+
+/// List the rows that break a foreign key constraint, via `PRAGMA foreign_key_check`.
+pub async fn foreign_key_check(
+    conn: &libsql::Connection,
+) -> Result<Vec<ForeignKeyViolation>, String> {
+    let mut rows = conn
+        .query("PRAGMA foreign_key_check", ())
+        .await
+        .map_err(|e| format!("Foreign key check failed: {e}"))?;
+
+    let mut violations = Vec::new();
+    while let Some(row) = rows
+        .next()
+        .await
+        .map_err(|e| format!("Foreign key check failed: {e}"))?
+    {
+        violations.push(ForeignKeyViolation {
+            table: row
+                .get(0)
+                .map_err(|e| format!("Failed to read violation: {e}"))?,
+            rowid: row
+                .get(1)
+                .map_err(|e| format!("Failed to read violation: {e}"))?,
+            parent: row
+                .get(2)
+                .map_err(|e| format!("Failed to read violation: {e}"))?,
+            fkid: row
+                .get(3)
+                .map_err(|e| format!("Failed to read violation: {e}"))?,
+        });
+    }
+
+    Ok(violations)
+}
+
+/// Run `statements` in a transaction with foreign key enforcement switched off.
+///
+/// Follows SQLite's procedure for redefining a table: turn `foreign_keys` off, run the
+/// statements in a transaction, check the result with `PRAGMA foreign_key_check`, and
+/// commit only if no references are broken. The connection's previous `foreign_keys`
+/// setting is restored afterwards, whatever the outcome.
+///
+/// Returns the violations found, in which case the transaction was rolled back. An empty
+/// list means the statements were committed. Fails if a transaction is already open, as
+/// `foreign_keys` cannot be changed inside one.
+pub async fn migrate_with_foreign_keys_off(
+    conn: &libsql::Connection,
+    statements: &[String],
+) -> Result<Vec<ForeignKeyViolation>, String> {
+    if !conn.is_autocommit() {
+        return Err("Cannot migrate while a transaction is open".to_string());
+    }
+
+    let foreign_keys = pragma_i64(conn, "foreign_keys").await? != 0;
+    if foreign_keys {
+        conn.execute("PRAGMA foreign_keys = OFF", ())
+            .await
+            .map_err(|e| format!("Failed to disable foreign keys: {e}"))?;
+    }
+
+    let result = run_migration(conn, statements).await;
+
+    if !conn.is_autocommit() {
+        let _ = conn.execute("ROLLBACK", ()).await;
+    }
+    if foreign_keys {
+        conn.execute("PRAGMA foreign_keys = ON", ())
+            .await
+            .map_err(|e| format!("Failed to re-enable foreign keys: {e}"))?;
+    }
+
+    result
+}
+
+/// The transactional part of `migrate_with_foreign_keys_off`, which leaves the
+/// transaction open on failure for the caller to roll back.
+async fn run_migration(
+    conn: &libsql::Connection,
+    statements: &[String],
+) -> Result<Vec<ForeignKeyViolation>, String> {
+    conn.execute("BEGIN", ())
+        .await
+        .map_err(|e| format!("Failed to begin migration: {e}"))?;
+
+    for (index, sql) in statements.iter().enumerate() {
+        conn.execute_batch(sql)
+            .await
+            .map_err(|e| format!("Statement {index} failed: {e}"))?;
+    }
+
+    let violations = foreign_key_check(conn).await?;
+    if violations.is_empty() {
+        conn.execute("COMMIT", ())
+            .await
+            .map_err(|e| format!("Failed to commit migration: {e}"))?;
+    }
+
+    Ok(violations)
+}
+
+/// Run a table migration with foreign keys disabled, as SQLite recommends for
+/// redefining tables.
+///
+/// Disables `foreign_keys`, runs `statements` in a transaction, aborts if
+/// `PRAGMA foreign_key_check` finds broken references, otherwise commits, then
+/// restores the previous `foreign_keys` setting. The setting is restored on failure too.
+///
+/// # Arguments
+/// - `conn_id`: Database connection ID
+/// - `statements`: SQL statements to run, in order
+///
+/// # Returns
+/// - `:ok` - The migration was committed
+/// - `{:error, {:foreign_key_violations, [{table, rowid, parent, fkid}]}}` - Broken
+///   references were found and the migration was rolled back
+/// - `{:error, reason}` - A statement failed (rolled back) or a transaction was open
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn migrate_table(conn_id: &str, statements: Vec<String>) -> NifResult<rustler::Atom> {
+    let client = {
+        let conn_map = safe_lock(&CONNECTION_REGISTRY, "migrate_table conn_map")?;
+        conn_map
+            .get(conn_id)
+            .cloned()
+            .ok_or_else(|| rustler::Error::Term(Box::new("Invalid connection ID")))?
+    }; // Lock dropped here
+
+    // Clone the inner connection Arc and drop the outer lock before async operations
+    let connection = {
+        let client_guard = safe_lock_arc(&client, "migrate_table client")?;
+        count_statements(conn_id, statements.len() as u64);
+        client_guard.client.clone()
+    }; // Outer lock dropped here
+
+    // SAFETY: We're inside TOKIO_RUNTIME.block_on(), so this is synchronous execution.
+    // The std::sync::Mutex guards are safe to hold across await points here because
+    // we're not in a true async context - block_on runs the future to completion.
+    #[allow(clippy::await_holding_lock)]
+    let violations = TOKIO_RUNTIME.block_on(async {
+        let conn_guard = safe_lock_arc(&connection, "migrate_table conn")?;
+
+        migrate_with_foreign_keys_off(&conn_guard, &statements)
+            .await
+            .map_err(|e| rustler::Error::Term(Box::new(e)))
+    })?;
+
+    if violations.is_empty() {
+        return Ok(rustler::types::atom::ok());
+    }
+
+    let violations: Vec<(String, Option<i64>, String, i64)> = violations
+        .into_iter()
+        .map(|v| (v.table, v.rowid, v.parent, v.fkid))
+        .collect();
+    Err(rustler::Error::Term(Box::new((
+        foreign_key_violations(),
+        violations,
+    ))))
+}
+
+/// Find the key groups in `table` that would stop a unique index on `columns` being built.
+///
+/// Runs a `GROUP BY ... HAVING count(*) > 1` over `columns`, skipping rows with a NULL
+/// in any of them, as SQLite treats NULLs as distinct in unique indexes. Groups compare
+/// with each column's own collation, as the index would. Returns each duplicated key
+/// with its row count, most duplicated first.
+pub async fn duplicate_keys(
+    conn: &libsql::Connection,
+    table: &str,
+    columns: &[String],
+) -> Result<Vec<(Vec<Value>, i64)>, String> {
+    if columns.is_empty() {
+        return Err("At least one column is required".to_string());
+    }
+
+    // SQLite reads a double-quoted name matching no column as a string literal, which
+    // would group every row under one constant key, so check the names up front
+    let known = table_info(conn, table).await?;
+    if let Some(column) = columns.iter().find(|column| {
+        !known
+            .iter()
+            .any(|(name, _, _)| name.eq_ignore_ascii_case(column))
+    }) {
+        return Err(format!("Column not found: {table}.{column}"));
+    }
+
+    let quoted: Vec<String> = columns
+        .iter()
+        .map(|column| quote_identifier(column, QuoteStyle::Backtick))
+        .collect();
+    let key = quoted.join(", ");
+    let not_null = quoted
+        .iter()
+        .map(|column| format!("{column} IS NOT NULL"))
+        .collect::<Vec<_>>()
+        .join(" AND ");
+    let sql = format!(
+        "SELECT {key}, count(*) FROM {} WHERE {not_null} \
+         GROUP BY {key} HAVING count(*) > 1 ORDER BY count(*) DESC, {key}",
+        quote_identifier(table, QuoteStyle::Backtick)
+    );
+
+    let mut rows = conn
+        .query(&sql, ())
+        .await
+        .map_err(|e| format!("Duplicate check failed: {e}"))?;
+
+    let mut duplicates = Vec::new();
+    while let Some(row) = rows
+        .next()
+        .await
+        .map_err(|e| format!("Duplicate check failed: {e}"))?
+    {
+        let values = (0..columns.len())
+            .map(|index| row.get_value(index as i32))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to read duplicate key: {e}"))?;
+        let count: i64 = row
+            .get(columns.len() as i32)
+            .map_err(|e| format!("Failed to read duplicate count: {e}"))?;
+        duplicates.push((values, count));
+    }
+
+    Ok(duplicates)
+}
+
+/// List the duplicate keys that would make creating a unique index fail.
+///
+/// `CREATE UNIQUE INDEX` fails on the first duplicate without saying which rows clash.
+/// This reports every duplicated key so migrations can clean them up first. Composite
+/// keys are supported, and rows with a NULL in any key column are ignored, as they never
+/// conflict. See `duplicate_keys`.
+///
+/// # Arguments
+/// - `conn_id`: Database connection ID
+/// - `table`: Table the index would be created on
+/// - `columns`: Columns the index would cover, in order
+///
+/// # Returns
+/// - List of `{key_values, count}` tuples, most duplicated first; empty if the index
+///   can be created
+/// - `{:error, reason}` - No columns given, or the table or a column does not exist
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn find_unique_violations<'a>(
+    env: Env<'a>,
+    conn_id: &str,
+    table: &str,
+    columns: Vec<String>,
+) -> NifResult<Vec<(Vec<Term<'a>>, i64)>> {
+    let client = {
+        let conn_map = safe_lock(&CONNECTION_REGISTRY, "find_unique_violations conn_map")?;
+        conn_map
+            .get(conn_id)
+            .cloned()
+            .ok_or_else(|| rustler::Error::Term(Box::new("Invalid connection ID")))?
+    }; // Lock dropped here
+
+    let connection = {
+        let client_guard = safe_lock_arc(&client, "find_unique_violations client")?;
+        count_statements(conn_id, 1);
+        client_guard.client.clone()
+    }; // Outer lock dropped here
+
+    // SAFETY: We're inside TOKIO_RUNTIME.block_on(), so this is synchronous execution.
+    // The std::sync::Mutex guards are safe to hold across await points here because
+    // we're not in a true async context - block_on runs the future to completion.
+    #[allow(clippy::await_holding_lock)]
+    let duplicates = TOKIO_RUNTIME.block_on(async {
+        let conn_guard = safe_lock_arc(&connection, "find_unique_violations conn")?;
+
+        duplicate_keys(&conn_guard, table, &columns)
+            .await
+            .map_err(|e| rustler::Error::Term(Box::new(e)))
+    })?;
+
+    duplicates
+        .into_iter()
+        .map(|(values, count)| {
+            let key = values
+                .iter()
+                .map(|value| encode_value(env, value))
+                .collect::<Option<Vec<_>>>()
+                .ok_or_else(|| {
+                    rustler::Error::Term(Box::new("Failed to allocate binary for key"))
+                })?;
+            Ok((key, count))
+        })
+        .collect()
+}
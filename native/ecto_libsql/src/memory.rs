@@ -0,0 +1,126 @@
+/// Process-wide SQLite memory settings
+///
+/// These settings belong to the SQLite library rather than to any one connection, so
+/// they affect every connection in the BEAM process. libsql doesn't expose the C
+/// functions directly, so each is driven through the equivalent pragma on a short-lived
+/// in-memory connection.
+use crate::constants::*;
+use libsql::Builder;
+use rustler::{Atom, Env, NifResult};
+
+/// Run a process-wide pragma on a throwaway in-memory connection and return its value.
+pub async fn global_pragma(sql: &str) -> Result<i64, String> {
+    let db = Builder::new_local(":memory:")
+        .build()
+        .await
+        .map_err(|e| format!("Failed to open scratch database: {e}"))?;
+    let conn = db
+        .connect()
+        .map_err(|e| format!("Failed to open scratch connection: {e}"))?;
+
+    let mut rows = conn
+        .query(sql, ())
+        .await
+        .map_err(|e| format!("Failed to run {sql}: {e}"))?;
+    let row = rows
+        .next()
+        .await
+        .map_err(|e| format!("Failed to run {sql}: {e}"))?
+        .ok_or_else(|| format!("{sql} returned no value"))?;
+    row.get(0)
+        .map_err(|e| format!("Failed to read result of {sql}: {e}"))
+}
+
+/// Set the soft heap limit for the whole process.
+///
+/// Wraps `sqlite3_soft_heap_limit64` (via `PRAGMA soft_heap_limit`). When SQLite's heap
+/// grows past the limit it frees cache memory to get back under it, but allocations never
+/// fail because of it. A limit of 0 removes the limit.
+///
+/// This is process-global: it applies to every connection, not just one.
+///
+/// # Arguments
+/// - `bytes`: New soft heap limit in bytes, or 0 for no limit
+///
+/// # Returns
+/// - `:ok` - Limit set
+/// - `{:error, reason}` - The limit could not be applied
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn set_soft_heap_limit(bytes: u64) -> NifResult<Atom> {
+    let bytes = i64::try_from(bytes)
+        .map_err(|_| rustler::Error::Term(Box::new("Soft heap limit out of range")))?;
+
+    TOKIO_RUNTIME.block_on(async {
+        global_pragma(&format!("PRAGMA soft_heap_limit = {bytes}"))
+            .await
+            .map(|_| rustler::types::atom::ok())
+            .map_err(|e| rustler::Error::Term(Box::new(e)))
+    })
+}
+
+/// Get the process-wide soft heap limit.
+///
+/// # Returns
+/// - The current soft heap limit in bytes, 0 meaning no limit
+/// - `{:error, reason}` - The limit could not be read
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn get_soft_heap_limit() -> NifResult<u64> {
+    TOKIO_RUNTIME.block_on(async {
+        let bytes = global_pragma("PRAGMA soft_heap_limit")
+            .await
+            .map_err(|e| rustler::Error::Term(Box::new(e)))?;
+        u64::try_from(bytes)
+            .map_err(|_| rustler::Error::Term(Box::new("Soft heap limit out of range")))
+    })
+}
+
+/// Report SQLite's process-wide memory use and its high-water mark.
+///
+/// **NOT SUPPORTED** - The figures come from `sqlite3_memory_used` and
+/// `sqlite3_memory_highwater`. libsql does not expose either, no pragma reports them, and
+/// the `sqlite_memstat` virtual table isn't compiled into libsql's SQLite. Calling them
+/// directly would require raw FFI, which this crate forbids (`unsafe_code = "deny"`).
+///
+/// # Alternatives
+///
+/// 1. **OS-level measurement** - Compare the BEAM's resident memory with
+///    `:erlang.memory(:total)`; the difference is native memory, SQLite's included
+///
+/// 2. **Bounded caches** - Cap SQLite's memory with `set_soft_heap_limit` and each
+///    connection's `PRAGMA cache_size`, rather than measuring it
+///
+/// # Returns
+/// - `{:error, :unsupported}` - Always returns unsupported
+#[rustler::nif]
+pub fn memory_stats(env: Env) -> NifResult<(Atom, Atom)> {
+    Ok((
+        Atom::from_str(env, "error")?,
+        Atom::from_str(env, "unsupported")?,
+    ))
+}
+
+/// Report SQLite's engine-wide status counters, each with its current value and
+/// high-water mark.
+///
+/// **NOT SUPPORTED** - The counters (memory used, page cache used and overflow, malloc
+/// sizes and so on) are read with `sqlite3_status64`, which libsql does not expose and
+/// which has no pragma equivalent. Calling it directly would require raw FFI, which this
+/// crate forbids (`unsafe_code = "deny"`).
+///
+/// # Alternatives
+///
+/// 1. **Per-connection pragmas** - `PRAGMA cache_size`, `page_count` and
+///    `freelist_count` describe each connection's cache and database file
+///
+/// 2. **Storage statistics** - The `dbstat` virtual table, compiled into libsql's
+///    SQLite, reports page usage per table and index
+///
+/// # Returns
+/// - `{:error, :unsupported}` - Always returns unsupported
+#[rustler::nif]
+pub fn status_counters(env: Env) -> NifResult<(Atom, Atom)> {
+    Ok((
+        Atom::from_str(env, "error")?,
+        Atom::from_str(env, "unsupported")?,
+    ))
+}
@@ -1,10 +1,32 @@
 /// Database metadata and introspection functions
 ///
 /// This module provides functions to query database metadata and state information,
-/// such as the number of affected rows, last inserted row IDs, and autocommit mode.
+/// such as the number of affected rows, last inserted row IDs, autocommit mode,
+/// column definitions, indexes, table sizes, and attached databases.
 use crate::constants::*;
-use crate::utils::{safe_lock, safe_lock_arc};
-use rustler::NifResult;
+use crate::models::{ColumnDetail, Generated, Mode};
+use crate::utils::{
+    classify_busy, encode_value, generated_column_expr, last_error_from, quote_identifier,
+    read_busy_timeout, safe_lock, safe_lock_arc, QuoteStyle,
+};
+use libsql::Value;
+use rustler::{Atom, Env, NifResult, Term};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Type alias for the `{code, extended_code, message}` tuple returned by `last_error`
+type LastErrorTuple = (Option<i32>, Option<i32>, String);
+
+/// Type alias for the `{name, type, default, generated}` tuples returned by `column_details`
+type ColumnDetailTuple = (
+    String,
+    String,
+    Option<String>,
+    Option<(Atom, Option<String>)>,
+);
+
+/// Type alias for the `{seq, name, file}` tuples returned by `database_list`
+type DatabaseTuple = (i64, String, Option<String>);
 
 /// Get the rowid of the last inserted row in the current connection.
 ///
@@ -149,3 +171,840 @@ pub fn is_autocommit(conn_id: &str) -> NifResult<bool> {
         Err(rustler::Error::Term(Box::new("Invalid connection ID")))
     }
 }
+
+/// SQLite's autocommit flag for a connection, and whether the transaction registry
+/// holds a transaction for it, as `(sqlite_autocommit, registry_has_txn)`.
+///
+/// Waits for the connection lock, which a running query holds, so callers must run on a
+/// dirty scheduler.
+pub fn transaction_flags(conn_id: &str) -> NifResult<(bool, bool)> {
+    let client = safe_lock(&CONNECTION_REGISTRY, "transaction_flags conn_map")?
+        .get(conn_id)
+        .cloned()
+        .ok_or_else(|| rustler::Error::Term(Box::new("Invalid connection ID")))?;
+
+    let connection = safe_lock_arc(&client, "transaction_flags client")?
+        .client
+        .clone();
+    let sqlite_autocommit = safe_lock_arc(&connection, "transaction_flags conn")?.is_autocommit();
+
+    let registry_has_txn = safe_lock(&TXN_REGISTRY, "transaction_flags txn_registry")?
+        .values()
+        .any(|entry| entry.conn_id == conn_id);
+
+    Ok((sqlite_autocommit, registry_has_txn))
+}
+
+/// Check whether SQLite's transaction state agrees with the transaction registry.
+///
+/// Transactions begun with `begin_transaction` are tracked in the registry, but a raw
+/// `BEGIN` run through `query_args` opens a transaction the registry knows nothing
+/// about, and a raw `COMMIT` can end a registry transaction behind its back. Either
+/// leaves later transaction calls misbehaving, so callers mixing both can check here
+/// and recover (for instance by rolling back).
+///
+/// # Arguments
+/// - `conn_id`: Database connection ID
+///
+/// # Returns
+/// - `{sqlite_autocommit, registry_has_txn, consistent}` - `consistent` is `true` when
+///   SQLite is inside a transaction exactly when the registry holds one
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn transaction_state(conn_id: &str) -> NifResult<(bool, bool, bool)> {
+    let (sqlite_autocommit, registry_has_txn) = transaction_flags(conn_id)?;
+    Ok((
+        sqlite_autocommit,
+        registry_has_txn,
+        sqlite_autocommit != registry_has_txn,
+    ))
+}
+
+/// Probe whether this connection could take the database write lock right now.
+///
+/// Tries `BEGIN IMMEDIATE` with the busy timeout temporarily set to zero, so the probe
+/// never waits, and rolls straight back if it succeeded. The previous busy timeout is
+/// restored afterwards. Refuses to run inside a transaction, where `BEGIN` would fail
+/// regardless of the lock.
+///
+/// Returns `Ok(false)` when another connection holds the write lock (`SQLITE_BUSY`).
+pub async fn probe_write_lock(conn: &libsql::Connection) -> Result<bool, String> {
+    if !conn.is_autocommit() {
+        return Err("Cannot probe the write lock inside a transaction".to_string());
+    }
+
+    let previous_ms = read_busy_timeout(conn).await?;
+
+    conn.busy_timeout(Duration::ZERO)
+        .map_err(|e| format!("Failed to clear busy_timeout: {e}"))?;
+
+    let probed = match conn.execute("BEGIN IMMEDIATE", ()).await {
+        Ok(_) => conn
+            .execute("ROLLBACK", ())
+            .await
+            .map(|_| true)
+            .map_err(|e| format!("Failed to roll back write lock probe: {e}")),
+        Err(e) if classify_busy(&last_error_from(&e)).is_some() => Ok(false),
+        Err(e) => Err(format!("Write lock probe failed: {e}")),
+    };
+
+    let restored = conn
+        .busy_timeout(Duration::from_millis(previous_ms))
+        .map_err(|e| format!("Failed to restore busy_timeout: {e}"));
+
+    // Report the probe's own error in preference to a restore failure
+    let available = probed?;
+    restored?;
+    Ok(available)
+}
+
+/// Check whether a write lock on the database is obtainable right now.
+///
+/// A non-blocking probe for write availability when diagnosing contention: SQLite
+/// has no API reporting who holds the write lock, but `BEGIN IMMEDIATE` fails with
+/// `SQLITE_BUSY` while another connection holds it. The probe never waits and never
+/// leaves a transaction open; see `probe_write_lock`. The answer is only a snapshot,
+/// as another writer may take the lock straight afterwards.
+///
+/// # Arguments
+/// - `conn_id`: Database connection ID
+///
+/// # Returns
+/// - `true` - The write lock was free (and has been released again)
+/// - `false` - Another connection holds the write lock
+/// - `{:error, :unsupported}` - For remote connections, whose locks are on the server
+/// - `{:error, reason}` - Inside a transaction, or the probe failed
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn can_acquire_write_lock(conn_id: &str) -> NifResult<bool> {
+    let client = safe_lock(&CONNECTION_REGISTRY, "can_acquire_write_lock conn_map")?
+        .get(conn_id)
+        .cloned()
+        .ok_or_else(|| rustler::Error::Term(Box::new("Invalid connection ID")))?;
+
+    let connection = {
+        let client_guard = safe_lock_arc(&client, "can_acquire_write_lock client")?;
+        if matches!(client_guard.mode, Mode::Remote) {
+            return Err(rustler::Error::Term(Box::new(unsupported())));
+        }
+        client_guard.client.clone()
+    }; // Outer lock dropped here
+
+    // SAFETY: We're inside TOKIO_RUNTIME.block_on(), so this is synchronous execution.
+    // The std::sync::Mutex guards are safe to hold across await points here because
+    // we're not in a true async context - block_on runs the future to completion.
+    #[allow(clippy::await_holding_lock)]
+    TOKIO_RUNTIME.block_on(async {
+        let conn_guard = safe_lock_arc(&connection, "can_acquire_write_lock conn")?;
+        probe_write_lock(&conn_guard)
+            .await
+            .map_err(|e| rustler::Error::Term(Box::new(e)))
+    })
+}
+
+/// SQLite's compile-time threading mode, from the `THREADSAFE=N` entry of
+/// `PRAGMA compile_options`.
+///
+/// `0` is single-thread, `1` serialized and `2` multi-thread. Returns `None` if the
+/// option isn't listed.
+pub async fn compiled_threadsafe(conn: &libsql::Connection) -> Result<Option<i64>, String> {
+    let mut rows = conn
+        .query("PRAGMA compile_options", ())
+        .await
+        .map_err(|e| format!("Failed to read compile options: {e}"))?;
+    while let Some(row) = rows
+        .next()
+        .await
+        .map_err(|e| format!("Failed to read compile options: {e}"))?
+    {
+        let option: String = row
+            .get(0)
+            .map_err(|e| format!("Failed to read compile options: {e}"))?;
+        if let Some(level) = option.strip_prefix("THREADSAFE=") {
+            return level
+                .parse()
+                .map(Some)
+                .map_err(|_| format!("Unexpected compile option: {option}"));
+        }
+    }
+    Ok(None)
+}
+
+/// Report the threading mode of the SQLite library behind a connection.
+///
+/// A read-only diagnostic. In serialized mode SQLite guards each connection with its
+/// own mutex; in multi-thread mode a connection must never be used by two threads at
+/// once, and single-thread mode disables SQLite's mutexes entirely. Connections here
+/// are always used under the registry's mutexes, so any mode is safe, but the mode
+/// shows whether that external locking is what keeps them safe.
+///
+/// The mode is read from the library's compile options. libsql opens connections
+/// without flags that override it per connection.
+///
+/// # Arguments
+/// - `conn_id`: Database connection ID
+///
+/// # Returns
+/// - `:serialized`, `:multi_thread` or `:single_thread`
+/// - `{:error, :unsupported}` - For remote connections, which run on the server
+/// - `{:error, reason}` - The mode could not be determined
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn connection_threading_mode(conn_id: &str) -> NifResult<Atom> {
+    let client = safe_lock(&CONNECTION_REGISTRY, "connection_threading_mode conn_map")?
+        .get(conn_id)
+        .cloned()
+        .ok_or_else(|| rustler::Error::Term(Box::new("Invalid connection ID")))?;
+
+    let connection = {
+        let client_guard = safe_lock_arc(&client, "connection_threading_mode client")?;
+        if matches!(client_guard.mode, Mode::Remote) {
+            return Err(rustler::Error::Term(Box::new(unsupported())));
+        }
+        client_guard.client.clone()
+    }; // Outer lock dropped here
+
+    // SAFETY: We're inside TOKIO_RUNTIME.block_on(), so this is synchronous execution.
+    // The std::sync::Mutex guards are safe to hold across await points here because
+    // we're not in a true async context - block_on runs the future to completion.
+    #[allow(clippy::await_holding_lock)]
+    let level = TOKIO_RUNTIME.block_on(async {
+        let conn_guard = safe_lock_arc(&connection, "connection_threading_mode conn")?;
+        compiled_threadsafe(&conn_guard)
+            .await
+            .map_err(|e| rustler::Error::Term(Box::new(e)))
+    })?;
+
+    match level {
+        Some(0) => Ok(single_thread()),
+        Some(1) => Ok(serialized()),
+        Some(2) => Ok(multi_thread()),
+        Some(other) => Err(rustler::Error::Term(Box::new(format!(
+            "Unknown threading mode: THREADSAFE={other}"
+        )))),
+        None => Err(rustler::Error::Term(Box::new(
+            "Threading mode not listed in compile options",
+        ))),
+    }
+}
+
+/// Get the most recent database error recorded for a connection.
+///
+/// NIFs that perform database work record any `libsql` error they encounter before
+/// wrapping it into an error term. This exposes the raw code and message so failures
+/// can be diagnosed even where the original error was wrapped or swallowed.
+///
+/// The record is sticky: it is replaced by the next error, not cleared by successful
+/// operations. It is discarded when the connection is closed.
+///
+/// # Arguments
+/// - `conn_id`: Database connection ID
+///
+/// # Returns
+/// - `{code, extended_code, message}` - Codes are `nil` for errors that didn't come from SQLite
+/// - `nil` - No error has been recorded for this connection
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn last_error(conn_id: &str) -> NifResult<Option<LastErrorTuple>> {
+    let conn_map = safe_lock(&CONNECTION_REGISTRY, "last_error conn_map")?;
+    if !conn_map.contains_key(conn_id) {
+        return Err(rustler::Error::Term(Box::new("Invalid connection ID")));
+    }
+    drop(conn_map);
+
+    let registry = safe_lock(&LAST_ERROR_REGISTRY, "last_error registry")?;
+
+    Ok(registry
+        .get(conn_id)
+        .map(|e| (e.code, e.extended_code, e.message.clone())))
+}
+
+/// Describe each column of `table`, including generated columns and their expressions.
+///
+/// `PRAGMA table_xinfo` (unlike `table_info`) lists generated columns and reports
+/// whether they are `VIRTUAL` or `STORED`, but not the generating expression, so that
+/// is read from the table's `CREATE TABLE` SQL. Columns are in declaration order.
+pub async fn column_details(
+    conn: &libsql::Connection,
+    table: &str,
+) -> Result<Vec<ColumnDetail>, String> {
+    let mut rows = conn
+        .query(
+            "SELECT name, sql FROM sqlite_master WHERE type = 'table' AND name = ?1 COLLATE NOCASE",
+            vec![Value::Text(table.to_string())],
+        )
+        .await
+        .map_err(|e| format!("Failed to look up table: {e}"))?;
+    let (table, create_sql): (String, String) = match rows
+        .next()
+        .await
+        .map_err(|e| format!("Failed to read table definition: {e}"))?
+    {
+        Some(row) => (
+            row.get(0)
+                .map_err(|e| format!("Failed to read table name: {e}"))?,
+            row.get(1)
+                .map_err(|e| format!("Failed to read table definition: {e}"))?,
+        ),
+        None => return Err(format!("Table not found: {table}")),
+    };
+    drop(rows);
+
+    let mut rows = conn
+        .query(
+            "SELECT name, type, dflt_value, hidden FROM pragma_table_xinfo(?1) ORDER BY cid",
+            vec![Value::Text(table)],
+        )
+        .await
+        .map_err(|e| format!("Failed to read columns: {e}"))?;
+
+    let mut columns = Vec::new();
+    while let Some(row) = rows
+        .next()
+        .await
+        .map_err(|e| format!("Failed to read column: {e}"))?
+    {
+        let name: String = row
+            .get(0)
+            .map_err(|e| format!("Failed to read column name: {e}"))?;
+        // hidden is 2 for VIRTUAL and 3 for STORED generated columns
+        let hidden: i64 = row
+            .get(3)
+            .map_err(|e| format!("Failed to read column kind: {e}"))?;
+        let generated = matches!(hidden, 2 | 3).then(|| Generated {
+            stored: hidden == 3,
+            expression: generated_column_expr(&create_sql, &name),
+        });
+
+        columns.push(ColumnDetail {
+            column_type: row
+                .get(1)
+                .map_err(|e| format!("Failed to read column type: {e}"))?,
+            default: row
+                .get(2)
+                .map_err(|e| format!("Failed to read column default: {e}"))?,
+            name,
+            generated,
+        });
+    }
+
+    Ok(columns)
+}
+
+/// Describe the columns of a table, including generated columns.
+///
+/// # Arguments
+/// - `conn_id`: Database connection ID
+/// - `table`: Table name, matched case-insensitively
+///
+/// # Returns
+/// - List of `{name, type, default, generated}` tuples in declaration order, where
+///   `generated` is `nil` or `{:virtual | :stored, expression}`
+/// - `{:error, reason}` - Unknown table or query failure
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn table_column_details(conn_id: &str, table: &str) -> NifResult<Vec<ColumnDetailTuple>> {
+    let client = safe_lock(&CONNECTION_REGISTRY, "table_column_details conn_map")?
+        .get(conn_id)
+        .cloned()
+        .ok_or_else(|| rustler::Error::Term(Box::new("Invalid connection ID")))?;
+
+    let connection = {
+        let client_guard = safe_lock_arc(&client, "table_column_details client")?;
+        client_guard.client.clone()
+    }; // Outer lock dropped here
+
+    // SAFETY: We're inside TOKIO_RUNTIME.block_on(), so this is synchronous execution.
+    // The std::sync::Mutex guards are safe to hold across await points here because
+    // we're not in a true async context - block_on runs the future to completion.
+    #[allow(clippy::await_holding_lock)]
+    let columns = TOKIO_RUNTIME.block_on(async {
+        let conn_guard = safe_lock_arc(&connection, "table_column_details conn")?;
+        column_details(&conn_guard, table)
+            .await
+            .map_err(|e| rustler::Error::Term(Box::new(e)))
+    })?;
+
+    Ok(columns
+        .into_iter()
+        .map(|column| {
+            let generated = column.generated.map(|g| {
+                let kind = if g.stored { stored() } else { virtual_() };
+                (kind, g.expression)
+            });
+            (column.name, column.column_type, column.default, generated)
+        })
+        .collect())
+}
+
+/// List the databases open on a connection, as `PRAGMA database_list` reports them.
+///
+/// Returns `(seq, name, file)` for `main`, `temp` (once the connection has used it) and
+/// each attached database, in `seq` order. `file` is the absolute path of the database
+/// file, or `None` for in-memory and temporary databases, which have none.
+pub async fn list_databases(conn: &libsql::Connection) -> Result<Vec<DatabaseTuple>, String> {
+    let mut rows = conn
+        .query("PRAGMA database_list", ())
+        .await
+        .map_err(|e| format!("Failed to query database_list: {e}"))?;
+
+    let mut databases = Vec::new();
+    while let Some(row) = rows
+        .next()
+        .await
+        .map_err(|e| format!("Failed to read database_list: {e}"))?
+    {
+        let seq: i64 = row
+            .get(0)
+            .map_err(|e| format!("Failed to read database seq: {e}"))?;
+        let name: String = row
+            .get(1)
+            .map_err(|e| format!("Failed to read database name: {e}"))?;
+        let file: Option<String> = row
+            .get(2)
+            .map_err(|e| format!("Failed to read database file: {e}"))?;
+        databases.push((seq, name, file.filter(|file| !file.is_empty())));
+    }
+
+    Ok(databases)
+}
+
+/// List the databases open on a connection: `main`, `temp` and any attached.
+///
+/// For managing and debugging `ATTACH`ed databases, such as shard setups. See
+/// `list_databases`.
+///
+/// # Arguments
+/// - `conn_id`: Database connection ID
+///
+/// # Returns
+/// - List of `{seq, name, file}` tuples, where `file` is `nil` for in-memory and
+///   temporary databases
+/// - `{:error, reason}` - Query failure
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn database_list(conn_id: &str) -> NifResult<Vec<DatabaseTuple>> {
+    let client = safe_lock(&CONNECTION_REGISTRY, "database_list conn_map")?
+        .get(conn_id)
+        .cloned()
+        .ok_or_else(|| rustler::Error::Term(Box::new("Invalid connection ID")))?;
+
+    let connection = {
+        let client_guard = safe_lock_arc(&client, "database_list client")?;
+        client_guard.client.clone()
+    }; // Outer lock dropped here
+
+    // SAFETY: We're inside TOKIO_RUNTIME.block_on(), so this is synchronous execution.
+    // The std::sync::Mutex guards are safe to hold across await points here because
+    // we're not in a true async context - block_on runs the future to completion.
+    #[allow(clippy::await_holding_lock)]
+    TOKIO_RUNTIME.block_on(async {
+        let conn_guard = safe_lock_arc(&connection, "database_list conn")?;
+        list_databases(&conn_guard)
+            .await
+            .map_err(|e| rustler::Error::Term(Box::new(e)))
+    })
+}
+
+/// The `(name, declared type, primary key position)` of each column of `table`.
+///
+/// Reads `PRAGMA table_info`, so generated columns are not included. The primary key
+/// position is `0` for columns outside the primary key. Fails with `Table not found`
+/// when the pragma returns nothing, as every table has at least one column.
+pub async fn table_info(
+    conn: &libsql::Connection,
+    table: &str,
+) -> Result<Vec<(String, String, i64)>, String> {
+    let mut rows = conn
+        .query(
+            &format!(
+                "PRAGMA table_info({})",
+                quote_identifier(table, QuoteStyle::DoubleQuote)
+            ),
+            (),
+        )
+        .await
+        .map_err(|e| format!("Failed to read columns: {e}"))?;
+
+    let mut columns = Vec::new();
+    while let Some(row) = rows
+        .next()
+        .await
+        .map_err(|e| format!("Failed to read column: {e}"))?
+    {
+        columns.push((
+            row.get(1)
+                .map_err(|e| format!("Failed to read column name: {e}"))?,
+            row.get(2)
+                .map_err(|e| format!("Failed to read column type: {e}"))?,
+            row.get(5)
+                .map_err(|e| format!("Failed to read primary key flag: {e}"))?,
+        ));
+    }
+
+    if columns.is_empty() {
+        return Err(format!("Table not found: {table}"));
+    }
+    Ok(columns)
+}
+
+/// Whether `column` is indexed, and the indexes on `table` that include it.
+///
+/// Each index comes with the column's zero-based position in it; `0` means the index
+/// leads with the column and can serve lookups on it alone. The column counts as indexed
+/// when some index leads with it, or when it is the table's rowid alias (an `INTEGER
+/// PRIMARY KEY` on a rowid table), which the table itself is keyed by. Names are matched
+/// case-insensitively, as SQLite does.
+pub async fn indexes_with_column(
+    conn: &libsql::Connection,
+    table: &str,
+    column: &str,
+) -> Result<(bool, Vec<(String, usize)>), String> {
+    let columns = table_info(conn, table).await?;
+    let pk_columns = columns.iter().filter(|(_, _, pk)| *pk > 0).count();
+    let Some((_, column_type, pk)) = columns
+        .iter()
+        .find(|(name, _, _)| name.eq_ignore_ascii_case(column))
+    else {
+        return Err(format!("Column not found: {table}.{column}"));
+    };
+    let mut rowid_alias = pk_columns == 1 && *pk > 0 && column_type.eq_ignore_ascii_case("INTEGER");
+
+    let mut indexes = Vec::new();
+    let mut index_names = Vec::new();
+    {
+        let mut rows = conn
+            .query(
+                &format!(
+                    "PRAGMA index_list({})",
+                    quote_identifier(table, QuoteStyle::DoubleQuote)
+                ),
+                (),
+            )
+            .await
+            .map_err(|e| format!("Failed to query index list: {e}"))?;
+        while let Some(row) = rows
+            .next()
+            .await
+            .map_err(|e| format!("Failed to read index list row: {e}"))?
+        {
+            let name: String = row
+                .get(1)
+                .map_err(|e| format!("Failed to get index name: {e}"))?;
+            let origin: String = row
+                .get(3)
+                .map_err(|e| format!("Failed to get index origin: {e}"))?;
+            // A WITHOUT ROWID table's primary key is an index, so its key isn't a rowid alias
+            if origin == "pk" {
+                rowid_alias = false;
+            }
+            index_names.push(name);
+        }
+    }
+
+    for index_name in index_names {
+        let mut rows = conn
+            .query(
+                &format!(
+                    "PRAGMA index_info({})",
+                    quote_identifier(&index_name, QuoteStyle::DoubleQuote)
+                ),
+                (),
+            )
+            .await
+            .map_err(|e| format!("Failed to query index info: {e}"))?;
+        while let Some(row) = rows
+            .next()
+            .await
+            .map_err(|e| format!("Failed to read index info row: {e}"))?
+        {
+            // Expression and rowid entries have no column name
+            let indexed: Option<String> = row
+                .get(2)
+                .map_err(|e| format!("Failed to get column name: {e}"))?;
+            if indexed.is_some_and(|name| name.eq_ignore_ascii_case(column)) {
+                let position: i64 = row
+                    .get(0)
+                    .map_err(|e| format!("Failed to get column position: {e}"))?;
+                indexes.push((index_name.clone(), usize::try_from(position).unwrap_or(0)));
+                break;
+            }
+        }
+    }
+
+    let leading = indexes.iter().any(|(_, position)| *position == 0);
+    Ok((rowid_alias || leading, indexes))
+}
+
+/// Report whether a column is indexed, for warning about filters that will scan.
+///
+/// # Arguments
+/// - `conn_id`: Database connection ID
+/// - `table`: Table name
+/// - `column`: Column name
+///
+/// # Returns
+/// - `{indexed, indexes}` - `indexed` is true when the column is the table's rowid alias
+///   or leads some index, so a filter on it alone can avoid a scan; `indexes` lists
+///   `{index_name, position}` for every index including the column, `0` being leading
+/// - `{:error, reason}` - Unknown table or column, or query failure
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn is_column_indexed(
+    conn_id: &str,
+    table: &str,
+    column: &str,
+) -> NifResult<(bool, Vec<(String, usize)>)> {
+    let client = safe_lock(&CONNECTION_REGISTRY, "is_column_indexed conn_map")?
+        .get(conn_id)
+        .cloned()
+        .ok_or_else(|| rustler::Error::Term(Box::new("Invalid connection ID")))?;
+
+    let connection = {
+        let client_guard = safe_lock_arc(&client, "is_column_indexed client")?;
+        client_guard.client.clone()
+    }; // Outer lock dropped here
+
+    // SAFETY: We're inside TOKIO_RUNTIME.block_on(), so this is synchronous execution.
+    // The std::sync::Mutex guards are safe to hold across await points here because
+    // we're not in a true async context - block_on runs the future to completion.
+    #[allow(clippy::await_holding_lock)]
+    TOKIO_RUNTIME.block_on(async {
+        let conn_guard = safe_lock_arc(&connection, "is_column_indexed conn")?;
+        indexes_with_column(&conn_guard, table, column)
+            .await
+            .map_err(|e| rustler::Error::Term(Box::new(e)))
+    })
+}
+
+/// Row count estimates per table from the `sqlite_stat1` statistics written by `ANALYZE`.
+///
+/// Empty when the database has never been analysed.
+pub async fn stat1_row_estimates(
+    conn: &libsql::Connection,
+) -> Result<HashMap<String, i64>, String> {
+    let mut rows = conn
+        .query(
+            "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'sqlite_stat1'",
+            (),
+        )
+        .await
+        .map_err(|e| format!("Failed to look up statistics: {e}"))?;
+    let has_stats = rows
+        .next()
+        .await
+        .map_err(|e| format!("Failed to look up statistics: {e}"))?
+        .is_some();
+    drop(rows);
+
+    let mut estimates = HashMap::new();
+    if !has_stats {
+        return Ok(estimates);
+    }
+
+    // The first number of each `stat` entry is the row count of the table or index.
+    let mut rows = conn
+        .query(
+            "SELECT tbl, max(CAST(stat AS INTEGER)) FROM sqlite_stat1 GROUP BY tbl",
+            (),
+        )
+        .await
+        .map_err(|e| format!("Failed to read statistics: {e}"))?;
+    while let Some(row) = rows
+        .next()
+        .await
+        .map_err(|e| format!("Failed to read statistics: {e}"))?
+    {
+        let table: String = row
+            .get(0)
+            .map_err(|e| format!("Failed to read statistics: {e}"))?;
+        let estimate: i64 = row
+            .get(1)
+            .map_err(|e| format!("Failed to read statistics: {e}"))?;
+        estimates.insert(table, estimate);
+    }
+    Ok(estimates)
+}
+
+/// Count the rows of every user table in the main schema.
+///
+/// Virtual tables, their shadow tables, and SQLite's internal `sqlite_*` tables are
+/// skipped. With `approximate`, counts come from the `sqlite_stat1` estimates written by
+/// `ANALYZE` where available, and only tables without statistics are counted exactly.
+pub async fn row_counts(
+    conn: &libsql::Connection,
+    approximate: bool,
+) -> Result<HashMap<String, i64>, String> {
+    let mut rows = conn
+        .query(
+            "SELECT name FROM pragma_table_list \
+             WHERE schema = 'main' AND type = 'table' AND name NOT LIKE 'sqlite\\_%' ESCAPE '\\'",
+            (),
+        )
+        .await
+        .map_err(|e| format!("Failed to list tables: {e}"))?;
+    let mut tables = Vec::new();
+    while let Some(row) = rows
+        .next()
+        .await
+        .map_err(|e| format!("Failed to list tables: {e}"))?
+    {
+        tables.push(
+            row.get::<String>(0)
+                .map_err(|e| format!("Failed to read table name: {e}"))?,
+        );
+    }
+    drop(rows);
+
+    let estimates = if approximate {
+        stat1_row_estimates(conn).await?
+    } else {
+        HashMap::new()
+    };
+
+    let mut counts = HashMap::with_capacity(tables.len());
+    for table in tables {
+        if let Some(estimate) = estimates.get(&table) {
+            counts.insert(table, *estimate);
+            continue;
+        }
+
+        let sql = format!(
+            "SELECT count(*) FROM {}",
+            quote_identifier(&table, QuoteStyle::DoubleQuote)
+        );
+        let mut rows = conn
+            .query(&sql, ())
+            .await
+            .map_err(|e| format!("Failed to count rows of {table}: {e}"))?;
+        let count: i64 = match rows
+            .next()
+            .await
+            .map_err(|e| format!("Failed to count rows of {table}: {e}"))?
+        {
+            Some(row) => row
+                .get(0)
+                .map_err(|e| format!("Failed to count rows of {table}: {e}"))?,
+            None => 0,
+        };
+        counts.insert(table, count);
+    }
+
+    Ok(counts)
+}
+
+/// Count the rows of every user table.
+///
+/// Exact counts scan each table, so this can be slow on large databases. Pass
+/// `approximate` to use `ANALYZE` statistics from `sqlite_stat1` where they exist.
+///
+/// # Arguments
+/// - `conn_id`: Database connection ID
+/// - `approximate`: Use `sqlite_stat1` estimates when available
+///
+/// # Returns
+/// - Map of table name to row count
+/// - `{:error, reason}` - Query failure
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn table_row_counts(conn_id: &str, approximate: bool) -> NifResult<HashMap<String, i64>> {
+    let client = safe_lock(&CONNECTION_REGISTRY, "table_row_counts conn_map")?
+        .get(conn_id)
+        .cloned()
+        .ok_or_else(|| rustler::Error::Term(Box::new("Invalid connection ID")))?;
+
+    let connection = {
+        let client_guard = safe_lock_arc(&client, "table_row_counts client")?;
+        client_guard.client.clone()
+    }; // Outer lock dropped here
+
+    // SAFETY: We're inside TOKIO_RUNTIME.block_on(), so this is synchronous execution.
+    // The std::sync::Mutex guards are safe to hold across await points here because
+    // we're not in a true async context - block_on runs the future to completion.
+    #[allow(clippy::await_holding_lock)]
+    TOKIO_RUNTIME.block_on(async {
+        let conn_guard = safe_lock_arc(&connection, "table_row_counts conn")?;
+        row_counts(&conn_guard, approximate)
+            .await
+            .map_err(|e| rustler::Error::Term(Box::new(e)))
+    })
+}
+
+/// Pragmas reported by `pragma_snapshot`, in the order they are read.
+pub const SNAPSHOT_PRAGMAS: [&str; 9] = [
+    "journal_mode",
+    "synchronous",
+    "foreign_keys",
+    "busy_timeout",
+    "cache_size",
+    "page_size",
+    "temp_store",
+    "mmap_size",
+    "wal_autocheckpoint",
+];
+
+/// Read the current value of each of `SNAPSHOT_PRAGMAS`.
+///
+/// Values are returned as SQLite reports them: `journal_mode` as text, the rest as
+/// integers. A pragma that returns no row (e.g. `mmap_size` where memory mapping is
+/// unsupported) is reported as NULL.
+pub async fn pragma_settings(
+    conn: &libsql::Connection,
+) -> Result<Vec<(&'static str, Value)>, String> {
+    let mut settings = Vec::with_capacity(SNAPSHOT_PRAGMAS.len());
+    for pragma in SNAPSHOT_PRAGMAS {
+        let mut rows = conn
+            .query(&format!("PRAGMA {pragma}"), ())
+            .await
+            .map_err(|e| format!("Failed to read {pragma}: {e}"))?;
+        let value = match rows
+            .next()
+            .await
+            .map_err(|e| format!("Failed to read {pragma}: {e}"))?
+        {
+            Some(row) => row
+                .get_value(0)
+                .map_err(|e| format!("Failed to read {pragma}: {e}"))?,
+            None => Value::Null,
+        };
+        settings.push((pragma, value));
+    }
+    Ok(settings)
+}
+
+/// Snapshot the connection's effective configuration pragmas.
+///
+/// A single diagnostic dump of how a connection is actually configured, for tracking
+/// down configuration drift between connections. See `pragma_settings`.
+///
+/// # Arguments
+/// - `env`: Elixir environment
+/// - `conn_id`: Database connection ID
+///
+/// # Returns
+/// - Map of pragma name (as an atom) to its value
+/// - `{:error, reason}` - Query failure
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn pragma_snapshot<'a>(env: Env<'a>, conn_id: &str) -> NifResult<Term<'a>> {
+    let client = safe_lock(&CONNECTION_REGISTRY, "pragma_snapshot conn_map")?
+        .get(conn_id)
+        .cloned()
+        .ok_or_else(|| rustler::Error::Term(Box::new("Invalid connection ID")))?;
+
+    let connection = {
+        let client_guard = safe_lock_arc(&client, "pragma_snapshot client")?;
+        client_guard.client.clone()
+    }; // Outer lock dropped here
+
+    // SAFETY: We're inside TOKIO_RUNTIME.block_on(), so this is synchronous execution.
+    // The std::sync::Mutex guards are safe to hold across await points here because
+    // we're not in a true async context - block_on runs the future to completion.
+    #[allow(clippy::await_holding_lock)]
+    let settings = TOKIO_RUNTIME.block_on(async {
+        let conn_guard = safe_lock_arc(&connection, "pragma_snapshot conn")?;
+        pragma_settings(&conn_guard)
+            .await
+            .map_err(|e| rustler::Error::Term(Box::new(e)))
+    })?;
+
+    let mut snapshot = Term::map_new(env);
+    for (pragma, value) in settings {
+        let key = Atom::from_str(env, pragma)?;
+        let value = encode_value(env, &value)
+            .ok_or_else(|| rustler::Error::Term(Box::new("Failed to encode pragma value")))?;
+        snapshot = snapshot.map_put(key, value)?;
+    }
+    Ok(snapshot)
+}
@@ -4,6 +4,8 @@
 /// including connection wrappers, transaction entries, and cursor state.
 use libsql::{Transaction, Value};
 use rustler::Resource;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 
 /// LibSQL connection wrapper - resource passed to Elixir
@@ -16,6 +18,38 @@ pub struct LibSQLConn {
     pub db: libsql::Database,
     /// An active connection to the database
     pub client: Arc<std::sync::Mutex<libsql::Connection>>,
+    /// Connection mode the database was opened with
+    pub mode: Mode,
+    /// How duplicate result column names are disambiguated
+    pub column_naming: ColumnNaming,
+}
+
+/// A connection's database path, recorded once at connect time
+///
+/// Local paths are also kept in canonical form, so `./app.db` and `app.db` match without
+/// touching the filesystem on every lookup. Paths that can't be canonicalised (remote
+/// URLs, in-memory databases, missing files) are compared verbatim.
+#[derive(Debug, Clone)]
+pub struct DatabasePath {
+    /// Path as given at connect time (the URL for remote connections)
+    pub path: String,
+    /// Canonical form of `path`, if it names an existing file
+    pub canonical: Option<PathBuf>,
+}
+
+impl DatabasePath {
+    /// Record `path`, canonicalising it if it names an existing file.
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: path.to_string(),
+            canonical: std::fs::canonicalize(path).ok(),
+        }
+    }
+
+    /// Whether `self` and `other` refer to the same database.
+    pub fn matches(&self, other: &DatabasePath) -> bool {
+        self.path == other.path || (self.canonical.is_some() && self.canonical == other.canonical)
+    }
 }
 
 /// Resource implementation for LibSQLConn
@@ -37,6 +71,97 @@ pub struct CursorData {
     pub position: usize,
 }
 
+impl CursorData {
+    /// Estimated bytes held by the cursor: the row and column buffers, each value,
+    /// and the heap data of text and blob values.
+    pub fn estimated_memory(&self) -> usize {
+        let columns: usize = self
+            .columns
+            .iter()
+            .map(|name| std::mem::size_of::<String>() + name.capacity())
+            .sum();
+        let rows: usize = self
+            .rows
+            .iter()
+            .map(|row| {
+                std::mem::size_of::<Vec<Value>>()
+                    + row.capacity() * std::mem::size_of::<Value>()
+                    + row
+                        .iter()
+                        .map(|value| match value {
+                            Value::Text(text) => text.capacity(),
+                            Value::Blob(blob) => blob.capacity(),
+                            _ => 0,
+                        })
+                        .sum::<usize>()
+            })
+            .sum();
+        let spare_rows =
+            (self.rows.capacity() - self.rows.len()) * std::mem::size_of::<Vec<Value>>();
+        columns + rows + spare_rows
+    }
+}
+
+/// Broad class of a database error, for per-connection error counters
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorClass {
+    /// A constraint was violated (`SQLITE_CONSTRAINT`)
+    Constraint,
+    /// A lock could not be acquired (`SQLITE_BUSY` or `SQLITE_LOCKED`)
+    Busy,
+    /// The SQL could not be parsed
+    Syntax,
+    /// Any other error, including those that didn't come from SQLite
+    Other,
+}
+
+impl ErrorClass {
+    /// Every class, in the order counters are reported
+    pub const ALL: [ErrorClass; 4] = [
+        ErrorClass::Constraint,
+        ErrorClass::Busy,
+        ErrorClass::Syntax,
+        ErrorClass::Other,
+    ];
+}
+
+/// Per-connection error counters, one per `ErrorClass`
+///
+/// Plain atomics, so error paths can count without locking the connection.
+#[derive(Debug, Default)]
+pub struct ErrorCounts {
+    constraint: AtomicU64,
+    busy: AtomicU64,
+    syntax: AtomicU64,
+    other: AtomicU64,
+}
+
+impl ErrorCounts {
+    fn counter(&self, class: ErrorClass) -> &AtomicU64 {
+        match class {
+            ErrorClass::Constraint => &self.constraint,
+            ErrorClass::Busy => &self.busy,
+            ErrorClass::Syntax => &self.syntax,
+            ErrorClass::Other => &self.other,
+        }
+    }
+
+    /// Count one error of `class`.
+    pub fn record(&self, class: ErrorClass) {
+        self.counter(class).fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Number of errors of `class` counted so far.
+    pub fn get(&self, class: ErrorClass) -> u64 {
+        self.counter(class).load(Ordering::Relaxed)
+    }
+
+    /// Reset the count for `class` to zero, returning the count it replaced.
+    pub fn take(&self, class: ErrorClass) -> u64 {
+        self.counter(class).swap(0, Ordering::Relaxed)
+    }
+}
+
 /// Transaction entry with ownership tracking
 ///
 /// Tracks which connection owns a transaction and holds the transaction reference.
@@ -45,6 +170,88 @@ pub struct TransactionEntry {
     pub conn_id: String,
     /// The actual transaction object
     pub transaction: Transaction,
+    /// Names of the savepoints open within the transaction, innermost last
+    pub savepoints: Vec<String>,
+    /// Connection busy timeout to restore when the transaction ends, set when the
+    /// transaction was begun with its own busy timeout
+    pub restore_busy_timeout_ms: Option<u64>,
+}
+
+/// The SQL a prepared statement was built from and the schema it was built against
+///
+/// Stored alongside each registered statement so it can be checked for staleness after
+/// DDL, and re-prepared from the same SQL.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatementSource {
+    /// SQL the statement was prepared from
+    pub sql: String,
+    /// `PRAGMA schema_version` at the time the statement was prepared, or `None` on
+    /// remote connections, where the server prepares the SQL afresh for every run
+    pub schema_version: Option<i64>,
+}
+
+/// A row reported by `PRAGMA foreign_key_check`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForeignKeyViolation {
+    /// Table holding the row with the broken reference
+    pub table: String,
+    /// Rowid of the offending row, or `None` for `WITHOUT ROWID` tables
+    pub rowid: Option<i64>,
+    /// Table the foreign key refers to
+    pub parent: String,
+    /// Index of the foreign key in `PRAGMA foreign_key_list` for `table`
+    pub fkid: i64,
+}
+
+/// Most recent database error recorded for a connection
+///
+/// Captured from the `libsql::Error` returned by NIFs that perform database work,
+/// so errors can be inspected even when they were swallowed or wrapped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LastError {
+    /// Primary SQLite result code (e.g. 19 for `SQLITE_CONSTRAINT`), if available
+    pub code: Option<i32>,
+    /// Extended SQLite result code (e.g. 2067 for `SQLITE_CONSTRAINT_UNIQUE`), if available
+    pub extended_code: Option<i32>,
+    /// Error message as reported by SQLite or libsql
+    pub message: String,
+}
+
+/// A table column as described by `PRAGMA table_xinfo` and the table's SQL
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnDetail {
+    /// Column name
+    pub name: String,
+    /// Declared type, empty when none was given
+    pub column_type: String,
+    /// Default value expression as written in the schema, if any
+    pub default: Option<String>,
+    /// How the column is generated, if it is a generated column
+    pub generated: Option<Generated>,
+}
+
+/// How a generated column's value is kept
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Generated {
+    /// `true` for `STORED` columns, `false` for `VIRTUAL` ones
+    pub stored: bool,
+    /// The generating expression, or `None` if it couldn't be read from the schema
+    pub expression: Option<String>,
+}
+
+/// Which kind of lock a busy or locked error conflicted with
+///
+/// SQLite doesn't report which connection is blocking, but the result code says enough
+/// to decide between retrying the statement and restarting the transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BusyKind {
+    /// The conflict is with a read: this transaction's read snapshot is out of date
+    /// (`SQLITE_BUSY_SNAPSHOT`), or a table is in use by a reading statement
+    /// (`SQLITE_LOCKED`). Retrying the statement won't help; restart the transaction.
+    ReadLock,
+    /// Another connection holds the write lock (`SQLITE_BUSY` and its other extended
+    /// codes). Retrying after a backoff can succeed.
+    WriteLock,
 }
 
 /// Connection mode enumeration
@@ -59,3 +266,157 @@ pub enum Mode {
     /// Local replica with remote sync
     RemoteReplica,
 }
+
+/// Where a statement's writes land, given the connection mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WriteRoute {
+    /// The statement doesn't write
+    Read,
+    /// Written to the local database file
+    Local,
+    /// Sent to the remote server
+    Remote,
+    /// Sent to the remote primary; the local replica only sees the write once the
+    /// primary accepts it and the change is synced back
+    Replica,
+}
+
+/// Conflict resolution algorithm of an `INSERT`, `REPLACE` or `UPDATE` statement
+///
+/// Chosen with an `OR <action>` clause (or by `REPLACE` itself); statements without
+/// one use `Abort`. The action changes what the statement's change count means.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictAction {
+    /// Undo the failing statement and return an error (the default)
+    Abort,
+    /// Return an error, keeping changes the statement already made
+    Fail,
+    /// Skip the conflicting row; skipped rows are not counted as changes
+    Ignore,
+    /// Delete the conflicting rows, then write; only the written rows are counted
+    Replace,
+    /// Roll back the whole transaction and return an error
+    Rollback,
+}
+
+/// Category of operation an authorizer deny rule matches
+///
+/// Each groups one or more of SQLite's authorizer action codes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthorizerAction {
+    /// Reading a table column
+    Read,
+    /// Inserting rows into a table
+    Insert,
+    /// Updating a table column
+    Update,
+    /// Deleting rows from a table
+    Delete,
+    /// Creating, dropping, altering, reindexing or analysing schema objects
+    Schema,
+    /// Running any pragma, whether reading or setting it
+    Pragma,
+    /// Running a pragma with a value, such as `PRAGMA journal_mode = WAL`
+    PragmaWrite,
+    /// Attaching a database file
+    Attach,
+    /// Detaching a database
+    Detach,
+    /// Beginning, committing or rolling back a transaction
+    Transaction,
+    /// Calling an SQL function
+    Function,
+}
+
+/// Authorizer rule denying an action, everywhere or for one named object
+///
+/// `target` names the table, pragma, function, attached file or database the rule is
+/// limited to, compared case-insensitively; `None` denies the action for all of them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DenyRule {
+    pub action: AuthorizerAction,
+    pub target: Option<String>,
+}
+
+/// Encoding of text stored in a blob column
+///
+/// Legacy databases sometimes keep non-UTF-8 text as blobs; these name the encodings
+/// such blobs can be decoded from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextEncoding {
+    /// UTF-16, little-endian, without a byte order mark
+    Utf16Le,
+    /// ISO-8859-1, one byte per character
+    Latin1,
+}
+
+/// `PRAGMA cache_spill` setting
+///
+/// Whether dirty pages may be written to the database file before a transaction
+/// commits, and how full the page cache must be before they are.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheSpill {
+    /// Switch spilling on or off, keeping the current threshold
+    Enabled(bool),
+    /// Spill once this many pages are cached; `0` switches spilling off
+    Threshold(i64),
+}
+
+/// Predefined transformation applied to a result column as rows are collected
+///
+/// Text is transformed in place; `Base64` also encodes blobs. Other values are
+/// returned unchanged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnTransform {
+    /// Lowercase text (Unicode-aware, unlike SQLite's ASCII-only `lower()`)
+    Lower,
+    /// Uppercase text (Unicode-aware, unlike SQLite's ASCII-only `upper()`)
+    Upper,
+    /// Strip leading and trailing whitespace from text
+    Trim,
+    /// Encode text or blob bytes as standard padded base64 text
+    Base64,
+}
+
+/// Bytes uploaded to a blob builder so far
+#[derive(Debug)]
+pub struct BlobBuilder {
+    /// Chunks appended so far, in order
+    pub bytes: Vec<u8>,
+    /// When the builder was created or last appended to, for expiring abandoned builders
+    pub touched: std::time::Instant,
+}
+
+/// A result cell from a query run with lazy blob materialisation
+///
+/// Blobs that can be traced back to a row are replaced by a reference carrying the
+/// row's rowid, the blob's origin column, its size in bytes and the schema its table
+/// lives in.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LazyCell {
+    /// Any value other than a referenced blob, returned as-is
+    Value(Value),
+    /// A blob left in the database, to be read later by rowid
+    BlobRef {
+        rowid: i64,
+        column: String,
+        size: u64,
+        schema: String,
+    },
+}
+
+/// Result column naming strategy
+///
+/// Controls how duplicate column names (e.g. two `id` columns from a join) are
+/// disambiguated in query results.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ColumnNaming {
+    /// Column names exactly as SQLite reports them, duplicates included
+    #[default]
+    Raw,
+    /// Duplicates after the first get a numeric suffix (`id`, `id_2`)
+    IndexSuffix,
+    /// Duplicates are prefixed with their origin table (`users.id`), falling back
+    /// to a numeric suffix when the table is unknown or still ambiguous
+    TablePrefix,
+}
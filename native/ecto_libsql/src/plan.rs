@@ -0,0 +1,856 @@
+/// Query plan analysis for LibSQL databases
+///
+/// This module runs `EXPLAIN QUERY PLAN` and interprets its output, turning the
+/// human-readable plan details into structured information about how each table
+/// is accessed, and suggests indexes for tables that are scanned. It also times
+/// repeated runs of a query, for quick benchmarks.
+use crate::constants::*;
+use crate::metadata::stat1_row_estimates;
+use crate::utils::{
+    count_statements, decode_term_to_value, normalise_sql, quote_identifier, record_last_error,
+    safe_lock, safe_lock_arc, QuoteStyle,
+};
+use libsql::Value;
+use rustler::{Encoder, Env, NifResult, Term};
+use std::time::Instant;
+
+/// Most timed runs `benchmark_query` will make in one call
+///
+/// The connection stays locked for every run, so an unbounded count could shut other
+/// callers out of it indefinitely.
+pub const MAX_BENCHMARK_ITERATIONS: u32 = 10_000;
+
+/// How a query plan step reads a table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TableAccess {
+    /// Every row is visited (including full passes over a covering index and
+    /// automatic indexes, which SQLite builds by scanning the table)
+    Scan,
+    /// Rows are located through the named index
+    Index(String),
+    /// Rows are located through the rowid or primary key
+    PrimaryKey,
+}
+
+/// Interpret one `detail` line of `EXPLAIN QUERY PLAN` output.
+///
+/// Returns the table name (its alias, if the query gives it one) and how it is
+/// accessed for `SCAN`/`SEARCH` steps on a table, or `None` for other steps
+/// (temporary B-trees, subquery and constant-row scans, compound query markers).
+pub fn parse_plan_detail(detail: &str) -> Option<(String, TableAccess)> {
+    let mut tokens = detail.split_whitespace().peekable();
+    let step = tokens.next()?;
+    if step != "SCAN" && step != "SEARCH" {
+        return None;
+    }
+
+    // Older SQLite versions write `SCAN TABLE t`
+    if tokens.peek() == Some(&"TABLE") {
+        tokens.next();
+    }
+    let table = tokens.next()?;
+    if table.starts_with('(') || table == "CONSTANT" || table == "SUBQUERY" {
+        return None;
+    }
+
+    let rest: Vec<&str> = tokens.collect();
+    let access = match rest.iter().position(|t| *t == "USING") {
+        Some(using) if step == "SEARCH" => {
+            let how = &rest[using + 1..];
+            if how.first() == Some(&"AUTOMATIC") {
+                TableAccess::Scan
+            } else if let Some(index) = how.iter().position(|t| *t == "INDEX") {
+                how.get(index + 1).map_or(TableAccess::Scan, |name| {
+                    TableAccess::Index((*name).to_string())
+                })
+            } else if how.contains(&"PRIMARY") || how.contains(&"ROWID") {
+                TableAccess::PrimaryKey
+            } else {
+                TableAccess::Scan
+            }
+        }
+        _ => TableAccess::Scan,
+    };
+
+    Some((table.to_string(), access))
+}
+
+/// Run `EXPLAIN QUERY PLAN` for `sql` and return the plan's detail lines in order.
+pub async fn query_plan_details(
+    conn: &libsql::Connection,
+    sql: &str,
+    params: Vec<Value>,
+) -> Result<Vec<String>, libsql::Error> {
+    let mut rows = conn
+        .query(&format!("EXPLAIN QUERY PLAN {sql}"), params)
+        .await?;
+
+    // Columns are (id, parent, notused, detail)
+    let mut details = Vec::new();
+    while let Some(row) = rows.next().await? {
+        details.push(row.get::<String>(3)?);
+    }
+    Ok(details)
+}
+
+/// Report how each table in a query is accessed, using `EXPLAIN QUERY PLAN`.
+///
+/// The query is planned but not executed. Each table access step in the plan is
+/// returned as `{table, access}`, in plan order, where `table` is the table's alias
+/// if the query gives it one, and `access` is:
+/// - `:scan` - A full table scan (including automatic indexes, which require one)
+/// - `{:index, name}` - A lookup through the named index
+/// - `{:index, :primary_key}` - A lookup by rowid or primary key
+///
+/// # Arguments
+/// - `env`: Elixir environment
+/// - `conn_id`: Database connection ID
+/// - `sql`: Query to analyse
+/// - `args`: Query parameters
+///
+/// # Returns
+/// - List of `{table, access}` tuples (empty if the query reads no tables)
+/// - `{:error, reason}` - The query could not be planned
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn index_usage<'a>(
+    env: Env<'a>,
+    conn_id: &str,
+    sql: &str,
+    args: Vec<Term<'a>>,
+) -> NifResult<Vec<(String, Term<'a>)>> {
+    let client = {
+        let conn_map = safe_lock(&CONNECTION_REGISTRY, "index_usage conn_map")?;
+        conn_map
+            .get(conn_id)
+            .cloned()
+            .ok_or_else(|| rustler::Error::Term(Box::new("Invalid connection ID")))?
+    }; // Lock dropped here
+
+    let params: Vec<Value> = args
+        .into_iter()
+        .map(|t| decode_term_to_value(t))
+        .collect::<Result<_, _>>()
+        .map_err(|e| rustler::Error::Term(Box::new(e)))?;
+
+    // Clone the inner connection Arc and drop the outer lock before async operations
+    let connection = {
+        let client_guard = safe_lock_arc(&client, "index_usage client")?;
+        client_guard.client.clone()
+    }; // Outer lock dropped here
+
+    // SAFETY: We're inside TOKIO_RUNTIME.block_on(), so this is synchronous execution.
+    // The std::sync::Mutex guards are safe to hold across await points here because
+    // we're not in a true async context - block_on runs the future to completion.
+    #[allow(clippy::await_holding_lock)]
+    let details = TOKIO_RUNTIME.block_on(async {
+        let conn_guard = safe_lock_arc(&connection, "index_usage conn")?;
+
+        query_plan_details(&conn_guard, sql, params)
+            .await
+            .map_err(|e| {
+                record_last_error(conn_id, &e);
+                rustler::Error::Term(Box::new(format!("Failed to explain query: {e}")))
+            })
+    })?;
+
+    Ok(details
+        .iter()
+        .filter_map(|detail| parse_plan_detail(detail))
+        .map(|(table, access)| {
+            let access = match access {
+                TableAccess::Scan => scan().encode(env),
+                TableAccess::Index(name) => (index(), name).encode(env),
+                TableAccess::PrimaryKey => (index(), primary_key()).encode(env),
+            };
+            (table, access)
+        })
+        .collect())
+}
+
+/// Tables a plan reads with a full scan, in plan order and without repeats.
+pub fn scanned_tables(details: &[String]) -> Vec<String> {
+    let mut tables: Vec<String> = Vec::new();
+    for (table, access) in details.iter().filter_map(|d| parse_plan_detail(d)) {
+        if access == TableAccess::Scan && !tables.contains(&table) {
+            tables.push(table);
+        }
+    }
+    tables
+}
+
+/// Estimate the cost of a write statement before running it, using `EXPLAIN QUERY PLAN`.
+///
+/// The statement is planned but not executed, so nothing is written. Meant for checking
+/// that an `UPDATE` or `DELETE` will not scan a huge table, e.g. before a migration runs.
+///
+/// # Arguments
+/// - `env`: Elixir environment
+/// - `conn_id`: Database connection ID
+/// - `sql`: Statement to analyse
+/// - `args`: Statement parameters
+///
+/// # Returns
+/// - `%{full_scan: boolean, scans: [{table, estimated_rows}]}` - `scans` lists each table
+///   read with a full scan, with its row count from `sqlite_stat1` or `nil` if the table
+///   has not been analysed (or the statement refers to it by an alias)
+/// - `{:error, reason}` - The statement could not be planned
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn estimate_write_cost<'a>(
+    env: Env<'a>,
+    conn_id: &str,
+    sql: &str,
+    args: Vec<Term<'a>>,
+) -> NifResult<Term<'a>> {
+    let client = {
+        let conn_map = safe_lock(&CONNECTION_REGISTRY, "estimate_write_cost conn_map")?;
+        conn_map
+            .get(conn_id)
+            .cloned()
+            .ok_or_else(|| rustler::Error::Term(Box::new("Invalid connection ID")))?
+    }; // Lock dropped here
+
+    let params: Vec<Value> = args
+        .into_iter()
+        .map(|t| decode_term_to_value(t))
+        .collect::<Result<_, _>>()
+        .map_err(|e| rustler::Error::Term(Box::new(e)))?;
+
+    let connection = {
+        let client_guard = safe_lock_arc(&client, "estimate_write_cost client")?;
+        client_guard.client.clone()
+    }; // Outer lock dropped here
+
+    // SAFETY: We're inside TOKIO_RUNTIME.block_on(), so this is synchronous execution.
+    // The std::sync::Mutex guards are safe to hold across await points here because
+    // we're not in a true async context - block_on runs the future to completion.
+    #[allow(clippy::await_holding_lock)]
+    let (tables, estimates) = TOKIO_RUNTIME.block_on(async {
+        let conn_guard = safe_lock_arc(&connection, "estimate_write_cost conn")?;
+
+        let details = query_plan_details(&conn_guard, sql, params)
+            .await
+            .map_err(|e| {
+                record_last_error(conn_id, &e);
+                rustler::Error::Term(Box::new(format!("Failed to explain statement: {e}")))
+            })?;
+        let tables = scanned_tables(&details);
+
+        let estimates = if tables.is_empty() {
+            Default::default()
+        } else {
+            stat1_row_estimates(&conn_guard)
+                .await
+                .map_err(|e| rustler::Error::Term(Box::new(e)))?
+        };
+        Ok::<_, rustler::Error>((tables, estimates))
+    })?;
+
+    let scans: Vec<(String, Option<i64>)> = tables
+        .into_iter()
+        .map(|table| {
+            let rows = estimates.get(&table).copied();
+            (table, rows)
+        })
+        .collect();
+
+    Term::map_from_pairs(
+        env,
+        &[
+            (full_scan().encode(env), (!scans.is_empty()).encode(env)),
+            (scans_key().encode(env), scans.encode(env)),
+        ],
+    )
+}
+
+/// A column compared in a `WHERE` or `ON` clause, as found by `filter_columns`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilterColumn {
+    /// Table name or alias the column was qualified with, if any
+    pub qualifier: Option<String>,
+    pub column: String,
+    /// Compared with `=`, `IN` or `IS`, rather than as a range
+    pub equality: bool,
+}
+
+/// Split SQL into tokens: lowercased words, unquoted identifiers, `?` for literals and
+/// parameters, and single punctuation characters (or two-character comparisons).
+fn sql_tokens(sql: &str) -> Vec<String> {
+    let normalised = normalise_sql(sql);
+    let chars: Vec<char> = normalised.chars().collect();
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+
+    while pos < chars.len() {
+        let c = chars[pos];
+        if c == ' ' {
+            pos += 1;
+        } else if c == '"' || c == '`' || c == '[' {
+            // Quoted identifier, with doubled quotes as escapes (brackets have none)
+            let close = if c == '[' { ']' } else { c };
+            let mut name = String::new();
+            pos += 1;
+            while pos < chars.len() {
+                if chars[pos] == close {
+                    if close != ']' && chars.get(pos + 1) == Some(&close) {
+                        name.push(close);
+                        pos += 2;
+                        continue;
+                    }
+                    pos += 1;
+                    break;
+                }
+                name.push(chars[pos]);
+                pos += 1;
+            }
+            tokens.push(name);
+        } else if c.is_alphanumeric() || c == '_' || c == '$' {
+            let start = pos;
+            while pos < chars.len()
+                && (chars[pos].is_alphanumeric() || matches!(chars[pos], '_' | '$'))
+            {
+                pos += 1;
+            }
+            tokens.push(chars[start..pos].iter().collect());
+        } else {
+            let pair: String = chars[pos..(pos + 2).min(chars.len())].iter().collect();
+            if matches!(pair.as_str(), "<=" | ">=" | "==" | "!=" | "<>") {
+                tokens.push(pair);
+                pos += 2;
+            } else {
+                tokens.push(c.to_string());
+                pos += 1;
+            }
+        }
+    }
+    tokens
+}
+
+/// Keywords that can sit where an identifier might, and so are never column or table names
+const NON_IDENTIFIERS: &[&str] = &[
+    "?",
+    "all",
+    "and",
+    "as",
+    "asc",
+    "between",
+    "by",
+    "case",
+    "cast",
+    "collate",
+    "cross",
+    "delete",
+    "desc",
+    "distinct",
+    "else",
+    "end",
+    "escape",
+    "except",
+    "exists",
+    "false",
+    "from",
+    "glob",
+    "group",
+    "having",
+    "in",
+    "indexed",
+    "inner",
+    "insert",
+    "intersect",
+    "into",
+    "is",
+    "join",
+    "left",
+    "like",
+    "limit",
+    "natural",
+    "not",
+    "null",
+    "offset",
+    "on",
+    "or",
+    "order",
+    "outer",
+    "regexp",
+    "returning",
+    "right",
+    "select",
+    "set",
+    "then",
+    "true",
+    "union",
+    "update",
+    "using",
+    "values",
+    "when",
+    "where",
+    "window",
+    "with",
+];
+
+fn is_identifier(token: &str) -> bool {
+    token
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_alphabetic() || c == '_')
+        && !NON_IDENTIFIERS.contains(&token)
+}
+
+/// The clause a token sits in, tracked per level of parentheses
+#[derive(Clone, Copy, PartialEq)]
+enum Clause {
+    Other,
+    From,
+    Filter,
+}
+
+/// Walk `tokens`, calling `visit` with each token's index and the clause it sits in.
+fn walk_clauses(tokens: &[String], mut visit: impl FnMut(usize, Clause)) {
+    let mut clauses = vec![Clause::Other];
+    for (i, token) in tokens.iter().enumerate() {
+        match token.as_str() {
+            "(" => clauses.push(Clause::Other),
+            ")" if clauses.len() > 1 => {
+                clauses.pop();
+            }
+            "where" | "on" => {
+                if let Some(clause) = clauses.last_mut() {
+                    *clause = Clause::Filter;
+                }
+            }
+            "from" | "join" => {
+                if let Some(clause) = clauses.last_mut() {
+                    *clause = Clause::From;
+                }
+            }
+            "select" | "group" | "order" | "limit" | "set" | "values" | "returning" | "having"
+            | "window" | "union" | "except" | "intersect" => {
+                if let Some(clause) = clauses.last_mut() {
+                    *clause = Clause::Other;
+                }
+            }
+            _ => {}
+        }
+        visit(i, clauses.last().copied().unwrap_or(Clause::Other));
+    }
+}
+
+/// Tables named in `FROM` and `JOIN` clauses, as `(name_or_alias, table)` pairs.
+///
+/// Schema qualifiers are dropped. Subqueries and table-valued functions are skipped.
+pub fn table_aliases(sql: &str) -> Vec<(String, String)> {
+    let tokens = sql_tokens(sql);
+    let token = |i: usize| tokens.get(i).map_or("", String::as_str);
+    let mut aliases = Vec::new();
+
+    walk_clauses(&tokens, |i, clause| {
+        let starts_table = match token(i) {
+            "from" | "join" => true,
+            "," => clause == Clause::From,
+            _ => false,
+        };
+        if !starts_table || !is_identifier(token(i + 1)) {
+            return;
+        }
+
+        let mut end = i + 1;
+        if token(end + 1) == "." && is_identifier(token(end + 2)) {
+            end += 2;
+        }
+        if token(end + 1) == "(" {
+            return;
+        }
+        let table = token(end).to_string();
+        let alias_at = if token(end + 1) == "as" {
+            end + 2
+        } else {
+            end + 1
+        };
+        let alias = if is_identifier(token(alias_at)) {
+            token(alias_at).to_string()
+        } else {
+            table.clone()
+        };
+        aliases.push((alias, table));
+    });
+    aliases
+}
+
+/// Columns compared against something in `WHERE` and `ON` clauses, in order of appearance.
+///
+/// A token scan rather than a parse: it finds `column <op> ...` and `... <op> column`
+/// comparisons (`=`, `==`, `<`, `<=`, `>`, `>=`, `IN`, `IS`, `BETWEEN`), with or without
+/// a table qualifier. Columns wrapped in function calls or expressions are not reported,
+/// as a plain index on them would not help.
+pub fn filter_columns(sql: &str) -> Vec<FilterColumn> {
+    let tokens = sql_tokens(sql);
+    let token = |i: usize| tokens.get(i).map_or("", String::as_str);
+
+    // A column reference ending at `end` (inclusive), as `(qualifier, column)`
+    let reference_ending = |end: usize| -> Option<(Option<String>, String)> {
+        let column = token(end);
+        if !is_identifier(column) {
+            return None;
+        }
+        if end >= 2 && token(end - 1) == "." && is_identifier(token(end - 2)) {
+            Some((Some(token(end - 2).to_string()), column.to_string()))
+        } else if end >= 1 && token(end - 1) == "." {
+            None
+        } else {
+            Some((None, column.to_string()))
+        }
+    };
+    // A column reference starting at `start`, not followed by a call or a further operand
+    let reference_starting = |start: usize| -> Option<(Option<String>, String)> {
+        let end = if token(start + 1) == "." {
+            start + 2
+        } else {
+            start
+        };
+        if token(end + 1) == "(" || token(end + 1) == "." {
+            return None;
+        }
+        reference_ending(end)
+    };
+
+    let mut columns = Vec::new();
+    walk_clauses(&tokens, |i, clause| {
+        if clause != Clause::Filter {
+            return;
+        }
+        let (equality, both_sides) = match token(i) {
+            "=" | "==" => (true, true),
+            "in" => (true, false),
+            "is" if token(i + 1) != "not" => (true, false),
+            "<" | "<=" | ">" | ">=" => (false, true),
+            "between" => (false, false),
+            _ => return,
+        };
+
+        let mut found = Vec::new();
+        if i > 0 && token(i - 1) != "not" {
+            found.extend(reference_ending(i - 1));
+        }
+        if both_sides {
+            found.extend(reference_starting(i + 1));
+        }
+        for (qualifier, column) in found {
+            columns.push(FilterColumn {
+                qualifier,
+                column,
+                equality,
+            });
+        }
+    });
+    columns
+}
+
+/// Build a `CREATE INDEX` statement for `columns` of `table`, named as Ecto would name it.
+pub fn create_index_sql(table: &str, columns: &[String]) -> String {
+    let name = format!("{table}_{}_index", columns.join("_"));
+    let columns: Vec<String> = columns
+        .iter()
+        .map(|column| quote_identifier(column, QuoteStyle::DoubleQuote))
+        .collect();
+    format!(
+        "CREATE INDEX {} ON {} ({})",
+        quote_identifier(&name, QuoteStyle::DoubleQuote),
+        quote_identifier(table, QuoteStyle::DoubleQuote),
+        columns.join(", ")
+    )
+}
+
+/// Column names of the ordinary table `table`, with the table's own spelling of its name.
+///
+/// `None` when there is no such table, such as for views, CTEs and subquery aliases.
+async fn table_columns(
+    conn: &libsql::Connection,
+    table: &str,
+) -> Result<Option<(String, Vec<String>)>, String> {
+    let mut rows = conn
+        .query(
+            "SELECT name FROM sqlite_master WHERE type = 'table' AND name = ?1 COLLATE NOCASE",
+            vec![Value::Text(table.to_string())],
+        )
+        .await
+        .map_err(|e| format!("Failed to look up table: {e}"))?;
+    let table: String = match rows
+        .next()
+        .await
+        .map_err(|e| format!("Failed to look up table: {e}"))?
+    {
+        Some(row) => row
+            .get(0)
+            .map_err(|e| format!("Failed to read table name: {e}"))?,
+        None => return Ok(None),
+    };
+
+    let mut rows = conn
+        .query(
+            &format!(
+                "PRAGMA table_info({})",
+                quote_identifier(&table, QuoteStyle::DoubleQuote)
+            ),
+            (),
+        )
+        .await
+        .map_err(|e| format!("Failed to read columns: {e}"))?;
+    let mut columns = Vec::new();
+    while let Some(row) = rows
+        .next()
+        .await
+        .map_err(|e| format!("Failed to read column: {e}"))?
+    {
+        columns.push(
+            row.get(1)
+                .map_err(|e| format!("Failed to read column name: {e}"))?,
+        );
+    }
+    Ok(Some((table, columns)))
+}
+
+/// Suggest indexes for the tables a query reads with a full scan. Nothing is created.
+///
+/// A heuristic: for each table scanned in the plan, the columns compared in `WHERE` and
+/// `ON` clauses (see `filter_columns`) become an index with the equality columns first,
+/// then at most one range column, as SQLite can only use one range per index. Scans with
+/// no such columns get no suggestion, as an index would not avoid reading every row.
+pub async fn index_suggestions(
+    conn: &libsql::Connection,
+    sql: &str,
+    params: Vec<Value>,
+) -> Result<Vec<String>, String> {
+    let details = query_plan_details(conn, sql, params)
+        .await
+        .map_err(|e| format!("Failed to explain query: {e}"))?;
+    let scanned = scanned_tables(&details);
+    if scanned.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let aliases = table_aliases(sql);
+    let filters = filter_columns(sql);
+    let mut suggestions = Vec::new();
+
+    for name in scanned {
+        let table = aliases
+            .iter()
+            .find(|(alias, _)| alias.eq_ignore_ascii_case(&name))
+            .map_or(name.as_str(), |(_, table)| table.as_str());
+        let Some((table, known)) = table_columns(conn, table).await? else {
+            continue;
+        };
+
+        let (mut equalities, mut ranges): (Vec<String>, Vec<String>) = (Vec::new(), Vec::new());
+        for filter in &filters {
+            let ours = filter
+                .qualifier
+                .as_ref()
+                .is_none_or(|qualifier| qualifier.eq_ignore_ascii_case(&name));
+            let column = known
+                .iter()
+                .find(|column| column.eq_ignore_ascii_case(&filter.column));
+            if let (true, Some(column)) = (ours, column) {
+                let list = if filter.equality {
+                    &mut equalities
+                } else {
+                    &mut ranges
+                };
+                if !list.contains(column) {
+                    list.push(column.clone());
+                }
+            }
+        }
+
+        let mut columns = equalities;
+        if let Some(range) = ranges.into_iter().find(|column| !columns.contains(column)) {
+            columns.push(range);
+        }
+        if columns.is_empty() {
+            continue;
+        }
+        let suggestion = create_index_sql(&table, &columns);
+        if !suggestions.contains(&suggestion) {
+            suggestions.push(suggestion);
+        }
+    }
+
+    Ok(suggestions)
+}
+
+/// Suggest indexes that could avoid full table scans in a query, for development-time
+/// tuning.
+///
+/// The query is planned with `EXPLAIN QUERY PLAN` but not executed, and the suggested
+/// `CREATE INDEX` statements are returned without being run. They are heuristic hints:
+/// check them with `index_usage` after creating them. See `index_suggestions`.
+///
+/// # Arguments
+/// - `conn_id`: Database connection ID
+/// - `sql`: Query to analyse
+/// - `args`: Query parameters
+///
+/// # Returns
+/// - List of suggested `CREATE INDEX` statements (empty if there is nothing to suggest)
+/// - `{:error, reason}` - The query could not be planned
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn suggest_indexes(conn_id: &str, sql: &str, args: Vec<Term>) -> NifResult<Vec<String>> {
+    let client = {
+        let conn_map = safe_lock(&CONNECTION_REGISTRY, "suggest_indexes conn_map")?;
+        conn_map
+            .get(conn_id)
+            .cloned()
+            .ok_or_else(|| rustler::Error::Term(Box::new("Invalid connection ID")))?
+    }; // Lock dropped here
+
+    let params: Vec<Value> = args
+        .into_iter()
+        .map(|t| decode_term_to_value(t))
+        .collect::<Result<_, _>>()
+        .map_err(|e| rustler::Error::Term(Box::new(e)))?;
+
+    let connection = {
+        let client_guard = safe_lock_arc(&client, "suggest_indexes client")?;
+        client_guard.client.clone()
+    }; // Outer lock dropped here
+
+    // SAFETY: We're inside TOKIO_RUNTIME.block_on(), so this is synchronous execution.
+    // The std::sync::Mutex guards are safe to hold across await points here because
+    // we're not in a true async context - block_on runs the future to completion.
+    #[allow(clippy::await_holding_lock)]
+    TOKIO_RUNTIME.block_on(async {
+        let conn_guard = safe_lock_arc(&connection, "suggest_indexes conn")?;
+
+        index_suggestions(&conn_guard, sql, params)
+            .await
+            .map_err(|e| rustler::Error::Term(Box::new(e)))
+    })
+}
+
+/// Timing statistics for repeated runs of a query, in microseconds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BenchmarkStats {
+    pub min_us: u64,
+    pub max_us: u64,
+    pub mean_us: u64,
+    /// Nearest-rank 95th percentile
+    pub p95_us: u64,
+}
+
+/// Summarise run timings, sorting them in place. `None` when there are no timings.
+pub fn summarise_timings(timings_us: &mut [u64]) -> Option<BenchmarkStats> {
+    timings_us.sort_unstable();
+    let (&min_us, &max_us) = (timings_us.first()?, timings_us.last()?);
+    let count = timings_us.len() as u64;
+    let total: u128 = timings_us.iter().map(|&t| u128::from(t)).sum();
+    let p95_rank = (timings_us.len() * 95).div_ceil(100).max(1);
+    Some(BenchmarkStats {
+        min_us,
+        max_us,
+        mean_us: u64::try_from(total / u128::from(count)).unwrap_or(u64::MAX),
+        p95_us: timings_us[p95_rank - 1],
+    })
+}
+
+/// Run `sql` once to warm the page cache, then `iterations` more times, timing each run.
+///
+/// Every run steps through all of the rows, dropping each as it goes, so nothing is
+/// kept between runs. Statements that write take effect on every run.
+pub async fn benchmark_runs(
+    conn: &libsql::Connection,
+    sql: &str,
+    params: Vec<Value>,
+    iterations: u32,
+) -> Result<Option<BenchmarkStats>, libsql::Error> {
+    let run = |params: Vec<Value>| async move {
+        let mut rows = conn.query(sql, params).await?;
+        while rows.next().await?.is_some() {}
+        Ok::<_, libsql::Error>(())
+    };
+
+    run(params.clone()).await?;
+
+    let mut timings_us = Vec::with_capacity(iterations as usize);
+    for _ in 0..iterations {
+        let started = Instant::now();
+        run(params.clone()).await?;
+        timings_us.push(u64::try_from(started.elapsed().as_micros()).unwrap_or(u64::MAX));
+    }
+    Ok(summarise_timings(&mut timings_us))
+}
+
+/// Time a query over repeated runs, for quick performance checks during development.
+///
+/// Runs happen back to back in native code, so BEAM scheduling doesn't skew the
+/// timings. The first run only warms the cache and is not timed. See `benchmark_runs`.
+///
+/// # Arguments
+/// - `env`: Elixir environment
+/// - `conn_id`: Database connection ID
+/// - `sql`: Statement to time
+/// - `args`: Statement parameters
+/// - `iterations`: Number of timed runs, from 1 to `MAX_BENCHMARK_ITERATIONS`
+///
+/// # Returns
+/// - `%{min_us, max_us, mean_us, p95_us}` - Run times in microseconds
+/// - `{:error, reason}` - `iterations` was out of range, or a run failed
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn benchmark_query<'a>(
+    env: Env<'a>,
+    conn_id: &str,
+    sql: &str,
+    args: Vec<Term<'a>>,
+    iterations: u32,
+) -> NifResult<Term<'a>> {
+    if !(1..=MAX_BENCHMARK_ITERATIONS).contains(&iterations) {
+        return Err(rustler::Error::Term(Box::new(format!(
+            "iterations must be between 1 and {MAX_BENCHMARK_ITERATIONS}"
+        ))));
+    }
+
+    let client = {
+        let conn_map = safe_lock(&CONNECTION_REGISTRY, "benchmark_query conn_map")?;
+        conn_map
+            .get(conn_id)
+            .cloned()
+            .ok_or_else(|| rustler::Error::Term(Box::new("Invalid connection ID")))?
+    }; // Lock dropped here
+
+    let params: Vec<Value> = args
+        .into_iter()
+        .map(|t| decode_term_to_value(t))
+        .collect::<Result<_, _>>()
+        .map_err(|e| rustler::Error::Term(Box::new(e)))?;
+
+    let connection = {
+        let client_guard = safe_lock_arc(&client, "benchmark_query client")?;
+        count_statements(conn_id, u64::from(iterations) + 1);
+        client_guard.client.clone()
+    }; // Outer lock dropped here
+
+    // SAFETY: We're inside TOKIO_RUNTIME.block_on(), so this is synchronous execution.
+    // The std::sync::Mutex guards are safe to hold across await points here because
+    // we're not in a true async context - block_on runs the future to completion.
+    #[allow(clippy::await_holding_lock)]
+    let stats = TOKIO_RUNTIME
+        .block_on(async {
+            let conn_guard = safe_lock_arc(&connection, "benchmark_query conn")?;
+
+            benchmark_runs(&conn_guard, sql, params, iterations)
+                .await
+                .map_err(|e| {
+                    record_last_error(conn_id, &e);
+                    rustler::Error::Term(Box::new(format!("Benchmark run failed: {e}")))
+                })
+        })?
+        .ok_or_else(|| rustler::Error::Term(Box::new("No runs were timed")))?;
+
+    Term::map_from_pairs(
+        env,
+        &[
+            (min_us().encode(env), stats.min_us.encode(env)),
+            (max_us().encode(env), stats.max_us.encode(env)),
+            (mean_us().encode(env), stats.mean_us.encode(env)),
+            (p95_us().encode(env), stats.p95_us.encode(env)),
+        ],
+    )
+}
@@ -3,12 +3,24 @@
 /// This module handles executing SQL queries, returning results, and managing
 /// manual synchronization for remote replicas.
 use crate::constants::*;
+use crate::models::{ColumnNaming, ColumnTransform, ConflictAction, TextEncoding, WriteRoute};
+use crate::transaction::TransactionEntryGuard;
 use crate::utils::{
-    build_empty_result, collect_rows, enhance_constraint_error, safe_lock, safe_lock_arc,
-    should_use_query,
+    build_empty_result, coerce_numeric_text, collect_rows, collect_rows_named,
+    collect_rows_transformed, column_origin_tables, count_statements, decode_blob_columns,
+    dedupe_column_names, detect_conflict_action, dml_target_table, encode_value, keyset_page_sql,
+    normalise_datetime_text, place_indexed_params, qualify_table_references,
+    query_with_origin_tables, quote_identifier, require_replica, row_fingerprint_of, safe_lock,
+    safe_lock_arc, should_use_query, statement_error, write_route, QueryType, QuoteStyle,
+    ResultBudget,
 };
 use libsql::Value;
-use rustler::{Atom, Env, NifResult, Term};
+use rustler::{Atom, Encoder, Env, NifResult, Term};
+use std::collections::{HashMap, HashSet};
+
+/// Maximum ids bound per `IN (...)` query, SQLite's historical default limit on
+/// host parameters, so lookups work against older builds and remote servers alike.
+pub const IDS_PER_QUERY: usize = 999;
 
 /// Execute a SQL query with arguments and return results.
 ///
@@ -22,13 +34,21 @@ use rustler::{Atom, Env, NifResult, Term};
 /// **Automatic Sync**: For remote replicas, writes are automatically synced to the remote database
 /// by LibSQL. Manual sync is still available via `do_sync()` for explicit control.
 ///
+/// **Replica Writes**: A write on a remote replica goes to the remote primary and only
+/// appears locally once synced back, so its result map also carries
+/// `"write_route" => :replica` (see `write_route`), which the Elixir side logs as a warning.
+///
 /// # Arguments
 /// - `env`: Elixir environment
 /// - `conn_id`: Database connection ID
 /// - `query`: SQL query string
 /// - `args`: Query parameter values
+/// - `max_result_bytes`: Optional limit on the total size of returned values
 ///
-/// Returns a map with keys: `columns`, `rows`, `num_rows`
+/// # Returns
+/// - A map with keys: `columns`, `rows`, `num_rows`
+/// - `{:error, :result_too_large, bytes_so_far}` - The rows read so far passed
+///   `max_result_bytes`; see `collect_rows_named`
 #[rustler::nif(schedule = "DirtyIo")]
 pub fn query_args<'a>(
     env: Env<'a>,
@@ -37,6 +57,7 @@ pub fn query_args<'a>(
     _syncx: Atom,
     query: &str,
     args: Vec<Term<'a>>,
+    max_result_bytes: Option<u64>,
 ) -> NifResult<Term<'a>> {
     let client = {
         let conn_map = safe_lock(&CONNECTION_REGISTRY, "query_args conn_map")?;
@@ -53,85 +74,288 @@ pub fn query_args<'a>(
 
     let params = params.map_err(|e| rustler::Error::Term(Box::new(e)))?;
 
-    // Determine whether to use query() or execute() based on statement
-    let use_query = should_use_query(query);
-
     // Clone the inner connection Arc and drop the outer lock before async operations
     // This reduces lock coupling and prevents holding the LibSQLConn lock during I/O
-    let connection = {
+    let (connection, column_naming, mode) = {
         let client_guard = safe_lock_arc(&client, "query_args client")?;
-        client_guard.client.clone()
+        count_statements(conn_id, 1);
+        (
+            client_guard.client.clone(),
+            client_guard.column_naming,
+            client_guard.mode,
+        )
     }; // Outer lock dropped here
 
     // SAFETY: We're inside TOKIO_RUNTIME.block_on(), so this is synchronous execution.
     // The std::sync::Mutex guards are safe to hold across await points here because
     // we're not in a true async context - block_on runs the future to completion.
     #[allow(clippy::await_holding_lock)]
-    {
+    let result = {
         TOKIO_RUNTIME.block_on(async {
             let conn_guard: std::sync::MutexGuard<libsql::Connection> =
                 safe_lock_arc(&connection, "query_args conn")?;
 
-            // NOTE: LibSQL automatically syncs writes to remote for embedded replicas.
-            // According to Turso docs, "writes are sent to the remote primary database by default,
-            // then the local database updates automatically once the remote write succeeds."
-            // We do NOT need to manually call sync() after writes - that would be redundant
-            // and cause performance issues. Manual sync via do_sync() is still available for
-            // explicit user control.
-
-            if use_query {
-                // Statements that return rows (SELECT, or INSERT/UPDATE/DELETE with RETURNING)
-                let res = conn_guard.query(query, params).await;
-
-                match res {
-                    Ok(res_rows) => {
-                        let result = collect_rows(env, res_rows).await?;
-                        Ok(result)
-                    }
-                    Err(e) => {
-                        let error_msg = e.to_string();
-                        let enhanced_msg = enhance_constraint_error(&conn_guard, &error_msg)
-                            .await
-                            .unwrap_or(error_msg);
-                        Err(rustler::Error::Term(Box::new(enhanced_msg)))
-                    }
-                }
-            } else {
-                // Statements that don't return rows (INSERT/UPDATE/DELETE without RETURNING)
-                let res = conn_guard.execute(query, params).await;
+            run_query(
+                env,
+                &conn_guard,
+                conn_id,
+                query,
+                params,
+                column_naming,
+                max_result_bytes,
+            )
+            .await
+        })
+    }?;
 
-                match res {
-                    Ok(rows_affected) => Ok(build_empty_result(env, rows_affected)),
-                    Err(e) => {
-                        let error_msg = e.to_string();
-                        let enhanced_msg = enhance_constraint_error(&conn_guard, &error_msg)
-                            .await
-                            .unwrap_or(error_msg);
-                        Err(rustler::Error::Term(Box::new(enhanced_msg)))
-                    }
-                }
+    if write_route(mode, query) == WriteRoute::Replica {
+        // Only result maps are flagged; a `result_too_large` error is returned as it is
+        return Ok(result
+            .map_put("write_route".encode(env), replica().encode(env))
+            .unwrap_or(result));
+    }
+    Ok(result)
+}
+
+/// Execute a SQL query with each argument bound to an explicit parameter index.
+///
+/// Like `query_args`, but `indexed_args` pairs each value with the 1-based SQLite
+/// parameter index it binds to, so arguments can be given in any order. Useful for
+/// generated SQL whose argument order differs from its placeholder order, reused `?N`
+/// placeholders, and sparse bindings. Parameters left unbound are NULL.
+///
+/// # Arguments
+/// - `env`: Elixir environment
+/// - `conn_id`: Database connection ID
+/// - `query`: SQL query string
+/// - `indexed_args`: List of `{index, value}` tuples
+///
+/// # Returns
+/// - Result map as from `query_args`
+/// - `{:error, reason}` - An index is outside the statement's parameters or given twice,
+///   or the query failed
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn query_args_indexed<'a>(
+    env: Env<'a>,
+    conn_id: &str,
+    query: &str,
+    indexed_args: Vec<(i64, Term<'a>)>,
+) -> NifResult<Term<'a>> {
+    let client = {
+        let conn_map = safe_lock(&CONNECTION_REGISTRY, "query_args_indexed conn_map")?;
+        conn_map
+            .get(conn_id)
+            .cloned()
+            .ok_or_else(|| rustler::Error::Term(Box::new("Invalid connection ID")))?
+    }; // Lock dropped here
+
+    let indexed: Vec<(i64, Value)> = indexed_args
+        .into_iter()
+        .map(|(index, t)| crate::utils::decode_term_to_value(t).map(|value| (index, value)))
+        .collect::<Result<_, _>>()
+        .map_err(|e| rustler::Error::Term(Box::new(e)))?;
+
+    let (connection, column_naming) = {
+        let client_guard = safe_lock_arc(&client, "query_args_indexed client")?;
+        count_statements(conn_id, 1);
+        (client_guard.client.clone(), client_guard.column_naming)
+    }; // Outer lock dropped here
+
+    // SAFETY: We're inside TOKIO_RUNTIME.block_on(), so this is synchronous execution.
+    // The std::sync::Mutex guards are safe to hold across await points here because
+    // we're not in a true async context - block_on runs the future to completion.
+    #[allow(clippy::await_holding_lock)]
+    TOKIO_RUNTIME.block_on(async {
+        let conn_guard = safe_lock_arc(&connection, "query_args_indexed conn")?;
+
+        // Prepare once to learn how many parameters the statement takes
+        let param_count = match conn_guard.prepare(query).await {
+            Ok(stmt) => stmt.parameter_count(),
+            Err(e) => return Err(query_error(&conn_guard, conn_id, &e).await),
+        };
+        let params = place_indexed_params(param_count, indexed)
+            .map_err(|e| rustler::Error::Term(Box::new(e)))?;
+
+        run_query(
+            env,
+            &conn_guard,
+            conn_id,
+            query,
+            params,
+            column_naming,
+            None,
+        )
+        .await
+    })
+}
+
+/// Rewrite SQL to run against the attached database `db_alias`.
+///
+/// SQLite has no `USE` statement, so instead the unqualified table references naming
+/// a table or view of `db_alias` are qualified with it (see `qualify_table_references`).
+/// Other names, such as CTEs or tables only in `main`, are left to SQLite's usual
+/// lookup. Errors if no database named `db_alias` is open on the connection.
+pub async fn qualify_for_database(
+    conn: &libsql::Connection,
+    db_alias: &str,
+    sql: &str,
+) -> Result<String, String> {
+    let databases = crate::metadata::list_databases(conn).await?;
+    if !databases
+        .iter()
+        .any(|(_, name, _)| name.eq_ignore_ascii_case(db_alias))
+    {
+        return Err(format!("Unknown database: {db_alias}"));
+    }
+
+    let schema_sql = format!(
+        "SELECT name FROM {}.sqlite_schema WHERE type IN ('table', 'view')",
+        quote_identifier(db_alias, QuoteStyle::DoubleQuote)
+    );
+    let mut rows = conn
+        .query(&schema_sql, ())
+        .await
+        .map_err(|e| format!("Failed to list tables of {db_alias}: {e}"))?;
+    let mut tables = HashSet::new();
+    while let Some(row) = rows
+        .next()
+        .await
+        .map_err(|e| format!("Failed to read tables of {db_alias}: {e}"))?
+    {
+        let name: String = row
+            .get(0)
+            .map_err(|e| format!("Failed to read table name: {e}"))?;
+        tables.insert(name.to_ascii_lowercase());
+    }
+
+    Ok(qualify_table_references(sql, db_alias, &tables))
+}
+
+/// Execute a SQL query against a specific attached database.
+///
+/// Targets `db_alias` without prefixing every identifier, which simplifies
+/// shard-targeted queries. Unqualified references to the tables and views of
+/// `db_alias` in the statement are qualified with it before running, as described
+/// in `qualify_for_database`; the statement then runs as `query_args` would.
+///
+/// # Arguments
+/// - `env`: Elixir environment
+/// - `conn_id`: Database connection ID
+/// - `db_alias`: Name the database was attached as (`main` and `temp` also work)
+/// - `query`: SQL query string
+/// - `args`: Query parameter values
+///
+/// # Returns
+/// - Result map as from `query_args`
+/// - `{:error, reason}` - Unknown database, or the query failed
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn query_on_database<'a>(
+    env: Env<'a>,
+    conn_id: &str,
+    db_alias: &str,
+    query: &str,
+    args: Vec<Term<'a>>,
+) -> NifResult<Term<'a>> {
+    let client = {
+        let conn_map = safe_lock(&CONNECTION_REGISTRY, "query_on_database conn_map")?;
+        conn_map
+            .get(conn_id)
+            .cloned()
+            .ok_or_else(|| rustler::Error::Term(Box::new("Invalid connection ID")))?
+    }; // Lock dropped here
+
+    let params: Vec<Value> = args
+        .into_iter()
+        .map(|t| crate::utils::decode_term_to_value(t))
+        .collect::<Result<_, _>>()
+        .map_err(|e| rustler::Error::Term(Box::new(e)))?;
+
+    let (connection, column_naming) = {
+        let client_guard = safe_lock_arc(&client, "query_on_database client")?;
+        count_statements(conn_id, 1);
+        (client_guard.client.clone(), client_guard.column_naming)
+    }; // Outer lock dropped here
+
+    // SAFETY: We're inside TOKIO_RUNTIME.block_on(), so this is synchronous execution.
+    // The std::sync::Mutex guards are safe to hold across await points here because
+    // we're not in a true async context - block_on runs the future to completion.
+    #[allow(clippy::await_holding_lock)]
+    TOKIO_RUNTIME.block_on(async {
+        let conn_guard = safe_lock_arc(&connection, "query_on_database conn")?;
+
+        let sql = qualify_for_database(&conn_guard, db_alias, query)
+            .await
+            .map_err(|e| rustler::Error::Term(Box::new(e)))?;
+
+        run_query(env, &conn_guard, conn_id, &sql, params, column_naming, None).await
+    })
+}
+
+/// Run a statement for `query_args` and build its result map.
+///
+/// Automatically routes to `query()` for statements that return rows or `execute()` for
+/// those that don't. Returned rows are limited to `max_result_bytes` when given.
+pub async fn run_query<'a>(
+    env: Env<'a>,
+    conn: &libsql::Connection,
+    conn_id: &str,
+    query: &str,
+    params: Vec<Value>,
+    column_naming: ColumnNaming,
+    max_result_bytes: Option<u64>,
+) -> NifResult<Term<'a>> {
+    // NOTE: LibSQL automatically syncs writes to remote for embedded replicas.
+    // According to Turso docs, "writes are sent to the remote primary database by default,
+    // then the local database updates automatically once the remote write succeeds."
+    // We do NOT need to manually call sync() after writes - that would be redundant
+    // and cause performance issues. Manual sync via do_sync() is still available for
+    // explicit user control.
+
+    if should_use_query(query) {
+        // Statements that return rows (SELECT, or INSERT/UPDATE/DELETE with RETURNING)
+        match query_with_origin_tables(conn, query, params, column_naming).await {
+            Ok((res_rows, tables)) => {
+                collect_rows_named(env, res_rows, column_naming, &tables, max_result_bytes).await
             }
-        })
+            Err(e) => Err(query_error(conn, conn_id, &e).await),
+        }
+    } else {
+        // Statements that don't return rows (INSERT/UPDATE/DELETE without RETURNING)
+        match conn.execute(query, params).await {
+            Ok(rows_affected) => Ok(build_empty_result(env, rows_affected)),
+            Err(e) => Err(query_error(conn, conn_id, &e).await),
+        }
     }
 }
 
+/// Build the error term for a failed `query_args` statement.
+///
+/// See `statement_error`; the message is the libsql error as is.
+async fn query_error(
+    conn: &libsql::Connection,
+    conn_id: &str,
+    error: &libsql::Error,
+) -> rustler::Error {
+    statement_error(conn, conn_id, error, error.to_string()).await
+}
+
 /// Manually synchronize a remote replica database with the remote primary.
 ///
 /// For remote replicas, this triggers an explicit sync operation to pull the latest
 /// changes from the remote database. This is useful when you need to ensure read-after-write
 /// consistency or when automatic sync is disabled.
 ///
-/// For local and direct remote connections, this is a no-op.
+/// Local and direct remote connections have nothing to sync, so they fail with
+/// `{:error, :not_a_replica}`. The mode is the one stored on the connection.
 ///
 /// **Timeout**: Sync operations have a 30-second timeout to prevent indefinite blocking.
 ///
 /// # Arguments
 /// - `conn_id`: Database connection ID
-/// - `mode`: Connection mode (`:local`, `:remote`, `:remote_replica`)
 ///
 /// Returns `{:ok, "success sync"}` on success, error on failure.
 #[rustler::nif(schedule = "DirtyIo")]
-pub fn do_sync(conn_id: &str, mode: Atom) -> NifResult<(Atom, String)> {
+pub fn do_sync(conn_id: &str) -> NifResult<(Atom, String)> {
     let conn_map = safe_lock(&CONNECTION_REGISTRY, "do_sync")?;
     let client = conn_map
         .get(conn_id)
@@ -139,17 +363,13 @@ pub fn do_sync(conn_id: &str, mode: Atom) -> NifResult<(Atom, String)> {
         .clone();
 
     drop(conn_map); // Release lock before async operation
+    require_replica(safe_lock_arc(&client, "do_sync mode")?.mode)?;
 
-    let result = TOKIO_RUNTIME.block_on(async {
-        if matches!(
-            crate::decode::decode_mode(mode),
-            Some(crate::models::Mode::RemoteReplica)
-        ) {
-            crate::utils::sync_with_timeout(&client, DEFAULT_SYNC_TIMEOUT_SECS).await?;
-        }
-
-        Ok::<_, String>(())
-    });
+    let result = TOKIO_RUNTIME.block_on(crate::utils::sync_with_timeout(
+        conn_id,
+        &client,
+        DEFAULT_SYNC_TIMEOUT_SECS,
+    ));
 
     match result {
         Ok(()) => Ok((rustler::types::atom::ok(), "success sync".to_string())),
@@ -157,6 +377,66 @@ pub fn do_sync(conn_id: &str, mode: Atom) -> NifResult<(Atom, String)> {
     }
 }
 
+/// Classify where a statement's writes would land on a connection.
+///
+/// Writes on a remote replica go to the remote primary and only appear locally once
+/// synced back, which surprises callers expecting them to land like local writes.
+/// This lets them check before running a statement.
+///
+/// # Arguments
+/// - `conn_id`: Database connection ID
+/// - `sql`: Statement to classify
+///
+/// # Returns
+/// - `:read` - The statement doesn't write
+/// - `:local` - Written to the local database file
+/// - `:remote` - Sent to the remote server
+/// - `:replica` - Sent to the remote primary, then synced back to the replica
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn classify_write(conn_id: &str, sql: &str) -> NifResult<Atom> {
+    let client = safe_lock(&CONNECTION_REGISTRY, "classify_write conn_map")?
+        .get(conn_id)
+        .cloned()
+        .ok_or_else(|| rustler::Error::Term(Box::new("Invalid connection ID")))?;
+    let mode = safe_lock_arc(&client, "classify_write client")?.mode;
+
+    Ok(match write_route(mode, sql) {
+        WriteRoute::Read => read(),
+        WriteRoute::Local => local(),
+        WriteRoute::Remote => remote(),
+        WriteRoute::Replica => replica(),
+    })
+}
+
+/// Report the conflict action of an `INSERT`, `REPLACE` or `UPDATE` statement.
+///
+/// The action decides what the statement's change count means, which callers relying
+/// on affected-row counts need to interpret it:
+/// - `:abort`, `:fail`, `:rollback` - Every row written is counted; a conflict is an error
+/// - `:ignore` - Only rows actually written are counted, so skipped rows lower the count,
+///   to 0 when every row conflicted
+/// - `:replace` - Only rows written are counted; rows deleted to resolve a conflict are
+///   not, so one inserted row may have replaced several
+///
+/// See `detect_conflict_action`.
+///
+/// # Arguments
+/// - `sql`: Statement to analyse
+///
+/// # Returns
+/// - `:abort`, `:fail`, `:ignore`, `:replace` or `:rollback`
+/// - `nil` - Not an `INSERT`, `REPLACE` or `UPDATE` statement
+#[rustler::nif]
+pub fn statement_conflict_action(sql: &str) -> Option<Atom> {
+    detect_conflict_action(sql).map(|action| match action {
+        ConflictAction::Abort => abort(),
+        ConflictAction::Fail => fail(),
+        ConflictAction::Ignore => ignore(),
+        ConflictAction::Replace => replace(),
+        ConflictAction::Rollback => rollback(),
+    })
+}
+
 /// Execute a PRAGMA statement and return the result.
 ///
 /// PRAGMA statements are SQLite's configuration mechanism. They allow you to query
@@ -195,10 +475,10 @@ pub fn pragma_query<'a>(env: Env<'a>, conn_id: &str, pragma_stmt: &str) -> NifRe
             let conn_guard: std::sync::MutexGuard<libsql::Connection> =
                 safe_lock_arc(&client_guard.client, "pragma_query conn")?;
 
-            let rows = conn_guard
-                .query(pragma_stmt, ())
-                .await
-                .map_err(|e| rustler::Error::Term(Box::new(format!("PRAGMA query failed: {e}"))))?;
+            let rows = conn_guard.query(pragma_stmt, ()).await.map_err(|e| {
+                crate::utils::record_last_error(conn_id, &e);
+                rustler::Error::Term(Box::new(format!("PRAGMA query failed: {e}")))
+            })?;
 
             collect_rows(env, rows).await
         });
@@ -208,3 +488,1654 @@ pub fn pragma_query<'a>(env: Env<'a>, conn_id: &str, pragma_stmt: &str) -> NifRe
         Err(rustler::Error::Term(Box::new("Invalid connection ID")))
     }
 }
+
+/// Fetch the rows of `table` whose `id_column` is one of `ids`.
+///
+/// Runs one parameterised `IN (...)` query per `IDS_PER_QUERY` ids and merges the
+/// results. Returns the table's column names and, for each matching row, the id value
+/// followed by the row's values in column order.
+pub async fn fetch_rows_by_ids(
+    conn: &libsql::Connection,
+    table: &str,
+    id_column: &str,
+    ids: &[Value],
+) -> Result<(Vec<String>, Vec<(Value, Vec<Value>)>), String> {
+    let table = quote_identifier(table, QuoteStyle::Backtick);
+    let id_column = quote_identifier(id_column, QuoteStyle::Backtick);
+
+    let mut columns: Vec<String> = Vec::new();
+    let mut found = Vec::new();
+
+    for chunk in ids.chunks(IDS_PER_QUERY) {
+        let placeholders = vec!["?"; chunk.len()].join(", ");
+        // The id is selected first so it can be read regardless of the table's columns
+        let sql =
+            format!("SELECT {id_column}, * FROM {table} WHERE {id_column} IN ({placeholders})");
+
+        let mut rows = conn
+            .query(&sql, chunk.to_vec())
+            .await
+            .map_err(|e| format!("Failed to fetch rows: {e}"))?;
+
+        let column_count = rows.column_count();
+        if columns.is_empty() {
+            columns = (1..column_count)
+                .map(|i| rows.column_name(i).unwrap_or_default().to_string())
+                .collect();
+        }
+
+        while let Some(row) = rows
+            .next()
+            .await
+            .map_err(|e| format!("Failed to read row: {e}"))?
+        {
+            let id = row
+                .get_value(0)
+                .map_err(|e| format!("Failed to read id: {e}"))?;
+            let values = (1..column_count)
+                .map(|i| row.get_value(i))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| format!("Failed to read row: {e}"))?;
+            found.push((id, values));
+        }
+    }
+
+    Ok((columns, found))
+}
+
+/// Fetch multiple rows by id and return them keyed by id.
+///
+/// Builds parameterised `IN (...)` queries (chunked to stay within SQLite's host
+/// parameter limit) and returns a map of `id => row_map`, where each row map is keyed
+/// by column name. Ids that match no row are absent from the map. Table and column
+/// names are quoted internally.
+///
+/// # Arguments
+/// - `env`: Elixir environment
+/// - `conn_id`: Database connection ID
+/// - `table`: Table name
+/// - `id_column`: Column to match ids against (usually the primary key)
+/// - `ids`: Id values to look up
+///
+/// # Returns
+/// - Map of `id => %{column => value}`
+/// - `{:error, reason}` - Query failed (e.g. unknown table or column)
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn fetch_by_ids<'a>(
+    env: Env<'a>,
+    conn_id: &str,
+    table: &str,
+    id_column: &str,
+    ids: Vec<Term<'a>>,
+) -> NifResult<Term<'a>> {
+    let client = {
+        let conn_map = safe_lock(&CONNECTION_REGISTRY, "fetch_by_ids conn_map")?;
+        conn_map
+            .get(conn_id)
+            .cloned()
+            .ok_or_else(|| rustler::Error::Term(Box::new("Invalid connection ID")))?
+    }; // Lock dropped here
+
+    let ids: Vec<Value> = ids
+        .into_iter()
+        .map(|t| crate::utils::decode_term_to_value(t))
+        .collect::<Result<_, _>>()
+        .map_err(|e| rustler::Error::Term(Box::new(e)))?;
+
+    // Clone the inner connection Arc and drop the outer lock before async operations
+    let connection = {
+        let client_guard = safe_lock_arc(&client, "fetch_by_ids client")?;
+        client_guard.client.clone()
+    }; // Outer lock dropped here
+
+    // SAFETY: We're inside TOKIO_RUNTIME.block_on(), so this is synchronous execution.
+    // The std::sync::Mutex guards are safe to hold across await points here because
+    // we're not in a true async context - block_on runs the future to completion.
+    #[allow(clippy::await_holding_lock)]
+    let (columns, found) = TOKIO_RUNTIME.block_on(async {
+        let conn_guard = safe_lock_arc(&connection, "fetch_by_ids conn")?;
+
+        fetch_rows_by_ids(&conn_guard, table, id_column, &ids)
+            .await
+            .map_err(|e| rustler::Error::Term(Box::new(e)))
+    })?;
+
+    let alloc_error = || rustler::Error::Term(Box::new("Failed to allocate binary for row value"));
+    let column_terms: Vec<Term<'a>> = columns.iter().map(|c| c.encode(env)).collect();
+
+    let mut result = Term::map_new(env);
+    for (id, values) in found {
+        let value_terms = values
+            .iter()
+            .map(|v| encode_value(env, v))
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(alloc_error)?;
+        let row = Term::map_from_arrays(env, &column_terms, &value_terms)?;
+        result = result.map_put(encode_value(env, &id).ok_or_else(alloc_error)?, row)?;
+    }
+
+    Ok(result)
+}
+
+/// Fetch one keyset pagination page of `base_sql`, ordered by `key_column`.
+///
+/// Returns the base query's column names, the page's rows, and the key of the last row
+/// (`None` for an empty page), which is passed back as `after_key` for the next page.
+pub async fn fetch_keyset_page(
+    conn: &libsql::Connection,
+    base_sql: &str,
+    key_column: &str,
+    after_key: Option<Value>,
+    limit: i64,
+) -> Result<(Vec<String>, Vec<Vec<Value>>, Option<Value>), String> {
+    let sql = keyset_page_sql(base_sql, key_column, after_key.is_some())?;
+    let params = match after_key {
+        Some(key) => vec![key, Value::Integer(limit)],
+        None => vec![Value::Integer(limit)],
+    };
+
+    let mut rows = conn
+        .query(&sql, params)
+        .await
+        .map_err(|e| format!("Failed to fetch page: {e}"))?;
+
+    // The key is selected first so it can be read regardless of the query's columns
+    let column_count = rows.column_count();
+    let columns: Vec<String> = (1..column_count)
+        .map(|i| rows.column_name(i).unwrap_or_default().to_string())
+        .collect();
+
+    let mut page = Vec::new();
+    let mut last_key = None;
+    while let Some(row) = rows
+        .next()
+        .await
+        .map_err(|e| format!("Failed to read row: {e}"))?
+    {
+        last_key = Some(
+            row.get_value(0)
+                .map_err(|e| format!("Failed to read key: {e}"))?,
+        );
+        let values = (1..column_count)
+            .map(|i| row.get_value(i))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to read row: {e}"))?;
+        page.push(values);
+    }
+
+    Ok((columns, page, last_key))
+}
+
+/// Fetch one page of a query using keyset pagination.
+///
+/// Returns the rows of `base_sql` whose `key_column` is greater than `after_key`, in
+/// key order, up to `limit` rows. Unlike `OFFSET`, each page seeks straight to its
+/// first row through an index on the key, so later pages cost no more than the first.
+/// The key must be unique and appear in the base query's results.
+///
+/// # Arguments
+/// - `env`: Elixir environment
+/// - `conn_id`: Database connection ID
+/// - `base_sql`: A single `SELECT` without its own `ORDER BY`, `LIMIT` or `OFFSET`
+/// - `key_column`: Result column to order and page by
+/// - `after_key`: Last key of the previous page, or `nil` for the first page
+/// - `limit`: Maximum rows per page
+///
+/// # Returns
+/// - `{result, last_key}` - Result map as from `query_args`, and the key of the
+///   page's last row (`nil` when the page is empty)
+/// - `{:error, reason}` - Invalid base query or query failure
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn keyset_page<'a>(
+    env: Env<'a>,
+    conn_id: &str,
+    base_sql: &str,
+    key_column: &str,
+    after_key: Term<'a>,
+    limit: i64,
+) -> NifResult<Term<'a>> {
+    if limit < 0 {
+        return Err(rustler::Error::Term(Box::new(
+            "Page limit must not be negative",
+        )));
+    }
+
+    let client = {
+        let conn_map = safe_lock(&CONNECTION_REGISTRY, "keyset_page conn_map")?;
+        conn_map
+            .get(conn_id)
+            .cloned()
+            .ok_or_else(|| rustler::Error::Term(Box::new("Invalid connection ID")))?
+    }; // Lock dropped here
+
+    let after_key = match crate::utils::decode_term_to_value(after_key)
+        .map_err(|e| rustler::Error::Term(Box::new(e)))?
+    {
+        Value::Null => None,
+        key => Some(key),
+    };
+
+    // Clone the inner connection Arc and drop the outer lock before async operations
+    let connection = {
+        let client_guard = safe_lock_arc(&client, "keyset_page client")?;
+        client_guard.client.clone()
+    }; // Outer lock dropped here
+
+    // SAFETY: We're inside TOKIO_RUNTIME.block_on(), so this is synchronous execution.
+    // The std::sync::Mutex guards are safe to hold across await points here because
+    // we're not in a true async context - block_on runs the future to completion.
+    #[allow(clippy::await_holding_lock)]
+    let (columns, page, last_key) = TOKIO_RUNTIME.block_on(async {
+        let conn_guard = safe_lock_arc(&connection, "keyset_page conn")?;
+
+        fetch_keyset_page(&conn_guard, base_sql, key_column, after_key, limit)
+            .await
+            .map_err(|e| rustler::Error::Term(Box::new(e)))
+    })?;
+
+    let alloc_error = || rustler::Error::Term(Box::new("Failed to allocate binary for row value"));
+    let num_rows = page.len();
+    let rows = page
+        .iter()
+        .map(|values| {
+            values
+                .iter()
+                .map(|v| encode_value(env, v))
+                .collect::<Option<Vec<_>>>()
+        })
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(alloc_error)?;
+    let last_key = match &last_key {
+        Some(key) => encode_value(env, key).ok_or_else(alloc_error)?,
+        None => rustler::types::atom::nil().encode(env),
+    };
+
+    let mut result: HashMap<String, Term<'a>> = HashMap::with_capacity(3);
+    result.insert("columns".to_string(), columns.encode(env));
+    result.insert("rows".to_string(), rows.encode(env));
+    result.insert("num_rows".to_string(), num_rows.encode(env));
+
+    Ok((result, last_key).encode(env))
+}
+
+/// Switch `PRAGMA query_only` on or off for `conn`, returning its previous setting.
+///
+/// While it is on, SQLite rejects any statement that would write to the database.
+pub async fn set_query_only(conn: &libsql::Connection, on: bool) -> Result<bool, libsql::Error> {
+    let previous = match conn.query("PRAGMA query_only", ()).await?.next().await? {
+        Some(row) => row.get::<i64>(0)? != 0,
+        None => false,
+    };
+    conn.execute(&format!("PRAGMA query_only = {}", i32::from(on)), ())
+        .await?;
+    Ok(previous)
+}
+
+/// Run several independent read queries in one call, returning a result per query.
+///
+/// Saves a NIF round trip per query when a page fans out to many unrelated `SELECT`s.
+/// Queries run in order on the connection with `PRAGMA query_only` switched on, so
+/// anything that would write is rejected by SQLite rather than run. Execution stops at
+/// the first failing query. Should restoring `query_only` fail, the connection is
+/// marked broken so its next `ping` fails.
+///
+/// # Arguments
+/// - `env`: Elixir environment
+/// - `conn_id`: Database connection ID
+/// - `queries`: List of `{sql, params}` tuples
+///
+/// # Returns
+/// - List of result maps as from `query_args`, in query order
+/// - `{:error, {index, reason}}` - The query at zero-based `index` failed
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn query_multi<'a>(
+    env: Env<'a>,
+    conn_id: &str,
+    queries: Vec<Term<'a>>,
+) -> NifResult<Vec<Term<'a>>> {
+    let client = {
+        let conn_map = safe_lock(&CONNECTION_REGISTRY, "query_multi conn_map")?;
+        conn_map
+            .get(conn_id)
+            .cloned()
+            .ok_or_else(|| rustler::Error::Term(Box::new("Invalid connection ID")))?
+    }; // Lock dropped here
+
+    let mut decoded: Vec<(String, Vec<Value>)> = Vec::with_capacity(queries.len());
+    for (index, query_term) in queries.into_iter().enumerate() {
+        let (sql, args): (String, Vec<Term>) = query_term.decode().map_err(|e| {
+            rustler::Error::Term(Box::new((index, format!("Failed to decode query: {e:?}"))))
+        })?;
+        let params = args
+            .into_iter()
+            .map(|t| crate::utils::decode_term_to_value(t))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| rustler::Error::Term(Box::new((index, e))))?;
+        decoded.push((sql, params));
+    }
+
+    let (connection, column_naming) = {
+        let client_guard = safe_lock_arc(&client, "query_multi client")?;
+        count_statements(conn_id, decoded.len() as u64);
+        (client_guard.client.clone(), client_guard.column_naming)
+    }; // Outer lock dropped here
+
+    // SAFETY: We're inside TOKIO_RUNTIME.block_on(), so this is synchronous execution.
+    // The std::sync::Mutex guards are safe to hold across await points here because
+    // we're not in a true async context - block_on runs the future to completion.
+    #[allow(clippy::await_holding_lock)]
+    TOKIO_RUNTIME.block_on(async {
+        let conn_guard = safe_lock_arc(&connection, "query_multi conn")?;
+
+        let was_query_only = set_query_only(&conn_guard, true)
+            .await
+            .map_err(|e| rustler::Error::Term(Box::new(e.to_string())))?;
+
+        let mut results = Vec::with_capacity(decoded.len());
+        let mut failure = None;
+        for (index, (sql, params)) in decoded.into_iter().enumerate() {
+            let (rows, tables) =
+                match query_with_origin_tables(&conn_guard, &sql, params, column_naming).await {
+                    Ok(queried) => queried,
+                    Err(e) => {
+                        crate::utils::record_last_error(conn_id, &e);
+                        failure = Some(rustler::Error::Term(Box::new((index, e.to_string()))));
+                        break;
+                    }
+                };
+            match collect_rows_named(env, rows, column_naming, &tables, None).await {
+                Ok(result) => results.push(result),
+                Err(e) => {
+                    failure = Some(rustler::Error::Term(Box::new((index, format!("{e:?}")))));
+                    break;
+                }
+            }
+        }
+
+        // Restore the caller's setting even when a query failed. If that fails the
+        // connection may be stuck read-only, so it is marked broken for the pool to
+        // replace, and any query failure is still the error reported.
+        if let Err(e) = set_query_only(&conn_guard, was_query_only).await {
+            crate::utils::record_last_error(conn_id, &e);
+            crate::utils::mark_connection_broken(
+                conn_id,
+                format!("Failed to restore query_only: {e}"),
+            );
+            failure.get_or_insert_with(|| rustler::Error::Term(Box::new(e.to_string())));
+        }
+
+        match failure {
+            Some(error) => Err(error),
+            None => Ok(results),
+        }
+    })
+}
+
+/// Run a query, passing each value through `convert` with its column's declared type.
+///
+/// Column types come from the prepared statement, so only columns that map directly
+/// to a table column have one; expressions see `None`. Returns the column names, each
+/// column's origin table, and the rows.
+///
+/// With `max_bytes`, converted values are counted against the limit as rows are read,
+/// as `collect_rows_named` does. Once it is passed, reading stops and the inner result
+/// is `Err(bytes_so_far)`.
+pub async fn fetch_converting<F>(
+    conn: &libsql::Connection,
+    query: &str,
+    params: Vec<Value>,
+    convert: F,
+    max_bytes: Option<u64>,
+) -> Result<Result<FetchedRows, u64>, libsql::Error>
+where
+    F: Fn(Value, Option<&str>) -> Value,
+{
+    let stmt = conn.prepare(query).await?;
+    let decl_types: Vec<Option<String>> = stmt
+        .columns()
+        .iter()
+        .map(|column| column.decl_type().map(str::to_string))
+        .collect();
+    let tables = column_origin_tables(&stmt);
+
+    let mut rows = stmt.query(params).await?;
+    let column_count = rows.column_count();
+    let columns: Vec<String> = (0..column_count)
+        .map(|i| {
+            rows.column_name(i)
+                .map_or_else(|| format!("col{i}"), str::to_string)
+        })
+        .collect();
+
+    let mut budget = ResultBudget::new(max_bytes);
+    let mut collected = Vec::new();
+    while let Some(row) = rows.next().await? {
+        let values = (0..column_count)
+            .map(|i| {
+                let decl_type = decl_types.get(i as usize).and_then(Option::as_deref);
+                row.get_value(i).map(|value| convert(value, decl_type))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        for value in &values {
+            if let Err(bytes_so_far) = budget.add(value) {
+                return Ok(Err(bytes_so_far));
+            }
+        }
+        collected.push(values);
+    }
+
+    Ok(Ok((columns, tables, collected)))
+}
+
+/// Run a query, converting numbers stored as text in numeric-declared columns.
+///
+/// See `fetch_converting` and `coerce_numeric_text`.
+pub async fn fetch_coercing_numbers(
+    conn: &libsql::Connection,
+    query: &str,
+    params: Vec<Value>,
+) -> Result<FetchedRows, libsql::Error> {
+    // Without a limit the fetch can't run over it
+    fetch_converting(conn, query, params, coerce_numeric_text, None)
+        .await
+        .map(Result::unwrap_or_default)
+}
+
+/// Options shared by the NIFs that convert values as they are read.
+struct ConvertingOptions<'s> {
+    /// Limit on the total size of returned values, as for `query_args`
+    max_result_bytes: Option<u64>,
+    /// Open transaction to run the query in, as `query_with_trx_args` does
+    trx_id: Option<&'s str>,
+    /// Names the calling NIF in lock error messages
+    context: &'s str,
+}
+
+/// Run a query through `fetch_converting` and build a result map as from `query_args`.
+///
+/// Blobs in the columns named by `decodings` are then decoded to text; see
+/// `decode_blob_columns`. With a transaction id the query runs in that transaction,
+/// which must belong to `conn_id`.
+fn query_converting<'a, F>(
+    env: Env<'a>,
+    conn_id: &str,
+    query: &str,
+    args: Vec<Term<'a>>,
+    options: ConvertingOptions,
+    convert: F,
+    decodings: &[(String, TextEncoding)],
+) -> NifResult<Term<'a>>
+where
+    F: Fn(Value, Option<&str>) -> Value,
+{
+    let context = options.context;
+    let client = {
+        let conn_map = safe_lock(&CONNECTION_REGISTRY, &format!("{context} conn_map"))?;
+        conn_map
+            .get(conn_id)
+            .cloned()
+            .ok_or_else(|| rustler::Error::Term(Box::new("Invalid connection ID")))?
+    }; // Lock dropped here
+
+    let params: Vec<Value> = args
+        .into_iter()
+        .map(|t| crate::utils::decode_term_to_value(t))
+        .collect::<Result<_, _>>()
+        .map_err(|e| rustler::Error::Term(Box::new(e)))?;
+
+    // Take the transaction entry, with ownership verification, before the client lock
+    let trx_guard = options
+        .trx_id
+        .map(|trx_id| TransactionEntryGuard::take(trx_id, conn_id))
+        .transpose()?;
+
+    let (connection, column_naming) = {
+        let client_guard = safe_lock_arc(&client, &format!("{context} client"))?;
+        count_statements(conn_id, 1);
+        (client_guard.client.clone(), client_guard.column_naming)
+    }; // Outer lock dropped here
+
+    // SAFETY: We're inside TOKIO_RUNTIME.block_on(), so this is synchronous execution.
+    // The std::sync::Mutex guards are safe to hold across await points here because
+    // we're not in a true async context - block_on runs the future to completion.
+    #[allow(clippy::await_holding_lock)]
+    let fetched = TOKIO_RUNTIME.block_on(async {
+        let conn_guard;
+        let conn: &libsql::Connection = if let Some(guard) = &trx_guard {
+            guard.transaction()?
+        } else {
+            conn_guard = safe_lock_arc(&connection, &format!("{context} conn"))?;
+            &conn_guard
+        };
+        match fetch_converting(conn, query, params, convert, options.max_result_bytes).await {
+            Ok(fetched) => Ok(fetched),
+            Err(e) => Err(query_error(conn, conn_id, &e).await),
+        }
+    })?;
+    // The guard re-inserts the transaction entry on drop
+    drop(trx_guard);
+
+    let (columns, tables, mut rows) = match fetched {
+        Ok(fetched) => fetched,
+        Err(bytes_so_far) => return Ok((error(), result_too_large(), bytes_so_far).encode(env)),
+    };
+    decode_blob_columns(&columns, &mut rows, decodings)
+        .map_err(|e| rustler::Error::Term(Box::new(e)))?;
+
+    encode_fetched(env, &columns, &tables, &rows, column_naming)
+}
+
+/// Build a result map as from `query_args` out of rows already fetched as values.
+fn encode_fetched<'a>(
+    env: Env<'a>,
+    columns: &[String],
+    tables: &[Option<String>],
+    rows: &[Vec<Value>],
+    column_naming: ColumnNaming,
+) -> NifResult<Term<'a>> {
+    let columns = dedupe_column_names(columns, tables, column_naming);
+    let num_rows = rows.len();
+    let rows = rows
+        .iter()
+        .map(|values| {
+            values
+                .iter()
+                .map(|v| encode_value(env, v))
+                .collect::<Option<Vec<_>>>()
+        })
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(|| rustler::Error::Term(Box::new("Failed to allocate binary for row value")))?;
+
+    let mut result: HashMap<String, Term<'a>> = HashMap::with_capacity(3);
+    result.insert("columns".to_string(), columns.encode(env));
+    result.insert("rows".to_string(), rows.encode(env));
+    result.insert("num_rows".to_string(), num_rows.encode(env));
+
+    Ok(result.encode(env))
+}
+
+/// Execute a read query, returning numbers stored as text as numbers.
+///
+/// For legacy schemas that store numbers in `TEXT` form: values such as `"123"` read
+/// from a column declared with numeric affinity (`INTEGER`, `REAL`, `NUMERIC`, ...) are
+/// returned as `123`. This is best-effort: only columns selected directly from a table
+/// have a declared type, and text that doesn't parse as a number is left alone.
+/// Parameters are bound unchanged.
+///
+/// # Arguments
+/// - `env`: Elixir environment
+/// - `conn_id`: Database connection ID
+/// - `query`: SQL query returning rows
+/// - `args`: Query parameter values
+/// - `max_result_bytes`: Optional limit on the total size of returned values
+/// - `trx_id`: Optional transaction to run the query in
+///
+/// Returns a result map as from `query_args`
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn query_coerce_numbers<'a>(
+    env: Env<'a>,
+    conn_id: &str,
+    query: &str,
+    args: Vec<Term<'a>>,
+    max_result_bytes: Option<u64>,
+    trx_id: Option<&str>,
+) -> NifResult<Term<'a>> {
+    query_converting(
+        env,
+        conn_id,
+        query,
+        args,
+        ConvertingOptions {
+            max_result_bytes,
+            trx_id,
+            context: "query_coerce_numbers",
+        },
+        coerce_numeric_text,
+        &[],
+    )
+}
+
+/// Execute a read query, returning datetime text in canonical ISO 8601 form.
+///
+/// SQLite accepts datetimes as text in several shapes (`2024-01-02 03:04:05`,
+/// `2024-01-02T03:04`, with or without fractional seconds or a UTC offset). Text read
+/// from a column declared `DATE`, `DATETIME` or `TIMESTAMP` is rewritten as
+/// `YYYY-MM-DDTHH:MM:SS[.fff][offset]`; see `normalise_datetime_text`. Like
+/// `query_coerce_numbers`, only columns selected directly from a table have a declared
+/// type, and unrecognised text is left alone.
+///
+/// # Arguments
+/// - `env`: Elixir environment
+/// - `conn_id`: Database connection ID
+/// - `query`: SQL query returning rows
+/// - `args`: Query parameter values
+/// - `coerce_numbers`: Also convert numeric text, as `query_coerce_numbers` does
+/// - `max_result_bytes`: Optional limit on the total size of returned values
+/// - `trx_id`: Optional transaction to run the query in
+///
+/// Returns a result map as from `query_args`
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn query_normalise_datetimes<'a>(
+    env: Env<'a>,
+    conn_id: &str,
+    query: &str,
+    args: Vec<Term<'a>>,
+    coerce_numbers: bool,
+    max_result_bytes: Option<u64>,
+    trx_id: Option<&str>,
+) -> NifResult<Term<'a>> {
+    query_converting(
+        env,
+        conn_id,
+        query,
+        args,
+        ConvertingOptions {
+            max_result_bytes,
+            trx_id,
+            context: "query_normalise_datetimes",
+        },
+        |value, decl_type| {
+            let value = normalise_datetime_text(value, decl_type);
+            if coerce_numbers {
+                coerce_numeric_text(value, decl_type)
+            } else {
+                value
+            }
+        },
+        &[],
+    )
+}
+
+/// Execute a read query, decoding blobs in the named columns as text.
+///
+/// For legacy databases that store UTF-16 or Latin-1 text as blobs, which would otherwise
+/// be returned as binaries. Each `{column, encoding}` pair names a result column and the
+/// encoding its blobs hold (`:utf16le` or `:latin1`); those blobs are returned as UTF-8
+/// strings. Other values in the column are returned unchanged.
+///
+/// # Arguments
+/// - `env`: Elixir environment
+/// - `conn_id`: Database connection ID
+/// - `query`: SQL query returning rows
+/// - `args`: Query parameter values
+/// - `decodings`: `{column, encoding}` pairs
+/// - `max_result_bytes`: Optional limit on the total size of returned values
+/// - `trx_id`: Optional transaction to run the query in
+///
+/// # Returns
+/// - Result map as from `query_args`
+/// - `{:error, reason}` - A named column isn't in the result, or a blob isn't valid in its
+///   encoding
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn query_decode_blobs<'a>(
+    env: Env<'a>,
+    conn_id: &str,
+    query: &str,
+    args: Vec<Term<'a>>,
+    decodings: Vec<(String, Atom)>,
+    max_result_bytes: Option<u64>,
+    trx_id: Option<&str>,
+) -> NifResult<Term<'a>> {
+    let decodings = decodings
+        .into_iter()
+        .map(|(column, encoding)| {
+            crate::decode::decode_text_encoding(encoding)
+                .map(|encoding| (column, encoding))
+                .ok_or_else(|| {
+                    rustler::Error::Term(Box::new(
+                        "Invalid text encoding; expected :utf16le or :latin1",
+                    ))
+                })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    query_converting(
+        env,
+        conn_id,
+        query,
+        args,
+        ConvertingOptions {
+            max_result_bytes,
+            trx_id,
+            context: "query_decode_blobs",
+        },
+        |value, _| value,
+        &decodings,
+    )
+}
+
+/// Result rows together with what can be known about each column's nullability.
+pub struct NullableRows {
+    /// Result column names, in order
+    pub columns: Vec<String>,
+    /// Origin table of each column, where known
+    pub tables: Vec<Option<String>>,
+    /// Whether each column can be NULL: `None` when it isn't a plain column reference
+    pub nullable: Vec<Option<bool>>,
+    /// Row values
+    pub rows: Vec<Vec<Value>>,
+}
+
+/// Whether each `(database, table, column)` origin can hold NULL, from the table schema.
+///
+/// A column is not nullable if it is declared `NOT NULL`, is an `INTEGER PRIMARY KEY`
+/// (an alias for the rowid), or is part of the primary key of a `WITHOUT ROWID` table.
+/// Origins that can't be found in the schema, such as the `rowid` pseudo-column, are
+/// `None`.
+pub async fn origin_nullability(
+    conn: &libsql::Connection,
+    origins: &[Option<(String, String, String)>],
+) -> Result<Vec<Option<bool>>, libsql::Error> {
+    // (name, notnull, pk, type) for each column, and whether the table is WITHOUT ROWID
+    type TableInfo = (Vec<(String, bool, i64, String)>, bool);
+    let mut tables: HashMap<(String, String), TableInfo> = HashMap::new();
+
+    let mut nullability = Vec::with_capacity(origins.len());
+    for origin in origins {
+        let Some((database, table, column)) = origin else {
+            nullability.push(None);
+            continue;
+        };
+
+        let key = (database.clone(), table.clone());
+        if !tables.contains_key(&key) {
+            let mut columns = Vec::new();
+            let mut rows = conn
+                .query(
+                    "SELECT name, \"notnull\", pk, type FROM pragma_table_info(?1, ?2)",
+                    vec![Value::Text(table.clone()), Value::Text(database.clone())],
+                )
+                .await?;
+            while let Some(row) = rows.next().await? {
+                columns.push((
+                    row.get::<String>(0)?,
+                    row.get::<i64>(1)? != 0,
+                    row.get::<i64>(2)?,
+                    row.get::<String>(3)?,
+                ));
+            }
+            drop(rows);
+
+            let mut rows = conn
+                .query(
+                    "SELECT wr FROM pragma_table_list WHERE schema = ?1 AND name = ?2",
+                    vec![Value::Text(database.clone()), Value::Text(table.clone())],
+                )
+                .await?;
+            let without_rowid = match rows.next().await? {
+                Some(row) => row.get::<i64>(0)? != 0,
+                None => false,
+            };
+            tables.insert(key.clone(), (columns, without_rowid));
+        }
+
+        let nullable = tables.get(&key).and_then(|(columns, without_rowid)| {
+            let pk_columns = columns.iter().filter(|(_, _, pk, _)| *pk > 0).count();
+            columns
+                .iter()
+                .find(|(name, ..)| name.eq_ignore_ascii_case(column))
+                .map(|(_, notnull, pk, decl_type)| {
+                    let rowid_alias =
+                        *pk > 0 && pk_columns == 1 && decl_type.eq_ignore_ascii_case("INTEGER");
+                    let without_rowid_key = *pk > 0 && *without_rowid;
+                    !(*notnull || rowid_alias || without_rowid_key)
+                })
+        });
+        nullability.push(nullable);
+    }
+
+    Ok(nullability)
+}
+
+/// Run a query and report which of its result columns can be NULL.
+///
+/// Nullability is derived from the schema of each column's origin table, so it is only
+/// known for plain column references. Expressions, aggregates and literals are `None`.
+pub async fn fetch_with_nullability(
+    conn: &libsql::Connection,
+    query: &str,
+    params: Vec<Value>,
+) -> Result<NullableRows, libsql::Error> {
+    let stmt = conn.prepare(query).await?;
+    let origins: Vec<Option<(String, String, String)>> = stmt
+        .columns()
+        .iter()
+        .map(|column| {
+            match (
+                column.database_name(),
+                column.table_name(),
+                column.origin_name(),
+            ) {
+                (Some(database), Some(table), Some(origin)) => {
+                    Some((database.to_string(), table.to_string(), origin.to_string()))
+                }
+                _ => None,
+            }
+        })
+        .collect();
+    let tables = column_origin_tables(&stmt);
+    let nullable = origin_nullability(conn, &origins).await?;
+
+    let mut rows = stmt.query(params).await?;
+    let column_count = rows.column_count();
+    let columns: Vec<String> = (0..column_count)
+        .map(|i| {
+            rows.column_name(i)
+                .map_or_else(|| format!("col{i}"), str::to_string)
+        })
+        .collect();
+
+    let mut collected = Vec::new();
+    while let Some(row) = rows.next().await? {
+        let values = (0..column_count)
+            .map(|i| row.get_value(i))
+            .collect::<Result<Vec<_>, _>>()?;
+        collected.push(values);
+    }
+
+    Ok(NullableRows {
+        columns,
+        tables,
+        nullable,
+        rows: collected,
+    })
+}
+
+/// Execute a query, annotating each result column with whether it can be NULL.
+///
+/// For code generation from query results. Nullability comes from the origin table's
+/// schema (`NOT NULL`, `INTEGER PRIMARY KEY`, `WITHOUT ROWID` keys), so it is only known
+/// for plain column references; expressions and aggregates are reported as `:unknown`.
+/// Columns from the optional side of an outer join are reported from their table
+/// definition, although the join itself may produce NULLs.
+///
+/// # Arguments
+/// - `env`: Elixir environment
+/// - `conn_id`: Database connection ID
+/// - `query`: SQL query returning rows
+/// - `args`: Query parameter values
+///
+/// # Returns
+/// - A result map as from `query_args`, plus `"nullable"`: a list holding `true`,
+///   `false` or `:unknown` for each column
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn query_with_nullability<'a>(
+    env: Env<'a>,
+    conn_id: &str,
+    query: &str,
+    args: Vec<Term<'a>>,
+) -> NifResult<Term<'a>> {
+    let client = {
+        let conn_map = safe_lock(&CONNECTION_REGISTRY, "query_with_nullability conn_map")?;
+        conn_map
+            .get(conn_id)
+            .cloned()
+            .ok_or_else(|| rustler::Error::Term(Box::new("Invalid connection ID")))?
+    }; // Lock dropped here
+
+    let params: Vec<Value> = args
+        .into_iter()
+        .map(|t| crate::utils::decode_term_to_value(t))
+        .collect::<Result<_, _>>()
+        .map_err(|e| rustler::Error::Term(Box::new(e)))?;
+
+    let (connection, column_naming) = {
+        let client_guard = safe_lock_arc(&client, "query_with_nullability client")?;
+        count_statements(conn_id, 1);
+        (client_guard.client.clone(), client_guard.column_naming)
+    }; // Outer lock dropped here
+
+    // SAFETY: We're inside TOKIO_RUNTIME.block_on(), so this is synchronous execution.
+    // The std::sync::Mutex guards are safe to hold across await points here because
+    // we're not in a true async context - block_on runs the future to completion.
+    #[allow(clippy::await_holding_lock)]
+    let fetched = TOKIO_RUNTIME.block_on(async {
+        let conn_guard = safe_lock_arc(&connection, "query_with_nullability conn")?;
+        match fetch_with_nullability(&conn_guard, query, params).await {
+            Ok(fetched) => Ok(fetched),
+            Err(e) => Err(query_error(&conn_guard, conn_id, &e).await),
+        }
+    })?;
+
+    let columns = dedupe_column_names(&fetched.columns, &fetched.tables, column_naming);
+    let nullable: Vec<Term<'a>> = fetched
+        .nullable
+        .iter()
+        .map(|n| match n {
+            Some(nullable) => nullable.encode(env),
+            None => unknown().encode(env),
+        })
+        .collect();
+    let num_rows = fetched.rows.len();
+    let rows = fetched
+        .rows
+        .iter()
+        .map(|values| {
+            values
+                .iter()
+                .map(|v| encode_value(env, v))
+                .collect::<Option<Vec<_>>>()
+        })
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(|| rustler::Error::Term(Box::new("Failed to allocate binary for row value")))?;
+
+    let mut result: HashMap<String, Term<'a>> = HashMap::with_capacity(4);
+    result.insert("columns".to_string(), columns.encode(env));
+    result.insert("nullable".to_string(), nullable.encode(env));
+    result.insert("rows".to_string(), rows.encode(env));
+    result.insert("num_rows".to_string(), num_rows.encode(env));
+
+    Ok(result.encode(env))
+}
+
+/// Temporary objects used to capture rowids; dropped again after every capture.
+/// Names are unqualified because trigger bodies can't use schema-qualified tables.
+const CAPTURE_TABLE: &str = "ecto_libsql_captured_rowids";
+const CAPTURE_TRIGGER: &str = "ecto_libsql_capture_rowids";
+const CAPTURE_UPSERT_TRIGGER: &str = "ecto_libsql_capture_upserted_rowids";
+
+/// Run a single `INSERT`, `UPDATE` or `DELETE` and return the rowids it affected.
+///
+/// A temporary `AFTER` trigger on the target table records each affected rowid
+/// (`NEW.rowid`, or `OLD.rowid` for deletes) in a temporary table while the statement
+/// runs. Inserts also get an `AFTER UPDATE` trigger, so rows changed by an upsert's
+/// `ON CONFLICT ... DO UPDATE` are captured too. The triggers and table are dropped
+/// afterwards whether or not the statement succeeded. Rowids are returned in the order
+/// the rows were changed.
+pub async fn execute_capturing(
+    conn: &libsql::Connection,
+    sql: &str,
+    params: Vec<Value>,
+) -> Result<Vec<i64>, String> {
+    let (query_type, table) = dml_target_table(sql).ok_or_else(|| {
+        "Rowid capture requires a single INSERT, UPDATE or DELETE statement".to_string()
+    })?;
+    let (event, row) = match query_type {
+        QueryType::Insert => ("INSERT", "NEW"),
+        QueryType::Update => ("UPDATE", "NEW"),
+        _ => ("DELETE", "OLD"),
+    };
+
+    let upsert_trigger = if query_type == QueryType::Insert {
+        format!(
+            "CREATE TEMP TRIGGER {CAPTURE_UPSERT_TRIGGER} AFTER UPDATE ON {table} BEGIN
+               INSERT INTO {CAPTURE_TABLE} (id) VALUES (NEW.rowid);
+             END;"
+        )
+    } else {
+        String::new()
+    };
+
+    let setup = format!(
+        "DROP TRIGGER IF EXISTS temp.{CAPTURE_TRIGGER};
+         DROP TRIGGER IF EXISTS temp.{CAPTURE_UPSERT_TRIGGER};
+         CREATE TEMP TABLE IF NOT EXISTS temp.{CAPTURE_TABLE} (id INTEGER);
+         DELETE FROM temp.{CAPTURE_TABLE};
+         CREATE TEMP TRIGGER {CAPTURE_TRIGGER} AFTER {event} ON {table} BEGIN
+           INSERT INTO {CAPTURE_TABLE} (id) VALUES ({row}.rowid);
+         END;
+         {upsert_trigger}"
+    );
+
+    let captured = async {
+        conn.execute_batch(&setup)
+            .await
+            .map_err(|e| format!("Failed to set up rowid capture: {e}"))?;
+        conn.execute(sql, params)
+            .await
+            .map_err(|e| format!("Failed to execute statement: {e}"))?;
+
+        let mut rows = conn
+            .query(
+                &format!("SELECT id FROM temp.{CAPTURE_TABLE} ORDER BY rowid"),
+                (),
+            )
+            .await
+            .map_err(|e| format!("Failed to read captured rowids: {e}"))?;
+        let mut rowids = Vec::new();
+        while let Some(row) = rows
+            .next()
+            .await
+            .map_err(|e| format!("Failed to read captured rowids: {e}"))?
+        {
+            rowids.push(
+                row.get::<i64>(0)
+                    .map_err(|e| format!("Failed to read captured rowid: {e}"))?,
+            );
+        }
+        Ok::<_, String>(rowids)
+    }
+    .await;
+
+    let cleanup = conn
+        .execute_batch(&format!(
+            "DROP TRIGGER IF EXISTS temp.{CAPTURE_TRIGGER};
+             DROP TRIGGER IF EXISTS temp.{CAPTURE_UPSERT_TRIGGER};
+             DROP TABLE IF EXISTS temp.{CAPTURE_TABLE};"
+        ))
+        .await
+        .map_err(|e| format!("Failed to clean up rowid capture: {e}"));
+
+    // Report the statement's own error in preference to a cleanup failure
+    let rowids = captured?;
+    cleanup?;
+    Ok(rowids)
+}
+
+/// Execute a DML statement and return the rowids of the rows it affected.
+///
+/// A fallback for capturing changed rows where `RETURNING` is unavailable or would
+/// return more than needed. Works by installing a temporary trigger on the statement's
+/// target table for the duration of the call; see `execute_capturing`. Rows changed by
+/// other triggers or foreign key actions on other tables are not included.
+///
+/// # Arguments
+/// - `conn_id`: Database connection ID
+/// - `sql`: A single `INSERT`, `REPLACE`, `UPDATE` or `DELETE` statement (not `WITH ...`)
+/// - `args`: Statement parameters
+///
+/// # Returns
+/// - List of affected rowids
+/// - `{:error, reason}` - Unsupported statement or the statement failed
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn capture_affected_rowids<'a>(
+    conn_id: &str,
+    sql: &str,
+    args: Vec<Term<'a>>,
+) -> NifResult<Vec<i64>> {
+    let client = {
+        let conn_map = safe_lock(&CONNECTION_REGISTRY, "capture_affected_rowids conn_map")?;
+        conn_map
+            .get(conn_id)
+            .cloned()
+            .ok_or_else(|| rustler::Error::Term(Box::new("Invalid connection ID")))?
+    }; // Lock dropped here
+
+    let params: Vec<Value> = args
+        .into_iter()
+        .map(|t| crate::utils::decode_term_to_value(t))
+        .collect::<Result<_, _>>()
+        .map_err(|e| rustler::Error::Term(Box::new(e)))?;
+
+    // Clone the inner connection Arc and drop the outer lock before async operations
+    let connection = {
+        let client_guard = safe_lock_arc(&client, "capture_affected_rowids client")?;
+        client_guard.client.clone()
+    }; // Outer lock dropped here
+
+    // SAFETY: We're inside TOKIO_RUNTIME.block_on(), so this is synchronous execution.
+    // The std::sync::Mutex guards are safe to hold across await points here because
+    // we're not in a true async context - block_on runs the future to completion.
+    #[allow(clippy::await_holding_lock)]
+    TOKIO_RUNTIME.block_on(async {
+        let conn_guard = safe_lock_arc(&connection, "capture_affected_rowids conn")?;
+
+        execute_capturing(&conn_guard, sql, params)
+            .await
+            .map_err(|e| rustler::Error::Term(Box::new(e)))
+    })
+}
+
+/// Savepoint used by `compare_and_execute` when called inside an open transaction.
+const COMPARE_SAVEPOINT: &str = "ecto_libsql_compare_and_execute";
+
+/// Outcome of a `compare_and_execute_in` call.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CompareOutcome {
+    /// The precheck matched and the update ran, changing this many rows.
+    Applied(u64),
+    /// The precheck returned this value instead, so nothing was changed.
+    Mismatch(Value),
+}
+
+/// Compare a precheck result with an expected value.
+///
+/// Integers and reals compare numerically, so `1` matches `1.0`; other values must
+/// match exactly, with NULL matching only NULL.
+pub fn precheck_matches(actual: &Value, expected: &Value) -> bool {
+    match (actual, expected) {
+        (Value::Integer(a), Value::Real(e)) => (*a as f64) == *e,
+        (Value::Real(a), Value::Integer(e)) => *a == (*e as f64),
+        _ => actual == expected,
+    }
+}
+
+/// Run `update_sql` only if the scalar `precheck_sql` returns `expected`.
+///
+/// The precheck and update run in one transaction, started with `BEGIN IMMEDIATE` so no
+/// other writer can change the checked value in between. Inside an open transaction a
+/// savepoint is used instead. The precheck's first column of its first row is compared
+/// (NULL when it returns no rows); on a mismatch the transaction is rolled back and the
+/// actual value is returned.
+pub async fn compare_and_execute_in(
+    conn: &libsql::Connection,
+    precheck_sql: &str,
+    precheck_params: Vec<Value>,
+    expected: &Value,
+    update_sql: &str,
+    update_params: Vec<Value>,
+) -> Result<CompareOutcome, libsql::Error> {
+    let nested = !conn.is_autocommit();
+    let (begin, commit, rollback) = if nested {
+        (
+            format!("SAVEPOINT {COMPARE_SAVEPOINT}"),
+            format!("RELEASE SAVEPOINT {COMPARE_SAVEPOINT}"),
+            format!(
+                "ROLLBACK TO SAVEPOINT {COMPARE_SAVEPOINT}; RELEASE SAVEPOINT {COMPARE_SAVEPOINT}"
+            ),
+        )
+    } else {
+        (
+            "BEGIN IMMEDIATE".to_string(),
+            "COMMIT".to_string(),
+            "ROLLBACK".to_string(),
+        )
+    };
+
+    conn.execute_batch(&begin).await?;
+
+    let outcome = async {
+        let actual = {
+            let mut rows = conn.query(precheck_sql, precheck_params).await?;
+            match rows.next().await? {
+                Some(row) => row.get_value(0)?,
+                None => Value::Null,
+            }
+        };
+        if !precheck_matches(&actual, expected) {
+            return Ok(CompareOutcome::Mismatch(actual));
+        }
+        let affected = conn.execute(update_sql, update_params).await?;
+        Ok(CompareOutcome::Applied(affected))
+    }
+    .await;
+
+    match outcome {
+        Ok(CompareOutcome::Applied(affected)) => {
+            if let Err(e) = conn.execute_batch(&commit).await {
+                let _ = conn.execute_batch(&rollback).await;
+                return Err(e);
+            }
+            Ok(CompareOutcome::Applied(affected))
+        }
+        other => {
+            // Best effort: report the original error rather than a failed rollback.
+            let _ = conn.execute_batch(&rollback).await;
+            other
+        }
+    }
+}
+
+/// Run an update only if a precheck query returns the expected value.
+///
+/// An optimistic-concurrency primitive: `precheck_sql` is a scalar query (e.g.
+/// `SELECT version FROM docs WHERE id = ?`) whose result is compared with `expected`.
+/// The precheck and `update_sql` run in a single transaction, so the checked value
+/// cannot change between the two. See `compare_and_execute_in`.
+///
+/// # Arguments
+/// - `env`: Elixir environment
+/// - `conn_id`: Database connection ID
+/// - `precheck_sql`: Scalar query; its first column of its first row is compared
+/// - `precheck_args`: Precheck parameters
+/// - `expected`: Value the precheck must return
+/// - `update_sql`: Statement to run when the precheck matches
+/// - `update_args`: Update parameters
+///
+/// # Returns
+/// - `{:ok, affected}` - The precheck matched and the update ran
+/// - `{:error, :precondition_failed, actual}` - The precheck returned `actual` instead
+/// - `{:error, reason}` - Either statement failed
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn compare_and_execute<'a>(
+    env: Env<'a>,
+    conn_id: &str,
+    precheck_sql: &str,
+    precheck_args: Vec<Term<'a>>,
+    expected: Term<'a>,
+    update_sql: &str,
+    update_args: Vec<Term<'a>>,
+) -> NifResult<Term<'a>> {
+    let client = {
+        let conn_map = safe_lock(&CONNECTION_REGISTRY, "compare_and_execute conn_map")?;
+        conn_map
+            .get(conn_id)
+            .cloned()
+            .ok_or_else(|| rustler::Error::Term(Box::new("Invalid connection ID")))?
+    }; // Lock dropped here
+
+    let decode = |args: Vec<Term<'a>>| -> NifResult<Vec<Value>> {
+        args.into_iter()
+            .map(|t| crate::utils::decode_term_to_value(t))
+            .collect::<Result<_, _>>()
+            .map_err(|e| rustler::Error::Term(Box::new(e)))
+    };
+    let precheck_params = decode(precheck_args)?;
+    let update_params = decode(update_args)?;
+    let expected = crate::utils::decode_term_to_value(expected)
+        .map_err(|e| rustler::Error::Term(Box::new(e)))?;
+
+    let connection = {
+        let client_guard = safe_lock_arc(&client, "compare_and_execute client")?;
+        count_statements(conn_id, 2);
+        client_guard.client.clone()
+    }; // Outer lock dropped here
+
+    // SAFETY: We're inside TOKIO_RUNTIME.block_on(), so this is synchronous execution.
+    // The std::sync::Mutex guards are safe to hold across await points here because
+    // we're not in a true async context - block_on runs the future to completion.
+    #[allow(clippy::await_holding_lock)]
+    let outcome = TOKIO_RUNTIME.block_on(async {
+        let conn_guard = safe_lock_arc(&connection, "compare_and_execute conn")?;
+        match compare_and_execute_in(
+            &conn_guard,
+            precheck_sql,
+            precheck_params,
+            &expected,
+            update_sql,
+            update_params,
+        )
+        .await
+        {
+            Ok(outcome) => Ok(outcome),
+            Err(e) => Err(query_error(&conn_guard, conn_id, &e).await),
+        }
+    })?;
+
+    match outcome {
+        CompareOutcome::Applied(affected) => Ok((ok(), affected).encode(env)),
+        CompareOutcome::Mismatch(actual) => {
+            let actual = encode_value(env, &actual).ok_or_else(|| {
+                rustler::Error::Term(Box::new("Failed to allocate binary for precheck value"))
+            })?;
+            Ok((error(), precondition_failed(), actual).encode(env))
+        }
+    }
+}
+
+/// Execute a query and return each row as a tuple, ready for `:ets.insert/2`.
+///
+/// Erlang offers no NIF API for writing to ETS, so the rows are built here as tuples
+/// (one element per column, in column order) and inserted by the caller with a single
+/// `:ets.insert/2`, rather than being converted from the row lists `query_args` returns.
+///
+/// # Arguments
+/// - `env`: Elixir environment
+/// - `conn_id`: Database connection ID
+/// - `query`: SQL query returning rows
+/// - `args`: Query parameter values
+///
+/// # Returns
+/// - List of row tuples
+/// - `{:error, reason}` - The query failed
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn query_tuples<'a>(
+    env: Env<'a>,
+    conn_id: &str,
+    query: &str,
+    args: Vec<Term<'a>>,
+) -> NifResult<Vec<Term<'a>>> {
+    let client = {
+        let conn_map = safe_lock(&CONNECTION_REGISTRY, "query_tuples conn_map")?;
+        conn_map
+            .get(conn_id)
+            .cloned()
+            .ok_or_else(|| rustler::Error::Term(Box::new("Invalid connection ID")))?
+    }; // Lock dropped here
+
+    let params: Vec<Value> = args
+        .into_iter()
+        .map(|t| crate::utils::decode_term_to_value(t))
+        .collect::<Result<_, _>>()
+        .map_err(|e| rustler::Error::Term(Box::new(e)))?;
+
+    let connection = {
+        let client_guard = safe_lock_arc(&client, "query_tuples client")?;
+        count_statements(conn_id, 1);
+        client_guard.client.clone()
+    }; // Outer lock dropped here
+
+    // SAFETY: We're inside TOKIO_RUNTIME.block_on(), so this is synchronous execution.
+    // The std::sync::Mutex guards are safe to hold across await points here because
+    // we're not in a true async context - block_on runs the future to completion.
+    #[allow(clippy::await_holding_lock)]
+    let rows = TOKIO_RUNTIME.block_on(async {
+        let conn_guard = safe_lock_arc(&connection, "query_tuples conn")?;
+        let fetched = async {
+            let rows = conn_guard.query(query, params).await?;
+            crate::cursor::collect_cursor_rows(rows, None).await
+        }
+        .await;
+        match fetched {
+            Ok((_columns, rows)) => Ok(rows),
+            Err(e) => Err(query_error(&conn_guard, conn_id, &e).await),
+        }
+    })?;
+
+    rows.iter()
+        .map(|values| {
+            values
+                .iter()
+                .map(|v| encode_value(env, v))
+                .collect::<Option<Vec<_>>>()
+                .map(|elements| rustler::types::tuple::make_tuple(env, &elements))
+        })
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(|| rustler::Error::Term(Box::new("Failed to allocate binary for row value")))
+}
+
+/// Rows fetched by `fetch_converting`: column names, origin tables and values.
+pub type FetchedRows = (Vec<String>, Vec<Option<String>>, Vec<Vec<Value>>);
+
+/// Start a read transaction and pin its snapshot, unless one is already open.
+///
+/// A deferred transaction only takes its snapshot at its first read, so the schema is
+/// read straight away to fix the point in time. Returns whether a transaction was begun,
+/// to pass to `end_read_snapshot`. Inside an open transaction the caller already has a
+/// consistent view, so nothing is begun.
+pub async fn begin_read_snapshot(conn: &libsql::Connection) -> Result<bool, libsql::Error> {
+    if !conn.is_autocommit() {
+        return Ok(false);
+    }
+    conn.execute_batch("BEGIN DEFERRED").await?;
+    let pinned = async {
+        let mut rows = conn.query("SELECT count(*) FROM sqlite_schema", ()).await?;
+        rows.next().await.map(|_| ())
+    }
+    .await;
+    if let Err(e) = pinned {
+        let _ = conn.execute_batch("ROLLBACK").await;
+        return Err(e);
+    }
+    Ok(true)
+}
+
+/// Finish a read transaction started by `begin_read_snapshot`, if it began one.
+pub async fn end_read_snapshot(
+    conn: &libsql::Connection,
+    begun: bool,
+) -> Result<(), libsql::Error> {
+    if begun {
+        conn.execute_batch("COMMIT").await?;
+    }
+    Ok(())
+}
+
+/// Run each of `queries` against the same snapshot of the database.
+///
+/// Results are returned in query order. If any query fails the read transaction is rolled
+/// back and the error returned.
+pub async fn consistent_read_in(
+    conn: &libsql::Connection,
+    queries: Vec<(String, Vec<Value>)>,
+) -> Result<Vec<FetchedRows>, libsql::Error> {
+    let begun = begin_read_snapshot(conn).await?;
+
+    let mut results = Vec::with_capacity(queries.len());
+    for (sql, params) in queries {
+        match fetch_converting(conn, &sql, params, |value, _| value, None).await {
+            // Without a limit the fetch can't run over it
+            Ok(fetched) => results.push(fetched.unwrap_or_default()),
+            Err(e) => {
+                if begun {
+                    let _ = conn.execute_batch("ROLLBACK").await;
+                }
+                return Err(e);
+            }
+        }
+    }
+
+    end_read_snapshot(conn, begun).await?;
+    Ok(results)
+}
+
+/// Run several read queries against one point-in-time snapshot of the database.
+///
+/// The queries share a single read transaction, so their results are consistent with
+/// each other even while other connections write: in WAL mode writers carry on and their
+/// changes are simply not seen, while in rollback-journal mode they wait for the read to
+/// finish. Inside an open transaction the queries run in it instead. Meant for exports
+/// that must not mix rows from before and after a concurrent write.
+///
+/// # Arguments
+/// - `env`: Elixir environment
+/// - `conn_id`: Database connection ID
+/// - `queries`: `{name, sql, args}` tuples; `name` may be any term
+///
+/// # Returns
+/// - Map of each `name` to a result map as from `query_args`
+/// - `{:error, reason}` - A query failed; no results are returned
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn consistent_read<'a>(
+    env: Env<'a>,
+    conn_id: &str,
+    queries: Vec<(Term<'a>, String, Vec<Term<'a>>)>,
+) -> NifResult<Term<'a>> {
+    let client = {
+        let conn_map = safe_lock(&CONNECTION_REGISTRY, "consistent_read conn_map")?;
+        conn_map
+            .get(conn_id)
+            .cloned()
+            .ok_or_else(|| rustler::Error::Term(Box::new("Invalid connection ID")))?
+    }; // Lock dropped here
+
+    let mut names = Vec::with_capacity(queries.len());
+    let mut statements = Vec::with_capacity(queries.len());
+    for (name, sql, args) in queries {
+        let params: Vec<Value> = args
+            .into_iter()
+            .map(|t| crate::utils::decode_term_to_value(t))
+            .collect::<Result<_, _>>()
+            .map_err(|e| rustler::Error::Term(Box::new(e)))?;
+        names.push(name);
+        statements.push((sql, params));
+    }
+
+    let (connection, column_naming) = {
+        let client_guard = safe_lock_arc(&client, "consistent_read client")?;
+        count_statements(conn_id, statements.len() as u64);
+        (client_guard.client.clone(), client_guard.column_naming)
+    }; // Outer lock dropped here
+
+    // SAFETY: We're inside TOKIO_RUNTIME.block_on(), so this is synchronous execution.
+    // The std::sync::Mutex guards are safe to hold across await points here because
+    // we're not in a true async context - block_on runs the future to completion.
+    #[allow(clippy::await_holding_lock)]
+    let results = TOKIO_RUNTIME.block_on(async {
+        let conn_guard = safe_lock_arc(&connection, "consistent_read conn")?;
+        match consistent_read_in(&conn_guard, statements).await {
+            Ok(results) => Ok(results),
+            Err(e) => Err(query_error(&conn_guard, conn_id, &e).await),
+        }
+    })?;
+
+    let mut map = Term::map_new(env);
+    for (name, (columns, tables, rows)) in names.into_iter().zip(results) {
+        let result = encode_fetched(env, &columns, &tables, &rows, column_naming)?;
+        map = map.map_put(name, result)?;
+    }
+    Ok(map)
+}
+
+/// Fingerprint the first row `sql` returns, or `None` when it returns no rows.
+///
+/// See `row_fingerprint_of` for how values are hashed. Rows after the first are ignored.
+pub async fn fingerprint_first_row(
+    conn: &libsql::Connection,
+    sql: &str,
+    params: Vec<Value>,
+) -> Result<Option<String>, libsql::Error> {
+    let mut rows = conn.query(sql, params).await?;
+    let Some(row) = rows.next().await? else {
+        return Ok(None);
+    };
+    let values = (0..row.column_count())
+        .map(|i| row.get_value(i))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Some(row_fingerprint_of(&values)))
+}
+
+/// Execute a single-row query and return a fingerprint of its values, for deduplication.
+///
+/// The fingerprint hashes the row's values in column order, tagging each with its type,
+/// so identical rows always fingerprint equal, across connections and restarts. It is a
+/// 64-bit hash: rows that differ almost always fingerprint differently, but collisions
+/// are possible, so confirm matches before discarding data. See `fingerprint_first_row`.
+///
+/// # Arguments
+/// - `conn_id`: Database connection ID
+/// - `sql`: Query returning the row; rows after the first are ignored
+/// - `args`: Query parameter values
+///
+/// # Returns
+/// - Fingerprint as 16 hex digits
+/// - `nil` - The query returned no rows
+/// - `{:error, reason}` - The query failed
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn row_fingerprint<'a>(
+    conn_id: &str,
+    sql: &str,
+    args: Vec<Term<'a>>,
+) -> NifResult<Option<String>> {
+    let client = {
+        let conn_map = safe_lock(&CONNECTION_REGISTRY, "row_fingerprint conn_map")?;
+        conn_map
+            .get(conn_id)
+            .cloned()
+            .ok_or_else(|| rustler::Error::Term(Box::new("Invalid connection ID")))?
+    }; // Lock dropped here
+
+    let params: Vec<Value> = args
+        .into_iter()
+        .map(|t| crate::utils::decode_term_to_value(t))
+        .collect::<Result<_, _>>()
+        .map_err(|e| rustler::Error::Term(Box::new(e)))?;
+
+    let connection = {
+        let client_guard = safe_lock_arc(&client, "row_fingerprint client")?;
+        count_statements(conn_id, 1);
+        client_guard.client.clone()
+    }; // Outer lock dropped here
+
+    // SAFETY: We're inside TOKIO_RUNTIME.block_on(), so this is synchronous execution.
+    // The std::sync::Mutex guards are safe to hold across await points here because
+    // we're not in a true async context - block_on runs the future to completion.
+    #[allow(clippy::await_holding_lock)]
+    TOKIO_RUNTIME.block_on(async {
+        let conn_guard = safe_lock_arc(&connection, "row_fingerprint conn")?;
+        match fingerprint_first_row(&conn_guard, sql, params).await {
+            Ok(fingerprint) => Ok(fingerprint),
+            Err(e) => Err(query_error(&conn_guard, conn_id, &e).await),
+        }
+    })
+}
+
+/// Match named column transforms to the result columns of a prepared statement.
+///
+/// Columns are named as query results name them, after duplicates are disambiguated
+/// with `naming`. Returns the transform for each column by index, alongside each
+/// column's origin table, or an error naming a transform column the query doesn't
+/// return. The statement is only inspected, so the caller can go on to run it.
+#[allow(clippy::type_complexity)]
+pub fn resolve_transforms(
+    stmt: &libsql::Statement,
+    naming: ColumnNaming,
+    transforms: &[(String, ColumnTransform)],
+) -> Result<(Vec<Option<ColumnTransform>>, Vec<Option<String>>), String> {
+    let names: Vec<String> = stmt
+        .columns()
+        .iter()
+        .map(|column| column.name().to_string())
+        .collect();
+    let tables = column_origin_tables(stmt);
+    let names = dedupe_column_names(&names, &tables, naming);
+
+    let mut by_index = vec![None; names.len()];
+    for (column, transform) in transforms {
+        let index = names
+            .iter()
+            .position(|name| name == column)
+            .ok_or_else(|| format!("Column not found in result: {column}"))?;
+        by_index[index] = Some(*transform);
+    }
+    Ok((by_index, tables))
+}
+
+/// Execute a query, applying predefined transforms to named columns as rows are read.
+///
+/// Keeps simple per-row string work, such as lowercasing an email column, out of
+/// Elixir. The transform set is deliberately small and fixed: `:lower`, `:upper`,
+/// `:trim` and `:base64` (see `ColumnTransform`). Columns are named as in query
+/// results, after the connection's duplicate-column naming is applied.
+///
+/// # Arguments
+/// - `conn_id`: Database connection ID
+/// - `sql`: Query to run
+/// - `args`: Positional query parameters
+/// - `transforms`: Map of column name to transform atom
+///
+/// # Returns
+/// - Result map with `columns`, `rows` and `num_rows`, as `query_args` returns
+/// - `{:error, reason}` - Unknown transform or column, or the query failed
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn query_transform<'a>(
+    env: Env<'a>,
+    conn_id: &str,
+    sql: &str,
+    args: Vec<Term<'a>>,
+    transforms: HashMap<String, Atom>,
+) -> NifResult<Term<'a>> {
+    let client = {
+        let conn_map = safe_lock(&CONNECTION_REGISTRY, "query_transform conn_map")?;
+        conn_map
+            .get(conn_id)
+            .cloned()
+            .ok_or_else(|| rustler::Error::Term(Box::new("Invalid connection ID")))?
+    }; // Lock dropped here
+
+    let params: Vec<Value> = args
+        .into_iter()
+        .map(|t| crate::utils::decode_term_to_value(t))
+        .collect::<Result<_, _>>()
+        .map_err(|e| rustler::Error::Term(Box::new(e)))?;
+
+    let transforms: Vec<(String, ColumnTransform)> = transforms
+        .into_iter()
+        .map(
+            |(column, atom)| match crate::decode::decode_column_transform(atom) {
+                Some(transform) => Ok((column, transform)),
+                None => Err(rustler::Error::Term(Box::new(format!(
+                    "Unknown transform for column {column}"
+                )))),
+            },
+        )
+        .collect::<Result<_, _>>()?;
+
+    let (connection, column_naming) = {
+        let client_guard = safe_lock_arc(&client, "query_transform client")?;
+        count_statements(conn_id, 1);
+        (client_guard.client.clone(), client_guard.column_naming)
+    }; // Outer lock dropped here
+
+    // SAFETY: We're inside TOKIO_RUNTIME.block_on(), so this is synchronous execution.
+    // The std::sync::Mutex guards are safe to hold across await points here because
+    // we're not in a true async context - block_on runs the future to completion.
+    #[allow(clippy::await_holding_lock)]
+    TOKIO_RUNTIME.block_on(async {
+        let conn_guard = safe_lock_arc(&connection, "query_transform conn")?;
+
+        let stmt = match conn_guard.prepare(sql).await {
+            Ok(stmt) => stmt,
+            Err(e) => return Err(query_error(&conn_guard, conn_id, &e).await),
+        };
+        let (by_index, tables) = resolve_transforms(&stmt, column_naming, &transforms)
+            .map_err(|e| rustler::Error::Term(Box::new(e)))?;
+
+        match stmt.query(params).await {
+            Ok(rows) => {
+                collect_rows_transformed(env, rows, column_naming, &tables, None, &by_index).await
+            }
+            Err(e) => Err(query_error(&conn_guard, conn_id, &e).await),
+        }
+    })
+}
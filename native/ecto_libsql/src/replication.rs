@@ -11,8 +11,8 @@
 /// This pattern is safe because we use `TOKIO_RUNTIME.block_on()` which executes
 /// the entire async block on a dedicated thread pool, preventing deadlocks.
 use crate::constants::*;
-use crate::utils::{safe_lock, safe_lock_arc};
-use rustler::{Atom, NifResult};
+use crate::utils::{require_replica, safe_lock, safe_lock_arc};
+use rustler::{Atom, Encoder, Env, NifResult, Term};
 
 /// Get the current replication index (frame number) from a remote replica database.
 ///
@@ -73,7 +73,8 @@ pub fn get_frame_number(conn_id: &str) -> NifResult<u64> {
 /// - `conn_id`: Database connection ID
 /// - `frame_no`: Target frame number to sync to
 ///
-/// Returns `:ok` when sync completes successfully, error on timeout or failure.
+/// Returns `:ok` when sync completes successfully, error on timeout or failure, or
+/// `{:error, :not_a_replica}` when the connection is not a remote replica.
 #[rustler::nif(schedule = "DirtyIo")]
 pub fn sync_until(conn_id: &str, frame_no: u64) -> NifResult<Atom> {
     let conn_map = safe_lock(&CONNECTION_REGISTRY, "sync_until conn_map")?;
@@ -82,6 +83,7 @@ pub fn sync_until(conn_id: &str, frame_no: u64) -> NifResult<Atom> {
         .ok_or_else(|| rustler::Error::Term(Box::new("Connection not found")))?
         .clone();
     drop(conn_map);
+    require_replica(safe_lock_arc(&client, "sync_until mode")?.mode)?;
 
     // SAFETY: We use TOKIO_RUNTIME.block_on(), which runs the future synchronously on a dedicated
     // thread pool. This prevents deadlocks that could occur if we were in a true async context
@@ -117,7 +119,8 @@ pub fn sync_until(conn_id: &str, frame_no: u64) -> NifResult<Atom> {
 /// # Arguments
 /// - `conn_id`: Database connection ID
 ///
-/// Returns the frame number after flush (0 if not a replica)
+/// Returns the frame number after flush, or `{:error, :not_a_replica}` when the
+/// connection is not a remote replica
 #[rustler::nif(schedule = "DirtyIo")]
 pub fn flush_replicator(conn_id: &str) -> NifResult<u64> {
     let conn_map = safe_lock(&CONNECTION_REGISTRY, "flush_replicator conn_map")?;
@@ -126,6 +129,7 @@ pub fn flush_replicator(conn_id: &str) -> NifResult<u64> {
         .ok_or_else(|| rustler::Error::Term(Box::new("Connection not found")))?
         .clone();
     drop(conn_map);
+    require_replica(safe_lock_arc(&client, "flush_replicator mode")?.mode)?;
 
     // SAFETY: We use TOKIO_RUNTIME.block_on(), which runs the future synchronously on a dedicated
     // thread pool. This prevents deadlocks that could occur if we were in a true async context
@@ -144,7 +148,7 @@ pub fn flush_replicator(conn_id: &str) -> NifResult<u64> {
             })?
             .map_err(|e| format!("flush_replicator failed: {e}"))?;
 
-        // Return 0 if not a replica (consistent with get_frame_number behavior)
+        // Return 0 if no frames have been applied (consistent with get_frame_number)
         Ok(frame_no.unwrap_or(0))
     });
 
@@ -185,6 +189,186 @@ pub fn max_write_replication_index(conn_id: &str) -> NifResult<u64> {
     Ok(max_write_frame.unwrap_or(0))
 }
 
+/// Whether a replica at `current_frame` trails `max_write_frame` by more than
+/// `max_lag_frames` frames.
+pub fn sync_needed(current_frame: u64, max_write_frame: u64, max_lag_frames: u64) -> bool {
+    max_write_frame.saturating_sub(current_frame) > max_lag_frames
+}
+
+/// Sync a remote replica only when it is more than `max_lag_frames` frames behind.
+///
+/// For cost control: compares the replica's replication index with the highest frame
+/// written through this database (`max_write_replication_index`) and only makes the
+/// sync round trip when the gap exceeds `max_lag_frames` (see `sync_needed`). The
+/// primary's own position can't be read without syncing, so writes made by other
+/// clients since the last sync are not counted as lag.
+///
+/// **Timeout**: The sync has the default sync timeout.
+///
+/// # Arguments
+/// - `env`: Elixir environment
+/// - `conn_id`: Database connection ID
+/// - `max_lag_frames`: Frames the replica may trail by before it is synced
+///
+/// # Returns
+/// - `{:synced, frame}` - The replica was synced; `frame` is its new replication index
+/// - `:up_to_date` - Within `max_lag_frames`, so no sync was made
+/// - `{:error, :not_a_replica}` - The connection is local or direct remote
+/// - `{:error, reason}` - The sync failed or timed out
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn sync_if_behind<'a>(env: Env<'a>, conn_id: &str, max_lag_frames: u64) -> NifResult<Term<'a>> {
+    let conn_map = safe_lock(&CONNECTION_REGISTRY, "sync_if_behind conn_map")?;
+    let client = conn_map
+        .get(conn_id)
+        .ok_or_else(|| rustler::Error::Term(Box::new("Connection not found")))?
+        .clone();
+    drop(conn_map);
+
+    // SAFETY: We use TOKIO_RUNTIME.block_on(), which runs the future synchronously on a dedicated
+    // thread pool. This prevents deadlocks that could occur if we were in a true async context
+    // with std::sync::Mutex guards held across await points.
+    #[allow(clippy::await_holding_lock)]
+    let synced_frame = TOKIO_RUNTIME.block_on(async {
+        // Lock must be held for the entire async operation since Database is not cloneable
+        let client_guard = safe_lock_arc(&client, "sync_if_behind client")?;
+        require_replica(client_guard.mode)?;
+
+        let current_frame = client_guard
+            .db
+            .replication_index()
+            .await
+            .map_err(|e| rustler::Error::Term(Box::new(format!("replication_index failed: {e}"))))?
+            .unwrap_or(0);
+        let max_write_frame = client_guard.db.max_write_replication_index().unwrap_or(0);
+        if !sync_needed(current_frame, max_write_frame, max_lag_frames) {
+            return Ok(None);
+        }
+
+        let timeout_duration = tokio::time::Duration::from_secs(DEFAULT_SYNC_TIMEOUT_SECS);
+        let replicated = tokio::time::timeout(timeout_duration, client_guard.db.sync())
+            .await
+            .map_err(|_| {
+                rustler::Error::Term(Box::new(format!(
+                    "sync timed out after {DEFAULT_SYNC_TIMEOUT_SECS} seconds"
+                )))
+            })?
+            .map_err(|e| rustler::Error::Term(Box::new(format!("sync failed: {e}"))))?;
+
+        Ok(Some(replicated.frame_no().unwrap_or(0)))
+    })?;
+
+    Ok(match synced_frame {
+        Some(frame) => (synced(), frame).encode(env),
+        None => up_to_date().encode(env),
+    })
+}
+
+/// Pause automatic sync on a remote replica connection
+///
+/// **NOT SUPPORTED** - ecto_libsql never syncs automatically after a write; the write
+/// paths ignore the sync mode and `do_sync` only runs when called. The per-write sync
+/// on an embedded replica is libsql's own read-your-writes catch-up, which is fixed
+/// when the `Database` is built (`read_your_writes`) and cannot be switched off per
+/// connection afterwards, so there is no branch for a pause flag to suppress.
+///
+/// # Alternatives
+///
+/// 1. **One transaction** - Run the bulk import inside a single transaction, so the
+///    writes reach the primary as one unit and are caught up once on commit
+///
+/// 2. **Batches** - Send the import through `execute_transactional_batch`, which runs
+///    many statements in one call
+///
+/// # Arguments
+/// - `_conn_id` - Connection identifier (ignored)
+///
+/// # Returns
+/// - `{:error, :unsupported}` - Always returns unsupported
+#[rustler::nif]
+pub fn pause_sync(env: Env, _conn_id: &str) -> NifResult<(Atom, Atom)> {
+    Ok((
+        Atom::from_str(env, "error")?,
+        Atom::from_str(env, "unsupported")?,
+    ))
+}
+
+/// Resume automatic sync on a remote replica connection
+///
+/// **NOT SUPPORTED** - Automatic sync cannot be paused (see `pause_sync`), so there is
+/// nothing to resume.
+///
+/// # Arguments
+/// - `_conn_id` - Connection identifier (ignored)
+///
+/// # Returns
+/// - `{:error, :unsupported}` - Always returns unsupported
+#[rustler::nif]
+pub fn resume_sync(env: Env, _conn_id: &str) -> NifResult<(Atom, Atom)> {
+    Ok((
+        Atom::from_str(env, "error")?,
+        Atom::from_str(env, "unsupported")?,
+    ))
+}
+
+/// Report a remote replica's replication state in one call.
+///
+/// Combines the frame numbers otherwise read separately, for diagnosing sync problems:
+/// - `current_frame` - The replica's committed replication index, as `get_frame_number`
+/// - `max_write_frame` - The highest frame written through this database, as
+///   `max_write_replication_index`
+/// - `durable_frame` - The frame the primary has confirmed as durable. libsql does not
+///   expose this for embedded replicas yet, so it is always `nil`
+///
+/// Frame numbers are 0 when no frames have been applied or written.
+///
+/// # Arguments
+/// - `env`: Elixir environment
+/// - `conn_id`: Database connection ID
+///
+/// # Returns
+/// - `%{current_frame: integer, max_write_frame: integer, durable_frame: nil}`
+/// - `{:error, :not_a_replica}` - The connection is local or direct remote
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn replication_diagnostics<'a>(env: Env<'a>, conn_id: &str) -> NifResult<Term<'a>> {
+    let conn_map = safe_lock(&CONNECTION_REGISTRY, "replication_diagnostics conn_map")?;
+    let client = conn_map
+        .get(conn_id)
+        .ok_or_else(|| rustler::Error::Term(Box::new("Connection not found")))?
+        .clone();
+    drop(conn_map);
+
+    // SAFETY: We use TOKIO_RUNTIME.block_on(), which runs the future synchronously on a dedicated
+    // thread pool. This prevents deadlocks that could occur if we were in a true async context
+    // with std::sync::Mutex guards held across await points.
+    #[allow(clippy::await_holding_lock)]
+    let (current_frame, max_write_frame) = TOKIO_RUNTIME.block_on(async {
+        // Lock must be held for the entire async operation since Database is not cloneable
+        let client_guard = safe_lock_arc(&client, "replication_diagnostics client")?;
+        require_replica(client_guard.mode)?;
+
+        let current_frame = client_guard.db.replication_index().await.map_err(|e| {
+            rustler::Error::Term(Box::new(format!("replication_index failed: {e}")))
+        })?;
+
+        Ok((
+            current_frame.unwrap_or(0),
+            client_guard.db.max_write_replication_index().unwrap_or(0),
+        ))
+    })?;
+
+    Term::map_from_pairs(
+        env,
+        &[
+            (current_frame_key().encode(env), current_frame.encode(env)),
+            (
+                max_write_frame_key().encode(env),
+                max_write_frame.encode(env),
+            ),
+            (durable_frame_key().encode(env), nil().encode(env)),
+        ],
+    )
+}
+
 /// **NOT SUPPORTED** - Freeze database operation is not implemented.
 ///
 /// Freeze is intended to convert a remote replica to a standalone local database
@@ -9,6 +9,21 @@ use crate::transaction::TransactionEntryGuard;
 use libsql::Value;
 use rustler::{Atom, NifResult};
 
+/// Drop savepoints nested inside `name` from a transaction's savepoint stack.
+///
+/// SQLite matches savepoint names case-insensitively, against the most recent
+/// savepoint of that name. Releasing a savepoint also releases every savepoint opened
+/// after it, and rolling back to one keeps it open but discards the later ones. With
+/// `keep` the named savepoint stays on the stack, as after `ROLLBACK TO`.
+pub fn unwind_savepoints(savepoints: &mut Vec<String>, name: &str, keep: bool) {
+    if let Some(position) = savepoints
+        .iter()
+        .rposition(|open| open.eq_ignore_ascii_case(name))
+    {
+        savepoints.truncate(if keep { position + 1 } else { position });
+    }
+}
+
 /// Create a savepoint within a transaction.
 ///
 /// Savepoints allow partial rollback without aborting the entire transaction.
@@ -33,7 +48,7 @@ pub fn savepoint(conn_id: &str, trx_id: &str, name: &str) -> NifResult<Atom> {
     validate_savepoint_name(name)?;
 
     // Take transaction entry with ownership verification using guard
-    let guard = TransactionEntryGuard::take(trx_id, conn_id)?;
+    let mut guard = TransactionEntryGuard::take(trx_id, conn_id)?;
 
     let sql = format!("SAVEPOINT {name}");
 
@@ -44,6 +59,7 @@ pub fn savepoint(conn_id: &str, trx_id: &str, name: &str) -> NifResult<Atom> {
             .await
             .map_err(|e| rustler::Error::Term(Box::new(format!("Savepoint failed: {e}"))))
     })?;
+    guard.savepoints_mut()?.push(name.to_string());
 
     // Guard automatically re-inserts the transaction on drop
     Ok(rustler::types::atom::ok())
@@ -67,7 +83,7 @@ pub fn release_savepoint(conn_id: &str, trx_id: &str, name: &str) -> NifResult<A
     validate_savepoint_name(name)?;
 
     // Take transaction entry with ownership verification using guard
-    let guard = TransactionEntryGuard::take(trx_id, conn_id)?;
+    let mut guard = TransactionEntryGuard::take(trx_id, conn_id)?;
 
     let sql = format!("RELEASE SAVEPOINT {name}");
 
@@ -78,6 +94,7 @@ pub fn release_savepoint(conn_id: &str, trx_id: &str, name: &str) -> NifResult<A
             .await
             .map_err(|e| rustler::Error::Term(Box::new(format!("Release savepoint failed: {e}"))))
     })?;
+    unwind_savepoints(guard.savepoints_mut()?, name, false);
 
     // Guard automatically re-inserts the transaction on drop
     Ok(rustler::types::atom::ok())
@@ -101,7 +118,7 @@ pub fn rollback_to_savepoint(conn_id: &str, trx_id: &str, name: &str) -> NifResu
     validate_savepoint_name(name)?;
 
     // Take transaction entry with ownership verification using guard
-    let guard = TransactionEntryGuard::take(trx_id, conn_id)?;
+    let mut guard = TransactionEntryGuard::take(trx_id, conn_id)?;
 
     let sql = format!("ROLLBACK TO SAVEPOINT {name}");
 
@@ -114,7 +131,31 @@ pub fn rollback_to_savepoint(conn_id: &str, trx_id: &str, name: &str) -> NifResu
                 rustler::Error::Term(Box::new(format!("Rollback to savepoint failed: {e}")))
             })
     })?;
+    unwind_savepoints(guard.savepoints_mut()?, name, true);
 
     // Guard automatically re-inserts the transaction on drop
     Ok(rustler::types::atom::ok())
 }
+
+/// Report how deeply transactions are nested on a connection.
+///
+/// Useful for debugging nested Ecto transactions, which are implemented with
+/// savepoints. Only savepoints created through the savepoint NIFs are counted.
+///
+/// # Arguments
+/// - `conn_id`: Database connection ID
+///
+/// # Returns
+/// - `0` - No transaction is active
+/// - `1` - A top-level transaction with no open savepoints
+/// - `1 + n` - A transaction with `n` open savepoints
+#[rustler::nif]
+pub fn transaction_depth(conn_id: &str) -> NifResult<usize> {
+    let txn_registry = crate::utils::safe_lock(&TXN_REGISTRY, "transaction_depth txn_registry")?;
+    Ok(txn_registry
+        .values()
+        .filter(|entry| entry.conn_id == conn_id)
+        .map(|entry| 1 + entry.savepoints.len())
+        .max()
+        .unwrap_or(0))
+}
@@ -0,0 +1,75 @@
+/// SQLite session extension support
+///
+/// The session extension records changes made on one connection as a changeset, which
+/// can be applied to another database, for offline-first sync. The SQLite bundled with
+/// libsql is compiled with it, but libsql exposes none of its functions
+/// (`sqlite3session_*` and `sqlite3changeset_*`), and calling them directly would need
+/// raw FFI, which this crate forbids (`unsafe_code = "deny"`). The functions here report
+/// `:session_not_available` so callers can detect that and fall back.
+use rustler::{Atom, Binary, Env, NifResult};
+
+/// Apply a changeset produced by the session extension to a connection
+///
+/// **NOT SUPPORTED** - Applying a changeset needs `sqlite3changeset_apply`, which libsql
+/// does not expose. No SQL function or pragma applies a changeset.
+///
+/// # Alternatives
+///
+/// 1. **Replay SQL** - Record changes as SQL statements (for example with
+///    `stream_table_dump`, or a change log table filled by triggers) and replay them
+///    with `execute_batch` inside a transaction
+///
+/// 2. **Embedded replicas** - Use a remote replica connection and `sync`, which ships
+///    changes through libsql's own replication protocol
+///
+/// # Arguments
+/// - `_conn_id` - Connection identifier (ignored)
+/// - `_changeset` - Changeset bytes (ignored)
+/// - `_policy` - Conflict policy: `:abort`, `:replace` or `:omit` (ignored)
+///
+/// # Returns
+/// - `{:error, :session_not_available}` - Always, as the session extension is unreachable
+#[rustler::nif]
+pub fn changeset_apply(
+    env: Env,
+    _conn_id: &str,
+    _changeset: Binary,
+    _policy: Atom,
+) -> NifResult<(Atom, Atom)> {
+    Ok((
+        Atom::from_str(env, "error")?,
+        Atom::from_str(env, "session_not_available")?,
+    ))
+}
+
+/// Record the changes made by a block of statements as a changeset
+///
+/// **NOT SUPPORTED** - Recording needs `sqlite3session_create`, `sqlite3session_attach`
+/// and `sqlite3session_changeset`, which libsql does not expose. The statements are not
+/// run, so callers that fall back to another approach don't apply them twice.
+///
+/// # Alternatives
+///
+/// 1. **Change log table** - Have triggers record inserts, updates and deletes in a log
+///    table, then read and clear the log in the same transaction as the statements
+///
+/// 2. **Embedded replicas** - Write through a remote replica connection, whose changes
+///    are shipped by libsql's own replication protocol
+///
+/// # Arguments
+/// - `_conn_id` - Connection identifier (ignored)
+/// - `_statements` - SQL statements to record (ignored, never run)
+///
+/// # Returns
+/// - `{:error, :session_not_available}` - Always, as the session extension is unreachable
+#[rustler::nif]
+pub fn changeset_capture(
+    env: Env,
+    _conn_id: &str,
+    _statements: Vec<String>,
+) -> NifResult<(Atom, Atom)> {
+    Ok((
+        Atom::from_str(env, "error")?,
+        Atom::from_str(env, "session_not_available")?,
+    ))
+}
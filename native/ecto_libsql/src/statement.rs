@@ -9,13 +9,52 @@
 /// Prepared statements are cached in a registry and identified by statement IDs.
 /// Each statement is associated with a connection ID to prevent cross-connection misuse.
 use crate::{
-    constants::{CONNECTION_REGISTRY, STMT_REGISTRY, TOKIO_RUNTIME},
-    decode, utils,
+    constants::{
+        database_key, expected_key, name_key, origin_column_key, supplied_key, table_key,
+        CONNECTION_REGISTRY, MAX_ERROR_OFFSET_PROBES, STMT_REGISTRY, TOKIO_RUNTIME,
+    },
+    decode,
+    models::{ColumnNaming, Mode, StatementSource},
+    utils,
 };
 use libsql::Value;
-use rustler::{Atom, Env, NifResult, Term};
+use rustler::types::atom::{error, ok};
+use rustler::{Atom, Encoder, Env, NifResult, Term};
 use std::sync::{Arc, Mutex};
 
+/// Read the schema cookie, which SQLite bumps on every schema change.
+pub async fn schema_version(conn: &libsql::Connection) -> Result<i64, libsql::Error> {
+    let mut rows = conn.query("PRAGMA schema_version", ()).await?;
+    match rows.next().await? {
+        Some(row) => row.get(0),
+        None => Ok(0),
+    }
+}
+
+/// Prepare `sql`, recording the schema version it was prepared against.
+///
+/// The version is read before preparing, so a schema change that races the prepare
+/// makes the statement look stale rather than current. Remote statements can't go
+/// stale, so the version isn't read for them, saving a round trip per prepare.
+pub async fn prepare_with_source(
+    conn: &libsql::Connection,
+    mode: Mode,
+    sql: &str,
+) -> Result<(libsql::Statement, StatementSource), libsql::Error> {
+    let schema_version = match mode {
+        Mode::Remote => None,
+        Mode::Local | Mode::RemoteReplica => Some(schema_version(conn).await?),
+    };
+    let stmt = conn.prepare(sql).await?;
+    Ok((
+        stmt,
+        StatementSource {
+            sql: sql.to_string(),
+            schema_version,
+        },
+    ))
+}
+
 /// Prepare a SQL statement for reuse.
 ///
 /// Statements are cached internally and identified by a unique statement ID.
@@ -42,9 +81,9 @@ pub fn prepare_statement(conn_id: &str, sql: &str) -> NifResult<String> {
     let sql_to_prepare = sql.to_string();
 
     // Clone the inner connection Arc and drop the outer lock before async operations
-    let connection = {
+    let (connection, mode) = {
         let client_guard = utils::safe_lock_arc(&client, "prepare_statement client")?;
-        client_guard.client.clone()
+        (client_guard.client.clone(), client_guard.mode)
     }; // Outer lock dropped here
 
     // SAFETY: We use TOKIO_RUNTIME.block_on(), which runs the future synchronously on a dedicated
@@ -54,18 +93,20 @@ pub fn prepare_statement(conn_id: &str, sql: &str) -> NifResult<String> {
     let stmt_result = TOKIO_RUNTIME.block_on(async {
         let conn_guard = utils::safe_lock_arc(&connection, "prepare_statement conn")?;
 
-        conn_guard
-            .prepare(&sql_to_prepare)
+        prepare_with_source(&conn_guard, mode, &sql_to_prepare)
             .await
-            .map_err(|e| rustler::Error::Term(Box::new(format!("Prepare failed: {e}"))))
+            .map_err(|e| {
+                utils::record_last_error(conn_id, &e);
+                rustler::Error::Term(Box::new(format!("Prepare failed: {e}")))
+            })
     });
 
     match stmt_result {
-        Ok(stmt) => {
+        Ok((stmt, source)) => {
             let stmt_id = uuid::Uuid::new_v4().to_string();
             utils::safe_lock(&STMT_REGISTRY, "prepare_statement stmt_registry")?.insert(
                 stmt_id.clone(),
-                (conn_id.to_string(), Arc::new(Mutex::new(stmt))),
+                (conn_id.to_string(), Arc::new(Mutex::new(stmt)), source),
             );
             Ok(stmt_id)
         }
@@ -73,6 +114,167 @@ pub fn prepare_statement(conn_id: &str, sql: &str) -> NifResult<String> {
     }
 }
 
+/// Byte offsets of `needle` in `sql` that are not part of a longer identifier.
+///
+/// Matching ignores ASCII case when `ignore_case` is set, as SQLite does for
+/// keywords and identifiers.
+fn token_offsets(sql: &str, needle: &str, ignore_case: bool) -> Vec<usize> {
+    if needle.is_empty() {
+        return Vec::new();
+    }
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_' || c == '$';
+    // Only a needle that starts or ends like an identifier can run into a longer one
+    let starts_ident = needle.chars().next().is_some_and(is_ident);
+    let ends_ident = needle.chars().next_back().is_some_and(is_ident);
+
+    (0..=sql.len().saturating_sub(needle.len()))
+        .filter(|&start| sql.is_char_boundary(start) && sql.is_char_boundary(start + needle.len()))
+        .filter(|&start| {
+            let candidate = &sql[start..start + needle.len()];
+            if ignore_case {
+                candidate.eq_ignore_ascii_case(needle)
+            } else {
+                candidate == needle
+            }
+        })
+        .filter(|&start| {
+            let joins_before =
+                starts_ident && sql[..start].chars().next_back().is_some_and(is_ident);
+            let joins_after = ends_ident
+                && sql[start + needle.len()..]
+                    .chars()
+                    .next()
+                    .is_some_and(is_ident);
+            !joins_before && !joins_after
+        })
+        .collect()
+}
+
+/// Recover the byte offset in `sql` of the error SQLite reported when preparing it.
+///
+/// libsql does not expose `sqlite3_error_offset`, so the offset is worked out from
+/// the error message. For `near "X": syntax error`, the failing occurrence of `X` is
+/// the first one where preparing the SQL up to and including it already fails with a
+/// syntax error. Only the first `MAX_ERROR_OFFSET_PROBES` occurrences are tried, and
+/// none on remote connections, where every probe would be a round trip to the server.
+/// Unknown tables, columns and functions and unrecognised tokens point at the first
+/// occurrence of the name. `None` when the error has no position, such as
+/// `incomplete input`.
+pub async fn prepare_error_offset(
+    conn: &libsql::Connection,
+    mode: Mode,
+    sql: &str,
+    message: &str,
+) -> Option<usize> {
+    if let Some(token) = message
+        .split_once("near \"")
+        .and_then(|(_, rest)| rest.split_once("\": syntax error"))
+        .map(|(token, _)| token)
+    {
+        if mode == Mode::Remote {
+            return None;
+        }
+        for start in token_offsets(sql, token, false)
+            .into_iter()
+            .take(MAX_ERROR_OFFSET_PROBES)
+        {
+            let prefix = &sql[..start + token.len()];
+            if let Err(e) = conn.prepare(prefix).await {
+                if utils::last_error_from(&e).message.contains("syntax error") {
+                    return Some(start);
+                }
+            }
+        }
+        return None;
+    }
+
+    let name = [
+        "no such table: ",
+        "no such column: ",
+        "no such function: ",
+        "unrecognized token: ",
+    ]
+    .iter()
+    .find_map(|prefix| message.split_once(prefix).map(|(_, name)| name.trim()))?;
+    let name = name
+        .strip_prefix('"')
+        .and_then(|name| name.strip_suffix('"'))
+        .unwrap_or(name);
+
+    // Qualified names may be written with different quoting, so fall back to the last part
+    token_offsets(sql, name, true).first().copied().or_else(|| {
+        let (_, last) = name.rsplit_once('.')?;
+        token_offsets(sql, last, true).first().copied()
+    })
+}
+
+/// Prepare a SQL statement, reporting where in the SQL a failure occurred.
+///
+/// Behaves like `prepare_statement` on success. On failure, the error carries the
+/// SQLite result code and the byte offset of the error in `sql`, so an editor can
+/// highlight the exact spot. See `prepare_error_offset` for how the offset is found.
+///
+/// # Arguments
+/// - `conn_id`: Database connection ID
+/// - `sql`: SQL query string to prepare
+///
+/// # Returns
+/// - A statement ID on success
+/// - `{:error, {message, offset, code}}` - `offset` and `code` are `nil` when unknown
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn prepare_statement_diagnostics(conn_id: &str, sql: &str) -> NifResult<String> {
+    let client = {
+        let conn_map = utils::safe_lock(
+            &CONNECTION_REGISTRY,
+            "prepare_statement_diagnostics conn_map",
+        )?;
+        conn_map
+            .get(conn_id)
+            .cloned()
+            .ok_or_else(|| rustler::Error::Term(Box::new("Invalid connection ID")))?
+    };
+
+    // Clone the inner connection Arc and drop the outer lock before async operations
+    let (connection, mode) = {
+        let client_guard = utils::safe_lock_arc(&client, "prepare_statement_diagnostics client")?;
+        (client_guard.client.clone(), client_guard.mode)
+    }; // Outer lock dropped here
+
+    // SAFETY: We use TOKIO_RUNTIME.block_on(), which runs the future synchronously on a dedicated
+    // thread pool. This prevents deadlocks that could occur if we were in a true async context
+    // with std::sync::Mutex guards held across await points.
+    #[allow(clippy::await_holding_lock)]
+    let (stmt, source) = TOKIO_RUNTIME.block_on(async {
+        let conn_guard = utils::safe_lock_arc(&connection, "prepare_statement_diagnostics conn")?;
+
+        match prepare_with_source(&conn_guard, mode, sql).await {
+            Ok(prepared) => Ok(prepared),
+            Err(e) => {
+                utils::record_last_error(conn_id, &e);
+                let last_error = utils::last_error_from(&e);
+                let offset =
+                    prepare_error_offset(&conn_guard, mode, sql, &last_error.message).await;
+                Err(rustler::Error::Term(Box::new((
+                    last_error.message,
+                    offset,
+                    last_error.code,
+                ))))
+            }
+        }
+    })?;
+
+    let stmt_id = uuid::Uuid::new_v4().to_string();
+    utils::safe_lock(
+        &STMT_REGISTRY,
+        "prepare_statement_diagnostics stmt_registry",
+    )?
+    .insert(
+        stmt_id.clone(),
+        (conn_id.to_string(), Arc::new(Mutex::new(stmt)), source),
+    );
+    Ok(stmt_id)
+}
+
 /// Execute a prepared SELECT query or RETURNING clause.
 ///
 /// Use this for SELECT statements or INSERT/UPDATE/DELETE with RETURNING clause.
@@ -85,6 +287,8 @@ pub fn prepare_statement(conn_id: &str, sql: &str) -> NifResult<String> {
 /// - `_mode`: Connection mode (unused, for API compatibility)
 /// - `_syncx`: Sync mode (unused, for API compatibility)
 /// - `args`: Query parameters
+/// - `debug_params`: Also report how many parameters the statement expected and how many
+///   values were supplied, returning `{result, %{expected: n, supplied: m}}`
 #[rustler::nif(schedule = "DirtyIo")]
 pub fn query_prepared<'a>(
     env: Env<'a>,
@@ -93,15 +297,17 @@ pub fn query_prepared<'a>(
     _mode: Atom,
     _syncx: Atom,
     args: Vec<Term<'a>>,
+    debug_params: bool,
 ) -> NifResult<Term<'a>> {
     let conn_map = utils::safe_lock(&CONNECTION_REGISTRY, "query_prepared conn_map")?;
     let stmt_registry = utils::safe_lock(&STMT_REGISTRY, "query_prepared stmt_registry")?;
 
-    if conn_map.get(conn_id).is_none() {
-        return Err(rustler::Error::Term(Box::new("Invalid connection ID")));
-    }
+    let client = conn_map
+        .get(conn_id)
+        .cloned()
+        .ok_or_else(|| rustler::Error::Term(Box::new("Invalid connection ID")))?;
 
-    let (stored_conn_id, cached_stmt) = stmt_registry
+    let (stored_conn_id, cached_stmt, _) = stmt_registry
         .get(stmt_id)
         .ok_or_else(|| rustler::Error::Term(Box::new("Statement not found")))?;
 
@@ -119,6 +325,12 @@ pub fn query_prepared<'a>(
     drop(stmt_registry); // Release lock before async operation
     drop(conn_map); // Release lock before async operation
 
+    let column_naming = {
+        let client_guard = utils::safe_lock_arc(&client, "query_prepared client")?;
+        utils::count_statements(conn_id, 1);
+        client_guard.column_naming
+    };
+
     // SAFETY: We use TOKIO_RUNTIME.block_on(), which runs the future synchronously on a dedicated
     // thread pool. This prevents deadlocks that could occur if we were in a true async context
     // with std::sync::Mutex guards held across await points.
@@ -130,17 +342,26 @@ pub fn query_prepared<'a>(
         // Reset clears any previous bindings
         stmt_guard.reset();
 
+        let counts = debug_params.then(|| (stmt_guard.parameter_count(), decoded_args.len()));
         let res = stmt_guard.query(decoded_args).await;
 
         match res {
             Ok(rows) => {
-                let collected = utils::collect_rows(env, rows)
+                let tables = if column_naming == ColumnNaming::TablePrefix {
+                    utils::column_origin_tables(&stmt_guard)
+                } else {
+                    Vec::new()
+                };
+                let collected = utils::collect_rows_named(env, rows, column_naming, &tables, None)
                     .await
                     .map_err(|e| rustler::Error::Term(Box::new(format!("{e:?}"))))?;
 
-                Ok(collected)
+                with_param_counts(env, collected, counts)
+            }
+            Err(e) => {
+                utils::record_last_error(conn_id, &e);
+                Err(rustler::Error::Term(Box::new(e.to_string())))
             }
-            Err(e) => Err(rustler::Error::Term(Box::new(e.to_string()))),
         }
     });
 
@@ -155,15 +376,17 @@ pub fn query_prepared<'a>(
 /// Returns the number of affected rows.
 ///
 /// # Arguments
-/// - `env`: Elixir environment (unused in this function, kept for API consistency)
+/// - `env`: Elixir environment
 /// - `conn_id`: Database connection ID
 /// - `stmt_id`: Prepared statement ID
 /// - `mode`: Connection mode (unused, for API compatibility)
 /// - `syncx`: Sync mode (unused, for API compatibility)
 /// - `sql_hint`: Original SQL for detecting if we need sync
 /// - `args`: Query parameters
+/// - `debug_params`: Also report how many parameters the statement expected and how many
+///   values were supplied, returning `{affected, %{expected: n, supplied: m}}`
 #[rustler::nif(schedule = "DirtyIo")]
-#[allow(unused_variables)]
+#[allow(unused_variables, clippy::too_many_arguments)]
 pub fn execute_prepared<'a>(
     env: Env<'a>,
     conn_id: &str,
@@ -172,15 +395,17 @@ pub fn execute_prepared<'a>(
     syncx: Atom,
     sql_hint: &str, // For detecting if we need sync
     args: Vec<Term<'a>>,
-) -> NifResult<u64> {
+    debug_params: bool,
+) -> NifResult<Term<'a>> {
     let conn_map = utils::safe_lock(&CONNECTION_REGISTRY, "execute_prepared conn_map")?;
     let stmt_registry = utils::safe_lock(&STMT_REGISTRY, "execute_prepared stmt_registry")?;
 
-    if conn_map.get(conn_id).is_none() {
-        return Err(rustler::Error::Term(Box::new("Invalid connection ID")));
-    }
+    let client = conn_map
+        .get(conn_id)
+        .cloned()
+        .ok_or_else(|| rustler::Error::Term(Box::new("Invalid connection ID")))?;
 
-    let (stored_conn_id, cached_stmt) = stmt_registry
+    let (stored_conn_id, cached_stmt, _) = stmt_registry
         .get(stmt_id)
         .ok_or_else(|| rustler::Error::Term(Box::new("Statement not found")))?;
 
@@ -198,6 +423,8 @@ pub fn execute_prepared<'a>(
     drop(stmt_registry); // Release lock before async operation
     drop(conn_map); // Release lock before async operation
 
+    utils::count_statements(conn_id, 1);
+
     // SAFETY: We use TOKIO_RUNTIME.block_on(), which runs the future synchronously on a dedicated
     // thread pool. This prevents deadlocks that could occur if we were in a true async context
     // with std::sync::Mutex guards held across await points.
@@ -209,20 +436,43 @@ pub fn execute_prepared<'a>(
         // Reset clears any previous bindings
         stmt_guard.reset();
 
-        let affected = stmt_guard
-            .execute(decoded_args)
-            .await
-            .map_err(|e| rustler::Error::Term(Box::new(format!("Execute failed: {e}"))))?;
+        let counts = debug_params.then(|| (stmt_guard.parameter_count(), decoded_args.len()));
+        let affected = stmt_guard.execute(decoded_args).await.map_err(|e| {
+            utils::record_last_error(conn_id, &e);
+            rustler::Error::Term(Box::new(format!("Execute failed: {e}")))
+        })?;
 
         // NOTE: LibSQL automatically syncs writes to remote for embedded replicas.
         // No manual sync needed here.
 
-        Ok(affected as u64)
+        with_param_counts(env, (affected as u64).encode(env), counts)
     });
 
     result
 }
 
+/// Pair `result` with the statement's `{expected, supplied}` parameter counts, if taken.
+///
+/// SQLite binds `NULL` to parameters no value was supplied for, so a statement given too
+/// few values still runs; reporting the counts makes the mismatch visible.
+fn with_param_counts<'a>(
+    env: Env<'a>,
+    result: Term<'a>,
+    counts: Option<(usize, usize)>,
+) -> NifResult<Term<'a>> {
+    let Some((expected, supplied)) = counts else {
+        return Ok(result);
+    };
+    let counts = Term::map_from_pairs(
+        env,
+        &[
+            (expected_key().encode(env), expected.encode(env)),
+            (supplied_key().encode(env), supplied.encode(env)),
+        ],
+    )?;
+    Ok((result, counts).encode(env))
+}
+
 /// Get the number of columns in a prepared statement's result set.
 ///
 /// This is useful for understanding the structure of a SELECT query
@@ -240,7 +490,7 @@ pub fn statement_column_count(conn_id: &str, stmt_id: &str) -> NifResult<usize>
         return Err(rustler::Error::Term(Box::new("Invalid connection ID")));
     }
 
-    let (stored_conn_id, cached_stmt) = stmt_registry
+    let (stored_conn_id, cached_stmt, _) = stmt_registry
         .get(stmt_id)
         .ok_or_else(|| rustler::Error::Term(Box::new("Statement not found")))?;
 
@@ -276,7 +526,7 @@ pub fn statement_column_name(conn_id: &str, stmt_id: &str, idx: usize) -> NifRes
         return Err(rustler::Error::Term(Box::new("Invalid connection ID")));
     }
 
-    let (stored_conn_id, cached_stmt) = stmt_registry
+    let (stored_conn_id, cached_stmt, _) = stmt_registry
         .get(stmt_id)
         .ok_or_else(|| rustler::Error::Term(Box::new("Statement not found")))?;
 
@@ -322,7 +572,7 @@ pub fn statement_parameter_count(conn_id: &str, stmt_id: &str) -> NifResult<usiz
         return Err(rustler::Error::Term(Box::new("Invalid connection ID")));
     }
 
-    let (stored_conn_id, cached_stmt) = stmt_registry
+    let (stored_conn_id, cached_stmt, _) = stmt_registry
         .get(stmt_id)
         .ok_or_else(|| rustler::Error::Term(Box::new("Statement not found")))?;
 
@@ -373,7 +623,7 @@ pub fn statement_parameter_name(
         return Err(rustler::Error::Term(Box::new("Invalid connection ID")));
     }
 
-    let (stored_conn_id, cached_stmt) = stmt_registry
+    let (stored_conn_id, cached_stmt, _) = stmt_registry
         .get(stmt_id)
         .ok_or_else(|| rustler::Error::Term(Box::new("Statement not found")))?;
 
@@ -430,7 +680,7 @@ pub fn reset_statement(conn_id: &str, stmt_id: &str) -> NifResult<Atom> {
         return Err(rustler::Error::Term(Box::new("Invalid connection ID")));
     }
 
-    let (stored_conn_id, cached_stmt) = stmt_registry
+    let (stored_conn_id, cached_stmt, _) = stmt_registry
         .get(stmt_id)
         .ok_or_else(|| rustler::Error::Term(Box::new("Statement not found")))?;
 
@@ -483,7 +733,7 @@ pub fn get_statement_columns(
         return Err(rustler::Error::Term(Box::new("Invalid connection ID")));
     }
 
-    let (stored_conn_id, cached_stmt) = stmt_registry
+    let (stored_conn_id, cached_stmt, _) = stmt_registry
         .get(stmt_id)
         .ok_or_else(|| rustler::Error::Term(Box::new("Statement not found")))?;
 
@@ -496,10 +746,18 @@ pub fn get_statement_columns(
     drop(conn_map);
 
     let stmt_guard = utils::safe_lock_arc(&cached_stmt, "get_statement_columns stmt")?;
-    let columns = stmt_guard.columns();
 
-    // Build list of column metadata tuples: (name, origin_name, decl_type)
-    let column_info: Vec<(String, String, Option<String>)> = columns
+    Ok(column_info(&stmt_guard))
+}
+
+/// Column metadata tuple: `(name, origin_name, decl_type)`.
+type ColumnInfo = (String, String, Option<String>);
+
+/// Build column metadata for a prepared statement.
+///
+/// `origin_name` falls back to `name` for expressions that don't map to a table column.
+fn column_info(stmt: &libsql::Statement) -> Vec<ColumnInfo> {
+    stmt.columns()
         .iter()
         .map(|col| {
             let name = col.name().to_string();
@@ -509,7 +767,398 @@ pub fn get_statement_columns(
             let decl_type = col.decl_type().map(ToString::to_string);
             (name, origin_name, decl_type)
         })
+        .collect()
+}
+
+/// Where a result column comes from: `(name, database, table, origin_column)`.
+type ColumnProvenance = (String, Option<String>, Option<String>, Option<String>);
+
+/// Report each result column's name alongside the database, table and column it is
+/// read from.
+///
+/// The source is only known for plain column references, and is reported under the
+/// real table and column names, whatever aliases the query uses. Expressions,
+/// aggregates and literals have `None` for all three.
+pub fn column_provenance_of(stmt: &libsql::Statement) -> Vec<ColumnProvenance> {
+    stmt.columns()
+        .iter()
+        .map(|col| {
+            (
+                col.name().to_string(),
+                col.database_name().map(ToString::to_string),
+                col.table_name().map(ToString::to_string),
+                col.origin_name().map(ToString::to_string),
+            )
+        })
+        .collect()
+}
+
+/// Get the source database, table and column of each result column in a prepared
+/// statement.
+///
+/// Lets tooling map aliased result columns back to the schema. Unlike
+/// `get_statement_columns`, expression columns are reported with `nil` sources rather
+/// than falling back to the result column name. See `column_provenance_of`.
+///
+/// # Arguments
+/// - `env`: Elixir environment
+/// - `conn_id`: Database connection ID
+/// - `stmt_id`: Prepared statement ID
+///
+/// # Returns
+/// - List of `%{name, database, table, origin_column}` maps, one per result column
+/// - `{:error, reason}` - Unknown connection or statement
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn column_provenance<'a>(
+    env: Env<'a>,
+    conn_id: &str,
+    stmt_id: &str,
+) -> NifResult<Vec<Term<'a>>> {
+    let conn_map = utils::safe_lock(&CONNECTION_REGISTRY, "column_provenance conn_map")?;
+    let stmt_registry = utils::safe_lock(&STMT_REGISTRY, "column_provenance stmt_registry")?;
+
+    if conn_map.get(conn_id).is_none() {
+        return Err(rustler::Error::Term(Box::new("Invalid connection ID")));
+    }
+
+    let (stored_conn_id, cached_stmt, _) = stmt_registry
+        .get(stmt_id)
+        .ok_or_else(|| rustler::Error::Term(Box::new("Statement not found")))?;
+
+    // Verify statement belongs to this connection
+    decode::verify_statement_ownership(stored_conn_id, conn_id)?;
+
+    let cached_stmt = cached_stmt.clone();
+
+    drop(stmt_registry);
+    drop(conn_map);
+
+    let stmt_guard = utils::safe_lock_arc(&cached_stmt, "column_provenance stmt")?;
+
+    column_provenance_of(&stmt_guard)
+        .into_iter()
+        .map(|(name, database, table, origin_column)| {
+            Term::map_from_pairs(
+                env,
+                &[
+                    (name_key().encode(env), name.encode(env)),
+                    (database_key().encode(env), database.encode(env)),
+                    (table_key().encode(env), table.encode(env)),
+                    (origin_column_key().encode(env), origin_column.encode(env)),
+                ],
+            )
+        })
+        .collect()
+}
+
+/// Prepare several SQL statements in one call and return their metadata.
+///
+/// Saves a round trip per statement for tools that prepare a set of statements and
+/// immediately inspect them. Each statement is stored with ownership of `conn_id`,
+/// exactly as `prepare_statement` would store it.
+///
+/// # Arguments
+/// - `conn_id`: Database connection ID
+/// - `sqls`: SQL strings to prepare, in order
+/// - `continue_on_error`: Whether to keep preparing after a statement fails
+///
+/// # Returns
+/// One entry per input statement, in order:
+/// - `{:ok, stmt_id, columns, param_count}` - `columns` as in `get_statement_columns`
+/// - `{:error, index, sql, reason}` - Only when `continue_on_error` is `true`
+///
+/// When `continue_on_error` is `false`, the first failure returns
+/// `{:error, {index, sql, reason}}` and releases any statements already prepared
+/// by this call.
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn prepare_many<'a>(
+    env: Env<'a>,
+    conn_id: &str,
+    sqls: Vec<String>,
+    continue_on_error: bool,
+) -> NifResult<Vec<Term<'a>>> {
+    let client = {
+        let conn_map = utils::safe_lock(&CONNECTION_REGISTRY, "prepare_many conn_map")?;
+        conn_map
+            .get(conn_id)
+            .cloned()
+            .ok_or_else(|| rustler::Error::Term(Box::new("Invalid connection ID")))?
+    };
+
+    // Clone the inner connection Arc and drop the outer lock before async operations
+    let (connection, mode) = {
+        let client_guard = utils::safe_lock_arc(&client, "prepare_many client")?;
+        (client_guard.client.clone(), client_guard.mode)
+    }; // Outer lock dropped here
+
+    // SAFETY: We use TOKIO_RUNTIME.block_on(), which runs the future synchronously on a dedicated
+    // thread pool. This prevents deadlocks that could occur if we were in a true async context
+    // with std::sync::Mutex guards held across await points.
+    #[allow(clippy::await_holding_lock)]
+    let prepared = TOKIO_RUNTIME.block_on(async {
+        let conn_guard = utils::safe_lock_arc(&connection, "prepare_many conn")?;
+
+        let mut prepared = Vec::with_capacity(sqls.len());
+        for sql in &sqls {
+            let result = prepare_with_source(&conn_guard, mode, sql)
+                .await
+                .map_err(|e| {
+                    utils::record_last_error(conn_id, &e);
+                    format!("Prepare failed: {e}")
+                });
+            let failed = result.is_err();
+            prepared.push(result);
+            if failed && !continue_on_error {
+                break;
+            }
+        }
+        Ok::<_, rustler::Error>(prepared)
+    })?;
+
+    if !continue_on_error {
+        if let Some((index, Err(reason))) = prepared.iter().enumerate().find(|(_, r)| r.is_err()) {
+            // Earlier statements were never registered, so dropping them releases them
+            return Err(rustler::Error::Term(Box::new((
+                index,
+                sqls[index].clone(),
+                reason.clone(),
+            ))));
+        }
+    }
+
+    let mut stmt_registry = utils::safe_lock(&STMT_REGISTRY, "prepare_many stmt_registry")?;
+    let entries = prepared
+        .into_iter()
+        .enumerate()
+        .map(|(index, result)| match result {
+            Ok((stmt, source)) => {
+                let columns = column_info(&stmt);
+                let param_count = stmt.parameter_count();
+                let stmt_id = uuid::Uuid::new_v4().to_string();
+                stmt_registry.insert(
+                    stmt_id.clone(),
+                    (conn_id.to_string(), Arc::new(Mutex::new(stmt)), source),
+                );
+                (ok(), stmt_id, columns, param_count).encode(env)
+            }
+            Err(reason) => (error(), index, sqls[index].as_str(), reason).encode(env),
+        })
         .collect();
 
-    Ok(column_info)
+    Ok(entries)
+}
+
+/// Whether a prepare error is a name-resolution failure rather than a syntax error.
+///
+/// Validation prepares statements without running them, so a script that creates a
+/// table and then uses it fails to resolve the table in later statements. These
+/// errors are tolerated; only errors SQLite raises regardless of schema are reported.
+fn is_unresolved_reference(message: &str) -> bool {
+    [
+        "no such table",
+        "no such column",
+        "no such index",
+        "no such view",
+        "no such trigger",
+    ]
+    .iter()
+    .any(|pattern| message.contains(pattern))
+        || message.contains("already exists")
+}
+
+/// Prepare each statement of `sql` without executing it and return the first failure.
+///
+/// Returns `(index, statement, reason)` for the first statement that fails to prepare,
+/// or `None` if the whole script is valid. Prepared statements are dropped immediately,
+/// so nothing is left registered or holding locks.
+pub async fn first_invalid_statement(
+    conn: &libsql::Connection,
+    sql: &str,
+) -> Option<(usize, String, String)> {
+    for (index, stmt) in utils::split_statements(sql).into_iter().enumerate() {
+        if let Err(e) = conn.prepare(stmt).await {
+            let reason = e.to_string();
+            if !is_unresolved_reference(&reason) {
+                return Some((index, stmt.to_string(), reason));
+            }
+        }
+    }
+    None
+}
+
+/// Check that every statement in an SQL script parses, without executing any of them.
+///
+/// The script is split on statement boundaries (respecting string literals, quoted
+/// identifiers, comments and trigger bodies) and each statement is prepared and
+/// released in turn. References to tables, columns and other objects that don't
+/// exist yet are not reported, since earlier statements in the script may create them.
+///
+/// # Arguments
+/// - `conn_id`: Database connection ID
+/// - `sql`: SQL script to check
+///
+/// # Returns
+/// - `:ok` - Every statement parsed
+/// - `{:error, {index, sql, reason}}` - The first statement that failed, with its
+///   zero-based position in the script
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn validate_statements(conn_id: &str, sql: &str) -> NifResult<Atom> {
+    let client = {
+        let conn_map = utils::safe_lock(&CONNECTION_REGISTRY, "validate_statements conn_map")?;
+        conn_map
+            .get(conn_id)
+            .cloned()
+            .ok_or_else(|| rustler::Error::Term(Box::new("Invalid connection ID")))?
+    };
+
+    // Clone the inner connection Arc and drop the outer lock before async operations
+    let connection = {
+        let client_guard = utils::safe_lock_arc(&client, "validate_statements client")?;
+        client_guard.client.clone()
+    }; // Outer lock dropped here
+
+    // SAFETY: We use TOKIO_RUNTIME.block_on(), which runs the future synchronously on a dedicated
+    // thread pool. This prevents deadlocks that could occur if we were in a true async context
+    // with std::sync::Mutex guards held across await points.
+    #[allow(clippy::await_holding_lock)]
+    TOKIO_RUNTIME.block_on(async {
+        let conn_guard = utils::safe_lock_arc(&connection, "validate_statements conn")?;
+
+        match first_invalid_statement(&conn_guard, sql).await {
+            None => Ok(ok()),
+            Some(failure) => Err(rustler::Error::Term(Box::new(failure))),
+        }
+    })
+}
+
+/// Compute the shape digest of an SQL statement, for grouping telemetry by query shape.
+///
+/// Literal values are stripped before hashing, so statements differing only in the
+/// values they use share a digest and the digest never reveals those values. Needs no
+/// connection.
+///
+/// # Arguments
+/// - `sql`: SQL statement to digest
+///
+/// # Returns
+/// - `{digest, normalised_sql}` - 16 hex digit digest and the normalised text it hashes
+#[rustler::nif]
+pub fn statement_digest(sql: &str) -> (String, String) {
+    (utils::sql_digest(sql), utils::normalise_sql(sql))
+}
+
+/// Look up a registered statement owned by `conn_id`, returning the connection and its
+/// mode, the statement, and the SQL and schema version it was prepared with.
+#[allow(clippy::type_complexity)]
+fn owned_statement(
+    conn_id: &str,
+    stmt_id: &str,
+    context: &str,
+) -> NifResult<(
+    Arc<Mutex<libsql::Connection>>,
+    Mode,
+    Arc<Mutex<libsql::Statement>>,
+    StatementSource,
+)> {
+    let client = utils::safe_lock(&CONNECTION_REGISTRY, context)?
+        .get(conn_id)
+        .cloned()
+        .ok_or_else(|| rustler::Error::Term(Box::new("Invalid connection ID")))?;
+
+    let (cached_stmt, source) = {
+        let stmt_registry = utils::safe_lock(&STMT_REGISTRY, context)?;
+        let (stored_conn_id, cached_stmt, source) = stmt_registry
+            .get(stmt_id)
+            .ok_or_else(|| rustler::Error::Term(Box::new("Statement not found")))?;
+
+        // Verify statement belongs to this connection
+        decode::verify_statement_ownership(stored_conn_id, conn_id)?;
+
+        (cached_stmt.clone(), source.clone())
+    }; // Lock dropped here
+
+    let (connection, mode) = {
+        let client_guard = utils::safe_lock_arc(&client, context)?;
+        (client_guard.client.clone(), client_guard.mode)
+    };
+
+    Ok((connection, mode, cached_stmt, source))
+}
+
+/// Check whether a prepared statement was prepared against the current schema.
+///
+/// Each statement records `PRAGMA schema_version` when it is prepared. Any DDL (from
+/// this or another connection) bumps the version, after which the statement is
+/// reported as stale: its cached column metadata may be wrong, and running it may
+/// fail with "schema has changed". Use `reprepare_statement` to refresh it. Remote
+/// statements are prepared by the server on every run, so they are always current.
+///
+/// # Arguments
+/// - `conn_id`: Database connection ID
+/// - `stmt_id`: Prepared statement ID
+///
+/// # Returns
+/// - `true` if the schema is unchanged since the statement was prepared, `false` otherwise
+/// - `{:error, reason}` - Unknown statement or connection, or wrong owner
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn statement_valid(conn_id: &str, stmt_id: &str) -> NifResult<bool> {
+    let (connection, _, _, source) = owned_statement(conn_id, stmt_id, "statement_valid")?;
+    let Some(prepared_version) = source.schema_version else {
+        return Ok(true);
+    };
+
+    // SAFETY: We use TOKIO_RUNTIME.block_on(), which runs the future synchronously on a dedicated
+    // thread pool. This prevents deadlocks that could occur if we were in a true async context
+    // with std::sync::Mutex guards held across await points.
+    #[allow(clippy::await_holding_lock)]
+    TOKIO_RUNTIME.block_on(async {
+        let conn_guard = utils::safe_lock_arc(&connection, "statement_valid conn")?;
+        let current = schema_version(&conn_guard)
+            .await
+            .map_err(|e| rustler::Error::Term(Box::new(e.to_string())))?;
+        Ok(current == prepared_version)
+    })
+}
+
+/// Re-prepare a statement from its original SQL against the current schema.
+///
+/// The statement keeps its ID, so callers holding it carry on unchanged. Fails without
+/// touching the existing statement if the SQL no longer prepares (for example, a
+/// column it references was dropped).
+///
+/// # Arguments
+/// - `conn_id`: Database connection ID
+/// - `stmt_id`: Prepared statement ID
+///
+/// # Returns
+/// - `:ok` - The statement was re-prepared
+/// - `{:error, reason}` - Unknown statement, wrong owner, or the SQL failed to prepare
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn reprepare_statement(conn_id: &str, stmt_id: &str) -> NifResult<Atom> {
+    let (connection, mode, cached_stmt, source) =
+        owned_statement(conn_id, stmt_id, "reprepare_statement")?;
+
+    // SAFETY: We use TOKIO_RUNTIME.block_on(), which runs the future synchronously on a dedicated
+    // thread pool. This prevents deadlocks that could occur if we were in a true async context
+    // with std::sync::Mutex guards held across await points.
+    #[allow(clippy::await_holding_lock)]
+    let (stmt, source) = TOKIO_RUNTIME.block_on(async {
+        let conn_guard = utils::safe_lock_arc(&connection, "reprepare_statement conn")?;
+        prepare_with_source(&conn_guard, mode, &source.sql)
+            .await
+            .map_err(|e| {
+                utils::record_last_error(conn_id, &e);
+                rustler::Error::Term(Box::new(format!("Prepare failed: {e}")))
+            })
+    })?;
+
+    // Swap the statement in place so clones of the Arc see the new one too
+    *utils::safe_lock_arc(&cached_stmt, "reprepare_statement stmt")? = stmt;
+    if let Some(entry) =
+        utils::safe_lock(&STMT_REGISTRY, "reprepare_statement stmt_registry")?.get_mut(stmt_id)
+    {
+        entry.2 = source;
+    }
+
+    Ok(ok())
 }
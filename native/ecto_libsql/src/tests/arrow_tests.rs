@@ -0,0 +1,278 @@
+//! Tests for Arrow IPC export
+//!
+//! These tests encode query results as an Arrow IPC stream and read the stream back
+//! with a minimal flatbuffer reader, checking the schema and column buffers.
+
+// Allow unwrap() in tests for cleaner test code - see CLAUDE.md "Test Code Exception"
+#![allow(clippy::unwrap_used)]
+
+use super::test_utils::{setup_test_db_with_prefix, TestDbGuard};
+use crate::arrow::{column_types, encode_ipc_stream, query_values, ArrowType};
+use libsql::{Builder, Value};
+
+fn u16_at(buf: &[u8], pos: usize) -> usize {
+    u16::from_le_bytes(buf[pos..pos + 2].try_into().unwrap()) as usize
+}
+
+fn u32_at(buf: &[u8], pos: usize) -> usize {
+    u32::from_le_bytes(buf[pos..pos + 4].try_into().unwrap()) as usize
+}
+
+fn i64_at(buf: &[u8], pos: usize) -> i64 {
+    i64::from_le_bytes(buf[pos..pos + 8].try_into().unwrap())
+}
+
+/// Follow the `uoffset` stored at `pos`.
+fn deref(buf: &[u8], pos: usize) -> usize {
+    pos + u32_at(buf, pos)
+}
+
+/// Position of field `index` of the table at `table`, if present.
+fn field(buf: &[u8], table: usize, index: usize) -> Option<usize> {
+    let soffset = i32::from_le_bytes(buf[table..table + 4].try_into().unwrap());
+    let vtable = (table as i64 - i64::from(soffset)) as usize;
+    if 4 + 2 * index >= u16_at(buf, vtable) {
+        return None;
+    }
+    match u16_at(buf, vtable + 4 + 2 * index) {
+        0 => None,
+        offset => Some(table + offset),
+    }
+}
+
+/// Positions of the tables in the vector referenced by field `index`.
+fn table_vector(buf: &[u8], table: usize, index: usize) -> Vec<usize> {
+    let vector = deref(buf, field(buf, table, index).unwrap());
+    (0..u32_at(buf, vector))
+        .map(|i| deref(buf, vector + 4 + 4 * i))
+        .collect()
+}
+
+/// Pairs of `long`s in the struct vector referenced by field `index`.
+fn struct_vector(buf: &[u8], table: usize, index: usize) -> Vec<(i64, i64)> {
+    let vector = deref(buf, field(buf, table, index).unwrap());
+    (0..u32_at(buf, vector))
+        .map(|i| {
+            let at = vector + 4 + 16 * i;
+            (i64_at(buf, at), i64_at(buf, at + 8))
+        })
+        .collect()
+}
+
+/// Split an IPC stream into `(header_type, metadata, body)` messages.
+fn messages(stream: &[u8]) -> Vec<(u8, Vec<u8>, Vec<u8>)> {
+    let mut pos = 0;
+    let mut out = Vec::new();
+    loop {
+        assert_eq!(u32_at(stream, pos), 0xFFFF_FFFF);
+        let len = u32_at(stream, pos + 4);
+        if len == 0 {
+            assert_eq!(pos + 8, stream.len());
+            return out;
+        }
+        assert_eq!(len % 8, 0);
+        let meta = stream[pos + 8..pos + 8 + len].to_vec();
+        let message = deref(&meta, 0);
+        assert_eq!(
+            i16::from_le_bytes(
+                meta[field(&meta, message, 0).unwrap()..][..2]
+                    .try_into()
+                    .unwrap()
+            ),
+            4
+        );
+        let header_type = meta[field(&meta, message, 1).unwrap()];
+        let body_len = i64_at(&meta, field(&meta, message, 3).unwrap()) as usize;
+        let body_start = pos + 8 + len;
+        out.push((
+            header_type,
+            meta,
+            stream[body_start..body_start + body_len].to_vec(),
+        ));
+        pos = body_start + body_len;
+    }
+}
+
+/// A decoded column value
+#[derive(Debug, PartialEq)]
+enum Cell {
+    Null,
+    Int(i64),
+    Float(f64),
+    Text(String),
+    Bytes(Vec<u8>),
+}
+
+/// Decode a stream into column `(name, type id)` pairs and rows of cells.
+fn read_stream(stream: &[u8]) -> (Vec<(String, u8)>, Vec<Vec<Cell>>) {
+    let messages = messages(stream);
+    assert_eq!(messages.len(), 2);
+
+    let (header_type, meta, _) = &messages[0];
+    assert_eq!(*header_type, 1);
+    let schema = deref(meta, field(meta, deref(meta, 0), 2).unwrap());
+    let fields: Vec<(String, u8)> = table_vector(meta, schema, 1)
+        .into_iter()
+        .map(|f| {
+            let name = deref(meta, field(meta, f, 0).unwrap());
+            let name = String::from_utf8(meta[name + 4..name + 4 + u32_at(meta, name)].to_vec());
+            (name.unwrap(), meta[field(meta, f, 2).unwrap()])
+        })
+        .collect();
+
+    let (header_type, meta, body) = &messages[1];
+    assert_eq!(*header_type, 3);
+    let batch = deref(meta, field(meta, deref(meta, 0), 2).unwrap());
+    let length = i64_at(meta, field(meta, batch, 0).unwrap()) as usize;
+    let nodes = struct_vector(meta, batch, 1);
+    let buffers = struct_vector(meta, batch, 2);
+    let slice = |(offset, len): (i64, i64)| &body[offset as usize..(offset + len) as usize];
+
+    let mut rows: Vec<Vec<Cell>> = (0..length).map(|_| Vec::new()).collect();
+    let mut next_buffer = 0;
+    for ((_, type_id), (node_len, null_count)) in fields.iter().zip(nodes) {
+        assert_eq!(node_len as usize, length);
+        if *type_id == 1 {
+            assert_eq!(null_count as usize, length);
+            rows.iter_mut().for_each(|row| row.push(Cell::Null));
+            continue;
+        }
+        let validity = slice(buffers[next_buffer]);
+        let is_valid = |i: usize| null_count == 0 || validity[i / 8] & (1 << (i % 8)) != 0;
+        match type_id {
+            2 | 3 => {
+                let values = slice(buffers[next_buffer + 1]);
+                next_buffer += 2;
+                for (i, row) in rows.iter_mut().enumerate() {
+                    let raw = values[i * 8..i * 8 + 8].try_into().unwrap();
+                    row.push(match (is_valid(i), type_id) {
+                        (false, _) => Cell::Null,
+                        (true, 2) => Cell::Int(i64::from_le_bytes(raw)),
+                        (true, _) => Cell::Float(f64::from_le_bytes(raw)),
+                    });
+                }
+            }
+            _ => {
+                let offsets = slice(buffers[next_buffer + 1]);
+                let data = slice(buffers[next_buffer + 2]);
+                next_buffer += 3;
+                for (i, row) in rows.iter_mut().enumerate() {
+                    let start = u32_at(offsets, i * 4);
+                    let end = u32_at(offsets, i * 4 + 4);
+                    let bytes = data[start..end].to_vec();
+                    row.push(match (is_valid(i), type_id) {
+                        (false, _) => Cell::Null,
+                        (true, 5) => Cell::Text(String::from_utf8(bytes).unwrap()),
+                        (true, _) => Cell::Bytes(bytes),
+                    });
+                }
+            }
+        }
+    }
+    assert_eq!(next_buffer, buffers.len());
+    for (offset, _) in buffers {
+        assert_eq!(offset % 8, 0);
+    }
+    (fields, rows)
+}
+
+#[test]
+fn test_column_types_from_first_non_null_value() {
+    let columns = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+    let rows = vec![
+        vec![Value::Null, Value::Integer(1), Value::Null],
+        vec![Value::Text("x".into()), Value::Real(2.5), Value::Null],
+    ];
+    assert_eq!(
+        column_types(&columns, &rows).unwrap(),
+        vec![ArrowType::Utf8, ArrowType::Float64, ArrowType::Null]
+    );
+}
+
+#[test]
+fn test_column_types_reject_mixed_storage_classes() {
+    let columns = vec!["a".to_string()];
+    let rows = vec![vec![Value::Integer(1)], vec![Value::Text("x".into())]];
+    let err = column_types(&columns, &rows).unwrap_err();
+    assert!(err.contains("Column a mixes integer and text"), "{err}");
+}
+
+#[test]
+fn test_empty_result_encodes_schema_only_batch() {
+    let stream = encode_ipc_stream(&["id".to_string()], &[]).unwrap();
+    let (fields, rows) = read_stream(&stream);
+    assert_eq!(fields, vec![("id".to_string(), 1)]);
+    assert!(rows.is_empty());
+}
+
+#[tokio::test]
+async fn test_query_results_round_trip() {
+    let db_path = setup_test_db_with_prefix("arrow");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let db = Builder::new_local(db_path.to_str().unwrap())
+        .build()
+        .await
+        .unwrap();
+    let conn = db.connect().unwrap();
+
+    conn.execute(
+        "CREATE TABLE readings (id INTEGER PRIMARY KEY, label TEXT, value REAL, raw BLOB)",
+        (),
+    )
+    .await
+    .unwrap();
+    conn.execute(
+        "INSERT INTO readings (label, value, raw) VALUES
+           ('a', 1.5, x'0102'), (NULL, 2, NULL), ('ccc', NULL, x'')",
+        (),
+    )
+    .await
+    .unwrap();
+
+    let (columns, rows) = query_values(
+        &conn,
+        "SELECT id, label, value, raw, NULL AS missing FROM readings WHERE id > ?1 ORDER BY id",
+        vec![Value::Integer(0)],
+    )
+    .await
+    .unwrap();
+    let stream = encode_ipc_stream(&columns, &rows).unwrap();
+    let (fields, rows) = read_stream(&stream);
+
+    assert_eq!(
+        fields,
+        vec![
+            ("id".to_string(), 2),
+            ("label".to_string(), 5),
+            ("value".to_string(), 3),
+            ("raw".to_string(), 4),
+            ("missing".to_string(), 1),
+        ]
+    );
+    assert_eq!(
+        rows,
+        vec![
+            vec![
+                Cell::Int(1),
+                Cell::Text("a".into()),
+                Cell::Float(1.5),
+                Cell::Bytes(vec![1, 2]),
+                Cell::Null
+            ],
+            vec![
+                Cell::Int(2),
+                Cell::Null,
+                Cell::Float(2.0),
+                Cell::Null,
+                Cell::Null
+            ],
+            vec![
+                Cell::Int(3),
+                Cell::Text("ccc".into()),
+                Cell::Null,
+                Cell::Bytes(vec![]),
+                Cell::Null
+            ],
+        ]
+    );
+}
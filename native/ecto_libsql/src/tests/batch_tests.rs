@@ -0,0 +1,270 @@
+//! Tests for batch helpers
+//!
+//! These tests run counted write scripts through libsql's native batch execution,
+//! and retry conflicting transactions, against a real local database, without going
+//! through the NIF layer.
+
+// Allow unwrap() in tests for cleaner test code - see CLAUDE.md "Test Code Exception"
+#![allow(clippy::unwrap_used)]
+
+use super::test_utils::{setup_test_db_with_prefix, TestDbGuard};
+use crate::batch::{
+    affected_counts, counted_write_script, execute_with_retry, execute_without_triggers,
+    table_triggers, RetryError,
+};
+use libsql::{Builder, Connection, Value};
+use std::time::Duration;
+
+#[tokio::test]
+async fn test_counted_write_script_reports_counts_per_statement() {
+    let db_path = setup_test_db_with_prefix("write_batch");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let db = Builder::new_local(db_path.to_str().unwrap())
+        .build()
+        .await
+        .unwrap();
+    let conn = db.connect().unwrap();
+    conn.execute("CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT)", ())
+        .await
+        .unwrap();
+
+    let statements = vec![
+        (
+            "INSERT INTO items (id, name) VALUES (?, ?), (?, ?)".to_string(),
+            vec![
+                Value::Integer(1),
+                Value::Text("a".to_string()),
+                Value::Integer(2),
+                Value::Text("O'Brien".to_string()),
+            ],
+        ),
+        (
+            "INSERT INTO items (id, name) VALUES (?, ?)".to_string(),
+            vec![Value::Integer(3), Value::Null],
+        ),
+        (
+            "UPDATE items SET name = 'z' WHERE id > ?".to_string(),
+            vec![Value::Integer(0)],
+        ),
+        (
+            "DELETE FROM items WHERE id = ?;".to_string(),
+            vec![Value::Integer(99)],
+        ),
+    ];
+
+    let script = counted_write_script(&statements).unwrap();
+    let batch_rows = conn.execute_batch(&script).await.unwrap();
+    let counts = affected_counts(batch_rows, statements.len()).await.unwrap();
+
+    assert_eq!(counts, vec![2, 1, 3, 0]);
+}
+
+#[test]
+fn test_counted_write_script_reports_failing_statement_index() {
+    let statements = vec![
+        ("DELETE FROM items".to_string(), vec![]),
+        ("DELETE FROM items WHERE id = ?".to_string(), vec![]),
+    ];
+
+    let err = counted_write_script(&statements).unwrap_err();
+    assert!(err.starts_with("Statement 1:"), "{err}");
+}
+
+async fn setup_audited_items(conn: &Connection) {
+    conn.execute_batch(
+        "CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT NOT NULL);
+         CREATE TABLE audit (item_id INTEGER);
+         CREATE TRIGGER items_audit_insert AFTER INSERT ON items
+         BEGIN INSERT INTO audit (item_id) VALUES (NEW.id); END;
+         CREATE TRIGGER items_audit_delete AFTER DELETE ON items
+         BEGIN INSERT INTO audit (item_id) VALUES (OLD.id); END;",
+    )
+    .await
+    .unwrap();
+}
+
+async fn count(conn: &Connection, sql: &str) -> i64 {
+    let mut rows = conn.query(sql, ()).await.unwrap();
+    rows.next().await.unwrap().unwrap().get(0).unwrap()
+}
+
+#[tokio::test]
+async fn test_execute_without_triggers_skips_and_restores_triggers() {
+    let db_path = setup_test_db_with_prefix("without_triggers");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let db = Builder::new_local(db_path.to_str().unwrap())
+        .build()
+        .await
+        .unwrap();
+    let conn = db.connect().unwrap();
+    setup_audited_items(&conn).await;
+    let before = table_triggers(&conn, "items").await.unwrap();
+    assert_eq!(before.len(), 2);
+
+    let counts = execute_without_triggers(
+        &conn,
+        "items",
+        vec![
+            (
+                "INSERT INTO items (name) VALUES (?), (?), (?)".to_string(),
+                vec![
+                    Value::Text("a".to_string()),
+                    Value::Text("b".to_string()),
+                    Value::Text("c".to_string()),
+                ],
+            ),
+            (
+                "DELETE FROM items WHERE name = ?".to_string(),
+                vec![Value::Text("b".to_string())],
+            ),
+        ],
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(counts, vec![3, 1]);
+    assert_eq!(count(&conn, "SELECT count(*) FROM audit").await, 0);
+    assert_eq!(table_triggers(&conn, "items").await.unwrap(), before);
+
+    // The restored triggers fire again
+    conn.execute("INSERT INTO items (name) VALUES ('d')", ())
+        .await
+        .unwrap();
+    assert_eq!(count(&conn, "SELECT count(*) FROM audit").await, 1);
+}
+
+#[tokio::test]
+async fn test_execute_without_triggers_restores_triggers_on_failure() {
+    let db_path = setup_test_db_with_prefix("without_triggers");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let db = Builder::new_local(db_path.to_str().unwrap())
+        .build()
+        .await
+        .unwrap();
+    let conn = db.connect().unwrap();
+    setup_audited_items(&conn).await;
+    let before = table_triggers(&conn, "items").await.unwrap();
+
+    let result = execute_without_triggers(
+        &conn,
+        "items",
+        vec![
+            ("INSERT INTO items (name) VALUES ('a')".to_string(), vec![]),
+            ("INSERT INTO items (name) VALUES (NULL)".to_string(), vec![]),
+        ],
+    )
+    .await;
+
+    assert!(result.is_err());
+    assert_eq!(table_triggers(&conn, "items").await.unwrap(), before);
+    assert_eq!(count(&conn, "SELECT count(*) FROM items").await, 0);
+    assert!(conn.is_autocommit());
+}
+
+async fn contended_connections(db_path: &std::path::Path) -> (Connection, Connection) {
+    let db = Builder::new_local(db_path.to_str().unwrap())
+        .build()
+        .await
+        .unwrap();
+    let holder = db.connect().unwrap();
+    holder
+        .execute_batch("PRAGMA journal_mode = WAL; CREATE TABLE items (id INTEGER PRIMARY KEY);")
+        .await
+        .unwrap();
+    let writer = db.connect().unwrap();
+    writer.busy_timeout(Duration::ZERO).unwrap();
+    (holder, writer)
+}
+
+fn insert_items() -> Vec<(String, Vec<Value>)> {
+    vec![
+        (
+            "INSERT OR REPLACE INTO items (id) VALUES (1)".to_string(),
+            vec![],
+        ),
+        (
+            "INSERT OR REPLACE INTO items (id) VALUES (2)".to_string(),
+            vec![],
+        ),
+    ]
+}
+
+#[tokio::test]
+async fn test_execute_with_retry_succeeds_once_conflict_clears() {
+    let db_path = setup_test_db_with_prefix("retry_txn");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let (holder, writer) = contended_connections(&db_path).await;
+
+    // Hold the write lock, then release it from another thread while retries back off
+    holder.execute_batch("BEGIN IMMEDIATE").await.unwrap();
+    let releaser = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(50));
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(holder.execute_batch("COMMIT"))
+            .unwrap();
+    });
+
+    let (counts, attempts) =
+        execute_with_retry(&writer, &insert_items(), 20, Duration::from_millis(5))
+            .await
+            .unwrap();
+    releaser.join().unwrap();
+
+    assert_eq!(counts, vec![1, 1]);
+    assert!(attempts > 1, "expected a retry, got {attempts} attempt(s)");
+    assert!(writer.is_autocommit());
+    let mut rows = writer
+        .query("SELECT count(*) FROM items", ())
+        .await
+        .unwrap();
+    assert_eq!(
+        rows.next().await.unwrap().unwrap().get::<i64>(0).unwrap(),
+        2
+    );
+}
+
+#[tokio::test]
+async fn test_execute_with_retry_reports_exhaustion() {
+    let db_path = setup_test_db_with_prefix("retry_txn");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let (holder, writer) = contended_connections(&db_path).await;
+    holder.execute_batch("BEGIN IMMEDIATE").await.unwrap();
+
+    let result = execute_with_retry(&writer, &insert_items(), 2, Duration::from_millis(1)).await;
+
+    assert!(matches!(
+        result,
+        Err(RetryError::Exhausted { attempts: 3, .. })
+    ));
+    assert!(writer.is_autocommit());
+    holder.execute_batch("ROLLBACK").await.unwrap();
+}
+
+#[tokio::test]
+async fn test_execute_with_retry_does_not_retry_other_errors() {
+    let db_path = setup_test_db_with_prefix("retry_txn");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let (_holder, writer) = contended_connections(&db_path).await;
+
+    let mut statements = insert_items();
+    statements.push(("INSERT INTO missing VALUES (1)".to_string(), vec![]));
+    let result = execute_with_retry(&writer, &statements, 5, Duration::from_millis(1)).await;
+    assert!(matches!(result, Err(RetryError::Failed(_))));
+
+    // The failed attempt was rolled back
+    let mut rows = writer
+        .query("SELECT count(*) FROM items", ())
+        .await
+        .unwrap();
+    assert_eq!(
+        rows.next().await.unwrap().unwrap().get::<i64>(0).unwrap(),
+        0
+    );
+    drop(rows);
+
+    writer.execute_batch("BEGIN").await.unwrap();
+    let result = execute_with_retry(&writer, &insert_items(), 5, Duration::from_millis(1)).await;
+    assert!(matches!(result, Err(RetryError::InTransaction)));
+}
@@ -0,0 +1,403 @@
+//! Tests for in-place blob region writes, blob builders and blob references
+//!
+//! These tests exercise `write_blob_region` and blob-ref queries directly against
+//! a real local database, and blob builders through the builder registry, without
+//! going through the NIF layer.
+
+// Allow unwrap() in tests for cleaner test code - see CLAUDE.md "Test Code Exception"
+#![allow(clippy::unwrap_used)]
+
+use super::test_utils::{connect, setup_test_db_with_prefix, TestDbGuard};
+use crate::blob::{
+    append_to_builder, blob_ref_sql, query_blob_ref_rows, take_builder_params, write_blob_region,
+};
+use crate::constants::{BLOB_BUILDER_IDLE_TIMEOUT, BLOB_BUILDER_REGISTRY};
+use crate::models::{BlobBuilder, ColumnNaming, LazyCell};
+use libsql::{Connection, Value};
+use std::time::Instant;
+
+async fn connect_with_files(db_path: &std::path::Path) -> Connection {
+    let conn = connect(db_path).await;
+    conn.execute("CREATE TABLE files (id INTEGER PRIMARY KEY, data BLOB)", ())
+        .await
+        .unwrap();
+    conn
+}
+
+fn insert_builder(id: &str, touched: Instant) {
+    BLOB_BUILDER_REGISTRY.lock().unwrap().insert(
+        id.to_string(),
+        BlobBuilder {
+            bytes: Vec::new(),
+            touched,
+        },
+    );
+}
+
+async fn read_blob(conn: &Connection, rowid: i64) -> Value {
+    let mut rows = conn
+        .query(
+            "SELECT data FROM files WHERE rowid = ?1",
+            vec![Value::Integer(rowid)],
+        )
+        .await
+        .unwrap();
+    rows.next().await.unwrap().unwrap().get_value(0).unwrap()
+}
+
+#[tokio::test]
+async fn test_write_blob_region_patches_middle() {
+    let db_path = setup_test_db_with_prefix("blob");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect_with_files(&db_path).await;
+
+    // Include NUL and non-UTF-8 bytes to make sure the splice is byte-exact
+    let original: Vec<u8> = vec![0x00, 0x01, 0xFF, 0x00, 0xFE, 0x02, 0x03, 0x00];
+    conn.execute(
+        "INSERT INTO files (id, data) VALUES (1, ?1)",
+        vec![Value::Blob(original)],
+    )
+    .await
+    .unwrap();
+
+    write_blob_region(&conn, "files", "data", 1, 3, &[0xAA, 0x00, 0xBB])
+        .await
+        .unwrap();
+
+    assert_eq!(
+        read_blob(&conn, 1).await,
+        Value::Blob(vec![0x00, 0x01, 0xFF, 0xAA, 0x00, 0xBB, 0x03, 0x00])
+    );
+}
+
+#[tokio::test]
+async fn test_write_blob_region_up_to_end() {
+    let db_path = setup_test_db_with_prefix("blob");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect_with_files(&db_path).await;
+
+    conn.execute("INSERT INTO files (id, data) VALUES (1, X'00010203')", ())
+        .await
+        .unwrap();
+
+    write_blob_region(&conn, "files", "data", 1, 2, &[0x09, 0x09])
+        .await
+        .unwrap();
+
+    assert_eq!(
+        read_blob(&conn, 1).await,
+        Value::Blob(vec![0x00, 0x01, 0x09, 0x09])
+    );
+}
+
+#[tokio::test]
+async fn test_write_blob_region_on_utf16_database() {
+    let db_path = setup_test_db_with_prefix("blob_utf16");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect(&db_path).await;
+    // The encoding can only be chosen before the database has any content
+    conn.execute("PRAGMA encoding = 'UTF-16le'", ())
+        .await
+        .unwrap();
+    conn.execute("CREATE TABLE files (id INTEGER PRIMARY KEY, data BLOB)", ())
+        .await
+        .unwrap();
+
+    // An odd length, and bytes that aren't valid UTF-16, survive the splice unchanged
+    conn.execute("INSERT INTO files (id, data) VALUES (1, X'00D8FF0102')", ())
+        .await
+        .unwrap();
+    write_blob_region(&conn, "files", "data", 1, 1, &[0xAA])
+        .await
+        .unwrap();
+
+    assert_eq!(
+        read_blob(&conn, 1).await,
+        Value::Blob(vec![0x00, 0xAA, 0xFF, 0x01, 0x02])
+    );
+}
+
+#[tokio::test]
+async fn test_write_blob_region_rejects_growth() {
+    let db_path = setup_test_db_with_prefix("blob");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect_with_files(&db_path).await;
+
+    conn.execute("INSERT INTO files (id, data) VALUES (1, X'00010203')", ())
+        .await
+        .unwrap();
+
+    let err = write_blob_region(&conn, "files", "data", 1, 3, &[0x09, 0x09])
+        .await
+        .unwrap_err();
+    assert!(err.contains("exceeds blob size of 4 bytes"), "{err}");
+
+    // The blob must be untouched
+    assert_eq!(
+        read_blob(&conn, 1).await,
+        Value::Blob(vec![0x00, 0x01, 0x02, 0x03])
+    );
+}
+
+#[tokio::test]
+async fn test_write_blob_region_missing_row_and_non_blob() {
+    let db_path = setup_test_db_with_prefix("blob");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect_with_files(&db_path).await;
+
+    conn.execute("INSERT INTO files (id, data) VALUES (1, 'text')", ())
+        .await
+        .unwrap();
+
+    let err = write_blob_region(&conn, "files", "data", 2, 0, &[0x01])
+        .await
+        .unwrap_err();
+    assert!(err.contains("Row not found"), "{err}");
+
+    let err = write_blob_region(&conn, "files", "data", 1, 0, &[0x01])
+        .await
+        .unwrap_err();
+    assert!(err.contains("not a blob"), "{err}");
+}
+
+#[tokio::test]
+async fn test_blob_builder_assembles_chunks_for_insert() {
+    let db_path = setup_test_db_with_prefix("blob_builder");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect_with_files(&db_path).await;
+
+    insert_builder("builder-chunks", Instant::now());
+    assert_eq!(append_to_builder("builder-chunks", b"abc").unwrap(), 3);
+    assert_eq!(append_to_builder("builder-chunks", b"").unwrap(), 3);
+    assert_eq!(append_to_builder("builder-chunks", b"defgh").unwrap(), 8);
+
+    let mut params = vec![Value::Integer(1), Value::Null];
+    take_builder_params(&mut params, &[None, Some("builder-chunks".to_string())]).unwrap();
+    assert!(!BLOB_BUILDER_REGISTRY
+        .lock()
+        .unwrap()
+        .contains_key("builder-chunks"));
+    conn.execute("INSERT INTO files (id, data) VALUES (?1, ?2)", params)
+        .await
+        .unwrap();
+
+    let mut rows = conn
+        .query("SELECT data FROM files WHERE id = 1", ())
+        .await
+        .unwrap();
+    let row = rows.next().await.unwrap().unwrap();
+    assert_eq!(row.get::<Vec<u8>>(0).unwrap(), b"abcdefgh".to_vec());
+}
+
+#[test]
+fn test_repeated_builder_is_rejected_without_consuming_it() {
+    insert_builder("builder-repeated", Instant::now());
+    append_to_builder("builder-repeated", b"abc").unwrap();
+
+    let id = Some("builder-repeated".to_string());
+    let mut params = vec![Value::Null, Value::Null];
+    let err = take_builder_params(&mut params, &[id.clone(), id]).unwrap_err();
+    assert!(err.contains("more than once"), "{err}");
+    assert_eq!(params, vec![Value::Null, Value::Null]);
+
+    let builder = BLOB_BUILDER_REGISTRY
+        .lock()
+        .unwrap()
+        .remove("builder-repeated")
+        .unwrap();
+    assert_eq!(builder.bytes, b"abc".to_vec());
+}
+
+#[test]
+fn test_idle_builder_is_swept() {
+    let Some(stale) = Instant::now().checked_sub(BLOB_BUILDER_IDLE_TIMEOUT * 2) else {
+        return;
+    };
+    insert_builder("builder-idle", stale);
+    insert_builder("builder-active", Instant::now());
+
+    append_to_builder("builder-active", b"abc").unwrap();
+    assert!(append_to_builder("builder-idle", b"abc")
+        .unwrap_err()
+        .contains("not found"));
+    BLOB_BUILDER_REGISTRY
+        .lock()
+        .unwrap()
+        .remove("builder-active");
+}
+
+#[test]
+fn test_append_to_unknown_builder_fails() {
+    assert!(append_to_builder("builder-missing", b"abc")
+        .unwrap_err()
+        .contains("not found"));
+}
+
+#[tokio::test]
+async fn test_blob_refs_replace_blobs_and_fetch_on_demand() {
+    let db_path = setup_test_db_with_prefix("blob_ref");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect_with_files(&db_path).await;
+
+    conn.execute(
+        "INSERT INTO files (id, data) VALUES (1, ?1), (2, 'not a blob')",
+        vec![Value::Blob(vec![0x00, 0xFF, 0x10])],
+    )
+    .await
+    .unwrap();
+
+    // SQLite reports `rowid` under the INTEGER PRIMARY KEY alias, so both spellings work
+    for sql in [
+        "SELECT id, data FROM files ORDER BY id",
+        "SELECT rowid, data FROM files ORDER BY rowid",
+    ] {
+        let (columns, rows) = query_blob_ref_rows(&conn, sql, vec![], ColumnNaming::Raw)
+            .await
+            .unwrap();
+        assert_eq!(columns, vec!["id", "data"], "{sql}");
+        assert_eq!(
+            rows,
+            vec![
+                vec![
+                    LazyCell::Value(Value::Integer(1)),
+                    LazyCell::BlobRef {
+                        rowid: 1,
+                        column: "data".to_string(),
+                        size: 3,
+                        schema: "main".to_string(),
+                    },
+                ],
+                vec![
+                    LazyCell::Value(Value::Integer(2)),
+                    LazyCell::Value(Value::Text("not a blob".to_string())),
+                ],
+            ],
+            "{sql}"
+        );
+    }
+
+    assert_eq!(
+        crate::blob::read_blob(&conn, "main", "files", "data", 1)
+            .await
+            .unwrap(),
+        vec![0x00, 0xFF, 0x10]
+    );
+    let err = crate::blob::read_blob(&conn, "main", "files", "data", 2)
+        .await
+        .unwrap_err();
+    assert!(err.contains("not a blob (found text)"), "{err}");
+    let err = crate::blob::read_blob(&conn, "main", "files", "data", 3)
+        .await
+        .unwrap_err();
+    assert!(err.contains("Row not found"), "{err}");
+}
+
+#[tokio::test]
+async fn test_blob_refs_use_implicit_rowid_and_inline_untraceable_blobs() {
+    let db_path = setup_test_db_with_prefix("blob_ref");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect_with_files(&db_path).await;
+
+    conn.execute("CREATE TABLE notes (title TEXT, body BLOB)", ())
+        .await
+        .unwrap();
+    conn.execute("INSERT INTO notes VALUES ('a', x'0102')", ())
+        .await
+        .unwrap();
+
+    let (_, rows) = query_blob_ref_rows(
+        &conn,
+        "SELECT oid, body, x'09' AS computed FROM notes",
+        vec![],
+        ColumnNaming::Raw,
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        rows,
+        vec![vec![
+            LazyCell::Value(Value::Integer(1)),
+            LazyCell::BlobRef {
+                rowid: 1,
+                column: "body".to_string(),
+                size: 2,
+                schema: "main".to_string(),
+            },
+            LazyCell::Value(Value::Blob(vec![0x09])),
+        ]]
+    );
+
+    let err = query_blob_ref_rows(&conn, "SELECT body FROM notes", vec![], ColumnNaming::Raw)
+        .await
+        .unwrap_err();
+    assert!(err.contains("must select the rowid"), "{err}");
+}
+
+#[tokio::test]
+async fn test_blob_refs_carry_the_schema_of_their_table() {
+    let db_path = setup_test_db_with_prefix("blob_ref");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect_with_files(&db_path).await;
+
+    conn.execute("INSERT INTO files (id, data) VALUES (1, x'01')", ())
+        .await
+        .unwrap();
+    conn.execute(
+        "CREATE TEMP TABLE files (id INTEGER PRIMARY KEY, data BLOB)",
+        (),
+    )
+    .await
+    .unwrap();
+    conn.execute("INSERT INTO temp.files (id, data) VALUES (1, x'0203')", ())
+        .await
+        .unwrap();
+
+    let (_, rows) = query_blob_ref_rows(
+        &conn,
+        "SELECT id, data FROM temp.files;",
+        vec![],
+        ColumnNaming::Raw,
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        rows[0][1],
+        LazyCell::BlobRef {
+            rowid: 1,
+            column: "data".to_string(),
+            size: 2,
+            schema: "temp".to_string(),
+        }
+    );
+
+    assert_eq!(
+        crate::blob::read_blob(&conn, "temp", "files", "data", 1)
+            .await
+            .unwrap(),
+        vec![0x02, 0x03]
+    );
+}
+
+#[test]
+fn test_blob_ref_sql_reads_lengths_of_sourced_columns() {
+    let sql = blob_ref_sql(
+        "SELECT id, name, data FROM files -- all of them\n;",
+        &[
+            Some(("main".to_string(), 0)),
+            None,
+            Some(("main".to_string(), 0)),
+        ],
+    );
+    assert_eq!(
+        sql,
+        "WITH blob_ref_query(c0, c1, c2) AS (\n\
+         SELECT id, name, data FROM files -- all of them\n\
+         ) SELECT \
+         CASE WHEN typeof(c0) = 'blob' AND typeof(c0) = 'integer' THEN NULL ELSE c0 END, \
+         CASE WHEN typeof(c0) = 'blob' AND typeof(c0) = 'integer' THEN length(c0) END, \
+         c1, \
+         CASE WHEN typeof(c2) = 'blob' AND typeof(c0) = 'integer' THEN NULL ELSE c2 END, \
+         CASE WHEN typeof(c2) = 'blob' AND typeof(c0) = 'integer' THEN length(c2) END \
+         FROM blob_ref_query"
+    );
+}
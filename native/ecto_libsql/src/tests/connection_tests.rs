@@ -0,0 +1,651 @@
+//! Tests for connection helpers
+//!
+//! These tests cover building SQLite URI filenames for the `vfs` connect option,
+//! opening real local databases through them, looking up registered connections by
+//! database path, counting the statements run on each connection, round-tripping
+//! databases through bytes, sweeping a closed connection's statements and cursors,
+//! validating connection option keys per mode, releasing cache memory, verifying
+//! backup files, and counting each connection's errors by class.
+
+// Allow unwrap() in tests for cleaner test code - see CLAUDE.md "Test Code Exception"
+#![allow(clippy::unwrap_used)]
+
+use super::test_utils::{setup_test_db_with_prefix, TestDbGuard};
+use crate::connection::{
+    check_connect_options, connection_ids_for_path, local_uri_with_vfs, open_from_bytes,
+    release_memory, serialize_connection, shared_memory_uri, sweep_connection_resources,
+    verify_backup_file, ConnectOptionError,
+};
+use crate::constants::{
+    BROKEN_CONNECTION_REGISTRY, CONNECTION_PATH_REGISTRY, CONNECTION_REGISTRY, CURSOR_REGISTRY,
+    ERROR_COUNT_REGISTRY, LAST_ERROR_REGISTRY, STATEMENT_COUNT_REGISTRY, STMT_REGISTRY,
+};
+use crate::models::{
+    ColumnNaming, CursorData, DatabasePath, ErrorClass, ErrorCounts, LibSQLConn, Mode,
+    StatementSource,
+};
+use crate::utils::{
+    check_not_broken, count_statements, mark_connection_broken, record_last_error,
+    sync_with_timeout,
+};
+use libsql::Builder;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Open a local database and register it under `id`, as the `connect` NIF would.
+async fn register_local(id: &str, path: &str) {
+    let db = Builder::new_local(path).build().await.unwrap();
+    let conn = db.connect().unwrap();
+    ERROR_COUNT_REGISTRY
+        .lock()
+        .unwrap()
+        .insert(id.to_string(), Arc::new(ErrorCounts::default()));
+    STATEMENT_COUNT_REGISTRY
+        .lock()
+        .unwrap()
+        .insert(id.to_string(), Arc::new(AtomicU64::new(0)));
+    CONNECTION_PATH_REGISTRY
+        .lock()
+        .unwrap()
+        .insert(id.to_string(), DatabasePath::new(path));
+    CONNECTION_REGISTRY.lock().unwrap().insert(
+        id.to_string(),
+        Arc::new(Mutex::new(LibSQLConn {
+            db,
+            client: Arc::new(Mutex::new(conn)),
+            mode: Mode::Local,
+            column_naming: ColumnNaming::Raw,
+        })),
+    );
+}
+
+#[test]
+fn test_uri_with_vfs_plain_path() {
+    assert_eq!(
+        local_uri_with_vfs("data/app.db", "unix-none"),
+        "file:data/app.db?vfs=unix-none"
+    );
+}
+
+#[test]
+fn test_uri_with_vfs_encodes_reserved_characters() {
+    assert_eq!(
+        local_uri_with_vfs("odd?#%.db", "my&vfs=1"),
+        "file:odd%3F%23%25.db?vfs=my%26vfs%3D1"
+    );
+}
+
+#[tokio::test]
+async fn test_open_with_builtin_vfs() {
+    let db_path = setup_test_db_with_prefix("vfs_builtin");
+    let _guard = TestDbGuard::new(db_path.clone());
+
+    let uri = local_uri_with_vfs(db_path.to_str().unwrap(), "unix");
+    let db = Builder::new_local(uri).build().await.unwrap();
+    let conn = db.connect().unwrap();
+
+    conn.execute("CREATE TABLE t (id INTEGER)", ())
+        .await
+        .unwrap();
+    conn.execute("INSERT INTO t VALUES (1)", ()).await.unwrap();
+
+    // The URI must resolve to the plain file path, not a file named after the URI
+    assert!(db_path.exists());
+}
+
+#[tokio::test]
+async fn test_open_with_unknown_vfs_fails() {
+    let db_path = setup_test_db_with_prefix("vfs_unknown");
+    let _guard = TestDbGuard::new(db_path.clone());
+
+    let uri = local_uri_with_vfs(db_path.to_str().unwrap(), "no-such-vfs");
+    let db = Builder::new_local(uri).build().await.unwrap();
+
+    assert!(db.connect().is_err());
+    assert!(!db_path.exists());
+}
+
+#[test]
+fn test_shared_memory_uri() {
+    assert_eq!(
+        shared_memory_uri("pool_test"),
+        "file:pool_test?mode=memory&cache=shared"
+    );
+    assert_eq!(
+        shared_memory_uri("a?b#c"),
+        "file:a%3Fb%23c?mode=memory&cache=shared"
+    );
+}
+
+async fn count_rows(conn: &libsql::Connection, table: &str) -> i64 {
+    let mut rows = conn
+        .query(&format!("SELECT count(*) FROM {table}"), ())
+        .await
+        .unwrap();
+    rows.next().await.unwrap().unwrap().get(0).unwrap()
+}
+
+#[tokio::test]
+async fn test_shared_memory_visible_across_connections() {
+    let db_a = Builder::new_local(shared_memory_uri("conn_tests_shared"))
+        .build()
+        .await
+        .unwrap();
+    let db_b = Builder::new_local(shared_memory_uri("conn_tests_shared"))
+        .build()
+        .await
+        .unwrap();
+    let conn_a = db_a.connect().unwrap();
+    let conn_b = db_b.connect().unwrap();
+
+    conn_a
+        .execute("CREATE TABLE items (id INTEGER PRIMARY KEY)", ())
+        .await
+        .unwrap();
+    conn_a
+        .execute("INSERT INTO items VALUES (1), (2)", ())
+        .await
+        .unwrap();
+    assert_eq!(count_rows(&conn_b, "items").await, 2);
+
+    // A differently named database is separate
+    let db_other = Builder::new_local(shared_memory_uri("conn_tests_other"))
+        .build()
+        .await
+        .unwrap();
+    let conn_other = db_other.connect().unwrap();
+    assert!(conn_other.query("SELECT * FROM items", ()).await.is_err());
+
+    // Nothing is written to disk
+    assert!(!std::path::Path::new("conn_tests_shared").exists());
+}
+
+#[test]
+fn test_database_path_compares_verbatim_when_not_canonicalisable() {
+    assert!(DatabasePath::new("libsql://db.example.io")
+        .matches(&DatabasePath::new("libsql://db.example.io")));
+    assert!(!DatabasePath::new("missing-a.db").matches(&DatabasePath::new("missing-b.db")));
+}
+
+#[tokio::test]
+async fn test_connection_ids_for_path_counts_duplicates() {
+    let db_path = setup_test_db_with_prefix("conns_for_path");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let other_path = setup_test_db_with_prefix("conns_for_path_other");
+    let _other_guard = TestDbGuard::new(other_path.clone());
+    let path = db_path.to_str().unwrap();
+
+    let ids = ["cfp-test-a", "cfp-test-b", "cfp-test-c"];
+    for id in ids {
+        register_local(id, path).await;
+    }
+    register_local("cfp-test-other", other_path.to_str().unwrap()).await;
+
+    // A different spelling of the same file must match too
+    let respelled = format!(
+        "{}/./{}",
+        db_path.parent().unwrap().display(),
+        db_path.file_name().unwrap().to_str().unwrap()
+    );
+
+    let found = connection_ids_for_path(path).unwrap();
+    let found_respelled = connection_ids_for_path(&respelled).unwrap();
+
+    for id in ids.iter().chain(["cfp-test-other"].iter()) {
+        CONNECTION_REGISTRY.lock().unwrap().remove(*id);
+        CONNECTION_PATH_REGISTRY.lock().unwrap().remove(*id);
+    }
+
+    assert_eq!(found, ids);
+    assert_eq!(found_respelled, ids);
+}
+
+#[tokio::test]
+async fn test_statement_count_accumulates_per_connection() {
+    let db_path = setup_test_db_with_prefix("statement_count");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let path = db_path.to_str().unwrap();
+    register_local("sc-test-a", path).await;
+    register_local("sc-test-b", path).await;
+
+    count_statements("sc-test-a", 1);
+    count_statements("sc-test-a", 4);
+    count_statements("sc-test-b", 2);
+    // Unknown connections are ignored
+    count_statements("sc-test-missing", 1);
+
+    let counts: Vec<u64> = ["sc-test-a", "sc-test-b"]
+        .iter()
+        .map(|id| {
+            CONNECTION_REGISTRY.lock().unwrap().remove(*id);
+            let total = STATEMENT_COUNT_REGISTRY
+                .lock()
+                .unwrap()
+                .remove(*id)
+                .unwrap();
+            total.load(Ordering::Relaxed)
+        })
+        .collect();
+
+    assert_eq!(counts, vec![5, 2]);
+}
+
+#[tokio::test]
+async fn test_error_counts_classify_recorded_errors() {
+    let db_path = setup_test_db_with_prefix("error_counts");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let path = db_path.to_str().unwrap();
+    register_local("ec-test", path).await;
+
+    let client = CONNECTION_REGISTRY
+        .lock()
+        .unwrap()
+        .get("ec-test")
+        .cloned()
+        .unwrap();
+    let conn = client.lock().unwrap().client.lock().unwrap().clone();
+    let counts = ERROR_COUNT_REGISTRY
+        .lock()
+        .unwrap()
+        .get("ec-test")
+        .cloned()
+        .unwrap();
+
+    conn.execute("CREATE TABLE users (email TEXT UNIQUE)", ())
+        .await
+        .unwrap();
+    conn.execute("INSERT INTO users VALUES ('a@example.com')", ())
+        .await
+        .unwrap();
+    for _ in 0..2 {
+        let err = conn
+            .execute("INSERT INTO users VALUES ('a@example.com')", ())
+            .await
+            .unwrap_err();
+        record_last_error("ec-test", &err);
+    }
+
+    // A second connection finds the write lock taken and gives up straight away
+    let other = Builder::new_local(path)
+        .build()
+        .await
+        .unwrap()
+        .connect()
+        .unwrap();
+    other.busy_timeout(Duration::ZERO).unwrap();
+    conn.execute("BEGIN IMMEDIATE", ()).await.unwrap();
+    for _ in 0..2 {
+        let err = other.execute("BEGIN IMMEDIATE", ()).await.unwrap_err();
+        record_last_error("ec-test", &err);
+    }
+    conn.execute("ROLLBACK", ()).await.unwrap();
+
+    for sql in ["SELEC 1", "SELECT * FROM missing"] {
+        let err = conn.execute(sql, ()).await.unwrap_err();
+        record_last_error("ec-test", &err);
+    }
+
+    let by_class = |counts: &ErrorCounts| -> Vec<u64> {
+        ErrorClass::ALL
+            .iter()
+            .map(|class| counts.get(*class))
+            .collect()
+    };
+    assert_eq!(by_class(&counts), vec![2, 2, 1, 1]);
+
+    // Resetting hands back the counts it cleared
+    let taken: Vec<u64> = ErrorClass::ALL
+        .iter()
+        .map(|class| counts.take(*class))
+        .collect();
+    assert_eq!(taken, vec![2, 2, 1, 1]);
+    assert_eq!(by_class(&counts), vec![0, 0, 0, 0]);
+
+    CONNECTION_REGISTRY.lock().unwrap().remove("ec-test");
+    ERROR_COUNT_REGISTRY.lock().unwrap().remove("ec-test");
+}
+
+#[tokio::test]
+async fn test_database_round_trips_through_bytes() {
+    let db_path = setup_test_db_with_prefix("from_bytes");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let db = Builder::new_local(db_path.to_str().unwrap())
+        .build()
+        .await
+        .unwrap();
+    let conn = db.connect().unwrap();
+    conn.execute("CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT)", ())
+        .await
+        .unwrap();
+    conn.execute("INSERT INTO items (name) VALUES ('a'), ('b')", ())
+        .await
+        .unwrap();
+
+    let bytes = serialize_connection(&conn).await.unwrap();
+    assert!(bytes.starts_with(b"SQLite format 3\0"));
+
+    // Writes to the copy stay in memory and leave the source untouched
+    let (_copy_db, copy, uri) = open_from_bytes(&bytes).await.unwrap();
+    assert!(uri.contains("vfs=memdb"));
+    copy.execute("INSERT INTO items (name) VALUES ('c')", ())
+        .await
+        .unwrap();
+    assert_eq!(count_rows(&copy, "items").await, 3);
+    assert_eq!(count_rows(&conn, "items").await, 2);
+
+    // The copy can itself be serialised, writes included
+    let again = serialize_connection(&copy).await.unwrap();
+    let (_again_db, reopened, _) = open_from_bytes(&again).await.unwrap();
+    assert_eq!(count_rows(&reopened, "items").await, 3);
+}
+
+#[tokio::test]
+async fn test_open_from_bytes_rejects_non_database() {
+    let result = open_from_bytes(b"definitely not a database").await;
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn test_serialize_memory_database() {
+    let db = Builder::new_local(":memory:").build().await.unwrap();
+    let conn = db.connect().unwrap();
+    conn.execute("CREATE TABLE items (name TEXT)", ())
+        .await
+        .unwrap();
+    conn.execute("INSERT INTO items VALUES ('a')", ())
+        .await
+        .unwrap();
+
+    let bytes = serialize_connection(&conn).await.unwrap();
+    let (_copy_db, copy, _) = open_from_bytes(&bytes).await.unwrap();
+    assert_eq!(count_rows(&copy, "items").await, 1);
+}
+
+#[tokio::test]
+async fn test_serialize_includes_uncheckpointed_wal() {
+    let db_path = setup_test_db_with_prefix("serialize_wal");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let db = Builder::new_local(db_path.to_str().unwrap())
+        .build()
+        .await
+        .unwrap();
+    let conn = db.connect().unwrap();
+    conn.query("PRAGMA journal_mode = WAL", ()).await.unwrap();
+    conn.query("PRAGMA wal_autocheckpoint = 0", ())
+        .await
+        .unwrap();
+    conn.execute("CREATE TABLE items (name TEXT)", ())
+        .await
+        .unwrap();
+    conn.execute("INSERT INTO items VALUES ('a'), ('b')", ())
+        .await
+        .unwrap();
+
+    let bytes = serialize_connection(&conn).await.unwrap();
+    let (_copy_db, copy, _) = open_from_bytes(&bytes).await.unwrap();
+    assert_eq!(count_rows(&copy, "items").await, 2);
+}
+
+#[tokio::test]
+async fn test_serialize_refuses_open_transaction() {
+    let db = Builder::new_local(":memory:").build().await.unwrap();
+    let conn = db.connect().unwrap();
+    conn.execute("CREATE TABLE items (name TEXT)", ())
+        .await
+        .unwrap();
+    conn.execute("BEGIN", ()).await.unwrap();
+
+    let err = serialize_connection(&conn).await.unwrap_err();
+    assert!(err.contains("transaction"), "unexpected error: {err}");
+}
+
+#[tokio::test]
+async fn test_sweep_removes_only_the_connections_statements_and_cursors() {
+    let db = Builder::new_local(":memory:").build().await.unwrap();
+    let conn = db.connect().unwrap();
+    let stmt = conn.prepare("SELECT 1").await.unwrap();
+
+    let cursor = |owner: &str| CursorData {
+        conn_id: owner.to_string(),
+        columns: vec!["x".to_string()],
+        rows: vec![],
+        position: 0,
+    };
+    CURSOR_REGISTRY
+        .lock()
+        .unwrap()
+        .insert("sweep-cursor-owned".to_string(), cursor("sweep-conn"));
+    CURSOR_REGISTRY
+        .lock()
+        .unwrap()
+        .insert("sweep-cursor-other".to_string(), cursor("sweep-other-conn"));
+    STMT_REGISTRY.lock().unwrap().insert(
+        "sweep-stmt-owned".to_string(),
+        (
+            "sweep-conn".to_string(),
+            Arc::new(Mutex::new(stmt)),
+            StatementSource {
+                sql: "SELECT 1".to_string(),
+                schema_version: Some(0),
+            },
+        ),
+    );
+
+    assert_eq!(sweep_connection_resources("sweep-conn").unwrap(), 2);
+    assert!(!CURSOR_REGISTRY
+        .lock()
+        .unwrap()
+        .contains_key("sweep-cursor-owned"));
+    assert!(!STMT_REGISTRY
+        .lock()
+        .unwrap()
+        .contains_key("sweep-stmt-owned"));
+    assert!(CURSOR_REGISTRY
+        .lock()
+        .unwrap()
+        .contains_key("sweep-cursor-other"));
+
+    // Nothing left to sweep
+    assert_eq!(sweep_connection_resources("sweep-conn").unwrap(), 0);
+    CURSOR_REGISTRY.lock().unwrap().remove("sweep-cursor-other");
+}
+
+#[test]
+fn test_check_connect_options_accepts_each_mode() {
+    assert_eq!(check_connect_options(&["database"], Mode::Local), Ok(()));
+    assert_eq!(
+        check_connect_options(&["shared_memory", "busy_timeout"], Mode::Local),
+        Ok(())
+    );
+    assert_eq!(
+        check_connect_options(&["uri", "auth_token"], Mode::Remote),
+        Ok(())
+    );
+    assert_eq!(
+        check_connect_options(
+            &["database", "uri", "auth_token", "sync"],
+            Mode::RemoteReplica
+        ),
+        Ok(())
+    );
+}
+
+#[test]
+fn test_check_connect_options_reports_missing_keys() {
+    assert_eq!(
+        check_connect_options(&["uri"], Mode::Remote),
+        Err(ConnectOptionError::Missing("auth_token"))
+    );
+    assert_eq!(
+        check_connect_options(&["uri", "auth_token"], Mode::RemoteReplica),
+        Err(ConnectOptionError::Missing("database"))
+    );
+    assert_eq!(
+        check_connect_options(&[], Mode::Local),
+        Err(ConnectOptionError::Missing("database"))
+    );
+}
+
+#[test]
+fn test_check_connect_options_reports_unknown_before_missing() {
+    // The typo explains why auth_token looks missing, so it is the more useful error
+    assert_eq!(
+        check_connect_options(&["uri", "auth_tokne"], Mode::Remote),
+        Err(ConnectOptionError::Unknown("auth_tokne".to_string()))
+    );
+}
+
+#[tokio::test]
+async fn test_release_memory_after_heavy_query() {
+    let db_path = setup_test_db_with_prefix("shrink_memory");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let db = Builder::new_local(db_path.to_str().unwrap())
+        .build()
+        .await
+        .unwrap();
+    let conn = db.connect().unwrap();
+    conn.execute_batch(
+        "CREATE TABLE blobs AS
+         WITH RECURSIVE n(x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM n WHERE x < 2000)
+         SELECT x AS id, randomblob(1024) AS data FROM n",
+    )
+    .await
+    .unwrap();
+    {
+        let mut rows = conn
+            .query("SELECT sum(length(data)) FROM blobs", ())
+            .await
+            .unwrap();
+        let total: i64 = rows.next().await.unwrap().unwrap().get(0).unwrap();
+        assert_eq!(total, 2000 * 1024);
+    }
+
+    release_memory(&conn).await.unwrap();
+
+    // The connection keeps working, refilling its cache as needed
+    let mut rows = conn.query("SELECT count(*) FROM blobs", ()).await.unwrap();
+    let count: i64 = rows.next().await.unwrap().unwrap().get(0).unwrap();
+    assert_eq!(count, 2000);
+}
+
+/// Write a backup of a multi-page database to `path` and return its bytes.
+async fn write_backup(path: &std::path::Path) -> Vec<u8> {
+    let db = Builder::new_local(":memory:").build().await.unwrap();
+    let conn = db.connect().unwrap();
+    conn.execute("CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT)", ())
+        .await
+        .unwrap();
+    conn.execute(
+        "WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 500) \
+         INSERT INTO items (name) SELECT printf('%0200d', i) FROM n",
+        (),
+    )
+    .await
+    .unwrap();
+    let bytes = serialize_connection(&conn).await.unwrap();
+    std::fs::write(path, &bytes).unwrap();
+    bytes
+}
+
+#[tokio::test]
+async fn test_verify_backup_accepts_good_backup() {
+    let db_path = setup_test_db_with_prefix("verify_backup_good");
+    let _guard = TestDbGuard::new(db_path.clone());
+    write_backup(&db_path).await;
+
+    let schema_version = verify_backup_file(db_path.to_str().unwrap()).await.unwrap();
+    assert!(schema_version > 0);
+}
+
+#[tokio::test]
+async fn test_verify_backup_reports_truncated_backup() {
+    let db_path = setup_test_db_with_prefix("verify_backup_truncated");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let bytes = write_backup(&db_path).await;
+    std::fs::write(&db_path, &bytes[..bytes.len() / 2]).unwrap();
+
+    let problems = verify_backup_file(db_path.to_str().unwrap())
+        .await
+        .unwrap_err();
+    assert!(!problems.is_empty());
+}
+
+#[tokio::test]
+async fn test_verify_backup_reports_corrupt_and_missing_files() {
+    let db_path = setup_test_db_with_prefix("verify_backup_corrupt");
+    let _guard = TestDbGuard::new(db_path.clone());
+    std::fs::write(&db_path, b"definitely not a database").unwrap();
+
+    let problems = verify_backup_file(db_path.to_str().unwrap())
+        .await
+        .unwrap_err();
+    assert!(problems[0].contains("Not an SQLite database"));
+
+    let missing = db_path.with_extension("missing");
+    let problems = verify_backup_file(missing.to_str().unwrap())
+        .await
+        .unwrap_err();
+    assert!(problems[0].contains("Failed to read backup"));
+    assert!(!missing.exists());
+}
+
+#[tokio::test]
+async fn test_sync_failure_is_recorded_as_last_error() {
+    let db_path = setup_test_db_with_prefix("sync_last_error");
+    let _guard = TestDbGuard::new(db_path.clone());
+    register_local("sync-error-test", db_path.to_str().unwrap()).await;
+    let client = CONNECTION_REGISTRY
+        .lock()
+        .unwrap()
+        .get("sync-error-test")
+        .cloned()
+        .unwrap();
+
+    // A local database has nothing to sync from, so syncing it fails
+    let err = sync_with_timeout("sync-error-test", &client, 5)
+        .await
+        .unwrap_err();
+    let recorded = LAST_ERROR_REGISTRY
+        .lock()
+        .unwrap()
+        .get("sync-error-test")
+        .cloned()
+        .unwrap();
+    assert_eq!(format!("Sync error: {}", recorded.message), err);
+
+    CONNECTION_REGISTRY
+        .lock()
+        .unwrap()
+        .remove("sync-error-test");
+    ERROR_COUNT_REGISTRY
+        .lock()
+        .unwrap()
+        .remove("sync-error-test");
+    LAST_ERROR_REGISTRY
+        .lock()
+        .unwrap()
+        .remove("sync-error-test");
+}
+
+#[test]
+fn test_broken_connection_fails_health_check() {
+    assert!(check_not_broken("broken-test").is_ok());
+
+    mark_connection_broken(
+        "broken-test",
+        "Failed to restore query_only: boom".to_string(),
+    );
+    let err = check_not_broken("broken-test").unwrap_err();
+    assert!(err.contains("unknown state"), "got: {err}");
+    assert!(
+        err.contains("Failed to restore query_only: boom"),
+        "got: {err}"
+    );
+
+    BROKEN_CONNECTION_REGISTRY
+        .lock()
+        .unwrap()
+        .remove("broken-test");
+    assert!(check_not_broken("broken-test").is_ok());
+}
@@ -0,0 +1,119 @@
+//! Tests for atomic counters
+//!
+//! These tests run increments directly against a real local database, without going
+//! through the NIF layer.
+
+// Allow unwrap() in tests for cleaner test code - see CLAUDE.md "Test Code Exception"
+#![allow(clippy::unwrap_used)]
+
+use super::test_utils::{setup_test_db_with_prefix, TestDbGuard};
+use crate::counter::increment_counter;
+use libsql::{Builder, Connection, Value};
+
+async fn counters_db(db_path: &std::path::Path) -> Connection {
+    let db = Builder::new_local(db_path.to_str().unwrap())
+        .build()
+        .await
+        .unwrap();
+    let conn = db.connect().unwrap();
+    conn.execute(
+        "CREATE TABLE \"page counters\" (\"key\" TEXT PRIMARY KEY, hits INTEGER)",
+        (),
+    )
+    .await
+    .unwrap();
+    conn
+}
+
+async fn stored_hits(conn: &Connection, key: &str) -> Option<i64> {
+    let mut rows = conn
+        .query(
+            "SELECT hits FROM \"page counters\" WHERE \"key\" = ?1",
+            [key],
+        )
+        .await
+        .unwrap();
+    rows.next().await.unwrap().map(|row| row.get(0).unwrap())
+}
+
+#[tokio::test]
+async fn test_increment_existing_counter() {
+    let db_path = setup_test_db_with_prefix("counter_existing");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = counters_db(&db_path).await;
+    conn.execute(
+        "INSERT INTO \"page counters\" VALUES ('home', 41), ('about', 7)",
+        (),
+    )
+    .await
+    .unwrap();
+
+    let home = Value::Text("home".to_string());
+    let value = increment_counter(&conn, "page counters", "key", home.clone(), "hits", 1)
+        .await
+        .unwrap();
+    assert_eq!(value, 42);
+
+    let value = increment_counter(&conn, "page counters", "key", home, "hits", -2)
+        .await
+        .unwrap();
+    assert_eq!(value, 40);
+    assert_eq!(stored_hits(&conn, "home").await, Some(40));
+    assert_eq!(stored_hits(&conn, "about").await, Some(7));
+}
+
+#[tokio::test]
+async fn test_increment_creates_missing_counter() {
+    let db_path = setup_test_db_with_prefix("counter_missing");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = counters_db(&db_path).await;
+
+    let key = Value::Text("new".to_string());
+    let value = increment_counter(&conn, "page counters", "key", key.clone(), "hits", 5)
+        .await
+        .unwrap();
+    assert_eq!(value, 5);
+
+    let value = increment_counter(&conn, "page counters", "key", key, "hits", 5)
+        .await
+        .unwrap();
+    assert_eq!(value, 10);
+    assert_eq!(stored_hits(&conn, "new").await, Some(10));
+}
+
+#[tokio::test]
+async fn test_increment_treats_null_counter_as_zero() {
+    let db_path = setup_test_db_with_prefix("counter_null");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = counters_db(&db_path).await;
+    conn.execute("INSERT INTO \"page counters\" VALUES ('blank', NULL)", ())
+        .await
+        .unwrap();
+
+    let key = Value::Text("blank".to_string());
+    let value = increment_counter(&conn, "page counters", "key", key, "hits", 3)
+        .await
+        .unwrap();
+    assert_eq!(value, 3);
+}
+
+#[tokio::test]
+async fn test_increment_requires_unique_key() {
+    let db_path = setup_test_db_with_prefix("counter_not_unique");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = counters_db(&db_path).await;
+    conn.execute("CREATE TABLE loose (name TEXT, total INTEGER)", ())
+        .await
+        .unwrap();
+
+    let result = increment_counter(
+        &conn,
+        "loose",
+        "name",
+        Value::Text("a".to_string()),
+        "total",
+        1,
+    )
+    .await;
+    assert!(result.is_err());
+}
@@ -0,0 +1,107 @@
+//! Tests for cursor buffering
+//!
+//! These tests collect result sets the way cursors do and check the row-count hint
+//! and the buffered memory estimate, without going through the NIF layer.
+
+// Allow unwrap() in tests for cleaner test code - see CLAUDE.md "Test Code Exception"
+#![allow(clippy::unwrap_used)]
+
+use super::test_utils::{setup_test_db_with_prefix, TestDbGuard};
+use crate::cursor::{collect_cursor_rows, MAX_EXPECTED_ROWS};
+use crate::models::CursorData;
+use libsql::{Builder, Connection};
+
+async fn seeded_connection(db_path: &std::path::Path, count: i64) -> Connection {
+    let db = Builder::new_local(db_path.to_str().unwrap())
+        .build()
+        .await
+        .unwrap();
+    let conn = db.connect().unwrap();
+    conn.execute("CREATE TABLE items (id INTEGER, label TEXT, data BLOB)", ())
+        .await
+        .unwrap();
+    conn.execute(
+        "WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < ?1)
+         INSERT INTO items SELECT i, 'item-' || i, zeroblob(16) FROM n",
+        [count],
+    )
+    .await
+    .unwrap();
+    conn
+}
+
+#[tokio::test]
+async fn test_expected_rows_reserves_buffer_up_front() {
+    let db_path = setup_test_db_with_prefix("cursor_hint");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = seeded_connection(&db_path, 500).await;
+
+    let rows = conn.query("SELECT * FROM items", ()).await.unwrap();
+    let (columns, hinted) = collect_cursor_rows(rows, Some(500)).await.unwrap();
+    assert_eq!(columns, vec!["id", "label", "data"]);
+    assert_eq!(hinted.len(), 500);
+    // Reserved once, so the buffer never grew past the hint.
+    assert_eq!(hinted.capacity(), 500);
+
+    let rows = conn.query("SELECT * FROM items", ()).await.unwrap();
+    let (_, unhinted) = collect_cursor_rows(rows, None).await.unwrap();
+    assert_eq!(unhinted.len(), 500);
+    // Grown by doubling, leaving slack beyond the row count.
+    assert!(unhinted.capacity() > 500);
+}
+
+#[tokio::test]
+async fn test_expected_rows_hint_is_capped() {
+    let db_path = setup_test_db_with_prefix("cursor_hint_cap");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = seeded_connection(&db_path, 1).await;
+
+    let rows = conn.query("SELECT * FROM items", ()).await.unwrap();
+    let (_, collected) = collect_cursor_rows(rows, Some(usize::MAX)).await.unwrap();
+    assert_eq!(collected.len(), 1);
+    assert_eq!(collected.capacity(), MAX_EXPECTED_ROWS);
+}
+
+#[tokio::test]
+async fn test_empty_result_has_no_columns() {
+    let db_path = setup_test_db_with_prefix("cursor_empty");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = seeded_connection(&db_path, 1).await;
+
+    let rows = conn
+        .query("SELECT * FROM items WHERE id < 0", ())
+        .await
+        .unwrap();
+    let (columns, collected) = collect_cursor_rows(rows, Some(10)).await.unwrap();
+    assert!(columns.is_empty());
+    assert!(collected.is_empty());
+}
+
+#[tokio::test]
+async fn test_estimated_memory_grows_with_buffered_rows() {
+    let db_path = setup_test_db_with_prefix("cursor_memory");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = seeded_connection(&db_path, 100).await;
+
+    let cursor_for = |columns, rows| CursorData {
+        conn_id: "conn".to_string(),
+        columns,
+        rows,
+        position: 0,
+    };
+
+    let rows = conn
+        .query("SELECT * FROM items LIMIT 10", ())
+        .await
+        .unwrap();
+    let (columns, collected) = collect_cursor_rows(rows, Some(10)).await.unwrap();
+    let small = cursor_for(columns, collected).estimated_memory();
+
+    let rows = conn.query("SELECT * FROM items", ()).await.unwrap();
+    let (columns, collected) = collect_cursor_rows(rows, Some(100)).await.unwrap();
+    let large = cursor_for(columns, collected).estimated_memory();
+
+    assert!(small > 0);
+    // Each row carries at least its 16 byte blob.
+    assert!(large >= small + 90 * 16);
+}
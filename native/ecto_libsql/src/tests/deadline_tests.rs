@@ -0,0 +1,96 @@
+//! Tests for deadline tokens
+//!
+//! These tests share one budget across several queries on a real local database and check
+//! that a query running past the deadline is interrupted.
+
+// Allow unwrap() in tests for cleaner test code - see CLAUDE.md "Test Code Exception"
+#![allow(clippy::unwrap_used)]
+
+use super::test_utils::{connect, setup_test_db_with_prefix, TestDbGuard};
+use crate::constants::{DEADLINE_REGISTRY, DEADLINE_RETENTION};
+use crate::deadline::{remaining, run_within, sweep_expired};
+use libsql::Connection;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+fn set_deadline(token: &str, ms: u64) {
+    DEADLINE_REGISTRY.lock().unwrap().insert(
+        token.to_string(),
+        Instant::now() + Duration::from_millis(ms),
+    );
+}
+
+async fn count(conn: &Connection, sql: &str) -> Result<i64, libsql::Error> {
+    let mut rows = conn.query(sql, ()).await?;
+    let row = rows.next().await?.unwrap();
+    row.get(0)
+}
+
+/// Counts far enough that it never finishes within the test's budget.
+const SLOW_QUERY: &str = "WITH RECURSIVE n(x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM n \
+                          WHERE x < 10000000000) SELECT count(*) FROM n";
+
+#[tokio::test]
+async fn test_second_query_exceeds_shared_budget() {
+    let db_path = setup_test_db_with_prefix("deadline");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect(&db_path).await;
+    let token = "deadline-shared-budget";
+    set_deadline(token, 500);
+
+    // The first query runs well within the budget...
+    let budget = remaining(token).unwrap();
+    let first = run_within(&conn, budget, count(&conn, "SELECT 1")).await;
+    assert_eq!(first.unwrap().unwrap(), 1);
+
+    // ...but the caller then spends most of what is left.
+    std::thread::sleep(Duration::from_millis(400));
+
+    let budget = remaining(token).unwrap();
+    assert!(budget <= Duration::from_millis(100));
+
+    let started = Instant::now();
+    let second = run_within(&conn, budget, count(&conn, SLOW_QUERY)).await;
+    assert!(second.is_none(), "slow query should hit the deadline");
+    assert!(started.elapsed() < Duration::from_secs(2));
+
+    // The connection is still usable afterwards.
+    assert_eq!(count(&conn, "SELECT 2").await.unwrap(), 2);
+
+    DEADLINE_REGISTRY.lock().unwrap().remove(token);
+}
+
+#[tokio::test]
+async fn test_expired_deadline_has_no_time_left() {
+    let token = "deadline-expired";
+    set_deadline(token, 0);
+    std::thread::sleep(Duration::from_millis(5));
+
+    assert_eq!(remaining(token).unwrap(), Duration::ZERO);
+
+    DEADLINE_REGISTRY.lock().unwrap().remove(token);
+}
+
+#[test]
+fn test_sweep_keeps_recently_expired_tokens() {
+    let now = Instant::now();
+    let mut deadlines = HashMap::from([
+        ("pending".to_string(), now + DEADLINE_RETENTION * 2),
+        ("recent".to_string(), now + DEADLINE_RETENTION / 2),
+        ("stale".to_string(), now),
+    ]);
+
+    sweep_expired(&mut deadlines, now + DEADLINE_RETENTION);
+
+    let mut kept: Vec<_> = deadlines.keys().map(String::as_str).collect();
+    kept.sort_unstable();
+    assert_eq!(kept, vec!["pending", "recent"]);
+}
+
+#[test]
+fn test_unknown_token_is_an_error() {
+    assert_eq!(
+        remaining("deadline-never-set").unwrap_err(),
+        "Unknown deadline token"
+    );
+}
@@ -11,12 +11,16 @@
 //! 4. Transaction errors (operations after commit, double rollback)
 //! 5. Query syntax errors (invalid SQL, non-existent table/column)
 //! 6. Resource exhaustion (too many prepared statements/cursors)
+//! 7. Lock contention (busy and locked errors)
 
 // Allow unwrap() in tests for cleaner test code - see CLAUDE.md "Test Code Exception"
 #![allow(clippy::unwrap_used)]
 
 use super::test_utils::{setup_test_db_with_prefix, TestDbGuard};
+use crate::models::BusyKind;
+use crate::utils::{classify_busy, last_error_from};
 use libsql::{Builder, Value};
+use std::time::Duration;
 
 // ============================================================================
 // CONSTRAINT VIOLATION TESTS
@@ -614,6 +618,86 @@ async fn test_database_persistence_and_reopen() {
     );
 }
 
+// ============================================================================
+// LOCK CONTENTION TESTS
+// ============================================================================
+
+#[tokio::test]
+async fn test_write_lock_contention_classified_as_write_lock() {
+    let db_path = setup_test_db_with_prefix("errors");
+    let _guard = TestDbGuard::new(db_path.clone());
+
+    let db = Builder::new_local(db_path.to_str().unwrap())
+        .build()
+        .await
+        .unwrap();
+    let holder = db.connect().unwrap();
+    let waiter = db.connect().unwrap();
+    waiter.busy_timeout(Duration::from_millis(0)).unwrap();
+
+    holder
+        .execute("CREATE TABLE t (id INTEGER PRIMARY KEY)", ())
+        .await
+        .unwrap();
+    holder.execute("BEGIN IMMEDIATE", ()).await.unwrap();
+
+    let err = waiter
+        .execute("INSERT INTO t VALUES (1)", ())
+        .await
+        .unwrap_err();
+    assert_eq!(
+        classify_busy(&last_error_from(&err)),
+        Some(BusyKind::WriteLock),
+        "unexpected error: {err}"
+    );
+
+    holder.execute("ROLLBACK", ()).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_stale_snapshot_classified_as_read_lock() {
+    let db_path = setup_test_db_with_prefix("errors");
+    let _guard = TestDbGuard::new(db_path.clone());
+
+    let db = Builder::new_local(db_path.to_str().unwrap())
+        .build()
+        .await
+        .unwrap();
+    let reader = db.connect().unwrap();
+    let writer = db.connect().unwrap();
+    reader.busy_timeout(Duration::from_millis(0)).unwrap();
+
+    let mut rows = writer.query("PRAGMA journal_mode = WAL", ()).await.unwrap();
+    rows.next().await.unwrap();
+    drop(rows);
+    writer
+        .execute("CREATE TABLE t (id INTEGER PRIMARY KEY)", ())
+        .await
+        .unwrap();
+
+    // The reader's transaction pins a snapshot, which the writer then moves past
+    reader.execute("BEGIN", ()).await.unwrap();
+    let mut rows = reader.query("SELECT count(*) FROM t", ()).await.unwrap();
+    rows.next().await.unwrap();
+    drop(rows);
+    writer
+        .execute("INSERT INTO t VALUES (1)", ())
+        .await
+        .unwrap();
+
+    let err = reader
+        .execute("INSERT INTO t VALUES (2)", ())
+        .await
+        .unwrap_err();
+    assert_eq!(
+        classify_busy(&last_error_from(&err)),
+        Some(BusyKind::ReadLock),
+        "unexpected error: {err}"
+    );
+
+    reader.execute("ROLLBACK", ()).await.unwrap();
+}
+
 // ============================================================================
 // EDGE CASE TESTS
 // ============================================================================
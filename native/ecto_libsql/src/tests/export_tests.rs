@@ -0,0 +1,345 @@
+//! Tests for data export helpers
+//!
+//! These tests exercise the SQL dump, newline-delimited JSON and binary frame helpers
+//! directly against real local databases, without going through the NIF layer.
+
+// Allow unwrap() in tests for cleaner test code - see CLAUDE.md "Test Code Exception"
+#![allow(clippy::unwrap_used)]
+
+use super::test_utils::{connect, setup_test_db_with_prefix, TestDbGuard};
+use crate::export::{dump_table, push_frame_value, query_frame, query_ndjson, resolve_table_name};
+use crate::models::ColumnNaming;
+use libsql::Value;
+
+const CREATE_ITEMS: &str =
+    "CREATE TABLE \"odd \"\"items\"\"\" (id INTEGER PRIMARY KEY, name TEXT, price REAL, data BLOB)";
+
+#[tokio::test]
+async fn test_dump_replays_into_fresh_database() {
+    let source_path = setup_test_db_with_prefix("export_src");
+    let _source_guard = TestDbGuard::new(source_path.clone());
+    let target_path = setup_test_db_with_prefix("export_dst");
+    let _target_guard = TestDbGuard::new(target_path.clone());
+
+    let source = connect(&source_path).await;
+    source.execute(CREATE_ITEMS, ()).await.unwrap();
+    for (id, name, price, data) in [
+        (
+            1,
+            Value::Text("O'Brien".into()),
+            Value::Real(1.0),
+            Value::Blob(vec![0, 1, 255]),
+        ),
+        (2, Value::Null, Value::Real(-2.5), Value::Null),
+        (
+            3,
+            Value::Text("multi\nline".into()),
+            Value::Null,
+            Value::Blob(vec![]),
+        ),
+    ] {
+        source
+            .execute(
+                "INSERT INTO \"odd \"\"items\"\"\" VALUES (?1, ?2, ?3, ?4)",
+                vec![Value::Integer(id), name, price, data],
+            )
+            .await
+            .unwrap();
+    }
+
+    let mut chunks: Vec<Vec<String>> = Vec::new();
+    let completed = dump_table(&source, "odd \"items\"", 2, |chunk| {
+        chunks.push(chunk);
+        true
+    })
+    .await
+    .unwrap();
+
+    assert!(completed);
+    assert_eq!(chunks.iter().map(Vec::len).collect::<Vec<_>>(), vec![2, 1]);
+
+    let target = connect(&target_path).await;
+    target.execute(CREATE_ITEMS, ()).await.unwrap();
+    for statement in chunks.iter().flatten() {
+        target.execute(statement, ()).await.unwrap();
+    }
+
+    let query =
+        "SELECT id, name, price, typeof(price), data FROM \"odd \"\"items\"\"\" ORDER BY id";
+    let mut expected = source.query(query, ()).await.unwrap();
+    let mut actual = target.query(query, ()).await.unwrap();
+    let mut count = 0;
+    while let Some(expected_row) = expected.next().await.unwrap() {
+        let actual_row = actual.next().await.unwrap().unwrap();
+        for i in 0..5 {
+            assert_eq!(
+                expected_row.get_value(i).unwrap(),
+                actual_row.get_value(i).unwrap()
+            );
+        }
+        count += 1;
+    }
+    assert_eq!(count, 3);
+    assert!(actual.next().await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_dump_leaves_out_generated_columns() {
+    let db_path = setup_test_db_with_prefix("export_generated");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect(&db_path).await;
+
+    conn.execute(
+        "CREATE TABLE t (a INTEGER, doubled INTEGER AS (a * 2), b TEXT, \
+         upper_b TEXT AS (upper(b)) STORED)",
+        (),
+    )
+    .await
+    .unwrap();
+    conn.execute("INSERT INTO t (a, b) VALUES (1, 'x'), (2, 'y')", ())
+        .await
+        .unwrap();
+
+    let mut statements = Vec::new();
+    dump_table(&conn, "t", 10, |chunk| {
+        statements.extend(chunk);
+        true
+    })
+    .await
+    .unwrap();
+    assert_eq!(
+        statements,
+        vec![
+            "INSERT INTO \"t\" (\"a\", \"b\") VALUES (1, 'x');",
+            "INSERT INTO \"t\" (\"a\", \"b\") VALUES (2, 'y');",
+        ]
+    );
+
+    // Replaying recomputes the generated columns
+    conn.execute("DELETE FROM t", ()).await.unwrap();
+    for statement in &statements {
+        conn.execute(statement, ()).await.unwrap();
+    }
+    let mut rows = conn
+        .query("SELECT doubled, upper_b FROM t ORDER BY a", ())
+        .await
+        .unwrap();
+    let row = rows.next().await.unwrap().unwrap();
+    assert_eq!(row.get_value(0).unwrap(), Value::Integer(2));
+    assert_eq!(row.get_value(1).unwrap(), Value::Text("X".into()));
+}
+
+#[tokio::test]
+async fn test_dump_missing_table() {
+    let db_path = setup_test_db_with_prefix("export_missing");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect(&db_path).await;
+
+    let err = dump_table(&conn, "missing", 10, |_| true)
+        .await
+        .unwrap_err();
+    assert!(err.contains("Table not found"), "{err}");
+}
+
+#[tokio::test]
+async fn test_dump_stops_when_emit_returns_false() {
+    let db_path = setup_test_db_with_prefix("export_stop");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect(&db_path).await;
+
+    conn.execute("CREATE TABLE t (id INTEGER)", ())
+        .await
+        .unwrap();
+    conn.execute("INSERT INTO t VALUES (1), (2), (3), (4), (5)", ())
+        .await
+        .unwrap();
+
+    let mut calls = 0;
+    let completed = dump_table(&conn, "t", 1, |_| {
+        calls += 1;
+        false
+    })
+    .await
+    .unwrap();
+
+    assert!(!completed);
+    assert_eq!(calls, 1);
+}
+
+#[tokio::test]
+async fn test_dump_empty_table_emits_nothing() {
+    let db_path = setup_test_db_with_prefix("export_empty");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect(&db_path).await;
+
+    conn.execute("CREATE TABLE t (id INTEGER)", ())
+        .await
+        .unwrap();
+
+    let mut calls = 0;
+    let completed = dump_table(&conn, "t", 10, |_| {
+        calls += 1;
+        true
+    })
+    .await
+    .unwrap();
+
+    assert!(completed);
+    assert_eq!(calls, 0);
+}
+
+#[tokio::test]
+async fn test_dump_rejects_zero_chunk_size() {
+    let db_path = setup_test_db_with_prefix("export_zero");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect(&db_path).await;
+
+    conn.execute("CREATE TABLE t (id INTEGER)", ())
+        .await
+        .unwrap();
+
+    let result = dump_table(&conn, "t", 0, |_| true).await;
+    assert!(result.unwrap_err().contains("Chunk size"));
+}
+
+#[tokio::test]
+async fn test_resolve_table_name() {
+    let db_path = setup_test_db_with_prefix("export_resolve");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect(&db_path).await;
+
+    conn.execute("CREATE TABLE Users (id INTEGER)", ())
+        .await
+        .unwrap();
+
+    assert_eq!(resolve_table_name(&conn, "users").await.unwrap(), "Users");
+    assert!(resolve_table_name(&conn, "missing")
+        .await
+        .unwrap_err()
+        .contains("Table not found"));
+}
+
+#[tokio::test]
+async fn test_query_ndjson_renders_each_row() {
+    let db_path = setup_test_db_with_prefix("export_ndjson");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect(&db_path).await;
+
+    conn.execute(
+        "CREATE TABLE t (id INTEGER, score REAL, name TEXT, data BLOB)",
+        (),
+    )
+    .await
+    .unwrap();
+    conn.execute(
+        "INSERT INTO t VALUES (1, 2.5, 'a\"b', X'010203'), (2, NULL, NULL, NULL)",
+        (),
+    )
+    .await
+    .unwrap();
+
+    let out = query_ndjson(
+        &conn,
+        "SELECT * FROM t WHERE id >= ? ORDER BY id",
+        vec![Value::Integer(1)],
+        ColumnNaming::Raw,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(
+        out,
+        concat!(
+            r#"{"id":1,"score":2.5,"name":"a\"b","data":"AQID"}"#,
+            "\n",
+            r#"{"id":2,"score":null,"name":null,"data":null}"#,
+            "\n"
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_query_ndjson_empty_result_and_naming() {
+    let db_path = setup_test_db_with_prefix("export_ndjson_naming");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect(&db_path).await;
+
+    let empty = query_ndjson(&conn, "SELECT 1 WHERE 0", vec![], ColumnNaming::Raw)
+        .await
+        .unwrap();
+    assert_eq!(empty, "");
+
+    let out = query_ndjson(
+        &conn,
+        "SELECT 1 AS id, 2 AS id",
+        vec![],
+        ColumnNaming::IndexSuffix,
+    )
+    .await
+    .unwrap();
+    assert_eq!(out, "{\"id\":1,\"id_2\":2}\n");
+}
+
+#[test]
+fn test_frame_value_encoding() {
+    let mut frame = Vec::new();
+    for value in [
+        Value::Null,
+        Value::Integer(-2),
+        Value::Real(1.5),
+        Value::Text("hé".to_string()),
+        Value::Blob(vec![0, 255]),
+    ] {
+        push_frame_value(&mut frame, &value).unwrap();
+    }
+
+    let mut expected = vec![0, 1];
+    expected.extend_from_slice(&(-2i64).to_be_bytes());
+    expected.push(2);
+    expected.extend_from_slice(&1.5f64.to_be_bytes());
+    expected.extend_from_slice(&[3, 0, 0, 0, 3, b'h', 0xC3, 0xA9]);
+    expected.extend_from_slice(&[4, 0, 0, 0, 2, 0, 255]);
+    assert_eq!(frame, expected);
+}
+
+#[tokio::test]
+async fn test_query_frame_encodes_rows_in_order() {
+    let db_path = setup_test_db_with_prefix("export_frame");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect(&db_path).await;
+
+    conn.execute_batch(
+        "CREATE TABLE t (id INTEGER, name TEXT);
+         INSERT INTO t VALUES (1, 'a'), (2, NULL);",
+    )
+    .await
+    .unwrap();
+
+    let (columns, frame) = query_frame(
+        &conn,
+        "SELECT id, name FROM t WHERE id >= ? ORDER BY id",
+        vec![Value::Integer(1)],
+    )
+    .await
+    .unwrap();
+    assert_eq!(columns, vec!["id".to_string(), "name".to_string()]);
+
+    let mut expected = Vec::new();
+    for value in [
+        Value::Integer(1),
+        Value::Text("a".to_string()),
+        Value::Integer(2),
+        Value::Null,
+    ] {
+        push_frame_value(&mut expected, &value).unwrap();
+    }
+    assert_eq!(frame, expected);
+
+    let (_, empty) = query_frame(&conn, "SELECT * FROM t WHERE 0", vec![])
+        .await
+        .unwrap();
+    assert!(empty.is_empty());
+    assert!(query_frame(&conn, "SELECT * FROM missing", vec![])
+        .await
+        .is_err());
+}
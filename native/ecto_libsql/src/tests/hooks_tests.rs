@@ -0,0 +1,178 @@
+//! Tests for dirty table tracking and authorizer deny rules
+//!
+//! These tests install the recording update hook and the deny-rule authorizer directly
+//! on a real local database, without going through the NIF layer.
+
+// Allow unwrap() in tests for cleaner test code - see CLAUDE.md "Test Code Exception"
+#![allow(clippy::unwrap_used)]
+
+use super::test_utils::{setup_test_db_with_prefix, TestDbGuard};
+use crate::hooks::{install_deny_rules, record_dirty_tables};
+use crate::models::{AuthorizerAction, DenyRule};
+use libsql::{Builder, Connection};
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+
+fn taken(tables: &Arc<Mutex<HashSet<String>>>) -> Vec<String> {
+    let mut names: Vec<String> = std::mem::take(&mut *tables.lock().unwrap())
+        .into_iter()
+        .collect();
+    names.sort();
+    names
+}
+
+#[tokio::test]
+async fn test_writes_to_two_tables_are_recorded_then_cleared() {
+    let db_path = setup_test_db_with_prefix("dirty_tables");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let db = Builder::new_local(db_path.to_str().unwrap())
+        .build()
+        .await
+        .unwrap();
+    let conn = db.connect().unwrap();
+    conn.execute_batch(
+        "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT);
+         CREATE TABLE posts (id INTEGER PRIMARY KEY, title TEXT);
+         CREATE TABLE tags (id INTEGER PRIMARY KEY);",
+    )
+    .await
+    .unwrap();
+
+    let tables = Arc::new(Mutex::new(HashSet::new()));
+    record_dirty_tables(&conn, tables.clone()).unwrap();
+
+    // Reads are not recorded
+    conn.query("SELECT * FROM tags", ()).await.unwrap();
+    assert!(taken(&tables).is_empty());
+
+    conn.execute("INSERT INTO users (name) VALUES ('a'), ('b')", ())
+        .await
+        .unwrap();
+    conn.execute("INSERT INTO posts (title) VALUES ('x')", ())
+        .await
+        .unwrap();
+    conn.execute("UPDATE users SET name = 'c' WHERE id = 1", ())
+        .await
+        .unwrap();
+    assert_eq!(taken(&tables), vec!["posts", "users"]);
+
+    // Taking the set clears it
+    assert!(taken(&tables).is_empty());
+
+    conn.execute("DELETE FROM posts WHERE id = 1", ())
+        .await
+        .unwrap();
+    assert_eq!(taken(&tables), vec!["posts"]);
+}
+
+#[tokio::test]
+async fn test_temp_tables_are_qualified() {
+    let db_path = setup_test_db_with_prefix("dirty_tables_temp");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let db = Builder::new_local(db_path.to_str().unwrap())
+        .build()
+        .await
+        .unwrap();
+    let conn = db.connect().unwrap();
+    conn.execute("CREATE TEMP TABLE scratch (id INTEGER)", ())
+        .await
+        .unwrap();
+
+    let tables = Arc::new(Mutex::new(HashSet::new()));
+    record_dirty_tables(&conn, tables.clone()).unwrap();
+    conn.execute("INSERT INTO scratch VALUES (1)", ())
+        .await
+        .unwrap();
+
+    assert_eq!(taken(&tables), vec!["temp.scratch"]);
+}
+
+async fn connect_with_secrets(db_path: &std::path::Path) -> Connection {
+    let db = Builder::new_local(db_path.to_str().unwrap())
+        .build()
+        .await
+        .unwrap();
+    let conn = db.connect().unwrap();
+    conn.execute_batch(
+        "CREATE TABLE secrets (id INTEGER PRIMARY KEY, token TEXT);
+         CREATE TABLE posts (id INTEGER PRIMARY KEY, title TEXT);
+         INSERT INTO secrets VALUES (1, 's3cret');
+         INSERT INTO posts VALUES (1, 'hello');",
+    )
+    .await
+    .unwrap();
+    conn
+}
+
+fn deny(action: AuthorizerAction, target: Option<&str>) -> DenyRule {
+    DenyRule {
+        action,
+        target: target.map(str::to_string),
+    }
+}
+
+#[tokio::test]
+async fn test_denied_table_read_is_rejected_at_prepare() {
+    let db_path = setup_test_db_with_prefix("authorizer");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect_with_secrets(&db_path).await;
+
+    install_deny_rules(&conn, vec![deny(AuthorizerAction::Read, Some("SECRETS"))]).unwrap();
+
+    let err = match conn.prepare("SELECT token FROM secrets").await {
+        Ok(_) => String::from("prepared"),
+        Err(e) => e.to_string(),
+    };
+    assert!(
+        err.contains("access to secrets.token is prohibited"),
+        "unexpected result: {err}"
+    );
+
+    // Reads through a subquery are caught too
+    assert!(conn
+        .prepare("SELECT title FROM posts WHERE id IN (SELECT id FROM secrets)")
+        .await
+        .is_err());
+
+    // Other tables, and writes to the protected one, are still allowed
+    conn.prepare("SELECT title FROM posts").await.unwrap();
+    conn.execute("INSERT INTO secrets (token) VALUES ('new')", ())
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_pragma_write_and_attach_rules() {
+    let db_path = setup_test_db_with_prefix("authorizer");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect_with_secrets(&db_path).await;
+
+    install_deny_rules(
+        &conn,
+        vec![
+            deny(AuthorizerAction::PragmaWrite, None),
+            deny(AuthorizerAction::Attach, None),
+        ],
+    )
+    .unwrap();
+
+    assert!(conn.prepare("PRAGMA foreign_keys = ON").await.is_err());
+    conn.prepare("PRAGMA foreign_keys").await.unwrap();
+    assert!(conn
+        .execute("ATTACH DATABASE ':memory:' AS other", ())
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn test_empty_rules_remove_the_authorizer() {
+    let db_path = setup_test_db_with_prefix("authorizer");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect_with_secrets(&db_path).await;
+
+    install_deny_rules(&conn, vec![deny(AuthorizerAction::Read, None)]).unwrap();
+    assert!(conn.prepare("SELECT title FROM posts").await.is_err());
+
+    install_deny_rules(&conn, Vec::new()).unwrap();
+    conn.prepare("SELECT token FROM secrets").await.unwrap();
+}
@@ -330,3 +330,126 @@ async fn test_null_values() {
     let email_value = row.get_value(0).unwrap();
     assert!(matches!(email_value, Value::Null));
 }
+
+#[tokio::test]
+async fn test_on_conflict_do_nothing_reports_zero_affected() {
+    let db_path = setup_test_db();
+    let _guard = TestDbGuard::new(db_path.clone());
+
+    let db = Builder::new_local(db_path.to_str().unwrap())
+        .build()
+        .await
+        .unwrap();
+    let conn = db.connect().unwrap();
+
+    conn.execute("CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT)", ())
+        .await
+        .unwrap();
+
+    let upsert = "INSERT INTO users (id, name) VALUES (?1, ?2) ON CONFLICT (id) DO NOTHING";
+    let params = || vec![Value::Integer(1), Value::Text("Alice".to_string())];
+
+    // A fresh insert affects one row, a conflicting one must report zero rather
+    // than the previous statement's count.
+    assert_eq!(conn.execute(upsert, params()).await.unwrap(), 1);
+    assert_eq!(conn.execute(upsert, params()).await.unwrap(), 0);
+    assert_eq!(conn.changes(), 0);
+
+    // Same inside a transaction
+    let tx = conn.transaction().await.unwrap();
+    tx.execute(
+        "INSERT INTO users (id, name) VALUES (?1, ?2)",
+        vec![Value::Integer(2), Value::Text("Bob".to_string())],
+    )
+    .await
+    .unwrap();
+    assert_eq!(tx.execute(upsert, params()).await.unwrap(), 0);
+    tx.commit().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_self_join_column_names_can_be_deduplicated() {
+    use crate::models::ColumnNaming;
+    use crate::utils::{column_origin_tables, dedupe_column_names};
+
+    let db_path = setup_test_db();
+    let _guard = TestDbGuard::new(db_path.clone());
+
+    let db = Builder::new_local(db_path.to_str().unwrap())
+        .build()
+        .await
+        .unwrap();
+    let conn = db.connect().unwrap();
+    conn.execute(
+        "CREATE TABLE items (id INTEGER PRIMARY KEY, parent_id INTEGER)",
+        (),
+    )
+    .await
+    .unwrap();
+
+    let stmt = conn
+        .prepare("SELECT c.id, p.id FROM items c JOIN items p ON p.id = c.parent_id")
+        .await
+        .unwrap();
+    let raw: Vec<String> = stmt
+        .columns()
+        .iter()
+        .map(|c| c.name().to_string())
+        .collect();
+    assert_eq!(raw, vec!["id", "id"]);
+
+    let tables = column_origin_tables(&stmt);
+    assert_eq!(
+        tables,
+        vec![Some("items".to_string()), Some("items".to_string())]
+    );
+
+    assert_eq!(
+        dedupe_column_names(&raw, &tables, ColumnNaming::IndexSuffix),
+        vec!["id", "id_2"]
+    );
+    assert_eq!(
+        dedupe_column_names(&raw, &tables, ColumnNaming::TablePrefix),
+        vec!["items.id", "items.id_2"]
+    );
+}
+
+#[tokio::test]
+async fn test_origin_tables_come_from_the_executed_statement() {
+    use crate::models::ColumnNaming;
+    use crate::utils::query_with_origin_tables;
+
+    let db_path = setup_test_db();
+    let _guard = TestDbGuard::new(db_path.clone());
+
+    let db = Builder::new_local(db_path.to_str().unwrap())
+        .build()
+        .await
+        .unwrap();
+    let conn = db.connect().unwrap();
+    conn.execute("CREATE TABLE items (id INTEGER PRIMARY KEY)", ())
+        .await
+        .unwrap();
+    conn.execute("INSERT INTO items (id) VALUES (7)", ())
+        .await
+        .unwrap();
+
+    let sql = "SELECT id, 1 FROM items WHERE id = ?1";
+    let (mut rows, tables) = query_with_origin_tables(
+        &conn,
+        sql,
+        vec![Value::Integer(7)],
+        ColumnNaming::TablePrefix,
+    )
+    .await
+    .unwrap();
+    assert_eq!(tables, vec![Some("items".to_string()), None]);
+    let row = rows.next().await.unwrap().unwrap();
+    assert_eq!(row.get::<i64>(0).unwrap(), 7);
+
+    let (_, tables) =
+        query_with_origin_tables(&conn, sql, vec![Value::Integer(7)], ColumnNaming::Raw)
+            .await
+            .unwrap();
+    assert!(tables.is_empty());
+}
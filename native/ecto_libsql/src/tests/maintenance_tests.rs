@@ -0,0 +1,453 @@
+//! Tests for database maintenance helpers
+//!
+//! These tests exercise the maintenance helpers directly against a real local
+//! database, without going through the NIF layer.
+
+// Allow unwrap() in tests for cleaner test code - see CLAUDE.md "Test Code Exception"
+#![allow(clippy::unwrap_used)]
+
+use super::test_utils::{connect, setup_test_db_with_prefix, TestDbGuard};
+use crate::maintenance::{
+    database_size, duplicate_keys, migrate_with_foreign_keys_off, set_autocheckpoint, set_spill,
+    sync_autoincrement_sequence, vacuum_measured, wal_file,
+};
+use crate::models::CacheSpill;
+use libsql::{Connection, Value};
+
+async fn query_i64(conn: &Connection, sql: &str) -> i64 {
+    let mut rows = conn.query(sql, ()).await.unwrap();
+    rows.next().await.unwrap().unwrap().get::<i64>(0).unwrap()
+}
+
+#[tokio::test]
+async fn test_sync_sequence_raises_lagging_sequence() {
+    let db_path = setup_test_db_with_prefix("maintenance");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect(&db_path).await;
+
+    conn.execute(
+        "CREATE TABLE items (id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT)",
+        (),
+    )
+    .await
+    .unwrap();
+    conn.execute(
+        "INSERT INTO items (id, name) VALUES (?1, ?2), (?3, ?4)",
+        vec![
+            Value::Integer(500),
+            Value::Text("a".to_string()),
+            Value::Integer(1000),
+            Value::Text("b".to_string()),
+        ],
+    )
+    .await
+    .unwrap();
+    // Simulate a sequence left behind by an external import
+    conn.execute(
+        "UPDATE sqlite_sequence SET seq = 1 WHERE name = 'items'",
+        (),
+    )
+    .await
+    .unwrap();
+
+    let seq = sync_autoincrement_sequence(&conn, "items").await.unwrap();
+    assert_eq!(seq, Some(1000));
+    assert_eq!(
+        query_i64(
+            &conn,
+            "SELECT seq FROM sqlite_sequence WHERE name = 'items'"
+        )
+        .await,
+        1000
+    );
+
+    conn.execute("INSERT INTO items (name) VALUES ('c')", ())
+        .await
+        .unwrap();
+    assert_eq!(conn.last_insert_rowid(), 1001);
+}
+
+#[tokio::test]
+async fn test_sync_sequence_never_lowers_sequence() {
+    let db_path = setup_test_db_with_prefix("maintenance");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect(&db_path).await;
+
+    conn.execute(
+        "CREATE TABLE items (id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT)",
+        (),
+    )
+    .await
+    .unwrap();
+    conn.execute("INSERT INTO items (id, name) VALUES (50, 'a')", ())
+        .await
+        .unwrap();
+    conn.execute("DELETE FROM items", ()).await.unwrap();
+
+    let seq = sync_autoincrement_sequence(&conn, "items").await.unwrap();
+    assert_eq!(seq, Some(50));
+}
+
+#[tokio::test]
+async fn test_sync_sequence_is_noop_without_autoincrement() {
+    let db_path = setup_test_db_with_prefix("maintenance");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect(&db_path).await;
+
+    conn.execute("CREATE TABLE plain (id INTEGER PRIMARY KEY, name TEXT)", ())
+        .await
+        .unwrap();
+    conn.execute("INSERT INTO plain (id, name) VALUES (10, 'a')", ())
+        .await
+        .unwrap();
+
+    let seq = sync_autoincrement_sequence(&conn, "plain").await.unwrap();
+    assert_eq!(seq, None);
+
+    // Mentions of the keyword in comments and literals don't make a table AUTOINCREMENT
+    conn.execute(
+        "CREATE TABLE mentions (
+            id INTEGER PRIMARY KEY, -- not AUTOINCREMENT
+            note TEXT DEFAULT 'AUTOINCREMENT'
+        )",
+        (),
+    )
+    .await
+    .unwrap();
+    let seq = sync_autoincrement_sequence(&conn, "mentions")
+        .await
+        .unwrap();
+    assert_eq!(seq, None);
+}
+
+#[tokio::test]
+async fn test_sync_sequence_unknown_table_errors() {
+    let db_path = setup_test_db_with_prefix("maintenance");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect(&db_path).await;
+
+    let result = sync_autoincrement_sequence(&conn, "missing").await;
+    assert!(result.unwrap_err().contains("Table not found"));
+}
+
+#[tokio::test]
+async fn test_vacuum_reports_reclaimed_space() {
+    let db_path = setup_test_db_with_prefix("maintenance");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect(&db_path).await;
+
+    conn.execute("CREATE TABLE docs (id INTEGER PRIMARY KEY, body BLOB)", ())
+        .await
+        .unwrap();
+    for _ in 0..200 {
+        conn.execute(
+            "INSERT INTO docs (body) VALUES (?1)",
+            vec![Value::Blob(vec![7u8; 4096])],
+        )
+        .await
+        .unwrap();
+    }
+    conn.execute("DELETE FROM docs WHERE id > 10", ())
+        .await
+        .unwrap();
+
+    let (before, after) = vacuum_measured(&conn).await.unwrap();
+    assert!(before > after, "expected {before} > {after}");
+    assert_eq!(after, database_size(&conn).await.unwrap());
+}
+
+#[tokio::test]
+async fn test_vacuum_fails_inside_transaction() {
+    let db_path = setup_test_db_with_prefix("maintenance");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect(&db_path).await;
+
+    conn.execute("BEGIN", ()).await.unwrap();
+    let err = vacuum_measured(&conn).await.unwrap_err();
+    assert!(err.starts_with("Vacuum failed"), "unexpected error: {err}");
+}
+
+async fn seed_parent_child(conn: &Connection) {
+    conn.execute_batch(
+        "PRAGMA foreign_keys = ON;
+         CREATE TABLE authors (id INTEGER PRIMARY KEY, name TEXT);
+         CREATE TABLE books (
+             id INTEGER PRIMARY KEY,
+             author_id INTEGER REFERENCES authors (id),
+             price TEXT
+         );
+         INSERT INTO authors VALUES (1, 'a'), (2, 'b');
+         INSERT INTO books VALUES (1, 1, '9.5'), (2, 2, '12');",
+    )
+    .await
+    .unwrap();
+}
+
+fn recreate_books_with(price_type: &str) -> Vec<String> {
+    vec![
+        format!(
+            "CREATE TABLE books_new (
+                 id INTEGER PRIMARY KEY,
+                 author_id INTEGER REFERENCES authors (id),
+                 price {price_type}
+             )"
+        ),
+        "INSERT INTO books_new SELECT id, author_id, CAST(price AS REAL) FROM books".to_string(),
+        "DROP TABLE books".to_string(),
+        "ALTER TABLE books_new RENAME TO books".to_string(),
+    ]
+}
+
+#[tokio::test]
+async fn test_migrate_changes_column_type_by_recreating_table() {
+    let db_path = setup_test_db_with_prefix("migrate_table");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect(&db_path).await;
+    seed_parent_child(&conn).await;
+
+    let violations = migrate_with_foreign_keys_off(&conn, &recreate_books_with("REAL"))
+        .await
+        .unwrap();
+    assert!(violations.is_empty());
+
+    assert_eq!(
+        query_i64(
+            &conn,
+            "SELECT count(*) FROM pragma_table_info('books') WHERE name = 'price' AND type = 'REAL'"
+        )
+        .await,
+        1
+    );
+    assert_eq!(
+        query_i64(
+            &conn,
+            "SELECT count(*) FROM books WHERE typeof(price) = 'real'"
+        )
+        .await,
+        2
+    );
+    // Enforcement is back on and the transaction is closed
+    assert_eq!(query_i64(&conn, "PRAGMA foreign_keys").await, 1);
+    assert!(conn.is_autocommit());
+}
+
+#[tokio::test]
+async fn test_migrate_rolls_back_on_foreign_key_violations() {
+    let db_path = setup_test_db_with_prefix("migrate_table_fk");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect(&db_path).await;
+    seed_parent_child(&conn).await;
+
+    let mut statements = recreate_books_with("REAL");
+    statements.push("DELETE FROM authors WHERE id = 2".to_string());
+
+    let violations = migrate_with_foreign_keys_off(&conn, &statements)
+        .await
+        .unwrap();
+    assert_eq!(violations.len(), 1);
+    assert_eq!(violations[0].table, "books");
+    assert_eq!(violations[0].rowid, Some(2));
+    assert_eq!(violations[0].parent, "authors");
+
+    // Nothing was applied
+    assert_eq!(
+        query_i64(
+            &conn,
+            "SELECT count(*) FROM pragma_table_info('books') WHERE name = 'price' AND type = 'TEXT'"
+        )
+        .await,
+        1
+    );
+    assert_eq!(query_i64(&conn, "SELECT count(*) FROM authors").await, 2);
+    assert_eq!(query_i64(&conn, "PRAGMA foreign_keys").await, 1);
+    assert!(conn.is_autocommit());
+}
+
+#[tokio::test]
+async fn test_migrate_restores_settings_when_a_statement_fails() {
+    let db_path = setup_test_db_with_prefix("migrate_table_fail");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect(&db_path).await;
+    seed_parent_child(&conn).await;
+
+    let statements = vec![
+        "DROP TABLE books".to_string(),
+        "SELECT * FROM missing".to_string(),
+    ];
+    let result = migrate_with_foreign_keys_off(&conn, &statements).await;
+    assert!(result.unwrap_err().contains("Statement 1 failed"));
+
+    assert_eq!(query_i64(&conn, "SELECT count(*) FROM books").await, 2);
+    assert_eq!(query_i64(&conn, "PRAGMA foreign_keys").await, 1);
+    assert!(conn.is_autocommit());
+}
+
+#[tokio::test]
+async fn test_migrate_refuses_inside_transaction() {
+    let db_path = setup_test_db_with_prefix("migrate_table_trx");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect(&db_path).await;
+    seed_parent_child(&conn).await;
+
+    conn.execute("BEGIN", ()).await.unwrap();
+    let result = migrate_with_foreign_keys_off(&conn, &recreate_books_with("REAL")).await;
+    assert!(result.is_err());
+    conn.execute("ROLLBACK", ()).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_wal_autocheckpoint_round_trips() {
+    let db_path = setup_test_db_with_prefix("wal_autocheckpoint");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect(&db_path).await;
+
+    assert_eq!(set_autocheckpoint(&conn, 250).await.unwrap(), 250);
+    assert_eq!(set_autocheckpoint(&conn, 0).await.unwrap(), 0);
+
+    let error = set_autocheckpoint(&conn, -1).await.unwrap_err();
+    assert!(error.contains("non-negative"));
+    // A rejected value leaves the setting alone
+    let mut rows = conn.query("PRAGMA wal_autocheckpoint", ()).await.unwrap();
+    let row = rows.next().await.unwrap().unwrap();
+    assert_eq!(row.get::<i64>(0).unwrap(), 0);
+}
+
+#[tokio::test]
+async fn test_set_spill_toggles_and_sets_threshold() {
+    let db_path = setup_test_db_with_prefix("cache_spill");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect(&db_path).await;
+    conn.execute("PRAGMA cache_size = 100", ()).await.unwrap();
+
+    assert_eq!(
+        set_spill(&conn, CacheSpill::Enabled(false)).await.unwrap(),
+        0
+    );
+    assert_eq!(query_i64(&conn, "PRAGMA cache_spill").await, 0);
+
+    // Thresholds above the cache size are reported as set
+    assert_eq!(
+        set_spill(&conn, CacheSpill::Threshold(5000)).await.unwrap(),
+        5000
+    );
+    assert_eq!(set_spill(&conn, CacheSpill::Threshold(0)).await.unwrap(), 0);
+    assert_eq!(
+        set_spill(&conn, CacheSpill::Enabled(true)).await.unwrap(),
+        5000
+    );
+
+    let error = set_spill(&conn, CacheSpill::Threshold(-1))
+        .await
+        .unwrap_err();
+    assert!(error.contains("between 0 and"));
+    assert_eq!(query_i64(&conn, "PRAGMA cache_spill").await, 5000);
+}
+
+#[tokio::test]
+async fn test_duplicate_keys_reports_composite_groups() {
+    let db_path = setup_test_db_with_prefix("maint_duplicates");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect(&db_path).await;
+
+    conn.execute_batch(
+        "CREATE TABLE \"user accounts\" (id INTEGER PRIMARY KEY, org INTEGER, email TEXT);
+         INSERT INTO \"user accounts\" (org, email) VALUES
+           (1, 'a@x'), (1, 'a@x'), (1, 'a@x'),
+           (1, 'b@x'), (1, 'b@x'),
+           (2, 'a@x'),
+           (1, NULL), (1, NULL);",
+    )
+    .await
+    .unwrap();
+
+    let columns = vec!["org".to_string(), "email".to_string()];
+    let duplicates = duplicate_keys(&conn, "user accounts", &columns)
+        .await
+        .unwrap();
+    assert_eq!(
+        duplicates,
+        vec![
+            (vec![Value::Integer(1), Value::Text("a@x".into())], 3),
+            (vec![Value::Integer(1), Value::Text("b@x".into())], 2),
+        ]
+    );
+
+    // Once the duplicates are gone the index can be created
+    conn.execute(
+        "DELETE FROM \"user accounts\" WHERE id NOT IN \
+         (SELECT min(id) FROM \"user accounts\" GROUP BY org, email)",
+        (),
+    )
+    .await
+    .unwrap();
+    assert!(duplicate_keys(&conn, "user accounts", &columns)
+        .await
+        .unwrap()
+        .is_empty());
+    conn.execute(
+        "CREATE UNIQUE INDEX accounts_org_email ON \"user accounts\" (org, email)",
+        (),
+    )
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn test_duplicate_keys_rejects_bad_input() {
+    let db_path = setup_test_db_with_prefix("maint_duplicates_bad");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect(&db_path).await;
+
+    conn.execute("CREATE TABLE t (a INTEGER)", ())
+        .await
+        .unwrap();
+
+    assert!(duplicate_keys(&conn, "t", &[]).await.is_err());
+    // Unknown columns must not fall back to string literals
+    assert!(duplicate_keys(&conn, "t", &["missing".to_string()])
+        .await
+        .unwrap_err()
+        .contains("Column not found"));
+    assert!(duplicate_keys(&conn, "missing", &["a".to_string()])
+        .await
+        .unwrap_err()
+        .contains("Table not found"));
+}
+
+#[tokio::test]
+async fn test_wal_file_none_outside_wal_mode() {
+    let db_path = setup_test_db_with_prefix("maintenance");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect(&db_path).await;
+
+    conn.execute("CREATE TABLE t (x INTEGER)", ())
+        .await
+        .unwrap();
+
+    assert_eq!(wal_file(&conn).await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn test_wal_file_grows_until_checkpoint() {
+    let db_path = setup_test_db_with_prefix("maintenance");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect(&db_path).await;
+
+    conn.query("PRAGMA journal_mode = WAL", ()).await.unwrap();
+    conn.query("PRAGMA wal_autocheckpoint = 0", ())
+        .await
+        .unwrap();
+    conn.execute("CREATE TABLE t (x BLOB)", ()).await.unwrap();
+
+    let (path, before) = wal_file(&conn).await.unwrap().unwrap();
+    assert_eq!(path, format!("{}-wal", db_path.display()));
+
+    for _ in 0..20 {
+        conn.execute("INSERT INTO t (x) VALUES (zeroblob(4096))", ())
+            .await
+            .unwrap();
+    }
+
+    let (_, after) = wal_file(&conn).await.unwrap().unwrap();
+    assert!(after > before, "WAL grew from {before} to {after} bytes");
+}
@@ -0,0 +1,32 @@
+//! Tests for process-wide memory settings
+//!
+//! These settings are shared by every connection in the process, so each test restores
+//! what it changes.
+
+// Allow unwrap() in tests for cleaner test code - see CLAUDE.md "Test Code Exception"
+#![allow(clippy::unwrap_used)]
+
+use crate::memory::global_pragma;
+
+#[tokio::test]
+async fn test_soft_heap_limit_round_trip() {
+    let previous = global_pragma("PRAGMA soft_heap_limit").await.unwrap();
+
+    let limit = 64 * 1024 * 1024;
+    global_pragma(&format!("PRAGMA soft_heap_limit = {limit}"))
+        .await
+        .unwrap();
+    // The limit is process-wide, so a fresh connection sees it.
+    assert_eq!(
+        global_pragma("PRAGMA soft_heap_limit").await.unwrap(),
+        limit
+    );
+
+    global_pragma(&format!("PRAGMA soft_heap_limit = {previous}"))
+        .await
+        .unwrap();
+    assert_eq!(
+        global_pragma("PRAGMA soft_heap_limit").await.unwrap(),
+        previous
+    );
+}
@@ -0,0 +1,408 @@
+//! Tests for metadata helpers
+//!
+//! These tests exercise column and index introspection, row counts, pragma snapshots, write lock
+//! probes, threading mode, transaction state checks and attached database listing directly
+//! against a real local database, without going through the NIF layer.
+
+// Allow unwrap() in tests for cleaner test code - see CLAUDE.md "Test Code Exception"
+#![allow(clippy::unwrap_used)]
+
+use super::test_utils::{setup_test_db_with_prefix, TestDbGuard};
+use crate::constants::{CONNECTION_REGISTRY, TXN_REGISTRY};
+use crate::metadata::{
+    column_details, compiled_threadsafe, indexes_with_column, list_databases, pragma_settings,
+    probe_write_lock, row_counts, transaction_flags, SNAPSHOT_PRAGMAS,
+};
+use crate::models::{ColumnDetail, ColumnNaming, Generated, LibSQLConn, Mode, TransactionEntry};
+use libsql::{Builder, Value};
+use std::sync::{Arc, Mutex};
+
+#[tokio::test]
+async fn test_column_details_include_generated_columns() {
+    let db_path = setup_test_db_with_prefix("column_details");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let db = Builder::new_local(db_path.to_str().unwrap())
+        .build()
+        .await
+        .unwrap();
+    let conn = db.connect().unwrap();
+    conn.execute(
+        "CREATE TABLE items (
+            id INTEGER PRIMARY KEY,
+            price REAL NOT NULL DEFAULT 0.0,
+            qty INTEGER DEFAULT (1 + 1),
+            total REAL GENERATED ALWAYS AS (price * qty) VIRTUAL,
+            \"Label\" TEXT AS (upper(printf('%s, x', id))) STORED
+        )",
+        (),
+    )
+    .await
+    .unwrap();
+
+    let columns = column_details(&conn, "ITEMS").await.unwrap();
+
+    let column = |name: &str, column_type: &str, default: Option<&str>| ColumnDetail {
+        name: name.to_string(),
+        column_type: column_type.to_string(),
+        default: default.map(str::to_string),
+        generated: None,
+    };
+    assert_eq!(
+        columns,
+        vec![
+            column("id", "INTEGER", None),
+            column("price", "REAL", Some("0.0")),
+            column("qty", "INTEGER", Some("1 + 1")),
+            ColumnDetail {
+                generated: Some(Generated {
+                    stored: false,
+                    expression: Some("price * qty".to_string()),
+                }),
+                ..column("total", "REAL", None)
+            },
+            ColumnDetail {
+                generated: Some(Generated {
+                    stored: true,
+                    expression: Some("upper(printf('%s, x', id))".to_string()),
+                }),
+                ..column("Label", "TEXT", None)
+            },
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_column_details_unknown_table() {
+    let db_path = setup_test_db_with_prefix("column_details_missing");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let db = Builder::new_local(db_path.to_str().unwrap())
+        .build()
+        .await
+        .unwrap();
+    let conn = db.connect().unwrap();
+
+    let err = column_details(&conn, "missing").await.unwrap_err();
+    assert!(err.contains("Table not found"));
+}
+
+#[tokio::test]
+async fn test_transaction_flags_detect_raw_begin_and_registry_transactions() {
+    let db_path = setup_test_db_with_prefix("transaction_flags");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let path = db_path.to_str().unwrap();
+    let db = Builder::new_local(path).build().await.unwrap();
+    let conn = db.connect().unwrap();
+    let client = Arc::new(Mutex::new(conn.clone()));
+    CONNECTION_REGISTRY.lock().unwrap().insert(
+        "txn-flags".to_string(),
+        Arc::new(Mutex::new(LibSQLConn {
+            db,
+            client,
+            mode: Mode::Local,
+            column_naming: ColumnNaming::Raw,
+        })),
+    );
+
+    assert_eq!(transaction_flags("txn-flags").unwrap(), (true, false));
+
+    // A raw BEGIN bypasses the registry
+    conn.execute("BEGIN", ()).await.unwrap();
+    assert_eq!(transaction_flags("txn-flags").unwrap(), (false, false));
+    conn.execute("ROLLBACK", ()).await.unwrap();
+
+    // A registry transaction is reflected in both
+    let transaction = conn.transaction().await.unwrap();
+    TXN_REGISTRY.lock().unwrap().insert(
+        "txn-flags-trx".to_string(),
+        TransactionEntry {
+            conn_id: "txn-flags".to_string(),
+            transaction,
+            savepoints: Vec::new(),
+            restore_busy_timeout_ms: None,
+        },
+    );
+    assert_eq!(transaction_flags("txn-flags").unwrap(), (false, true));
+
+    // Committing behind the registry's back leaves it holding a finished transaction
+    conn.execute("COMMIT", ()).await.unwrap();
+    assert_eq!(transaction_flags("txn-flags").unwrap(), (true, true));
+
+    TXN_REGISTRY.lock().unwrap().remove("txn-flags-trx");
+    CONNECTION_REGISTRY.lock().unwrap().remove("txn-flags");
+}
+
+async fn seed_row_count_tables(conn: &libsql::Connection) {
+    conn.execute_batch(
+        "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT);
+         CREATE TABLE \"order items\" (id INTEGER PRIMARY KEY AUTOINCREMENT, sku TEXT);
+         CREATE TABLE empty (id INTEGER);
+         CREATE INDEX order_items_sku ON \"order items\" (sku);
+         INSERT INTO users (name) VALUES ('a'), ('b'), ('c');
+         INSERT INTO \"order items\" (sku) VALUES ('x'), ('y'), ('z'), ('x'), ('y');",
+    )
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn test_row_counts_are_exact_for_user_tables() {
+    let db_path = setup_test_db_with_prefix("row_counts");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let db = Builder::new_local(db_path.to_str().unwrap())
+        .build()
+        .await
+        .unwrap();
+    let conn = db.connect().unwrap();
+    seed_row_count_tables(&conn).await;
+
+    let counts = row_counts(&conn, false).await.unwrap();
+
+    // sqlite_sequence exists because of AUTOINCREMENT, but is internal.
+    assert_eq!(counts.len(), 3);
+    assert_eq!(counts["users"], 3);
+    assert_eq!(counts["order items"], 5);
+    assert_eq!(counts["empty"], 0);
+}
+
+#[tokio::test]
+async fn test_approximate_row_counts_use_statistics() {
+    let db_path = setup_test_db_with_prefix("row_counts_approx");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let db = Builder::new_local(db_path.to_str().unwrap())
+        .build()
+        .await
+        .unwrap();
+    let conn = db.connect().unwrap();
+    seed_row_count_tables(&conn).await;
+
+    // Without statistics, approximate counts fall back to exact ones.
+    let counts = row_counts(&conn, true).await.unwrap();
+    assert_eq!(counts["order items"], 5);
+
+    conn.execute("ANALYZE", ()).await.unwrap();
+    conn.execute("INSERT INTO users (name) VALUES ('d')", ())
+        .await
+        .unwrap();
+
+    // The estimate still reflects the table as it was analysed.
+    let counts = row_counts(&conn, true).await.unwrap();
+    assert_eq!(counts.len(), 3);
+    assert_eq!(counts["users"], 3);
+    assert_eq!(counts["order items"], 5);
+    assert_eq!(counts["empty"], 0);
+
+    let counts = row_counts(&conn, false).await.unwrap();
+    assert_eq!(counts["users"], 4);
+}
+
+#[tokio::test]
+async fn test_pragma_settings_reflect_configured_values() {
+    let db_path = setup_test_db_with_prefix("pragma_snapshot");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let db = Builder::new_local(db_path.to_str().unwrap())
+        .build()
+        .await
+        .unwrap();
+    let conn = db.connect().unwrap();
+    conn.execute_batch(
+        "PRAGMA journal_mode = WAL;
+         PRAGMA foreign_keys = ON;
+         PRAGMA cache_size = -4000;
+         PRAGMA wal_autocheckpoint = 500;",
+    )
+    .await
+    .unwrap();
+
+    let settings = pragma_settings(&conn).await.unwrap();
+    let names: Vec<&str> = settings.iter().map(|(name, _)| *name).collect();
+    assert_eq!(names, SNAPSHOT_PRAGMAS);
+
+    let value = |name: &str| {
+        settings
+            .iter()
+            .find(|(pragma, _)| *pragma == name)
+            .map(|(_, value)| value.clone())
+            .unwrap()
+    };
+    assert_eq!(value("journal_mode"), Value::Text("wal".to_string()));
+    assert_eq!(value("foreign_keys"), Value::Integer(1));
+    assert_eq!(value("cache_size"), Value::Integer(-4000));
+    assert_eq!(value("wal_autocheckpoint"), Value::Integer(500));
+    assert!(matches!(value("page_size"), Value::Integer(n) if n > 0));
+}
+
+#[tokio::test]
+async fn test_probe_write_lock_sees_another_connections_lock() {
+    let db_path = setup_test_db_with_prefix("write_lock_probe");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let db = Builder::new_local(db_path.to_str().unwrap())
+        .build()
+        .await
+        .unwrap();
+    let prober = db.connect().unwrap();
+    let holder = db.connect().unwrap();
+    prober
+        .execute("CREATE TABLE t (id INTEGER PRIMARY KEY)", ())
+        .await
+        .unwrap();
+    prober
+        .busy_timeout(std::time::Duration::from_millis(2000))
+        .unwrap();
+
+    assert!(probe_write_lock(&prober).await.unwrap());
+    assert!(prober.is_autocommit());
+
+    holder.execute("BEGIN IMMEDIATE", ()).await.unwrap();
+    let started = std::time::Instant::now();
+    assert!(!probe_write_lock(&prober).await.unwrap());
+    // The probe doesn't wait out the busy timeout, and leaves no transaction open
+    assert!(started.elapsed() < std::time::Duration::from_millis(1000));
+    assert!(prober.is_autocommit());
+    holder.execute("ROLLBACK", ()).await.unwrap();
+
+    assert!(probe_write_lock(&prober).await.unwrap());
+
+    let mut rows = prober.query("PRAGMA busy_timeout", ()).await.unwrap();
+    let row = rows.next().await.unwrap().unwrap();
+    assert_eq!(row.get::<i64>(0).unwrap(), 2000);
+}
+
+#[tokio::test]
+async fn test_probe_write_lock_refuses_inside_transaction() {
+    let db_path = setup_test_db_with_prefix("write_lock_probe_txn");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let db = Builder::new_local(db_path.to_str().unwrap())
+        .build()
+        .await
+        .unwrap();
+    let conn = db.connect().unwrap();
+
+    conn.execute("BEGIN", ()).await.unwrap();
+    assert!(probe_write_lock(&conn).await.is_err());
+    assert!(!conn.is_autocommit());
+    conn.execute("ROLLBACK", ()).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_compiled_threadsafe_reports_a_known_mode() {
+    let db_path = setup_test_db_with_prefix("threading_mode");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let db = Builder::new_local(db_path.to_str().unwrap())
+        .build()
+        .await
+        .unwrap();
+    let conn = db.connect().unwrap();
+
+    // libsql builds SQLite with SQLITE_THREADSAFE=1 (serialized)
+    assert_eq!(compiled_threadsafe(&conn).await.unwrap(), Some(1));
+}
+
+#[tokio::test]
+async fn test_indexes_with_column() {
+    let db_path = setup_test_db_with_prefix("column_indexes");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let db = Builder::new_local(db_path.to_str().unwrap())
+        .build()
+        .await
+        .unwrap();
+    let conn = db.connect().unwrap();
+    conn.execute_batch(
+        r#"CREATE TABLE "order items" (id INTEGER PRIMARY KEY, sku TEXT, qty INTEGER, note TEXT);
+           CREATE INDEX "order items_sku" ON "order items" (sku);
+           CREATE INDEX order_items_sku_qty ON "order items" (sku, qty);
+           CREATE TABLE tags (name TEXT PRIMARY KEY, n INTEGER) WITHOUT ROWID;"#,
+    )
+    .await
+    .unwrap();
+
+    let (indexed, mut indexes) = indexes_with_column(&conn, "order items", "SKU")
+        .await
+        .unwrap();
+    indexes.sort();
+    assert!(indexed);
+    assert_eq!(
+        indexes,
+        vec![
+            ("order items_sku".to_string(), 0),
+            ("order_items_sku_qty".to_string(), 0)
+        ]
+    );
+
+    // Only a non-leading column of a composite index
+    let (indexed, indexes) = indexes_with_column(&conn, "order items", "qty")
+        .await
+        .unwrap();
+    assert!(!indexed);
+    assert_eq!(indexes, vec![("order_items_sku_qty".to_string(), 1)]);
+
+    let (indexed, indexes) = indexes_with_column(&conn, "order items", "note")
+        .await
+        .unwrap();
+    assert!(!indexed);
+    assert!(indexes.is_empty());
+
+    // The rowid alias is indexed by the table itself
+    let (indexed, indexes) = indexes_with_column(&conn, "order items", "id")
+        .await
+        .unwrap();
+    assert!(indexed);
+    assert!(indexes.is_empty());
+
+    // A WITHOUT ROWID primary key is an index of its own
+    let (indexed, indexes) = indexes_with_column(&conn, "tags", "name").await.unwrap();
+    assert!(indexed);
+    assert_eq!(indexes.len(), 1);
+    assert_eq!(indexes[0].1, 0);
+
+    assert!(indexes_with_column(&conn, "order items", "missing")
+        .await
+        .unwrap_err()
+        .contains("Column not found"));
+    assert!(indexes_with_column(&conn, "missing", "id")
+        .await
+        .unwrap_err()
+        .contains("Table not found"));
+}
+
+#[tokio::test]
+async fn test_list_databases_includes_attached() {
+    let db_path = setup_test_db_with_prefix("database_list");
+    let shard_a = setup_test_db_with_prefix("database_list_a");
+    let shard_b = setup_test_db_with_prefix("database_list_b");
+    let _guards = [
+        TestDbGuard::new(db_path.clone()),
+        TestDbGuard::new(shard_a.clone()),
+        TestDbGuard::new(shard_b.clone()),
+    ];
+    let db = Builder::new_local(db_path.to_str().unwrap())
+        .build()
+        .await
+        .unwrap();
+    let conn = db.connect().unwrap();
+
+    for (path, name) in [(&shard_a, "shard_a"), (&shard_b, "shard_b")] {
+        conn.execute(
+            &format!("ATTACH DATABASE ?1 AS {name}"),
+            vec![Value::Text(path.to_str().unwrap().to_string())],
+        )
+        .await
+        .unwrap();
+    }
+
+    let databases = list_databases(&conn).await.unwrap();
+    let path = |p: &std::path::PathBuf| Some(p.to_str().unwrap().to_string());
+    assert_eq!(
+        databases,
+        vec![
+            (0, "main".to_string(), path(&db_path)),
+            (2, "shard_a".to_string(), path(&shard_a)),
+            (3, "shard_b".to_string(), path(&shard_b)),
+        ]
+    );
+
+    conn.execute("ATTACH DATABASE ':memory:' AS scratch", ())
+        .await
+        .unwrap();
+    let databases = list_databases(&conn).await.unwrap();
+    assert_eq!(databases.last(), Some(&(4, "scratch".to_string(), None)));
+}
@@ -3,9 +3,27 @@
 //! This module organizes all tests for the NIF implementation into logical submodules
 //! that correspond to the main library modules.
 
+mod arrow_tests;
+mod batch_tests;
+mod blob_tests;
+mod connection_tests;
 mod constants_tests;
+mod counter_tests;
+mod cursor_tests;
+mod deadline_tests;
 mod error_handling_tests;
+mod export_tests;
+mod hooks_tests;
 mod integration_tests;
+mod maintenance_tests;
+mod memory_tests;
+mod metadata_tests;
+mod plan_tests;
 mod proptest_tests;
+mod query_tests;
+mod replication_tests;
+mod savepoint_tests;
+mod statement_tests;
 mod test_utils;
+mod transaction_tests;
 mod utils_tests;
@@ -0,0 +1,392 @@
+//! Tests for query plan analysis
+//!
+//! These tests cover interpreting `EXPLAIN QUERY PLAN` detail lines, both from
+//! fixed strings and from plans produced by a real local database, finding the
+//! tables a write statement would scan, suggesting indexes for scanned tables, and
+//! timing repeated runs of a query.
+
+// Allow unwrap() in tests for cleaner test code - see CLAUDE.md "Test Code Exception"
+#![allow(clippy::unwrap_used)]
+
+use super::test_utils::{connect, setup_test_db_with_prefix, TestDbGuard};
+use crate::metadata::stat1_row_estimates;
+use crate::plan::{
+    benchmark_runs, create_index_sql, filter_columns, index_suggestions, parse_plan_detail,
+    query_plan_details, scanned_tables, summarise_timings, table_aliases, BenchmarkStats,
+    FilterColumn, TableAccess,
+};
+use libsql::{Connection, Value};
+
+fn access(detail: &str) -> Option<(String, TableAccess)> {
+    parse_plan_detail(detail)
+}
+
+#[test]
+fn test_parse_scan() {
+    assert_eq!(
+        access("SCAN users"),
+        Some(("users".to_string(), TableAccess::Scan))
+    );
+    assert_eq!(
+        access("SCAN TABLE users"),
+        Some(("users".to_string(), TableAccess::Scan))
+    );
+    assert_eq!(
+        access("SCAN users USING COVERING INDEX users_email"),
+        Some(("users".to_string(), TableAccess::Scan))
+    );
+}
+
+#[test]
+fn test_parse_search() {
+    assert_eq!(
+        access("SEARCH users USING INDEX users_email (email=?)"),
+        Some((
+            "users".to_string(),
+            TableAccess::Index("users_email".to_string())
+        ))
+    );
+    assert_eq!(
+        access("SEARCH users AS u USING COVERING INDEX users_email (email=?)"),
+        Some((
+            "users".to_string(),
+            TableAccess::Index("users_email".to_string())
+        ))
+    );
+    assert_eq!(
+        access("SEARCH users USING INTEGER PRIMARY KEY (rowid=?)"),
+        Some(("users".to_string(), TableAccess::PrimaryKey))
+    );
+    assert_eq!(
+        access("SEARCH t USING AUTOMATIC COVERING INDEX (a=?)"),
+        Some(("t".to_string(), TableAccess::Scan))
+    );
+}
+
+#[test]
+fn test_parse_ignores_non_table_steps() {
+    assert_eq!(access("USE TEMP B-TREE FOR ORDER BY"), None);
+    assert_eq!(access("SCAN CONSTANT ROW"), None);
+    assert_eq!(access("SCAN (subquery-1)"), None);
+    assert_eq!(access("COMPOUND QUERY"), None);
+}
+
+async fn connect_with_schema(db_path: &std::path::Path) -> Connection {
+    let conn = connect(db_path).await;
+    conn.execute(
+        "CREATE TABLE users (id INTEGER PRIMARY KEY, email TEXT, name TEXT)",
+        (),
+    )
+    .await
+    .unwrap();
+    conn.execute("CREATE INDEX users_email ON users (email)", ())
+        .await
+        .unwrap();
+    conn.execute(
+        "CREATE TABLE posts (id INTEGER PRIMARY KEY, user_id INTEGER, title TEXT)",
+        (),
+    )
+    .await
+    .unwrap();
+    conn
+}
+
+async fn plan(conn: &Connection, sql: &str, params: Vec<Value>) -> Vec<(String, TableAccess)> {
+    query_plan_details(conn, sql, params)
+        .await
+        .unwrap()
+        .iter()
+        .filter_map(|d| parse_plan_detail(d))
+        .collect()
+}
+
+#[tokio::test]
+async fn test_indexed_lookup_vs_scan() {
+    let db_path = setup_test_db_with_prefix("plan");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect_with_schema(&db_path).await;
+
+    assert_eq!(
+        plan(
+            &conn,
+            "SELECT * FROM users WHERE email = ?",
+            vec![Value::Text("a@example.com".to_string())]
+        )
+        .await,
+        vec![(
+            "users".to_string(),
+            TableAccess::Index("users_email".to_string())
+        )]
+    );
+    assert_eq!(
+        plan(
+            &conn,
+            "SELECT * FROM users WHERE name = ?",
+            vec![Value::Text("a".to_string())]
+        )
+        .await,
+        vec![("users".to_string(), TableAccess::Scan)]
+    );
+    assert_eq!(
+        plan(&conn, "SELECT * FROM users WHERE id = 1", vec![]).await,
+        vec![("users".to_string(), TableAccess::PrimaryKey)]
+    );
+}
+
+#[tokio::test]
+async fn test_multi_table_plan() {
+    let db_path = setup_test_db_with_prefix("plan");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect_with_schema(&db_path).await;
+
+    let steps = plan(
+        &conn,
+        "SELECT u.name, p.title FROM posts p JOIN users u ON u.id = p.user_id",
+        vec![],
+    )
+    .await;
+
+    // Aliased tables are reported by alias, which keeps self-joins distinguishable
+    assert_eq!(
+        steps,
+        vec![
+            ("p".to_string(), TableAccess::Scan),
+            ("u".to_string(), TableAccess::PrimaryKey)
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_unindexed_delete_reports_scan() {
+    let db_path = setup_test_db_with_prefix("plan");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect_with_schema(&db_path).await;
+
+    let details = query_plan_details(
+        &conn,
+        "DELETE FROM users WHERE name = ?",
+        vec![Value::Text("a".to_string())],
+    )
+    .await
+    .unwrap();
+    assert_eq!(scanned_tables(&details), vec!["users".to_string()]);
+
+    let details = query_plan_details(
+        &conn,
+        "UPDATE users SET name = 'b' WHERE email = ?",
+        vec![Value::Text("a@example.com".to_string())],
+    )
+    .await
+    .unwrap();
+    assert!(scanned_tables(&details).is_empty());
+
+    // Nothing written by planning the statement
+    let mut rows = conn.query("SELECT count(*) FROM users", ()).await.unwrap();
+    let count: i64 = rows.next().await.unwrap().unwrap().get(0).unwrap();
+    assert_eq!(count, 0);
+}
+
+#[tokio::test]
+async fn test_stat1_row_estimates_after_analyze() {
+    let db_path = setup_test_db_with_prefix("plan");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect_with_schema(&db_path).await;
+
+    assert!(stat1_row_estimates(&conn).await.unwrap().is_empty());
+
+    for i in 0..25 {
+        conn.execute(
+            "INSERT INTO users (email, name) VALUES (?1, ?1)",
+            vec![Value::Text(format!("user{i}@example.com"))],
+        )
+        .await
+        .unwrap();
+    }
+    conn.execute("ANALYZE", ()).await.unwrap();
+
+    let estimates = stat1_row_estimates(&conn).await.unwrap();
+    assert_eq!(estimates.get("users"), Some(&25));
+}
+
+#[test]
+fn test_summarise_timings() {
+    let mut timings: Vec<u64> = (1..=20).rev().collect();
+    assert_eq!(
+        summarise_timings(&mut timings),
+        Some(BenchmarkStats {
+            min_us: 1,
+            max_us: 20,
+            mean_us: 10,
+            p95_us: 19,
+        })
+    );
+    assert_eq!(summarise_timings(&mut [7]).unwrap().p95_us, 7);
+    assert_eq!(summarise_timings(&mut []), None);
+}
+
+#[tokio::test]
+async fn test_benchmark_runs_reports_ordered_stats() {
+    let db_path = setup_test_db_with_prefix("benchmark");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect_with_schema(&db_path).await;
+
+    let stats = benchmark_runs(
+        &conn,
+        "SELECT count(*) FROM users WHERE name > ?1",
+        vec![Value::Text("a".to_string())],
+        25,
+    )
+    .await
+    .unwrap()
+    .unwrap();
+
+    assert!(stats.min_us <= stats.mean_us);
+    assert!(stats.mean_us <= stats.max_us);
+    assert!(stats.min_us <= stats.p95_us && stats.p95_us <= stats.max_us);
+
+    assert!(benchmark_runs(&conn, "SELECT * FROM missing", vec![], 3)
+        .await
+        .is_err());
+}
+
+fn filter(qualifier: Option<&str>, column: &str, equality: bool) -> FilterColumn {
+    FilterColumn {
+        qualifier: qualifier.map(str::to_string),
+        column: column.to_string(),
+        equality,
+    }
+}
+
+#[test]
+fn test_filter_columns() {
+    assert_eq!(
+        filter_columns("SELECT * FROM t WHERE a = ? AND ? < t.b AND \"C\" IN (1, 2)"),
+        vec![
+            filter(None, "a", true),
+            filter(Some("t"), "b", false),
+            filter(None, "C", true),
+        ]
+    );
+    assert_eq!(
+        filter_columns("SELECT * FROM p JOIN u ON u.id = p.user_id WHERE p.n BETWEEN 1 AND 5"),
+        vec![
+            filter(Some("u"), "id", true),
+            filter(Some("p"), "user_id", true),
+            filter(Some("p"), "n", false),
+        ]
+    );
+
+    // Assignments, selected expressions, function calls and negations are not filters
+    assert_eq!(
+        filter_columns(
+            "UPDATE t SET a = 1 WHERE lower(b) = 'x' AND c IS NOT NULL AND d NOT IN (1)"
+        ),
+        vec![]
+    );
+    assert_eq!(
+        filter_columns("SELECT a = 1 FROM t WHERE b = (SELECT max(c) FROM u) AND d > 2"),
+        vec![filter(None, "b", true), filter(None, "d", false)]
+    );
+}
+
+#[test]
+fn test_table_aliases() {
+    assert_eq!(
+        table_aliases("SELECT * FROM main.posts AS p, tags JOIN users u ON u.id = p.user_id"),
+        vec![
+            ("p".to_string(), "posts".to_string()),
+            ("tags".to_string(), "tags".to_string()),
+            ("u".to_string(), "users".to_string()),
+        ]
+    );
+    assert_eq!(
+        table_aliases("SELECT * FROM (SELECT * FROM t) s, json_each(?) WHERE s.a = 1"),
+        vec![("t".to_string(), "t".to_string())]
+    );
+}
+
+#[test]
+fn test_create_index_sql() {
+    assert_eq!(
+        create_index_sql("users", &["org_id".to_string(), "email".to_string()]),
+        r#"CREATE INDEX "users_org_id_email_index" ON "users" ("org_id", "email")"#
+    );
+}
+
+#[tokio::test]
+async fn test_index_suggestions_for_unindexed_filter() {
+    let db_path = setup_test_db_with_prefix("suggest");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect_with_schema(&db_path).await;
+
+    let suggestions = index_suggestions(
+        &conn,
+        "SELECT * FROM users WHERE name = ?",
+        vec![Value::Text("a".to_string())],
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        suggestions,
+        vec![r#"CREATE INDEX "users_name_index" ON "users" ("name")"#.to_string()]
+    );
+
+    // The suggestion really does replace the scan
+    conn.execute(&suggestions[0], ()).await.unwrap();
+    let steps = plan(&conn, "SELECT * FROM users WHERE name = 'a'", vec![]).await;
+    assert_eq!(
+        steps,
+        vec![(
+            "users".to_string(),
+            TableAccess::Index("users_name_index".to_string())
+        )]
+    );
+}
+
+#[tokio::test]
+async fn test_index_suggestions_for_joins_and_ranges() {
+    let db_path = setup_test_db_with_prefix("suggest");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect_with_schema(&db_path).await;
+
+    assert_eq!(
+        index_suggestions(
+            &conn,
+            "SELECT u.name, p.title FROM posts p JOIN users u ON u.id = p.user_id",
+            vec![],
+        )
+        .await
+        .unwrap(),
+        vec![r#"CREATE INDEX "posts_user_id_index" ON "posts" ("user_id")"#.to_string()]
+    );
+
+    // Equality columns come before the range column, whatever order they're written in
+    assert_eq!(
+        index_suggestions(
+            &conn,
+            "SELECT * FROM posts WHERE title > 'm' AND user_id = 3",
+            vec![],
+        )
+        .await
+        .unwrap(),
+        vec![
+            r#"CREATE INDEX "posts_user_id_title_index" ON "posts" ("user_id", "title")"#
+                .to_string()
+        ]
+    );
+
+    // Indexed lookups and unfiltered scans get no suggestion
+    assert!(
+        index_suggestions(&conn, "SELECT * FROM users WHERE email = 'a'", vec![])
+            .await
+            .unwrap()
+            .is_empty()
+    );
+    assert!(index_suggestions(&conn, "SELECT * FROM posts", vec![])
+        .await
+        .unwrap()
+        .is_empty());
+    assert!(index_suggestions(&conn, "SELECT * FROM missing", vec![])
+        .await
+        .is_err());
+}
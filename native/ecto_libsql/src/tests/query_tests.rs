@@ -0,0 +1,849 @@
+//! Tests for query helpers
+//!
+//! These tests exercise fetching rows by id, keyset pagination, capturing affected
+//! rowids, numeric text coercion, datetime normalisation, index-mapped parameters, column
+//! nullability, compare-and-execute, point-in-time multi-query reads, row fingerprints,
+//! column transform resolution, conflict clause change counts, queries on an attached
+//! database and the read-only guard used by `query_multi` directly against a real local
+//! database, without going through the NIF layer.
+
+// Allow unwrap() in tests for cleaner test code - see CLAUDE.md "Test Code Exception"
+#![allow(clippy::unwrap_used)]
+
+use super::test_utils::{setup_test_db_with_prefix, TestDbGuard};
+use crate::models::{ColumnNaming, ColumnTransform};
+use crate::query::{
+    begin_read_snapshot, compare_and_execute_in, consistent_read_in, end_read_snapshot,
+    execute_capturing, fetch_coercing_numbers, fetch_converting, fetch_keyset_page,
+    fetch_rows_by_ids, fetch_with_nullability, fingerprint_first_row, precheck_matches,
+    qualify_for_database, resolve_transforms, set_query_only, CompareOutcome, IDS_PER_QUERY,
+};
+use crate::utils::{coerce_numeric_text, normalise_datetime_text, place_indexed_params};
+use libsql::{Builder, Connection, Value};
+
+async fn connect_with_items(db_path: &std::path::Path, count: i64) -> Connection {
+    let db = Builder::new_local(db_path.to_str().unwrap())
+        .build()
+        .await
+        .unwrap();
+    let conn = db.connect().unwrap();
+    conn.execute("CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT)", ())
+        .await
+        .unwrap();
+    conn.execute(
+        "WITH RECURSIVE n(x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM n WHERE x < ?1)
+         INSERT INTO items (id, name) SELECT x, 'item ' || x FROM n",
+        vec![Value::Integer(count)],
+    )
+    .await
+    .unwrap();
+    conn
+}
+
+#[tokio::test]
+async fn test_fetch_rows_by_ids_returns_only_matches() {
+    let db_path = setup_test_db_with_prefix("fetch_by_ids");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect_with_items(&db_path, 5).await;
+
+    let ids = vec![
+        Value::Integer(1),
+        Value::Integer(3),
+        Value::Integer(5),
+        Value::Integer(42),
+    ];
+    let (columns, mut rows) = fetch_rows_by_ids(&conn, "items", "id", &ids).await.unwrap();
+    rows.sort_by_key(|(id, _)| match id {
+        Value::Integer(i) => *i,
+        _ => 0,
+    });
+
+    assert_eq!(columns, vec!["id", "name"]);
+    assert_eq!(
+        rows,
+        vec![
+            (
+                Value::Integer(1),
+                vec![Value::Integer(1), Value::Text("item 1".to_string())]
+            ),
+            (
+                Value::Integer(3),
+                vec![Value::Integer(3), Value::Text("item 3".to_string())]
+            ),
+            (
+                Value::Integer(5),
+                vec![Value::Integer(5), Value::Text("item 5".to_string())]
+            ),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_fetch_rows_by_ids_merges_chunks() {
+    let db_path = setup_test_db_with_prefix("fetch_by_ids");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let total = (IDS_PER_QUERY * 2 + 10) as i64;
+    let conn = connect_with_items(&db_path, total).await;
+
+    let ids: Vec<Value> = (1..=total).map(Value::Integer).collect();
+    let (_columns, rows) = fetch_rows_by_ids(&conn, "items", "id", &ids).await.unwrap();
+
+    assert_eq!(rows.len() as i64, total);
+}
+
+#[tokio::test]
+async fn test_fetch_rows_by_ids_unknown_column_fails() {
+    let db_path = setup_test_db_with_prefix("fetch_by_ids");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect_with_items(&db_path, 1).await;
+
+    let err = fetch_rows_by_ids(&conn, "items", "nope", &[Value::Integer(1)])
+        .await
+        .unwrap_err();
+    assert!(err.contains("Failed to fetch rows"), "{err}");
+}
+
+async fn temp_objects(conn: &Connection) -> i64 {
+    let mut rows = conn
+        .query("SELECT count(*) FROM sqlite_temp_master", ())
+        .await
+        .unwrap();
+    rows.next().await.unwrap().unwrap().get(0).unwrap()
+}
+
+#[tokio::test]
+async fn test_execute_capturing_multi_row_update() {
+    let db_path = setup_test_db_with_prefix("capture_rowids");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect_with_items(&db_path, 10).await;
+
+    let rowids = execute_capturing(
+        &conn,
+        "UPDATE items SET name = 'even' WHERE id % 2 = ?1",
+        vec![Value::Integer(0)],
+    )
+    .await
+    .unwrap();
+    let mut sorted = rowids.clone();
+    sorted.sort_unstable();
+    assert_eq!(sorted, vec![2, 4, 6, 8, 10]);
+    assert_eq!(temp_objects(&conn).await, 0);
+}
+
+#[tokio::test]
+async fn test_execute_capturing_insert_and_delete() {
+    let db_path = setup_test_db_with_prefix("capture_rowids");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect_with_items(&db_path, 3).await;
+
+    let inserted = execute_capturing(
+        &conn,
+        "INSERT INTO main.\"items\" (name) VALUES ('a'), ('b')",
+        vec![],
+    )
+    .await
+    .unwrap();
+    assert_eq!(inserted, vec![4, 5]);
+
+    let deleted = execute_capturing(&conn, "DELETE FROM items WHERE id <= 2", vec![])
+        .await
+        .unwrap();
+    assert_eq!(deleted, vec![1, 2]);
+
+    let none = execute_capturing(&conn, "UPDATE items SET name = 'x' WHERE id > 100", vec![])
+        .await
+        .unwrap();
+    assert!(none.is_empty());
+}
+
+#[tokio::test]
+async fn test_execute_capturing_upsert_captures_updated_rows() {
+    let db_path = setup_test_db_with_prefix("capture_rowids");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect_with_items(&db_path, 3).await;
+
+    let rowids = execute_capturing(
+        &conn,
+        "INSERT INTO items (id, name) VALUES (2, 'x'), (7, 'y') \
+         ON CONFLICT (id) DO UPDATE SET name = excluded.name",
+        vec![],
+    )
+    .await
+    .unwrap();
+    assert_eq!(rowids, vec![2, 7]);
+    assert_eq!(temp_objects(&conn).await, 0);
+}
+
+#[tokio::test]
+async fn test_execute_capturing_cleans_up_on_error() {
+    let db_path = setup_test_db_with_prefix("capture_rowids");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect_with_items(&db_path, 3).await;
+    conn.execute("CREATE UNIQUE INDEX items_name ON items (name)", ())
+        .await
+        .unwrap();
+
+    let err = execute_capturing(&conn, "UPDATE items SET name = 'same'", vec![])
+        .await
+        .unwrap_err();
+    assert!(err.contains("UNIQUE"), "unexpected error: {err}");
+    assert_eq!(temp_objects(&conn).await, 0);
+
+    let err = execute_capturing(&conn, "SELECT * FROM items", vec![])
+        .await
+        .unwrap_err();
+    assert!(err.contains("INSERT, UPDATE or DELETE"));
+}
+
+#[tokio::test]
+async fn test_keyset_pages_cover_every_row_once() {
+    let db_path = setup_test_db_with_prefix("keyset_page");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect_with_items(&db_path, 25).await;
+    // Insert out of key order and delete some rows, leaving gaps in the keys
+    conn.execute_batch(
+        "INSERT INTO items (id, name) VALUES (100, 'late'), (50, 'middle');
+         DELETE FROM items WHERE id IN (3, 10, 11);",
+    )
+    .await
+    .unwrap();
+
+    let base = "SELECT name, id FROM items WHERE name <> 'item 7'";
+    let mut seen = Vec::new();
+    let mut after = None;
+    let mut pages = 0;
+    loop {
+        let (columns, page, last_key) = fetch_keyset_page(&conn, base, "id", after, 4)
+            .await
+            .unwrap();
+        assert_eq!(columns, vec!["name", "id"]);
+        assert!(page.len() <= 4);
+        let Some(last_key) = last_key else {
+            assert!(page.is_empty());
+            break;
+        };
+        seen.extend(page.iter().map(|row| row[1].clone()));
+        assert_eq!(last_key, page.last().unwrap()[1]);
+        after = Some(last_key);
+        pages += 1;
+    }
+
+    let expected: Vec<Value> = (1..=25)
+        .filter(|id| ![3, 7, 10, 11].contains(id))
+        .chain([50, 100])
+        .map(Value::Integer)
+        .collect();
+    assert_eq!(seen, expected);
+    assert_eq!(pages, expected.len().div_ceil(4));
+}
+
+#[tokio::test]
+async fn test_keyset_page_rejects_unknown_key_column() {
+    let db_path = setup_test_db_with_prefix("keyset_page");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect_with_items(&db_path, 3).await;
+
+    let result = fetch_keyset_page(&conn, "SELECT id FROM items", "missing", None, 10).await;
+    assert!(result.unwrap_err().contains("no such column"));
+}
+
+#[tokio::test]
+async fn test_query_only_rejects_writes_and_restores() {
+    let db_path = setup_test_db_with_prefix("query_only");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect_with_items(&db_path, 3).await;
+
+    let previous = set_query_only(&conn, true).await.unwrap();
+    assert!(!previous);
+
+    let mut rows = conn.query("SELECT count(*) FROM items", ()).await.unwrap();
+    let count: i64 = rows.next().await.unwrap().unwrap().get(0).unwrap();
+    assert_eq!(count, 3);
+    drop(rows);
+
+    let write = conn
+        .execute("INSERT INTO items (name) VALUES ('extra')", ())
+        .await;
+    assert!(write.is_err());
+
+    let was_on = set_query_only(&conn, previous).await.unwrap();
+    assert!(was_on);
+    conn.execute("INSERT INTO items (name) VALUES ('extra')", ())
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_numbers_stored_as_text_round_trip() {
+    let db_path = setup_test_db_with_prefix("coerce_numbers");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let db = Builder::new_local(db_path.to_str().unwrap())
+        .build()
+        .await
+        .unwrap();
+    let conn = db.connect().unwrap();
+
+    // Numeric affinity converts numeric text on insert, so legacy data like this only
+    // arises when declared types change under existing rows: store the values in TEXT
+    // columns, then retype the columns in place.
+    conn.execute("CREATE TABLE legacy (code TEXT, qty TEXT, price TEXT)", ())
+        .await
+        .unwrap();
+    conn.execute(
+        "INSERT INTO legacy VALUES ('007', '12', 'n/a'), ('1', '42', '9.5')",
+        (),
+    )
+    .await
+    .unwrap();
+    conn.execute_batch(
+        "PRAGMA writable_schema = ON;
+         UPDATE sqlite_master
+            SET sql = 'CREATE TABLE legacy (code TEXT, qty INTEGER, price REAL)'
+          WHERE name = 'legacy';
+         PRAGMA writable_schema = OFF;",
+    )
+    .await
+    .unwrap();
+    drop(conn);
+    let conn = db.connect().unwrap();
+
+    let (columns, _, rows) = fetch_coercing_numbers(
+        &conn,
+        "SELECT code, qty, price, qty || '' AS expr FROM legacy ORDER BY rowid",
+        vec![],
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(columns, vec!["code", "qty", "price", "expr"]);
+    assert_eq!(
+        rows,
+        vec![
+            vec![
+                Value::Text("007".to_string()),
+                Value::Integer(12),
+                Value::Text("n/a".to_string()),
+                Value::Text("12".to_string()),
+            ],
+            vec![
+                Value::Text("1".to_string()),
+                Value::Integer(42),
+                Value::Real(9.5),
+                Value::Text("42".to_string()),
+            ],
+        ]
+    );
+
+    // Without coercion the values read back as stored
+    let mut plain = conn
+        .query("SELECT qty FROM legacy ORDER BY rowid", ())
+        .await
+        .unwrap();
+    let qty = plain.next().await.unwrap().unwrap().get_value(0).unwrap();
+    assert_eq!(qty, Value::Text("12".to_string()));
+}
+
+#[tokio::test]
+async fn test_indexed_params_bind_out_of_order() {
+    let db_path = setup_test_db_with_prefix("indexed_params");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect_with_items(&db_path, 0).await;
+
+    let sql = "SELECT ?2 AS second, ?1 AS first, ?2 || ?1 AS joined";
+    let param_count = conn.prepare(sql).await.unwrap().parameter_count();
+    assert_eq!(param_count, 2);
+
+    // ?2 is given before ?1, and ?2 is used twice
+    let params = place_indexed_params(
+        param_count,
+        vec![
+            (2, Value::Text("b".to_string())),
+            (1, Value::Text("a".to_string())),
+        ],
+    )
+    .unwrap();
+    let mut rows = conn.query(sql, params).await.unwrap();
+    let row = rows.next().await.unwrap().unwrap();
+    assert_eq!(row.get::<String>(0).unwrap(), "b");
+    assert_eq!(row.get::<String>(1).unwrap(), "a");
+    assert_eq!(row.get::<String>(2).unwrap(), "ba");
+
+    // Sparse bindings leave the rest NULL
+    let params = place_indexed_params(param_count, vec![(2, Value::Integer(7))]).unwrap();
+    let mut rows = conn.query(sql, params).await.unwrap();
+    let row = rows.next().await.unwrap().unwrap();
+    assert_eq!(row.get::<i64>(0).unwrap(), 7);
+    assert_eq!(row.get_value(1).unwrap(), Value::Null);
+}
+
+#[tokio::test]
+async fn test_nullability_from_table_schema() {
+    let db_path = setup_test_db_with_prefix("nullability");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect_with_items(&db_path, 0).await;
+    conn.execute_batch(
+        "CREATE TABLE people (
+             id INTEGER PRIMARY KEY,
+             name TEXT NOT NULL,
+             nickname TEXT
+         );
+         CREATE TABLE tags (code TEXT PRIMARY KEY, label TEXT) WITHOUT ROWID;
+         INSERT INTO people (name, nickname) VALUES ('Ada', NULL);
+         INSERT INTO tags VALUES ('a', 'A');",
+    )
+    .await
+    .unwrap();
+
+    let fetched = fetch_with_nullability(
+        &conn,
+        "SELECT id, name, nickname AS nick, upper(name), count(*) FROM people",
+        vec![],
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        fetched.columns,
+        vec!["id", "name", "nick", "upper(name)", "count(*)"]
+    );
+    assert_eq!(
+        fetched.nullable,
+        vec![Some(false), Some(false), Some(true), None, None]
+    );
+    assert_eq!(fetched.rows.len(), 1);
+    assert_eq!(fetched.rows[0][2], Value::Null);
+
+    let fetched = fetch_with_nullability(&conn, "SELECT code, label FROM tags", vec![])
+        .await
+        .unwrap();
+    assert_eq!(fetched.nullable, vec![Some(false), Some(true)]);
+}
+
+#[tokio::test]
+async fn test_compare_and_execute_runs_update_on_match() {
+    let db_path = setup_test_db_with_prefix("compare_match");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect_with_items(&db_path, 2).await;
+
+    let outcome = compare_and_execute_in(
+        &conn,
+        "SELECT name FROM items WHERE id = ?",
+        vec![Value::Integer(1)],
+        &Value::Text("item 1".to_string()),
+        "UPDATE items SET name = ? WHERE id = ?",
+        vec![Value::Text("renamed".to_string()), Value::Integer(1)],
+    )
+    .await
+    .unwrap();
+    assert_eq!(outcome, CompareOutcome::Applied(1));
+    assert!(conn.is_autocommit());
+
+    let mut rows = conn
+        .query("SELECT name FROM items WHERE id = 1", ())
+        .await
+        .unwrap();
+    let row = rows.next().await.unwrap().unwrap();
+    assert_eq!(row.get::<String>(0).unwrap(), "renamed");
+}
+
+#[tokio::test]
+async fn test_compare_and_execute_skips_update_on_mismatch() {
+    let db_path = setup_test_db_with_prefix("compare_mismatch");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect_with_items(&db_path, 2).await;
+
+    let outcome = compare_and_execute_in(
+        &conn,
+        "SELECT count(*) FROM items",
+        vec![],
+        &Value::Integer(5),
+        "DELETE FROM items",
+        vec![],
+    )
+    .await
+    .unwrap();
+    assert_eq!(outcome, CompareOutcome::Mismatch(Value::Integer(2)));
+    assert!(conn.is_autocommit());
+
+    // No row compares as NULL
+    let outcome = compare_and_execute_in(
+        &conn,
+        "SELECT name FROM items WHERE id = 99",
+        vec![],
+        &Value::Text("x".to_string()),
+        "DELETE FROM items",
+        vec![],
+    )
+    .await
+    .unwrap();
+    assert_eq!(outcome, CompareOutcome::Mismatch(Value::Null));
+
+    let mut rows = conn.query("SELECT count(*) FROM items", ()).await.unwrap();
+    let row = rows.next().await.unwrap().unwrap();
+    assert_eq!(row.get::<i64>(0).unwrap(), 2);
+}
+
+#[tokio::test]
+async fn test_compare_and_execute_inside_transaction_uses_savepoint() {
+    let db_path = setup_test_db_with_prefix("compare_nested");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect_with_items(&db_path, 1).await;
+
+    conn.execute("BEGIN", ()).await.unwrap();
+    let outcome = compare_and_execute_in(
+        &conn,
+        "SELECT id FROM items WHERE id = 1",
+        vec![],
+        &Value::Real(1.0),
+        "DELETE FROM items WHERE id = 1",
+        vec![],
+    )
+    .await
+    .unwrap();
+    assert_eq!(outcome, CompareOutcome::Applied(1));
+    // The outer transaction is still open
+    assert!(!conn.is_autocommit());
+    conn.execute("ROLLBACK", ()).await.unwrap();
+
+    let mut rows = conn.query("SELECT count(*) FROM items", ()).await.unwrap();
+    let row = rows.next().await.unwrap().unwrap();
+    assert_eq!(row.get::<i64>(0).unwrap(), 1);
+}
+
+#[test]
+fn test_precheck_matches_compares_numbers_numerically() {
+    assert!(precheck_matches(&Value::Integer(3), &Value::Real(3.0)));
+    assert!(precheck_matches(&Value::Null, &Value::Null));
+    assert!(!precheck_matches(&Value::Null, &Value::Integer(0)));
+    assert!(!precheck_matches(
+        &Value::Text("3".to_string()),
+        &Value::Integer(3)
+    ));
+}
+
+#[tokio::test]
+async fn test_fetch_converting_normalises_mixed_datetime_text() {
+    let db_path = setup_test_db_with_prefix("normalise_datetimes");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect_with_items(&db_path, 0).await;
+    conn.execute_batch(
+        "CREATE TABLE events (id INTEGER PRIMARY KEY, at DATETIME, note TEXT);
+         INSERT INTO events (at, note) VALUES
+           ('2024-03-01 09:30:00', '2024-03-01 09:30:00'),
+           ('2024-03-01T10:15:30.250', 'second');",
+    )
+    .await
+    .unwrap();
+
+    let (columns, _, rows) = fetch_converting(
+        &conn,
+        "SELECT at, note FROM events ORDER BY id",
+        vec![],
+        normalise_datetime_text,
+        None,
+    )
+    .await
+    .unwrap()
+    .unwrap();
+
+    assert_eq!(columns, vec!["at", "note"]);
+    assert_eq!(
+        rows,
+        vec![
+            vec![
+                Value::Text("2024-03-01T09:30:00".to_string()),
+                // TEXT columns keep their raw text
+                Value::Text("2024-03-01 09:30:00".to_string()),
+            ],
+            vec![
+                Value::Text("2024-03-01T10:15:30.250".to_string()),
+                Value::Text("second".to_string()),
+            ],
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_fetch_converting_stops_past_max_bytes() {
+    let db_path = setup_test_db_with_prefix("converting_budget");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect_with_items(&db_path, 100).await;
+
+    // Each row is an 8-byte integer plus an "item N" string of at least 6 bytes
+    let over = fetch_converting(
+        &conn,
+        "SELECT id, name FROM items ORDER BY id",
+        vec![],
+        coerce_numeric_text,
+        Some(50),
+    )
+    .await
+    .unwrap();
+    assert!(matches!(over, Err(bytes) if bytes > 50 && bytes < 80));
+
+    let (_, _, rows) = fetch_converting(
+        &conn,
+        "SELECT id, name FROM items ORDER BY id",
+        vec![],
+        coerce_numeric_text,
+        Some(10_000),
+    )
+    .await
+    .unwrap()
+    .unwrap();
+    assert_eq!(rows.len(), 100);
+}
+
+async fn count_items(conn: &Connection) -> i64 {
+    let mut rows = conn.query("SELECT count(*) FROM items", ()).await.unwrap();
+    rows.next().await.unwrap().unwrap().get(0).unwrap()
+}
+
+#[tokio::test]
+async fn test_read_snapshot_ignores_concurrent_writes() {
+    let db_path = setup_test_db_with_prefix("consistent_read");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let reader = connect_with_items(&db_path, 5).await;
+    reader.query("PRAGMA journal_mode = WAL", ()).await.unwrap();
+
+    let writer = Builder::new_local(db_path.to_str().unwrap())
+        .build()
+        .await
+        .unwrap()
+        .connect()
+        .unwrap();
+
+    assert!(begin_read_snapshot(&reader).await.unwrap());
+    assert_eq!(count_items(&reader).await, 5);
+
+    // A concurrent writer commits in the middle of the read
+    writer
+        .execute(
+            "INSERT INTO items (id, name) VALUES (6, 'item 6'), (7, 'item 7')",
+            (),
+        )
+        .await
+        .unwrap();
+    writer
+        .execute("DELETE FROM items WHERE id = 1", ())
+        .await
+        .unwrap();
+
+    assert_eq!(count_items(&reader).await, 5);
+    let bounds = {
+        let mut rows = reader
+            .query("SELECT min(id), max(id) FROM items", ())
+            .await
+            .unwrap();
+        let row = rows.next().await.unwrap().unwrap();
+        (row.get::<i64>(0).unwrap(), row.get::<i64>(1).unwrap())
+    };
+    assert_eq!(bounds, (1, 5));
+
+    end_read_snapshot(&reader, true).await.unwrap();
+    assert!(reader.is_autocommit());
+    assert_eq!(count_items(&reader).await, 6);
+    assert_eq!(count_items(&writer).await, 6);
+}
+
+#[tokio::test]
+async fn test_consistent_read_returns_results_in_order() {
+    let db_path = setup_test_db_with_prefix("consistent_read");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect_with_items(&db_path, 3).await;
+
+    let results = consistent_read_in(
+        &conn,
+        vec![
+            ("SELECT count(*) AS n FROM items".to_string(), vec![]),
+            (
+                "SELECT name FROM items WHERE id = ?1".to_string(),
+                vec![Value::Integer(2)],
+            ),
+        ],
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].0, vec!["n"]);
+    assert_eq!(results[0].2, vec![vec![Value::Integer(3)]]);
+    assert_eq!(results[1].2, vec![vec![Value::Text("item 2".to_string())]]);
+    assert!(conn.is_autocommit());
+}
+
+#[tokio::test]
+async fn test_consistent_read_rolls_back_on_failure() {
+    let db_path = setup_test_db_with_prefix("consistent_read");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect_with_items(&db_path, 3).await;
+
+    let result = consistent_read_in(
+        &conn,
+        vec![
+            ("SELECT count(*) FROM items".to_string(), vec![]),
+            ("SELECT * FROM missing".to_string(), vec![]),
+        ],
+    )
+    .await;
+
+    assert!(result.is_err());
+    assert!(conn.is_autocommit());
+}
+
+#[tokio::test]
+async fn test_row_fingerprint_matches_identical_rows_only() {
+    let db_path = setup_test_db_with_prefix("row_fingerprint");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect_with_items(&db_path, 0).await;
+    conn.execute_batch(
+        "CREATE TABLE events (id INTEGER PRIMARY KEY, kind TEXT, payload BLOB, note TEXT);
+         INSERT INTO events VALUES (1, 'click', x'0102', NULL);
+         INSERT INTO events VALUES (2, 'click', x'0102', NULL);
+         INSERT INTO events VALUES (3, 'click', x'0103', NULL);",
+    )
+    .await
+    .unwrap();
+
+    let sql = "SELECT kind, payload, note FROM events WHERE id = ?1";
+    let fingerprint = |id: i64| {
+        let conn = conn.clone();
+        async move {
+            fingerprint_first_row(&conn, sql, vec![Value::Integer(id)])
+                .await
+                .unwrap()
+        }
+    };
+
+    let first = fingerprint(1).await.unwrap();
+    assert_eq!(fingerprint(2).await, Some(first.clone()));
+    assert_ne!(fingerprint(3).await, Some(first));
+    assert_eq!(fingerprint(4).await, None);
+}
+
+#[tokio::test]
+async fn test_resolve_transforms_by_result_column_name() {
+    let db_path = setup_test_db_with_prefix("query_transform");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect_with_items(&db_path, 0).await;
+    conn.execute(
+        "CREATE TABLE users (id INTEGER PRIMARY KEY, email TEXT)",
+        (),
+    )
+    .await
+    .unwrap();
+
+    let stmt = conn
+        .prepare("SELECT id, email AS address FROM users")
+        .await
+        .unwrap();
+
+    let transforms = vec![("address".to_string(), ColumnTransform::Lower)];
+    let (by_index, tables) = resolve_transforms(&stmt, ColumnNaming::Raw, &transforms).unwrap();
+    assert_eq!(by_index, vec![None, Some(ColumnTransform::Lower)]);
+    assert_eq!(tables, vec![Some("users".to_string()); 2]);
+
+    let transforms = vec![("email".to_string(), ColumnTransform::Lower)];
+    let err = resolve_transforms(&stmt, ColumnNaming::Raw, &transforms).unwrap_err();
+    assert_eq!(err, "Column not found in result: email");
+}
+
+#[tokio::test]
+async fn test_conflict_clause_change_counts() {
+    let db_path = setup_test_db_with_prefix("conflict_action");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect_with_items(&db_path, 0).await;
+    conn.execute_batch(
+        "CREATE TABLE users (id INTEGER PRIMARY KEY, email TEXT UNIQUE);
+         INSERT INTO users VALUES (1, 'a@example.com'), (2, 'b@example.com');",
+    )
+    .await
+    .unwrap();
+
+    // Ignored rows are not counted
+    let ignored = conn
+        .execute(
+            "INSERT OR IGNORE INTO users VALUES (1, 'x@example.com'), (3, 'c@example.com')",
+            (),
+        )
+        .await
+        .unwrap();
+    assert_eq!(ignored, 1);
+
+    // Replacing conflicts on two different rows deletes both but counts one insert
+    let replaced = conn
+        .execute("REPLACE INTO users VALUES (1, 'b@example.com')", ())
+        .await
+        .unwrap();
+    assert_eq!(replaced, 1);
+    let mut rows = conn.query("SELECT COUNT(*) FROM users", ()).await.unwrap();
+    let remaining: i64 = rows.next().await.unwrap().unwrap().get(0).unwrap();
+    assert_eq!(remaining, 2, "rows 1 and 2 were replaced by a single row");
+}
+
+#[tokio::test]
+async fn test_qualify_for_database_targets_attached_table() {
+    let db_path = setup_test_db_with_prefix("query_on_db");
+    let shard_path = setup_test_db_with_prefix("query_on_db_shard");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let _shard_guard = TestDbGuard::new(shard_path.clone());
+    let conn = Builder::new_local(db_path.to_str().unwrap())
+        .build()
+        .await
+        .unwrap()
+        .connect()
+        .unwrap();
+
+    // `users` exists in both databases, so unqualified SQL would read main's
+    conn.execute_batch(
+        "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT);
+         INSERT INTO users VALUES (1, 'main user');",
+    )
+    .await
+    .unwrap();
+    conn.execute(
+        "ATTACH DATABASE ?1 AS shard",
+        vec![Value::Text(shard_path.to_str().unwrap().to_string())],
+    )
+    .await
+    .unwrap();
+    conn.execute_batch(
+        "CREATE TABLE shard.users (id INTEGER PRIMARY KEY, name TEXT);
+         CREATE TABLE shard.orders (id INTEGER PRIMARY KEY, user_id INTEGER);",
+    )
+    .await
+    .unwrap();
+
+    let insert = qualify_for_database(&conn, "shard", "INSERT INTO users VALUES (1, 'shard user')")
+        .await
+        .unwrap();
+    conn.execute(&insert, ()).await.unwrap();
+    conn.execute("INSERT INTO shard.orders VALUES (10, 1)", ())
+        .await
+        .unwrap();
+
+    let select = qualify_for_database(
+        &conn,
+        "shard",
+        "SELECT u.name, o.id FROM users u JOIN orders o ON o.user_id = u.id",
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        select,
+        "SELECT u.name, o.id FROM \"shard\".users u JOIN \"shard\".orders o ON o.user_id = u.id"
+    );
+    let mut rows = conn.query(&select, ()).await.unwrap();
+    let row = rows.next().await.unwrap().unwrap();
+    assert_eq!(row.get::<String>(0).unwrap(), "shard user");
+    assert_eq!(row.get::<i64>(1).unwrap(), 10);
+
+    // main's row is untouched
+    let mut rows = conn.query("SELECT name FROM main.users", ()).await.unwrap();
+    let row = rows.next().await.unwrap().unwrap();
+    assert_eq!(row.get::<String>(0).unwrap(), "main user");
+
+    let err = qualify_for_database(&conn, "missing", "SELECT * FROM users")
+        .await
+        .unwrap_err();
+    assert_eq!(err, "Unknown database: missing");
+}
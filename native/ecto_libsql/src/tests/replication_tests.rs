@@ -0,0 +1,25 @@
+//! Tests for replication helpers
+//!
+//! These tests exercise the lag check behind conditional syncing. Syncing itself needs
+//! a remote primary, so it is not covered here.
+
+use crate::replication::sync_needed;
+
+#[test]
+fn test_sync_needed_when_behind_threshold() {
+    assert!(sync_needed(10, 25, 5));
+    assert!(sync_needed(0, 1, 0));
+}
+
+#[test]
+fn test_up_to_date_within_threshold() {
+    assert!(!sync_needed(10, 15, 5));
+    assert!(!sync_needed(10, 10, 0));
+    assert!(!sync_needed(0, 0, 0));
+}
+
+#[test]
+fn test_replica_ahead_of_writes_is_up_to_date() {
+    // Syncing can move the replica past this database's own writes
+    assert!(!sync_needed(40, 25, 0));
+}
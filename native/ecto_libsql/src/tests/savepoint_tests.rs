@@ -0,0 +1,38 @@
+//! Tests for savepoint tracking
+//!
+//! These tests cover how the savepoint stack kept for each transaction follows
+//! SQLite's `RELEASE` and `ROLLBACK TO` semantics.
+
+use crate::savepoint::unwind_savepoints;
+
+fn stack(names: &[&str]) -> Vec<String> {
+    names.iter().map(|name| (*name).to_string()).collect()
+}
+
+#[test]
+fn test_release_removes_savepoint_and_later_ones() {
+    let mut savepoints = stack(&["sp1", "sp2", "sp3"]);
+    unwind_savepoints(&mut savepoints, "sp2", false);
+    assert_eq!(savepoints, stack(&["sp1"]));
+}
+
+#[test]
+fn test_rollback_to_keeps_savepoint_open() {
+    let mut savepoints = stack(&["sp1", "sp2", "sp3"]);
+    unwind_savepoints(&mut savepoints, "sp2", true);
+    assert_eq!(savepoints, stack(&["sp1", "sp2"]));
+}
+
+#[test]
+fn test_matches_most_recent_savepoint_case_insensitively() {
+    let mut savepoints = stack(&["outer", "inner", "outer"]);
+    unwind_savepoints(&mut savepoints, "OUTER", false);
+    assert_eq!(savepoints, stack(&["outer", "inner"]));
+}
+
+#[test]
+fn test_unknown_savepoint_leaves_stack_unchanged() {
+    let mut savepoints = stack(&["sp1"]);
+    unwind_savepoints(&mut savepoints, "missing", false);
+    assert_eq!(savepoints, stack(&["sp1"]));
+}
@@ -0,0 +1,306 @@
+//! Tests for prepared statement helpers
+//!
+//! These tests cover checking SQL scripts by preparing each statement against a real
+//! local database without executing it, detecting statements made stale by
+//! schema changes, how statements given too few values are bound, where result
+//! columns come from, and locating the error in SQL that fails to prepare.
+
+// Allow unwrap() in tests for cleaner test code - see CLAUDE.md "Test Code Exception"
+#![allow(clippy::unwrap_used)]
+
+use super::test_utils::{connect, setup_test_db_with_prefix, TestDbGuard};
+use crate::constants::MAX_ERROR_OFFSET_PROBES;
+use crate::models::Mode;
+use crate::statement::{
+    column_provenance_of, first_invalid_statement, prepare_error_offset, prepare_with_source,
+    schema_version,
+};
+use crate::utils::last_error_from;
+use libsql::{Connection, Value};
+
+#[tokio::test]
+async fn test_valid_script_passes() {
+    let db_path = setup_test_db_with_prefix("validate");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect(&db_path).await;
+
+    let script = "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT);\n\
+                  CREATE INDEX users_name ON users (name);\n\
+                  INSERT INTO users (name) VALUES ('a;b');\n\
+                  SELECT id, name FROM users WHERE name = ?;";
+    assert_eq!(first_invalid_statement(&conn, script).await, None);
+
+    // Nothing was executed
+    let mut rows = conn
+        .query(
+            "SELECT count(*) FROM sqlite_master WHERE name = 'users'",
+            (),
+        )
+        .await
+        .unwrap();
+    let count: i64 = rows.next().await.unwrap().unwrap().get(0).unwrap();
+    assert_eq!(count, 0);
+}
+
+#[tokio::test]
+async fn test_reports_index_of_bad_statement() {
+    let db_path = setup_test_db_with_prefix("validate");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect(&db_path).await;
+
+    let script = "CREATE TABLE users (id INTEGER PRIMARY KEY);\n\
+                  SELECT 1;\n\
+                  SELEC id FROM users;\n\
+                  SELECT FROM;";
+    let (index, sql, reason) = first_invalid_statement(&conn, script).await.unwrap();
+    assert_eq!(index, 2);
+    assert_eq!(sql, "SELEC id FROM users");
+    assert!(
+        reason.contains("syntax error"),
+        "unexpected reason: {reason}"
+    );
+}
+
+#[tokio::test]
+async fn test_schema_change_makes_statement_stale() {
+    let db_path = setup_test_db_with_prefix("stmt_stale");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect(&db_path).await;
+    conn.execute("CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT)", ())
+        .await
+        .unwrap();
+
+    let (stmt, source) = prepare_with_source(&conn, Mode::Local, "SELECT * FROM users")
+        .await
+        .unwrap();
+    assert_eq!(source.sql, "SELECT * FROM users");
+    assert_eq!(stmt.column_count(), 2);
+    assert_eq!(
+        Some(schema_version(&conn).await.unwrap()),
+        source.schema_version
+    );
+
+    // Inserting data leaves the schema alone
+    conn.execute("INSERT INTO users (name) VALUES ('a')", ())
+        .await
+        .unwrap();
+    assert_eq!(
+        Some(schema_version(&conn).await.unwrap()),
+        source.schema_version
+    );
+
+    conn.execute("ALTER TABLE users ADD COLUMN email TEXT", ())
+        .await
+        .unwrap();
+    assert_ne!(
+        Some(schema_version(&conn).await.unwrap()),
+        source.schema_version
+    );
+
+    // Re-preparing from the stored SQL picks up the new schema
+    let (stmt, refreshed) = prepare_with_source(&conn, Mode::Local, &source.sql)
+        .await
+        .unwrap();
+    assert_eq!(stmt.column_count(), 3);
+    assert_eq!(
+        Some(schema_version(&conn).await.unwrap()),
+        refreshed.schema_version
+    );
+}
+
+#[tokio::test]
+async fn test_remote_prepare_skips_schema_version() {
+    let db_path = setup_test_db_with_prefix("stmt_remote_source");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect(&db_path).await;
+
+    let (_, source) = prepare_with_source(&conn, Mode::Remote, "SELECT 1")
+        .await
+        .unwrap();
+    assert_eq!(source.schema_version, None);
+}
+
+#[tokio::test]
+async fn test_schema_change_from_another_connection_is_seen() {
+    let db_path = setup_test_db_with_prefix("stmt_stale_other");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect(&db_path).await;
+    let other = connect(&db_path).await;
+    conn.execute("CREATE TABLE users (id INTEGER PRIMARY KEY)", ())
+        .await
+        .unwrap();
+
+    let (_, source) = prepare_with_source(&conn, Mode::Local, "SELECT id FROM users")
+        .await
+        .unwrap();
+    other
+        .execute("CREATE INDEX users_id ON users (id)", ())
+        .await
+        .unwrap();
+
+    assert_ne!(
+        Some(schema_version(&conn).await.unwrap()),
+        source.schema_version
+    );
+}
+
+#[tokio::test]
+async fn test_too_few_values_bind_null() {
+    let db_path = setup_test_db_with_prefix("debug_params");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect(&db_path).await;
+    conn.execute("CREATE TABLE users (name TEXT, email TEXT)", ())
+        .await
+        .unwrap();
+
+    // SQLite runs the statement anyway, which is why `debug_params` reports the counts
+    let stmt = conn
+        .prepare("INSERT INTO users (name, email) VALUES (?, ?)")
+        .await
+        .unwrap();
+    assert_eq!(stmt.parameter_count(), 2);
+    assert_eq!(
+        stmt.execute(vec![Value::Text("alice".to_string())])
+            .await
+            .unwrap(),
+        1
+    );
+
+    let mut rows = conn
+        .query("SELECT name, email FROM users", ())
+        .await
+        .unwrap();
+    let row = rows.next().await.unwrap().unwrap();
+    assert_eq!(row.get_value(0).unwrap(), Value::Text("alice".to_string()));
+    assert_eq!(row.get_value(1).unwrap(), Value::Null);
+}
+
+#[tokio::test]
+async fn test_column_provenance_through_aliases() {
+    let db_path = setup_test_db_with_prefix("provenance");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect(&db_path).await;
+
+    conn.execute_batch(
+        "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT);
+         CREATE TABLE posts (id INTEGER PRIMARY KEY, user_id INTEGER, title TEXT);",
+    )
+    .await
+    .unwrap();
+
+    let stmt = conn
+        .prepare(
+            "SELECT u.name AS author, p.title, upper(p.title) AS shout, 1 AS one \
+             FROM posts p JOIN users u ON u.id = p.user_id",
+        )
+        .await
+        .unwrap();
+
+    let some = |s: &str| Some(s.to_string());
+    assert_eq!(
+        column_provenance_of(&stmt),
+        vec![
+            (
+                "author".to_string(),
+                some("main"),
+                some("users"),
+                some("name")
+            ),
+            (
+                "title".to_string(),
+                some("main"),
+                some("posts"),
+                some("title")
+            ),
+            ("shout".to_string(), None, None, None),
+            ("one".to_string(), None, None, None),
+        ]
+    );
+}
+
+/// Prepare `sql`, which must fail, and return the error message and recovered offset.
+async fn prepare_failure(conn: &Connection, sql: &str) -> (String, Option<usize>) {
+    let message = match conn.prepare(sql).await {
+        Ok(_) => String::new(),
+        Err(e) => last_error_from(&e).message,
+    };
+    assert!(!message.is_empty(), "expected {sql} to fail to prepare");
+    let offset = prepare_error_offset(conn, Mode::Local, sql, &message).await;
+    (message, offset)
+}
+
+#[tokio::test]
+async fn test_prepare_error_offset_points_at_syntax_error() {
+    let db_path = setup_test_db_with_prefix("prepare_offset");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect(&db_path).await;
+    conn.execute("CREATE TABLE users (id INTEGER, name TEXT)", ())
+        .await
+        .unwrap();
+
+    let sql = "SELECT id, name FORM users";
+    let (message, offset) = prepare_failure(&conn, sql).await;
+    assert!(message.contains("syntax error"), "{message}");
+    assert_eq!(offset, sql.find("users"));
+
+    // An earlier, valid occurrence of the same token is skipped
+    let sql = "SELECT id FROM users WHERE id = 1 FROM users";
+    let (_, offset) = prepare_failure(&conn, sql).await;
+    assert_eq!(offset, sql.rfind("FROM"));
+
+    // Truncated SQL has no position to report
+    let (message, offset) = prepare_failure(&conn, "SELECT id FROM").await;
+    assert!(message.contains("incomplete input"), "{message}");
+    assert_eq!(offset, None);
+}
+
+#[tokio::test]
+async fn test_prepare_error_offset_limits_syntax_probes() {
+    let db_path = setup_test_db_with_prefix("prepare_offset");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect(&db_path).await;
+
+    // Remote connections would pay a round trip per probe, so they don't probe at all
+    let sql = "SELECT id, name FORM users";
+    let message = last_error_from(&conn.prepare(sql).await.err().unwrap()).message;
+    assert_eq!(
+        prepare_error_offset(&conn, Mode::Remote, sql, &message).await,
+        None
+    );
+
+    // A failing token repeated more often than the cap is given up on
+    let values = vec!["1"; MAX_ERROR_OFFSET_PROBES + 1].join(", ");
+    let sql = format!("SELECT {values} 1");
+    let (message, offset) = prepare_failure(&conn, &sql).await;
+    assert!(message.contains("near \"1\""), "{message}");
+    assert_eq!(offset, None);
+
+    let values = vec!["1"; MAX_ERROR_OFFSET_PROBES - 2].join(", ");
+    let sql = format!("SELECT {values} 1");
+    let (_, offset) = prepare_failure(&conn, &sql).await;
+    assert_eq!(offset, sql.rfind('1'));
+}
+
+#[tokio::test]
+async fn test_prepare_error_offset_points_at_unknown_names() {
+    let db_path = setup_test_db_with_prefix("prepare_offset");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect(&db_path).await;
+    conn.execute("CREATE TABLE users (id INTEGER, name TEXT)", ())
+        .await
+        .unwrap();
+
+    let sql = "SELECT id, nmae FROM users";
+    let (message, offset) = prepare_failure(&conn, sql).await;
+    assert!(message.contains("no such column"), "{message}");
+    assert_eq!(offset, sql.find("nmae"));
+
+    // `user` must not match inside `users_old`
+    conn.execute("CREATE TABLE users_old (id INTEGER)", ())
+        .await
+        .unwrap();
+    let sql = "SELECT * FROM users_old JOIN user ON 1";
+    let (message, offset) = prepare_failure(&conn, sql).await;
+    assert!(message.contains("no such table"), "{message}");
+    assert_eq!(offset, sql.find("user ON"));
+}
@@ -3,8 +3,9 @@
 //! This module provides common test infrastructure used across multiple test files
 //! to avoid duplication and ensure consistent test behavior.
 
+use libsql::{Builder, Connection};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use uuid::Uuid;
 
 /// RAII guard that ensures database and associated SQLite files are cleaned up
@@ -84,6 +85,25 @@ pub fn setup_test_db() -> PathBuf {
     temp_dir.join(db_name)
 }
 
+/// Open a connection to a local test database, creating the file if needed.
+///
+/// # Example
+///
+/// ```ignore
+/// let db_path = setup_test_db_with_prefix("blob");
+/// let _guard = TestDbGuard::new(db_path.clone());
+/// let conn = connect(&db_path).await;
+/// ```
+// Allow unwrap() in test helpers - see CLAUDE.md "Test Code Exception"
+#[allow(clippy::unwrap_used)]
+pub async fn connect(db_path: &Path) -> Connection {
+    let db = Builder::new_local(db_path.to_str().unwrap())
+        .build()
+        .await
+        .unwrap();
+    db.connect().unwrap()
+}
+
 /// Set up a test database with a specific name prefix.
 ///
 /// Useful when you want to ensure a specific database name pattern for debugging.
@@ -0,0 +1,81 @@
+//! Tests for transactions begun with their own busy timeout
+//!
+//! These tests exercise `begin_with_busy_timeout` and `finish_transaction` directly
+//! against a real local database, checking the connection's busy timeout is
+//! overridden while the transaction is open and restored once it ends.
+
+// Allow unwrap() in tests for cleaner test code - see CLAUDE.md "Test Code Exception"
+#![allow(clippy::unwrap_used)]
+
+use super::test_utils::{connect, setup_test_db_with_prefix, TestDbGuard};
+use crate::models::TransactionEntry;
+use crate::transaction::{begin_with_busy_timeout, finish_transaction, restore_busy_timeout};
+use crate::utils::read_busy_timeout;
+use libsql::{Connection, TransactionBehavior};
+use std::time::Duration;
+
+async fn connect_with_busy_timeout(db_path: &std::path::Path) -> Connection {
+    let conn = connect(db_path).await;
+    conn.busy_timeout(Duration::from_millis(250)).unwrap();
+    conn
+}
+
+async fn begin_entry(conn: &Connection, busy_timeout_ms: u64) -> TransactionEntry {
+    let (transaction, previous_ms) =
+        begin_with_busy_timeout(conn, TransactionBehavior::Immediate, busy_timeout_ms)
+            .await
+            .unwrap();
+    TransactionEntry {
+        conn_id: "txn-timeout".to_string(),
+        transaction,
+        savepoints: Vec::new(),
+        restore_busy_timeout_ms: Some(previous_ms),
+    }
+}
+
+#[tokio::test]
+async fn test_busy_timeout_restored_after_commit_and_rollback() {
+    let db_path = setup_test_db_with_prefix("txn_timeout");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect_with_busy_timeout(&db_path).await;
+
+    for commit in [true, false] {
+        let entry = begin_entry(&conn, 5_000).await;
+        assert_eq!(read_busy_timeout(&conn).await.unwrap(), 5_000);
+
+        finish_transaction(entry, commit).await.unwrap();
+        assert!(conn.is_autocommit());
+        assert_eq!(read_busy_timeout(&conn).await.unwrap(), 250);
+    }
+}
+
+#[tokio::test]
+async fn test_busy_timeout_restored_when_transaction_is_closed() {
+    let db_path = setup_test_db_with_prefix("txn_timeout");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect_with_busy_timeout(&db_path).await;
+
+    let entry = begin_entry(&conn, 0).await;
+    assert_eq!(read_busy_timeout(&conn).await.unwrap(), 0);
+
+    // Closing restores the timeout, then dropping the entry rolls back
+    restore_busy_timeout(&entry).unwrap();
+    drop(entry);
+    assert_eq!(read_busy_timeout(&conn).await.unwrap(), 250);
+}
+
+#[tokio::test]
+async fn test_busy_timeout_restored_when_begin_fails() {
+    let db_path = setup_test_db_with_prefix("txn_timeout");
+    let _guard = TestDbGuard::new(db_path.clone());
+    let conn = connect_with_busy_timeout(&db_path).await;
+
+    conn.execute("BEGIN", ()).await.unwrap();
+    let err = begin_with_busy_timeout(&conn, TransactionBehavior::Deferred, 5_000)
+        .await
+        .map(|_| ())
+        .unwrap_err();
+    assert!(err.contains("Begin failed"), "{err}");
+    assert_eq!(read_busy_timeout(&conn).await.unwrap(), 250);
+    conn.execute("ROLLBACK", ()).await.unwrap();
+}
@@ -3,6 +3,24 @@
 //! These tests verify the correctness of:
 //! - `detect_query_type()` - Categorizes SQL statements by type
 //! - `should_use_query()` - Determines whether to use query() vs execute()
+//! - `last_error_from()` - Extracts SQLite error codes from libsql errors
+//! - `classify_busy()` - Classifies busy and locked errors by lock kind
+//! - `classify_error()` - Classifies errors for the per-connection error counters
+//! - `sql_literal()` - Renders values as escaped SQLite literals
+//! - `quote_identifier()` - Quotes identifiers in each supported style
+//! - `inline_params()` - Inlines positional parameters as escaped literals
+//! - `dedupe_column_names()` - Disambiguates duplicate result column names
+//! - `coerce_numeric_text()` - Converts numbers stored as text by declared column affinity
+//! - `normalise_datetime_text()` - Canonicalises datetime text in date-declared columns
+//! - `ResultBudget` - Tracks returned bytes against `max_result_bytes`
+//! - `decode_text_bytes()` / `decode_blob_columns()` - Decode legacy-encoded blobs as text
+//! - `generated_column_expr()` - Extracts generated column expressions from table SQL
+//! - `write_route()` / `require_replica()` - Classify writes and replica-only operations by mode
+//! - `row_fingerprint_of()` - Hashes row values for deduplication
+//! - `apply_transform()` - Applies predefined column transforms to result values
+//! - `detect_conflict_action()` - Recognises `OR <action>` conflict clauses
+//! - `has_keyword()` - Finds keywords outside literals and comments
+//! - `qualify_table_references()` - Qualifies table references with a database name
 
 // Allow unwrap() in tests for cleaner test code - see CLAUDE.md "Test Code Exception"
 #![allow(clippy::unwrap_used)]
@@ -731,3 +749,1290 @@ mod should_use_query_tests {
         assert!(should_use_query("PRAGMA"));
     }
 }
+
+/// Tests for classifying busy and locked errors
+mod classify_busy_tests {
+    use crate::models::{BusyKind, LastError};
+    use crate::utils::classify_busy;
+
+    fn error(code: Option<i32>, extended_code: Option<i32>) -> LastError {
+        LastError {
+            code,
+            extended_code,
+            message: "database is locked".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_busy_codes_are_write_locks() {
+        // SQLITE_BUSY, SQLITE_BUSY_RECOVERY, SQLITE_BUSY_TIMEOUT, and a remote BUSY
+        for extended in [Some(5), Some(261), Some(773), None] {
+            assert_eq!(
+                classify_busy(&error(Some(5), extended)),
+                Some(BusyKind::WriteLock)
+            );
+        }
+    }
+
+    #[test]
+    fn test_snapshot_and_locked_codes_are_read_locks() {
+        // SQLITE_BUSY_SNAPSHOT
+        assert_eq!(
+            classify_busy(&error(Some(5), Some(517))),
+            Some(BusyKind::ReadLock)
+        );
+        // SQLITE_LOCKED and SQLITE_LOCKED_SHAREDCACHE
+        assert_eq!(
+            classify_busy(&error(Some(6), Some(6))),
+            Some(BusyKind::ReadLock)
+        );
+        assert_eq!(
+            classify_busy(&error(Some(6), Some(262))),
+            Some(BusyKind::ReadLock)
+        );
+    }
+
+    #[test]
+    fn test_other_errors_are_not_classified() {
+        assert_eq!(classify_busy(&error(Some(19), Some(2067))), None);
+        assert_eq!(classify_busy(&error(None, None)), None);
+    }
+}
+
+/// Tests for classifying errors into error counter classes
+mod classify_error_tests {
+    use crate::models::{ErrorClass, LastError};
+    use crate::utils::classify_error;
+
+    fn error(code: Option<i32>, extended_code: Option<i32>, message: &str) -> LastError {
+        LastError {
+            code,
+            extended_code,
+            message: message.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_constraint_and_busy_errors() {
+        let unique = error(
+            Some(19),
+            Some(2067),
+            "UNIQUE constraint failed: users.email",
+        );
+        assert_eq!(classify_error(&unique), ErrorClass::Constraint);
+        let locked = error(Some(5), Some(5), "database is locked");
+        assert_eq!(classify_error(&locked), ErrorClass::Busy);
+        let shared = error(Some(6), Some(262), "database table is locked");
+        assert_eq!(classify_error(&shared), ErrorClass::Busy);
+    }
+
+    #[test]
+    fn test_parse_failures_are_syntax_errors() {
+        for message in [
+            "near \"SELEC\": syntax error",
+            "incomplete input",
+            "unrecognized token: \"'abc\"",
+        ] {
+            assert_eq!(
+                classify_error(&error(Some(1), Some(1), message)),
+                ErrorClass::Syntax
+            );
+        }
+    }
+
+    #[test]
+    fn test_remaining_errors_are_other() {
+        let missing = error(Some(1), Some(1), "no such table: missing");
+        assert_eq!(classify_error(&missing), ErrorClass::Other);
+        let remote = error(None, None, "Hrana: stream closed");
+        assert_eq!(classify_error(&remote), ErrorClass::Other);
+    }
+}
+
+/// Tests for converting libsql errors into `LastError` records
+mod last_error_tests {
+    use crate::utils::last_error_from;
+
+    #[test]
+    fn test_sqlite_failure_splits_primary_and_extended_code() {
+        // SQLITE_CONSTRAINT_UNIQUE = 2067, primary SQLITE_CONSTRAINT = 19
+        let err = libsql::Error::SqliteFailure(2067, "UNIQUE constraint failed: t.a".to_string());
+        let last = last_error_from(&err);
+
+        assert_eq!(last.code, Some(19));
+        assert_eq!(last.extended_code, Some(2067));
+        assert_eq!(last.message, "UNIQUE constraint failed: t.a");
+    }
+
+    #[test]
+    fn test_remote_failure_keeps_reported_codes() {
+        let err = libsql::Error::RemoteSqliteFailure(19, 787, "FOREIGN KEY".to_string());
+        let last = last_error_from(&err);
+
+        assert_eq!(last.code, Some(19));
+        assert_eq!(last.extended_code, Some(787));
+        assert_eq!(last.message, "FOREIGN KEY");
+    }
+
+    #[test]
+    fn test_non_sqlite_error_has_no_codes() {
+        let last = last_error_from(&libsql::Error::ExecuteReturnedRows);
+
+        assert_eq!(last.code, None);
+        assert_eq!(last.extended_code, None);
+        assert_eq!(last.message, "Execute returned rows");
+    }
+}
+
+/// Tests for rendering values as SQLite literals
+mod sql_literal_tests {
+    use crate::utils::sql_literal;
+    use libsql::Value;
+
+    #[test]
+    fn test_null_and_integers() {
+        assert_eq!(sql_literal(&Value::Null), "NULL");
+        assert_eq!(sql_literal(&Value::Integer(42)), "42");
+        assert_eq!(
+            sql_literal(&Value::Integer(i64::MIN)),
+            "-9223372036854775808"
+        );
+    }
+
+    #[test]
+    fn test_reals_keep_real_affinity() {
+        assert_eq!(sql_literal(&Value::Real(1.0)), "1.0");
+        assert_eq!(sql_literal(&Value::Real(-2.5)), "-2.5");
+        assert_eq!(sql_literal(&Value::Real(1e300)), "1e300");
+        assert_eq!(sql_literal(&Value::Real(f64::INFINITY)), "9e999");
+        assert_eq!(sql_literal(&Value::Real(f64::NEG_INFINITY)), "-9e999");
+        assert_eq!(sql_literal(&Value::Real(f64::NAN)), "NULL");
+    }
+
+    #[test]
+    fn test_text_escapes_single_quotes() {
+        assert_eq!(sql_literal(&Value::Text("plain".into())), "'plain'");
+        assert_eq!(sql_literal(&Value::Text("O'Brien".into())), "'O''Brien'");
+        assert_eq!(sql_literal(&Value::Text("''".into())), "''''''");
+    }
+
+    #[test]
+    fn test_text_has_no_backslash_escapes() {
+        // SQLite string literals only escape quotes; everything else is taken verbatim
+        assert_eq!(
+            sql_literal(&Value::Text("it's a \\path\nline 2".into())),
+            "'it''s a \\path\nline 2'"
+        );
+    }
+
+    #[test]
+    fn test_blob_uses_hex_notation() {
+        assert_eq!(sql_literal(&Value::Blob(vec![])), "X''");
+        assert_eq!(
+            sql_literal(&Value::Blob(vec![0x00, 0xAB, 0xff])),
+            "X'00ABFF'"
+        );
+    }
+}
+
+/// Tests for quoting identifiers embedded in generated SQL
+mod quote_identifier_tests {
+    use crate::utils::{quote_identifier, QuoteStyle};
+
+    #[test]
+    fn test_default_style_is_double_quote() {
+        assert_eq!(QuoteStyle::default(), QuoteStyle::DoubleQuote);
+    }
+
+    #[test]
+    fn test_plain_identifier_in_each_style() {
+        assert_eq!(
+            quote_identifier("users", QuoteStyle::DoubleQuote),
+            "\"users\""
+        );
+        assert_eq!(quote_identifier("users", QuoteStyle::Backtick), "`users`");
+        assert_eq!(quote_identifier("users", QuoteStyle::Bracket), "[users]");
+    }
+
+    #[test]
+    fn test_identifier_with_double_quotes() {
+        assert_eq!(
+            quote_identifier("my\"table", QuoteStyle::DoubleQuote),
+            "\"my\"\"table\""
+        );
+        assert_eq!(
+            quote_identifier("my\"table", QuoteStyle::Backtick),
+            "`my\"table`"
+        );
+        assert_eq!(
+            quote_identifier("my\"table", QuoteStyle::Bracket),
+            "[my\"table]"
+        );
+    }
+
+    #[test]
+    fn test_identifier_with_backticks() {
+        assert_eq!(
+            quote_identifier("my`table", QuoteStyle::Backtick),
+            "`my``table`"
+        );
+        assert_eq!(
+            quote_identifier("my`table", QuoteStyle::DoubleQuote),
+            "\"my`table\""
+        );
+        assert_eq!(
+            quote_identifier("my`table", QuoteStyle::Bracket),
+            "[my`table]"
+        );
+    }
+
+    #[test]
+    fn test_identifier_with_brackets() {
+        assert_eq!(quote_identifier("[a]", QuoteStyle::DoubleQuote), "\"[a]\"");
+        assert_eq!(quote_identifier("[a]", QuoteStyle::Backtick), "`[a]`");
+        // `]` can't be escaped inside brackets, so fall back to double quotes
+        assert_eq!(quote_identifier("a]b", QuoteStyle::Bracket), "\"a]b\"");
+        assert_eq!(quote_identifier("a[b", QuoteStyle::Bracket), "[a[b]");
+    }
+
+    #[tokio::test]
+    async fn test_quoted_identifiers_round_trip_through_sqlite() {
+        let db = libsql::Builder::new_local(":memory:")
+            .build()
+            .await
+            .unwrap();
+        let conn = db.connect().unwrap();
+
+        for (name, style) in [
+            ("we\"ird", QuoteStyle::DoubleQuote),
+            ("we`ird", QuoteStyle::Backtick),
+            ("we[ird", QuoteStyle::Bracket),
+            ("we]ird", QuoteStyle::Bracket),
+        ] {
+            let quoted = quote_identifier(name, style);
+            conn.execute(&format!("CREATE TABLE {quoted} (id INTEGER)"), ())
+                .await
+                .unwrap();
+            let mut rows = conn
+                .query(
+                    "SELECT name FROM sqlite_master WHERE type = 'table' AND name = ?1",
+                    vec![libsql::Value::Text(name.to_string())],
+                )
+                .await
+                .unwrap();
+            assert!(rows.next().await.unwrap().is_some(), "{quoted} not created");
+        }
+    }
+}
+
+/// Tests for inlining positional parameters
+mod inline_params_tests {
+    use crate::utils::inline_params;
+    use libsql::Value;
+
+    #[test]
+    fn test_inlines_positional_placeholders() {
+        let sql = inline_params(
+            "INSERT INTO t (a, b) VALUES (?, ?)",
+            &[Value::Integer(1), Value::Text("it's".to_string())],
+        )
+        .unwrap();
+        assert_eq!(sql, "INSERT INTO t (a, b) VALUES (1, 'it''s')");
+    }
+
+    #[test]
+    fn test_inlines_numbered_placeholders() {
+        let sql = inline_params(
+            "UPDATE t SET a = ?2 WHERE id = ?1 OR parent = ?1",
+            &[Value::Integer(7), Value::Null],
+        )
+        .unwrap();
+        assert_eq!(sql, "UPDATE t SET a = NULL WHERE id = 7 OR parent = 7");
+    }
+
+    #[test]
+    fn test_ignores_placeholders_in_literals_and_comments() {
+        let sql = inline_params(
+            "INSERT INTO \"q?\" VALUES ('?', ?) -- why?\n/* ? */",
+            &[Value::Integer(1)],
+        )
+        .unwrap();
+        assert_eq!(sql, "INSERT INTO \"q?\" VALUES ('?', 1) -- why?\n/* ? */");
+    }
+
+    #[test]
+    fn test_drops_trailing_semicolon() {
+        let sql = inline_params("DELETE FROM t WHERE id = ?;  ", &[Value::Integer(3)]).unwrap();
+        assert_eq!(sql, "DELETE FROM t WHERE id = 3");
+    }
+
+    #[test]
+    fn test_rejects_multiple_statements() {
+        let err = inline_params("DELETE FROM t; DROP TABLE t", &[]).unwrap_err();
+        assert!(err.contains("one SQL statement"), "{err}");
+    }
+
+    #[test]
+    fn test_rejects_named_parameters() {
+        let err = inline_params("DELETE FROM t WHERE id = :id", &[Value::Integer(1)]).unwrap_err();
+        assert!(err.contains("Named parameters"), "{err}");
+    }
+
+    #[test]
+    fn test_rejects_parameter_count_mismatch() {
+        assert!(inline_params("SELECT ?", &[]).is_err());
+        assert!(inline_params("SELECT ?", &[Value::Integer(1), Value::Integer(2)]).is_err());
+    }
+}
+
+/// Tests for result column name de-duplication
+mod dedupe_column_names_tests {
+    use crate::models::ColumnNaming;
+    use crate::utils::dedupe_column_names;
+
+    fn names(list: &[&str]) -> Vec<String> {
+        list.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn test_raw_keeps_duplicates() {
+        let cols = names(&["id", "name", "id"]);
+        assert_eq!(dedupe_column_names(&cols, &[], ColumnNaming::Raw), cols);
+    }
+
+    #[test]
+    fn test_index_suffix_renames_later_duplicates() {
+        assert_eq!(
+            dedupe_column_names(
+                &names(&["id", "name", "id", "id"]),
+                &[],
+                ColumnNaming::IndexSuffix
+            ),
+            names(&["id", "name", "id_2", "id_3"])
+        );
+    }
+
+    #[test]
+    fn test_index_suffix_skips_existing_names() {
+        assert_eq!(
+            dedupe_column_names(
+                &names(&["id", "id", "id_2"]),
+                &[],
+                ColumnNaming::IndexSuffix
+            ),
+            names(&["id", "id_3", "id_2"])
+        );
+    }
+
+    #[test]
+    fn test_table_prefix_uses_origin_tables() {
+        let tables = vec![
+            Some("users".to_string()),
+            Some("users".to_string()),
+            Some("posts".to_string()),
+        ];
+        assert_eq!(
+            dedupe_column_names(
+                &names(&["id", "name", "id"]),
+                &tables,
+                ColumnNaming::TablePrefix
+            ),
+            names(&["users.id", "name", "posts.id"])
+        );
+    }
+
+    #[test]
+    fn test_table_prefix_falls_back_to_suffix() {
+        // Self-join: both columns come from the same table; the last has no origin
+        let tables = vec![Some("items".to_string()), Some("items".to_string()), None];
+        assert_eq!(
+            dedupe_column_names(
+                &names(&["id", "id", "id"]),
+                &tables,
+                ColumnNaming::TablePrefix
+            ),
+            names(&["items.id", "items.id_2", "id"])
+        );
+    }
+}
+
+/// Tests for finding keywords outside literals and comments
+mod has_keyword_tests {
+    use crate::utils::has_keyword;
+
+    #[test]
+    fn test_matches_bare_keyword_case_insensitively() {
+        assert!(has_keyword(
+            "CREATE TABLE t (id INTEGER PRIMARY KEY autoincrement)",
+            "AUTOINCREMENT"
+        ));
+    }
+
+    #[test]
+    fn test_ignores_literals_comments_and_longer_words() {
+        for sql in [
+            "CREATE TABLE t (note TEXT DEFAULT 'AUTOINCREMENT')",
+            "CREATE TABLE t (id INTEGER) -- AUTOINCREMENT",
+            "CREATE TABLE t (\"AUTOINCREMENT\" INTEGER, autoincrement_id INTEGER)",
+        ] {
+            assert!(!has_keyword(sql, "AUTOINCREMENT"), "{sql}");
+        }
+    }
+}
+
+mod split_statements_tests {
+    use crate::utils::split_statements;
+
+    #[test]
+    fn test_splits_on_semicolons() {
+        assert_eq!(
+            split_statements("CREATE TABLE t (a);\nINSERT INTO t VALUES (1);  SELECT * FROM t"),
+            vec![
+                "CREATE TABLE t (a)",
+                "INSERT INTO t VALUES (1)",
+                "SELECT * FROM t"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ignores_semicolons_in_literals_and_comments() {
+        assert_eq!(
+            split_statements("INSERT INTO \"a;b\" VALUES ('x;y'); -- c;d\nSELECT 1 /* ; */;"),
+            vec![
+                "INSERT INTO \"a;b\" VALUES ('x;y')",
+                "-- c;d\nSELECT 1 /* ; */"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_drops_empty_and_comment_only_statements() {
+        assert_eq!(
+            split_statements(";; SELECT 1;\n  ;-- trailing comment\n"),
+            vec!["SELECT 1"]
+        );
+        assert!(split_statements("").is_empty());
+    }
+
+    #[test]
+    fn test_keeps_trigger_bodies_together() {
+        let sql = "CREATE TEMP TRIGGER trg AFTER INSERT ON t BEGIN \
+                   UPDATE t SET a = CASE WHEN a > 0 THEN 1 ELSE 0 END; \
+                   DELETE FROM u; \
+                   END; SELECT 1";
+        let statements = split_statements(sql);
+        assert_eq!(statements.len(), 2);
+        assert!(statements[0].ends_with("DELETE FROM u; END"));
+        assert_eq!(statements[1], "SELECT 1");
+    }
+
+    #[test]
+    fn test_begin_outside_trigger_is_a_statement() {
+        assert_eq!(
+            split_statements("BEGIN; SELECT 1; END"),
+            vec!["BEGIN", "SELECT 1", "END"]
+        );
+    }
+}
+
+mod dml_target_table_tests {
+    use crate::utils::{dml_target_table, QueryType};
+
+    #[test]
+    fn test_finds_plain_targets() {
+        assert_eq!(
+            dml_target_table("UPDATE users SET a = 1"),
+            Some((QueryType::Update, "users"))
+        );
+        assert_eq!(
+            dml_target_table("delete from users where id = 1"),
+            Some((QueryType::Delete, "users"))
+        );
+        assert_eq!(
+            dml_target_table("INSERT INTO users (a) VALUES (1)"),
+            Some((QueryType::Insert, "users"))
+        );
+        assert_eq!(
+            dml_target_table("REPLACE INTO users VALUES (1)"),
+            Some((QueryType::Insert, "users"))
+        );
+    }
+
+    #[test]
+    fn test_handles_conflict_clauses_quoting_and_schemas() {
+        assert_eq!(
+            dml_target_table("-- note\nINSERT OR IGNORE INTO \"my table\"(a) VALUES (1)"),
+            Some((QueryType::Insert, "\"my table\""))
+        );
+        assert_eq!(
+            dml_target_table("UPDATE OR REPLACE main . [t] SET a = 1"),
+            Some((QueryType::Update, "main . [t]"))
+        );
+        assert_eq!(
+            dml_target_table("DELETE FROM `s`.`t`"),
+            Some((QueryType::Delete, "`s`.`t`"))
+        );
+    }
+
+    #[test]
+    fn test_rejects_other_statements() {
+        assert_eq!(dml_target_table("SELECT * FROM users"), None);
+        assert_eq!(
+            dml_target_table("WITH x AS (SELECT 1) DELETE FROM users"),
+            None
+        );
+        assert_eq!(dml_target_table("DELETE users"), None);
+        assert_eq!(dml_target_table(""), None);
+    }
+}
+
+/// Tests for qualifying table references with an attached database's schema
+mod qualify_table_references_tests {
+    use crate::utils::qualify_table_references;
+    use std::collections::HashSet;
+
+    fn qualify(sql: &str) -> String {
+        let tables: HashSet<String> = ["users", "orders"]
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        qualify_table_references(sql, "shard", &tables)
+    }
+
+    #[test]
+    fn test_qualifies_from_join_and_comma_lists() {
+        assert_eq!(
+            qualify("SELECT * FROM users u JOIN orders o ON o.user_id = u.id"),
+            "SELECT * FROM \"shard\".users u JOIN \"shard\".orders o ON o.user_id = u.id"
+        );
+        assert_eq!(
+            qualify("SELECT users.id FROM Users, \"orders\" WHERE orders.id = 1"),
+            "SELECT users.id FROM \"shard\".Users, \"shard\".\"orders\" WHERE orders.id = 1"
+        );
+    }
+
+    #[test]
+    fn test_qualifies_write_targets() {
+        assert_eq!(
+            qualify("INSERT INTO users (id) VALUES (1)"),
+            "INSERT INTO \"shard\".users (id) VALUES (1)"
+        );
+        assert_eq!(
+            qualify("UPDATE OR IGNORE users SET name = 'a'"),
+            "UPDATE OR IGNORE \"shard\".users SET name = 'a'"
+        );
+        assert_eq!(
+            qualify("DELETE FROM orders WHERE id IN (SELECT id FROM users)"),
+            "DELETE FROM \"shard\".orders WHERE id IN (SELECT id FROM \"shard\".users)"
+        );
+    }
+
+    #[test]
+    fn test_leaves_other_references_alone() {
+        // Already qualified, unknown tables, select-list commas, literals and comments
+        for sql in [
+            "SELECT * FROM main.users",
+            "SELECT * FROM accounts",
+            "SELECT id, users FROM accounts",
+            "SELECT 'FROM users' -- FROM users",
+            "SELECT a IS DISTINCT FROM users FROM accounts",
+            "SELECT * FROM json_each('[]')",
+        ] {
+            assert_eq!(qualify(sql), sql);
+        }
+    }
+
+    #[test]
+    fn test_from_list_ends_at_where() {
+        assert_eq!(
+            qualify("SELECT * FROM accounts WHERE id IN (1, 2), users"),
+            "SELECT * FROM accounts WHERE id IN (1, 2), users"
+        );
+    }
+}
+
+mod sql_digest_tests {
+    use crate::utils::{normalise_sql, sql_digest};
+
+    #[test]
+    fn test_queries_differing_only_in_literals_share_a_digest() {
+        let a = "SELECT * FROM users WHERE name = 'Alice' AND age > 30";
+        let b = "select *\n  from users\n where name = 'O''Brien' and age > 4.5e-1 -- adults";
+        assert_eq!(normalise_sql(a), "select*from users where name=? and age>?");
+        assert_eq!(normalise_sql(a), normalise_sql(b));
+        assert_eq!(sql_digest(a), sql_digest(b));
+    }
+
+    #[test]
+    fn test_different_shapes_have_different_digests() {
+        assert_ne!(
+            sql_digest("SELECT id FROM users WHERE id = 1"),
+            sql_digest("SELECT id FROM posts WHERE id = 1")
+        );
+        assert_ne!(
+            sql_digest("SELECT id FROM users WHERE id = 1"),
+            sql_digest("SELECT id FROM users WHERE id > 1")
+        );
+    }
+
+    #[test]
+    fn test_parameters_and_lists_collapse() {
+        assert_eq!(
+            normalise_sql("SELECT * FROM t WHERE id IN (1, 2, 3) AND k = :key"),
+            normalise_sql("SELECT * FROM t WHERE id IN (?1) AND k = ?")
+        );
+        assert_eq!(
+            normalise_sql("INSERT INTO t (a, b) VALUES (X'CAFE', $b)"),
+            "insert into t(a,b)values(?)"
+        );
+    }
+
+    #[test]
+    fn test_quoted_identifiers_are_kept() {
+        assert_eq!(
+            normalise_sql(r#"SELECT "Name", [Order] FROM "My Table""#),
+            r#"select "Name",[Order] from "My Table""#
+        );
+        assert_ne!(
+            sql_digest(r#"SELECT "a" FROM t"#),
+            sql_digest("SELECT 'a' FROM t")
+        );
+    }
+
+    #[test]
+    fn test_digest_is_sixteen_hex_digits() {
+        let digest = sql_digest("SELECT 1");
+        assert_eq!(digest.len(), 16);
+        assert!(digest.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_eq!(digest, sql_digest("SELECT 2"));
+    }
+}
+
+mod keyset_page_sql_tests {
+    use crate::utils::keyset_page_sql;
+
+    #[test]
+    fn test_wraps_base_query() {
+        assert_eq!(
+            keyset_page_sql("SELECT * FROM users WHERE active = 1;", "id", true).unwrap(),
+            "SELECT `id`, * FROM (SELECT * FROM users WHERE active = 1) \
+             WHERE `id` > ?1 ORDER BY `id` LIMIT ?2"
+        );
+        assert_eq!(
+            keyset_page_sql("SELECT * FROM users", "created`at", false).unwrap(),
+            "SELECT `created``at`, * FROM (SELECT * FROM users) ORDER BY `created``at` LIMIT ?1"
+        );
+    }
+
+    #[test]
+    fn test_rejects_non_select_and_multiple_statements() {
+        assert!(keyset_page_sql("DELETE FROM users", "id", false).is_err());
+        assert!(keyset_page_sql("SELECT 1; SELECT 2", "id", false).is_err());
+        assert!(keyset_page_sql("", "id", false).is_err());
+    }
+
+    #[test]
+    fn test_rejects_top_level_order_and_limit() {
+        assert!(keyset_page_sql("SELECT * FROM users ORDER BY name", "id", false).is_err());
+        assert!(keyset_page_sql("SELECT * FROM users LIMIT 5", "id", false).is_err());
+        assert!(keyset_page_sql("select * from users limit 5 offset 5", "id", false).is_err());
+    }
+
+    #[test]
+    fn test_allows_nested_order_and_keywords_in_literals() {
+        assert!(keyset_page_sql(
+            "SELECT * FROM users WHERE id IN (SELECT user_id FROM posts ORDER BY id LIMIT 3)",
+            "id",
+            false
+        )
+        .is_ok());
+        assert!(keyset_page_sql(
+            "SELECT * FROM users WHERE note = 'order by limit' -- LIMIT",
+            "id",
+            false
+        )
+        .is_ok());
+    }
+}
+
+/// Tests for classifying where writes land by connection mode
+mod write_route_tests {
+    use crate::models::{Mode, WriteRoute};
+    use crate::utils::{require_replica, write_route};
+
+    const WRITE: &str = "INSERT INTO users (name) VALUES ('Alice')";
+
+    #[test]
+    fn test_write_lands_per_mode() {
+        assert_eq!(write_route(Mode::Local, WRITE), WriteRoute::Local);
+        assert_eq!(write_route(Mode::Remote, WRITE), WriteRoute::Remote);
+        assert_eq!(write_route(Mode::RemoteReplica, WRITE), WriteRoute::Replica);
+    }
+
+    #[test]
+    fn test_reads_are_reads_in_every_mode() {
+        for mode in [Mode::Local, Mode::Remote, Mode::RemoteReplica] {
+            assert_eq!(write_route(mode, "SELECT * FROM users"), WriteRoute::Read);
+            assert_eq!(write_route(mode, "PRAGMA user_version"), WriteRoute::Read);
+            assert_eq!(write_route(mode, "BEGIN"), WriteRoute::Read);
+        }
+    }
+
+    #[test]
+    fn test_every_write_kind_is_classified() {
+        for sql in [
+            "update users SET name = 'Bob'",
+            "DELETE FROM users",
+            "REPLACE INTO users (id) VALUES (1)",
+            "CREATE TABLE t (x)",
+            "DROP TABLE t",
+            "ALTER TABLE t ADD COLUMN y",
+        ] {
+            assert_eq!(
+                write_route(Mode::RemoteReplica, sql),
+                WriteRoute::Replica,
+                "{sql}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_replica_only_operations_allow_replicas() {
+        // The `:not_a_replica` error needs a BEAM env to build its atom, so the
+        // rejecting modes are covered by test/write_route_test.exs instead
+        assert!(require_replica(Mode::RemoteReplica).is_ok());
+    }
+}
+
+/// Tests for reading generated column expressions from `CREATE TABLE` SQL
+mod generated_column_expr_tests {
+    use crate::utils::generated_column_expr;
+
+    const SQL: &str = "CREATE TABLE t (
+        a INTEGER CHECK (a > 0),
+        [b c] TEXT GENERATED ALWAYS AS (a || ')') STORED, -- note ( unbalanced
+        \"d\"\"e\" AS ((a + 1) * 2),
+        f TEXT DEFAULT 'AS (x)',
+        CONSTRAINT pk PRIMARY KEY (a)
+    )";
+
+    #[test]
+    fn test_reads_expressions_with_nested_parentheses_and_literals() {
+        assert_eq!(
+            generated_column_expr(SQL, "b c"),
+            Some("a || ')'".to_string())
+        );
+        assert_eq!(
+            generated_column_expr(SQL, "d\"e"),
+            Some("(a + 1) * 2".to_string())
+        );
+    }
+
+    #[test]
+    fn test_matches_names_case_insensitively() {
+        assert_eq!(
+            generated_column_expr(SQL, "B C"),
+            Some("a || ')'".to_string())
+        );
+    }
+
+    #[test]
+    fn test_ordinary_and_unknown_columns_have_no_expression() {
+        assert_eq!(generated_column_expr(SQL, "a"), None);
+        assert_eq!(generated_column_expr(SQL, "f"), None);
+        assert_eq!(generated_column_expr(SQL, "missing"), None);
+    }
+}
+
+/// Tests for coercing numeric text by declared column type
+mod coerce_numeric_text_tests {
+    use crate::utils::coerce_numeric_text;
+    use libsql::Value;
+
+    fn text(s: &str) -> Value {
+        Value::Text(s.to_string())
+    }
+
+    #[test]
+    fn test_integer_affinity_yields_integers() {
+        assert_eq!(
+            coerce_numeric_text(text("123"), Some("INTEGER")),
+            Value::Integer(123)
+        );
+        assert_eq!(
+            coerce_numeric_text(text(" -7 "), Some("bigint")),
+            Value::Integer(-7)
+        );
+        assert_eq!(
+            coerce_numeric_text(text("1.5"), Some("INT")),
+            Value::Real(1.5)
+        );
+    }
+
+    #[test]
+    fn test_real_and_numeric_affinity() {
+        assert_eq!(
+            coerce_numeric_text(text("2"), Some("REAL")),
+            Value::Real(2.0)
+        );
+        assert_eq!(
+            coerce_numeric_text(text("2"), Some("DECIMAL(10,2)")),
+            Value::Integer(2)
+        );
+        assert_eq!(
+            coerce_numeric_text(text("2.25"), Some("NUMERIC")),
+            Value::Real(2.25)
+        );
+    }
+
+    #[test]
+    fn test_text_blob_and_undeclared_columns_are_untouched() {
+        for decl in [Some("TEXT"), Some("VARCHAR(10)"), Some("BLOB"), None] {
+            assert_eq!(coerce_numeric_text(text("123"), decl), text("123"));
+        }
+    }
+
+    #[test]
+    fn test_non_numeric_text_is_untouched() {
+        for s in ["abc", "", "inf", "NaN", "12abc"] {
+            assert_eq!(coerce_numeric_text(text(s), Some("INTEGER")), text(s));
+        }
+        assert_eq!(
+            coerce_numeric_text(Value::Integer(5), Some("TEXT")),
+            Value::Integer(5)
+        );
+    }
+}
+
+/// Tests for normalising datetime text by declared column type
+mod normalise_datetime_text_tests {
+    use crate::utils::normalise_datetime_text;
+    use libsql::Value;
+
+    fn text(s: &str) -> Value {
+        Value::Text(s.to_string())
+    }
+
+    #[test]
+    fn test_datetime_shapes_become_iso8601() {
+        let cases = [
+            ("2024-01-02 03:04:05", "2024-01-02T03:04:05"),
+            ("2024-01-02T03:04:05", "2024-01-02T03:04:05"),
+            ("2024-01-02 03:04", "2024-01-02T03:04:00"),
+            ("2024-01-02 03:04:05.123", "2024-01-02T03:04:05.123"),
+            ("2024-01-02 03:04:05Z", "2024-01-02T03:04:05Z"),
+            ("2024-01-02 03:04:05 +0930", "2024-01-02T03:04:05+09:30"),
+            ("2024-01-02T03:04:05.5-05:00", "2024-01-02T03:04:05.5-05:00"),
+            ("2024-01-02", "2024-01-02"),
+        ];
+        for (stored, expected) in cases {
+            assert_eq!(
+                normalise_datetime_text(text(stored), Some("DATETIME")),
+                text(expected),
+                "{stored}"
+            );
+        }
+        assert_eq!(
+            normalise_datetime_text(text("2024-01-02 03:04:05"), Some("timestamp")),
+            text("2024-01-02T03:04:05")
+        );
+    }
+
+    #[test]
+    fn test_other_columns_and_unrecognised_text_are_untouched() {
+        for decl in [Some("TEXT"), Some("INTEGER"), None] {
+            assert_eq!(
+                normalise_datetime_text(text("2024-01-02 03:04:05"), decl),
+                text("2024-01-02 03:04:05")
+            );
+        }
+        for s in [
+            "yesterday",
+            "2024-1-2",
+            "2024-01-02 3:04",
+            "2024-01-02 03:04:05 PST",
+        ] {
+            assert_eq!(normalise_datetime_text(text(s), Some("DATETIME")), text(s));
+        }
+        assert_eq!(
+            normalise_datetime_text(Value::Integer(1_700_000_000), Some("DATETIME")),
+            Value::Integer(1_700_000_000)
+        );
+    }
+}
+
+/// Tests for result size budgets
+mod result_budget_tests {
+    use crate::utils::{result_value_size, ResultBudget};
+    use libsql::Value;
+
+    #[test]
+    fn test_value_sizes() {
+        assert_eq!(result_value_size(&Value::Blob(vec![0; 100])), 100);
+        assert_eq!(result_value_size(&Value::Text("héllo".to_string())), 6);
+        assert_eq!(result_value_size(&Value::Integer(1)), 8);
+        assert_eq!(result_value_size(&Value::Real(1.0)), 8);
+        assert_eq!(result_value_size(&Value::Null), 0);
+    }
+
+    #[test]
+    fn test_budget_reports_bytes_once_exceeded() {
+        let mut budget = ResultBudget::new(Some(250));
+        let blob = Value::Blob(vec![0; 100]);
+        assert_eq!(budget.add(&blob), Ok(()));
+        assert_eq!(budget.add(&blob), Ok(()));
+        assert_eq!(budget.add(&Value::Null), Ok(()));
+        assert_eq!(budget.add(&blob), Err(300));
+    }
+
+    #[test]
+    fn test_budget_at_limit_is_allowed() {
+        let mut budget = ResultBudget::new(Some(16));
+        assert_eq!(budget.add(&Value::Integer(1)), Ok(()));
+        assert_eq!(budget.add(&Value::Integer(2)), Ok(()));
+        assert_eq!(budget.add(&Value::Integer(3)), Err(24));
+    }
+
+    #[test]
+    fn test_unlimited_budget_never_fails() {
+        let mut budget = ResultBudget::new(None);
+        for _ in 0..10 {
+            assert_eq!(budget.add(&Value::Blob(vec![0; 1 << 20])), Ok(()));
+        }
+    }
+}
+
+mod place_indexed_params_tests {
+    use crate::utils::place_indexed_params;
+    use libsql::Value;
+
+    #[test]
+    fn test_values_land_on_their_index() {
+        let params = place_indexed_params(
+            2,
+            vec![
+                (2, Value::Text("second".to_string())),
+                (1, Value::Integer(1)),
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            params,
+            vec![Value::Integer(1), Value::Text("second".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_missing_indexes_are_null() {
+        let params = place_indexed_params(3, vec![(3, Value::Integer(3))]).unwrap();
+        assert_eq!(params, vec![Value::Null, Value::Null, Value::Integer(3)]);
+        assert_eq!(place_indexed_params(0, vec![]).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn test_out_of_range_indexes_are_rejected() {
+        for index in [0, -1, 3] {
+            let error = place_indexed_params(2, vec![(index, Value::Null)]).unwrap_err();
+            assert!(error.contains("out of range"), "{error}");
+        }
+    }
+
+    #[test]
+    fn test_duplicate_indexes_are_rejected() {
+        let error = place_indexed_params(2, vec![(1, Value::Integer(1)), (1, Value::Integer(2))])
+            .unwrap_err();
+        assert!(error.contains("more than once"));
+    }
+}
+
+/// Tests for decoding legacy-encoded text stored as blobs
+mod decode_text_bytes_tests {
+    use crate::models::TextEncoding;
+    use crate::utils::{decode_blob_columns, decode_text_bytes};
+    use libsql::Value;
+
+    #[test]
+    fn test_latin1_to_utf8() {
+        // "café £5" in ISO-8859-1
+        let bytes = [0x63, 0x61, 0x66, 0xE9, 0x20, 0xA3, 0x35];
+        assert_eq!(
+            decode_text_bytes(&bytes, TextEncoding::Latin1).unwrap(),
+            "café £5"
+        );
+    }
+
+    #[test]
+    fn test_utf16le_to_utf8() {
+        let bytes: Vec<u8> = "héllo 🦀"
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect();
+        assert_eq!(
+            decode_text_bytes(&bytes, TextEncoding::Utf16Le).unwrap(),
+            "héllo 🦀"
+        );
+    }
+
+    #[test]
+    fn test_utf16le_rejects_invalid_input() {
+        assert!(
+            decode_text_bytes(&[0x61, 0x00, 0x62], TextEncoding::Utf16Le)
+                .unwrap_err()
+                .contains("odd byte length")
+        );
+        // A lone high surrogate
+        assert!(decode_text_bytes(&[0x3D, 0xD8], TextEncoding::Utf16Le).is_err());
+    }
+
+    #[test]
+    fn test_decode_blob_columns_only_touches_blobs_in_named_columns() {
+        let columns = vec!["id".to_string(), "name".to_string()];
+        let mut rows = vec![
+            vec![Value::Integer(1), Value::Blob(vec![0x63, 0x61, 0x66, 0xE9])],
+            vec![Value::Integer(2), Value::Null],
+            vec![Value::Integer(3), Value::Text("already".to_string())],
+        ];
+        decode_blob_columns(
+            &columns,
+            &mut rows,
+            &[("name".to_string(), TextEncoding::Latin1)],
+        )
+        .unwrap();
+
+        assert_eq!(rows[0][1], Value::Text("café".to_string()));
+        assert_eq!(rows[1][1], Value::Null);
+        assert_eq!(rows[2][1], Value::Text("already".to_string()));
+        assert_eq!(rows[0][0], Value::Integer(1));
+    }
+
+    #[test]
+    fn test_decode_blob_columns_rejects_unknown_column() {
+        let error = decode_blob_columns(
+            &["id".to_string()],
+            &mut [],
+            &[("nmae".to_string(), TextEncoding::Latin1)],
+        )
+        .unwrap_err();
+        assert!(error.contains("nmae"));
+    }
+}
+
+mod row_fingerprint_tests {
+    use crate::utils::row_fingerprint_of;
+    use libsql::Value;
+
+    #[test]
+    fn test_values_of_different_types_differ() {
+        let fingerprints = [
+            row_fingerprint_of(&[Value::Null]),
+            row_fingerprint_of(&[Value::Integer(0)]),
+            row_fingerprint_of(&[Value::Real(0.0)]),
+            row_fingerprint_of(&[Value::Text(String::new())]),
+            row_fingerprint_of(&[Value::Blob(vec![])]),
+            row_fingerprint_of(&[]),
+        ];
+        for (i, a) in fingerprints.iter().enumerate() {
+            assert_eq!(a.len(), 16);
+            for b in &fingerprints[i + 1..] {
+                assert_ne!(a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn test_value_boundaries_are_part_of_the_fingerprint() {
+        assert_ne!(
+            row_fingerprint_of(&[Value::Text("ab".into()), Value::Text("c".into())]),
+            row_fingerprint_of(&[Value::Text("a".into()), Value::Text("bc".into())])
+        );
+        assert_ne!(
+            row_fingerprint_of(&[Value::Blob(vec![1, 2]), Value::Null]),
+            row_fingerprint_of(&[Value::Null, Value::Blob(vec![1, 2])])
+        );
+    }
+}
+
+mod json_tests {
+    use crate::utils::{base64_encode, push_json_string, push_json_value};
+    use libsql::Value;
+
+    fn json(value: &Value) -> String {
+        let mut out = String::new();
+        push_json_value(&mut out, value);
+        out
+    }
+
+    #[test]
+    fn test_base64_padding() {
+        assert_eq!(base64_encode(b""), "");
+        assert_eq!(base64_encode(b"f"), "Zg==");
+        assert_eq!(base64_encode(b"fo"), "Zm8=");
+        assert_eq!(base64_encode(b"foo"), "Zm9v");
+        assert_eq!(base64_encode(&[0xFB, 0xFF]), "+/8=");
+    }
+
+    #[test]
+    fn test_strings_are_escaped() {
+        let mut out = String::new();
+        push_json_string(&mut out, "a\"b\\c\nd\te\u{1}é");
+        assert_eq!(out, r#""a\"b\\c\nd\te\u0001é""#);
+    }
+
+    #[test]
+    fn test_values() {
+        assert_eq!(json(&Value::Null), "null");
+        assert_eq!(json(&Value::Integer(-42)), "-42");
+        assert_eq!(json(&Value::Real(1.5)), "1.5");
+        assert_eq!(json(&Value::Real(2.0)), "2.0");
+        assert_eq!(json(&Value::Real(f64::NAN)), "null");
+        assert_eq!(json(&Value::Text("hi".into())), r#""hi""#);
+        assert_eq!(json(&Value::Blob(vec![1, 2, 3])), r#""AQID""#);
+    }
+}
+
+/// Tests for predefined result column transforms
+mod transform_tests {
+    use crate::models::ColumnTransform;
+    use crate::utils::apply_transform;
+    use libsql::Value;
+
+    fn text(s: &str) -> Value {
+        Value::Text(s.to_string())
+    }
+
+    #[test]
+    fn test_text_transforms() {
+        assert_eq!(
+            apply_transform(text("Ada@Example.COM"), ColumnTransform::Lower),
+            text("ada@example.com")
+        );
+        assert_eq!(
+            apply_transform(text("straße"), ColumnTransform::Upper),
+            text("STRASSE")
+        );
+        assert_eq!(
+            apply_transform(text(" \tpadded\n "), ColumnTransform::Trim),
+            text("padded")
+        );
+        assert_eq!(
+            apply_transform(text("hi!"), ColumnTransform::Base64),
+            text("aGkh")
+        );
+    }
+
+    #[test]
+    fn test_base64_encodes_blobs_as_text() {
+        assert_eq!(
+            apply_transform(Value::Blob(vec![0xff, 0x00]), ColumnTransform::Base64),
+            text("/wA=")
+        );
+    }
+
+    #[test]
+    fn test_other_values_pass_through() {
+        for transform in [
+            ColumnTransform::Lower,
+            ColumnTransform::Upper,
+            ColumnTransform::Trim,
+            ColumnTransform::Base64,
+        ] {
+            assert_eq!(apply_transform(Value::Null, transform), Value::Null);
+            assert_eq!(
+                apply_transform(Value::Integer(42), transform),
+                Value::Integer(42)
+            );
+            assert_eq!(
+                apply_transform(Value::Real(1.5), transform),
+                Value::Real(1.5)
+            );
+        }
+        assert_eq!(
+            apply_transform(Value::Blob(vec![1]), ColumnTransform::Lower),
+            Value::Blob(vec![1])
+        );
+    }
+}
+
+/// Tests for conflict clause detection
+mod conflict_action_tests {
+    use crate::models::ConflictAction;
+    use crate::utils::detect_conflict_action;
+
+    #[test]
+    fn test_plain_writes_abort() {
+        assert_eq!(
+            detect_conflict_action("INSERT INTO t (a) VALUES (1)"),
+            Some(ConflictAction::Abort)
+        );
+        assert_eq!(
+            detect_conflict_action("UPDATE t SET a = 1"),
+            Some(ConflictAction::Abort)
+        );
+        assert_eq!(
+            detect_conflict_action("INSERT OR ABORT INTO t (a) VALUES (1)"),
+            Some(ConflictAction::Abort)
+        );
+    }
+
+    #[test]
+    fn test_insert_or_ignore() {
+        assert_eq!(
+            detect_conflict_action("insert or ignore into t (a) values (1)"),
+            Some(ConflictAction::Ignore)
+        );
+    }
+
+    #[test]
+    fn test_insert_or_replace_and_replace() {
+        assert_eq!(
+            detect_conflict_action("INSERT OR REPLACE INTO t (a) VALUES (1)"),
+            Some(ConflictAction::Replace)
+        );
+        assert_eq!(
+            detect_conflict_action("REPLACE INTO t (a) VALUES (1)"),
+            Some(ConflictAction::Replace)
+        );
+    }
+
+    #[test]
+    fn test_insert_or_rollback_and_fail() {
+        assert_eq!(
+            detect_conflict_action("INSERT OR ROLLBACK INTO t (a) VALUES (1)"),
+            Some(ConflictAction::Rollback)
+        );
+        assert_eq!(
+            detect_conflict_action("INSERT OR FAIL INTO t (a) VALUES (1)"),
+            Some(ConflictAction::Fail)
+        );
+    }
+
+    #[test]
+    fn test_update_or_clause() {
+        assert_eq!(
+            detect_conflict_action("UPDATE OR IGNORE t SET a = 1"),
+            Some(ConflictAction::Ignore)
+        );
+    }
+
+    #[test]
+    fn test_comments_and_whitespace_are_skipped() {
+        assert_eq!(
+            detect_conflict_action(
+                "  -- upsert\n INSERT /* note */\n  OR\tIGNORE INTO t VALUES (1)"
+            ),
+            Some(ConflictAction::Ignore)
+        );
+    }
+
+    #[test]
+    fn test_other_statements_have_no_action() {
+        assert_eq!(detect_conflict_action("SELECT 1"), None);
+        assert_eq!(detect_conflict_action("DELETE FROM t"), None);
+        assert_eq!(
+            detect_conflict_action("WITH x AS (SELECT 1) INSERT INTO t SELECT * FROM x"),
+            None
+        );
+        assert_eq!(detect_conflict_action(""), None);
+    }
+}
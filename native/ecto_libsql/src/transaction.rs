@@ -19,8 +19,9 @@ use crate::{
     models::TransactionEntry,
     utils,
 };
-use rustler::{Atom, Env, NifResult, Term};
+use rustler::{Atom, Encoder, Env, NifResult, Term};
 use std::sync::MutexGuard;
+use std::time::Duration;
 
 /// RAII guard for transaction entry management.
 ///
@@ -110,6 +111,22 @@ impl TransactionEntryGuard {
             .ok_or_else(|| rustler::Error::Term(Box::new("Transaction entry is missing")))
     }
 
+    /// Get mutable access to the transaction's open savepoint names.
+    ///
+    /// Returns an error if the entry has already been consumed via `consume()`.
+    pub fn savepoints_mut(&mut self) -> Result<&mut Vec<String>, rustler::Error> {
+        if self.consumed {
+            return Err(rustler::Error::Term(Box::new(
+                "Transaction entry already consumed",
+            )));
+        }
+
+        self.entry
+            .as_mut()
+            .map(|e| &mut e.savepoints)
+            .ok_or_else(|| rustler::Error::Term(Box::new("Transaction entry is missing")))
+    }
+
     /// Consume the guard without re-inserting the entry.
     ///
     /// This is used for commit/rollback operations where the transaction
@@ -193,6 +210,8 @@ pub fn begin_transaction(conn_id: &str) -> NifResult<String> {
     let entry = TransactionEntry {
         conn_id: conn_id.to_string(),
         transaction: trx,
+        savepoints: Vec::new(),
+        restore_busy_timeout_ms: None,
     };
     utils::safe_lock(&TXN_REGISTRY, "begin_transaction txn_registry")?
         .insert(trx_id.clone(), entry);
@@ -200,6 +219,19 @@ pub fn begin_transaction(conn_id: &str) -> NifResult<String> {
     Ok(trx_id)
 }
 
+/// Decode a transaction behaviour atom for the `begin_transaction_*` NIFs.
+///
+/// An unrecognised behaviour is returned to Elixir as an error naming the valid ones,
+/// so the application can handle it explicitly.
+fn transaction_behavior(behavior: Atom) -> NifResult<libsql::TransactionBehavior> {
+    decode::decode_transaction_behavior(behavior).ok_or_else(|| {
+        rustler::Error::Term(Box::new(format!(
+            "Invalid transaction behavior: {behavior:?}. \
+             Use :deferred, :immediate, :exclusive, or :read_only"
+        )))
+    })
+}
+
 /// Begin a new database transaction with specific locking behaviour.
 ///
 /// Allows control over how aggressively the transaction acquires locks:
@@ -215,13 +247,7 @@ pub fn begin_transaction(conn_id: &str) -> NifResult<String> {
 /// Returns a transaction ID on success, error on failure.
 #[rustler::nif(schedule = "DirtyIo")]
 pub fn begin_transaction_with_behavior(conn_id: &str, behavior: Atom) -> NifResult<String> {
-    let Some(trx_behavior) = decode::decode_transaction_behavior(behavior) else {
-        // Unrecognised behaviour - return error to Elixir for proper logging
-        // This allows the application to handle unknown behaviours explicitly
-        return Err(rustler::Error::Term(Box::new(format!(
-            "Invalid transaction behavior: {behavior:?}. Use :deferred, :immediate, :exclusive, or :read_only"
-        ))));
-    };
+    let trx_behavior = transaction_behavior(behavior)?;
 
     let conn_map = utils::safe_lock(
         &CONNECTION_REGISTRY,
@@ -257,6 +283,8 @@ pub fn begin_transaction_with_behavior(conn_id: &str, behavior: Atom) -> NifResu
     let entry = TransactionEntry {
         conn_id: conn_id.to_string(),
         transaction: trx,
+        savepoints: Vec::new(),
+        restore_busy_timeout_ms: None,
     };
     utils::safe_lock(
         &TXN_REGISTRY,
@@ -267,25 +295,168 @@ pub fn begin_transaction_with_behavior(conn_id: &str, behavior: Atom) -> NifResu
     Ok(trx_id)
 }
 
+/// Begin a transaction with its own busy timeout.
+///
+/// The busy timeout is set before `BEGIN`, so acquiring the transaction's locks also
+/// waits for up to `busy_timeout_ms`. Returns the transaction and the connection's
+/// previous busy timeout, which is restored straight away if `BEGIN` fails.
+pub async fn begin_with_busy_timeout(
+    conn: &libsql::Connection,
+    behavior: libsql::TransactionBehavior,
+    busy_timeout_ms: u64,
+) -> Result<(libsql::Transaction, u64), String> {
+    let previous_ms = utils::read_busy_timeout(conn).await?;
+    conn.busy_timeout(Duration::from_millis(busy_timeout_ms))
+        .map_err(|e| format!("busy_timeout failed: {e}"))?;
+
+    match conn.transaction_with_behavior(behavior).await {
+        Ok(trx) => Ok((trx, previous_ms)),
+        Err(e) => {
+            // Report the begin failure in preference to a restore failure
+            let _ = conn.busy_timeout(Duration::from_millis(previous_ms));
+            Err(format!("Begin failed: {e}"))
+        }
+    }
+}
+
+/// Restore the busy timeout a transaction overrode, if it was begun with one.
+pub fn restore_busy_timeout(entry: &TransactionEntry) -> Result<(), String> {
+    match entry.restore_busy_timeout_ms {
+        Some(timeout_ms) => entry
+            .transaction
+            .busy_timeout(Duration::from_millis(timeout_ms))
+            .map_err(|e| format!("Failed to restore busy_timeout: {e}")),
+        None => Ok(()),
+    }
+}
+
+/// Commit or roll back a transaction taken from the registry.
+///
+/// A busy timeout overridden for the transaction stays in effect for the commit
+/// itself, and is restored afterwards whether or not the commit succeeded.
+pub async fn finish_transaction(entry: TransactionEntry, commit: bool) -> Result<(), String> {
+    let conn = (*entry.transaction).clone();
+    let restore_ms = entry.restore_busy_timeout_ms;
+
+    let finished = if commit {
+        entry
+            .transaction
+            .commit()
+            .await
+            .map_err(|e| format!("Commit error: {e}"))
+    } else {
+        entry
+            .transaction
+            .rollback()
+            .await
+            .map_err(|e| format!("Rollback error: {e}"))
+    };
+
+    let restored = match restore_ms {
+        Some(timeout_ms) => conn
+            .busy_timeout(Duration::from_millis(timeout_ms))
+            .map_err(|e| format!("Failed to restore busy_timeout: {e}")),
+        None => Ok(()),
+    };
+
+    finished?;
+    restored
+}
+
+/// Begin a new database transaction with its own busy timeout.
+///
+/// Sets the connection's busy timeout to `busy_timeout_ms` for the lifetime of the
+/// transaction, so a contended write can wait longer (or shorter) than the
+/// connection default. The previous timeout is restored when the transaction is
+/// committed, rolled back or closed.
+///
+/// # Arguments
+/// - `conn_id`: Database connection ID
+/// - `behavior`: Transaction behavior atom, as for `begin_transaction_with_behavior`
+/// - `busy_timeout_ms`: Busy timeout in milliseconds while the transaction is open
+///
+/// Returns a transaction ID on success, error on failure.
+#[rustler::nif(schedule = "DirtyIo")]
+pub fn begin_transaction_with_timeout(
+    conn_id: &str,
+    behavior: Atom,
+    busy_timeout_ms: u64,
+) -> NifResult<String> {
+    let trx_behavior = transaction_behavior(behavior)?;
+
+    let client = {
+        let conn_map = utils::safe_lock(
+            &CONNECTION_REGISTRY,
+            "begin_transaction_with_timeout conn_map",
+        )?;
+        conn_map
+            .get(conn_id)
+            .cloned()
+            .ok_or_else(|| rustler::Error::Term(Box::new("Invalid connection ID")))?
+    }; // Lock dropped here
+
+    // Clone the inner connection Arc and drop the outer lock before async operations
+    let connection = {
+        let client_guard = utils::safe_lock_arc(&client, "begin_transaction_with_timeout client")?;
+        client_guard.client.clone()
+    }; // Outer lock dropped here
+
+    // SAFETY: We use TOKIO_RUNTIME.block_on(), which runs the future synchronously on a dedicated
+    // thread pool. This prevents deadlocks that could occur if we were in a true async context
+    // with std::sync::Mutex guards held across await points.
+    #[allow(clippy::await_holding_lock)]
+    let (trx, previous_ms) = TOKIO_RUNTIME.block_on(async {
+        let conn_guard = utils::safe_lock_arc(&connection, "begin_transaction_with_timeout conn")?;
+        begin_with_busy_timeout(&conn_guard, trx_behavior, busy_timeout_ms)
+            .await
+            .map_err(|e| rustler::Error::Term(Box::new(e)))
+    })?;
+
+    let trx_id = uuid::Uuid::new_v4().to_string();
+    let entry = TransactionEntry {
+        conn_id: conn_id.to_string(),
+        transaction: trx,
+        savepoints: Vec::new(),
+        restore_busy_timeout_ms: Some(previous_ms),
+    };
+    utils::safe_lock(&TXN_REGISTRY, "begin_transaction_with_timeout txn_registry")?
+        .insert(trx_id.clone(), entry);
+
+    Ok(trx_id)
+}
+
 /// Execute a SQL statement within a transaction without returning rows.
 ///
 /// Use this for INSERT, UPDATE, DELETE statements within a transaction.
 /// For statements that return rows, use `query_with_trx_args` instead.
 ///
-/// Returns the number of affected rows.
+/// Returns the number of rows changed by this statement. A no-op write, such as
+/// `INSERT ... ON CONFLICT DO NOTHING` hitting a conflict, returns `0`.
+///
+/// With `return_rowid`, the transaction connection's `last_insert_rowid` is read in
+/// the same call, straight after the statement, so it reflects this insert even
+/// before the transaction commits.
 ///
 /// # Arguments
+/// - `env`: Elixir environment
 /// - `trx_id`: Transaction ID
 /// - `conn_id`: Connection ID (for ownership verification)
 /// - `query`: SQL query string
 /// - `args`: Query parameters
+/// - `return_rowid`: Also return the last inserted rowid
+///
+/// # Returns
+/// - `affected` - Rows changed, when `return_rowid` is `false`
+/// - `{affected, last_rowid}` - When `return_rowid` is `true`
 #[rustler::nif(schedule = "DirtyIo")]
 pub fn execute_with_transaction<'a>(
+    env: Env<'a>,
     trx_id: &str,
     conn_id: &str,
     query: &str,
     args: Vec<Term<'a>>,
-) -> NifResult<u64> {
+    return_rowid: bool,
+) -> NifResult<Term<'a>> {
     // Decode args before locking
     let decoded_args: Vec<libsql::Value> = args
         .into_iter()
@@ -298,10 +469,24 @@ pub fn execute_with_transaction<'a>(
 
     // Get transaction reference (already returns rustler::Error on failure)
     let trx = guard.transaction()?;
+    utils::count_statements(conn_id, 1);
 
     let result = TOKIO_RUNTIME
-        .block_on(async { trx.execute(query, decoded_args).await })
-        .map_err(|e| rustler::Error::Term(Box::new(format!("Execute failed: {e}"))));
+        .block_on(async {
+            let affected = trx.execute(query, decoded_args).await?;
+            Ok::<_, libsql::Error>((affected, trx.last_insert_rowid()))
+        })
+        .map(|(affected, last_rowid)| {
+            if return_rowid {
+                (affected, last_rowid).encode(env)
+            } else {
+                affected.encode(env)
+            }
+        })
+        .map_err(|e| {
+            utils::record_last_error(conn_id, &e);
+            rustler::Error::Term(Box::new(format!("Execute failed: {e}")))
+        });
     // Guard automatically re-inserts the entry on drop
     result
 }
@@ -318,6 +503,8 @@ pub fn execute_with_transaction<'a>(
 /// - `conn_id`: Connection ID (for ownership verification)
 /// - `query`: SQL query string
 /// - `args`: Query parameters
+/// - `max_result_bytes`: Optional limit on the total size of returned values, as for
+///   `query_args`
 #[rustler::nif(schedule = "DirtyIo")]
 pub fn query_with_trx_args<'a>(
     env: Env<'a>,
@@ -325,6 +512,7 @@ pub fn query_with_trx_args<'a>(
     conn_id: &str,
     query: &str,
     args: Vec<Term<'a>>,
+    max_result_bytes: Option<u64>,
 ) -> NifResult<Term<'a>> {
     // UTF-8 validation is guaranteed by Rust's &str type and Rustler's conversion,
     // so we can rely on the type system rather than runtime checks.
@@ -345,14 +533,15 @@ pub fn query_with_trx_args<'a>(
     // Get transaction reference (already returns rustler::Error on failure)
     let trx = guard.transaction()?;
 
-    // Get connection for error enhancement
-    let connection = {
+    // Get connection for error enhancement, and the column naming to apply as in `query_args`
+    let (connection, column_naming) = {
         let conn_map = utils::safe_lock(&CONNECTION_REGISTRY, "query_with_trx_args conn_map")?;
         let client = conn_map
             .get(conn_id)
             .ok_or_else(|| rustler::Error::Term(Box::new("Connection not found")))?;
         let client_guard = utils::safe_lock_arc(client, "query_with_trx_args client")?;
-        client_guard.client.clone()
+        utils::count_statements(conn_id, 1);
+        (client_guard.client.clone(), client_guard.column_naming)
     };
 
     // Execute async operation without holding the lock
@@ -363,19 +552,31 @@ pub fn query_with_trx_args<'a>(
     let result = TOKIO_RUNTIME.block_on(async {
         if use_query {
             // Statements that return rows (SELECT, or INSERT/UPDATE/DELETE with RETURNING)
-            let res = trx.query(query, decoded_args).await;
+            let res =
+                utils::query_with_origin_tables(trx, query, decoded_args, column_naming).await;
 
             match res {
-                Ok(res_rows) => utils::collect_rows(env, res_rows).await,
+                Ok((res_rows, tables)) => {
+                    utils::collect_rows_named(
+                        env,
+                        res_rows,
+                        column_naming,
+                        &tables,
+                        max_result_bytes,
+                    )
+                    .await
+                }
                 Err(e) => {
-                    let error_msg = format!("Query failed: {e}");
                     // safe_lock_arc already returns rustler::Error with good context
                     let conn_guard: MutexGuard<libsql::Connection> =
                         utils::safe_lock_arc(&connection, "query_with_trx_args conn for error")?;
-                    let enhanced_msg = utils::enhance_constraint_error(&conn_guard, &error_msg)
-                        .await
-                        .unwrap_or(error_msg);
-                    Err(rustler::Error::Term(Box::new(enhanced_msg)))
+                    Err(utils::statement_error(
+                        &conn_guard,
+                        conn_id,
+                        &e,
+                        format!("Query failed: {e}"),
+                    )
+                    .await)
                 }
             }
         } else {
@@ -385,14 +586,16 @@ pub fn query_with_trx_args<'a>(
             match res {
                 Ok(rows_affected) => Ok(utils::build_empty_result(env, rows_affected)),
                 Err(e) => {
-                    let error_msg = format!("Execute failed: {e}");
                     // safe_lock_arc already returns rustler::Error with good context
                     let conn_guard: MutexGuard<libsql::Connection> =
                         utils::safe_lock_arc(&connection, "query_with_trx_args conn for error")?;
-                    let enhanced_msg = utils::enhance_constraint_error(&conn_guard, &error_msg)
-                        .await
-                        .unwrap_or(error_msg);
-                    Err(rustler::Error::Term(Box::new(enhanced_msg)))
+                    Err(utils::statement_error(
+                        &conn_guard,
+                        conn_id,
+                        &e,
+                        format!("Execute failed: {e}"),
+                    )
+                    .await)
                 }
             }
         }
@@ -423,7 +626,8 @@ pub fn handle_status_transaction(trx_id: &str) -> NifResult<Atom> {
 /// - `"commit"` - Commit the transaction
 /// - `"rollback"` - Rollback the transaction
 ///
-/// After commit or rollback, the transaction is removed from the registry.
+/// After commit or rollback, the transaction is removed from the registry, and any
+/// busy timeout the transaction overrode is restored.
 ///
 /// # Arguments
 /// - `trx_id`: Transaction ID
@@ -445,26 +649,9 @@ pub fn commit_or_rollback_transaction(
     // Consume the entry (we don't want to re-insert after commit/rollback)
     let entry = guard.consume()?;
 
-    let result = TOKIO_RUNTIME.block_on(async {
-        if param == "commit" {
-            entry
-                .transaction
-                .commit()
-                .await
-                .map_err(|e| format!("Commit error: {e}"))?;
-        } else {
-            entry
-                .transaction
-                .rollback()
-                .await
-                .map_err(|e| format!("Rollback error: {e}"))?;
-        }
-
-        // NOTE: LibSQL automatically syncs transaction commits to remote for embedded replicas.
-        // No manual sync needed here.
-
-        Ok::<_, String>(())
-    });
+    // NOTE: LibSQL automatically syncs transaction commits to remote for embedded replicas.
+    // No manual sync needed here.
+    let result = TOKIO_RUNTIME.block_on(finish_transaction(entry, param == "commit"));
 
     match result {
         Ok(()) => Ok((rustler::types::atom::ok(), format!("{param} success"))),
@@ -2,11 +2,19 @@
 ///
 /// This module provides commonly used helper functions for locking, error handling,
 /// value conversion, and result processing.
-use crate::models::LibSQLConn;
+use crate::constants::{
+    busy, error, not_a_replica, read_lock, result_too_large, write_lock,
+    BROKEN_CONNECTION_REGISTRY, ERROR_COUNT_REGISTRY, LAST_ERROR_REGISTRY,
+    STATEMENT_COUNT_REGISTRY,
+};
+use crate::models::{
+    BusyKind, ColumnNaming, ColumnTransform, ConflictAction, ErrorClass, LastError, LibSQLConn,
+    Mode, TextEncoding, WriteRoute,
+};
 use libsql::{Rows, Value};
 use rustler::types::atom::nil;
 use rustler::{Binary, Encoder, Env, OwnedBinary, Term};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::{Arc, Mutex, MutexGuard};
 use std::time::Duration;
 
@@ -34,15 +42,129 @@ pub fn safe_lock_arc<'a, T>(
     })
 }
 
+/// Convert a `libsql::Error` into a `LastError` record
+///
+/// Local SQLite failures carry the extended result code, from which the primary
+/// code is derived. Remote (Hrana) failures report both codes directly. Other
+/// libsql errors have no SQLite code and only keep their message.
+pub fn last_error_from(error: &libsql::Error) -> LastError {
+    match error {
+        libsql::Error::SqliteFailure(extended_code, message) => LastError {
+            code: Some(extended_code & 0xff),
+            extended_code: Some(*extended_code),
+            message: message.clone(),
+        },
+        libsql::Error::RemoteSqliteFailure(code, extended_code, message) => LastError {
+            code: Some(*code),
+            extended_code: Some(*extended_code),
+            message: message.clone(),
+        },
+        other => LastError {
+            code: None,
+            extended_code: None,
+            message: other.to_string(),
+        },
+    }
+}
+
+/// Classify a busy or locked error by the kind of lock it conflicted with
+///
+/// Returns `None` for errors that aren't `SQLITE_BUSY` or `SQLITE_LOCKED`, including
+/// errors without an SQLite result code.
+pub fn classify_busy(error: &LastError) -> Option<BusyKind> {
+    const SQLITE_BUSY: i32 = 5;
+    const SQLITE_LOCKED: i32 = 6;
+    const SQLITE_BUSY_SNAPSHOT: i32 = SQLITE_BUSY | (2 << 8);
+
+    match (error.code?, error.extended_code) {
+        (SQLITE_BUSY, Some(SQLITE_BUSY_SNAPSHOT)) | (SQLITE_LOCKED, _) => Some(BusyKind::ReadLock),
+        (SQLITE_BUSY, _) => Some(BusyKind::WriteLock),
+        _ => None,
+    }
+}
+
+/// Classify an error for the per-connection error counters
+///
+/// Busy and locked errors are classed as `Busy` whatever the lock (see
+/// `classify_busy`). Syntax errors are the parse failures SQLite reports under the
+/// generic `SQLITE_ERROR` code; other `SQLITE_ERROR`s, such as unknown tables, are
+/// `Other`.
+pub fn classify_error(error: &LastError) -> ErrorClass {
+    const SQLITE_ERROR: i32 = 1;
+    const SQLITE_CONSTRAINT: i32 = 19;
+
+    if classify_busy(error).is_some() {
+        return ErrorClass::Busy;
+    }
+    match error.code {
+        Some(SQLITE_CONSTRAINT) => ErrorClass::Constraint,
+        Some(SQLITE_ERROR)
+            if ["syntax error", "incomplete input", "unrecognized token"]
+                .iter()
+                .any(|needle| error.message.contains(needle)) =>
+        {
+            ErrorClass::Syntax
+        }
+        _ => ErrorClass::Other,
+    }
+}
+
+/// Record the most recent error for a connection
+///
+/// Also counts the error against the connection's error counters, which live in
+/// `ERROR_COUNT_REGISTRY` rather than on the connection, whose lock the caller may hold.
+///
+/// Best-effort: if a registry lock is poisoned the error is silently dropped,
+/// since this runs on error paths that are already reporting a failure.
+pub fn record_last_error(conn_id: &str, error: &libsql::Error) {
+    record_error(conn_id, last_error_from(error));
+}
+
+/// Record an error that didn't come from libsql, such as a timeout, for a connection
+///
+/// Like `record_last_error`, counting and storing it as the connection's last error.
+pub fn record_error(conn_id: &str, last_error: LastError) {
+    if let Ok(registry) = safe_lock(&ERROR_COUNT_REGISTRY, "record_last_error counts") {
+        if let Some(counts) = registry.get(conn_id) {
+            counts.record(classify_error(&last_error));
+        }
+    }
+
+    if let Ok(mut registry) = safe_lock(&LAST_ERROR_REGISTRY, "record_last_error") {
+        registry.insert(conn_id.to_string(), last_error);
+    }
+}
+
+/// Mark a connection as left in an unknown state, so its health check fails
+///
+/// Best-effort, like `record_last_error`, as it runs on paths already reporting a failure.
+pub fn mark_connection_broken(conn_id: &str, reason: String) {
+    if let Ok(mut registry) = safe_lock(&BROKEN_CONNECTION_REGISTRY, "mark_connection_broken") {
+        registry.insert(conn_id.to_string(), reason);
+    }
+}
+
+/// Fail with the reason a connection was marked broken, if it was
+pub fn check_not_broken(conn_id: &str) -> Result<(), String> {
+    let registry = safe_lock(&BROKEN_CONNECTION_REGISTRY, "check_not_broken")
+        .map_err(|_| "Failed to lock broken connection registry".to_string())?;
+    match registry.get(conn_id) {
+        Some(reason) => Err(format!("Connection is in an unknown state: {reason}")),
+        None => Ok(()),
+    }
+}
+
 /// Perform sync with timeout for remote replicas
 ///
-/// Executes a sync operation with a configurable timeout.
+/// Executes a sync operation with a configurable timeout. Sync failures and timeouts
+/// are recorded as the connection's last error.
 ///
 /// # Note on Lock Safety
 /// This function holds a std::sync::Mutex guard across an await point. This is intentional
 /// and safe because it is called within TOKIO_RUNTIME.block_on() which executes synchronously.
 #[allow(clippy::await_holding_lock)]
 pub async fn sync_with_timeout(
+    conn_id: &str,
     client: &Arc<Mutex<LibSQLConn>>,
     timeout_secs: u64,
 ) -> Result<(), String> {
@@ -51,15 +173,44 @@ pub async fn sync_with_timeout(
     tokio::time::timeout(timeout, async {
         let client_guard =
             safe_lock_arc(client, "sync_with_timeout client").map_err(|e| format!("{e:?}"))?;
-        client_guard
-            .db
-            .sync()
-            .await
-            .map_err(|e| format!("Sync error: {e}"))?;
+        client_guard.db.sync().await.map_err(|e| {
+            record_last_error(conn_id, &e);
+            format!("Sync error: {e}")
+        })?;
         Ok::<_, String>(())
     })
     .await
-    .map_err(|_| format!("Sync timeout after {timeout_secs} seconds"))?
+    .map_err(|_| {
+        let message = format!("Sync timeout after {timeout_secs} seconds");
+        record_error(
+            conn_id,
+            LastError {
+                code: None,
+                extended_code: None,
+                message: message.clone(),
+            },
+        );
+        message
+    })?
+}
+
+/// Read the connection's current busy timeout in milliseconds
+pub async fn read_busy_timeout(conn: &libsql::Connection) -> Result<u64, String> {
+    let mut rows = conn
+        .query("PRAGMA busy_timeout", ())
+        .await
+        .map_err(|e| format!("Failed to read busy_timeout: {e}"))?;
+    let timeout_ms: i64 = match rows
+        .next()
+        .await
+        .map_err(|e| format!("Failed to read busy_timeout: {e}"))?
+    {
+        Some(row) => row
+            .get(0)
+            .map_err(|e| format!("Failed to read busy_timeout: {e}"))?,
+        None => 0,
+    };
+    Ok(timeout_ms.max(0) as u64)
 }
 
 /// Build an empty result map for write operations (INSERT/UPDATE/DELETE without RETURNING)
@@ -74,6 +225,33 @@ pub fn build_empty_result<'a>(env: Env<'a>, rows_affected: u64) -> Term<'a> {
     result_map.encode(env)
 }
 
+/// Record a failed statement and build the error term returned for it.
+///
+/// Busy and locked errors become `{:busy, :read_lock | :write_lock, message}` so callers
+/// can choose between retrying and aborting; constraint errors are enhanced with index
+/// names; anything else is returned as `message`.
+pub async fn statement_error(
+    conn: &libsql::Connection,
+    conn_id: &str,
+    error: &libsql::Error,
+    message: String,
+) -> rustler::Error {
+    record_last_error(conn_id, error);
+
+    if let Some(kind) = classify_busy(&last_error_from(error)) {
+        let lock = match kind {
+            BusyKind::ReadLock => read_lock(),
+            BusyKind::WriteLock => write_lock(),
+        };
+        return rustler::Error::Term(Box::new((busy(), lock, message)));
+    }
+
+    let enhanced_msg = enhance_constraint_error(conn, &message)
+        .await
+        .unwrap_or(message);
+    rustler::Error::Term(Box::new(enhanced_msg))
+}
+
 /// Enhance constraint error messages with actual index names
 ///
 /// SQLite only reports column names in constraint errors, not index/constraint names.
@@ -120,14 +298,11 @@ pub async fn enhance_constraint_error(
         })
         .collect();
 
-    // Helper function to quote SQLite identifiers safely
-    let quote_identifier = |id: &str| -> String {
-        // Escape any double quotes by doubling them, then wrap in double quotes
-        format!("\"{}\"", id.replace("\"", "\"\""))
-    };
-
     // Query SQLite for unique indexes on this table
-    let pragma_query = format!("PRAGMA index_list({})", quote_identifier(table_name));
+    let pragma_query = format!(
+        "PRAGMA index_list({})",
+        quote_identifier(table_name, QuoteStyle::DoubleQuote)
+    );
     let params: Vec<Value> = vec![];
     let mut rows = conn
         .query(&pragma_query, params)
@@ -153,7 +328,10 @@ pub async fn enhance_constraint_error(
         }
 
         // Query the columns in this index
-        let info_query = format!("PRAGMA index_info({})", quote_identifier(&index_name));
+        let info_query = format!(
+            "PRAGMA index_info({})",
+            quote_identifier(&index_name, QuoteStyle::DoubleQuote)
+        );
         let info_params: Vec<Value> = vec![];
         let mut info_rows = conn
             .query(&info_query, info_params)
@@ -191,7 +369,93 @@ pub async fn enhance_constraint_error(
 /// Collect rows from a query result into a map of columns and rows
 ///
 /// Processes async row iterator and converts LibSQL values to Elixir terms.
-pub async fn collect_rows<'a>(env: Env<'a>, mut rows: Rows) -> Result<Term<'a>, rustler::Error> {
+/// Column names are returned exactly as SQLite reports them.
+pub async fn collect_rows<'a>(env: Env<'a>, rows: Rows) -> Result<Term<'a>, rustler::Error> {
+    collect_rows_named(env, rows, ColumnNaming::Raw, &[], None).await
+}
+
+/// Apply a predefined column transform to a value
+///
+/// Text is lowercased, uppercased, trimmed or base64-encoded; `Base64` also encodes
+/// blobs, returning text. Every other value, NULL included, is returned unchanged.
+pub fn apply_transform(value: Value, transform: ColumnTransform) -> Value {
+    match (transform, value) {
+        (ColumnTransform::Lower, Value::Text(text)) => Value::Text(text.to_lowercase()),
+        (ColumnTransform::Upper, Value::Text(text)) => Value::Text(text.to_uppercase()),
+        (ColumnTransform::Trim, Value::Text(text)) => Value::Text(text.trim().to_string()),
+        (ColumnTransform::Base64, Value::Text(text)) => Value::Text(base64_encode(text.as_bytes())),
+        (ColumnTransform::Base64, Value::Blob(blob)) => Value::Text(base64_encode(&blob)),
+        (_, value) => value,
+    }
+}
+
+/// Bytes a value contributes to a result, for `max_result_bytes` budgets
+///
+/// Text and blobs count their length, numbers 8 bytes, and NULL nothing.
+pub fn result_value_size(value: &Value) -> u64 {
+    match value {
+        Value::Text(text) => text.len() as u64,
+        Value::Blob(blob) => blob.len() as u64,
+        Value::Integer(_) | Value::Real(_) => 8,
+        Value::Null => 0,
+    }
+}
+
+/// Running total of result bytes against an optional limit
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ResultBudget {
+    limit: Option<u64>,
+    used: u64,
+}
+
+impl ResultBudget {
+    pub fn new(limit: Option<u64>) -> Self {
+        Self { limit, used: 0 }
+    }
+
+    /// Count `value` against the budget.
+    ///
+    /// Returns `Err(bytes_so_far)`, including this value, once the limit is exceeded.
+    pub fn add(&mut self, value: &Value) -> Result<(), u64> {
+        self.used = self.used.saturating_add(result_value_size(value));
+        match self.limit {
+            Some(limit) if self.used > limit => Err(self.used),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Collect rows like `collect_rows`, disambiguating duplicate column names
+///
+/// `tables` holds each column's origin table where known (from the prepared
+/// statement); it is only consulted for `ColumnNaming::TablePrefix`.
+///
+/// With `max_bytes`, the size of each value is added up as rows are read (see
+/// `result_value_size`). Once the total passes the limit, collection stops and
+/// `{:error, :result_too_large, bytes_so_far}` is returned in place of the result map.
+pub async fn collect_rows_named<'a>(
+    env: Env<'a>,
+    rows: Rows,
+    naming: ColumnNaming,
+    tables: &[Option<String>],
+    max_bytes: Option<u64>,
+) -> Result<Term<'a>, rustler::Error> {
+    collect_rows_transformed(env, rows, naming, tables, max_bytes, &[]).await
+}
+
+/// Collect rows like `collect_rows_named`, applying `transforms` as values are read
+///
+/// `transforms` holds the transform for each column by index, if any; columns past
+/// its end are left as they are. See `apply_transform`.
+pub async fn collect_rows_transformed<'a>(
+    env: Env<'a>,
+    mut rows: Rows,
+    naming: ColumnNaming,
+    tables: &[Option<String>],
+    max_bytes: Option<u64>,
+    transforms: &[Option<ColumnTransform>],
+) -> Result<Term<'a>, rustler::Error> {
+    let mut budget = ResultBudget::new(max_bytes);
     let mut column_names: Vec<String> = Vec::new();
     let mut collected_rows: Vec<Vec<Term<'a>>> = Vec::new();
     let mut column_count: usize = 0;
@@ -210,16 +474,21 @@ pub async fn collect_rows<'a>(env: Env<'a>, mut rows: Rows) -> Result<Term<'a>,
                     column_names.push(format!("col{i}"));
                 }
             }
+            column_names = dedupe_column_names(&column_names, tables, naming);
         }
 
         let mut row_terms = Vec::with_capacity(column_count);
         for i in 0..column_names.len() {
             let term = match row_result.get(i as i32) {
-                Ok(Value::Text(val)) => val.encode(env),
-                Ok(Value::Integer(val)) => val.encode(env),
-                Ok(Value::Real(val)) => val.encode(env),
-                Ok(Value::Blob(val)) => OwnedBinary::new(val.len())
-                    .ok_or_else(|| {
+                Ok(value) => {
+                    let value = match transforms.get(i).copied().flatten() {
+                        Some(transform) => apply_transform(value, transform),
+                        None => value,
+                    };
+                    if let Err(bytes_so_far) = budget.add(&value) {
+                        return Ok((error(), result_too_large(), bytes_so_far).encode(env));
+                    }
+                    encode_value(env, &value).ok_or_else(|| {
                         let col_name = column_names
                             .get(i)
                             .unwrap_or(&"unknown".to_string())
@@ -227,12 +496,8 @@ pub async fn collect_rows<'a>(env: Env<'a>, mut rows: Rows) -> Result<Term<'a>,
                         rustler::Error::Term(Box::new(format!(
                             "Failed to allocate binary for column '{col_name}' (index {i})"
                         )))
-                    })
-                    .map(|mut owned| {
-                        owned.as_mut_slice().copy_from_slice(&val);
-                        Binary::from_owned(owned, env).encode(env)
-                    })?,
-                Ok(Value::Null) => nil().encode(env),
+                    })?
+                }
                 Err(err) => {
                     let col_name = column_names
                         .get(i)
@@ -262,6 +527,325 @@ pub async fn collect_rows<'a>(env: Env<'a>, mut rows: Rows) -> Result<Term<'a>,
     Ok(result_map.encode(env))
 }
 
+/// Encode a LibSQL value as an Elixir term
+///
+/// Blobs become binaries and NULL becomes `nil`. Returns `None` if a binary could not
+/// be allocated for a blob.
+pub fn encode_value<'a>(env: Env<'a>, value: &Value) -> Option<Term<'a>> {
+    match value {
+        Value::Text(val) => Some(val.encode(env)),
+        Value::Integer(val) => Some(val.encode(env)),
+        Value::Real(val) => Some(val.encode(env)),
+        Value::Blob(val) => OwnedBinary::new(val.len()).map(|mut owned| {
+            owned.as_mut_slice().copy_from_slice(val);
+            Binary::from_owned(owned, env).encode(env)
+        }),
+        Value::Null => Some(nil().encode(env)),
+    }
+}
+
+/// Convert numeric-looking text back to a number when its column is declared numeric
+///
+/// Legacy databases sometimes store numbers as `TEXT`, in columns whose declared type
+/// nonetheless has numeric affinity (`INTEGER`, `REAL`, `NUMERIC`, `DECIMAL`, ...).
+/// Text that parses as an integer becomes `Integer` (`Real` for `REAL`-affinity
+/// columns); other decimal text becomes `Real`. Everything else is returned unchanged,
+/// including text in columns with text, blob or no declared type.
+pub fn coerce_numeric_text(value: Value, decl_type: Option<&str>) -> Value {
+    let Value::Text(text) = &value else {
+        return value;
+    };
+    // SQLite's affinity rules, in precedence order
+    let decl = decl_type.unwrap_or("").to_ascii_uppercase();
+    let integer_affinity = decl.contains("INT");
+    let real_affinity = ["REAL", "FLOA", "DOUB"].iter().any(|t| decl.contains(t));
+    let numeric = !decl.is_empty()
+        && (integer_affinity
+            || !(["CHAR", "CLOB", "TEXT", "BLOB"]
+                .iter()
+                .any(|t| decl.contains(t))));
+    if !numeric {
+        return value;
+    }
+
+    let trimmed = text.trim();
+    // Rust's float parser also accepts words like "inf" and "NaN", which SQLite doesn't
+    let decimal = !trimmed.is_empty()
+        && trimmed
+            .bytes()
+            .all(|b| b.is_ascii_digit() || matches!(b, b'+' | b'-' | b'.' | b'e' | b'E'));
+    if !decimal {
+        return value;
+    }
+
+    match (trimmed.parse::<i64>(), trimmed.parse::<f64>()) {
+        (Ok(i), _) if !real_affinity || integer_affinity => Value::Integer(i),
+        (_, Ok(f)) if f.is_finite() => Value::Real(f),
+        _ => value,
+    }
+}
+
+/// Rewrite datetime text as canonical ISO 8601 when its column is declared as a date
+///
+/// SQLite stores datetimes as text in whatever shape they were written: a space or `T`
+/// separator, with or without seconds or fractional seconds, optionally followed by `Z`
+/// or a `+HH:MM` / `+HHMM` offset. For columns whose declared type mentions `DATE` or
+/// `TIMESTAMP`, such text becomes `YYYY-MM-DDTHH:MM:SS`, keeping any fraction as stored
+/// and writing offsets as `+HH:MM`. Bare dates are left as `YYYY-MM-DD`. Anything else,
+/// including text that isn't a recognised datetime, is returned unchanged.
+pub fn normalise_datetime_text(value: Value, decl_type: Option<&str>) -> Value {
+    let Value::Text(text) = &value else {
+        return value;
+    };
+    let decl = decl_type.unwrap_or("").to_ascii_uppercase();
+    if !(decl.contains("DATE") || decl.contains("TIMESTAMP")) {
+        return value;
+    }
+    match canonical_datetime(text) {
+        Some(canonical) => Value::Text(canonical),
+        None => value,
+    }
+}
+
+/// Parse SQLite datetime text into `YYYY-MM-DD[THH:MM:SS[.f][offset]]`
+fn canonical_datetime(text: &str) -> Option<String> {
+    let digits = |bytes: &[u8]| !bytes.is_empty() && bytes.iter().all(u8::is_ascii_digit);
+
+    let text = text.trim();
+    let b = text.as_bytes();
+    let date = b.len() >= 10
+        && digits(&b[0..4])
+        && b[4] == b'-'
+        && digits(&b[5..7])
+        && b[7] == b'-'
+        && digits(&b[8..10]);
+    if !date {
+        return None;
+    }
+    let mut out = text[..10].to_string();
+    if b.len() == 10 {
+        return Some(out);
+    }
+    if !matches!(b[10], b' ' | b'T') {
+        return None;
+    }
+
+    let time = &b[11..];
+    if time.len() < 5 || !digits(&time[0..2]) || time[2] != b':' || !digits(&time[3..5]) {
+        return None;
+    }
+    out.push('T');
+    out.push_str(&text[11..16]);
+    let mut i = 5;
+    if time.get(i) == Some(&b':') {
+        if !time.get(i + 1..i + 3).is_some_and(digits) {
+            return None;
+        }
+        out.push_str(&text[11 + i..11 + i + 3]);
+        i += 3;
+        if time.get(i) == Some(&b'.') {
+            let end = time[i + 1..]
+                .iter()
+                .position(|c| !c.is_ascii_digit())
+                .map_or(time.len(), |n| i + 1 + n);
+            if end == i + 1 {
+                return None;
+            }
+            out.push_str(&text[11 + i..11 + end]);
+            i = end;
+        }
+    } else {
+        out.push_str(":00");
+    }
+
+    // An offset may follow directly or after a space
+    let offset = text[11 + i..].trim_start().as_bytes();
+    match offset {
+        [] => {}
+        [b'Z' | b'z'] => out.push('Z'),
+        [sign @ (b'+' | b'-'), h1, h2, b':', m1, m2] | [sign @ (b'+' | b'-'), h1, h2, m1, m2]
+            if digits(&[*h1, *h2, *m1, *m2]) =>
+        {
+            out.push(*sign as char);
+            out.extend([*h1 as char, *h2 as char, ':', *m1 as char, *m2 as char]);
+        }
+        _ => return None,
+    }
+    Some(out)
+}
+
+/// Decode text stored as bytes in `encoding` into a UTF-8 string
+///
+/// Every byte is a valid Latin-1 character. UTF-16LE input must have an even length and
+/// no unpaired surrogates.
+pub fn decode_text_bytes(bytes: &[u8], encoding: TextEncoding) -> Result<String, String> {
+    match encoding {
+        TextEncoding::Latin1 => Ok(bytes.iter().map(|&b| char::from(b)).collect()),
+        TextEncoding::Utf16Le => {
+            if !bytes.len().is_multiple_of(2) {
+                return Err(format!(
+                    "Invalid UTF-16LE text: odd byte length {}",
+                    bytes.len()
+                ));
+            }
+            let units = bytes
+                .chunks_exact(2)
+                .map(|pair| u16::from_le_bytes([pair[0], pair[1]]));
+            char::decode_utf16(units)
+                .collect::<Result<String, _>>()
+                .map_err(|e| format!("Invalid UTF-16LE text: {e}"))
+        }
+    }
+}
+
+/// Reinterpret the blobs of the named columns as text in the given encoding
+///
+/// Values in those columns that aren't blobs (`NULL`, or text already) are left alone.
+/// Fails if a named column isn't in the result or a blob isn't valid in its encoding.
+pub fn decode_blob_columns(
+    columns: &[String],
+    rows: &mut [Vec<Value>],
+    decodings: &[(String, TextEncoding)],
+) -> Result<(), String> {
+    for (column, encoding) in decodings {
+        let index = columns
+            .iter()
+            .position(|name| name == column)
+            .ok_or_else(|| format!("No column named {column} to decode"))?;
+        for row in rows.iter_mut() {
+            if let Some(Value::Blob(bytes)) = row.get(index) {
+                let text = decode_text_bytes(bytes, *encoding)
+                    .map_err(|e| format!("Cannot decode column {column}: {e}"))?;
+                row[index] = Value::Text(text);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Origin table of each result column of a prepared statement, where SQLite knows it
+///
+/// Expressions and computed columns have no origin table and yield `None`.
+pub fn column_origin_tables(stmt: &libsql::Statement) -> Vec<Option<String>> {
+    stmt.columns()
+        .iter()
+        .map(|column| column.table_name().map(str::to_string))
+        .collect()
+}
+
+/// Run a statement that returns rows, with the origin tables `naming` needs
+///
+/// With `ColumnNaming::TablePrefix` the statement is prepared once, its origin tables
+/// read from the prepared statement and the rows taken from running that same
+/// statement. Otherwise it is run directly and no tables are returned.
+pub async fn query_with_origin_tables(
+    conn: &libsql::Connection,
+    sql: &str,
+    params: Vec<Value>,
+    naming: ColumnNaming,
+) -> Result<(Rows, Vec<Option<String>>), libsql::Error> {
+    if naming == ColumnNaming::TablePrefix {
+        let stmt = conn.prepare(sql).await?;
+        let tables = column_origin_tables(&stmt);
+        Ok((stmt.query(params).await?, tables))
+    } else {
+        Ok((conn.query(sql, params).await?, Vec::new()))
+    }
+}
+
+/// Disambiguate duplicate result column names according to `naming`
+///
+/// Only names that occur more than once are changed. With `TablePrefix`, duplicates
+/// become `table.column` using `tables` (each column's origin table, where known).
+/// Any name still colliding afterwards gets the first free numeric suffix (`id_2`,
+/// `id_3`, ...), skipping suffixes that would clash with another column's name.
+pub fn dedupe_column_names(
+    names: &[String],
+    tables: &[Option<String>],
+    naming: ColumnNaming,
+) -> Vec<String> {
+    if naming == ColumnNaming::Raw {
+        return names.to_vec();
+    }
+
+    let mut occurrences: HashMap<&str, usize> = HashMap::with_capacity(names.len());
+    for name in names {
+        *occurrences.entry(name.as_str()).or_insert(0) += 1;
+    }
+
+    let candidates: Vec<String> = names
+        .iter()
+        .enumerate()
+        .map(|(i, name)| {
+            let duplicated = occurrences.get(name.as_str()).copied().unwrap_or(0) > 1;
+            match (naming, tables.get(i)) {
+                (ColumnNaming::TablePrefix, Some(Some(table))) if duplicated => {
+                    format!("{table}.{name}")
+                }
+                _ => name.clone(),
+            }
+        })
+        .collect();
+
+    let reserved: HashSet<&str> = candidates.iter().map(String::as_str).collect();
+    let mut used: HashSet<String> = HashSet::with_capacity(candidates.len());
+    let mut result = Vec::with_capacity(candidates.len());
+
+    for candidate in &candidates {
+        if used.contains(candidate) {
+            let mut suffix = 2;
+            let mut unique = format!("{candidate}_{suffix}");
+            while used.contains(&unique) || reserved.contains(unique.as_str()) {
+                suffix += 1;
+                unique = format!("{candidate}_{suffix}");
+            }
+            used.insert(unique.clone());
+            result.push(unique);
+        } else {
+            used.insert(candidate.clone());
+            result.push(candidate.clone());
+        }
+    }
+
+    result
+}
+
+/// Quoting style for identifiers embedded in generated SQL
+///
+/// SQLite accepts all three styles; double quotes are the SQL standard and the default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum QuoteStyle {
+    /// `"name"`, escaping `"` as `""`
+    #[default]
+    DoubleQuote,
+    /// `` `name` ``, escaping `` ` `` as ``` `` ```
+    Backtick,
+    /// `[name]`, which has no escape for `]`
+    Bracket,
+}
+
+/// Quote an SQLite identifier (table, column, index name) for embedding in SQL
+///
+/// Embedded quote characters are doubled so the identifier can't terminate the quoting
+/// early. Bracket quoting has no escape mechanism, so identifiers containing `]` fall
+/// back to double quotes.
+///
+/// Generated SQL that refers to a caller-supplied column in an expression (a `WHERE`
+/// clause, `SET`, `ON CONFLICT` or select list) quotes it, and its table, with
+/// `QuoteStyle::Backtick`. SQLite silently treats an unknown double-quoted name as a
+/// string literal, so a misspelt column would compare against a constant instead of
+/// failing. Names read back from the schema, and pragma arguments, use the default
+/// double quotes.
+pub fn quote_identifier(id: &str, style: QuoteStyle) -> String {
+    match style {
+        QuoteStyle::Backtick => format!("`{}`", id.replace('`', "``")),
+        QuoteStyle::Bracket if !id.contains(']') => format!("[{id}]"),
+        QuoteStyle::DoubleQuote | QuoteStyle::Bracket => {
+            format!("\"{}\"", id.replace('"', "\"\""))
+        }
+    }
+}
+
 /// Query type enumeration for dispatching queries vs. executions
 #[derive(Debug, PartialEq, Eq)]
 pub enum QueryType {
@@ -304,6 +888,84 @@ pub fn detect_query_type(query: &str) -> QueryType {
     }
 }
 
+/// Detect the conflict action of an `INSERT`, `REPLACE` or `UPDATE` statement
+///
+/// Recognises `INSERT OR <action>`, `UPDATE OR <action>` and `REPLACE` (shorthand for
+/// `INSERT OR REPLACE`), skipping comments. Statements without an `OR` clause use
+/// `Abort`. Returns `None` for any other statement, including writes behind `WITH`.
+pub fn detect_conflict_action(sql: &str) -> Option<ConflictAction> {
+    let normalised = normalise_sql(sql);
+    let mut words = normalised.split(' ');
+    match words.next()? {
+        "replace" => return Some(ConflictAction::Replace),
+        "insert" | "update" => {}
+        _ => return None,
+    }
+    if words.next() != Some("or") {
+        return Some(ConflictAction::Abort);
+    }
+    match words.next()? {
+        "abort" => Some(ConflictAction::Abort),
+        "fail" => Some(ConflictAction::Fail),
+        "ignore" => Some(ConflictAction::Ignore),
+        "replace" => Some(ConflictAction::Replace),
+        "rollback" => Some(ConflictAction::Rollback),
+        _ => None,
+    }
+}
+
+/// Classify where a statement's writes land on a connection in `mode`.
+///
+/// Looks only at the leading keyword: `INSERT`, `REPLACE`, `UPDATE`, `DELETE` and
+/// schema changes count as writes. Writes hidden behind `WITH` are classified as reads.
+pub fn write_route(mode: Mode, sql: &str) -> WriteRoute {
+    let writes = match detect_query_type(sql) {
+        QueryType::Insert
+        | QueryType::Update
+        | QueryType::Delete
+        | QueryType::Create
+        | QueryType::Drop
+        | QueryType::Alter => true,
+        QueryType::Other => sql
+            .split_whitespace()
+            .next()
+            .is_some_and(|keyword| keyword.eq_ignore_ascii_case("REPLACE")),
+        _ => false,
+    };
+
+    match (writes, mode) {
+        (false, _) => WriteRoute::Read,
+        (true, Mode::Local) => WriteRoute::Local,
+        (true, Mode::Remote) => WriteRoute::Remote,
+        (true, Mode::RemoteReplica) => WriteRoute::Replica,
+    }
+}
+
+/// Check that a replica-only operation is running on a remote replica connection.
+///
+/// Operations such as syncing, waiting for a frame or flushing the replicator have
+/// nothing to act on for local files or direct remote connections, so they fail with
+/// `{:error, :not_a_replica}` rather than appear to succeed.
+pub fn require_replica(mode: Mode) -> Result<(), rustler::Error> {
+    match mode {
+        Mode::RemoteReplica => Ok(()),
+        Mode::Local | Mode::Remote => Err(rustler::Error::Term(Box::new(not_a_replica()))),
+    }
+}
+
+/// Add `count` statements to a connection's running total, looked up by id.
+///
+/// The total lives in `STATEMENT_COUNT_REGISTRY`, so the connection is never locked.
+/// Unknown connections are ignored, as are lock failures, since the count is only a
+/// throughput signal.
+pub fn count_statements(conn_id: &str, count: u64) {
+    if let Ok(registry) = safe_lock(&STATEMENT_COUNT_REGISTRY, "count_statements registry") {
+        if let Some(total) = registry.get(conn_id) {
+            total.fetch_add(count, std::sync::atomic::Ordering::Relaxed);
+        }
+    }
+}
+
 /// Skip leading whitespace and SQL comments in a byte slice.
 ///
 /// Handles both single-line comments (`-- comment`) and block comments (`/* comment */`).
@@ -536,3 +1198,890 @@ pub fn decode_term_to_value(term: Term) -> Result<Value, String> {
         Err(format!("Unsupported argument type: {term:?}"))
     }
 }
+
+/// Render a LibSQL value as an SQLite literal suitable for inlining into SQL text
+///
+/// Text is single-quoted with embedded quotes doubled, blobs use `X'..'` hex notation,
+/// and reals always keep a decimal point or exponent so they round-trip as REAL rather
+/// than INTEGER. Infinities use out-of-range literals (SQLite parses `9e999` as `Inf`);
+/// NaN has no SQLite representation and becomes `NULL`, matching how SQLite stores it.
+pub fn sql_literal(value: &Value) -> String {
+    match value {
+        Value::Null => "NULL".to_string(),
+        Value::Integer(i) => i.to_string(),
+        Value::Real(f) if f.is_nan() => "NULL".to_string(),
+        Value::Real(f) if f.is_infinite() => {
+            if f.is_sign_positive() {
+                "9e999".to_string()
+            } else {
+                "-9e999".to_string()
+            }
+        }
+        // Debug formatting always includes `.0` or an exponent, unlike Display
+        Value::Real(f) => format!("{f:?}"),
+        Value::Text(s) => format!("'{}'", s.replace('\'', "''")),
+        Value::Blob(bytes) => {
+            let hex: String = bytes.iter().map(|b| format!("{b:02X}")).collect();
+            format!("X'{hex}'")
+        }
+    }
+}
+
+/// Append `text` to `out` as a JSON string literal
+///
+/// Quotes, backslashes and control characters are escaped; everything else, including
+/// non-ASCII text, is copied as UTF-8.
+pub fn push_json_string(out: &mut String, text: &str) {
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if u32::from(c) < 0x20 => out.push_str(&format!("\\u{:04x}", u32::from(c))),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// Standard base64 encoding of `bytes`, with padding
+pub fn base64_encode(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, b)| n | u32::from(*b) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(char::from(ALPHABET[(n >> (18 - 6 * i)) as usize & 63]));
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// Append a LibSQL value to `out` as a JSON value
+///
+/// Numbers are bare (reals keep a decimal point or exponent), text is a JSON string,
+/// blobs are base64 strings and NULL is `null`. JSON has no infinities or NaN, so
+/// those become `null` too.
+pub fn push_json_value(out: &mut String, value: &Value) {
+    match value {
+        Value::Null => out.push_str("null"),
+        Value::Integer(i) => out.push_str(&i.to_string()),
+        Value::Real(f) if !f.is_finite() => out.push_str("null"),
+        // Debug formatting always includes `.0` or an exponent, unlike Display
+        Value::Real(f) => out.push_str(&format!("{f:?}")),
+        Value::Text(text) => push_json_string(out, text),
+        Value::Blob(bytes) => {
+            out.push('"');
+            out.push_str(&base64_encode(bytes));
+            out.push('"');
+        }
+    }
+}
+
+/// If a string literal, quoted identifier or comment starts at `pos`, return the
+/// position just past its end (or the end of input if it is unterminated).
+fn skip_literal_or_comment(bytes: &[u8], pos: usize) -> Option<usize> {
+    let len = bytes.len();
+    let close = match bytes.get(pos)? {
+        b'\'' => b'\'',
+        b'"' => b'"',
+        b'`' => b'`',
+        b'[' => b']',
+        b'-' | b'/' => {
+            let skipped = skip_whitespace_and_comments(&bytes[pos..]);
+            return (skipped > 0).then_some(pos + skipped);
+        }
+        _ => return None,
+    };
+
+    // A doubled closing quote is an escape; brackets have no escape
+    let mut p = pos + 1;
+    while p < len {
+        if bytes[p] == close {
+            if p + 1 < len && bytes[p + 1] == close && close != b']' {
+                p += 2;
+                continue;
+            }
+            return Some(p + 1);
+        }
+        p += 1;
+    }
+    Some(len)
+}
+
+/// Whether `keyword` appears in SQL as a bare word
+///
+/// Compares ASCII case-insensitively and skips string literals, quoted identifiers and
+/// comments, so a default value, comment or column named after the keyword doesn't
+/// count.
+pub fn has_keyword(sql: &str, keyword: &str) -> bool {
+    let bytes = sql.as_bytes();
+    let mut pos = 0;
+
+    while pos < bytes.len() {
+        if let Some(end) = skip_literal_or_comment(bytes, pos) {
+            pos = end;
+            continue;
+        }
+
+        let b = bytes[pos];
+        if b.is_ascii_alphanumeric() || b == b'_' {
+            let mut end = pos + 1;
+            while end < bytes.len() && (bytes[end].is_ascii_alphanumeric() || bytes[end] == b'_') {
+                end += 1;
+            }
+            if sql[pos..end].eq_ignore_ascii_case(keyword) {
+                return true;
+            }
+            pos = end;
+            continue;
+        }
+        pos += 1;
+    }
+    false
+}
+
+/// Split an SQL script into its individual statements
+///
+/// Splits on `;` outside string literals, quoted identifiers and comments. Semicolons
+/// inside a `CREATE TRIGGER` body (between `BEGIN` and its matching `END`) don't end
+/// the statement. Statements are trimmed, without their terminating `;`, and empty or
+/// comment-only statements are dropped.
+pub fn split_statements(sql: &str) -> Vec<&str> {
+    let bytes = sql.as_bytes();
+    let len = bytes.len();
+    let mut statements = Vec::new();
+    let mut start = 0;
+    let mut pos = 0;
+    // The first few keywords of the current statement, to recognise triggers
+    let mut leading: Vec<&str> = Vec::with_capacity(3);
+    let mut depth: i32 = 0;
+
+    fn push<'s>(statements: &mut Vec<&'s str>, stmt: &'s str) {
+        let stmt = stmt.trim();
+        if skip_whitespace_and_comments(stmt.as_bytes()) < stmt.len() {
+            statements.push(stmt);
+        }
+    }
+
+    while pos < len {
+        if let Some(end) = skip_literal_or_comment(bytes, pos) {
+            pos = end;
+            continue;
+        }
+
+        let b = bytes[pos];
+        if b.is_ascii_alphabetic() || b == b'_' {
+            let mut end = pos + 1;
+            while end < len && (bytes[end].is_ascii_alphanumeric() || bytes[end] == b'_') {
+                end += 1;
+            }
+            let word = &sql[pos..end];
+            if leading.len() < 3 {
+                leading.push(word);
+            }
+
+            let is = |keyword: &str| word.eq_ignore_ascii_case(keyword);
+            let in_trigger = leading
+                .first()
+                .is_some_and(|w| w.eq_ignore_ascii_case("CREATE"))
+                && leading.iter().any(|w| w.eq_ignore_ascii_case("TRIGGER"));
+            if in_trigger {
+                if is("BEGIN") || is("CASE") {
+                    depth += 1;
+                } else if is("END") {
+                    depth -= 1;
+                }
+            }
+            pos = end;
+            continue;
+        }
+
+        if b == b';' && depth <= 0 {
+            push(&mut statements, &sql[start..pos]);
+            start = pos + 1;
+            leading.clear();
+            depth = 0;
+        }
+        pos += 1;
+    }
+
+    push(&mut statements, &sql[start..]);
+    statements
+}
+
+/// Extract the expression of a generated column from its table's `CREATE TABLE` SQL
+///
+/// Finds `column`'s definition among the top-level items of the table body and
+/// returns the text between the parentheses of its `AS (...)` clause, trimmed. Names
+/// are compared case-insensitively and may be quoted. Returns `None` if the column
+/// isn't defined there or isn't generated.
+pub fn generated_column_expr(create_sql: &str, column: &str) -> Option<String> {
+    let bytes = create_sql.as_bytes();
+    let mut depth = 0usize;
+    let mut item_start = 0;
+    let mut pos = 0;
+
+    while pos < bytes.len() {
+        if let Some(end) = skip_literal_or_comment(bytes, pos) {
+            pos = end;
+            continue;
+        }
+        match bytes[pos] {
+            b'(' => {
+                depth += 1;
+                if depth == 1 {
+                    item_start = pos + 1;
+                }
+            }
+            b',' | b')' if depth == 1 => {
+                let definition = &create_sql[item_start..pos];
+                if definition_names(definition, column) {
+                    return generated_expr_in(definition);
+                }
+                if bytes[pos] == b')' {
+                    return None;
+                }
+                item_start = pos + 1;
+            }
+            b')' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+        pos += 1;
+    }
+    None
+}
+
+/// Whether a column definition (or table constraint) starts with the name `column`.
+fn definition_names(definition: &str, column: &str) -> bool {
+    let bytes = definition.as_bytes();
+    let start = skip_whitespace_and_comments(bytes);
+    let name = if let Some(open @ (b'"' | b'`' | b'[')) = bytes.get(start) {
+        let end = skip_literal_or_comment(bytes, start).unwrap_or(bytes.len());
+        // Unterminated quotes can't name a column SQLite accepted
+        if end < start + 2 {
+            return false;
+        }
+        let inner = &definition[start + 1..end - 1];
+        if *open == b'[' {
+            inner.to_string()
+        } else {
+            let quote = char::from(*open).to_string();
+            inner.replace(&quote.repeat(2), &quote)
+        }
+    } else {
+        let end = bytes[start..]
+            .iter()
+            .position(|b| !(b.is_ascii_alphanumeric() || *b == b'_'))
+            .map_or(bytes.len(), |n| start + n);
+        definition[start..end].to_string()
+    };
+    !name.is_empty() && name.eq_ignore_ascii_case(column)
+}
+
+/// The expression inside the top-level `AS (...)` clause of a column definition.
+fn generated_expr_in(definition: &str) -> Option<String> {
+    let bytes = definition.as_bytes();
+    let len = bytes.len();
+    let mut depth = 0usize;
+    let mut pos = 0;
+
+    while pos < len {
+        if let Some(end) = skip_literal_or_comment(bytes, pos) {
+            pos = end;
+            continue;
+        }
+        let b = bytes[pos];
+        if b.is_ascii_alphabetic() || b == b'_' {
+            let mut end = pos + 1;
+            while end < len && (bytes[end].is_ascii_alphanumeric() || bytes[end] == b'_') {
+                end += 1;
+            }
+            if depth == 0 && definition[pos..end].eq_ignore_ascii_case("AS") {
+                let open = end + skip_whitespace_and_comments(&bytes[end..]);
+                if bytes.get(open) == Some(&b'(') {
+                    return balanced_contents(definition, open);
+                }
+            }
+            pos = end;
+            continue;
+        }
+        match b {
+            b'(' => depth += 1,
+            b')' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+        pos += 1;
+    }
+    None
+}
+
+/// The trimmed text between the parenthesis at `open` and its matching close.
+fn balanced_contents(sql: &str, open: usize) -> Option<String> {
+    let bytes = sql.as_bytes();
+    let mut depth = 0usize;
+    let mut pos = open;
+    while pos < bytes.len() {
+        if let Some(end) = skip_literal_or_comment(bytes, pos) {
+            pos = end;
+            continue;
+        }
+        match bytes[pos] {
+            b'(' => depth += 1,
+            b')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(sql[open + 1..pos].trim().to_string());
+                }
+            }
+            _ => {}
+        }
+        pos += 1;
+    }
+    None
+}
+
+/// Normalise SQL to its shape, for grouping statements that differ only in literals
+///
+/// A lightweight token scan rather than a parse: string, blob and numeric literals and
+/// bound parameters become `?`, runs of `?` separated by commas (such as `IN` lists)
+/// collapse to a single `?`, comments are dropped, unquoted words are lowercased and
+/// whitespace is reduced to single spaces between words. Quoted identifiers are kept
+/// as written.
+pub fn normalise_sql(sql: &str) -> String {
+    fn is_word_byte(b: u8) -> bool {
+        b.is_ascii_alphanumeric() || b == b'_' || b == b'$' || b >= 0x80
+    }
+
+    let bytes = sql.as_bytes();
+    let len = bytes.len();
+    let mut out = String::with_capacity(len);
+    // Whether the last token emitted was a word, so the next word needs a space
+    let mut after_word = false;
+    let mut pos = 0;
+
+    while pos < len {
+        let b = bytes[pos];
+        let (token, end, word): (&str, usize, bool) = if b.is_ascii_whitespace() {
+            pos += 1;
+            continue;
+        } else if b == b'\'' || ((b == b'x' || b == b'X') && bytes.get(pos + 1) == Some(&b'\'')) {
+            let start = if b == b'\'' { pos } else { pos + 1 };
+            let end = skip_literal_or_comment(bytes, start).unwrap_or(len);
+            ("?", end, true)
+        } else if let Some(end) = skip_literal_or_comment(bytes, pos) {
+            if b == b'-' || b == b'/' {
+                // Comment
+                pos = end;
+                continue;
+            }
+            (&sql[pos..end], end, true)
+        } else if b.is_ascii_digit()
+            || (b == b'.' && bytes.get(pos + 1).is_some_and(u8::is_ascii_digit))
+        {
+            let mut end = pos + 1;
+            while end < len {
+                let c = bytes[end];
+                let exponent_sign = (c == b'+' || c == b'-')
+                    && matches!(bytes[end - 1], b'e' | b'E')
+                    && !sql[pos..end].starts_with("0x")
+                    && !sql[pos..end].starts_with("0X");
+                if c.is_ascii_alphanumeric() || c == b'.' || c == b'_' || exponent_sign {
+                    end += 1;
+                } else {
+                    break;
+                }
+            }
+            ("?", end, true)
+        } else if matches!(b, b'?' | b':' | b'@' | b'$')
+            && (b == b'?' || bytes.get(pos + 1).copied().is_some_and(is_word_byte))
+        {
+            let mut end = pos + 1;
+            while end < len && is_word_byte(bytes[end]) {
+                end += 1;
+            }
+            ("?", end, true)
+        } else if is_word_byte(b) {
+            let mut end = pos + 1;
+            while end < len && is_word_byte(bytes[end]) {
+                end += 1;
+            }
+            (&sql[pos..end], end, true)
+        } else {
+            (&sql[pos..pos + 1], pos + 1, false)
+        };
+
+        if token == "?" && out.ends_with("?,") {
+            out.pop();
+        } else {
+            if word && after_word {
+                out.push(' ');
+            }
+            if word && !matches!(b, b'"' | b'`' | b'[') {
+                out.push_str(&token.to_ascii_lowercase());
+            } else {
+                out.push_str(token);
+            }
+        }
+        after_word = word;
+        pos = end;
+    }
+
+    out
+}
+
+/// Digest of a statement's shape, as 16 lowercase hex digits
+///
+/// The 64-bit FNV-1a hash of `normalise_sql(sql)`, so statements differing only in
+/// literal values share a digest. The hash is fixed, so digests are stable across
+/// restarts and releases.
+pub fn sql_digest(sql: &str) -> String {
+    let hash = fnv1a(FNV_OFFSET_BASIS, normalise_sql(sql).as_bytes());
+    format!("{hash:016x}")
+}
+
+/// Starting state of a 64-bit FNV-1a hash
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+
+/// Feed `bytes` into a 64-bit FNV-1a hash whose state so far is `hash`
+fn fnv1a(hash: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(hash, |hash, b| {
+        (hash ^ u64::from(*b)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Fingerprint of a row's values, as 16 lowercase hex digits
+///
+/// The 64-bit FNV-1a hash of the values in column order. Each value is hashed as a type
+/// tag followed by its bytes, with text and blobs length-prefixed, so `NULL`, `0`, `''`
+/// and an empty blob all differ, as do rows whose values only differ in where one value
+/// ends and the next begins. Reals hash by their bit pattern, so `1` and `1.0` differ.
+pub fn row_fingerprint_of(values: &[Value]) -> String {
+    let hash = values
+        .iter()
+        .fold(FNV_OFFSET_BASIS, |hash, value| match value {
+            Value::Null => fnv1a(hash, &[0]),
+            Value::Integer(i) => fnv1a(fnv1a(hash, &[1]), &i.to_le_bytes()),
+            Value::Real(f) => fnv1a(fnv1a(hash, &[2]), &f.to_bits().to_le_bytes()),
+            Value::Text(s) => {
+                let hash = fnv1a(fnv1a(hash, &[3]), &(s.len() as u64).to_le_bytes());
+                fnv1a(hash, s.as_bytes())
+            }
+            Value::Blob(b) => {
+                let hash = fnv1a(fnv1a(hash, &[4]), &(b.len() as u64).to_le_bytes());
+                fnv1a(hash, b)
+            }
+        });
+    format!("{hash:016x}")
+}
+
+/// Build the query for one keyset pagination page over a base `SELECT`
+///
+/// The base query is wrapped as a subquery, so any `WHERE` or `JOIN` it has is kept,
+/// and the page filter, ordering and limit are applied to its results. The key is
+/// selected first, ahead of the base query's own columns. With
+/// `after_key` the query binds `?1` as the last key of the previous page and `?2` as
+/// the limit; without it (the first page) it binds only the limit as `?1`.
+///
+/// The base query must be a single `SELECT` without its own top-level `ORDER BY`,
+/// `LIMIT` or `OFFSET`, since those would conflict with the page's.
+pub fn keyset_page_sql(
+    base_sql: &str,
+    key_column: &str,
+    after_key: bool,
+) -> Result<String, String> {
+    let statements = split_statements(base_sql);
+    let [base] = statements.as_slice() else {
+        return Err("Keyset pagination requires a single SELECT statement".to_string());
+    };
+    if detect_query_type(base) != QueryType::Select {
+        return Err("Keyset pagination requires a SELECT statement".to_string());
+    }
+
+    // Scan the top level of the query, outside literals, comments and parentheses
+    let bytes = base.as_bytes();
+    let mut depth: i32 = 0;
+    let mut pos = 0;
+    while pos < bytes.len() {
+        if let Some(end) = skip_literal_or_comment(bytes, pos) {
+            pos = end;
+            continue;
+        }
+        let b = bytes[pos];
+        if b.is_ascii_alphabetic() || b == b'_' {
+            let mut end = pos + 1;
+            while end < bytes.len() && (bytes[end].is_ascii_alphanumeric() || bytes[end] == b'_') {
+                end += 1;
+            }
+            let word = &base[pos..end];
+            if depth == 0
+                && ["ORDER", "LIMIT", "OFFSET"]
+                    .iter()
+                    .any(|keyword| word.eq_ignore_ascii_case(keyword))
+            {
+                return Err(format!(
+                    "Base query must not have its own {}; keyset pagination adds it",
+                    word.to_ascii_uppercase()
+                ));
+            }
+            pos = end;
+            continue;
+        }
+        match b {
+            b'(' => depth += 1,
+            b')' => depth -= 1,
+            _ => {}
+        }
+        pos += 1;
+    }
+
+    let key = quote_identifier(key_column, QuoteStyle::Backtick);
+    Ok(if after_key {
+        format!("SELECT {key}, * FROM ({base}) WHERE {key} > ?1 ORDER BY {key} LIMIT ?2")
+    } else {
+        format!("SELECT {key}, * FROM ({base}) ORDER BY {key} LIMIT ?1")
+    })
+}
+
+/// Find the table written to by a single `INSERT`, `REPLACE`, `UPDATE` or `DELETE` statement
+///
+/// Returns the statement type and the table reference exactly as written (including any
+/// schema qualifier and quoting), so it can be embedded in other SQL. Returns `None` for
+/// other statements, including DML introduced by a `WITH` clause.
+pub fn dml_target_table(sql: &str) -> Option<(QueryType, &str)> {
+    let bytes = sql.as_bytes();
+
+    // Position of the next token after whitespace and comments
+    let next = |pos: usize| pos + skip_whitespace_and_comments(&bytes[pos..]);
+    // A bare word starting at `pos`, and the position after it
+    let word = |pos: usize| -> (&str, usize) {
+        let mut end = pos;
+        while end < bytes.len() && (bytes[end].is_ascii_alphanumeric() || bytes[end] == b'_') {
+            end += 1;
+        }
+        (&sql[pos..end], end)
+    };
+    // A quoted or bare identifier starting at `pos`
+    let identifier = |pos: usize| -> Option<usize> {
+        match bytes.get(pos)? {
+            b'"' | b'`' | b'[' => skip_literal_or_comment(bytes, pos),
+            _ => Some(word(pos).1).filter(|end| *end > pos),
+        }
+    };
+
+    let (keyword, mut pos) = word(next(0));
+    let keyword = keyword.to_ascii_uppercase();
+    let query_type = match keyword.as_str() {
+        "INSERT" | "REPLACE" => QueryType::Insert,
+        "UPDATE" => QueryType::Update,
+        "DELETE" => QueryType::Delete,
+        _ => return None,
+    };
+
+    // Skip `OR <conflict resolution>`, then `INTO` or `FROM` as the statement requires
+    let (w, end) = word(next(pos));
+    if w.eq_ignore_ascii_case("OR") && keyword != "DELETE" {
+        pos = word(next(end)).1;
+    }
+    if query_type != QueryType::Update {
+        let expected = if query_type == QueryType::Delete {
+            "FROM"
+        } else {
+            "INTO"
+        };
+        let (w, end) = word(next(pos));
+        if !w.eq_ignore_ascii_case(expected) {
+            return None;
+        }
+        pos = end;
+    }
+
+    let start = next(pos);
+    let mut end = identifier(start)?;
+    // Schema-qualified name
+    if bytes.get(next(end)) == Some(&b'.') {
+        end = identifier(next(next(end) + 1))?;
+    }
+    Some((query_type, &sql[start..end]))
+}
+
+/// Qualify the unqualified table references in SQL with a schema name
+///
+/// Prefixes `schema.` to each table named where SQL expects a table: after `FROM` and
+/// `JOIN`, in the comma-separated list following `FROM`, after `INTO`, and after
+/// `UPDATE` (and its `OR <conflict resolution>`). Only names in `tables` are qualified,
+/// compared ASCII case-insensitively, so CTEs, table-valued functions and tables that
+/// only exist elsewhere resolve as before. References already qualified, string
+/// literals and comments are left alone, as is `IS [NOT] DISTINCT FROM`.
+///
+/// This is a token scan rather than a parse: a CTE sharing its name with a table in
+/// `tables` would be qualified too.
+pub fn qualify_table_references(sql: &str, schema: &str, tables: &HashSet<String>) -> String {
+    let bytes = sql.as_bytes();
+    let len = bytes.len();
+    let prefix = format!("{}.", quote_identifier(schema, QuoteStyle::DoubleQuote));
+    let mut out = String::with_capacity(len + prefix.len() * 2);
+    let mut copied = 0;
+
+    let mut pos = 0;
+    let mut depth = 0usize;
+    // Paren depths at which a `FROM` list is open, so commas there separate tables
+    let mut from_depths: Vec<usize> = Vec::new();
+    let mut expect_table = false;
+    // After `UPDATE`, then after its `OR`, where the next word is the conflict action
+    let mut after_update = false;
+    let mut conflict_action_next = false;
+    // After `INTO`, where a following `(` opens a column list rather than a call
+    let mut after_into = false;
+    let mut prev_words: [&str; 2] = ["", ""];
+
+    while pos < len {
+        let start = pos + skip_whitespace_and_comments(&bytes[pos..]);
+        if start >= len {
+            break;
+        }
+
+        let b = bytes[start];
+        let (end, name) = match b {
+            b'"' | b'`' | b'[' => {
+                let end = skip_literal_or_comment(bytes, start).unwrap_or(len);
+                let close = if b == b'[' { ']' } else { b as char };
+                let inner = sql[start + 1..end]
+                    .strip_suffix(close)
+                    .unwrap_or(&sql[start + 1..end]);
+                let name = if close == ']' {
+                    inner.to_string()
+                } else {
+                    inner.replace(&format!("{close}{close}"), &close.to_string())
+                };
+                (end, Some(name))
+            }
+            b'\'' => (skip_literal_or_comment(bytes, start).unwrap_or(len), None),
+            _ if b.is_ascii_alphanumeric() || b == b'_' => {
+                let mut end = start + 1;
+                while end < len && (bytes[end].is_ascii_alphanumeric() || bytes[end] == b'_') {
+                    end += 1;
+                }
+                let word = &sql[start..end];
+                (end, (!b.is_ascii_digit()).then(|| word.to_string()))
+            }
+            _ => (start + 1, None),
+        };
+        pos = end;
+
+        if expect_table {
+            if let Some(name) = name {
+                if after_update && name.eq_ignore_ascii_case("OR") {
+                    after_update = false;
+                    conflict_action_next = true;
+                    continue;
+                }
+                if conflict_action_next {
+                    conflict_action_next = false;
+                    continue;
+                }
+
+                expect_table = false;
+                after_update = false;
+                let next = end + skip_whitespace_and_comments(&bytes[end..]);
+                let qualified_or_call = match bytes.get(next) {
+                    Some(b'.') => true,
+                    Some(b'(') => !after_into,
+                    _ => false,
+                };
+                after_into = false;
+                if !qualified_or_call && tables.contains(&name.to_ascii_lowercase()) {
+                    out.push_str(&sql[copied..start]);
+                    out.push_str(&prefix);
+                    copied = start;
+                }
+                prev_words = [prev_words[1], &sql[start..end]];
+                continue;
+            }
+            expect_table = false;
+            after_update = false;
+            after_into = false;
+        }
+
+        match b {
+            b'(' => depth += 1,
+            b')' => {
+                depth = depth.saturating_sub(1);
+                while from_depths.last().is_some_and(|d| *d > depth) {
+                    from_depths.pop();
+                }
+            }
+            b',' => expect_table = from_depths.last() == Some(&depth),
+            b';' => from_depths.clear(),
+            _ => {}
+        }
+
+        if b.is_ascii_alphabetic() || b == b'_' {
+            let word = &sql[start..end];
+            let is = |keyword: &str| word.eq_ignore_ascii_case(keyword);
+            if is("FROM") {
+                let distinct_from = prev_words[1].eq_ignore_ascii_case("DISTINCT")
+                    && (prev_words[0].eq_ignore_ascii_case("IS")
+                        || prev_words[0].eq_ignore_ascii_case("NOT"));
+                if !distinct_from {
+                    expect_table = true;
+                    if from_depths.last() != Some(&depth) {
+                        from_depths.push(depth);
+                    }
+                }
+            } else if is("JOIN") || is("INTO") || is("UPDATE") {
+                expect_table = true;
+                after_update = is("UPDATE");
+                after_into = is("INTO");
+            } else if [
+                "WHERE",
+                "GROUP",
+                "ORDER",
+                "LIMIT",
+                "HAVING",
+                "WINDOW",
+                "UNION",
+                "INTERSECT",
+                "EXCEPT",
+                "RETURNING",
+                "SET",
+                "VALUES",
+            ]
+            .iter()
+            .any(|keyword| is(keyword))
+                && from_depths.last() == Some(&depth)
+            {
+                from_depths.pop();
+            }
+            prev_words = [prev_words[1], word];
+        }
+    }
+
+    out.push_str(&sql[copied..]);
+    out
+}
+
+/// Inline positional parameters into a single SQL statement as escaped literals
+///
+/// Replaces `?` and `?NNN` placeholders with `sql_literal` renderings of `params`,
+/// skipping string literals, quoted identifiers and comments. A trailing `;` is
+/// dropped. Named parameters, multiple statements, and placeholder counts that
+/// don't match `params` are rejected, so the result is always one complete statement.
+pub fn inline_params(sql: &str, params: &[Value]) -> Result<String, String> {
+    let bytes = sql.as_bytes();
+    let len = bytes.len();
+    let mut out = String::with_capacity(len);
+    let mut copied = 0;
+    let mut pos = 0;
+    let mut max_index = 0;
+
+    while pos < len {
+        if let Some(end) = skip_literal_or_comment(bytes, pos) {
+            pos = end;
+            continue;
+        }
+
+        match bytes[pos] {
+            b'?' => {
+                let digits_start = pos + 1;
+                let mut end = digits_start;
+                while end < len && bytes[end].is_ascii_digit() {
+                    end += 1;
+                }
+                let index = if end > digits_start {
+                    sql[digits_start..end]
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|i| *i > 0)
+                        .ok_or_else(|| format!("Invalid parameter index: {}", &sql[pos..end]))?
+                } else {
+                    // A bare `?` takes the next index after the largest used so far
+                    max_index + 1
+                };
+                let value = params.get(index - 1).ok_or_else(|| {
+                    format!(
+                        "Statement references parameter {index} but only {} provided",
+                        params.len()
+                    )
+                })?;
+                out.push_str(&sql[copied..pos]);
+                out.push_str(&sql_literal(value));
+                copied = end;
+                pos = end;
+                max_index = max_index.max(index);
+            }
+            b':' | b'@' | b'$'
+                if pos + 1 < len
+                    && (bytes[pos + 1].is_ascii_alphanumeric() || bytes[pos + 1] == b'_')
+                    && (pos == 0
+                        || !(bytes[pos - 1].is_ascii_alphanumeric() || bytes[pos - 1] == b'_')) =>
+            {
+                return Err("Named parameters are not supported; use ? placeholders".to_string());
+            }
+            b';' => {
+                if pos + 1 + skip_whitespace_and_comments(&bytes[pos + 1..]) < len {
+                    return Err("Only one SQL statement is allowed per entry".to_string());
+                }
+                out.push_str(&sql[copied..pos]);
+                return finish_inline(out, max_index, params.len());
+            }
+            _ => pos += 1,
+        }
+    }
+
+    out.push_str(&sql[copied..]);
+    finish_inline(out, max_index, params.len())
+}
+
+fn finish_inline(out: String, used: usize, provided: usize) -> Result<String, String> {
+    if used == provided {
+        Ok(out)
+    } else {
+        Err(format!(
+            "Statement uses {used} parameters but {provided} provided"
+        ))
+    }
+}
+
+/// Arrange `(index, value)` pairs into positional parameters for a statement
+///
+/// Indexes are 1-based SQLite parameter numbers, so each value lands on `?N` (or the
+/// named parameter at that position) regardless of the order the pairs are given in.
+/// Parameters without a pair are bound to NULL. Indexes outside `1..=param_count` and
+/// indexes given more than once are rejected.
+pub fn place_indexed_params(
+    param_count: usize,
+    indexed: Vec<(i64, Value)>,
+) -> Result<Vec<Value>, String> {
+    let mut params = vec![Value::Null; param_count];
+    let mut bound = vec![false; param_count];
+
+    for (index, value) in indexed {
+        let slot = usize::try_from(index)
+            .ok()
+            .filter(|i| (1..=param_count).contains(i))
+            .ok_or_else(|| {
+                format!(
+                    "Parameter index {index} out of range; statement has {param_count} parameters"
+                )
+            })?
+            - 1;
+        if bound[slot] {
+            return Err(format!("Parameter index {index} bound more than once"));
+        }
+        bound[slot] = true;
+        params[slot] = value;
+    }
+
+    Ok(params)
+}
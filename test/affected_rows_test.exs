@@ -0,0 +1,63 @@
+defmodule EctoLibSql.AffectedRowsTest do
+  @moduledoc """
+  Tests that write results report exactly the rows changed by the statement, so a
+  no-op upsert (`ON CONFLICT DO NOTHING`) is distinguishable from a real insert.
+  """
+  use ExUnit.Case
+
+  @upsert "INSERT INTO users (id, name) VALUES (?, ?) ON CONFLICT (id) DO NOTHING"
+
+  setup do
+    test_db = "z_ecto_libsql_test-affected_rows_#{:erlang.unique_integer([:positive])}.db"
+
+    {:ok, state} = EctoLibSql.connect(database: test_db)
+
+    {:ok, _, _, state} =
+      EctoLibSql.handle_execute(
+        "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT)",
+        [],
+        [],
+        state
+      )
+
+    on_exit(fn ->
+      EctoLibSql.disconnect([], state)
+      EctoLibSql.TestHelpers.cleanup_db_files(test_db)
+    end)
+
+    {:ok, state: state}
+  end
+
+  test "conflicting DO NOTHING insert reports 0 affected rows", %{state: state} do
+    {:ok, _, result, state} = EctoLibSql.handle_execute(@upsert, [1, "Alice"], [], state)
+    assert result.num_rows == 1
+
+    {:ok, _, result, _state} = EctoLibSql.handle_execute(@upsert, [1, "Alice"], [], state)
+    assert result.num_rows == 0
+  end
+
+  test "conflicting DO NOTHING insert reports 0 inside a transaction", %{state: state} do
+    {:ok, _, result, state} = EctoLibSql.handle_execute(@upsert, [1, "Alice"], [], state)
+    assert result.num_rows == 1
+
+    {:ok, _, state} = EctoLibSql.handle_begin([], state)
+
+    {:ok, _, result, state} = EctoLibSql.handle_execute(@upsert, [2, "Bob"], [], state)
+    assert result.num_rows == 1
+
+    {:ok, _, result, state} = EctoLibSql.handle_execute(@upsert, [1, "Alice"], [], state)
+    assert result.num_rows == 0
+
+    {:ok, _, _state} = EctoLibSql.handle_commit([], state)
+  end
+
+  test "conflicting DO NOTHING insert with RETURNING returns no rows", %{state: state} do
+    sql = @upsert <> " RETURNING id"
+
+    {:ok, _, result, state} = EctoLibSql.handle_execute(sql, [1, "Alice"], [], state)
+    assert result.num_rows == 1
+
+    {:ok, _, result, _state} = EctoLibSql.handle_execute(sql, [1, "Alice"], [], state)
+    assert result.num_rows == 0
+  end
+end
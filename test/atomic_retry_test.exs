@@ -0,0 +1,83 @@
+defmodule EctoLibSql.AtomicRetryTest do
+  use ExUnit.Case
+
+  alias EctoLibSql.Native
+
+  setup do
+    db_file = "z_ecto_libsql_test-atomic_retry_#{:erlang.unique_integer([:positive])}.db"
+    {:ok, state} = EctoLibSql.connect(database: db_file, busy_timeout: 0)
+    {:ok, _} = EctoLibSql.Pragma.set_journal_mode(state, :wal)
+    {:ok, holder} = EctoLibSql.connect(database: db_file)
+
+    {:ok, _, _, state} =
+      EctoLibSql.handle_execute(
+        "CREATE TABLE balances (id INTEGER PRIMARY KEY, amount INTEGER)",
+        [],
+        [],
+        state
+      )
+
+    on_exit(fn ->
+      EctoLibSql.disconnect([], holder)
+      EctoLibSql.disconnect([], state)
+      EctoLibSql.TestHelpers.cleanup_db_files(db_file)
+    end)
+
+    {:ok, state: state, holder: holder}
+  end
+
+  @statements [
+    {"INSERT OR REPLACE INTO balances (id, amount) VALUES (?, ?)", [1, 100]},
+    {"INSERT OR REPLACE INTO balances (id, amount) VALUES (?, ?)", [2, 50]}
+  ]
+
+  test "commits without conflict on the first attempt", %{state: state} do
+    assert {:ok, %{counts: [1, 1], attempts: 1}} = Native.atomic_with_retry(state, @statements)
+  end
+
+  test "retries the whole transaction until a conflict clears", %{
+    state: state,
+    holder: holder
+  } do
+    {:ok, trx_state} = Native.begin(holder, behavior: :immediate)
+
+    releaser =
+      Task.async(fn ->
+        Process.sleep(50)
+        {:ok, _} = Native.commit(trx_state)
+      end)
+
+    assert {:ok, %{counts: [1, 1], attempts: attempts}} =
+             Native.atomic_with_retry(state, @statements, 10)
+
+    Task.await(releaser)
+    assert attempts > 1
+
+    {:ok, _, result, _} =
+      EctoLibSql.handle_execute("SELECT sum(amount) FROM balances", [], [], state)
+
+    assert result.rows == [[150]]
+  end
+
+  test "reports exhausted retries", %{state: state, holder: holder} do
+    {:ok, trx_state} = Native.begin(holder, behavior: :immediate)
+
+    assert {:error, {:retries_exhausted, 3, message}} =
+             Native.atomic_with_retry(state, @statements, 2)
+
+    assert message =~ "locked"
+    {:ok, _} = Native.rollback(trx_state)
+  end
+
+  test "does not retry other errors", %{state: state} do
+    statements = @statements ++ [{"INSERT INTO missing VALUES (1)", []}]
+    assert {:error, message} = Native.atomic_with_retry(state, statements)
+
+    assert message =~ "missing"
+
+    {:ok, _, result, _} =
+      EctoLibSql.handle_execute("SELECT count(*) FROM balances", [], [], state)
+
+    assert result.rows == [[0]]
+  end
+end
@@ -0,0 +1,115 @@
+defmodule EctoLibSql.AttachedDatabasesTest do
+  use ExUnit.Case
+
+  alias EctoLibSql.Native
+
+  setup do
+    suffix = :erlang.unique_integer([:positive])
+    db_file = "z_ecto_libsql_test-attached_#{suffix}.db"
+    shard_a = "z_ecto_libsql_test-attached_a_#{suffix}.db"
+    shard_b = "z_ecto_libsql_test-attached_b_#{suffix}.db"
+    {:ok, state} = EctoLibSql.connect(database: db_file)
+
+    on_exit(fn ->
+      EctoLibSql.disconnect([], state)
+
+      for file <- [db_file, shard_a, shard_b] do
+        EctoLibSql.TestHelpers.cleanup_db_files(file)
+      end
+    end)
+
+    {:ok, state: state, db_file: db_file, shard_a: shard_a, shard_b: shard_b}
+  end
+
+  describe "attached_databases/1" do
+    test "lists main and two attached databases with their files", ctx do
+      %{state: state, db_file: db_file, shard_a: shard_a, shard_b: shard_b} = ctx
+
+      {:ok, _, _, state} =
+        EctoLibSql.handle_execute("ATTACH DATABASE ? AS shard_a", [shard_a], [], state)
+
+      {:ok, _, _, state} =
+        EctoLibSql.handle_execute("ATTACH DATABASE ? AS shard_b", [shard_b], [], state)
+
+      assert {:ok, databases} = Native.attached_databases(state)
+
+      assert [
+               %{seq: 0, name: "main", file: main_file},
+               %{seq: 2, name: "shard_a", file: shard_a_file},
+               %{seq: 3, name: "shard_b", file: shard_b_file}
+             ] = databases
+
+      assert main_file == Path.expand(db_file)
+      assert shard_a_file == Path.expand(shard_a)
+      assert shard_b_file == Path.expand(shard_b)
+    end
+
+    test "reports nil files for in-memory and temp databases", %{state: state} do
+      {:ok, _, _, state} =
+        EctoLibSql.handle_execute("ATTACH DATABASE ':memory:' AS scratch", [], [], state)
+
+      {:ok, _, _, state} =
+        EctoLibSql.handle_execute("CREATE TEMP TABLE t (x INTEGER)", [], [], state)
+
+      assert {:ok, databases} = Native.attached_databases(state)
+      assert %{name: "temp", file: nil} = Enum.find(databases, &(&1.name == "temp"))
+      assert %{name: "scratch", file: nil} = Enum.find(databases, &(&1.name == "scratch"))
+    end
+
+    test "drops detached databases from the list", %{state: state, shard_a: shard_a} do
+      {:ok, _, _, state} =
+        EctoLibSql.handle_execute("ATTACH DATABASE ? AS shard_a", [shard_a], [], state)
+
+      {:ok, _, _, state} = EctoLibSql.handle_execute("DETACH DATABASE shard_a", [], [], state)
+
+      assert {:ok, [%{name: "main"}]} = Native.attached_databases(state)
+    end
+  end
+
+  describe "query_attached/4" do
+    setup %{state: state, shard_a: shard_a} do
+      {:ok, _, _, state} =
+        EctoLibSql.handle_execute(
+          "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT)",
+          [],
+          [],
+          state
+        )
+
+      {:ok, _, _, state} =
+        EctoLibSql.handle_execute("INSERT INTO users VALUES (1, 'main user')", [], [], state)
+
+      {:ok, _, _, state} =
+        EctoLibSql.handle_execute("ATTACH DATABASE ? AS shard_a", [shard_a], [], state)
+
+      {:ok, _, _, state} =
+        EctoLibSql.handle_execute(
+          "CREATE TABLE shard_a.users (id INTEGER PRIMARY KEY, name TEXT)",
+          [],
+          [],
+          state
+        )
+
+      {:ok, state: state}
+    end
+
+    test "reads and writes the attached database's table", %{state: state} do
+      assert {:ok, %{num_rows: 1}} =
+               Native.query_attached(state, "shard_a", "INSERT INTO users VALUES (?, ?)", [
+                 1,
+                 "shard user"
+               ])
+
+      assert {:ok, %{rows: [["shard user"]]}} =
+               Native.query_attached(state, "shard_a", "SELECT name FROM users WHERE id = ?", [1])
+
+      assert {:ok, %{rows: [["main user"]]}} =
+               Native.query_attached(state, "main", "SELECT name FROM users")
+    end
+
+    test "rejects a database that isn't attached", %{state: state} do
+      assert {:error, "Unknown database: shard_b"} =
+               Native.query_attached(state, "shard_b", "SELECT * FROM users")
+    end
+  end
+end
@@ -250,4 +250,48 @@ defmodule EctoLibSql.BatchFeaturesTest do
       EctoLibSql.disconnect([], state)
     end
   end
+
+  describe "remote_batch_write" do
+    test "returns affected row counts for each statement", %{database: database} do
+      {:ok, state} = EctoLibSql.connect(database: database)
+
+      {:ok, _, _, state} =
+        EctoLibSql.handle_execute(
+          "CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT)",
+          [],
+          [],
+          state
+        )
+
+      assert {:ok, [2, 1, 3, 0]} =
+               EctoLibSql.Native.remote_batch_write(state, [
+                 {"INSERT INTO items (id, name) VALUES (?, ?), (?, ?)", [1, "a", 2, "b"]},
+                 {"INSERT INTO items (id, name) VALUES (?, ?)", [3, nil]},
+                 {"UPDATE items SET name = ?", ["z"]},
+                 {"DELETE FROM items WHERE id = ?", [99]}
+               ])
+
+      EctoLibSql.disconnect([], state)
+    end
+
+    test "rolls back every statement when one fails", %{database: database} do
+      {:ok, state} = EctoLibSql.connect(database: database)
+
+      {:ok, _, _, state} =
+        EctoLibSql.handle_execute("CREATE TABLE items (id INTEGER PRIMARY KEY)", [], [], state)
+
+      assert {:error, _reason} =
+               EctoLibSql.Native.remote_batch_write(state, [
+                 {"INSERT INTO items (id) VALUES (?)", [1]},
+                 {"INSERT INTO items (id) VALUES (?)", [1]}
+               ])
+
+      {:ok, _, result, _state} =
+        EctoLibSql.handle_execute("SELECT COUNT(*) FROM items", [], [], state)
+
+      assert result.rows == [[0]]
+
+      EctoLibSql.disconnect([], state)
+    end
+  end
 end
@@ -0,0 +1,53 @@
+defmodule EctoLibSql.BenchmarkTest do
+  use ExUnit.Case
+
+  alias EctoLibSql.Native
+
+  setup do
+    db_file = "z_ecto_libsql_test-benchmark_#{:erlang.unique_integer([:positive])}.db"
+    {:ok, state} = EctoLibSql.connect(database: db_file)
+
+    {:ok, _, _, state} =
+      EctoLibSql.handle_execute(
+        """
+        CREATE TABLE items AS
+        WITH RECURSIVE n(x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM n WHERE x < 500)
+        SELECT x AS id, 'item ' || x AS name FROM n
+        """,
+        [],
+        [],
+        state
+      )
+
+    on_exit(fn ->
+      EctoLibSql.disconnect([], state)
+      EctoLibSql.TestHelpers.cleanup_db_files(db_file)
+    end)
+
+    {:ok, state: state}
+  end
+
+  test "returns ordered timing stats", %{state: state} do
+    assert {:ok, stats} =
+             Native.benchmark(state, "SELECT * FROM items WHERE name LIKE ?", ["item 1%"], 20)
+
+    assert Map.keys(stats) |> Enum.sort() == [:max_us, :mean_us, :min_us, :p95_us]
+    assert Enum.all?(Map.values(stats), &(is_integer(&1) and &1 >= 0))
+    assert stats.min_us <= stats.mean_us and stats.mean_us <= stats.max_us
+    assert stats.min_us <= stats.p95_us and stats.p95_us <= stats.max_us
+  end
+
+  test "defaults to 100 runs without arguments", %{state: state} do
+    assert {:ok, %{min_us: _}} = Native.benchmark(state, "SELECT count(*) FROM items")
+  end
+
+  test "rejects more runs than the cap", %{state: state} do
+    assert {:error, message} = Native.benchmark(state, "SELECT 1", [], 10_001)
+    assert message =~ "10000"
+  end
+
+  test "reports a failing query", %{state: state} do
+    assert {:error, message} = Native.benchmark(state, "SELECT * FROM missing", [], 3)
+    assert message =~ "missing"
+  end
+end
@@ -0,0 +1,65 @@
+defmodule EctoLibSql.BinaryFrameTest do
+  use ExUnit.Case
+
+  alias EctoLibSql.Native
+
+  setup do
+    db_file = "z_ecto_libsql_test-binary_frame_#{:erlang.unique_integer([:positive])}.db"
+    {:ok, state} = EctoLibSql.connect(database: db_file)
+
+    {:ok, _, _, state} =
+      EctoLibSql.handle_execute(
+        "CREATE TABLE items (id INTEGER PRIMARY KEY, price REAL, name TEXT, data BLOB)",
+        [],
+        [],
+        state
+      )
+
+    state =
+      Enum.reduce(
+        [[1, 9.5, "first", <<0, 255>>], [2, nil, "", nil], [3, -1.25, "héllo", <<>>]],
+        state,
+        fn row, state ->
+          {:ok, _, _, state} =
+            EctoLibSql.handle_execute("INSERT INTO items VALUES (?, ?, ?, ?)", row, [], state)
+
+          state
+        end
+      )
+
+    on_exit(fn ->
+      EctoLibSql.disconnect([], state)
+      EctoLibSql.TestHelpers.cleanup_db_files(db_file)
+    end)
+
+    {:ok, state: state}
+  end
+
+  @sql "SELECT id, price, name, data, id * 2 AS doubled FROM items WHERE id >= ? ORDER BY id"
+
+  test "decoded frame matches the regular query result", %{state: state} do
+    assert {:ok, {columns, frame}} = Native.query_frame(state, @sql, [1])
+    assert is_binary(frame)
+
+    {:ok, _, result, _state} = EctoLibSql.handle_execute(@sql, [1], [], state)
+
+    assert columns == result.columns
+    assert Native.decode_frame(columns, frame) == result.rows
+  end
+
+  test "frame layout follows the documented format", %{state: state} do
+    assert {:ok, {["a", "b"], frame}} =
+             Native.query_frame(state, "SELECT NULL AS a, 'hi' AS b")
+
+    assert frame == <<0, 3, 2::32, "hi">>
+  end
+
+  test "empty results give an empty frame", %{state: state} do
+    assert {:ok, {_columns, ""}} = Native.query_frame(state, "SELECT * FROM items WHERE 0")
+    assert Native.decode_frame(["id"], "") == []
+  end
+
+  test "invalid SQL returns an error", %{state: state} do
+    assert {:error, _} = Native.query_frame(state, "SELECT * FROM missing_table")
+  end
+end
@@ -0,0 +1,79 @@
+defmodule EctoLibSql.BlobBuilderTest do
+  use ExUnit.Case
+
+  alias EctoLibSql.Native
+
+  setup do
+    db_file = "z_ecto_libsql_test-blob_builder_#{:erlang.unique_integer([:positive])}.db"
+    {:ok, state} = EctoLibSql.connect(database: db_file)
+
+    {:ok, _, _, state} =
+      EctoLibSql.handle_execute(
+        "CREATE TABLE files (id INTEGER PRIMARY KEY, name TEXT, data BLOB)",
+        [],
+        [],
+        state
+      )
+
+    on_exit(fn ->
+      EctoLibSql.disconnect([], state)
+      EctoLibSql.TestHelpers.cleanup_db_files(db_file)
+    end)
+
+    {:ok, state: state}
+  end
+
+  test "appends three chunks and inserts the combined blob", %{state: state} do
+    {:ok, builder} = Native.new_blob_builder()
+
+    assert {:ok, 3} = Native.append_blob_chunk(builder, <<1, 2, 3>>)
+    assert {:ok, 5} = Native.append_blob_chunk(builder, <<4, 5>>)
+    assert {:ok, 9} = Native.append_blob_chunk(builder, <<6, 7, 8, 9>>)
+
+    assert {:ok, 1} =
+             Native.insert_built_blob(
+               state,
+               "INSERT INTO files (name, data) VALUES (?, ?)",
+               ["combined", builder]
+             )
+
+    assert {:ok, _, %EctoLibSql.Result{rows: [["combined", blob]]}, _} =
+             EctoLibSql.handle_execute("SELECT name, data FROM files", [], [], state)
+
+    assert blob == <<1, 2, 3, 4, 5, 6, 7, 8, 9>>
+  end
+
+  test "builders are consumed by the insert", %{state: state} do
+    {:ok, builder} = Native.new_blob_builder()
+    {:ok, _} = Native.append_blob_chunk(builder, "data")
+
+    {:ok, 1} =
+      Native.insert_built_blob(state, "INSERT INTO files (data) VALUES (?)", [builder])
+
+    assert {:error, _} = Native.append_blob_chunk(builder, "more")
+    assert {:error, _} =
+             Native.insert_built_blob(state, "INSERT INTO files (data) VALUES (?)", [builder])
+  end
+
+  test "a builder bound twice is rejected without being consumed", %{state: state} do
+    {:ok, builder} = Native.new_blob_builder()
+    {:ok, _} = Native.append_blob_chunk(builder, "data")
+
+    assert {:error, reason} =
+             Native.insert_built_blob(state, "INSERT INTO files (name, data) VALUES (?, ?)", [
+               builder,
+               builder
+             ])
+
+    assert reason =~ "more than once"
+    assert {:ok, 8} = Native.append_blob_chunk(builder, "more")
+    assert :ok = Native.discard_blob_builder(builder)
+  end
+
+  test "discarded builders can't be appended to" do
+    {:ok, builder} = Native.new_blob_builder()
+    assert :ok = Native.discard_blob_builder(builder)
+    assert :ok = Native.discard_blob_builder(builder)
+    assert {:error, _} = Native.append_blob_chunk(builder, "data")
+  end
+end
@@ -0,0 +1,112 @@
+defmodule EctoLibSql.BlobRefTest do
+  use ExUnit.Case
+
+  alias EctoLibSql.Native
+
+  setup do
+    db_file = "z_ecto_libsql_test-blob_ref_#{:erlang.unique_integer([:positive])}.db"
+    {:ok, state} = EctoLibSql.connect(database: db_file)
+
+    {:ok, _, _, state} =
+      EctoLibSql.handle_execute(
+        "CREATE TABLE files (id INTEGER PRIMARY KEY, name TEXT, data BLOB)",
+        [],
+        [],
+        state
+      )
+
+    on_exit(fn ->
+      EctoLibSql.disconnect([], state)
+      EctoLibSql.TestHelpers.cleanup_db_files(db_file)
+    end)
+
+    {:ok, state: state}
+  end
+
+  describe "query_blob_refs/3 and fetch_blob/3" do
+    test "returns markers for blobs and fetches one on demand", %{state: state} do
+      big = :binary.copy(<<0, 255, 1>>, 100_000)
+
+      {:ok, _, _, state} =
+        EctoLibSql.handle_execute(
+          "INSERT INTO files (id, name, data) VALUES (1, 'big.bin', ?), (2, 'tiny.bin', ?)",
+          [{:blob, big}, {:blob, <<7>>}],
+          [],
+          state
+        )
+
+      assert {:ok, result} =
+               Native.query_blob_refs(state, "SELECT id, name, data FROM files ORDER BY id")
+
+      assert result.columns == ["id", "name", "data"]
+
+      assert [
+               [1, "big.bin", {:blob_ref, 1, "data", 300_000, "main"} = big_ref],
+               [2, "tiny.bin", {:blob_ref, 2, "data", 1, "main"}]
+             ] = result.rows
+
+      assert {:ok, ^big} = Native.fetch_blob(state, "files", big_ref)
+      assert {:ok, ^big} = Native.fetch_blob(state, :files, big_ref)
+    end
+
+    test "returns non-blob values and computed blobs inline", %{state: state} do
+      {:ok, _, _, state} =
+        EctoLibSql.handle_execute(
+          "INSERT INTO files (id, name, data) VALUES (1, 'a', NULL)",
+          [],
+          [],
+          state
+        )
+
+      sql = "SELECT rowid, data, x'09' FROM files WHERE id = ?"
+      assert {:ok, %{rows: [[1, nil, <<9>>]]}} = Native.query_blob_refs(state, sql, [1])
+    end
+
+    test "fetches from the schema the blob was selected from", %{state: state} do
+      {:ok, _, _, state} =
+        EctoLibSql.handle_execute(
+          "INSERT INTO files (id, name, data) VALUES (1, 'main', x'01')",
+          [],
+          [],
+          state
+        )
+
+      {:ok, _, _, state} =
+        EctoLibSql.handle_execute(
+          "CREATE TEMP TABLE files (id INTEGER PRIMARY KEY, data BLOB)",
+          [],
+          [],
+          state
+        )
+
+      {:ok, _, _, state} =
+        EctoLibSql.handle_execute("INSERT INTO temp.files VALUES (1, x'0203')", [], [], state)
+
+      assert {:ok, %{rows: [[1, {:blob_ref, 1, "data", 2, "temp"} = ref]]}} =
+               Native.query_blob_refs(state, "SELECT id, data FROM temp.files")
+
+      assert {:ok, <<2, 3>>} = Native.fetch_blob(state, "files", ref)
+    end
+
+    test "requires the query to select the rowid", %{state: state} do
+      assert {:error, reason} = Native.query_blob_refs(state, "SELECT data FROM files")
+      assert reason =~ "must select the rowid"
+    end
+
+    test "fetching a deleted row fails", %{state: state} do
+      {:ok, _, _, state} =
+        EctoLibSql.handle_execute(
+          "INSERT INTO files (id, name, data) VALUES (1, 'a', x'01')",
+          [],
+          [],
+          state
+        )
+
+      {:ok, %{rows: [[_id, ref]]}} = Native.query_blob_refs(state, "SELECT id, data FROM files")
+      {:ok, _, _, state} = EctoLibSql.handle_execute("DELETE FROM files", [], [], state)
+
+      assert {:error, reason} = Native.fetch_blob(state, "files", ref)
+      assert reason =~ "Row not found"
+    end
+  end
+end
@@ -0,0 +1,70 @@
+defmodule EctoLibSql.BlobWriteTest do
+  @moduledoc """
+  Tests for overwriting a region of a blob in place.
+  """
+  use ExUnit.Case
+
+  alias EctoLibSql.Native
+
+  setup do
+    test_db = "z_ecto_libsql_test-blob_write_#{:erlang.unique_integer([:positive])}.db"
+
+    {:ok, state} = EctoLibSql.connect(database: test_db)
+
+    {:ok, _, _, state} =
+      EctoLibSql.handle_execute(
+        "CREATE TABLE files (id INTEGER PRIMARY KEY, data BLOB)",
+        [],
+        [],
+        state
+      )
+
+    on_exit(fn ->
+      EctoLibSql.disconnect([], state)
+      EctoLibSql.TestHelpers.cleanup_db_files(test_db)
+    end)
+
+    {:ok, state: state}
+  end
+
+  defp read_blob(state, id) do
+    {:ok, _, %{rows: [[data]]}, _} =
+      EctoLibSql.handle_execute("SELECT data FROM files WHERE id = ?", [id], [], state)
+
+    data
+  end
+
+  describe "write_blob/6" do
+    test "patches the middle of a blob", %{state: state} do
+      {:ok, _, _, state} =
+        EctoLibSql.handle_execute(
+          "INSERT INTO files (id, data) VALUES (1, ?)",
+          [<<0, 1, 2, 3, 4, 5, 6, 255>>],
+          [],
+          state
+        )
+
+      assert :ok = Native.write_blob(state, "files", :data, 1, 2, <<170, 0, 187>>)
+      assert read_blob(state, 1) == <<0, 1, 170, 0, 187, 5, 6, 255>>
+    end
+
+    test "rejects writes past the end of the blob", %{state: state} do
+      {:ok, _, _, state} =
+        EctoLibSql.handle_execute(
+          "INSERT INTO files (id, data) VALUES (1, ?)",
+          [<<0, 1, 2, 255>>],
+          [],
+          state
+        )
+
+      assert {:error, reason} = Native.write_blob(state, :files, :data, 1, 3, <<9, 9>>)
+      assert reason =~ "exceeds blob size"
+      assert read_blob(state, 1) == <<0, 1, 2, 255>>
+    end
+
+    test "returns an error for a missing row", %{state: state} do
+      assert {:error, reason} = Native.write_blob(state, "files", "data", 42, 0, <<1>>)
+      assert reason =~ "Row not found"
+    end
+  end
+end
@@ -0,0 +1,76 @@
+defmodule EctoLibSql.CaptureRowidsTest do
+  @moduledoc """
+  Tests for capturing the rowids affected by a DML statement.
+  """
+  use ExUnit.Case
+
+  alias EctoLibSql.Native
+
+  setup do
+    test_db = "z_ecto_libsql_test-capture_rowids_#{:erlang.unique_integer([:positive])}.db"
+
+    {:ok, state} = EctoLibSql.connect(database: test_db)
+
+    {:ok, _, _, state} =
+      EctoLibSql.handle_execute(
+        "CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT)",
+        [],
+        [],
+        state
+      )
+
+    {:ok, _, _, state} =
+      EctoLibSql.handle_execute(
+        "INSERT INTO items (name) VALUES ('a'), ('b'), ('c'), ('d'), ('e')",
+        [],
+        [],
+        state
+      )
+
+    on_exit(fn ->
+      EctoLibSql.disconnect([], state)
+      EctoLibSql.TestHelpers.cleanup_db_files(test_db)
+    end)
+
+    {:ok, state: state}
+  end
+
+  defp temp_objects(state) do
+    {:ok, _, %{rows: [[count]]}, _} =
+      EctoLibSql.handle_execute("SELECT count(*) FROM sqlite_temp_master", [], [], state)
+
+    count
+  end
+
+  test "returns every rowid touched by a multi-row UPDATE", %{state: state} do
+    assert {:ok, rowids} =
+             Native.execute_capturing_rowids(
+               state,
+               "UPDATE items SET name = upper(name) WHERE id IN (?, ?, ?)",
+               [1, 3, 5]
+             )
+
+    assert Enum.sort(rowids) == [1, 3, 5]
+    assert temp_objects(state) == 0
+  end
+
+  test "returns rowids for inserts and deletes", %{state: state} do
+    assert {:ok, [6, 7]} =
+             Native.execute_capturing_rowids(state, "INSERT INTO items (name) VALUES ('f'), ('g')")
+
+    assert {:ok, [1, 2]} =
+             Native.execute_capturing_rowids(state, "DELETE FROM items WHERE id < ?", [3])
+  end
+
+  test "cleans up after a failing statement", %{state: state} do
+    assert {:error, _reason} =
+             Native.execute_capturing_rowids(state, "UPDATE items SET missing = 1")
+
+    assert temp_objects(state) == 0
+  end
+
+  test "rejects statements that aren't INSERT, UPDATE or DELETE", %{state: state} do
+    assert {:error, reason} = Native.execute_capturing_rowids(state, "SELECT * FROM items")
+    assert reason =~ "INSERT, UPDATE or DELETE"
+  end
+end
@@ -0,0 +1,38 @@
+defmodule EctoLibSql.CloseSweepTest do
+  use ExUnit.Case
+
+  alias EctoLibSql.Native
+
+  setup do
+    db_file = "z_ecto_libsql_test-close_sweep_#{:erlang.unique_integer([:positive])}.db"
+    {:ok, state} = EctoLibSql.connect(database: db_file)
+
+    {:ok, _} =
+      Native.execute_batch_sql(state, """
+      CREATE TABLE items (id INTEGER PRIMARY KEY);
+      INSERT INTO items (id) VALUES (1), (2), (3);
+      """)
+
+    on_exit(fn ->
+      EctoLibSql.TestHelpers.cleanup_db_files(db_file)
+    end)
+
+    {:ok, state: state}
+  end
+
+  test "closing a connection removes its cursors and statements", %{state: state} do
+    cursor_id = Native.declare_cursor(state.conn_id, "SELECT id FROM items", [], nil)
+    assert is_binary(cursor_id)
+    {:ok, stmt_id} = Native.prepare(state, "SELECT id FROM items")
+
+    assert {:ok, 2} = Native.close(state.conn_id, :conn_id)
+
+    assert {:error, "Cursor not found"} = Native.close(cursor_id, :cursor_id)
+    assert {:error, "Statement not found"} = Native.close(stmt_id, :stmt_id)
+  end
+
+  test "disconnect still returns :ok", %{state: state} do
+    _cursor_id = Native.declare_cursor(state.conn_id, "SELECT id FROM items", [], nil)
+    assert :ok = EctoLibSql.disconnect([], state)
+  end
+end
@@ -0,0 +1,85 @@
+defmodule EctoLibSql.CoerceTextNumbersTest do
+  use ExUnit.Case
+
+  setup do
+    db_file = "z_ecto_libsql_test-coerce_numbers_#{:erlang.unique_integer([:positive])}.db"
+    {:ok, state} = EctoLibSql.connect(database: db_file)
+
+    # Numeric affinity converts numeric text on insert, so legacy data like this only
+    # arises when declared types change under existing rows: store the values in TEXT
+    # columns, then retype the columns in place.
+    {:ok, _} =
+      EctoLibSql.Native.execute_batch_sql(state, """
+      CREATE TABLE legacy (code TEXT, qty TEXT, price TEXT);
+      INSERT INTO legacy VALUES ('007', '12', '9.5');
+      PRAGMA writable_schema = ON;
+      UPDATE sqlite_master
+         SET sql = 'CREATE TABLE legacy (code TEXT, qty INTEGER, price REAL)'
+       WHERE name = 'legacy';
+      PRAGMA writable_schema = OFF;
+      """)
+
+    EctoLibSql.disconnect([], state)
+    {:ok, state} = EctoLibSql.connect(database: db_file)
+
+    on_exit(fn ->
+      EctoLibSql.disconnect([], state)
+      EctoLibSql.TestHelpers.cleanup_db_files(db_file)
+    end)
+
+    {:ok, state: state}
+  end
+
+  test "returns numeric text as numbers when asked", %{state: state} do
+    sql = "SELECT code, qty, price FROM legacy"
+
+    {:ok, _, plain, _} = EctoLibSql.handle_execute(sql, [], [], state)
+    assert plain.rows == [["007", "12", "9.5"]]
+
+    {:ok, _, coerced, _} =
+      EctoLibSql.handle_execute(sql, [], [coerce_text_numbers: true], state)
+
+    assert coerced.columns == ["code", "qty", "price"]
+    assert coerced.rows == [["007", 12, 9.5]]
+  end
+
+  test "leaves expressions without a declared type unchanged", %{state: state} do
+    {:ok, _, result, _} =
+      EctoLibSql.handle_execute(
+        "SELECT qty || '' AS qty_text FROM legacy",
+        [],
+        [coerce_text_numbers: true],
+        state
+      )
+
+    assert result.rows == [["12"]]
+  end
+
+  test "reads the open transaction's uncommitted writes", %{state: state} do
+    {:ok, :begin, state} = EctoLibSql.handle_begin([], state)
+
+    {:ok, _, _, state} =
+      EctoLibSql.handle_execute("INSERT INTO legacy VALUES ('008', '3', '1.5')", [], [], state)
+
+    {:ok, _, result, state} =
+      EctoLibSql.handle_execute(
+        "SELECT qty FROM legacy ORDER BY code",
+        [],
+        [coerce_text_numbers: true],
+        state
+      )
+
+    assert result.rows == [[12], [3]]
+    {:ok, _, _state} = EctoLibSql.handle_rollback([], state)
+  end
+
+  test "honours max_result_bytes", %{state: state} do
+    assert {:error, %EctoLibSql.Error{sqlite: %{code: :result_too_large}}, _} =
+             EctoLibSql.handle_execute(
+               "SELECT code, qty, price FROM legacy",
+               [],
+               [coerce_text_numbers: true, max_result_bytes: 4],
+               state
+             )
+  end
+end
@@ -0,0 +1,52 @@
+defmodule EctoLibSql.ColumnDetailsTest do
+  use ExUnit.Case
+
+  alias EctoLibSql.Native
+
+  setup do
+    db_file = "z_ecto_libsql_test-column_details_#{:erlang.unique_integer([:positive])}.db"
+    {:ok, state} = EctoLibSql.connect(database: db_file)
+
+    on_exit(fn ->
+      EctoLibSql.disconnect([], state)
+      EctoLibSql.TestHelpers.cleanup_db_files(db_file)
+    end)
+
+    {:ok, state: state}
+  end
+
+  test "describes defaults and generated columns", %{state: state} do
+    {:ok, _, _, state} =
+      EctoLibSql.handle_execute(
+        """
+        CREATE TABLE items (
+          id INTEGER PRIMARY KEY,
+          price REAL NOT NULL DEFAULT 0.0,
+          qty INTEGER DEFAULT 1,
+          total REAL GENERATED ALWAYS AS (price * qty) VIRTUAL,
+          slug TEXT AS (lower(name)) STORED,
+          name TEXT
+        )
+        """,
+        [],
+        [],
+        state
+      )
+
+    assert {:ok, columns} = Native.column_details(state, "items")
+
+    assert Enum.map(columns, & &1.name) == ["id", "price", "qty", "total", "slug", "name"]
+
+    by_name = Map.new(columns, &{&1.name, &1})
+    assert by_name["price"] == %{name: "price", type: "REAL", default: "0.0", generated: nil}
+    assert by_name["qty"].default == "1"
+    assert by_name["total"].generated == {:virtual, "price * qty"}
+    assert by_name["slug"].generated == {:stored, "lower(name)"}
+    assert by_name["name"].generated == nil
+  end
+
+  test "returns an error for an unknown table", %{state: state} do
+    assert {:error, reason} = Native.column_details(state, "missing")
+    assert reason =~ "Table not found"
+  end
+end
@@ -0,0 +1,58 @@
+defmodule EctoLibSql.ColumnIndexedTest do
+  use ExUnit.Case
+
+  alias EctoLibSql.Native
+
+  setup do
+    db_file = "z_ecto_libsql_test-column_indexed_#{:erlang.unique_integer([:positive])}.db"
+    {:ok, state} = EctoLibSql.connect(database: db_file)
+
+    {:ok, _, _, state} =
+      EctoLibSql.handle_execute(
+        "CREATE TABLE orders (id INTEGER PRIMARY KEY, customer_id INTEGER, status TEXT, note TEXT)",
+        [],
+        [],
+        state
+      )
+
+    {:ok, _, _, state} =
+      EctoLibSql.handle_execute(
+        "CREATE INDEX orders_customer_status ON orders (customer_id, status)",
+        [],
+        [],
+        state
+      )
+
+    on_exit(fn ->
+      EctoLibSql.disconnect([], state)
+      EctoLibSql.TestHelpers.cleanup_db_files(db_file)
+    end)
+
+    {:ok, state: state}
+  end
+
+  test "a leading index column is indexed", %{state: state} do
+    assert {:ok, %{indexed: true, indexes: [{"orders_customer_status", 0}]}} =
+             Native.column_indexed(state, "orders", "customer_id")
+  end
+
+  test "an unindexed column is not", %{state: state} do
+    assert {:ok, %{indexed: false, indexes: []}} = Native.column_indexed(state, "orders", "note")
+  end
+
+  test "a non-leading index column is reported but not indexed", %{state: state} do
+    assert {:ok, %{indexed: false, indexes: [{"orders_customer_status", 1}]}} =
+             Native.column_indexed(state, "orders", "status")
+  end
+
+  test "the rowid alias is indexed", %{state: state} do
+    assert {:ok, %{indexed: true, indexes: []}} = Native.column_indexed(state, "orders", "id")
+  end
+
+  test "unknown columns and tables are errors", %{state: state} do
+    assert {:error, message} = Native.column_indexed(state, "orders", "missing")
+    assert message =~ "Column not found"
+    assert {:error, message} = Native.column_indexed(state, "missing", "id")
+    assert message =~ "Table not found"
+  end
+end
@@ -0,0 +1,98 @@
+defmodule EctoLibSql.ColumnNamingTest do
+  @moduledoc """
+  Tests for disambiguating duplicate result column names, such as those produced by joins.
+  """
+  use ExUnit.Case
+
+  alias EctoLibSql.Native
+
+  @self_join "SELECT c.id, c.name, p.id FROM items c JOIN items p ON p.id = c.parent_id"
+
+  setup do
+    test_db = "z_ecto_libsql_test-column_naming_#{:erlang.unique_integer([:positive])}.db"
+
+    on_exit(fn ->
+      EctoLibSql.TestHelpers.cleanup_db_files(test_db)
+    end)
+
+    {:ok, database: test_db}
+  end
+
+  defp connect_with_items(opts) do
+    {:ok, state} = EctoLibSql.connect(opts)
+
+    for sql <- [
+          "CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT, parent_id INTEGER)",
+          "CREATE TABLE tags (id INTEGER PRIMARY KEY, item_id INTEGER)",
+          "INSERT INTO items (id, name, parent_id) VALUES (1, 'root', NULL), (2, 'child', 1)",
+          "INSERT INTO tags (id, item_id) VALUES (10, 2)"
+        ] do
+      {:ok, _, _, _} = EctoLibSql.handle_execute(sql, [], [], state)
+    end
+
+    state
+  end
+
+  defp columns(state, sql) do
+    {:ok, _, result, _} = EctoLibSql.handle_execute(sql, [], [], state)
+    result.columns
+  end
+
+  test "raw names are the default", %{database: database} do
+    state = connect_with_items(database: database)
+
+    assert columns(state, @self_join) == ["id", "name", "id"]
+
+    EctoLibSql.disconnect([], state)
+  end
+
+  test ":index_suffix renames later duplicates in a self-join", %{database: database} do
+    state = connect_with_items(database: database)
+
+    assert :ok = Native.column_naming(state, :index_suffix)
+    assert columns(state, @self_join) == ["id", "name", "id_2"]
+
+    EctoLibSql.disconnect([], state)
+  end
+
+  test ":table_prefix uses origin tables and falls back for self-joins", %{database: database} do
+    state = connect_with_items(database: database, column_naming: :table_prefix)
+
+    assert columns(state, "SELECT i.id, t.id FROM items i JOIN tags t ON t.item_id = i.id") ==
+             ["items.id", "tags.id"]
+
+    assert columns(state, @self_join) == ["items.id", "name", "items.id_2"]
+
+    EctoLibSql.disconnect([], state)
+  end
+
+  test "queries inside a transaction use the connection setting", %{database: database} do
+    state = connect_with_items(database: database, column_naming: :table_prefix)
+
+    {:ok, _, state} = EctoLibSql.handle_begin([], state)
+
+    assert columns(state, "SELECT i.id, t.id FROM items i JOIN tags t ON t.item_id = i.id") ==
+             ["items.id", "tags.id"]
+
+    {:ok, _, state} = EctoLibSql.handle_rollback([], state)
+    EctoLibSql.disconnect([], state)
+  end
+
+  test "prepared statement queries use the connection setting", %{database: database} do
+    state = connect_with_items(database: database, column_naming: :index_suffix)
+
+    {:ok, stmt_id} = Native.prepare(state, @self_join)
+    {:ok, result} = Native.query_stmt(state, stmt_id, [])
+
+    assert result.columns == ["id", "name", "id_2"]
+    assert result.rows == [[2, "child", 1]]
+
+    Native.close_stmt(stmt_id)
+    EctoLibSql.disconnect([], state)
+  end
+
+  test "invalid connect option is rejected", %{database: database} do
+    assert {:error, reason} = EctoLibSql.connect(database: database, column_naming: :bogus)
+    assert reason =~ "column_naming"
+  end
+end
@@ -0,0 +1,57 @@
+defmodule EctoLibSql.ColumnProvenanceTest do
+  use ExUnit.Case
+
+  alias EctoLibSql.Native
+
+  setup do
+    db_file = "z_ecto_libsql_test-column_provenance_#{:erlang.unique_integer([:positive])}.db"
+    {:ok, state} = EctoLibSql.connect(database: db_file)
+
+    {:ok, _, _, state} =
+      EctoLibSql.handle_execute(
+        "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT)",
+        [],
+        [],
+        state
+      )
+
+    {:ok, _, _, state} =
+      EctoLibSql.handle_execute(
+        "CREATE TABLE posts (id INTEGER PRIMARY KEY, user_id INTEGER, title TEXT)",
+        [],
+        [],
+        state
+      )
+
+    on_exit(fn ->
+      EctoLibSql.disconnect([], state)
+      EctoLibSql.TestHelpers.cleanup_db_files(db_file)
+    end)
+
+    {:ok, state: state}
+  end
+
+  test "maps aliased columns of a join back to their source", %{state: state} do
+    {:ok, stmt_id} =
+      Native.prepare(
+        state,
+        "SELECT u.name AS author, p.title, p.id AS post_id, length(p.title) AS len " <>
+          "FROM posts p JOIN users u ON u.id = p.user_id"
+      )
+
+    on_exit(fn -> Native.close_stmt(stmt_id) end)
+
+    assert {:ok, columns} = Native.stmt_column_provenance(state, stmt_id)
+
+    assert columns == [
+             %{name: "author", database: "main", table: "users", origin_column: "name"},
+             %{name: "title", database: "main", table: "posts", origin_column: "title"},
+             %{name: "post_id", database: "main", table: "posts", origin_column: "id"},
+             %{name: "len", database: nil, table: nil, origin_column: nil}
+           ]
+  end
+
+  test "unknown statements are errors", %{state: state} do
+    assert {:error, _} = Native.stmt_column_provenance(state, "missing")
+  end
+end
@@ -0,0 +1,87 @@
+defmodule EctoLibSql.CompareAndUpdateTest do
+  use ExUnit.Case
+
+  alias EctoLibSql.Native
+
+  setup do
+    db_file = "z_ecto_libsql_test-compare_and_update_#{:erlang.unique_integer([:positive])}.db"
+    {:ok, state} = EctoLibSql.connect(database: db_file)
+
+    state =
+      Enum.reduce(
+        [
+          "CREATE TABLE docs (id INTEGER PRIMARY KEY, body TEXT, version INTEGER NOT NULL)",
+          "INSERT INTO docs (id, body, version) VALUES (1, 'draft', 3)"
+        ],
+        state,
+        fn sql, state ->
+          {:ok, _, _, state} = EctoLibSql.handle_execute(sql, [], [], state)
+          state
+        end
+      )
+
+    on_exit(fn ->
+      EctoLibSql.disconnect([], state)
+      EctoLibSql.TestHelpers.cleanup_db_files(db_file)
+    end)
+
+    {:ok, state: state}
+  end
+
+  defp doc(state) do
+    {:ok, _, result, _} =
+      EctoLibSql.handle_execute("SELECT body, version FROM docs WHERE id = 1", [], [], state)
+
+    hd(result.rows)
+  end
+
+  test "runs the update when the precheck matches", %{state: state} do
+    assert {:ok, 1} =
+             Native.compare_and_update(
+               state,
+               "SELECT version FROM docs WHERE id = ?",
+               [1],
+               3,
+               "UPDATE docs SET body = ?, version = version + 1 WHERE id = ?",
+               ["final", 1]
+             )
+
+    assert doc(state) == ["final", 4]
+  end
+
+  test "returns the actual value and leaves the row alone on a mismatch", %{state: state} do
+    assert {:error, :precondition_failed, 3} =
+             Native.compare_and_update(
+               state,
+               "SELECT version FROM docs WHERE id = ?",
+               [1],
+               2,
+               "UPDATE docs SET body = ?, version = version + 1 WHERE id = ?",
+               ["stale", 1]
+             )
+
+    assert doc(state) == ["draft", 3]
+  end
+
+  test "treats a precheck with no rows as nil", %{state: state} do
+    assert {:error, :precondition_failed, nil} =
+             Native.compare_and_update(
+               state,
+               "SELECT version FROM docs WHERE id = ?",
+               [99],
+               3,
+               "DELETE FROM docs",
+               []
+             )
+
+    assert {:ok, 0} =
+             Native.compare_and_update(
+               state,
+               "SELECT version FROM docs WHERE id = ?",
+               [99],
+               nil,
+               "DELETE FROM docs WHERE id = ?",
+               [99]
+             )
+  end
+end
@@ -0,0 +1,94 @@
+defmodule EctoLibSql.ConflictActionTest do
+  use ExUnit.Case
+
+  alias EctoLibSql.Native
+
+  describe "conflict_action/1" do
+    test "plain writes and OR ABORT use :abort" do
+      assert Native.conflict_action("INSERT INTO users (id) VALUES (1)") == :abort
+      assert Native.conflict_action("UPDATE users SET id = 2") == :abort
+      assert Native.conflict_action("INSERT OR ABORT INTO users (id) VALUES (1)") == :abort
+    end
+
+    test "detects OR IGNORE" do
+      assert Native.conflict_action("insert or ignore into users (id) values (1)") == :ignore
+      assert Native.conflict_action("UPDATE OR IGNORE users SET id = 2") == :ignore
+    end
+
+    test "detects OR REPLACE and REPLACE" do
+      assert Native.conflict_action("INSERT OR REPLACE INTO users (id) VALUES (1)") == :replace
+      assert Native.conflict_action("REPLACE INTO users (id) VALUES (1)") == :replace
+    end
+
+    test "detects OR ROLLBACK and OR FAIL" do
+      assert Native.conflict_action("INSERT OR ROLLBACK INTO users (id) VALUES (1)") ==
+               :rollback
+
+      assert Native.conflict_action("INSERT OR FAIL INTO users (id) VALUES (1)") == :fail
+    end
+
+    test "skips leading comments" do
+      sql = "-- upsert\nINSERT /* x */ OR IGNORE INTO users VALUES (1)"
+      assert Native.conflict_action(sql) == :ignore
+    end
+
+    test "returns nil for other statements" do
+      assert Native.conflict_action("SELECT 1") == nil
+      assert Native.conflict_action("DELETE FROM users") == nil
+    end
+  end
+
+  describe "affected-row counts" do
+    setup do
+      db_file = "z_ecto_libsql_test-conflict_action_#{:erlang.unique_integer([:positive])}.db"
+      {:ok, state} = EctoLibSql.connect(database: db_file)
+
+      {:ok, _, _, state} =
+        EctoLibSql.handle_execute(
+          "CREATE TABLE users (id INTEGER PRIMARY KEY, email TEXT UNIQUE)",
+          [],
+          [],
+          state
+        )
+
+      {:ok, _, _, state} =
+        EctoLibSql.handle_execute(
+          "INSERT INTO users VALUES (1, 'a@example.com'), (2, 'b@example.com')",
+          [],
+          [],
+          state
+        )
+
+      on_exit(fn ->
+        EctoLibSql.disconnect([], state)
+        EctoLibSql.TestHelpers.cleanup_db_files(db_file)
+      end)
+
+      {:ok, state: state}
+    end
+
+    test "OR IGNORE counts only rows written", %{state: state} do
+      {:ok, _, result, _state} =
+        EctoLibSql.handle_execute(
+          "INSERT OR IGNORE INTO users VALUES (1, 'x@example.com'), (3, 'c@example.com')",
+          [],
+          [],
+          state
+        )
+
+      assert result.num_rows == 1
+    end
+
+    test "REPLACE counts the inserted row, not the rows it replaced", %{state: state} do
+      {:ok, _, result, state} =
+        EctoLibSql.handle_execute("REPLACE INTO users VALUES (1, 'b@example.com')", [], [], state)
+
+      assert result.num_rows == 1
+
+      {:ok, _, count, _state} =
+        EctoLibSql.handle_execute("SELECT COUNT(*) FROM users", [], [], state)
+
+      assert count.rows == [[1]]
+    end
+  end
+end
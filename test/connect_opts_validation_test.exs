@@ -0,0 +1,39 @@
+defmodule EctoLibSql.ConnectOptsValidationTest do
+  use ExUnit.Case, async: true
+
+  alias EctoLibSql.Native
+
+  test "accepts a local database with adapter options" do
+    assert {:ok, [database: "app.db", busy_timeout: 1000]} =
+             Native.check_connect_opts(database: "app.db", busy_timeout: 1000)
+  end
+
+  test "accepts a complete remote replica configuration" do
+    opts = [database: "replica.db", uri: "libsql://x.turso.io", auth_token: "t", sync: true]
+    assert {:ok, ^opts} = Native.check_connect_opts(opts)
+  end
+
+  test "reports a missing auth_token in remote mode" do
+    assert {:error, {:missing_option, :auth_token}} =
+             Native.check_connect_opts([uri: "libsql://x.turso.io"], :remote)
+  end
+
+  test "treats a nil auth_token as missing" do
+    assert {:error, {:missing_option, :auth_token}} =
+             Native.check_connect_opts([uri: "libsql://x.turso.io", auth_token: nil], :remote)
+  end
+
+  test "reports a typo'd key as unknown" do
+    assert {:error, {:unknown_option, :auth_tokne}} =
+             Native.check_connect_opts(uri: "libsql://x.turso.io", auth_tokne: "t")
+  end
+
+  test "reports a missing database in local mode" do
+    assert {:error, {:missing_option, :database}} = Native.check_connect_opts([], :local)
+  end
+
+  test "keeps the first of repeated keys" do
+    assert {:ok, [database: "first.db"]} =
+             Native.check_connect_opts(database: "first.db", database: "second.db")
+  end
+end
@@ -73,6 +73,45 @@ defmodule EctoLibSql.ConnectionFeaturesTest do
 
       EctoLibSql.disconnect([], state)
     end
+
+    test "transaction busy_timeout is restored on commit and rollback", %{database: database} do
+      {:ok, state} = EctoLibSql.connect(database: database, busy_timeout: 1_000)
+
+      for finish <- [:commit, :rollback] do
+        {:ok, trx_state} =
+          EctoLibSql.Native.begin(state, behavior: :immediate, busy_timeout: 30_000)
+
+        assert busy_timeout(trx_state) == 30_000
+        assert {:ok, _} = apply(EctoLibSql.Native, finish, [trx_state])
+        assert busy_timeout(state) == 1_000
+      end
+
+      EctoLibSql.disconnect([], state)
+    end
+
+    test "an invalid transaction busy_timeout is an error", %{database: database} do
+      {:ok, state} = EctoLibSql.connect(database: database)
+
+      for timeout <- [nil, -1, 1.5] do
+        assert {:error, reason} = EctoLibSql.Native.begin(state, busy_timeout: timeout)
+        assert reason =~ "busy_timeout must be a non-negative integer"
+      end
+
+      EctoLibSql.disconnect([], state)
+    end
+
+    test "transaction busy_timeout is restored when the transaction is closed", %{
+      database: database
+    } do
+      {:ok, state} = EctoLibSql.connect(database: database, busy_timeout: 1_000)
+      {:ok, trx_state} = EctoLibSql.Native.begin(state, busy_timeout: 0)
+      assert busy_timeout(trx_state) == 0
+
+      assert :ok = EctoLibSql.Native.close(trx_state.trx_id, :trx_id)
+      assert busy_timeout(state) == 1_000
+
+      EctoLibSql.disconnect([], state)
+    end
   end
 
   # ============================================================================
@@ -276,6 +315,56 @@ defmodule EctoLibSql.ConnectionFeaturesTest do
     end
   end
 
+  # ============================================================================
+  # release_memory - IMPLEMENTED ✅
+  # ============================================================================
+
+  describe "release_memory" do
+    test "shrinks memory after a big query", %{database: database} do
+      {:ok, state} = EctoLibSql.connect(database: database)
+
+      {:ok, _query, _result, state} =
+        EctoLibSql.handle_execute(
+          """
+          CREATE TABLE big AS
+          WITH RECURSIVE n(x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM n WHERE x < 5000)
+          SELECT x AS id, randomblob(1024) AS data FROM n
+          """,
+          [],
+          [],
+          state
+        )
+
+      {:ok, _query, result, state} =
+        EctoLibSql.handle_execute("SELECT sum(length(data)) FROM big", [], [], state)
+
+      assert result.rows == [[5000 * 1024]]
+
+      assert :ok = EctoLibSql.Native.release_memory(state)
+
+      {:ok, _query, result, _state} =
+        EctoLibSql.handle_execute("SELECT count(*) FROM big", [], [], state)
+
+      assert result.rows == [[5000]]
+
+      EctoLibSql.disconnect([], state)
+    end
+
+    test "reset can shrink memory too", %{database: database} do
+      {:ok, state} = EctoLibSql.connect(database: database)
+
+      {:ok, _query, _result, state} =
+        EctoLibSql.handle_execute("SELECT randomblob(100000)", [], [], state)
+
+      assert :ok = EctoLibSql.Native.reset(state, shrink_memory: true)
+
+      {:ok, _query, result, _state} = EctoLibSql.handle_execute("SELECT 1", [], [], state)
+      assert result.rows == [[1]]
+
+      EctoLibSql.disconnect([], state)
+    end
+  end
+
   # ============================================================================
   # Connection interrupt - IMPLEMENTED ✅
   # ============================================================================
@@ -446,6 +535,148 @@ defmodule EctoLibSql.ConnectionFeaturesTest do
   # Integration tests
   # ============================================================================
 
+  describe "diagnostics option" do
+    test "records connection phase timings when enabled", %{database: database} do
+      {:ok, state} = EctoLibSql.connect(database: database, diagnostics: true)
+
+      assert %{build_us: build_us, connect_us: connect_us, ping_us: nil} =
+               state.connect_timings
+
+      assert is_integer(build_us) and build_us >= 0
+      assert is_integer(connect_us) and connect_us >= 0
+
+      EctoLibSql.disconnect([], state)
+    end
+
+    test "leaves timings unset by default", %{database: database} do
+      {:ok, state} = EctoLibSql.connect(database: database)
+
+      assert state.connect_timings == nil
+
+      EctoLibSql.disconnect([], state)
+    end
+
+    test "native connect returns a plain connection id without the flag", %{database: database} do
+      conn_id = EctoLibSql.Native.connect([database: database], :local)
+      assert is_binary(conn_id)
+
+      EctoLibSql.Native.close(conn_id, :conn_id)
+    end
+  end
+
+  describe "vfs option" do
+    test "opens a local database with a built-in VFS", %{database: database} do
+      {:ok, state} = EctoLibSql.connect(database: database, vfs: "unix")
+
+      {:ok, _query, result, _state} = EctoLibSql.handle_execute("SELECT 1", [], [], state)
+      assert result.rows == [[1]]
+
+      EctoLibSql.disconnect([], state)
+      assert File.exists?(database)
+    end
+
+    test "unregistered VFS returns a descriptive error", %{database: database} do
+      assert {:error, message} = EctoLibSql.connect(database: database, vfs: "no-such-vfs")
+      assert message =~ "VFS"
+      assert message =~ "no-such-vfs"
+    end
+  end
+
+  describe "shared_memory option" do
+    test "connections with the same name share one in-memory database" do
+      name = "shared_memory_#{:erlang.unique_integer([:positive])}"
+      {:ok, state_a} = EctoLibSql.connect(shared_memory: name)
+      {:ok, state_b} = EctoLibSql.connect(shared_memory: name)
+      assert state_a.mode == :local
+
+      {:ok, _, _, state_a} =
+        EctoLibSql.handle_execute("CREATE TABLE items (id INTEGER PRIMARY KEY)", [], [], state_a)
+
+      {:ok, _, _, state_a} =
+        EctoLibSql.handle_execute("INSERT INTO items VALUES (1), (2)", [], [], state_a)
+
+      {:ok, _query, result, _state} =
+        EctoLibSql.handle_execute("SELECT count(*) FROM items", [], [], state_b)
+
+      assert result.rows == [[2]]
+
+      # A different name is a separate database
+      {:ok, other} = EctoLibSql.connect(shared_memory: name <> "_other")
+
+      assert {:error, _, _} =
+               EctoLibSql.handle_execute("SELECT * FROM items", [], [], other)
+
+      Enum.each([state_a, state_b, other], &EctoLibSql.disconnect([], &1))
+      refute File.exists?(name)
+    end
+
+    test "cannot be combined with a database path", %{database: database} do
+      assert {:error, message} = EctoLibSql.connect(database: database, shared_memory: "x")
+      assert message =~ "shared_memory"
+    end
+  end
+
+  describe "count_connections_for_path" do
+    test "counts every connection opened against the same file", %{database: database} do
+      states =
+        for _ <- 1..3 do
+          {:ok, state} = EctoLibSql.connect(database: database)
+          state
+        end
+
+      conn_ids = states |> Enum.map(& &1.conn_id) |> Enum.sort()
+
+      assert {:ok, %{count: 3, conn_ids: ^conn_ids}} =
+               EctoLibSql.Native.count_connections_for_path(database)
+
+      assert {:ok, %{count: 3}} =
+               EctoLibSql.Native.count_connections_for_path("./" <> database)
+
+      EctoLibSql.disconnect([], hd(states))
+
+      assert {:ok, %{count: 2}} = EctoLibSql.Native.count_connections_for_path(database)
+
+      Enum.each(tl(states), &EctoLibSql.disconnect([], &1))
+    end
+
+    test "returns zero for a path with no connections" do
+      assert {:ok, %{count: 0, conn_ids: []}} =
+               EctoLibSql.Native.count_connections_for_path("no-such-database.db")
+    end
+  end
+
+  describe "statement count" do
+    test "counts each statement run on the connection", %{database: database} do
+      {:ok, state} = EctoLibSql.connect(database: database)
+      {:ok, other} = EctoLibSql.connect(database: database)
+      before = EctoLibSql.Native.get_statement_count(state)
+      other_before = EctoLibSql.Native.get_statement_count(other)
+
+      for sql <- [
+            "CREATE TABLE counted (id INTEGER PRIMARY KEY, n INTEGER)",
+            "INSERT INTO counted (n) VALUES (1)",
+            "SELECT * FROM counted"
+          ] do
+        {:ok, _, _, _} = EctoLibSql.handle_execute(sql, [], [], state)
+      end
+
+      # A failed statement still counts
+      {:error, _, _} = EctoLibSql.handle_execute("SELECT * FROM missing", [], [], state)
+
+      {:ok, _} =
+        EctoLibSql.Native.batch(state, [
+          {"INSERT INTO counted (n) VALUES (?)", [2]},
+          {"INSERT INTO counted (n) VALUES (?)", [3]}
+        ])
+
+      assert EctoLibSql.Native.get_statement_count(state) == before + 6
+      assert EctoLibSql.Native.get_statement_count(other) == other_before
+
+      EctoLibSql.disconnect([], state)
+      EctoLibSql.disconnect([], other)
+    end
+  end
+
   describe "integration with Ecto connection options" do
     test "busy_timeout in config works", %{database: database} do
       # Simulate Ecto-style config
@@ -464,4 +695,11 @@ defmodule EctoLibSql.ConnectionFeaturesTest do
       EctoLibSql.disconnect([], state)
     end
   end
+
+  defp busy_timeout(state) do
+    {:ok, _query, %{rows: [[timeout_ms]]}, _state} =
+      EctoLibSql.handle_execute("PRAGMA busy_timeout", [], [], state)
+
+    timeout_ms
+  end
 end
@@ -0,0 +1,82 @@
+defmodule EctoLibSql.CursorMemoryTest do
+  use ExUnit.Case
+
+  alias EctoLibSql.Native
+
+  setup do
+    db_file = "z_ecto_libsql_test-cursor_memory_#{:erlang.unique_integer([:positive])}.db"
+    {:ok, state} = EctoLibSql.connect(database: db_file)
+
+    {:ok, _, _, state} =
+      EctoLibSql.handle_execute(
+        "CREATE TABLE items (id INTEGER PRIMARY KEY, label TEXT)",
+        [],
+        [],
+        state
+      )
+
+    {:ok, _, _, state} =
+      EctoLibSql.handle_execute(
+        """
+        WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 200)
+        INSERT INTO items (id, label) SELECT i, 'item-' || i FROM n
+        """,
+        [],
+        [],
+        state
+      )
+
+    on_exit(fn ->
+      EctoLibSql.disconnect([], state)
+      EctoLibSql.TestHelpers.cleanup_db_files(db_file)
+    end)
+
+    {:ok, state: state}
+  end
+
+  defp declare(state, sql, opts \\ []) do
+    query = %EctoLibSql.Query{statement: sql}
+    {:ok, ^query, cursor, state} = EctoLibSql.handle_declare(query, [], opts, state)
+    {query, cursor, state}
+  end
+
+  test "expected_rows hint still streams every row", %{state: state} do
+    {query, cursor, state} =
+      declare(state, "SELECT * FROM items ORDER BY id", expected_rows: 200)
+
+    {:cont, result, state} = EctoLibSql.handle_fetch(query, cursor, [max_rows: 150], state)
+    assert result.num_rows == 150
+    {:cont, result, state} = EctoLibSql.handle_fetch(query, cursor, [max_rows: 150], state)
+    assert result.num_rows == 50
+    {:halt, _result, _state} = EctoLibSql.handle_fetch(query, cursor, [max_rows: 150], state)
+  end
+
+  test "reports a non-zero estimate that grows with the result", %{state: state} do
+    {_query, small, state} = declare(state, "SELECT * FROM items LIMIT 5")
+    {_query, large, state} = declare(state, "SELECT * FROM items")
+
+    assert {:ok, small_bytes} = Native.get_cursor_memory(state, small.ref)
+    assert {:ok, large_bytes} = Native.get_cursor_memory(state, large.ref)
+    assert small_bytes > 0
+    assert large_bytes > small_bytes
+  end
+
+  test "rejects a cursor owned by another connection", %{state: state} do
+    {_query, cursor, state} = declare(state, "SELECT * FROM items")
+
+    other_file = "z_ecto_libsql_test-cursor_memory_other_#{:erlang.unique_integer([:positive])}.db"
+    {:ok, other} = EctoLibSql.connect(database: other_file)
+
+    on_exit(fn ->
+      EctoLibSql.disconnect([], other)
+      EctoLibSql.TestHelpers.cleanup_db_files(other_file)
+    end)
+
+    assert {:ok, _} = Native.get_cursor_memory(state, cursor.ref)
+    assert {:error, _} = Native.get_cursor_memory(other, cursor.ref)
+  end
+
+  test "rejects an unknown cursor", %{state: state} do
+    assert {:error, "Cursor not found"} = Native.get_cursor_memory(state, "missing")
+  end
+end
@@ -0,0 +1,77 @@
+defmodule EctoLibSql.DeadlineTest do
+  @moduledoc """
+  Tests for deadline tokens shared across queries.
+  """
+  use ExUnit.Case
+
+  alias EctoLibSql.Native
+
+  @slow_query """
+  WITH RECURSIVE n(x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM n WHERE x < 10000000000)
+  SELECT count(*) FROM n
+  """
+
+  setup do
+    test_db = "z_ecto_libsql_test-deadline_#{:erlang.unique_integer([:positive])}.db"
+
+    {:ok, state} = EctoLibSql.connect(database: test_db)
+
+    on_exit(fn ->
+      EctoLibSql.disconnect([], state)
+      EctoLibSql.TestHelpers.cleanup_db_files(test_db)
+    end)
+
+    {:ok, state: state}
+  end
+
+  test "queries within the budget return results", %{state: state} do
+    {:ok, token} = Native.new_deadline(1_000)
+
+    assert {:ok, %EctoLibSql.Result{rows: [[1]], num_rows: 1}} =
+             Native.query_with_deadline(state, "SELECT ?", [1], token)
+
+    assert :ok = Native.release_deadline(token)
+  end
+
+  test "a later query is interrupted once the shared budget is spent", %{state: state} do
+    {:ok, token} = Native.new_deadline(500)
+
+    assert {:ok, _} = Native.query_with_deadline(state, "SELECT 1", [], token)
+
+    # Spend most of the remaining budget outside the database.
+    Process.sleep(400)
+
+    {elapsed_us, result} =
+      :timer.tc(fn -> Native.query_with_deadline(state, @slow_query, [], token) end)
+
+    assert result == {:error, :deadline_exceeded}
+    assert elapsed_us < 2_000_000
+
+    Native.release_deadline(token)
+
+    # The connection is still usable.
+    assert {:ok, _, %EctoLibSql.Result{rows: [[2]]}, _} =
+             EctoLibSql.handle_execute("SELECT 2", [], [], state)
+  end
+
+  test "a query started after the deadline is not run", %{state: state} do
+    {:ok, token} = Native.new_deadline(0)
+    Process.sleep(5)
+
+    assert {:error, :deadline_exceeded} =
+             Native.query_with_deadline(state, "CREATE TABLE never (id INTEGER)", [], token)
+
+    assert {:ok, _, %EctoLibSql.Result{rows: [[0]]}, _} =
+             EctoLibSql.handle_execute(
+               "SELECT count(*) FROM sqlite_master WHERE name = 'never'",
+               [],
+               [],
+               state
+             )
+  end
+
+  test "unknown tokens are an error", %{state: state} do
+    assert {:error, "Unknown deadline token"} =
+             Native.query_with_deadline(state, "SELECT 1", [], "no-such-token")
+  end
+end
@@ -0,0 +1,78 @@
+defmodule EctoLibSql.DecodeBlobsTest do
+  use ExUnit.Case
+
+  setup do
+    db_file = "z_ecto_libsql_test-decode_blobs_#{:erlang.unique_integer([:positive])}.db"
+    {:ok, state} = EctoLibSql.connect(database: db_file)
+
+    {:ok, _, _, state} =
+      EctoLibSql.handle_execute(
+        "CREATE TABLE legacy (id INTEGER PRIMARY KEY, name BLOB)",
+        [],
+        [],
+        state
+      )
+
+    on_exit(fn ->
+      EctoLibSql.disconnect([], state)
+      EctoLibSql.TestHelpers.cleanup_db_files(db_file)
+    end)
+
+    {:ok, state: state}
+  end
+
+  defp insert(state, name) do
+    {:ok, _, _, _} =
+      EctoLibSql.handle_execute("INSERT INTO legacy (name) VALUES (?)", [name], [], state)
+  end
+
+  test "round-trips a latin1-encoded blob into a UTF-8 string", %{state: state} do
+    latin1 = :unicode.characters_to_binary("café £5", :utf8, :latin1)
+    insert(state, {:blob, latin1})
+
+    assert {:ok, _, %EctoLibSql.Result{rows: [["café £5"]]}, _} =
+             EctoLibSql.handle_execute(
+               "SELECT name FROM legacy",
+               [],
+               [decode_blobs: [name: :latin1]],
+               state
+             )
+  end
+
+  test "decodes UTF-16LE blobs", %{state: state} do
+    utf16 = :unicode.characters_to_binary("héllo", :utf8, {:utf16, :little})
+    insert(state, {:blob, utf16})
+
+    assert {:ok, _, %EctoLibSql.Result{rows: [["héllo"]]}, _} =
+             EctoLibSql.handle_execute(
+               "SELECT name FROM legacy",
+               [],
+               [decode_blobs: %{"name" => :utf16le}],
+               state
+             )
+  end
+
+  test "leaves other columns and non-blob values alone", %{state: state} do
+    insert(state, nil)
+
+    assert {:ok, _, %EctoLibSql.Result{rows: [[1, nil]]}, _} =
+             EctoLibSql.handle_execute(
+               "SELECT id, name FROM legacy",
+               [],
+               [decode_blobs: [name: :latin1]],
+               state
+             )
+  end
+
+  test "errors on invalid byte sequences", %{state: state} do
+    insert(state, {:blob, <<0x61, 0x00, 0x62>>})
+
+    assert {:error, _, _} =
+             EctoLibSql.handle_execute(
+               "SELECT name FROM legacy",
+               [],
+               [decode_blobs: [name: :utf16le]],
+               state
+             )
+  end
+end
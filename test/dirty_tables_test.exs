@@ -0,0 +1,69 @@
+defmodule EctoLibSql.DirtyTablesTest do
+  use ExUnit.Case
+
+  alias EctoLibSql.Native
+
+  setup do
+    db_file = "z_ecto_libsql_test-dirty_tables_#{:erlang.unique_integer([:positive])}.db"
+    {:ok, state} = EctoLibSql.connect(database: db_file)
+
+    state =
+      Enum.reduce(
+        [
+          "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT)",
+          "CREATE TABLE posts (id INTEGER PRIMARY KEY, title TEXT)",
+          "CREATE TABLE tags (id INTEGER PRIMARY KEY)"
+        ],
+        state,
+        fn sql, state ->
+          {:ok, _, _, state} = EctoLibSql.handle_execute(sql, [], [], state)
+          state
+        end
+      )
+
+    on_exit(fn ->
+      EctoLibSql.disconnect([], state)
+      EctoLibSql.TestHelpers.cleanup_db_files(db_file)
+    end)
+
+    {:ok, state: state}
+  end
+
+  defp exec(state, sql) do
+    {:ok, _, _, state} = EctoLibSql.handle_execute(sql, [], [], state)
+    state
+  end
+
+  test "reports both written tables, then clears", %{state: state} do
+    :ok = Native.track_dirty_tables(state)
+
+    state = exec(state, "INSERT INTO users (name) VALUES ('a')")
+    state = exec(state, "INSERT INTO posts (title) VALUES ('x')")
+    state = exec(state, "SELECT * FROM tags")
+
+    assert {:ok, ["posts", "users"]} = Native.dirty_tables(state)
+    assert {:ok, []} = Native.dirty_tables(state)
+
+    state = exec(state, "UPDATE users SET name = 'b' WHERE id = 1")
+    assert {:ok, ["users"]} = Native.dirty_tables(state)
+  end
+
+  test "writes before tracking starts are not reported", %{state: state} do
+    state = exec(state, "INSERT INTO users (name) VALUES ('a')")
+    :ok = Native.track_dirty_tables(state)
+
+    assert {:ok, []} = Native.dirty_tables(state)
+  end
+
+  test "enabling twice keeps the accumulated set", %{state: state} do
+    :ok = Native.track_dirty_tables(state)
+    state = exec(state, "INSERT INTO tags DEFAULT VALUES")
+    :ok = Native.track_dirty_tables(state)
+
+    assert {:ok, ["tags"]} = Native.dirty_tables(state)
+  end
+
+  test "returns an error when tracking is not enabled", %{state: state} do
+    assert {:error, _} = Native.dirty_tables(state)
+  end
+end
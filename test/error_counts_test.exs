@@ -0,0 +1,88 @@
+defmodule EctoLibSql.ErrorCountsTest do
+  use ExUnit.Case
+
+  alias EctoLibSql.Native
+
+  setup do
+    db_file = "z_ecto_libsql_test-error_counts_#{:erlang.unique_integer([:positive])}.db"
+    {:ok, holder} = EctoLibSql.connect(database: db_file)
+    {:ok, state} = EctoLibSql.connect(database: db_file, busy_timeout: 0)
+
+    {:ok, _, _, holder} =
+      EctoLibSql.handle_execute("CREATE TABLE users (email TEXT UNIQUE)", [], [], holder)
+
+    {:ok, _, _, holder} =
+      EctoLibSql.handle_execute("INSERT INTO users VALUES ('a@example.com')", [], [], holder)
+
+    on_exit(fn ->
+      EctoLibSql.disconnect([], holder)
+      EctoLibSql.disconnect([], state)
+      EctoLibSql.TestHelpers.cleanup_db_files(db_file)
+    end)
+
+    {:ok, holder: holder, state: state}
+  end
+
+  defp insert_duplicate(state) do
+    assert {:error, _, state} =
+             EctoLibSql.handle_execute(
+               "INSERT INTO users VALUES ('a@example.com')",
+               [],
+               [],
+               state
+             )
+
+    state
+  end
+
+  describe "get_error_counts/1" do
+    test "starts at zero", %{state: state} do
+      assert {:ok, %{constraint: 0, busy: 0, syntax: 0, other: 0}} =
+               Native.get_error_counts(state)
+    end
+
+    test "counts constraint and busy errors", %{holder: holder, state: state} do
+      state = state |> insert_duplicate() |> insert_duplicate()
+
+      assert %{} =
+               Native.query_args(
+                 holder.conn_id,
+                 :local,
+                 :disable_sync,
+                 "BEGIN IMMEDIATE",
+                 [],
+                 nil
+               )
+
+      for _ <- 1..2 do
+        assert {:error, _, _state} =
+                 EctoLibSql.handle_execute("INSERT INTO users VALUES ('b')", [], [], state)
+      end
+
+      Native.query_args(holder.conn_id, :local, :disable_sync, "ROLLBACK", [], nil)
+
+      assert {:ok, %{constraint: 2, busy: 2, syntax: 0, other: 0}} =
+               Native.get_error_counts(state)
+
+      assert {:ok, %{constraint: 0, busy: 0}} = Native.get_error_counts(holder)
+    end
+
+    test "returns an error for an invalid connection" do
+      assert {:error, _} = Native.error_counts("invalid-connection")
+    end
+  end
+
+  describe "clear_error_counts/1" do
+    test "returns the cleared counts and starts again", %{state: state} do
+      state = insert_duplicate(state)
+
+      assert {:ok, %{constraint: 1}} = Native.clear_error_counts(state)
+
+      assert {:ok, %{constraint: 0, busy: 0, syntax: 0, other: 0}} =
+               Native.get_error_counts(state)
+
+      insert_duplicate(state)
+      assert {:ok, %{constraint: 1}} = Native.get_error_counts(state)
+    end
+  end
+end
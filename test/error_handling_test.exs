@@ -15,7 +15,8 @@ defmodule EctoLibSql.ErrorHandlingTest do
       # After: Returns a proper error tuple to Elixir
 
       fake_conn_id = "00000000-0000-0000-0000-000000000000"
-      result = EctoLibSql.Native.query_args(fake_conn_id, :local, :disable_sync, "SELECT 1", [])
+      result =
+        EctoLibSql.Native.query_args(fake_conn_id, :local, :disable_sync, "SELECT 1", [], nil)
 
       assert {:error, error_msg} = result
       assert error_msg =~ "Invalid connection" or error_msg =~ "Connection"
@@ -93,7 +94,8 @@ defmodule EctoLibSql.ErrorHandlingTest do
           fake_trx_id,
           fake_conn_id,
           "INSERT INTO test VALUES (1)",
-          []
+          [],
+          false
         )
 
       assert {:error, error_msg} = result
@@ -115,7 +117,8 @@ defmodule EctoLibSql.ErrorHandlingTest do
           fake_stmt_id,
           :local,
           :disable_sync,
-          []
+          [],
+          false
         )
 
       assert {:error, error_msg} = result
@@ -133,7 +136,8 @@ defmodule EctoLibSql.ErrorHandlingTest do
           :local,
           :disable_sync,
           "INSERT INTO test VALUES (1)",
-          []
+          [],
+          false
         )
 
       assert {:error, error_msg} = result
@@ -206,7 +210,8 @@ defmodule EctoLibSql.ErrorHandlingTest do
                   "invalid-trx-#{i}",
                   real_conn_id,
                   "SELECT 1",
-                  []
+                  [],
+                  false
                 )
             end
           end)
@@ -235,7 +240,8 @@ defmodule EctoLibSql.ErrorHandlingTest do
           :local,
           :disable_sync,
           "SELECT 1",
-          []
+          [],
+          nil
         )
 
       assert {:error, error_msg} = result
@@ -280,4 +286,139 @@ defmodule EctoLibSql.ErrorHandlingTest do
       send(pid, :terminate)
     end
   end
+
+  describe "last error inspection" do
+    setup do
+      test_db = "z_ecto_libsql_test-last_error_#{:erlang.unique_integer([:positive])}.db"
+      {:ok, state} = EctoLibSql.connect(database: test_db)
+
+      on_exit(fn ->
+        EctoLibSql.disconnect([], state)
+        EctoLibSql.TestHelpers.cleanup_db_files(test_db)
+      end)
+
+      {:ok, state: state}
+    end
+
+    test "returns nil before any error has occurred", %{state: state} do
+      assert EctoLibSql.Native.get_last_error(state) == nil
+    end
+
+    test "reflects a constraint violation", %{state: state} do
+      {:ok, _, _, state} =
+        EctoLibSql.handle_execute(
+          "CREATE TABLE users (id INTEGER PRIMARY KEY, email TEXT UNIQUE)",
+          [],
+          [],
+          state
+        )
+
+      {:ok, _, _, state} =
+        EctoLibSql.handle_execute(
+          "INSERT INTO users (email) VALUES ('a@example.com')",
+          [],
+          [],
+          state
+        )
+
+      assert {:error, _, state} =
+               EctoLibSql.handle_execute(
+                 "INSERT INTO users (email) VALUES ('a@example.com')",
+                 [],
+                 [],
+                 state
+               )
+
+      assert %{code: 19, extended_code: 2067, message: message} =
+               EctoLibSql.Native.get_last_error(state)
+
+      assert message =~ "UNIQUE constraint failed"
+    end
+
+    test "returns an error for an invalid connection" do
+      assert {:error, _} = EctoLibSql.Native.last_error("invalid-connection")
+    end
+  end
+
+  describe "busy error classification" do
+    setup do
+      test_db = "z_ecto_libsql_test-busy_#{:erlang.unique_integer([:positive])}.db"
+      {:ok, holder} = EctoLibSql.connect(database: test_db)
+      {:ok, waiter} = EctoLibSql.connect(database: test_db, busy_timeout: 0)
+
+      on_exit(fn ->
+        EctoLibSql.disconnect([], holder)
+        EctoLibSql.disconnect([], waiter)
+        EctoLibSql.TestHelpers.cleanup_db_files(test_db)
+      end)
+
+      {:ok, holder: holder, waiter: waiter}
+    end
+
+    test "classifies a held write lock", %{holder: holder, waiter: waiter} do
+      {:ok, _, _, holder} =
+        EctoLibSql.handle_execute("CREATE TABLE t (id INTEGER PRIMARY KEY)", [], [], holder)
+
+      assert %{} =
+               EctoLibSql.Native.query_args(
+                 holder.conn_id,
+                 :local,
+                 :disable_sync,
+                 "BEGIN IMMEDIATE",
+                 [],
+                 nil
+               )
+
+      assert {:error, {:busy, :write_lock, message}} =
+               EctoLibSql.Native.query_args(
+                 waiter.conn_id,
+                 :local,
+                 :disable_sync,
+                 "INSERT INTO t VALUES (1)",
+                 [],
+                 nil
+               )
+
+      assert message =~ "locked"
+
+      assert {:error, error, _state} =
+               EctoLibSql.handle_execute("INSERT INTO t VALUES (1)", [], [], waiter)
+
+      assert EctoLibSql.Error.busy(error) == {:busy, :write_lock}
+
+      EctoLibSql.Native.query_args(holder.conn_id, :local, :disable_sync, "ROLLBACK", [], nil)
+    end
+
+    test "classifies a held write lock inside a transaction", %{holder: holder, waiter: waiter} do
+      {:ok, _, _, holder} =
+        EctoLibSql.handle_execute("CREATE TABLE t (id INTEGER PRIMARY KEY)", [], [], holder)
+
+      assert %{} =
+               EctoLibSql.Native.query_args(
+                 holder.conn_id,
+                 :local,
+                 :disable_sync,
+                 "BEGIN IMMEDIATE",
+                 [],
+                 nil
+               )
+
+      {:ok, _, waiter} = EctoLibSql.handle_begin([], waiter)
+
+      assert {:error, error, waiter} =
+               EctoLibSql.handle_execute("INSERT INTO t VALUES (1) RETURNING id", [], [], waiter)
+
+      assert EctoLibSql.Error.busy(error) == {:busy, :write_lock}
+
+      EctoLibSql.handle_rollback([], waiter)
+      EctoLibSql.Native.query_args(holder.conn_id, :local, :disable_sync, "ROLLBACK", [], nil)
+    end
+
+    test "other errors are not classified as busy", %{waiter: waiter} do
+      assert {:error, error, _state} =
+               EctoLibSql.handle_execute("SELECT * FROM missing", [], [], waiter)
+
+      assert EctoLibSql.Error.busy(error) == nil
+    end
+  end
 end
@@ -0,0 +1,123 @@
+defmodule EctoLibSql.ExportTest do
+  @moduledoc """
+  Tests for exporting table contents, such as streaming a table as SQL `INSERT` statements.
+  """
+  use ExUnit.Case
+
+  alias EctoLibSql.Native
+
+  @create_items "CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT, price REAL, data BLOB)"
+
+  setup do
+    test_db = "z_ecto_libsql_test-export_#{:erlang.unique_integer([:positive])}.db"
+
+    {:ok, state} = EctoLibSql.connect(database: test_db)
+
+    on_exit(fn ->
+      EctoLibSql.disconnect([], state)
+      EctoLibSql.TestHelpers.cleanup_db_files(test_db)
+    end)
+
+    {:ok, state: state}
+  end
+
+  defp collect_dump(acc \\ []) do
+    receive do
+      {:dump_chunk, statements} -> collect_dump([statements | acc])
+      :done -> {:ok, Enum.reverse(acc)}
+      {:dump_error, reason} -> {:error, reason}
+    after
+      5_000 -> flunk("timed out waiting for table dump")
+    end
+  end
+
+  describe "stream_table_as_sql/4" do
+    test "streamed statements replay into a fresh database", %{state: state} do
+      {:ok, _, _, state} = EctoLibSql.handle_execute(@create_items, [], [], state)
+
+      rows = [
+        [1, "O'Brien", 1.0, <<0, 1, 255>>],
+        [2, nil, -2.5, nil],
+        [3, "multi\nline", nil, <<255>>]
+      ]
+
+      state =
+        Enum.reduce(rows, state, fn params, state ->
+          {:ok, _, _, state} =
+            EctoLibSql.handle_execute("INSERT INTO items VALUES (?, ?, ?, ?)", params, [], state)
+
+          state
+        end)
+
+      assert :ok = Native.stream_table_as_sql(state, :items, self(), 2)
+      assert {:ok, chunks} = collect_dump()
+      assert Enum.map(chunks, &length/1) == [2, 1]
+
+      replica_db = "z_ecto_libsql_test-export_replica_#{:erlang.unique_integer([:positive])}.db"
+      {:ok, replica} = EctoLibSql.connect(database: replica_db)
+
+      on_exit(fn ->
+        EctoLibSql.disconnect([], replica)
+        EctoLibSql.TestHelpers.cleanup_db_files(replica_db)
+      end)
+
+      {:ok, _, _, replica} = EctoLibSql.handle_execute(@create_items, [], [], replica)
+
+      replica =
+        chunks
+        |> List.flatten()
+        |> Enum.reduce(replica, fn sql, replica ->
+          {:ok, _, _, replica} = EctoLibSql.handle_execute(sql, [], [], replica)
+          replica
+        end)
+
+      select = "SELECT id, name, price, typeof(price), data FROM items ORDER BY id"
+      {:ok, _, expected, _} = EctoLibSql.handle_execute(select, [], [], state)
+      {:ok, _, actual, _} = EctoLibSql.handle_execute(select, [], [], replica)
+
+      assert actual.rows == expected.rows
+      assert [[1, "O'Brien", 1.0, "real", <<0, 1, 255>>] | _] = actual.rows
+    end
+
+    test "empty table sends only :done", %{state: state} do
+      {:ok, _, _, state} = EctoLibSql.handle_execute(@create_items, [], [], state)
+
+      assert :ok = Native.stream_table_as_sql(state, "items", self())
+      assert {:ok, []} = collect_dump()
+    end
+
+    test "returns an error for a missing table", %{state: state} do
+      assert {:error, message} = Native.stream_table_as_sql(state, "missing", self())
+      assert message =~ "Table not found"
+    end
+
+    test "rejects a non-positive chunk size", %{state: state} do
+      {:ok, _, _, state} = EctoLibSql.handle_execute(@create_items, [], [], state)
+
+      assert {:error, _} = Native.stream_table_as_sql(state, "items", self(), 0)
+    end
+
+    test "stops streaming when the receiver exits", %{state: state} do
+      {:ok, _, _, state} = EctoLibSql.handle_execute(@create_items, [], [], state)
+
+      {:ok, _, _, state} =
+        EctoLibSql.handle_execute(
+          "WITH RECURSIVE n(x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM n WHERE x < 1000) " <>
+            "INSERT INTO items (id, name) SELECT x, 'row' FROM n",
+          [],
+          [],
+          state
+        )
+
+      receiver = spawn(fn -> :ok end)
+      ref = Process.monitor(receiver)
+      assert_receive {:DOWN, ^ref, :process, _, _}
+
+      assert :ok = Native.stream_table_as_sql(state, "items", receiver, 10)
+
+      # The connection becomes usable again once the dump has given up.
+      assert {:ok, _, %{rows: [[1000]]}, _} =
+               EctoLibSql.handle_execute("SELECT COUNT(*) FROM items", [], [], state)
+    end
+  end
+end
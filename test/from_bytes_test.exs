@@ -0,0 +1,91 @@
+defmodule EctoLibSql.FromBytesTest do
+  use ExUnit.Case
+
+  alias EctoLibSql.Native
+
+  setup do
+    db_file = "z_ecto_libsql_test-from_bytes_#{:erlang.unique_integer([:positive])}.db"
+    {:ok, state} = EctoLibSql.connect(database: db_file)
+
+    {:ok, _, _, state} =
+      EctoLibSql.handle_execute(
+        "CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT)",
+        [],
+        [],
+        state
+      )
+
+    {:ok, _, _, state} =
+      EctoLibSql.handle_execute("INSERT INTO items (name) VALUES ('a'), ('b')", [], [], state)
+
+    on_exit(fn ->
+      EctoLibSql.disconnect([], state)
+      EctoLibSql.TestHelpers.cleanup_db_files(db_file)
+    end)
+
+    {:ok, state: state}
+  end
+
+  defp names(state) do
+    {:ok, _, result, _} =
+      EctoLibSql.handle_execute("SELECT name FROM items ORDER BY id", [], [], state)
+
+    List.flatten(result.rows)
+  end
+
+  test "round-trips a database through bytes", %{state: state} do
+    {:ok, bytes} = Native.serialize(state)
+    assert <<"SQLite format 3", 0, _::binary>> = bytes
+
+    {:ok, copy} = Native.open_from_bytes(bytes)
+    on_exit(fn -> EctoLibSql.disconnect([], copy) end)
+
+    assert names(copy) == ["a", "b"]
+
+    {:ok, _, _, copy} =
+      EctoLibSql.handle_execute("INSERT INTO items (name) VALUES ('c')", [], [], copy)
+
+    # Writes stay in the in-memory copy
+    assert names(copy) == ["a", "b", "c"]
+    assert names(state) == ["a", "b"]
+
+    {:ok, again} = Native.serialize(copy)
+    {:ok, reopened} = Native.open_from_bytes(again)
+    on_exit(fn -> EctoLibSql.disconnect([], reopened) end)
+
+    assert names(reopened) == ["a", "b", "c"]
+  end
+
+  test "snapshots an in-memory database" do
+    {:ok, memory} = EctoLibSql.connect(database: ":memory:")
+    on_exit(fn -> EctoLibSql.disconnect([], memory) end)
+
+    {:ok, _, _, memory} =
+      EctoLibSql.handle_execute(
+        "CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT)",
+        [],
+        [],
+        memory
+      )
+
+    {:ok, _, _, memory} =
+      EctoLibSql.handle_execute("INSERT INTO items (name) VALUES ('x')", [], [], memory)
+
+    {:ok, bytes} = Native.serialize(memory)
+    {:ok, snapshot} = Native.open_from_bytes(bytes)
+    on_exit(fn -> EctoLibSql.disconnect([], snapshot) end)
+
+    assert names(snapshot) == ["x"]
+  end
+
+  test "refuses to serialise inside a transaction", %{state: state} do
+    {:ok, trx_state} = Native.begin(state)
+    assert {:error, message} = Native.serialize(trx_state)
+    assert message =~ "transaction"
+    {:ok, _} = Native.rollback(trx_state)
+  end
+
+  test "rejects bytes that are not a database" do
+    assert {:error, _} = Native.open_from_bytes("not a database")
+  end
+end
@@ -0,0 +1,68 @@
+defmodule EctoLibSql.GetByIdsTest do
+  @moduledoc """
+  Tests for fetching multiple rows by id into a map keyed by id.
+  """
+  use ExUnit.Case
+
+  alias EctoLibSql.Native
+
+  setup do
+    test_db = "z_ecto_libsql_test-get_by_ids_#{:erlang.unique_integer([:positive])}.db"
+
+    {:ok, state} = EctoLibSql.connect(database: test_db)
+
+    {:ok, _, _, state} =
+      EctoLibSql.handle_execute(
+        "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT, email TEXT)",
+        [],
+        [],
+        state
+      )
+
+    for id <- 1..5 do
+      {:ok, _, _, _} =
+        EctoLibSql.handle_execute(
+          "INSERT INTO users (id, name, email) VALUES (?, ?, ?)",
+          [id, "user #{id}", "user#{id}@example.com"],
+          [],
+          state
+        )
+    end
+
+    on_exit(fn ->
+      EctoLibSql.disconnect([], state)
+      EctoLibSql.TestHelpers.cleanup_db_files(test_db)
+    end)
+
+    {:ok, state: state}
+  end
+
+  test "returns the requested rows keyed by id", %{state: state} do
+    assert {:ok, users} = Native.get_by_ids(state, "users", "id", [1, 3, 5])
+
+    assert Map.keys(users) |> Enum.sort() == [1, 3, 5]
+
+    assert users[3] == %{"id" => 3, "name" => "user 3", "email" => "user3@example.com"}
+  end
+
+  test "omits ids with no matching row", %{state: state} do
+    assert {:ok, users} = Native.get_by_ids(state, :users, :id, [2, 99])
+    assert Map.keys(users) == [2]
+  end
+
+  test "matches on a non-primary-key column", %{state: state} do
+    assert {:ok, users} =
+             Native.get_by_ids(state, "users", "email", ["user4@example.com"])
+
+    assert %{"user4@example.com" => %{"id" => 4}} = users
+  end
+
+  test "returns an empty map for no ids", %{state: state} do
+    assert {:ok, %{}} = Native.get_by_ids(state, "users", "id", [])
+  end
+
+  test "returns an error for an unknown column", %{state: state} do
+    assert {:error, reason} = Native.get_by_ids(state, "users", "missing", [1])
+    assert reason =~ "missing"
+  end
+end
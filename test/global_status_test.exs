@@ -0,0 +1,31 @@
+defmodule EctoLibSql.GlobalStatusTest do
+  use ExUnit.Case, async: true
+
+  alias EctoLibSql.Native
+
+  describe "global_status/0 - NOT SUPPORTED" do
+    test "returns :unsupported error" do
+      assert {:error, :unsupported} = Native.global_status()
+    end
+
+    test "dbstat is available as an alternative" do
+      {:ok, state} = EctoLibSql.connect(database: ":memory:")
+      on_exit(fn -> EctoLibSql.disconnect([], state) end)
+
+      {:ok, _, _, state} =
+        EctoLibSql.handle_execute("CREATE TABLE items (name TEXT)", [], [], state)
+
+      {:ok, _, result, _state} =
+        EctoLibSql.handle_execute(
+          "SELECT count(*) FROM dbstat WHERE name = 'items'",
+          [],
+          [],
+          state
+        )
+
+      assert [[pages]] = result.rows
+      assert pages >= 1
+      assert {:error, :unsupported} = Native.global_status()
+    end
+  end
+end
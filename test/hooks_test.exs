@@ -89,4 +89,183 @@ defmodule EctoLibSql.HooksTest do
       # No errors
     end
   end
+
+  describe "set_authorizer_rules/2" do
+    setup %{state: state} do
+      {:ok, _, _, state} =
+        EctoLibSql.handle_execute(
+          "CREATE TABLE secrets (id INTEGER PRIMARY KEY, token TEXT)",
+          [],
+          [],
+          state
+        )
+
+      {:ok, _, _, state} =
+        EctoLibSql.handle_execute(
+          "CREATE TABLE posts (id INTEGER PRIMARY KEY, title TEXT)",
+          [],
+          [],
+          state
+        )
+
+      {:ok, state: state}
+    end
+
+    test "rejects a denied table read at prepare time", %{state: state} do
+      assert :ok = Native.set_authorizer_rules(state, [{:read, "secrets"}])
+
+      assert {:error, %EctoLibSql.Error{message: message}, _state} =
+               EctoLibSql.handle_execute("SELECT token FROM secrets", [], [], state)
+
+      assert message =~ "access to secrets.token is prohibited"
+
+      assert {:ok, _, _, _state} =
+               EctoLibSql.handle_execute("SELECT title FROM posts", [], [], state)
+    end
+
+    test "denies attach and pragma writes but allows pragma reads", %{state: state} do
+      assert :ok = Native.set_authorizer_rules(state, [:attach, :pragma_write])
+
+      assert {:error, _, _} =
+               EctoLibSql.handle_execute("ATTACH DATABASE ':memory:' AS other", [], [], state)
+
+      assert {:error, _, _} =
+               EctoLibSql.handle_execute("PRAGMA foreign_keys = ON", [], [], state)
+
+      assert {:ok, _, _, _} = EctoLibSql.handle_execute("PRAGMA foreign_keys", [], [], state)
+    end
+
+    test "an empty rule list removes the authorizer", %{state: state} do
+      :ok = Native.set_authorizer_rules(state, [:read])
+      assert {:error, _, _} = EctoLibSql.handle_execute("SELECT * FROM posts", [], [], state)
+
+      :ok = Native.set_authorizer_rules(state, [])
+      assert {:ok, _, _, _} = EctoLibSql.handle_execute("SELECT * FROM posts", [], [], state)
+    end
+
+    test "rejects unknown actions", %{state: state} do
+      assert {:error, "Unknown authorizer action: " <> _} =
+               Native.set_authorizer_rules(state, [:drop_everything])
+    end
+  end
+
+  describe "set_scan_limit/2 - NOT SUPPORTED" do
+    test "returns :unsupported error", %{state: state} do
+      assert {:error, :unsupported} = Native.set_scan_limit(state, 1_000)
+    end
+
+    test "does not limit queries", %{state: state} do
+      {:error, :unsupported} = Native.set_scan_limit(state, 1)
+
+      {:ok, _, result, _state} =
+        EctoLibSql.handle_execute(
+          "WITH RECURSIVE n(x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM n WHERE x < 1000) SELECT COUNT(*) FROM n",
+          [],
+          [],
+          state
+        )
+
+      assert result.rows == [[1000]]
+    end
+  end
+
+  describe "set_defensive_mode/2 - NOT SUPPORTED" do
+    test "returns :unsupported error", %{state: state} do
+      assert {:error, :unsupported} = Native.set_defensive_mode(state, true)
+      assert {:error, :unsupported} = Native.defensive_mode(state)
+    end
+
+    test "does not block writes to FTS shadow tables", %{state: state} do
+      {:error, :unsupported} = Native.set_defensive_mode(state, true)
+
+      {:ok, _, _, state} =
+        EctoLibSql.handle_execute("CREATE VIRTUAL TABLE docs USING fts5(body)", [], [], state)
+
+      # With defensive mode on, SQLite would reject this write as read-only.
+      assert {:ok, _, _, _state} =
+               EctoLibSql.handle_execute(
+                 "INSERT INTO docs_data (id, block) VALUES (1000, x'00')",
+                 [],
+                 [],
+                 state
+               )
+    end
+  end
+
+  describe "set_max_attached/2 - NOT SUPPORTED" do
+    test "returns :unsupported error", %{state: state} do
+      assert {:error, :unsupported} = Native.set_max_attached(state, 20)
+      assert {:error, :unsupported} = Native.max_attached(state)
+    end
+
+    test "rejects limits above SQLite's hard maximum", %{state: state} do
+      assert_raise FunctionClauseError, fn -> Native.set_max_attached(state, 126) end
+    end
+
+    test "attaching more than 10 databases still fails", %{state: state} do
+      {:error, :unsupported} = Native.set_max_attached(state, 20)
+
+      state =
+        Enum.reduce(1..10, state, fn n, state ->
+          {:ok, _, _, state} =
+            EctoLibSql.handle_execute("ATTACH DATABASE ':memory:' AS shard#{n}", [], [], state)
+
+          state
+        end)
+
+      assert {:error, %EctoLibSql.Error{message: message}, _state} =
+               EctoLibSql.handle_execute("ATTACH DATABASE ':memory:' AS shard11", [], [], state)
+
+      assert message =~ "too many attached databases"
+    end
+  end
+
+  describe "set_max_sql_length/2 - NOT SUPPORTED" do
+    test "returns :unsupported error", %{state: state} do
+      assert {:error, :unsupported} = Native.set_max_sql_length(state, 100)
+      assert {:error, :unsupported} = Native.max_sql_length(state)
+    end
+
+    test "rejects limits outside SQLite's range", %{state: state} do
+      assert_raise FunctionClauseError, fn -> Native.set_max_sql_length(state, 0) end
+
+      assert_raise FunctionClauseError, fn ->
+        Native.set_max_sql_length(state, 1_000_000_001)
+      end
+    end
+
+    test "statements over the requested length still run", %{state: state} do
+      {:error, :unsupported} = Native.set_max_sql_length(state, 100)
+
+      sql = "SELECT 1 " <> String.duplicate("+ 1 ", 100)
+
+      {:ok, _, result, _state} = EctoLibSql.handle_execute(sql, [], [], state)
+      assert result.rows == [[101]]
+    end
+  end
+
+  describe "set_lookaside/3 - NOT SUPPORTED" do
+    test "returns :unsupported error", %{state: state} do
+      assert {:error, :unsupported} = Native.set_lookaside(state, 128, 500)
+    end
+
+    test "rejects negative sizes", %{state: state} do
+      assert_raise FunctionClauseError, fn -> Native.set_lookaside(state, -1, 500) end
+    end
+
+    test "queries still run after the call", %{state: state} do
+      {:error, :unsupported} = Native.set_lookaside(state, 128, 500)
+
+      {:ok, _, result, _state} =
+        EctoLibSql.handle_execute(
+          "WITH RECURSIVE n(x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM n WHERE x < 100) " <>
+            "SELECT sum(x) FROM n",
+          [],
+          [],
+          state
+        )
+
+      assert result.rows == [[5050]]
+    end
+  end
 end
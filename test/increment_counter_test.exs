@@ -0,0 +1,58 @@
+defmodule EctoLibSql.IncrementCounterTest do
+  use ExUnit.Case
+
+  alias EctoLibSql.Native
+
+  setup do
+    db_file = "z_ecto_libsql_test-increment_counter_#{:erlang.unique_integer([:positive])}.db"
+    {:ok, state} = EctoLibSql.connect(database: db_file)
+
+    {:ok, _, _, state} =
+      EctoLibSql.handle_execute(
+        "CREATE TABLE page_views (path TEXT PRIMARY KEY, hits INTEGER NOT NULL DEFAULT 0)",
+        [],
+        [],
+        state
+      )
+
+    on_exit(fn ->
+      EctoLibSql.disconnect([], state)
+      EctoLibSql.TestHelpers.cleanup_db_files(db_file)
+    end)
+
+    {:ok, state: state}
+  end
+
+  defp hits(state, path) do
+    {:ok, _, result, _} =
+      EctoLibSql.handle_execute("SELECT hits FROM page_views WHERE path = ?", [path], [], state)
+
+    result.rows
+  end
+
+  test "increments an existing counter", %{state: state} do
+    {:ok, _, _, state} =
+      EctoLibSql.handle_execute("INSERT INTO page_views VALUES ('/', 41)", [], [], state)
+
+    assert {:ok, 42} = Native.increment_counter(state, :page_views, :path, "/", :hits)
+    assert {:ok, 32} = Native.increment_counter(state, "page_views", "path", "/", "hits", -10)
+    assert hits(state, "/") == [[32]]
+  end
+
+  test "creates a missing counter at delta", %{state: state} do
+    assert {:ok, 5} = Native.increment_counter(state, :page_views, :path, "/new", :hits, 5)
+    assert {:ok, 6} = Native.increment_counter(state, :page_views, :path, "/new", :hits)
+    assert hits(state, "/new") == [[6]]
+  end
+
+  test "returns an error when the key column is not unique", %{state: state} do
+    {:ok, _, _, state} =
+      EctoLibSql.handle_execute("CREATE TABLE loose (name TEXT, total INTEGER)", [], [], state)
+
+    assert {:error, _} = Native.increment_counter(state, :loose, :name, "a", :total)
+  end
+
+  test "returns an error for an unknown table", %{state: state} do
+    assert {:error, _} = Native.increment_counter(state, :missing, :path, "/", :hits)
+  end
+end
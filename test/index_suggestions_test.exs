@@ -0,0 +1,65 @@
+defmodule EctoLibSql.IndexSuggestionsTest do
+  use ExUnit.Case
+
+  alias EctoLibSql.Native
+
+  setup do
+    db_file = "z_ecto_libsql_test-index_suggestions_#{:erlang.unique_integer([:positive])}.db"
+    {:ok, state} = EctoLibSql.connect(database: db_file)
+
+    {:ok, _, _, state} =
+      EctoLibSql.handle_execute(
+        "CREATE TABLE orders (id INTEGER PRIMARY KEY, customer_id INTEGER, status TEXT)",
+        [],
+        [],
+        state
+      )
+
+    on_exit(fn ->
+      EctoLibSql.disconnect([], state)
+      EctoLibSql.TestHelpers.cleanup_db_files(db_file)
+    end)
+
+    {:ok, state: state}
+  end
+
+  test "suggests an index for a filtered, unindexed column", %{state: state} do
+    assert {:ok, [suggestion]} =
+             Native.index_suggestions(state, "SELECT * FROM orders WHERE customer_id = ?", [1])
+
+    assert suggestion ==
+             ~s(CREATE INDEX "orders_customer_id_index" ON "orders" ("customer_id"))
+
+    # Suggestions are never run
+    assert {:ok, :scan} =
+             Native.used_index(state, "SELECT * FROM orders WHERE customer_id = ?", [1])
+  end
+
+  test "equality columns come before range columns", %{state: state} do
+    assert {:ok, [suggestion]} =
+             Native.index_suggestions(
+               state,
+               "SELECT * FROM orders WHERE customer_id > ? AND status = ?",
+               [1, "open"]
+             )
+
+    assert suggestion =~ ~s(("status", "customer_id"\))
+  end
+
+  test "no suggestion once the index exists", %{state: state} do
+    {:ok, _, _, state} =
+      EctoLibSql.handle_execute(
+        "CREATE INDEX orders_customer_id_index ON orders (customer_id)",
+        [],
+        [],
+        state
+      )
+
+    assert {:ok, []} =
+             Native.index_suggestions(state, "SELECT * FROM orders WHERE customer_id = ?", [1])
+  end
+
+  test "invalid SQL returns an error", %{state: state} do
+    assert {:error, _} = Native.index_suggestions(state, "SELECT * FROM missing WHERE a = 1")
+  end
+end
@@ -0,0 +1,84 @@
+defmodule EctoLibSql.KeysetPaginationTest do
+  @moduledoc """
+  Tests for keyset pagination through `Native.paginate_keyset/5`.
+  """
+  use ExUnit.Case
+
+  alias EctoLibSql.Native
+
+  setup do
+    test_db = "z_ecto_libsql_test-keyset_#{:erlang.unique_integer([:positive])}.db"
+
+    {:ok, state} = EctoLibSql.connect(database: test_db)
+
+    {:ok, _, _, state} =
+      EctoLibSql.handle_execute(
+        "CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT)",
+        [],
+        [],
+        state
+      )
+
+    {:ok, _, _, state} =
+      EctoLibSql.handle_execute(
+        """
+        WITH RECURSIVE n(x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM n WHERE x < 23)
+        INSERT INTO items (id, name) SELECT x * 3, 'item ' || x FROM n
+        """,
+        [],
+        [],
+        state
+      )
+
+    on_exit(fn ->
+      EctoLibSql.disconnect([], state)
+      EctoLibSql.TestHelpers.cleanup_db_files(test_db)
+    end)
+
+    {:ok, state: state}
+  end
+
+  defp all_pages(state, sql, after_key, acc) do
+    case Native.paginate_keyset(state, sql, "id", after_key, 5) do
+      {:ok, %EctoLibSql.Result{num_rows: 0}, nil} ->
+        Enum.reverse(acc)
+
+      {:ok, %EctoLibSql.Result{rows: rows}, next_key} ->
+        assert length(rows) <= 5
+        all_pages(state, sql, next_key, [rows | acc])
+    end
+  end
+
+  test "pages through every row once, in key order", %{state: state} do
+    pages = all_pages(state, "SELECT id, name FROM items", nil, [])
+
+    assert length(pages) == 5
+    ids = pages |> Enum.concat() |> Enum.map(fn [id, _name] -> id end)
+    assert ids == Enum.map(1..23, &(&1 * 3))
+  end
+
+  test "keeps the base query's filter", %{state: state} do
+    ids =
+      state
+      |> all_pages("SELECT id FROM items WHERE id % 2 = 0", nil, [])
+      |> Enum.concat()
+      |> Enum.map(fn [id] -> id end)
+
+    assert ids == for(x <- 1..23, rem(x * 3, 2) == 0, do: x * 3)
+  end
+
+  test "returns the last key of the page", %{state: state} do
+    assert {:ok, %EctoLibSql.Result{columns: ["id"], rows: [[3], [6]]}, 6} =
+             Native.paginate_keyset(state, "SELECT id FROM items", "id", nil, 2)
+
+    assert {:ok, %EctoLibSql.Result{rows: [[9], [12]]}, 12} =
+             Native.paginate_keyset(state, "SELECT id FROM items", :id, 6, 2)
+  end
+
+  test "rejects base queries it can't wrap", %{state: state} do
+    assert {:error, _} =
+             Native.paginate_keyset(state, "SELECT id FROM items LIMIT 3", "id", nil, 2)
+
+    assert {:error, _} = Native.paginate_keyset(state, "DELETE FROM items", "id", nil, 2)
+  end
+end
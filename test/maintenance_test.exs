@@ -0,0 +1,110 @@
+defmodule EctoLibSql.MaintenanceTest do
+  @moduledoc """
+  Tests for database maintenance helpers such as `AUTOINCREMENT` sequence normalisation
+  and `VACUUM` reporting.
+  """
+  use ExUnit.Case
+
+  alias EctoLibSql.Native
+
+  setup do
+    test_db = "z_ecto_libsql_test-maintenance_#{:erlang.unique_integer([:positive])}.db"
+
+    {:ok, state} = EctoLibSql.connect(database: test_db)
+
+    on_exit(fn ->
+      EctoLibSql.disconnect([], state)
+      EctoLibSql.TestHelpers.cleanup_db_files(test_db)
+    end)
+
+    {:ok, state: state}
+  end
+
+  describe "sync_autoincrement_sequence/2" do
+    test "next auto id follows explicit high ids", %{state: state} do
+      {:ok, _, _, state} =
+        EctoLibSql.handle_execute(
+          "CREATE TABLE items (id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT)",
+          [],
+          [],
+          state
+        )
+
+      {:ok, _, _, state} =
+        EctoLibSql.handle_execute(
+          "INSERT INTO items (id, name) VALUES (500, 'a'), (1000, 'b')",
+          [],
+          [],
+          state
+        )
+
+      # Simulate a sequence left behind by an external import
+      {:ok, _, _, state} =
+        EctoLibSql.handle_execute(
+          "UPDATE sqlite_sequence SET seq = 1 WHERE name = 'items'",
+          [],
+          [],
+          state
+        )
+
+      assert {:ok, 1000} = Native.sync_autoincrement_sequence(state, "items")
+
+      {:ok, _, _, state} =
+        EctoLibSql.handle_execute("INSERT INTO items (name) VALUES ('c')", [], [], state)
+
+      assert Native.get_last_insert_rowid(state) == 1001
+    end
+
+    test "is a no-op for tables without AUTOINCREMENT", %{state: state} do
+      {:ok, _, _, state} =
+        EctoLibSql.handle_execute(
+          "CREATE TABLE plain (id INTEGER PRIMARY KEY, name TEXT)",
+          [],
+          [],
+          state
+        )
+
+      assert {:ok, nil} = Native.sync_autoincrement_sequence(state, :plain)
+    end
+
+    test "returns an error for unknown tables", %{state: state} do
+      assert {:error, reason} = Native.sync_autoincrement_sequence(state, "missing")
+      assert reason =~ "Table not found"
+    end
+  end
+
+  describe "vacuum_report/1" do
+    test "reports space reclaimed after deleting rows", %{state: state} do
+      {:ok, _, _, state} =
+        EctoLibSql.handle_execute(
+          "CREATE TABLE docs (id INTEGER PRIMARY KEY, body BLOB)",
+          [],
+          [],
+          state
+        )
+
+      state =
+        Enum.reduce(1..200, state, fn _, state ->
+          {:ok, _, _, state} =
+            EctoLibSql.handle_execute(
+              "INSERT INTO docs (body) VALUES (?)",
+              [:binary.copy(<<7>>, 4096)],
+              [],
+              state
+            )
+
+          state
+        end)
+
+      {:ok, _, _, state} =
+        EctoLibSql.handle_execute("DELETE FROM docs WHERE id > 10", [], [], state)
+
+      assert {:ok, report} = Native.vacuum_report(state)
+      assert report.reclaimed_bytes > 0
+      assert report.reclaimed_bytes == report.before_bytes - report.after_bytes
+
+      # A second vacuum has nothing left to reclaim
+      assert {:ok, %{reclaimed_bytes: 0}} = Native.vacuum_report(state)
+    end
+  end
+end
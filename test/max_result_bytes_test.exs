@@ -0,0 +1,60 @@
+defmodule EctoLibSql.MaxResultBytesTest do
+  use ExUnit.Case
+
+  alias EctoLibSql.Native
+
+  setup do
+    db_file = "z_ecto_libsql_test-max_result_bytes_#{:erlang.unique_integer([:positive])}.db"
+    {:ok, state} = EctoLibSql.connect(database: db_file)
+
+    {:ok, _} =
+      Native.execute_batch_sql(state, """
+      CREATE TABLE files (id INTEGER PRIMARY KEY, data BLOB);
+      INSERT INTO files (data) VALUES (zeroblob(1000)), (zeroblob(1000)), (zeroblob(1000));
+      """)
+
+    on_exit(fn ->
+      EctoLibSql.disconnect([], state)
+      EctoLibSql.TestHelpers.cleanup_db_files(db_file)
+    end)
+
+    {:ok, state: state}
+  end
+
+  test "the NIF aborts once blob rows pass the budget", %{state: state} do
+    sql = "SELECT data FROM files ORDER BY id"
+
+    assert {:error, :result_too_large, 2000} =
+             Native.query_args(state.conn_id, state.mode, state.sync, sql, [], 1500)
+
+    assert %{"num_rows" => 3} =
+             Native.query_args(state.conn_id, state.mode, state.sync, sql, [], 3000)
+  end
+
+  test "handle_execute reports an oversized result as an error", %{state: state} do
+    assert {:error, %EctoLibSql.Error{sqlite: %{code: :result_too_large, bytes: 2016}}, _} =
+             EctoLibSql.handle_execute(
+               "SELECT id, data FROM files ORDER BY id",
+               [],
+               [max_result_bytes: 1500],
+               state
+             )
+
+    assert {:ok, _, %EctoLibSql.Result{num_rows: 3}, _} =
+             EctoLibSql.handle_execute("SELECT id, data FROM files", [], [], state)
+  end
+
+  test "applies inside a transaction", %{state: state} do
+    {:ok, :begin, state} = EctoLibSql.handle_begin([], state)
+
+    assert {:error, %EctoLibSql.Error{sqlite: %{code: :result_too_large}}, state} =
+             EctoLibSql.handle_execute(
+               "SELECT data FROM files",
+               [],
+               [max_result_bytes: 10],
+               state
+             )
+
+    {:ok, _, _state} = EctoLibSql.handle_rollback([], state)
+  end
+end
@@ -0,0 +1,27 @@
+defmodule EctoLibSql.MemoryStatsTest do
+  use ExUnit.Case, async: true
+
+  alias EctoLibSql.Native
+
+  describe "sqlite_memory_stats/0 - NOT SUPPORTED" do
+    test "returns :unsupported error" do
+      assert {:error, :unsupported} = Native.sqlite_memory_stats()
+    end
+
+    test "returns :unsupported after running queries" do
+      {:ok, state} = EctoLibSql.connect(database: ":memory:")
+      on_exit(fn -> EctoLibSql.disconnect([], state) end)
+
+      {:ok, _, result, _state} =
+        EctoLibSql.handle_execute(
+          "WITH RECURSIVE n(x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM n WHERE x < 1000) SELECT COUNT(*) FROM n",
+          [],
+          [],
+          state
+        )
+
+      assert result.rows == [[1000]]
+      assert {:error, :unsupported} = Native.sqlite_memory_stats()
+    end
+  end
+end
@@ -449,6 +449,47 @@ defmodule EctoLibSql.NamedParametersExecutionTest do
       EctoLibSql.Native.close_stmt(stmt_id)
     end
 
+    test "execute_stmt binds explicit nil as NULL", %{state: state} do
+      sql = "INSERT INTO users (id, name, email, age) VALUES (:id, :name, :email, :age)"
+      {:ok, stmt_id} = EctoLibSql.Native.prepare(state, sql)
+
+      {:ok, 1} =
+        EctoLibSql.Native.execute_stmt(state, stmt_id, sql, %{
+          id: 1,
+          name: "NilAge",
+          email: "nil@test.com",
+          age: nil
+        })
+
+      EctoLibSql.Native.close_stmt(stmt_id)
+
+      {:ok, _, result, _} =
+        EctoLibSql.handle_execute("SELECT age FROM users WHERE id = 1", [], [], state)
+
+      assert [[nil]] = result.rows
+    end
+
+    test "execute_stmt raises on a missing named parameter", %{state: state} do
+      sql = "INSERT INTO users (id, name, email, age) VALUES (:id, :name, :email, :age)"
+      {:ok, stmt_id} = EctoLibSql.Native.prepare(state, sql)
+
+      # Omitting :age must not silently bind NULL.
+      assert_raise ArgumentError, ~r/Missing required parameters: :age/, fn ->
+        EctoLibSql.Native.execute_stmt(state, stmt_id, sql, %{
+          id: 1,
+          name: "Missing",
+          email: "missing@test.com"
+        })
+      end
+
+      EctoLibSql.Native.close_stmt(stmt_id)
+
+      {:ok, _, result, _} =
+        EctoLibSql.handle_execute("SELECT COUNT(*) FROM users", [], [], state)
+
+      assert [[0]] = result.rows
+    end
+
     test "prepared statement functions still work with positional lists", %{state: state} do
       # Ensure backward compatibility - positional lists should still work.
       sql = "INSERT INTO users (id, name, email, age) VALUES (?, ?, ?, ?)"
@@ -0,0 +1,63 @@
+defmodule EctoLibSql.NdjsonTest do
+  use ExUnit.Case
+
+  alias EctoLibSql.Native
+
+  setup do
+    db_file = "z_ecto_libsql_test-ndjson_#{:erlang.unique_integer([:positive])}.db"
+    {:ok, state} = EctoLibSql.connect(database: db_file)
+
+    {:ok, _, _, state} =
+      EctoLibSql.handle_execute(
+        "CREATE TABLE items (id INTEGER PRIMARY KEY, price REAL, name TEXT, data BLOB)",
+        [],
+        [],
+        state
+      )
+
+    state =
+      Enum.reduce(
+        [[1, 9.5, "first \"one\"\nline", <<0, 255, 16>>], [2, nil, nil, nil]],
+        state,
+        fn row, state ->
+          {:ok, _, _, state} =
+            EctoLibSql.handle_execute("INSERT INTO items VALUES (?, ?, ?, ?)", row, [], state)
+
+          state
+        end
+      )
+
+    on_exit(fn ->
+      EctoLibSql.disconnect([], state)
+      EctoLibSql.TestHelpers.cleanup_db_files(db_file)
+    end)
+
+    {:ok, state: state}
+  end
+
+  test "each line parses back to its row", %{state: state} do
+    assert {:ok, ndjson} =
+             Native.query_ndjson(state, "SELECT * FROM items WHERE id >= ? ORDER BY id", [1])
+
+    assert String.ends_with?(ndjson, "\n")
+    lines = ndjson |> String.split("\n", trim: true) |> Enum.map(&Jason.decode!/1)
+
+    assert lines == [
+             %{
+               "id" => 1,
+               "price" => 9.5,
+               "name" => "first \"one\"\nline",
+               "data" => Base.encode64(<<0, 255, 16>>)
+             },
+             %{"id" => 2, "price" => nil, "name" => nil, "data" => nil}
+           ]
+  end
+
+  test "no rows gives an empty binary", %{state: state} do
+    assert {:ok, ""} = Native.query_ndjson(state, "SELECT * FROM items WHERE id > 100")
+  end
+
+  test "invalid SQL returns an error", %{state: state} do
+    assert {:error, _} = Native.query_ndjson(state, "SELECT * FROM missing_table")
+  end
+end
@@ -0,0 +1,46 @@
+defmodule EctoLibSql.NormaliseDatetimesTest do
+  use ExUnit.Case
+
+  setup do
+    db_file = "z_ecto_libsql_test-normalise_datetimes_#{:erlang.unique_integer([:positive])}.db"
+    {:ok, state} = EctoLibSql.connect(database: db_file)
+
+    {:ok, _} =
+      EctoLibSql.Native.execute_batch_sql(state, """
+      CREATE TABLE events (id INTEGER PRIMARY KEY, at DATETIME, label TEXT);
+      INSERT INTO events (at, label) VALUES ('2024-03-01 09:30:00', '2024-03-01 09:30:00');
+      INSERT INTO events (at, label) VALUES ('2024-03-01T10:15:30.250', 'second');
+      """)
+
+    on_exit(fn ->
+      EctoLibSql.disconnect([], state)
+      EctoLibSql.TestHelpers.cleanup_db_files(db_file)
+    end)
+
+    {:ok, state: state}
+  end
+
+  test "returns raw datetime text by default", %{state: state} do
+    {:ok, _, result, _} =
+      EctoLibSql.handle_execute("SELECT at FROM events ORDER BY id", [], [], state)
+
+    assert result.rows == [["2024-03-01 09:30:00"], ["2024-03-01T10:15:30.250"]]
+  end
+
+  test "normalises datetime columns stored in different formats", %{state: state} do
+    {:ok, _, result, _} =
+      EctoLibSql.handle_execute(
+        "SELECT at, label FROM events ORDER BY id",
+        [],
+        [normalise_datetimes: true],
+        state
+      )
+
+    assert result.columns == ["at", "label"]
+
+    assert result.rows == [
+             ["2024-03-01T09:30:00", "2024-03-01 09:30:00"],
+             ["2024-03-01T10:15:30.250", "second"]
+           ]
+  end
+end
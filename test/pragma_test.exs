@@ -112,6 +112,193 @@ defmodule EctoLibSql.PragmaTest do
         {:ok, _result} = Pragma.set_synchronous(state, level)
       end
     end
+
+    test "set_synchronous rejects invalid levels", %{state: state} do
+      assert {:error, message} = Pragma.set_synchronous(state, :fast)
+      assert message =~ "synchronous"
+
+      assert {:error, _} = Pragma.set_synchronous(state, 4)
+    end
+
+    test "synchronous connect option is applied after connecting" do
+      test_db = "z_ecto_libsql_test-pragma_sync_#{:erlang.unique_integer([:positive])}.db"
+      {:ok, state} = EctoLibSql.connect(database: test_db, synchronous: :normal)
+
+      on_exit(fn ->
+        EctoLibSql.disconnect([], state)
+        EctoLibSql.TestHelpers.cleanup_db_files(test_db)
+      end)
+
+      {:ok, result} = Pragma.synchronous(state)
+      assert result.rows == [[1]]
+    end
+
+    test "connect rejects an invalid synchronous option" do
+      test_db = "z_ecto_libsql_test-pragma_sync_#{:erlang.unique_integer([:positive])}.db"
+
+      assert {:error, message} = EctoLibSql.connect(database: test_db, synchronous: "normal")
+      assert message =~ "synchronous"
+      refute File.exists?(test_db)
+    end
+  end
+
+  describe "mmap_size" do
+    test "set_mmap_size sets the memory-mapped I/O size", %{state: state} do
+      {:ok, result} = Pragma.set_mmap_size(state, 268_435_456)
+      assert result.rows == [[268_435_456]]
+
+      {:ok, result} = Pragma.mmap_size(state)
+      assert result.rows == [[268_435_456]]
+    end
+
+    test "set_mmap_size with 0 disables memory-mapped I/O", %{state: state} do
+      {:ok, _} = Pragma.set_mmap_size(state, 268_435_456)
+      {:ok, _} = Pragma.set_mmap_size(state, 0)
+
+      {:ok, result} = Pragma.mmap_size(state)
+      assert result.rows == [[0]]
+    end
+
+    test "set_mmap_size rejects negative sizes", %{state: state} do
+      assert {:error, message} = Pragma.set_mmap_size(state, -1)
+      assert message =~ "non-negative"
+    end
+
+    test "mmap_size connect option is applied after connecting" do
+      test_db = "z_ecto_libsql_test-pragma_mmap_#{:erlang.unique_integer([:positive])}.db"
+      {:ok, state} = EctoLibSql.connect(database: test_db, mmap_size: 67_108_864)
+
+      on_exit(fn ->
+        EctoLibSql.disconnect([], state)
+        EctoLibSql.TestHelpers.cleanup_db_files(test_db)
+      end)
+
+      {:ok, result} = Pragma.mmap_size(state)
+      assert result.rows == [[67_108_864]]
+    end
+
+    test "connect rejects a negative mmap_size option" do
+      test_db = "z_ecto_libsql_test-pragma_mmap_#{:erlang.unique_integer([:positive])}.db"
+
+      assert {:error, message} = EctoLibSql.connect(database: test_db, mmap_size: -1)
+      assert message =~ "mmap_size"
+      refute File.exists?(test_db)
+    end
+  end
+
+  describe "wal_autocheckpoint" do
+    test "set_wal_autocheckpoint round-trips", %{state: state} do
+      assert {:ok, 1000} = Pragma.wal_autocheckpoint(state)
+
+      assert :ok = Pragma.set_wal_autocheckpoint(state, 4000)
+      assert {:ok, 4000} = Pragma.wal_autocheckpoint(state)
+
+      assert :ok = Pragma.set_wal_autocheckpoint(state, 0)
+      assert {:ok, 0} = Pragma.wal_autocheckpoint(state)
+    end
+
+    test "set_wal_autocheckpoint rejects negative values", %{state: state} do
+      assert {:error, message} = Pragma.set_wal_autocheckpoint(state, -5)
+      assert message =~ "non-negative"
+      assert {:ok, 1000} = Pragma.wal_autocheckpoint(state)
+    end
+  end
+
+  describe "cache_spill" do
+    test "set_cache_spill toggles spilling and sets a threshold", %{state: state} do
+      {:ok, _} = Pragma.query(state, "PRAGMA cache_size = 100")
+
+      assert :ok = Pragma.set_cache_spill(state, false)
+      assert {:ok, 0} = Pragma.cache_spill(state)
+      assert {:ok, %{rows: [[0]]}} = Pragma.query(state, "PRAGMA cache_spill")
+
+      assert :ok = Pragma.set_cache_spill(state, 5000)
+      assert {:ok, 5000} = Pragma.cache_spill(state)
+
+      assert :ok = Pragma.set_cache_spill(state, false)
+      assert :ok = Pragma.set_cache_spill(state, true)
+      assert {:ok, %{rows: [[5000]]}} = Pragma.query(state, "PRAGMA cache_spill")
+    end
+
+    test "set_cache_spill rejects invalid settings", %{state: state} do
+      assert {:error, message} = Pragma.set_cache_spill(state, -1)
+      assert message =~ "cache_spill"
+      assert {:error, _} = Pragma.set_cache_spill(state, :sometimes)
+    end
+  end
+
+  describe "secure_delete" do
+    test "set_secure_delete sets each mode", %{state: state} do
+      for {mode, expected} <- [on: 1, fast: 2, off: 0] do
+        {:ok, _} = Pragma.set_secure_delete(state, mode)
+
+        {:ok, result} = Pragma.secure_delete(state)
+        assert result.rows == [[expected]]
+      end
+    end
+
+    test "set_secure_delete rejects invalid modes", %{state: state} do
+      assert {:error, message} = Pragma.set_secure_delete(state, :sometimes)
+      assert message =~ "secure_delete"
+    end
+
+    test "secure_delete connect option is applied after connecting" do
+      test_db = "z_ecto_libsql_test-pragma_secure_#{:erlang.unique_integer([:positive])}.db"
+      {:ok, state} = EctoLibSql.connect(database: test_db, secure_delete: :on)
+
+      on_exit(fn ->
+        EctoLibSql.disconnect([], state)
+        EctoLibSql.TestHelpers.cleanup_db_files(test_db)
+      end)
+
+      {:ok, result} = Pragma.secure_delete(state)
+      assert result.rows == [[1]]
+    end
+
+    test "connect rejects an invalid secure_delete option" do
+      test_db = "z_ecto_libsql_test-pragma_secure_#{:erlang.unique_integer([:positive])}.db"
+
+      assert {:error, message} = EctoLibSql.connect(database: test_db, secure_delete: true)
+      assert message =~ "secure_delete"
+      refute File.exists?(test_db)
+    end
+  end
+
+  describe "temp_store" do
+    test "set_temp_store sets each mode", %{state: state} do
+      for {mode, expected} <- [memory: 2, file: 1, default: 0] do
+        {:ok, _} = Pragma.set_temp_store(state, mode)
+
+        {:ok, result} = Pragma.temp_store(state)
+        assert result.rows == [[expected]]
+      end
+    end
+
+    test "set_temp_store rejects invalid modes", %{state: state} do
+      assert {:error, message} = Pragma.set_temp_store(state, :disk)
+      assert message =~ "temp_store"
+    end
+
+    test "temp_store connect option is applied after connecting" do
+      test_db = "z_ecto_libsql_test-pragma_temp_store_#{:erlang.unique_integer([:positive])}.db"
+      {:ok, state} = EctoLibSql.connect(database: test_db, temp_store: :memory)
+
+      on_exit(fn ->
+        EctoLibSql.disconnect([], state)
+        EctoLibSql.TestHelpers.cleanup_db_files(test_db)
+      end)
+
+      {:ok, result} = Pragma.temp_store(state)
+      assert result.rows == [[2]]
+    end
+
+    test "connect rejects an invalid temp_store option" do
+      test_db = "z_ecto_libsql_test-pragma_temp_store_#{:erlang.unique_integer([:positive])}.db"
+
+      assert {:error, message} = EctoLibSql.connect(database: test_db, temp_store: "memory")
+      assert message =~ "temp_store"
+      refute File.exists?(test_db)
+    end
   end
 
   describe "table_info" do
@@ -215,6 +402,62 @@ defmodule EctoLibSql.PragmaTest do
     end
   end
 
+  describe "schema_version" do
+    test "increments after a schema change", %{state: state} do
+      {:ok, _, _, state} =
+        EctoLibSql.handle_execute("CREATE TABLE items (id INTEGER PRIMARY KEY)", [], [], state)
+
+      {:ok, %{rows: [[before]]}} = Pragma.schema_version(state)
+      assert is_integer(before)
+
+      {:ok, _, _, state} =
+        EctoLibSql.handle_execute("ALTER TABLE items ADD COLUMN name TEXT", [], [], state)
+
+      {:ok, %{rows: [[after_alter]]}} = Pragma.schema_version(state)
+      assert after_alter > before
+    end
+
+    test "is unchanged by data changes", %{state: state} do
+      {:ok, _, _, state} =
+        EctoLibSql.handle_execute("CREATE TABLE items (id INTEGER PRIMARY KEY)", [], [], state)
+
+      {:ok, %{rows: [[before]]}} = Pragma.schema_version(state)
+
+      {:ok, _, _, state} =
+        EctoLibSql.handle_execute("INSERT INTO items (id) VALUES (1)", [], [], state)
+
+      assert {:ok, %{rows: [[^before]]}} = Pragma.schema_version(state)
+    end
+  end
+
+  describe "snapshot" do
+    test "reports every pragma and reflects earlier settings", %{state: state} do
+      :ok = Pragma.enable_foreign_keys(state)
+      {:ok, _} = Pragma.set_journal_mode(state, :wal)
+      {:ok, _} = Pragma.query(state, "PRAGMA cache_size = -4000")
+
+      assert {:ok, snapshot} = Pragma.snapshot(state)
+
+      assert Map.keys(snapshot) |> Enum.sort() ==
+               Enum.sort([
+                 :journal_mode,
+                 :synchronous,
+                 :foreign_keys,
+                 :busy_timeout,
+                 :cache_size,
+                 :page_size,
+                 :temp_store,
+                 :mmap_size,
+                 :wal_autocheckpoint
+               ])
+
+      assert snapshot.journal_mode == "wal"
+      assert snapshot.foreign_keys == 1
+      assert snapshot.cache_size == -4000
+      assert is_integer(snapshot.page_size)
+    end
+  end
+
   describe "raw query" do
     test "query executes arbitrary PRAGMA statements", %{state: state} do
       # Test with foreign_keys
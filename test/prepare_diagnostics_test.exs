@@ -0,0 +1,55 @@
+defmodule EctoLibSql.PrepareDiagnosticsTest do
+  use ExUnit.Case
+
+  alias EctoLibSql.Native
+
+  setup do
+    db_file = "z_ecto_libsql_test-prepare_diag_#{:erlang.unique_integer([:positive])}.db"
+    {:ok, state} = EctoLibSql.connect(database: db_file)
+
+    {:ok, _, _, state} =
+      EctoLibSql.handle_execute("CREATE TABLE users (id INTEGER, name TEXT)", [], [], state)
+
+    on_exit(fn ->
+      EctoLibSql.disconnect([], state)
+      EctoLibSql.TestHelpers.cleanup_db_files(db_file)
+    end)
+
+    {:ok, state: state}
+  end
+
+  describe "prepare_with_diagnostics/2" do
+    test "points the offset at a typo", %{state: state} do
+      sql = "SELECT id, name FORM users"
+
+      assert {:error, %{message: message, offset: offset, code: 1}} =
+               Native.prepare_with_diagnostics(state, sql)
+
+      assert message =~ "syntax error"
+      assert offset == 21
+      assert binary_part(sql, offset, 5) == "users"
+    end
+
+    test "points the offset at an unknown column", %{state: state} do
+      sql = "SELECT id, nmae FROM users"
+
+      assert {:error, %{message: message, offset: 11}} =
+               Native.prepare_with_diagnostics(state, sql)
+
+      assert message =~ "no such column"
+    end
+
+    test "reports a nil offset for truncated SQL", %{state: state} do
+      assert {:error, %{message: message, offset: nil}} =
+               Native.prepare_with_diagnostics(state, "SELECT id FROM")
+
+      assert message =~ "incomplete input"
+    end
+
+    test "returns a usable statement on success", %{state: state} do
+      assert {:ok, stmt_id} = Native.prepare_with_diagnostics(state, "SELECT id FROM users")
+      assert {:ok, %{rows: []}} = Native.query_stmt(state, stmt_id, [])
+      Native.close_stmt(stmt_id)
+    end
+  end
+end
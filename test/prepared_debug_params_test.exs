@@ -0,0 +1,48 @@
+defmodule EctoLibSql.PreparedDebugParamsTest do
+  use ExUnit.Case
+
+  alias EctoLibSql.Native
+
+  setup do
+    db_file = "z_ecto_libsql_test-debug_params_#{:erlang.unique_integer([:positive])}.db"
+    {:ok, state} = EctoLibSql.connect(database: db_file)
+
+    {:ok, _, _, state} =
+      EctoLibSql.handle_execute("CREATE TABLE users (name TEXT, email TEXT)", [], [], state)
+
+    on_exit(fn ->
+      EctoLibSql.disconnect([], state)
+      EctoLibSql.TestHelpers.cleanup_db_files(db_file)
+    end)
+
+    {:ok, state: state}
+  end
+
+  test "reports too few arguments on execute", %{state: state} do
+    sql = "INSERT INTO users (name, email) VALUES (?, ?)"
+    {:ok, stmt_id} = Native.prepare(state, sql)
+
+    assert {:ok, 1, %{expected: 2, supplied: 1}} =
+             Native.execute_stmt(state, stmt_id, sql, ["alice"], debug_params: true)
+
+    Native.close_stmt(stmt_id)
+  end
+
+  test "reports matching counts on query", %{state: state} do
+    {:ok, stmt_id} = Native.prepare(state, "SELECT name FROM users WHERE name = ? OR email = ?")
+
+    assert {:ok, %EctoLibSql.Result{rows: []}, %{expected: 2, supplied: 2}} =
+             Native.query_stmt(state, stmt_id, ["a", "b"], debug_params: true)
+
+    Native.close_stmt(stmt_id)
+  end
+
+  test "results are unchanged without the flag", %{state: state} do
+    sql = "INSERT INTO users (name, email) VALUES (?, ?)"
+    {:ok, stmt_id} = Native.prepare(state, sql)
+
+    assert {:ok, 1} = Native.execute_stmt(state, stmt_id, sql, ["alice", "a@example.com"])
+
+    Native.close_stmt(stmt_id)
+  end
+end
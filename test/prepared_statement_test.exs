@@ -504,6 +504,159 @@ defmodule EctoLibSql.PreparedStatementTest do
     end
   end
 
+  describe "prepare_all - batch preparation" do
+    test "prepares three statements and returns metadata for each", %{state: state} do
+      insert_sql = "INSERT INTO users (name, email) VALUES (:name, :email)"
+
+      {:ok, [select, insert, count]} =
+        Native.prepare_all(state, [
+          "SELECT id, name FROM users WHERE id = ?",
+          insert_sql,
+          "SELECT COUNT(*) AS total FROM users"
+        ])
+
+      assert %{
+               stmt_id: select_id,
+               columns: [{"id", "id", "INTEGER"}, {"name", "name", "TEXT"}],
+               param_count: 1
+             } = select
+
+      assert %{stmt_id: insert_id, columns: [], param_count: 2} = insert
+      assert %{stmt_id: count_id, columns: [{"total", "total", nil}], param_count: 0} = count
+
+      # Statements are usable like any other prepared statement
+      assert {:ok, 1} =
+               Native.execute_stmt(state, insert_id, insert_sql, %{name: "Ada", email: "a@x"})
+
+      assert {:ok, %{rows: [[1]]}} = Native.query_stmt(state, count_id, [])
+      assert {:ok, %{rows: [[1, "Ada"]]}} = Native.query_stmt(state, select_id, [1])
+
+      Enum.each([select_id, insert_id, count_id], &Native.close_stmt/1)
+    end
+
+    test "statements are owned by the preparing connection", %{state: state} do
+      other_db = "z_ecto_libsql_test-prepared_other_#{:erlang.unique_integer([:positive])}.db"
+      other_conn_id = Native.connect([database: other_db], :local)
+
+      on_exit(fn ->
+        Native.close(other_conn_id, :conn_id)
+        EctoLibSql.TestHelpers.cleanup_db_files(other_db)
+      end)
+
+      {:ok, [%{stmt_id: stmt_id}]} = Native.prepare_all(state, ["SELECT ? AS val"])
+
+      assert Native.statement_parameter_count(state.conn_id, stmt_id) == 1
+
+      assert Native.statement_parameter_count(other_conn_id, stmt_id) ==
+               {:error, "Statement does not belong to connection"}
+
+      Native.close_stmt(stmt_id)
+    end
+
+    test "aborts on the first failure by default", %{state: state} do
+      assert {:error, %{index: 1, sql: "SELECT * FROM missing", error: error}} =
+               Native.prepare_all(state, [
+                 "SELECT 1",
+                 "SELECT * FROM missing",
+                 "SELECT 2"
+               ])
+
+      assert error =~ "no such table"
+    end
+
+    test "continues past failures with on_error: :continue", %{state: state} do
+      {:ok, [first, failed, last]} =
+        Native.prepare_all(
+          state,
+          ["SELECT 1", "SELECT * FROM missing", "SELECT 2"],
+          on_error: :continue
+        )
+
+      assert %{stmt_id: first_id, param_count: 0} = first
+      assert %{index: 1, sql: "SELECT * FROM missing", error: error} = failed
+      assert error =~ "no such table"
+      assert %{stmt_id: last_id} = last
+
+      Enum.each([first_id, last_id], &Native.close_stmt/1)
+    end
+
+    test "empty list returns no entries", %{state: state} do
+      assert {:ok, []} = Native.prepare_all(state, [])
+    end
+  end
+
+  describe "declare_stmt_cursor - cursors over prepared statements" do
+    test "declares a cursor from a parameterised statement and fetches rows", %{state: state} do
+      for i <- 1..5 do
+        {:ok, _, _, _} =
+          exec_sql(state, "INSERT INTO users (id, name, email) VALUES (?, ?, ?)", [
+            i,
+            "User #{i}",
+            "user#{i}@example.com"
+          ])
+      end
+
+      {:ok, stmt_id} = Native.prepare(state, "SELECT id, name FROM users WHERE id > ? ORDER BY id")
+
+      {:ok, cursor_id} = Native.declare_stmt_cursor(state, stmt_id, [2])
+
+      assert {["id", "name"], [[3, "User 3"], [4, "User 4"]], 2} =
+               Native.fetch_cursor(state.conn_id, cursor_id, 2)
+
+      assert {["id", "name"], [[5, "User 5"]], 1} =
+               Native.fetch_cursor(state.conn_id, cursor_id, 2)
+
+      # Re-declare over the same statement with new parameters
+      {:ok, second_cursor_id} = Native.declare_stmt_cursor(state, stmt_id, [4])
+
+      assert {_, [[5, "User 5"]], 1} = Native.fetch_cursor(state.conn_id, second_cursor_id, 10)
+
+      Native.close(cursor_id, :cursor_id)
+      Native.close(second_cursor_id, :cursor_id)
+      Native.close_stmt(stmt_id)
+    end
+
+    test "supports named parameters", %{state: state} do
+      {:ok, _, _, _} =
+        exec_sql(state, "INSERT INTO users (id, name, email) VALUES (1, 'Ada', 'ada@x')")
+
+      {:ok, stmt_id} = Native.prepare(state, "SELECT name FROM users WHERE id = :id")
+      {:ok, cursor_id} = Native.declare_stmt_cursor(state, stmt_id, %{id: 1})
+
+      assert {["name"], [["Ada"]], 1} = Native.fetch_cursor(state.conn_id, cursor_id, 10)
+
+      Native.close(cursor_id, :cursor_id)
+      Native.close_stmt(stmt_id)
+    end
+
+    test "empty results still report columns", %{state: state} do
+      {:ok, stmt_id} = Native.prepare(state, "SELECT id, email FROM users WHERE id = ?")
+      {:ok, cursor_id} = Native.declare_stmt_cursor(state, stmt_id, [999])
+
+      assert {["id", "email"], [], 0} = Native.fetch_cursor(state.conn_id, cursor_id, 10)
+
+      Native.close(cursor_id, :cursor_id)
+      Native.close_stmt(stmt_id)
+    end
+
+    test "rejects a statement owned by another connection", %{state: state} do
+      other_db = "z_ecto_libsql_test-prepared_other_#{:erlang.unique_integer([:positive])}.db"
+      other_conn_id = Native.connect([database: other_db], :local)
+
+      on_exit(fn ->
+        Native.close(other_conn_id, :conn_id)
+        EctoLibSql.TestHelpers.cleanup_db_files(other_db)
+      end)
+
+      {:ok, stmt_id} = Native.prepare(state, "SELECT 1")
+
+      assert {:error, "Statement does not belong to connection"} =
+               Native.declare_cursor_from_statement(other_conn_id, stmt_id, [])
+
+      Native.close_stmt(stmt_id)
+    end
+  end
+
   describe "statement parameter introspection" do
     test "parameter_count with named parameters", %{state: state} do
       # Test with colon-style named parameters (:name)
@@ -0,0 +1,33 @@
+defmodule EctoLibSql.QueryDigestTest do
+  @moduledoc """
+  Tests for statement shape digests used to group telemetry.
+  """
+  use ExUnit.Case, async: true
+
+  alias EctoLibSql.Native
+
+  test "queries differing only in literals share a digest" do
+    a = Native.query_digest("SELECT * FROM users WHERE name = 'Alice' AND age > 30")
+    b = Native.query_digest("select * from users\nwhere name = 'Bob' and age > 41.5")
+
+    assert a == b
+    assert a =~ ~r/\A[0-9a-f]{16}\z/
+  end
+
+  test "different shapes have different digests" do
+    refute Native.query_digest("SELECT * FROM users WHERE id = 1") ==
+             Native.query_digest("SELECT * FROM posts WHERE id = 1")
+  end
+
+  test "IN lists of any length share a digest" do
+    assert Native.query_digest("SELECT * FROM t WHERE id IN (1, 2, 3)") ==
+             Native.query_digest("SELECT * FROM t WHERE id IN (?)")
+  end
+
+  test "the shape does not contain literal values" do
+    shape = Native.query_shape("UPDATE users SET password = 'hunter2' WHERE id = 42")
+
+    assert shape == "update users set password=? where id=?"
+    refute shape =~ "hunter2"
+  end
+end
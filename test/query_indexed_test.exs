@@ -0,0 +1,61 @@
+defmodule EctoLibSql.QueryIndexedTest do
+  use ExUnit.Case
+
+  alias EctoLibSql.Native
+
+  setup do
+    db_file = "z_ecto_libsql_test-query_indexed_#{:erlang.unique_integer([:positive])}.db"
+    {:ok, state} = EctoLibSql.connect(database: db_file)
+
+    {:ok, _, _, state} =
+      EctoLibSql.handle_execute(
+        "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT, email TEXT)",
+        [],
+        [],
+        state
+      )
+
+    on_exit(fn ->
+      EctoLibSql.disconnect([], state)
+      EctoLibSql.TestHelpers.cleanup_db_files(db_file)
+    end)
+
+    {:ok, state: state}
+  end
+
+  test "binds ?2 before ?1", %{state: state} do
+    assert {:ok, %EctoLibSql.Result{rows: [["b", "a"]]}} =
+             Native.query_indexed(state, "SELECT ?2, ?1", [{2, "b"}, {1, "a"}])
+  end
+
+  test "reuses a placeholder bound once", %{state: state} do
+    assert {:ok, %EctoLibSql.Result{rows: [[6, 3]]}} =
+             Native.query_indexed(state, "SELECT ?1 + ?1, ?1", [{1, 3}])
+  end
+
+  test "leaves unbound parameters NULL", %{state: state} do
+    {:ok, %EctoLibSql.Result{num_rows: 1}} =
+      Native.query_indexed(
+        state,
+        "INSERT INTO users (id, name, email) VALUES (?1, ?2, ?3)",
+        [{3, "a@example.com"}, {1, 1}]
+      )
+
+    {:ok, _, result, _} =
+      EctoLibSql.handle_execute("SELECT id, name, email FROM users", [], [], state)
+
+    assert result.rows == [[1, nil, "a@example.com"]]
+  end
+
+  test "rejects an index out of range", %{state: state} do
+    assert {:error, reason} = Native.query_indexed(state, "SELECT ?1", [{2, "x"}])
+    assert reason =~ "out of range"
+
+    assert {:error, _} = Native.query_indexed(state, "SELECT ?1", [{0, "x"}])
+  end
+
+  test "rejects an index bound twice", %{state: state} do
+    assert {:error, reason} = Native.query_indexed(state, "SELECT ?1", [{1, "x"}, {1, "y"}])
+    assert reason =~ "more than once"
+  end
+end
@@ -0,0 +1,70 @@
+defmodule EctoLibSql.QueryMultipleTest do
+  use ExUnit.Case
+
+  alias EctoLibSql.Native
+
+  setup do
+    db_file = "z_ecto_libsql_test-query_multiple_#{:erlang.unique_integer([:positive])}.db"
+    {:ok, state} = EctoLibSql.connect(database: db_file)
+
+    {:ok, _, _, state} =
+      EctoLibSql.handle_execute(
+        "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT)",
+        [],
+        [],
+        state
+      )
+
+    {:ok, _, _, state} =
+      EctoLibSql.handle_execute(
+        "INSERT INTO users (name) VALUES ('Alice'), ('Bob'), ('Carol')",
+        [],
+        [],
+        state
+      )
+
+    on_exit(fn ->
+      EctoLibSql.disconnect([], state)
+      EctoLibSql.TestHelpers.cleanup_db_files(db_file)
+    end)
+
+    {:ok, state: state}
+  end
+
+  test "returns one result per query, in order", %{state: state} do
+    assert {:ok, [count, named, constant]} =
+             Native.query_multiple(state, [
+               {"SELECT count(*) FROM users", []},
+               {"SELECT name FROM users WHERE id > ? ORDER BY id", [1]},
+               {"SELECT 42 AS answer", []}
+             ])
+
+    assert count.rows == [[3]]
+    assert named.columns == ["name"]
+    assert named.rows == [["Bob"], ["Carol"]]
+    assert named.num_rows == 2
+    assert constant.rows == [[42]]
+  end
+
+  test "reports the index of a failing query", %{state: state} do
+    assert {:error, {1, reason}} =
+             Native.query_multiple(state, [
+               {"SELECT 1", []},
+               {"SELECT * FROM missing_table", []},
+               {"SELECT 2", []}
+             ])
+
+    assert reason =~ "missing_table"
+  end
+
+  test "rejects writes and leaves the connection writable", %{state: state} do
+    assert {:error, {0, _reason}} =
+             Native.query_multiple(state, [{"DELETE FROM users", []}])
+
+    {:ok, _, result, _} = EctoLibSql.handle_execute("SELECT count(*) FROM users", [], [], state)
+    assert result.rows == [[3]]
+
+    assert {:ok, _, _, _} =
+             EctoLibSql.handle_execute("INSERT INTO users (name) VALUES ('Dave')", [], [], state)
+  end
+end
@@ -0,0 +1,58 @@
+defmodule EctoLibSql.QueryNullabilityTest do
+  use ExUnit.Case
+
+  alias EctoLibSql.Native
+
+  setup do
+    db_file = "z_ecto_libsql_test-query_nullability_#{:erlang.unique_integer([:positive])}.db"
+    {:ok, state} = EctoLibSql.connect(database: db_file)
+
+    state =
+      Enum.reduce(
+        [
+          "CREATE TABLE people (id INTEGER PRIMARY KEY, name TEXT NOT NULL, nickname TEXT)",
+          "INSERT INTO people (name, nickname) VALUES ('Ada', NULL), ('Grace', 'Amazing')"
+        ],
+        state,
+        fn sql, state ->
+          {:ok, _, _, state} = EctoLibSql.handle_execute(sql, [], [], state)
+          state
+        end
+      )
+
+    on_exit(fn ->
+      EctoLibSql.disconnect([], state)
+      EctoLibSql.TestHelpers.cleanup_db_files(db_file)
+    end)
+
+    {:ok, state: state}
+  end
+
+  test "annotates NOT NULL and nullable columns", %{state: state} do
+    assert {:ok, %{columns: columns, rows: rows, num_rows: 2}} =
+             Native.query_nullability(state, "SELECT id, name, nickname FROM people ORDER BY id")
+
+    assert columns == [
+             %{name: "id", nullable: false},
+             %{name: "name", nullable: false},
+             %{name: "nickname", nullable: true}
+           ]
+
+    assert rows == [[1, "Ada", nil], [2, "Grace", "Amazing"]]
+  end
+
+  test "expressions and aggregates are unknown", %{state: state} do
+    assert {:ok, %{columns: columns}} =
+             Native.query_nullability(
+               state,
+               "SELECT name AS n, length(nickname), count(*) FROM people WHERE id > ?",
+               [0]
+             )
+
+    assert Enum.map(columns, & &1.nullable) == [false, :unknown, :unknown]
+  end
+
+  test "returns query errors", %{state: state} do
+    assert {:error, _} = Native.query_nullability(state, "SELECT * FROM missing")
+  end
+end
@@ -0,0 +1,67 @@
+defmodule EctoLibSql.QueryToArrowTest do
+  @moduledoc """
+  Tests for exporting query results as an Arrow IPC stream.
+  """
+  use ExUnit.Case
+
+  alias EctoLibSql.Native
+
+  @continuation <<0xFF, 0xFF, 0xFF, 0xFF>>
+
+  setup do
+    test_db = "z_ecto_libsql_test-query_to_arrow_#{:erlang.unique_integer([:positive])}.db"
+
+    {:ok, state} = EctoLibSql.connect(database: test_db)
+
+    {:ok, _, _, state} =
+      EctoLibSql.handle_execute(
+        "CREATE TABLE readings (id INTEGER PRIMARY KEY, label TEXT, value REAL)",
+        [],
+        [],
+        state
+      )
+
+    {:ok, _, _, state} =
+      EctoLibSql.handle_execute(
+        "INSERT INTO readings (label, value) VALUES ('first', 1.5), ('second', NULL)",
+        [],
+        [],
+        state
+      )
+
+    on_exit(fn ->
+      EctoLibSql.disconnect([], state)
+      EctoLibSql.TestHelpers.cleanup_db_files(test_db)
+    end)
+
+    {:ok, state: state}
+  end
+
+  test "returns a framed IPC stream containing the columns", %{state: state} do
+    assert {:ok, ipc} =
+             Native.query_to_arrow(state, "SELECT id, label, value FROM readings WHERE id > ?", [
+               0
+             ])
+
+    # Schema message first, end-of-stream marker last
+    assert <<@continuation, schema_len::little-32, _::binary>> = ipc
+    assert rem(schema_len, 8) == 0
+    assert binary_part(ipc, byte_size(ipc) - 8, 8) == @continuation <> <<0::32>>
+
+    for text <- ["id", "label", "value", "first", "second"] do
+      assert :binary.match(ipc, text) != :nomatch
+    end
+  end
+
+  test "rejects columns that mix storage classes", %{state: state} do
+    assert {:error, reason} =
+             Native.query_to_arrow(state, "SELECT label FROM readings UNION ALL SELECT 1")
+
+    assert reason =~ "mixes"
+  end
+
+  test "returns query errors", %{state: state} do
+    assert {:error, reason} = Native.query_to_arrow(state, "SELECT * FROM missing")
+    assert reason =~ "no such table"
+  end
+end
@@ -0,0 +1,65 @@
+defmodule EctoLibSql.QueryToEtsTest do
+  use ExUnit.Case
+
+  alias EctoLibSql.Native
+
+  setup do
+    db_file = "z_ecto_libsql_test-query_to_ets_#{:erlang.unique_integer([:positive])}.db"
+    {:ok, state} = EctoLibSql.connect(database: db_file)
+
+    state =
+      Enum.reduce(
+        [
+          "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT, email TEXT)",
+          "INSERT INTO users VALUES (1, 'Ada', 'ada@example.com'), (2, 'Grace', NULL)"
+        ],
+        state,
+        fn sql, state ->
+          {:ok, _, _, state} = EctoLibSql.handle_execute(sql, [], [], state)
+          state
+        end
+      )
+
+    on_exit(fn ->
+      EctoLibSql.disconnect([], state)
+      EctoLibSql.TestHelpers.cleanup_db_files(db_file)
+    end)
+
+    {:ok, state: state}
+  end
+
+  test "inserts each row as a tuple keyed by the first column", %{state: state} do
+    tid = :ets.new(:users, [:set, :public])
+
+    assert {:ok, 2} =
+             Native.query_to_ets(state, "SELECT id, name, email FROM users", [], tid, 0)
+
+    assert :ets.lookup(tid, 1) == [{1, "Ada", "ada@example.com"}]
+    assert :ets.lookup(tid, 2) == [{2, "Grace", nil}]
+  end
+
+  test "keys by another column when the table's keypos matches", %{state: state} do
+    tid = :ets.new(:users_by_name, [:set, :public, keypos: 2])
+
+    assert {:ok, 1} =
+             Native.query_to_ets(state, "SELECT id, name FROM users WHERE id = ?", [2], tid, 1)
+
+    assert :ets.lookup(tid, "Grace") == [{2, "Grace"}]
+  end
+
+  test "rejects a key_index that doesn't match the table's keypos", %{state: state} do
+    tid = :ets.new(:users, [:set, :public])
+
+    assert {:error, message} =
+             Native.query_to_ets(state, "SELECT id, name FROM users", [], tid, 1)
+
+    assert message =~ "keypos"
+    assert :ets.info(tid, :size) == 0
+  end
+
+  test "returns zero for an empty result", %{state: state} do
+    tid = :ets.new(:users, [:set, :public])
+
+    assert {:ok, 0} = Native.query_to_ets(state, "SELECT id FROM users WHERE id < 0", [], tid, 0)
+  end
+end
@@ -0,0 +1,73 @@
+defmodule EctoLibSql.QueryTransformTest do
+  use ExUnit.Case
+
+  alias EctoLibSql.Native
+
+  setup do
+    db_file = "z_ecto_libsql_test-query_transform_#{:erlang.unique_integer([:positive])}.db"
+    {:ok, state} = EctoLibSql.connect(database: db_file)
+
+    {:ok, _, _, state} =
+      EctoLibSql.handle_execute(
+        "CREATE TABLE users (id INTEGER PRIMARY KEY, email TEXT, note TEXT, avatar BLOB)",
+        [],
+        [],
+        state
+      )
+
+    {:ok, _, _, state} =
+      EctoLibSql.handle_execute(
+        "INSERT INTO users VALUES (1, 'Ada@Example.COM', '  hello  ', ?), (2, NULL, 'x', NULL)",
+        [<<255, 0>>],
+        [],
+        state
+      )
+
+    on_exit(fn ->
+      EctoLibSql.disconnect([], state)
+      EctoLibSql.TestHelpers.cleanup_db_files(db_file)
+    end)
+
+    {:ok, state: state}
+  end
+
+  describe "query_with_transforms/4" do
+    test "applies :lower to a text column", %{state: state} do
+      assert {:ok, result} =
+               Native.query_with_transforms(
+                 state,
+                 "SELECT id, email FROM users ORDER BY id",
+                 [],
+                 %{"email" => :lower}
+               )
+
+      assert result.columns == ["id", "email"]
+      assert result.rows == [[1, "ada@example.com"], [2, nil]]
+      assert result.num_rows == 2
+    end
+
+    test "applies several transforms at once", %{state: state} do
+      assert {:ok, result} =
+               Native.query_with_transforms(
+                 state,
+                 "SELECT email, note, avatar FROM users WHERE id = ?",
+                 [1],
+                 %{"email" => :upper, "note" => :trim, "avatar" => :base64}
+               )
+
+      assert result.rows == [["ADA@EXAMPLE.COM", "hello", "/wA="]]
+    end
+
+    test "rejects unknown columns and transforms", %{state: state} do
+      assert {:error, "Column not found in result: missing"} =
+               Native.query_with_transforms(state, "SELECT email FROM users", [], %{
+                 "missing" => :lower
+               })
+
+      assert {:error, "Unknown transform for column email"} =
+               Native.query_with_transforms(state, "SELECT email FROM users", [], %{
+                 "email" => :reverse
+               })
+    end
+  end
+end
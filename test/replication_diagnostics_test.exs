@@ -0,0 +1,63 @@
+defmodule EctoLibSql.ReplicationDiagnosticsTest do
+  use ExUnit.Case
+
+  alias EctoLibSql.Native
+
+  test "local connections are not replicas" do
+    db_file = "z_ecto_libsql_test-replication_diag_#{:erlang.unique_integer([:positive])}.db"
+    {:ok, state} = EctoLibSql.connect(database: db_file)
+
+    on_exit(fn ->
+      EctoLibSql.disconnect([], state)
+      EctoLibSql.TestHelpers.cleanup_db_files(db_file)
+    end)
+
+    assert {:error, :not_a_replica} = Native.replica_diagnostics(state)
+  end
+
+  describe "sync_replica_if_behind/2" do
+    setup do
+      db_file = "z_ecto_libsql_test-sync_if_behind_#{:erlang.unique_integer([:positive])}.db"
+      {:ok, state} = EctoLibSql.connect(database: db_file)
+
+      on_exit(fn ->
+        EctoLibSql.disconnect([], state)
+        EctoLibSql.TestHelpers.cleanup_db_files(db_file)
+      end)
+
+      {:ok, state: state}
+    end
+
+    test "rejects local connections", %{state: state} do
+      assert {:error, :not_a_replica} = Native.sync_replica_if_behind(state, 0)
+    end
+
+    test "rejects negative thresholds", %{state: state} do
+      assert_raise FunctionClauseError, fn -> Native.sync_replica_if_behind(state, -1) end
+    end
+  end
+
+  describe "pause_replica_sync/1 and resume_replica_sync/1" do
+    setup do
+      db_file = "z_ecto_libsql_test-pause_sync_#{:erlang.unique_integer([:positive])}.db"
+      {:ok, state} = EctoLibSql.connect(database: db_file)
+
+      on_exit(fn ->
+        EctoLibSql.disconnect([], state)
+        EctoLibSql.TestHelpers.cleanup_db_files(db_file)
+      end)
+
+      {:ok, state: state}
+    end
+
+    test "are unsupported and leave writes working", %{state: state} do
+      assert {:error, :unsupported} = Native.pause_replica_sync(state)
+
+      {:ok, _, _, state} = EctoLibSql.handle_execute("CREATE TABLE t (x INTEGER)", [], [], state)
+      {:ok, _, _, state} = EctoLibSql.handle_execute("INSERT INTO t VALUES (1)", [], [], state)
+
+      assert {:error, :unsupported} = Native.resume_replica_sync(state)
+      assert {:error, :not_a_replica} = Native.sync(state)
+    end
+  end
+end
@@ -0,0 +1,59 @@
+defmodule EctoLibSql.RowFingerprintTest do
+  use ExUnit.Case
+
+  alias EctoLibSql.Native
+
+  setup do
+    db_file = "z_ecto_libsql_test-row_fingerprint_#{:erlang.unique_integer([:positive])}.db"
+    {:ok, state} = EctoLibSql.connect(database: db_file)
+
+    {:ok, _, _, state} =
+      EctoLibSql.handle_execute(
+        "CREATE TABLE events (id INTEGER PRIMARY KEY, kind TEXT, payload BLOB, note TEXT)",
+        [],
+        [],
+        state
+      )
+
+    state =
+      Enum.reduce(
+        [[1, "click", <<1, 2>>, nil], [2, "click", <<1, 2>>, nil], [3, "click", <<1, 3>>, nil]],
+        state,
+        fn row, state ->
+          {:ok, _, _, state} =
+            EctoLibSql.handle_execute("INSERT INTO events VALUES (?, ?, ?, ?)", row, [], state)
+
+          state
+        end
+      )
+
+    on_exit(fn ->
+      EctoLibSql.disconnect([], state)
+      EctoLibSql.TestHelpers.cleanup_db_files(db_file)
+    end)
+
+    {:ok, state: state}
+  end
+
+  @sql "SELECT kind, payload, note FROM events WHERE id = ?"
+
+  test "identical rows fingerprint equal", %{state: state} do
+    assert {:ok, fingerprint} = Native.fingerprint_row(state, @sql, [1])
+    assert fingerprint =~ ~r/\A[0-9a-f]{16}\z/
+    assert {:ok, ^fingerprint} = Native.fingerprint_row(state, @sql, [2])
+  end
+
+  test "a differing row fingerprints differently", %{state: state} do
+    {:ok, first} = Native.fingerprint_row(state, @sql, [1])
+    assert {:ok, other} = Native.fingerprint_row(state, @sql, [3])
+    assert other != first
+  end
+
+  test "no rows gives nil", %{state: state} do
+    assert {:ok, nil} = Native.fingerprint_row(state, @sql, [99])
+  end
+
+  test "a failing query returns an error", %{state: state} do
+    assert {:error, _} = Native.fingerprint_row(state, "SELECT * FROM missing")
+  end
+end
@@ -491,4 +491,53 @@ defmodule EctoLibSql.SavepointTest do
       assert hd(result.rows) == [1, "Alice"]
     end
   end
+
+  describe "transaction depth" do
+    test "is 0 without a transaction and 1 inside one", %{state: state} do
+      assert Native.get_transaction_depth(state) == 0
+
+      {:ok, trx_state} = Native.begin(state)
+      assert Native.get_transaction_depth(trx_state) == 1
+
+      {:ok, _} = Native.commit(trx_state)
+      assert Native.get_transaction_depth(state) == 0
+    end
+
+    test "follows savepoints as they are created and released", %{state: state} do
+      {:ok, trx_state} = Native.begin(state)
+
+      :ok = Native.create_savepoint(trx_state, "sp1")
+      assert Native.get_transaction_depth(trx_state) == 2
+
+      :ok = Native.create_savepoint(trx_state, "sp2")
+      assert Native.get_transaction_depth(trx_state) == 3
+
+      :ok = Native.release_savepoint_by_name(trx_state, "sp2")
+      assert Native.get_transaction_depth(trx_state) == 2
+
+      :ok = Native.release_savepoint_by_name(trx_state, "sp1")
+      assert Native.get_transaction_depth(trx_state) == 1
+
+      {:ok, _} = Native.rollback(trx_state)
+      assert Native.get_transaction_depth(state) == 0
+    end
+
+    test "releasing an outer savepoint closes the inner ones", %{state: state} do
+      {:ok, trx_state} = Native.begin(state)
+
+      :ok = Native.create_savepoint(trx_state, "sp1")
+      :ok = Native.create_savepoint(trx_state, "sp2")
+      :ok = Native.create_savepoint(trx_state, "sp3")
+      assert Native.get_transaction_depth(trx_state) == 4
+
+      # Rolling back keeps the target savepoint open
+      :ok = Native.rollback_to_savepoint_by_name(trx_state, "sp2")
+      assert Native.get_transaction_depth(trx_state) == 3
+
+      :ok = Native.release_savepoint_by_name(trx_state, "sp1")
+      assert Native.get_transaction_depth(trx_state) == 1
+
+      {:ok, _} = Native.rollback(trx_state)
+    end
+  end
 end
@@ -0,0 +1,70 @@
+defmodule EctoLibSql.SessionTest do
+  use ExUnit.Case
+
+  alias EctoLibSql.Native
+
+  setup do
+    db_file = "z_ecto_libsql_test-session_#{:erlang.unique_integer([:positive])}.db"
+    {:ok, state} = EctoLibSql.connect(database: db_file)
+
+    {:ok, _, _, state} =
+      EctoLibSql.handle_execute(
+        "CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT)",
+        [],
+        [],
+        state
+      )
+
+    on_exit(fn ->
+      EctoLibSql.disconnect([], state)
+      EctoLibSql.TestHelpers.cleanup_db_files(db_file)
+    end)
+
+    {:ok, state: state}
+  end
+
+  describe "apply_changeset" do
+    test "reports that the session extension is not available", %{state: state} do
+      for policy <- [:abort, :replace, :omit] do
+        assert {:error, :session_not_available} =
+                 Native.apply_changeset(state, <<0x54, 0x02, 0x00>>, policy)
+      end
+
+      assert {:error, :session_not_available} = Native.apply_changeset(state, <<>>)
+    end
+
+    test "leaves the database unchanged", %{state: state} do
+      Native.apply_changeset(state, <<0x54, 0x02, 0x00>>)
+
+      {:ok, _, result, _state} =
+        EctoLibSql.handle_execute("SELECT count(*) FROM items", [], [], state)
+
+      assert result.rows == [[0]]
+    end
+
+    test "rejects unknown conflict policies", %{state: state} do
+      assert_raise FunctionClauseError, fn ->
+        Native.apply_changeset(state, <<>>, :ignore)
+      end
+    end
+  end
+
+  describe "capture_changeset" do
+    test "reports that the session extension is not available", %{state: state} do
+      assert {:error, :session_not_available} =
+               Native.capture_changeset(state, [
+                 "INSERT INTO items (name) VALUES ('a')",
+                 "INSERT INTO items (name) VALUES ('b')"
+               ])
+    end
+
+    test "does not run the statements", %{state: state} do
+      Native.capture_changeset(state, ["INSERT INTO items (name) VALUES ('a')"])
+
+      {:ok, _, result, _state} =
+        EctoLibSql.handle_execute("SELECT count(*) FROM items", [], [], state)
+
+      assert result.rows == [[0]]
+    end
+  end
+end
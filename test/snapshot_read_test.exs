@@ -0,0 +1,98 @@
+defmodule EctoLibSql.SnapshotReadTest do
+  use ExUnit.Case
+
+  alias EctoLibSql.Native
+
+  setup do
+    db_file = "z_ecto_libsql_test-snapshot_read_#{:erlang.unique_integer([:positive])}.db"
+    {:ok, state} = EctoLibSql.connect(database: db_file)
+    {:ok, _} = EctoLibSql.Pragma.set_journal_mode(state, :wal)
+
+    for sql <- [
+          "CREATE TABLE checking (id INTEGER PRIMARY KEY, balance INTEGER)",
+          "CREATE TABLE savings (id INTEGER PRIMARY KEY, balance INTEGER)",
+          "INSERT INTO checking (id, balance) VALUES (1, 1000)",
+          "INSERT INTO savings (id, balance) VALUES (1, 0)"
+        ] do
+      {:ok, _, _, _} = EctoLibSql.handle_execute(sql, [], [], state)
+    end
+
+    on_exit(fn ->
+      EctoLibSql.disconnect([], state)
+      EctoLibSql.TestHelpers.cleanup_db_files(db_file)
+    end)
+
+    {:ok, state: state, db_file: db_file}
+  end
+
+  test "returns a result per named query", %{state: state} do
+    assert {:ok, %{"checking" => checking, savings: savings}} =
+             Native.snapshot_read(state, [
+               {"checking", "SELECT balance FROM checking WHERE id = ?", [1]},
+               {:savings, "SELECT balance FROM savings", []}
+             ])
+
+    assert %EctoLibSql.Result{columns: ["balance"], rows: [[1000]], num_rows: 1} = checking
+    assert %EctoLibSql.Result{rows: [[0]]} = savings
+  end
+
+  test "stays consistent while a concurrent writer moves balances", %{
+    state: state,
+    db_file: db_file
+  } do
+    {:ok, writer} = EctoLibSql.connect(database: db_file)
+
+    # Each transfer keeps checking + savings at 1000, but a read that straddled one would
+    # see the money in both tables or in neither.
+    transfers =
+      Task.async(fn ->
+        for _ <- 1..200 do
+          {:ok, trx_state} = Native.begin(writer, behavior: :immediate)
+
+          {:ok, _, _, trx_state} =
+            EctoLibSql.handle_execute(
+              "UPDATE checking SET balance = balance - 1 WHERE id = 1",
+              [],
+              [],
+              trx_state
+            )
+
+          {:ok, _, _, trx_state} =
+            EctoLibSql.handle_execute(
+              "UPDATE savings SET balance = balance + 1 WHERE id = 1",
+              [],
+              [],
+              trx_state
+            )
+
+          {:ok, _} = Native.commit(trx_state)
+        end
+      end)
+
+    totals =
+      for _ <- 1..200 do
+        {:ok, %{checking: checking, savings: savings}} =
+          Native.snapshot_read(state, [
+            {:checking, "SELECT balance FROM checking WHERE id = 1", []},
+            {:savings, "SELECT balance FROM savings WHERE id = 1", []}
+          ])
+
+        [[c]] = checking.rows
+        [[s]] = savings.rows
+        c + s
+      end
+
+    Task.await(transfers, 30_000)
+    EctoLibSql.disconnect([], writer)
+
+    assert Enum.all?(totals, &(&1 == 1000))
+  end
+
+  test "fails as a whole when one query fails", %{state: state} do
+    assert {:error, _} =
+             Native.snapshot_read(state, [
+               {:checking, "SELECT balance FROM checking", []},
+               {:missing, "SELECT * FROM missing", []}
+             ])
+  end
+end
@@ -0,0 +1,38 @@
+defmodule EctoLibSql.SoftHeapLimitTest do
+  # The limit is process-global, so don't run alongside other tests.
+  use ExUnit.Case, async: false
+
+  alias EctoLibSql.Native
+
+  setup do
+    {:ok, previous} = Native.soft_heap_limit()
+    on_exit(fn -> Native.put_soft_heap_limit(previous) end)
+    :ok
+  end
+
+  test "sets a limit and reads it back" do
+    assert :ok = Native.put_soft_heap_limit(32 * 1024 * 1024)
+    assert {:ok, 33_554_432} = Native.soft_heap_limit()
+  end
+
+  test "zero removes the limit" do
+    assert :ok = Native.put_soft_heap_limit(1024 * 1024)
+    assert :ok = Native.put_soft_heap_limit(0)
+    assert {:ok, 0} = Native.soft_heap_limit()
+  end
+
+  test "applies to connections opened afterwards" do
+    assert :ok = Native.put_soft_heap_limit(16 * 1024 * 1024)
+
+    db_file = "z_ecto_libsql_test-soft_heap_limit_#{:erlang.unique_integer([:positive])}.db"
+    {:ok, state} = EctoLibSql.connect(database: db_file)
+
+    on_exit(fn ->
+      EctoLibSql.disconnect([], state)
+      EctoLibSql.TestHelpers.cleanup_db_files(db_file)
+    end)
+
+    {:ok, _, result, _state} = EctoLibSql.handle_execute("PRAGMA soft_heap_limit", [], [], state)
+    assert result.rows == [[16_777_216]]
+  end
+end
@@ -0,0 +1,55 @@
+defmodule EctoLibSql.SqlLiteralTest do
+  @moduledoc """
+  Tests for rendering values as SQLite literals with `Native.to_sql_literal/1`.
+  """
+  use ExUnit.Case, async: true
+
+  alias EctoLibSql.Native
+
+  test "renders nil as NULL" do
+    assert Native.to_sql_literal(nil) == {:ok, "NULL"}
+  end
+
+  test "renders numbers bare" do
+    assert Native.to_sql_literal(42) == {:ok, "42"}
+    assert Native.to_sql_literal(-7) == {:ok, "-7"}
+    assert Native.to_sql_literal(1.0) == {:ok, "1.0"}
+    assert Native.to_sql_literal(2.5e-3) == {:ok, "0.0025"}
+  end
+
+  test "renders booleans as integers" do
+    assert Native.to_sql_literal(true) == {:ok, "1"}
+    assert Native.to_sql_literal(false) == {:ok, "0"}
+  end
+
+  test "quotes text and doubles embedded quotes" do
+    assert Native.to_sql_literal("plain") == {:ok, "'plain'"}
+    assert Native.to_sql_literal("O'Brien's") == {:ok, "'O''Brien''s'"}
+    assert Native.to_sql_literal("") == {:ok, "''"}
+  end
+
+  test "renders blobs in hex notation" do
+    assert Native.to_sql_literal({:blob, <<0, 0xAB, 0xFF>>}) == {:ok, "X'00ABFF'"}
+    assert Native.to_sql_literal(<<0xFF, 0xFE>>) == {:ok, "X'FFFE'"}
+  end
+
+  test "renders maps as JSON text" do
+    assert Native.to_sql_literal(%{"name" => "it's"}) == {:ok, ~s('{"name":"it''s"}')}
+  end
+
+  test "the literal round-trips through SQLite" do
+    test_db = "z_ecto_libsql_test-sql_literal_#{:erlang.unique_integer([:positive])}.db"
+    {:ok, state} = EctoLibSql.connect(database: test_db)
+
+    values = ["a 'quoted' \\ value", 12, 3.5, nil, {:blob, <<1, 2, 3>>}]
+    literals = Enum.map(values, fn v -> v |> Native.to_sql_literal() |> elem(1) end)
+
+    {:ok, _, %EctoLibSql.Result{rows: [row]}, _} =
+      EctoLibSql.handle_execute("SELECT " <> Enum.join(literals, ", "), [], [], state)
+
+    assert row == ["a 'quoted' \\ value", 12, 3.5, nil, <<1, 2, 3>>]
+
+    EctoLibSql.disconnect([], state)
+    EctoLibSql.TestHelpers.cleanup_db_files(test_db)
+  end
+end
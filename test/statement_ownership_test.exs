@@ -109,7 +109,7 @@ defmodule EctoLibSql.StatementOwnershipTest do
       result =
         Native.execute_with_transaction(trx_id, conn_id2, "INSERT INTO test (value) VALUES (?)", [
           "test"
-        ])
+        ], false)
 
       assert {:error, msg} = result
       assert msg =~ "does not belong to this connection"
@@ -136,7 +136,7 @@ defmodule EctoLibSql.StatementOwnershipTest do
       trx_id = trx_state.trx_id
 
       # Try to query in transaction from connection 2 - should fail
-      result = Native.query_with_trx_args(trx_id, conn_id2, "SELECT * FROM test", [])
+      result = Native.query_with_trx_args(trx_id, conn_id2, "SELECT * FROM test", [], nil)
       assert {:error, msg} = result
       assert msg =~ "does not belong to this connection"
 
@@ -166,12 +166,12 @@ defmodule EctoLibSql.StatementOwnershipTest do
       num_rows =
         Native.execute_with_transaction(trx_id, conn_id1, "INSERT INTO test (value) VALUES (?)", [
           "value1"
-        ])
+        ], false)
 
       assert is_integer(num_rows) and num_rows >= 0
 
       # Query in transaction with correct connection
-      result = Native.query_with_trx_args(trx_id, conn_id1, "SELECT * FROM test", [])
+      result = Native.query_with_trx_args(trx_id, conn_id1, "SELECT * FROM test", [], nil)
 
       assert %{
                "columns" => ["id", "value"],
@@ -215,7 +215,8 @@ defmodule EctoLibSql.StatementOwnershipTest do
           state1.conn_id,
           :connection,
           "SELECT * FROM test",
-          []
+          [],
+          nil
         )
 
       true = is_binary(cursor_id) and byte_size(cursor_id) > 0
@@ -260,7 +261,8 @@ defmodule EctoLibSql.StatementOwnershipTest do
           conn_id1,
           :connection,
           "SELECT * FROM test",
-          []
+          [],
+          nil
         )
 
       true = is_binary(cursor_id) and byte_size(cursor_id) > 0
@@ -319,7 +321,8 @@ defmodule EctoLibSql.StatementOwnershipTest do
           trx_id,
           :transaction,
           "SELECT * FROM test",
-          []
+          [],
+          nil
         )
 
       assert {:error, msg} = result
@@ -332,7 +335,8 @@ defmodule EctoLibSql.StatementOwnershipTest do
           trx_id,
           :transaction,
           "SELECT * FROM test",
-          []
+          [],
+          nil
         )
 
       assert is_binary(result2)
@@ -364,7 +368,8 @@ defmodule EctoLibSql.StatementOwnershipTest do
           conn_id1,
           :connection,
           "SELECT * FROM test",
-          []
+          [],
+          nil
         )
 
       assert {:error, msg} = result
@@ -377,7 +382,8 @@ defmodule EctoLibSql.StatementOwnershipTest do
           conn_id1,
           :connection,
           "SELECT * FROM test",
-          []
+          [],
+          nil
         )
 
       assert is_binary(result2)
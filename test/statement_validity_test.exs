@@ -0,0 +1,65 @@
+defmodule EctoLibSql.StatementValidityTest do
+  use ExUnit.Case
+
+  alias EctoLibSql.Native
+
+  setup do
+    db_file = "z_ecto_libsql_test-statement_validity_#{:erlang.unique_integer([:positive])}.db"
+    {:ok, state} = EctoLibSql.connect(database: db_file)
+
+    {:ok, _, _, state} =
+      EctoLibSql.handle_execute(
+        "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT)",
+        [],
+        [],
+        state
+      )
+
+    on_exit(fn ->
+      EctoLibSql.disconnect([], state)
+      EctoLibSql.TestHelpers.cleanup_db_files(db_file)
+    end)
+
+    {:ok, state: state}
+  end
+
+  test "a fresh statement is valid", %{state: state} do
+    {:ok, stmt_id} = Native.prepare(state, "SELECT * FROM users")
+    assert {:ok, true} = Native.stmt_valid(state, stmt_id)
+    assert {:ok, :valid} = Native.revalidate_stmt(state, stmt_id)
+  end
+
+  test "altering the table makes the statement invalid", %{state: state} do
+    {:ok, stmt_id} = Native.prepare(state, "SELECT * FROM users")
+
+    {:ok, _, _, state} =
+      EctoLibSql.handle_execute("ALTER TABLE users ADD COLUMN email TEXT", [], [], state)
+
+    assert {:ok, false} = Native.stmt_valid(state, stmt_id)
+  end
+
+  test "revalidating re-prepares a stale statement under the same ID", %{state: state} do
+    {:ok, stmt_id} = Native.prepare(state, "SELECT * FROM users")
+
+    {:ok, _, _, state} =
+      EctoLibSql.handle_execute("ALTER TABLE users ADD COLUMN email TEXT", [], [], state)
+
+    assert {:ok, :reprepared} = Native.revalidate_stmt(state, stmt_id)
+    assert {:ok, true} = Native.stmt_valid(state, stmt_id)
+    assert {:ok, %EctoLibSql.Result{columns: ["id", "name", "email"]}} =
+             Native.query_stmt(state, stmt_id, [])
+  end
+
+  test "revalidating fails when the SQL no longer prepares", %{state: state} do
+    {:ok, stmt_id} = Native.prepare(state, "SELECT name FROM users")
+
+    {:ok, _, _, state} =
+      EctoLibSql.handle_execute("ALTER TABLE users DROP COLUMN name", [], [], state)
+
+    assert {:error, _} = Native.revalidate_stmt(state, stmt_id)
+  end
+
+  test "rejects unknown statements", %{state: state} do
+    assert {:error, "Statement not found"} = Native.stmt_valid(state, "missing")
+  end
+end
@@ -0,0 +1,85 @@
+defmodule EctoLibSql.TableMigrationTest do
+  use ExUnit.Case
+
+  alias EctoLibSql.Native
+
+  setup do
+    db_file = "z_ecto_libsql_test-table_migration_#{:erlang.unique_integer([:positive])}.db"
+    {:ok, state} = EctoLibSql.connect(database: db_file)
+    :ok = EctoLibSql.Pragma.enable_foreign_keys(state)
+
+    state =
+      Enum.reduce(
+        [
+          "CREATE TABLE authors (id INTEGER PRIMARY KEY, name TEXT)",
+          "CREATE TABLE books (id INTEGER PRIMARY KEY, author_id INTEGER REFERENCES authors (id), price TEXT)",
+          "INSERT INTO authors VALUES (1, 'a'), (2, 'b')",
+          "INSERT INTO books VALUES (1, 1, '9.5'), (2, 2, '12')"
+        ],
+        state,
+        fn sql, state ->
+          {:ok, _, _, state} = EctoLibSql.handle_execute(sql, [], [], state)
+          state
+        end
+      )
+
+    on_exit(fn ->
+      EctoLibSql.disconnect([], state)
+      EctoLibSql.TestHelpers.cleanup_db_files(db_file)
+    end)
+
+    {:ok, state: state}
+  end
+
+  @recreate_books [
+    "CREATE TABLE books_new (id INTEGER PRIMARY KEY, author_id INTEGER REFERENCES authors (id), price REAL)",
+    "INSERT INTO books_new SELECT id, author_id, CAST(price AS REAL) FROM books",
+    "DROP TABLE books",
+    "ALTER TABLE books_new RENAME TO books"
+  ]
+
+  defp rows(state, sql) do
+    {:ok, _, result, _} = EctoLibSql.handle_execute(sql, [], [], state)
+    result.rows
+  end
+
+  test "changes a column type by recreating the table", %{state: state} do
+    assert :ok = Native.run_table_migration(state, @recreate_books)
+
+    assert rows(state, "SELECT type FROM pragma_table_info('books') WHERE name = 'price'") ==
+             [["REAL"]]
+
+    assert rows(state, "SELECT price FROM books ORDER BY id") == [[9.5], [12.0]]
+    assert rows(state, "PRAGMA foreign_keys") == [[1]]
+  end
+
+  test "rolls back and reports foreign key violations", %{state: state} do
+    statements = @recreate_books ++ ["DELETE FROM authors WHERE id = 2"]
+
+    assert {:error, {:foreign_key_violations, [violation]}} =
+             Native.run_table_migration(state, statements)
+
+    assert %{table: "books", rowid: 2, parent: "authors"} = violation
+
+    assert rows(state, "SELECT type FROM pragma_table_info('books') WHERE name = 'price'") ==
+             [["TEXT"]]
+
+    assert rows(state, "SELECT count(*) FROM authors") == [[2]]
+    assert rows(state, "PRAGMA foreign_keys") == [[1]]
+  end
+
+  test "rolls back and restores foreign keys when a statement fails", %{state: state} do
+    assert {:error, reason} =
+             Native.run_table_migration(state, ["DROP TABLE books", "SELECT * FROM missing"])
+
+    assert reason =~ "Statement 1 failed"
+    assert rows(state, "SELECT count(*) FROM books") == [[2]]
+    assert rows(state, "PRAGMA foreign_keys") == [[1]]
+  end
+
+  test "refuses to run inside a transaction", %{state: state} do
+    {:ok, trx_state} = Native.begin(state)
+    assert {:error, _} = Native.run_table_migration(trx_state, @recreate_books)
+    {:ok, _} = Native.rollback(trx_state)
+  end
+end
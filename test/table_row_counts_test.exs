@@ -0,0 +1,49 @@
+defmodule EctoLibSql.TableRowCountsTest do
+  use ExUnit.Case
+
+  alias EctoLibSql.Native
+
+  setup do
+    db_file = "z_ecto_libsql_test-table_row_counts_#{:erlang.unique_integer([:positive])}.db"
+    {:ok, state} = EctoLibSql.connect(database: db_file)
+
+    state =
+      Enum.reduce(
+        [
+          "CREATE TABLE users (id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT)",
+          "CREATE TABLE posts (id INTEGER PRIMARY KEY, title TEXT)",
+          "INSERT INTO users (name) VALUES ('a'), ('b'), ('c')",
+          "INSERT INTO posts (title) VALUES ('x'), ('y')"
+        ],
+        state,
+        fn sql, state ->
+          {:ok, _, _, state} = EctoLibSql.handle_execute(sql, [], [], state)
+          state
+        end
+      )
+
+    on_exit(fn ->
+      EctoLibSql.disconnect([], state)
+      EctoLibSql.TestHelpers.cleanup_db_files(db_file)
+    end)
+
+    {:ok, state: state}
+  end
+
+  test "returns exact counts for user tables only", %{state: state} do
+    assert {:ok, %{"users" => 3, "posts" => 2} = counts} = Native.get_table_row_counts(state)
+    assert map_size(counts) == 2
+  end
+
+  test "approximate counts use ANALYZE statistics", %{state: state} do
+    {:ok, _, _, state} = EctoLibSql.handle_execute("ANALYZE", [], [], state)
+
+    {:ok, _, _, state} =
+      EctoLibSql.handle_execute("INSERT INTO users (name) VALUES ('d')", [], [], state)
+
+    assert {:ok, %{"users" => 3, "posts" => 2}} =
+             Native.get_table_row_counts(state, approximate: true)
+
+    assert {:ok, %{"users" => 4}} = Native.get_table_row_counts(state)
+  end
+end
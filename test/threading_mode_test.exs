@@ -0,0 +1,26 @@
+defmodule EctoLibSql.ThreadingModeTest do
+  use ExUnit.Case
+
+  alias EctoLibSql.Native
+
+  setup do
+    db_file = "z_ecto_libsql_test-threading_mode_#{:erlang.unique_integer([:positive])}.db"
+    {:ok, state} = EctoLibSql.connect(database: db_file)
+
+    on_exit(fn ->
+      EctoLibSql.disconnect([], state)
+      EctoLibSql.TestHelpers.cleanup_db_files(db_file)
+    end)
+
+    {:ok, state: state}
+  end
+
+  test "returns a known threading mode", %{state: state} do
+    assert {:ok, mode} = Native.threading_mode(state)
+    assert mode in [:serialized, :multi_thread, :single_thread]
+  end
+
+  test "libsql's SQLite build is serialized", %{state: state} do
+    assert {:ok, :serialized} = Native.threading_mode(state)
+  end
+end
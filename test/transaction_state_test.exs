@@ -0,0 +1,52 @@
+defmodule EctoLibSql.TransactionStateTest do
+  use ExUnit.Case
+
+  alias EctoLibSql.Native
+
+  setup do
+    db_file = "z_ecto_libsql_test-transaction_state_#{:erlang.unique_integer([:positive])}.db"
+    {:ok, state} = EctoLibSql.connect(database: db_file)
+
+    on_exit(fn ->
+      EctoLibSql.disconnect([], state)
+      EctoLibSql.TestHelpers.cleanup_db_files(db_file)
+    end)
+
+    {:ok, state: state}
+  end
+
+  test "is consistent outside any transaction", %{state: state} do
+    assert Native.get_transaction_state(state) == %{
+             sqlite_autocommit: true,
+             registry_has_txn: false,
+             consistent: true
+           }
+  end
+
+  test "is consistent inside a transaction begun with begin/2", %{state: state} do
+    {:ok, trx_state} = Native.begin(state)
+
+    assert Native.get_transaction_state(trx_state) == %{
+             sqlite_autocommit: false,
+             registry_has_txn: true,
+             consistent: true
+           }
+
+    {:ok, _} = Native.rollback(trx_state)
+    assert %{consistent: true, registry_has_txn: false} = Native.get_transaction_state(state)
+  end
+
+  test "reports a raw BEGIN that bypassed the registry", %{state: state} do
+    {:ok, _, _, state} = EctoLibSql.handle_execute("BEGIN", [], [], state)
+
+    assert Native.get_transaction_state(state) == %{
+             sqlite_autocommit: false,
+             registry_has_txn: false,
+             consistent: false
+           }
+
+    # Recover by ending the stray transaction
+    {:ok, _, _, state} = EctoLibSql.handle_execute("ROLLBACK", [], [], state)
+    assert %{consistent: true} = Native.get_transaction_state(state)
+  end
+end
@@ -0,0 +1,69 @@
+defmodule EctoLibSql.TriggersDisabledTest do
+  @moduledoc """
+  Tests for bulk loading with a table's triggers disabled.
+  """
+  use ExUnit.Case
+
+  alias EctoLibSql.Native
+
+  setup do
+    test_db = "z_ecto_libsql_test-triggers_disabled_#{:erlang.unique_integer([:positive])}.db"
+
+    {:ok, state} = EctoLibSql.connect(database: test_db)
+
+    for sql <- [
+          "CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT NOT NULL)",
+          "CREATE TABLE audit (item_id INTEGER)",
+          """
+          CREATE TRIGGER items_audit AFTER INSERT ON items
+          BEGIN INSERT INTO audit (item_id) VALUES (NEW.id); END
+          """
+        ] do
+      {:ok, _, _, _} = EctoLibSql.handle_execute(sql, [], [], state)
+    end
+
+    on_exit(fn ->
+      EctoLibSql.disconnect([], state)
+      EctoLibSql.TestHelpers.cleanup_db_files(test_db)
+    end)
+
+    {:ok, state: state}
+  end
+
+  defp scalar(state, sql) do
+    {:ok, _, %EctoLibSql.Result{rows: [[value]]}, _} =
+      EctoLibSql.handle_execute(sql, [], [], state)
+
+    value
+  end
+
+  test "loads rows without firing triggers and restores them", %{state: state} do
+    assert {:ok, [2, 1]} =
+             Native.with_triggers_disabled(state, "items", [
+               {"INSERT INTO items (name) VALUES (?), (?)", ["a", "b"]},
+               {"INSERT INTO items (name) VALUES (?)", ["c"]}
+             ])
+
+    assert scalar(state, "SELECT count(*) FROM items") == 3
+    assert scalar(state, "SELECT count(*) FROM audit") == 0
+
+    assert scalar(state, "SELECT count(*) FROM sqlite_master WHERE name = 'items_audit'") == 1
+
+    {:ok, _, _, _} =
+      EctoLibSql.handle_execute("INSERT INTO items (name) VALUES ('d')", [], [], state)
+
+    assert scalar(state, "SELECT count(*) FROM audit") == 1
+  end
+
+  test "restores triggers and rolls back when a statement fails", %{state: state} do
+    assert {:error, _reason} =
+             Native.with_triggers_disabled(state, "items", [
+               {"INSERT INTO items (name) VALUES (?)", ["a"]},
+               {"INSERT INTO items (name) VALUES (?)", [nil]}
+             ])
+
+    assert scalar(state, "SELECT count(*) FROM items") == 0
+
+    assert scalar(state, "SELECT count(*) FROM sqlite_master WHERE name = 'items_audit'") == 1
+  end
+end
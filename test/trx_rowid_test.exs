@@ -0,0 +1,70 @@
+defmodule EctoLibSql.TrxRowidTest do
+  use ExUnit.Case
+
+  alias EctoLibSql.Native
+
+  setup do
+    db_file = "z_ecto_libsql_test-trx_rowid_#{:erlang.unique_integer([:positive])}.db"
+    {:ok, state} = EctoLibSql.connect(database: db_file)
+
+    {:ok, _, _, state} =
+      EctoLibSql.handle_execute(
+        "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT)",
+        [],
+        [],
+        state
+      )
+
+    on_exit(fn ->
+      EctoLibSql.disconnect([], state)
+      EctoLibSql.TestHelpers.cleanup_db_files(db_file)
+    end)
+
+    {:ok, state: state}
+  end
+
+  test "returns the rowid of an insert before commit", %{state: state} do
+    {:ok, trx_state} = Native.begin(state)
+
+    assert {:ok, {1, 1}} =
+             Native.execute_in_trx_with_rowid(
+               trx_state,
+               "INSERT INTO users (name) VALUES (?)",
+               ["Ada"]
+             )
+
+    assert {:ok, {1, 42}} =
+             Native.execute_in_trx_with_rowid(
+               trx_state,
+               "INSERT INTO users (id, name) VALUES (?, ?)",
+               [42, "Grace"]
+             )
+
+    {:ok, _} = Native.commit(trx_state)
+
+    {:ok, _, result, _} =
+      EctoLibSql.handle_execute("SELECT id, name FROM users ORDER BY id", [], [], state)
+
+    assert result.rows == [[1, "Ada"], [42, "Grace"]]
+  end
+
+  test "plain execute_with_transaction still returns the affected count", %{state: state} do
+    {:ok, trx_state} = Native.begin(state)
+
+    assert 1 =
+             Native.execute_with_transaction(
+               trx_state.trx_id,
+               state.conn_id,
+               "INSERT INTO users (name) VALUES ('Ada')",
+               [],
+               false
+             )
+
+    {:ok, _} = Native.rollback(trx_state)
+  end
+
+  test "requires a transaction", %{state: state} do
+    assert {:error, _} =
+             Native.execute_in_trx_with_rowid(state, "INSERT INTO users (name) VALUES ('x')", [])
+  end
+end
@@ -719,6 +719,34 @@ defmodule TursoRemoteTest do
   end
 
   describe "embedded replica with sync" do
+    test "reports replication diagnostics" do
+      local_db = "z_ecto_libsql_test-replica_diag_#{:erlang.unique_integer([:positive])}.db"
+
+      on_exit(fn ->
+        cleanup_local_db(local_db)
+      end)
+
+      {:ok, replica_state} =
+        EctoLibSql.connect(
+          database: local_db,
+          uri: @turso_uri,
+          auth_token: @turso_token,
+          sync: true
+        )
+
+      assert {:ok,
+              %{
+                current_frame: current_frame,
+                max_write_frame: max_write_frame,
+                durable_frame: nil
+              }} = EctoLibSql.Native.replica_diagnostics(replica_state)
+
+      assert is_integer(current_frame) and current_frame >= 0
+      assert is_integer(max_write_frame) and max_write_frame >= 0
+
+      EctoLibSql.disconnect([], replica_state)
+    end
+
     test "automatic sync from local to remote", %{table_name: table} do
       # Create unique local database file for this test
       local_db = "z_ecto_libsql_test-replica_#{:erlang.unique_integer([:positive])}.db"
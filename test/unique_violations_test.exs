@@ -0,0 +1,75 @@
+defmodule EctoLibSql.UniqueViolationsTest do
+  use ExUnit.Case
+
+  alias EctoLibSql.Native
+
+  setup do
+    db_file = "z_ecto_libsql_test-unique_violations_#{:erlang.unique_integer([:positive])}.db"
+    {:ok, state} = EctoLibSql.connect(database: db_file)
+
+    {:ok, _, _, state} =
+      EctoLibSql.handle_execute(
+        "CREATE TABLE users (id INTEGER PRIMARY KEY, org_id INTEGER, email TEXT)",
+        [],
+        [],
+        state
+      )
+
+    state =
+      Enum.reduce(
+        [
+          [1, "a@x"],
+          [1, "a@x"],
+          [1, "a@x"],
+          [1, "b@x"],
+          [1, "b@x"],
+          [2, "a@x"],
+          [1, nil],
+          [1, nil]
+        ],
+        state,
+        fn row, state ->
+          {:ok, _, _, state} =
+            EctoLibSql.handle_execute(
+              "INSERT INTO users (org_id, email) VALUES (?, ?)",
+              row,
+              [],
+              state
+            )
+
+          state
+        end
+      )
+
+    on_exit(fn ->
+      EctoLibSql.disconnect([], state)
+      EctoLibSql.TestHelpers.cleanup_db_files(db_file)
+    end)
+
+    {:ok, state: state}
+  end
+
+  test "reports duplicated composite keys with counts", %{state: state} do
+    assert {:ok, duplicates} = Native.unique_violations(state, "users", ["org_id", "email"])
+
+    assert duplicates == [
+             %{key: [1, "a@x"], count: 3},
+             %{key: [1, "b@x"], count: 2}
+           ]
+  end
+
+  test "single-column keys", %{state: state} do
+    assert {:ok, [%{key: [1], count: 7}]} =
+             Native.unique_violations(state, "users", ["org_id"])
+  end
+
+  test "no duplicates gives an empty list", %{state: state} do
+    assert {:ok, []} = Native.unique_violations(state, "users", ["id"])
+  end
+
+  test "unknown tables and columns are errors", %{state: state} do
+    assert {:error, _} = Native.unique_violations(state, "users", ["missing"])
+    assert {:error, _} = Native.unique_violations(state, "missing", ["email"])
+    assert {:error, _} = Native.unique_violations(state, "users", [])
+  end
+end
@@ -0,0 +1,61 @@
+defmodule EctoLibSql.UsedIndexTest do
+  @moduledoc """
+  Tests for summarising `EXPLAIN QUERY PLAN` output into index usage.
+  """
+  use ExUnit.Case
+
+  alias EctoLibSql.Native
+
+  setup do
+    test_db = "z_ecto_libsql_test-used_index_#{:erlang.unique_integer([:positive])}.db"
+
+    {:ok, state} = EctoLibSql.connect(database: test_db)
+
+    for sql <- [
+          "CREATE TABLE users (id INTEGER PRIMARY KEY, email TEXT, bio TEXT)",
+          "CREATE INDEX users_email_index ON users (email)",
+          "CREATE TABLE posts (id INTEGER PRIMARY KEY, user_id INTEGER, title TEXT)"
+        ] do
+      {:ok, _, _, _} = EctoLibSql.handle_execute(sql, [], [], state)
+    end
+
+    on_exit(fn ->
+      EctoLibSql.disconnect([], state)
+      EctoLibSql.TestHelpers.cleanup_db_files(test_db)
+    end)
+
+    {:ok, state: state}
+  end
+
+  test "reports the index used by an indexed lookup", %{state: state} do
+    assert {:ok, {:index, "users_email_index"}} =
+             Native.used_index(state, "SELECT * FROM users WHERE email = ?", ["a@example.com"])
+  end
+
+  test "reports a scan on an unindexed column", %{state: state} do
+    assert {:ok, :scan} =
+             Native.used_index(state, "SELECT * FROM users WHERE bio = ?", ["hello"])
+  end
+
+  test "reports primary key lookups", %{state: state} do
+    assert {:ok, {:index, :primary_key}} =
+             Native.used_index(state, "SELECT * FROM users WHERE id = :id", %{id: 1})
+  end
+
+  test "returns every table access for joins", %{state: state} do
+    assert {:ok, [{"posts", :scan}, {"users", {:index, :primary_key}}]} =
+             Native.used_index(
+               state,
+               "SELECT posts.title FROM posts JOIN users ON users.id = posts.user_id",
+               []
+             )
+  end
+
+  test "returns an empty list for queries without tables", %{state: state} do
+    assert {:ok, []} = Native.used_index(state, "SELECT 1", [])
+  end
+
+  test "returns an error for invalid SQL", %{state: state} do
+    assert {:error, _reason} = Native.used_index(state, "SELECT * FROM missing_table", [])
+  end
+end
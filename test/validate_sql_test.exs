@@ -0,0 +1,59 @@
+defmodule EctoLibSql.ValidateSqlTest do
+  @moduledoc """
+  Tests for checking SQL scripts without executing them.
+  """
+  use ExUnit.Case
+
+  alias EctoLibSql.Native
+
+  setup do
+    test_db = "z_ecto_libsql_test-validate_sql_#{:erlang.unique_integer([:positive])}.db"
+
+    {:ok, state} = EctoLibSql.connect(database: test_db)
+
+    on_exit(fn ->
+      EctoLibSql.disconnect([], state)
+      EctoLibSql.TestHelpers.cleanup_db_files(test_db)
+    end)
+
+    {:ok, state: state}
+  end
+
+  test "accepts a valid multi-statement script without running it", %{state: state} do
+    script = """
+    CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT);
+    -- Seed data; semicolons in comments and strings don't split statements
+    INSERT INTO users (name) VALUES ('a;b');
+    CREATE TRIGGER users_touch AFTER UPDATE ON users BEGIN
+      UPDATE users SET name = name WHERE id = NEW.id;
+    END;
+    SELECT id, name FROM users;
+    """
+
+    assert :ok = Native.validate_sql(state, script)
+
+    assert {:ok, _, %{rows: [[0]]}, _} =
+             EctoLibSql.handle_execute(
+               "SELECT count(*) FROM sqlite_master WHERE name = 'users'",
+               [],
+               [],
+               state
+             )
+  end
+
+  test "reports the index of the first invalid statement", %{state: state} do
+    script = """
+    CREATE TABLE users (id INTEGER PRIMARY KEY);
+    SELECT 1;
+    SELEC id FROM users;
+    SELECT FROM;
+    """
+
+    assert {:error, {2, "SELEC id FROM users", reason}} = Native.validate_sql(state, script)
+    assert reason =~ "syntax error"
+  end
+
+  test "accepts an empty script", %{state: state} do
+    assert :ok = Native.validate_sql(state, "  -- nothing here\n")
+  end
+end
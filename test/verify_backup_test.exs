@@ -0,0 +1,68 @@
+defmodule EctoLibSql.VerifyBackupTest do
+  use ExUnit.Case
+
+  alias EctoLibSql.Native
+
+  setup do
+    suffix = :erlang.unique_integer([:positive])
+    db_file = "z_ecto_libsql_test-verify_backup_#{suffix}.db"
+    backup_file = "z_ecto_libsql_test-verify_backup_copy_#{suffix}.db"
+    {:ok, state} = EctoLibSql.connect(database: db_file)
+
+    {:ok, _, _, state} =
+      EctoLibSql.handle_execute(
+        "CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT)",
+        [],
+        [],
+        state
+      )
+
+    {:ok, _, _, state} =
+      EctoLibSql.handle_execute(
+        """
+        WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 500)
+        INSERT INTO items (name) SELECT printf('%0200d', i) FROM n
+        """,
+        [],
+        [],
+        state
+      )
+
+    {:ok, bytes} = Native.serialize(state)
+    File.write!(backup_file, bytes)
+
+    on_exit(fn ->
+      EctoLibSql.disconnect([], state)
+      EctoLibSql.TestHelpers.cleanup_db_files(db_file)
+      EctoLibSql.TestHelpers.cleanup_db_files(backup_file)
+    end)
+
+    {:ok, backup_file: backup_file, bytes: bytes}
+  end
+
+  test "a good backup passes", %{backup_file: backup_file} do
+    assert {:ok, %{schema_version: version}} = Native.verify_backup(backup_file)
+    assert version > 0
+  end
+
+  test "a truncated backup reports problems", %{backup_file: backup_file, bytes: bytes} do
+    File.write!(backup_file, binary_part(bytes, 0, div(byte_size(bytes), 2)))
+
+    assert {:error, [_ | _] = problems} = Native.verify_backup(backup_file)
+    assert Enum.all?(problems, &is_binary/1)
+  end
+
+  test "a corrupt file reports problems", %{backup_file: backup_file} do
+    File.write!(backup_file, "definitely not a database")
+
+    assert {:error, [problem]} = Native.verify_backup(backup_file)
+    assert problem =~ "Not an SQLite database"
+  end
+
+  test "a missing file is not created", %{backup_file: backup_file} do
+    missing = backup_file <> ".missing"
+
+    assert {:error, [_]} = Native.verify_backup(missing)
+    refute File.exists?(missing)
+  end
+end
@@ -0,0 +1,52 @@
+defmodule EctoLibSql.WalInfoTest do
+  use ExUnit.Case
+
+  alias EctoLibSql.Native
+
+  setup do
+    db_file = "z_ecto_libsql_test-wal_info_#{:erlang.unique_integer([:positive])}.db"
+    {:ok, state} = EctoLibSql.connect(database: db_file)
+
+    on_exit(fn ->
+      EctoLibSql.disconnect([], state)
+      EctoLibSql.TestHelpers.cleanup_db_files(db_file)
+    end)
+
+    {:ok, state: state, db_file: db_file}
+  end
+
+  describe "wal_info/1" do
+    test "reports :not_wal outside WAL mode", %{state: state} do
+      {:ok, _} = EctoLibSql.Pragma.set_journal_mode(state, :delete)
+
+      assert {:error, :not_wal} = Native.wal_info(state)
+    end
+
+    test "size grows with writes until a checkpoint", %{state: state, db_file: db_file} do
+      {:ok, _} = EctoLibSql.Pragma.set_journal_mode(state, :wal)
+      :ok = EctoLibSql.Pragma.set_wal_autocheckpoint(state, 0)
+
+      {:ok, _, _, state} =
+        EctoLibSql.handle_execute("CREATE TABLE blobs (data BLOB)", [], [], state)
+
+      {:ok, %{path: path, size_bytes: before}} = Native.wal_info(state)
+      assert path == Path.expand(db_file) <> "-wal"
+
+      state =
+        Enum.reduce(1..20, state, fn _, state ->
+          {:ok, _, _, state} =
+            EctoLibSql.handle_execute(
+              "INSERT INTO blobs (data) VALUES (zeroblob(4096))",
+              [],
+              [],
+              state
+            )
+
+          state
+        end)
+
+      {:ok, %{size_bytes: after_writes}} = Native.wal_info(state)
+      assert after_writes > before
+    end
+  end
+end
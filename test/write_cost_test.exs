@@ -0,0 +1,74 @@
+defmodule EctoLibSql.WriteCostTest do
+  use ExUnit.Case
+
+  alias EctoLibSql.Native
+
+  setup do
+    db_file = "z_ecto_libsql_test-write_cost_#{:erlang.unique_integer([:positive])}.db"
+    {:ok, state} = EctoLibSql.connect(database: db_file)
+
+    {:ok, _, _, state} =
+      EctoLibSql.handle_execute(
+        "CREATE TABLE events (id INTEGER PRIMARY KEY, kind TEXT, payload TEXT)",
+        [],
+        [],
+        state
+      )
+
+    {:ok, _, _, state} =
+      EctoLibSql.handle_execute("CREATE INDEX events_kind ON events (kind)", [], [], state)
+
+    on_exit(fn ->
+      EctoLibSql.disconnect([], state)
+      EctoLibSql.TestHelpers.cleanup_db_files(db_file)
+    end)
+
+    {:ok, state: state}
+  end
+
+  test "an unindexed DELETE reports a scan", %{state: state} do
+    assert {:ok, %{full_scan: true, scans: [{"events", nil}]}} =
+             Native.write_cost(state, "DELETE FROM events WHERE payload = ?", ["x"])
+  end
+
+  test "an indexed UPDATE reports no scan", %{state: state} do
+    assert {:ok, %{full_scan: false, scans: []}} =
+             Native.write_cost(state, "UPDATE events SET payload = 'y' WHERE kind = ?", ["a"])
+  end
+
+  test "includes row estimates once analysed", %{state: state} do
+    for i <- 1..10 do
+      {:ok, _, _, _} =
+        EctoLibSql.handle_execute(
+          "INSERT INTO events (kind, payload) VALUES (?, ?)",
+          ["k#{i}", "p#{i}"],
+          [],
+          state
+        )
+    end
+
+    {:ok, _, _, _} = EctoLibSql.handle_execute("ANALYZE", [], [], state)
+
+    assert {:ok, %{full_scan: true, scans: [{"events", 10}]}} =
+             Native.write_cost(state, "DELETE FROM events WHERE payload = ?", ["p1"])
+  end
+
+  test "does not run the statement", %{state: state} do
+    {:ok, _, _, _} =
+      EctoLibSql.handle_execute(
+        "INSERT INTO events (kind, payload) VALUES ('a', 'x')",
+        [],
+        [],
+        state
+      )
+
+    assert {:ok, _} = Native.write_cost(state, "DELETE FROM events", [])
+
+    assert {:ok, _, %EctoLibSql.Result{rows: [[1]]}, _} =
+             EctoLibSql.handle_execute("SELECT count(*) FROM events", [], [], state)
+  end
+
+  test "reports statements that cannot be planned", %{state: state} do
+    assert {:error, _} = Native.write_cost(state, "DELETE FROM missing WHERE id = 1", [])
+  end
+end
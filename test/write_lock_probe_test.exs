@@ -0,0 +1,47 @@
+defmodule EctoLibSql.WriteLockProbeTest do
+  use ExUnit.Case
+
+  alias EctoLibSql.Native
+
+  setup do
+    db_file = "z_ecto_libsql_test-write_lock_probe_#{:erlang.unique_integer([:positive])}.db"
+    {:ok, state} = EctoLibSql.connect(database: db_file)
+    {:ok, holder} = EctoLibSql.connect(database: db_file)
+
+    {:ok, _, _, state} =
+      EctoLibSql.handle_execute("CREATE TABLE t (id INTEGER PRIMARY KEY)", [], [], state)
+
+    on_exit(fn ->
+      EctoLibSql.disconnect([], holder)
+      EctoLibSql.disconnect([], state)
+      EctoLibSql.TestHelpers.cleanup_db_files(db_file)
+    end)
+
+    {:ok, state: state, holder: holder}
+  end
+
+  test "reports the write lock as free when nobody holds it", %{state: state} do
+    assert {:ok, true} = Native.write_lock_available(state)
+    # The probe leaves no transaction behind
+    assert Native.is_autocommit(state.conn_id)
+  end
+
+  test "reports false while a second connection holds the write lock", %{
+    state: state,
+    holder: holder
+  } do
+    {:ok, holder} = Native.begin(holder, behavior: :immediate)
+
+    assert {:ok, false} = Native.write_lock_available(state)
+    assert Native.is_autocommit(state.conn_id)
+
+    {:ok, _, _holder} = EctoLibSql.handle_rollback([], holder)
+    assert {:ok, true} = Native.write_lock_available(state)
+  end
+
+  test "refuses to probe inside a transaction", %{state: state} do
+    {:ok, :begin, state} = EctoLibSql.handle_begin([], state)
+    assert {:error, _reason} = Native.write_lock_available(state)
+    {:ok, _, _state} = EctoLibSql.handle_rollback([], state)
+  end
+end
@@ -0,0 +1,34 @@
+defmodule EctoLibSql.WriteRouteTest do
+  use ExUnit.Case
+
+  alias EctoLibSql.Native
+
+  setup do
+    db_file = "z_ecto_libsql_test-write_route_#{:erlang.unique_integer([:positive])}.db"
+    {:ok, state} = EctoLibSql.connect(database: db_file)
+
+    on_exit(fn ->
+      EctoLibSql.disconnect([], state)
+      EctoLibSql.TestHelpers.cleanup_db_files(db_file)
+    end)
+
+    {:ok, state: state}
+  end
+
+  test "writes on a local connection land locally", %{state: state} do
+    assert Native.write_route(state, "INSERT INTO t (x) VALUES (1)") == :local
+    assert Native.write_route(state, "update t SET x = 2") == :local
+    assert Native.write_route(state, "CREATE TABLE t (x)") == :local
+  end
+
+  test "reads are classified as reads", %{state: state} do
+    assert Native.write_route(state, "SELECT 1") == :read
+    assert Native.write_route(state, "PRAGMA user_version") == :read
+  end
+
+  test "replica-only operations fail on a local connection", %{state: state} do
+    assert {:error, :not_a_replica} = Native.sync_until_frame(state, 1)
+    assert {:error, :not_a_replica} = Native.flush_and_get_frame(state)
+    assert {:error, :not_a_replica} = Native.sync(state)
+  end
+end